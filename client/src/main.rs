@@ -204,18 +204,21 @@ fn ascii_table(query: &mkdb::QuerySet) -> String {
         .map(|row| {
             row.iter()
                 .map(|col| match col {
-                    Value::String(string) => string.replace('\n', "\\n"),
+                    Value::String(string) => string.trim_end_matches('\n').to_owned(),
                     other => other.to_string(),
                 })
                 .collect()
         })
         .collect();
 
-    // Find the maximum width for each column.
+    // Find the maximum width for each column. Strings that contain multiple
+    // lines (like EXPLAIN output) are as wide as their longest line.
     for row in &rows {
         for (i, col) in row.iter().enumerate() {
-            if col.len() > widths[i] {
-                widths[i] = col.len();
+            for line in col.lines() {
+                if line.chars().count() > widths[i] {
+                    widths[i] = line.chars().count();
+                }
             }
         }
     }
@@ -234,16 +237,30 @@ fn ascii_table(query: &mkdb::QuerySet) -> String {
     }
 
     // Builds one row: | for | example | this | one |
+    //
+    // Multi-line values span multiple lines of the same row.
     let make_row = |row: &Vec<String>| -> String {
-        let mut string = String::from('|');
+        let cells: Vec<Vec<&str>> = row.iter().map(|col| col.lines().collect()).collect();
+        let height = cells.iter().map(Vec::len).max().unwrap_or(0).max(1);
 
-        for (i, col) in row.iter().enumerate() {
-            string.push(' ');
-            string.push_str(col);
-            for _ in 0..widths[i] - col.len() - 1 {
-                string.push(' ');
+        let mut string = String::new();
+
+        for line in 0..height {
+            if line > 0 {
+                string.push('\n');
             }
+
             string.push('|');
+
+            for (i, cell) in cells.iter().enumerate() {
+                let content = cell.get(line).copied().unwrap_or("");
+                string.push(' ');
+                string.push_str(content);
+                for _ in 0..widths[i] - content.chars().count() - 1 {
+                    string.push(' ');
+                }
+                string.push('|');
+            }
         }
 
         string
@@ -5,11 +5,12 @@
 
 use std::{
     cell::RefCell,
-    collections::{HashMap, VecDeque},
+    collections::HashMap,
     ffi::OsString,
     fmt::Display,
     fs::File,
     io::{self, Read, Seek, Write},
+    iter,
     path::{Path, PathBuf},
     rc::Rc,
 };
@@ -17,20 +18,27 @@ use std::{
 use crate::{
     os::{FileSystemBlockSize, Open},
     paging::{
+        cache::Cache,
         io::FileOps,
-        pager::{PageNumber, Pager},
+        pager::{self, PageNumber, Pager, PagerStats},
     },
     query,
     sql::{
         self,
         analyzer::AnalyzerError,
         parser::{Parser, ParserError},
-        statement::{Column, Constraint, Create, DataType, Statement, Value},
+        statement::{join, Column, Constraint, Create, DataType, Direction, Statement, Value},
+        Limits,
+    },
+    storage::{
+        page::OverflowPage,
+        reassemble_payload,
+        tuple::{self, FormatVersion},
+        BTree, BTreeKeyComparator, Cursor, FixedSizeMemCmp,
     },
-    storage::{tuple, BTree, BTreeKeyComparator, FixedSizeMemCmp},
     vm::{
         self,
-        plan::{Plan, Tuple},
+        plan::{Plan, ScanStats, Tuple},
         TypeError, VmError,
     },
 };
@@ -72,23 +80,71 @@ enum TransactionState {
     None,
 }
 
+/// Execution metrics of the last statement.
+///
+/// Scan nodes report their own counts in [`Self::scans`], the rest of the
+/// counters are aggregated for the entire statement. See
+/// [`Database::last_plan_stats`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct PlanStats {
+    /// Tuples returned by the top level plan.
+    pub rows_returned: usize,
+    /// Tuples read from table or index BTrees by all the scan nodes.
+    pub rows_scanned: usize,
+    /// Tuples read by each scan node of the plan. See [`Plan::scan_stats`].
+    pub scans: Vec<ScanStats>,
+    /// Times that a tuple buffer ran out of memory and had to be written to a
+    /// temporary file (sorting large tables, for example).
+    pub spills: usize,
+    /// Pages that had to be read from disk.
+    pub pages_read: usize,
+    /// Pages that were already cached.
+    pub cache_hits: usize,
+}
+
+impl PlanStats {
+    /// Stores the counts of each scan node and their total.
+    fn record_scans(&mut self, scans: Vec<ScanStats>) {
+        self.rows_scanned = scans.iter().map(|scan| scan.rows).sum();
+        self.scans = scans;
+    }
+}
+
 /// Main entry point to everything.
 ///
 /// Provides the high level [`Database::exec`] API that receives SQL text and
 /// runs it.
-pub(crate) struct Database<F> {
+pub struct Database<F> {
     /// The database owns the pager.
     ///
     /// TODO: [`Rc<Refcell>`] is a temporary solution until we make the pager
     /// multithreaded. The pager should be able to allow multiple readers and
     /// one writer.
-    pub pager: Rc<RefCell<Pager<F>>>,
+    pub(crate) pager: Rc<RefCell<Pager<F>>>,
     /// Database context. See [`DatabaseContext`].
-    pub context: Context,
+    pub(crate) context: Context,
     /// Working directory (the directory of the file).
     pub work_dir: PathBuf,
     /// `true` if we are currently in a transaction.
-    pub transaction_state: TransactionState,
+    transaction_state: TransactionState,
+    /// Filtered queries that have to sequentially scan a table with more rows
+    /// than this will produce a warning. [`None`] disables the warnings.
+    pub full_scan_warning_threshold: Option<usize>,
+    /// Warnings produced while planning the last statement.
+    pub warnings: Vec<String>,
+    /// When `true`, literals in `INSERT` statements that don't match the
+    /// column type are converted if possible (`'123'` into an `INT` column,
+    /// for example) instead of causing a type error. Off by default.
+    pub implicit_casts: bool,
+    /// When `true`, the planner appends the table keys as implicit final
+    /// `ORDER BY` keys so that rows with equal sort keys are always returned
+    /// in the same order. Off by default.
+    pub deterministic_order: bool,
+    /// Metrics collected while executing the last prepared statement. They're
+    /// complete once the statement has returned all its tuples.
+    pub(crate) last_plan_stats: PlanStats,
+    /// Maximum size of the SQL inputs received from clients. See [`Limits`].
+    pub sql_limits: Limits,
 }
 
 /// Not really "Send" because of the [`Rc<RefCell>`], but we put the entire
@@ -102,8 +158,97 @@ pub(crate) struct Database<F> {
 unsafe impl Send for Database<File> {}
 
 impl Database<File> {
-    /// Initializes a [`Database`] instance from the given file.
+    /// Initializes a [`Database`] instance from the given file with the
+    /// default [`DbOptions`].
     pub fn init(path: impl AsRef<Path>) -> Result<Self, DbError> {
+        Self::options().open(path)
+    }
+
+    /// Returns the options used to open a database, same as
+    /// [`std::fs::File::options`].
+    pub fn options() -> DbOptions {
+        DbOptions::default()
+    }
+}
+
+/// Options that change how the database file is stored. See
+/// [`Database::options`].
+///
+/// They're not stored in the file, so the same options must be given every
+/// time the database is opened.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DbOptions {
+    compression: bool,
+    checksums: bool,
+    cache_size: Option<usize>,
+    max_cached_blocks: Option<usize>,
+    max_journal_buffered_pages: Option<usize>,
+}
+
+impl DbOptions {
+    /// Compresses pages before writing them to disk. Disabled by default.
+    ///
+    /// See [`pager::Builder::compression`].
+    pub fn compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Stores a checksum of every page and verifies it when reading the page
+    /// back, so corrupted pages cause errors instead of garbage results.
+    /// Disabled by default.
+    ///
+    /// See [`pager::Builder::checksums`].
+    pub fn checksums(mut self, checksums: bool) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    /// Maximum number of pages kept in memory. Defaults to
+    /// [`crate::paging::cache::DEFAULT_MAX_CACHE_SIZE`].
+    pub fn cache_size(mut self, cache_size: usize) -> Self {
+        self.cache_size = Some(cache_size);
+        self
+    }
+
+    /// Maximum number of file system blocks kept in memory when they're
+    /// bigger than pages. See [`pager::Builder::max_cached_blocks`].
+    pub fn max_cached_blocks(mut self, max_cached_blocks: usize) -> Self {
+        self.max_cached_blocks = Some(max_cached_blocks);
+        self
+    }
+
+    /// How many pages to buffer in memory before writing them to the journal
+    /// file. See [`pager::Builder::max_journal_buffered_pages`].
+    pub fn max_journal_buffered_pages(mut self, max_journal_buffered_pages: usize) -> Self {
+        self.max_journal_buffered_pages = Some(max_journal_buffered_pages);
+        self
+    }
+
+    /// Prepares a [`Pager`] builder that stores pages as configured.
+    pub(crate) fn pager_builder(&self) -> pager::Builder {
+        let mut builder = Pager::<File>::builder()
+            .compression(self.compression)
+            .checksums(self.checksums);
+
+        if let Some(cache_size) = self.cache_size {
+            builder = builder.cache(Cache::with_max_size(cache_size));
+        }
+
+        if let Some(max_cached_blocks) = self.max_cached_blocks {
+            builder = builder.max_cached_blocks(max_cached_blocks);
+        }
+
+        if let Some(max_journal_buffered_pages) = self.max_journal_buffered_pages {
+            builder = builder.max_journal_buffered_pages(max_journal_buffered_pages);
+        }
+
+        builder
+    }
+
+    /// Opens the database file located at `path` or creates it if it doesn't
+    /// exist.
+    pub fn open(&self, path: impl AsRef<Path>) -> Result<Database<File>, DbError> {
         let file = crate::os::Fs::options()
             .create(true)
             .truncate(false)
@@ -134,7 +279,8 @@ impl Database<File> {
 
         let journal_file_path = full_db_file_path.with_extension(extension);
 
-        let mut pager = Pager::<File>::builder()
+        let mut pager = self
+            .pager_builder()
             .page_size(DEFAULT_PAGE_SIZE)
             .block_size(block_size)
             .journal_file_path(journal_file_path)
@@ -157,7 +303,11 @@ pub(crate) enum SqlError {
     /// Table column not found or not usable in the context of the error.
     InvalidColumn(String),
     /// Duplicated UNIQUE columns, duplicated PRIMARY KEY columns, etc.
-    DuplicatedKey(Value),
+    DuplicatedKey {
+        table: String,
+        column: String,
+        value: Value,
+    },
     /// Errors caught by the [`sql::analyzer`].
     AnalyzerError(AnalyzerError),
     /// Data type errors. Trying to add numbers to strings, etc.
@@ -191,7 +341,14 @@ impl Display for SqlError {
         match self {
             Self::InvalidTable(name) => write!(f, "invalid table '{name}'"),
             Self::InvalidColumn(name) => write!(f, "invalid column '{name}'"),
-            Self::DuplicatedKey(key) => write!(f, "duplicated key {key}"),
+            Self::DuplicatedKey {
+                table,
+                column,
+                value,
+            } => write!(
+                f,
+                "UNIQUE violation on {table}.{column}: {value} already exists"
+            ),
             Self::AnalyzerError(analyzer_error) => write!(f, "{analyzer_error}"),
             Self::VmError(vm_error) => write!(f, "{vm_error}"),
             Self::TypeError(type_error) => write!(f, "{type_error}"),
@@ -248,6 +405,21 @@ impl From<ParserError> for DbError {
     }
 }
 
+/// Error returned by [`Database::exec_script`].
+#[derive(Debug)]
+pub struct ScriptError {
+    /// Position of the statement that failed in the script, starting at 0.
+    pub index: usize,
+    /// What went wrong with that statement.
+    pub error: Box<DbError>,
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "statement {} failed: {}", self.index + 1, self.error)
+    }
+}
+
 /// In-memory representation of a table schema.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Schema {
@@ -297,7 +469,12 @@ impl Schema {
             "schema already has {ROW_ID_COL}: {self:?}"
         );
 
-        let col = Column::new(ROW_ID_COL, DataType::UnsignedBigInt);
+        let col = Column {
+            name: String::from(ROW_ID_COL),
+            data_type: DataType::UnsignedBigInt,
+            constraints: vec![Constraint::NotNull],
+            default: None,
+        };
 
         self.columns.insert(0, col);
         self.index.values_mut().for_each(|idx| *idx += 1);
@@ -309,6 +486,30 @@ impl Schema {
         has_btree_key(&self.columns)
     }
 
+    /// Suggested display width of each column. See [`DataType::display_width`].
+    pub fn display_widths(&self) -> Vec<usize> {
+        self.columns
+            .iter()
+            .map(|col| col.data_type.display_width())
+            .collect()
+    }
+
+    /// Returns a copy of this schema where every column name is qualified with
+    /// the given table name, like `users.id`.
+    ///
+    /// Used to name the columns of joined tables.
+    pub fn qualified(&self, table: &str) -> Self {
+        Self::new(
+            self.columns
+                .iter()
+                .map(|col| Column {
+                    name: format!("{table}.{}", col.name),
+                    ..col.clone()
+                })
+                .collect(),
+        )
+    }
+
     /// Returns a list of owned [`Column::name`] strings.
     pub fn column_identifiers(&self) -> Vec<String> {
         self.columns.iter().map(|col| col.name.to_owned()).collect()
@@ -332,7 +533,10 @@ impl<'c, C: IntoIterator<Item = &'c Column>> From<C> for Schema {
 /// a direct table index, so we'll create a separate BTree index instead.
 pub fn has_btree_key(columns: &[Column]) -> bool {
     columns[0].constraints.contains(&Constraint::PrimaryKey)
-        && !matches!(columns[0].data_type, DataType::Varchar(_) | DataType::Bool)
+        && !matches!(
+            columns[0].data_type,
+            DataType::Varchar(_) | DataType::Char(_) | DataType::Bool | DataType::Double
+        )
 }
 
 /// This only exists because in earlier development stages the iterator model
@@ -382,7 +586,7 @@ impl QuerySet {
 /// Schema of the table used to keep track of the database information.
 pub(crate) fn mkdb_meta_schema() -> Schema {
     Schema::from(&[
-        // Either "index" or "table"
+        // Either "index", "table" or "stats"
         Column::new("type", DataType::Varchar(255)),
         // Index or table name
         Column::new("name", DataType::Varchar(255)),
@@ -403,14 +607,126 @@ pub(crate) struct IndexMetadata {
     pub root: PageNumber,
     /// Index name.
     pub name: String,
-    /// Column on which the index was created.
-    pub column: Column,
-    /// Schema of the index. Always key -> primary key.
+    /// Columns on which the index was created, in key order.
+    pub columns: Vec<Column>,
+    /// Sort direction of each column in [`Self::columns`].
+    pub directions: Vec<Direction>,
+    /// Schema of the index. Always key columns -> primary key.
     pub schema: Schema,
     /// Always `true` because non-unique indexes are not implemented.
     pub unique: bool,
 }
 
+impl IndexMetadata {
+    /// Creates the metadata of an index on the given `columns`.
+    ///
+    /// `table_key` is the column used as the BTree key of the table, which is
+    /// stored after the index key in every entry.
+    pub fn new(
+        root: PageNumber,
+        name: String,
+        columns: Vec<(Column, Direction)>,
+        table_key: Column,
+        unique: bool,
+    ) -> Self {
+        let (columns, directions): (Vec<_>, Vec<_>) = columns.into_iter().unzip();
+
+        let mut schema = Schema::new(columns.clone());
+        schema.push(table_key);
+
+        Self {
+            root,
+            name,
+            columns,
+            directions,
+            schema,
+            unique,
+        }
+    }
+
+    /// Key comparator of the index BTree.
+    ///
+    /// Multi-column keys are compared column by column.
+    pub fn comparator(&self, version: FormatVersion) -> BTreeKeyComparator {
+        let data_types = self.columns.iter().map(|column| &column.data_type);
+        BTreeKeyComparator::composite(data_types.zip(self.directions.iter().copied()), version)
+    }
+
+    /// Extracts the values of the index key from a tuple of the given table.
+    pub fn key_values(
+        &self,
+        table: &TableMetadata,
+        tuple: &[Value],
+    ) -> Result<Vec<Value>, DbError> {
+        self.columns
+            .iter()
+            .map(|column| {
+                let index = table.schema.index_of(&column.name).ok_or_else(|| {
+                    DbError::Corrupted(format!(
+                        "index column '{}' not found on table {} schema: {:?}",
+                        column.name, table.name, table.schema,
+                    ))
+                })?;
+
+                Ok(tuple[index].clone())
+            })
+            .collect()
+    }
+
+    /// Serializes the index key (without the table key) for BTree lookups.
+    pub fn serialize_key(
+        &self,
+        version: FormatVersion,
+        key: &[Value],
+    ) -> Result<Vec<u8>, SqlError> {
+        let mut buf = Vec::new();
+
+        for (column, value) in self.columns.iter().zip(key) {
+            buf.extend(tuple::serialize_key_with(
+                version,
+                &column.data_type,
+                value,
+            )?);
+        }
+
+        Ok(buf)
+    }
+
+    /// Serializes a complete index entry that maps `key` to `table_key`.
+    pub fn serialize_entry(
+        &self,
+        version: FormatVersion,
+        key: &[Value],
+        table_key: &Value,
+    ) -> Result<Vec<u8>, SqlError> {
+        let mut values = key.to_vec();
+        values.push(table_key.clone());
+
+        tuple::serialize_with(version, &self.schema, &values)
+    }
+
+    /// Builds the UNIQUE violation error for a `key` that already exists.
+    ///
+    /// Multi-column keys are reported as tuples, like `(a, b)`.
+    pub fn duplicated_key(&self, table: &str, mut key: Vec<Value>) -> SqlError {
+        let (column, value) = if self.columns.len() == 1 {
+            (self.columns[0].name.clone(), key.remove(0))
+        } else {
+            let names = self.columns.iter().map(|column| &column.name);
+            (
+                format!("({})", join(names, ", ")),
+                Value::String(format!("({})", join(&key, ", "))),
+            )
+        };
+
+        SqlError::DuplicatedKey {
+            table: table.into(),
+            column,
+            value,
+        }
+    }
+}
+
 /// Data that we need to know about tables at runtime.
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct TableMetadata {
@@ -424,6 +740,82 @@ pub(crate) struct TableMetadata {
     pub indexes: Vec<IndexMetadata>,
     /// Next [`RowId`] for this table.
     row_id: RowId,
+    /// Comparator of the table BTree. It only depends on the type of the
+    /// first column, so we build it once when the metadata is loaded instead
+    /// of every time we generate a plan. Schema changes must reload the
+    /// metadata through [`Context::invalidate`].
+    comparator: FixedSizeMemCmp,
+    /// Statistics about the contents of the table. [`None`] if they were
+    /// never collected.
+    pub stats: Option<TableStats>,
+}
+
+/// Approximate description of the contents of a table collected by the
+/// `ANALYZE` statement.
+///
+/// The optimizer uses this to estimate how expensive scan plans are, see
+/// [`crate::query::optimizer`]. Statistics are not updated when rows are
+/// inserted or deleted, so they might be outdated until `ANALYZE` runs again.
+///
+/// They're stored in the [`MKDB_META`] table as a row of type `stats` whose
+/// `sql` column contains the row count followed by `position=count` pairs,
+/// where `position` is the index of a column in the table schema and `count`
+/// is the approximate number of distinct values in that column. For example:
+///
+/// ```text
+/// 1000 2=1000 3=57
+/// ```
+///
+/// Column positions are used instead of names because names can contain any
+/// character when they're quoted.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct TableStats {
+    /// Number of rows in the table.
+    pub rows: usize,
+    /// Approximate number of distinct values of indexed columns.
+    pub distinct: HashMap<String, usize>,
+}
+
+impl TableStats {
+    /// Parses the format described in [`TableStats`].
+    ///
+    /// Returns [`None`] if the text is malformed or references columns that
+    /// don't exist in `schema`.
+    pub fn parse(text: &str, schema: &Schema) -> Option<Self> {
+        let mut parts = text.split_whitespace();
+        let rows = parts.next()?.parse().ok()?;
+
+        let distinct = parts
+            .map(|pair| {
+                let (position, count) = pair.split_once('=')?;
+                let column = schema.columns.get(position.parse::<usize>().ok()?)?;
+                Some((column.name.clone(), count.parse().ok()?))
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Self { rows, distinct })
+    }
+
+    /// Serializes the statistics of a table with the given `schema` into the
+    /// format described in [`TableStats`]. Columns are sorted by position.
+    pub fn serialize(&self, schema: &Schema) -> String {
+        let mut distinct = self
+            .distinct
+            .iter()
+            .filter_map(|(column, count)| Some((schema.index_of(column)?, count)))
+            .collect::<Vec<_>>();
+
+        distinct.sort();
+
+        iter::once(self.rows.to_string())
+            .chain(
+                distinct
+                    .into_iter()
+                    .map(|(position, count)| format!("{position}={count}")),
+            )
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
 }
 
 /// Dynamic dispatch for relation types.
@@ -448,16 +840,42 @@ impl Relation {
     }
 
     /// Dynamically dispatched key comparator for the BTree.
-    pub fn comparator(&self) -> BTreeKeyComparator {
+    ///
+    /// Keys must have been serialized with the given format `version`.
+    pub fn comparator(&self, version: FormatVersion) -> BTreeKeyComparator {
+        match self {
+            Self::Index(index) => index.comparator(version),
+            Self::Table(table) => {
+                BTreeKeyComparator::new(&table.schema.columns[0].data_type, version)
+            }
+        }
+    }
+
+    /// Comparator that only looks at the first column of the BTree key.
+    ///
+    /// Multi-column indexes can be scanned using only a prefix of their key,
+    /// see [`crate::vm::plan::RangeScan`].
+    pub fn leading_key_comparator(&self, version: FormatVersion) -> BTreeKeyComparator {
         match self {
-            Self::Index(index) => BTreeKeyComparator::from(&index.column.data_type),
-            Self::Table(table) => BTreeKeyComparator::from(&table.schema.columns[0].data_type),
+            Self::Index(index) => BTreeKeyComparator::with_direction(
+                &index.columns[0].data_type,
+                index.directions[0],
+                version,
+            ),
+            Self::Table(_) => self.comparator(version),
         }
     }
 
+    /// `true` if more than one entry of the BTree can share the same leading
+    /// key column. See [`Self::leading_key_comparator`].
+    pub fn has_composite_key(&self) -> bool {
+        matches!(self, Self::Index(index) if index.columns.len() > 1)
+    }
+
     /// Schema of the relation.
     ///
-    /// Indexes will always have 2 columns, the index key and the table key.
+    /// Indexes will always have the index key columns followed by the table
+    /// key.
     pub fn schema(&self) -> &Schema {
         match self {
             Self::Index(index) => &index.schema,
@@ -485,17 +903,46 @@ impl Relation {
 
     /// Returns the array index where the table key is located in the schema.
     ///
-    /// For tables the key is always at index 0 while for indexes it's at index
-    /// 1.
+    /// For tables the key is always at index 0 while for indexes it's right
+    /// after the index key columns.
     pub fn index_of_table_key(&self) -> usize {
         match self {
-            Self::Index(_) => 1,
+            Self::Index(index) => index.columns.len(),
             Self::Table(_) => 0,
         }
     }
 }
 
 impl TableMetadata {
+    /// Creates the metadata of a table with no indexes.
+    ///
+    /// As of right now all tables use integers as real primary keys. Varchar
+    /// primary keys are not used, we use the special "row_id" column in that
+    /// case. Any other BTree key means the table is corrupted.
+    pub fn new(
+        root: PageNumber,
+        name: String,
+        schema: Schema,
+        row_id: RowId,
+    ) -> Result<Self, DbError> {
+        let comparator = FixedSizeMemCmp::try_from(&schema.columns[0].data_type).map_err(|_| {
+            DbError::Corrupted(format!(
+                "table {name} is using a non-integer BTree key of type {}",
+                schema.columns[0].data_type
+            ))
+        })?;
+
+        Ok(Self {
+            root,
+            name,
+            schema,
+            indexes: Vec::new(),
+            row_id,
+            comparator,
+            stats: None,
+        })
+    }
+
     /// Returns the next [`RowId`] that should be used for rows in this table.
     pub fn next_row_id(&mut self) -> RowId {
         let row_id = self.row_id;
@@ -504,17 +951,9 @@ impl TableMetadata {
         row_id
     }
 
-    /// As of right now all tables use integers as real primary keys.
-    ///
-    /// Varchar primary keys are not used, we use the special "row_id" column
-    /// in that case.
-    pub(crate) fn comparator(&self) -> Result<FixedSizeMemCmp, DbError> {
-        FixedSizeMemCmp::try_from(&self.schema.columns[0].data_type).map_err(|_| {
-            DbError::Corrupted(format!(
-                "table {} is using a non-integer BTree key of type {}",
-                self.name, self.schema.columns[0].data_type
-            ))
-        })
+    /// Comparator of the table BTree. See [`TableMetadata::new`].
+    pub(crate) fn comparator(&self) -> FixedSizeMemCmp {
+        self.comparator
     }
 
     /// Generates a new schema that contains only the key of this table.
@@ -529,6 +968,19 @@ impl TableMetadata {
 pub(crate) trait DatabaseContext {
     /// Returns a [`TableMetadata`] object describing `table`.
     fn table_metadata(&mut self, table: &str) -> Result<&mut TableMetadata, DbError>;
+
+    /// Whether literal values should be implicitly casted to the column type.
+    /// See [`Database::implicit_casts`].
+    fn implicit_casts(&self) -> bool;
+
+    /// Whether the current transaction has a savepoint called `name`.
+    fn has_savepoint(&self, _name: &str) -> bool {
+        false
+    }
+
+    /// Returns the name of the table that owns `index` or [`None`] if the
+    /// index doesn't exist.
+    fn index_table(&mut self, index: &str) -> Result<Option<String>, DbError>;
 }
 
 /// Default value for [`Context::max_size`].
@@ -589,6 +1041,11 @@ impl Context {
         self.tables.insert(metadata.name.clone(), metadata);
     }
 
+    /// Removes all the tables from cache.
+    pub fn clear(&mut self) {
+        self.tables.clear();
+    }
+
     /// Removes the `table` from cache. Next it will be loaded from disk.
     pub fn invalidate(&mut self, table: &str) {
         self.tables.remove(table);
@@ -612,17 +1069,11 @@ impl TryFrom<&[&str]> for Context {
             let statement = Parser::new(sql).parse_statement()?;
 
             match statement {
-                Statement::Create(Create::Table { name, columns }) => {
+                Statement::Create(Create::Table { name, columns, .. }) => {
                     let mut schema = Schema::from(&columns);
                     schema.prepend_row_id();
 
-                    let mut metadata = TableMetadata {
-                        root,
-                        name: name.clone(),
-                        row_id: 1,
-                        schema,
-                        indexes: vec![],
-                    };
+                    let mut metadata = TableMetadata::new(root, name.clone(), schema, 1)?;
                     root += 1;
 
                     for column in &columns {
@@ -630,15 +1081,16 @@ impl TryFrom<&[&str]> for Context {
                             let index_name = match constraint {
                                 Constraint::PrimaryKey => format!("{name}_pk_index"),
                                 Constraint::Unique => format!("{name}_{}_uq_index", column.name),
+                                Constraint::NotNull => continue,
                             };
 
-                            metadata.indexes.push(IndexMetadata {
-                                column: column.clone(),
-                                schema: Schema::new(vec![column.clone(), columns[0].clone()]),
-                                name: index_name,
+                            metadata.indexes.push(IndexMetadata::new(
                                 root,
-                                unique: true,
-                            });
+                                index_name,
+                                vec![(column.clone(), Direction::Asc)],
+                                columns[0].clone(),
+                                true,
+                            ));
 
                             root += 1;
                         }
@@ -649,20 +1101,26 @@ impl TryFrom<&[&str]> for Context {
 
                 Statement::Create(Create::Index {
                     name,
-                    column,
+                    table,
+                    columns,
                     unique,
-                    ..
                 }) if unique => {
-                    let table = context.table_metadata(&name)?;
-                    let index_col = table.schema.columns[table.schema.index_of(&column).unwrap()].clone();
-
-                    table.indexes.push(IndexMetadata {
-                        column: index_col.clone(),
-                        schema: Schema::new(vec![index_col, table.schema.columns[0].clone()]),
-                        name,
+                    let table = context.table_metadata(&table)?;
+                    let index_cols = columns
+                        .iter()
+                        .map(|col| {
+                            let index = table.schema.index_of(&col.name).unwrap();
+                            (table.schema.columns[index].clone(), col.direction)
+                        })
+                        .collect();
+
+                    table.indexes.push(IndexMetadata::new(
                         root,
+                        name,
+                        index_cols,
+                        table.schema.columns[0].clone(),
                         unique,
-                    });
+                    ));
                     root += 1;
                 }
 
@@ -682,16 +1140,36 @@ impl DatabaseContext for Context {
             .get_mut(table)
             .ok_or_else(|| DbError::Sql(SqlError::InvalidTable(table.into())))
     }
+
+    // There's no session here, so values must match the column types just like
+    // they do by default in [`Database`].
+    fn implicit_casts(&self) -> bool {
+        false
+    }
+
+    fn index_table(&mut self, index: &str) -> Result<Option<String>, DbError> {
+        Ok(self
+            .tables
+            .values()
+            .find(|table| table.indexes.iter().any(|i| i.name == index))
+            .map(|table| table.name.clone()))
+    }
 }
 
 impl<F> Database<F> {
     /// Creates a new database.
-    pub fn new(pager: Rc<RefCell<Pager<F>>>, work_dir: PathBuf) -> Self {
+    pub(crate) fn new(pager: Rc<RefCell<Pager<F>>>, work_dir: PathBuf) -> Self {
         Self {
             pager,
             work_dir,
             context: Context::with_max_size(DEFAULT_RELATION_CACHE_SIZE),
             transaction_state: TransactionState::None,
+            full_scan_warning_threshold: None,
+            warnings: Vec::new(),
+            implicit_casts: false,
+            deterministic_order: false,
+            last_plan_stats: PlanStats::default(),
+            sql_limits: Limits::default(),
         }
     }
 
@@ -726,6 +1204,28 @@ impl<F: Seek + Read + Write + FileOps> DatabaseContext for Database<F> {
 
         self.context.table_metadata(table)
     }
+
+    fn implicit_casts(&self) -> bool {
+        self.implicit_casts
+    }
+
+    fn has_savepoint(&self, name: &str) -> bool {
+        self.pager.borrow().has_savepoint(name)
+    }
+
+    fn index_table(&mut self, index: &str) -> Result<Option<String>, DbError> {
+        let query = self.exec_statement(
+            Parser::new(&format!(
+                "SELECT table_name FROM {MKDB_META} where name = '{index}' AND type = 'index';"
+            ))
+            .parse_statement()?,
+        )?;
+
+        match query.get(0, "table_name") {
+            Some(Value::String(table)) => Ok(Some(table.clone())),
+            _ => Ok(None),
+        }
+    }
 }
 
 impl<F: Seek + Read + Write + FileOps> Database<F> {
@@ -758,28 +1258,18 @@ impl<F: Seek + Read + Write + FileOps> Database<F> {
             let mut schema = mkdb_meta_schema();
             schema.prepend_row_id();
 
-            return Ok(TableMetadata {
-                root: MKDB_META_ROOT,
-                name: String::from(table),
-                row_id: self.load_next_row_id(MKDB_META_ROOT)?,
-                schema,
-                indexes: vec![],
-            });
+            let row_id = self.load_next_row_id(MKDB_META_ROOT)?;
+            return TableMetadata::new(MKDB_META_ROOT, String::from(table), schema, row_id);
         }
 
-        let mut metadata = TableMetadata {
-            root: 1,
-            name: String::from(table),
-            row_id: 1,
-            schema: Schema::empty(),
-            indexes: Vec::new(),
-        };
-
-        let mut found_table_definition = false;
+        let mut metadata: Option<TableMetadata> = None;
 
-        let (schema, mut results) = self.prepare(&format!(
-            "SELECT root, sql FROM {MKDB_META} where table_name = '{table}';"
-        ))?;
+        let (schema, mut results) = self.prepare_statement(
+            Parser::new(&format!(
+                "SELECT type, root, sql FROM {MKDB_META} where table_name = '{table}';"
+            ))
+            .parse_statement()?,
+        )?;
 
         let corrupted_error = || {
             DbError::Corrupted(format!(
@@ -793,29 +1283,51 @@ impl<F: Seek + Read + Write + FileOps> Database<F> {
                 return Err(corrupted_error());
             };
 
+            // Statistics are not SQL and they're always stored after the
+            // table definition.
+            if tuple[schema.index_of("type").ok_or(corrupted_error())?]
+                == Value::String("stats".into())
+            {
+                let (Some(metadata), Value::String(stats)) = (
+                    &mut metadata,
+                    &tuple[schema.index_of("sql").ok_or(corrupted_error())?],
+                ) else {
+                    return Err(corrupted_error());
+                };
+
+                metadata.stats =
+                    Some(TableStats::parse(stats, &metadata.schema).ok_or(corrupted_error())?);
+
+                continue;
+            }
+
             match &tuple[schema.index_of("sql").ok_or(corrupted_error())?] {
                 Value::String(sql) => match Parser::new(sql).parse_statement()? {
                     Statement::Create(Create::Table { columns, .. }) => {
                         assert!(
-                            !found_table_definition,
+                            metadata.is_none(),
                             "multiple definitions of table '{table}'"
                         );
 
-                        metadata.root = *root as PageNumber;
-                        metadata.schema = Schema::new(columns);
+                        let mut schema = Schema::new(columns);
 
                         // Tables tha don't have an integer primary key as the
                         // first field will use a hidden primary key that we
                         // generate ourselves.
-                        if !metadata.schema.has_btree_key() {
-                            metadata.schema.prepend_row_id();
+                        if !schema.has_btree_key() {
+                            schema.prepend_row_id();
                         }
 
-                        found_table_definition = true;
+                        metadata = Some(TableMetadata::new(
+                            *root as PageNumber,
+                            String::from(table),
+                            schema,
+                            1,
+                        )?);
                     }
 
                     Statement::Create(Create::Index {
-                        column,
+                        columns,
                         name,
                         unique,
                         ..
@@ -824,24 +1336,31 @@ impl<F: Seek + Read + Write + FileOps> Database<F> {
                         // because it's impossible to define an index unless the
                         // table exists and the results are returned sorted by
                         // row_id.
-                        let col_idx = metadata.schema.index_of(&column).ok_or(
-                            SqlError::Other(format!(
-                                "could not find index column {column} in the definition of table {table}"
-                            )),
-                        )?;
-
-                        let index_col = metadata.schema.columns[col_idx].clone();
-
-                        metadata.indexes.push(IndexMetadata {
-                            column: index_col.clone(),
-                            schema: Schema::new(vec![
-                                index_col,
-                                metadata.schema.columns[0].clone(),
-                            ]),
+                        let Some(metadata) = &mut metadata else {
+                            return Err(corrupted_error());
+                        };
+
+                        let index_cols = columns
+                            .iter()
+                            .map(|column| {
+                                let col_idx = metadata.schema.index_of(&column.name).ok_or(
+                                    SqlError::Other(format!(
+                                        "could not find index column {} in the definition of table {table}",
+                                        column.name
+                                    )),
+                                )?;
+
+                                Ok((metadata.schema.columns[col_idx].clone(), column.direction))
+                            })
+                            .collect::<Result<Vec<_>, DbError>>()?;
+
+                        metadata.indexes.push(IndexMetadata::new(
+                            *root as PageNumber,
                             name,
-                            root: *root as PageNumber,
+                            index_cols,
+                            metadata.schema.columns[0].clone(),
                             unique,
-                        });
+                        ));
                     }
 
                     _ => return Err(corrupted_error()),
@@ -851,9 +1370,9 @@ impl<F: Seek + Read + Write + FileOps> Database<F> {
             };
         }
 
-        if !found_table_definition {
+        let Some(mut metadata) = metadata else {
             return Err(DbError::Sql(SqlError::InvalidTable(table.into())));
-        }
+        };
 
         if metadata.schema.columns[0].name == ROW_ID_COL {
             metadata.row_id = self.load_next_row_id(metadata.root)?;
@@ -864,22 +1383,13 @@ impl<F: Seek + Read + Write + FileOps> Database<F> {
 
     /// Returns the root page of `index` if it exists.
     fn index_metadata(&mut self, index_name: &str) -> Result<IndexMetadata, DbError> {
-        let query = self.exec(&format!(
-            "SELECT table_name FROM {MKDB_META} where name = '{index_name}' AND type = 'index';"
-        ))?;
-
-        if query.is_empty() {
+        let Some(table_name) = self.index_table(index_name)? else {
             return Err(DbError::Sql(SqlError::Other(format!(
                 "index {index_name} does not exist"
             ))));
-        }
-
-        let table_name = match query.get(0, "table_name") {
-            Some(Value::String(name)) => name,
-            _ => unreachable!(),
         };
 
-        let table_metadata = self.table_metadata(table_name)?;
+        let table_metadata = self.table_metadata(&table_name)?;
 
         // TODO: Innefficient.
         Ok(table_metadata
@@ -902,7 +1412,53 @@ impl<F: Seek + Read + Write + FileOps> Database<F> {
     /// usage to the size of internal buffers used the [`Plan`] execution engine
     /// at [`vm::plan`].
     pub fn exec(&mut self, input: &str) -> Result<QuerySet, DbError> {
-        let (schema, mut preapred_staement) = self.prepare(input)?;
+        self.exec_statement(self.parser(input).parse_statement()?)
+    }
+
+    /// Creates a parser for SQL received from clients, which has to respect
+    /// [`Self::sql_limits`]. Queries built internally use [`Parser::new`].
+    fn parser<'i>(&self, input: &'i str) -> Parser<'i> {
+        Parser::with_limits(input, self.sql_limits)
+    }
+
+    /// Executes all the statements in the `input` script one after the other
+    /// and returns their results in the same order.
+    ///
+    /// The entire script is parsed before running anything, so syntax errors
+    /// don't leave the script half executed. Other errors stop the execution at
+    /// the statement that failed, and whatever the previous statements did is
+    /// not undone unless they're part of an explicit transaction.
+    pub fn exec_script(&mut self, input: &str) -> Result<Vec<QuerySet>, ScriptError> {
+        let mut parser = self.parser(input);
+        let mut statements = Vec::new();
+
+        while let Some(statement) = parser.parse_next_statement() {
+            statements.push(statement.map_err(|error| ScriptError {
+                index: statements.len(),
+                error: Box::new(error.into()),
+            })?);
+        }
+
+        let mut results = Vec::with_capacity(statements.len());
+
+        for (index, statement) in statements.into_iter().enumerate() {
+            let query_set = self
+                .exec_statement(statement)
+                .map_err(|error| ScriptError {
+                    index,
+                    error: Box::new(error),
+                })?;
+
+            results.push(query_set);
+        }
+
+        Ok(results)
+    }
+
+    /// Runs an already parsed statement and collects its results. See
+    /// [`Database::exec`].
+    fn exec_statement(&mut self, statement: Statement) -> Result<QuerySet, DbError> {
+        let (schema, mut preapred_staement) = self.prepare_statement(statement)?;
 
         let mut query_set = QuerySet::new(schema, vec![]);
 
@@ -927,26 +1483,80 @@ impl<F: Seek + Read + Write + FileOps> Database<F> {
     /// with fixed memory usage (except for the size of the tuple itself). This
     /// is the API the should be used to process queries as it will not make use
     /// of all the system's RAM.
-    pub fn prepare(&mut self, sql: &str) -> Result<(Schema, PreparedStatement<'_, F>), DbError> {
-        let statement = sql::pipeline(sql, self)?;
+    pub(crate) fn prepare(
+        &mut self,
+        sql: &str,
+    ) -> Result<(Schema, PreparedStatement<'_, F>), DbError> {
+        self.prepare_statement(self.parser(sql).parse_statement()?)
+    }
+
+    /// Runs a query that produces rows and returns them as a [`QueryResult`].
+    ///
+    /// Unlike [`Database::exec`] the rows are not collected in memory, they're
+    /// produced one by one as the result is iterated. Only `SELECT` and
+    /// `EXPLAIN` statements are accepted here, everything else must go through
+    /// [`Database::exec`], which returns one empty row per affected row for
+    /// `INSERT`, `UPDATE` and `DELETE`.
+    pub fn query(&mut self, sql: &str) -> Result<QueryResult<'_, F>, DbError> {
+        let statement = self.parser(sql).parse_statement()?;
+
+        if !matches!(
+            statement,
+            Statement::Select { .. } | Statement::Explain { .. }
+        ) {
+            return Err(DbError::Other(String::from(
+                "query() only works with SELECT and EXPLAIN statements, use exec() instead",
+            )));
+        }
+
+        let (schema, statement) = self.prepare_statement(statement)?;
+        let warnings = statement.db.warnings.clone();
+
+        Ok(QueryResult {
+            schema,
+            warnings,
+            rows: RowStream { statement },
+        })
+    }
+
+    /// Same as [`Database::prepare`] but the SQL is already parsed.
+    fn prepare_statement(
+        &mut self,
+        statement: Statement,
+    ) -> Result<(Schema, PreparedStatement<'_, F>), DbError> {
+        self.warnings.clear();
+        self.last_plan_stats = PlanStats::default();
+        self.pager.borrow_mut().stats = PagerStats::default();
+
+        let statement = sql::pipeline(statement, self)?;
 
         let mut schema = Schema::empty();
 
         let exec = match statement {
             Statement::Create(_)
             | Statement::Drop(_)
+            | Statement::Alter(_)
             | Statement::StartTransaction
             | Statement::Commit
-            | Statement::Rollback => Exec::Statement(statement),
+            | Statement::Rollback
+            | Statement::Savepoint(_)
+            | Statement::RollbackTo(_)
+            | Statement::Analyze(_)
+            | Statement::Vacuum => Exec::Statement(statement),
 
-            Statement::Explain(inner) => match &*inner {
+            Statement::Explain { statement, analyze } => match &*statement {
                 Statement::Select { .. }
                 | Statement::Insert { .. }
                 | Statement::Update { .. }
                 | Statement::Delete { .. } => {
-                    schema = Schema::new(vec![Column::new("Query Plan", DataType::Varchar(255))]);
-                    let plan = query::planner::generate_plan(*inner, self)?;
-                    Exec::Explain(format!("{plan}").lines().map(String::from).collect())
+                    schema = explain_schema();
+                    let plan = query::planner::generate_plan(*statement, self)?;
+
+                    if analyze {
+                        Exec::ExplainAnalyze(plan.instrument())
+                    } else {
+                        Exec::Explain(Some(plan.to_string()))
+                    }
                 }
 
                 _ => {
@@ -974,17 +1584,163 @@ impl<F: Seek + Read + Write + FileOps> Database<F> {
         Ok((schema, prepared_statement))
     }
 
+    /// Returns the schema of the tuples that the given `sql` statement would
+    /// produce without executing it.
+    ///
+    /// This is the same schema returned by [`Database::prepare`], which is
+    /// computed by the query planner, so it's useful for clients that need
+    /// to know the result columns and their types beforehand. Statements that
+    /// don't return tuples have an empty schema.
+    pub fn describe(&mut self, sql: &str) -> Result<Schema, DbError> {
+        let statement = sql::pipeline(self.parser(sql).parse_statement()?, self)?;
+
+        Ok(match statement {
+            Statement::Create(_)
+            | Statement::Drop(_)
+            | Statement::Alter(_)
+            | Statement::StartTransaction
+            | Statement::Commit
+            | Statement::Rollback
+            | Statement::Savepoint(_)
+            | Statement::RollbackTo(_)
+            | Statement::Analyze(_)
+            | Statement::Vacuum => Schema::empty(),
+
+            Statement::Explain { .. } => explain_schema(),
+
+            _ => query::planner::generate_plan(statement, self)?
+                .schema()
+                .unwrap_or_else(Schema::empty),
+        })
+    }
+
     /// Manually rolls back the database and stops the current transaction.
     pub fn rollback(&mut self) -> Result<usize, DbError> {
         self.transaction_state = TransactionState::None;
         self.pager.borrow_mut().rollback()
     }
 
+    /// Undoes all the changes made after the savepoint `name` was created.
+    ///
+    /// The transaction stays open (and usable again if it was aborted) and the
+    /// savepoint can be used again. Cached table metadata is discarded since
+    /// the tables might have changed.
+    pub fn rollback_to_savepoint(&mut self, name: &str) -> Result<usize, DbError> {
+        let num_pages_rolled_back = self.pager.borrow_mut().rollback_to_savepoint(name)?;
+        self.context.clear();
+        self.transaction_state = TransactionState::InProgress;
+
+        Ok(num_pages_rolled_back)
+    }
+
     /// Manually commits the changes and stops the current transaction.
     pub fn commit(&mut self) -> io::Result<()> {
         self.transaction_state = TransactionState::None;
         self.pager.borrow_mut().commit()
     }
+
+    /// Returns information about how pages are used in the database file.
+    ///
+    /// Total and free page counts come straight from the [`DbHeader`] while
+    /// the per table counts are computed by walking every BTree that belongs to
+    /// each table, so this is not cheap. Indexes count towards the table they
+    /// belong to.
+    ///
+    /// The [`MKDB_META`] table is read directly instead of running a query, so
+    /// this doesn't touch [`Self::warnings`] or the stats of the last plan.
+    ///
+    /// [`DbHeader`]: crate::storage::page::DbHeader
+    pub fn page_stats(&mut self) -> Result<PageStats, DbError> {
+        let header = self.pager.borrow_mut().read_header()?;
+
+        let mut schema = mkdb_meta_schema();
+        schema.prepend_row_id();
+
+        let [r#type, root, table_name] =
+            ["type", "root", "table_name"].map(|col| schema.index_of(col).unwrap());
+
+        let mut btrees = Vec::new();
+        let mut pager = self.pager.borrow_mut();
+        let mut cursor = Cursor::new(MKDB_META_ROOT, 0);
+
+        while let Some((page, slot)) = cursor.try_next(&mut pager)? {
+            let tuple = tuple::deserialize_with(
+                pager.format_version,
+                reassemble_payload(&mut pager, page, slot)?.as_ref(),
+                &schema,
+            )?;
+
+            match (&tuple[r#type], &tuple[root], &tuple[table_name]) {
+                (Value::String(r#type), _, _) if r#type != "table" && r#type != "index" => {}
+                (_, Value::Number(root), Value::String(table_name)) => {
+                    btrees.push((table_name.clone(), *root as PageNumber));
+                }
+                _ => {
+                    return Err(DbError::Corrupted(format!(
+                        "{MKDB_META} table contains unexpected data: {tuple:?}"
+                    )))
+                }
+            }
+        }
+
+        drop(pager);
+
+        let mut tables: Vec<(String, usize)> = Vec::new();
+
+        for (table_name, root) in btrees {
+            let pages = self.count_btree_pages(root)?;
+
+            match tables.iter_mut().find(|(name, _)| *name == table_name) {
+                Some((_, count)) => *count += pages,
+                None => tables.push((table_name, pages)),
+            }
+        }
+
+        Ok(PageStats {
+            total_pages: header.total_pages as usize,
+            free_pages: header.free_pages as usize,
+            tables,
+        })
+    }
+
+    /// Counts all the pages of the BTree rooted at `root`, including overflow
+    /// pages.
+    fn count_btree_pages(&mut self, root: PageNumber) -> io::Result<usize> {
+        let mut pager = self.pager.borrow_mut();
+        let mut stack = vec![root];
+        let mut pages = 0;
+
+        while let Some(page_number) = stack.pop() {
+            let page = pager.get(page_number)?;
+            stack.extend(page.iter_children());
+            pages += 1;
+
+            let overflow = (0..page.len())
+                .map(|slot| page.cell(slot).overflow_page())
+                .filter(|overflow_page| *overflow_page != 0)
+                .collect::<Vec<_>>();
+
+            for mut overflow_page in overflow {
+                while overflow_page != 0 {
+                    overflow_page = pager.get_as::<OverflowPage>(overflow_page)?.header().next;
+                    pages += 1;
+                }
+            }
+        }
+
+        Ok(pages)
+    }
+}
+
+/// Page usage statistics returned by [`Database::page_stats`].
+#[derive(Debug, PartialEq)]
+pub struct PageStats {
+    /// Number of pages in the file, both free and used.
+    pub total_pages: usize,
+    /// Pages in the free list that can be reused.
+    pub free_pages: usize,
+    /// Table name and number of pages used by the table and its indexes.
+    pub tables: Vec<(String, usize)>,
 }
 
 /// Not all statements need [`Plan`] trees for execution.
@@ -996,7 +1752,18 @@ enum Exec<F> {
     /// Complex statements that require [`Plan`] trees executed by [`vm::plan`].
     Plan(Plan<F>),
     /// Return a string that describes the generated plan.
-    Explain(VecDeque<String>),
+    ///
+    /// The whole plan tree is returned in one single row, it becomes [`None`]
+    /// once the row is consumed.
+    Explain(Option<String>),
+    /// Execute the instrumented plan and then return its string representation
+    /// with the collected stats. See [`Plan::instrument`].
+    ExplainAnalyze(Plan<F>),
+}
+
+/// Schema of `EXPLAIN` results: one column that contains the plan tree.
+fn explain_schema() -> Schema {
+    Schema::new(vec![Column::new("Query Plan", DataType::Varchar(65535))])
 }
 
 /// A prepared statement is a statement that has been successfully parsed and
@@ -1054,7 +1821,7 @@ impl<'d, F: Seek + Read + Write + FileOps> PreparedStatement<'d, F> {
         if self.db.transaction_aborted()
             && !matches!(
                 exec,
-                Exec::Statement(Statement::Commit) | Exec::Statement(Statement::Rollback)
+                Exec::Statement(Statement::Commit | Statement::Rollback | Statement::RollbackTo(_))
             )
         {
             return Err(DbError::Other(String::from(
@@ -1074,6 +1841,26 @@ impl<'d, F: Seek + Read + Write + FileOps> PreparedStatement<'d, F> {
             return Ok(None);
         }
 
+        // Savepoints make no sense in transactions that end right after the
+        // statement.
+        if let Exec::Statement(Statement::Savepoint(_) | Statement::RollbackTo(_)) = exec {
+            if !self.db.active_transaction() {
+                return Err(DbError::Other(String::from(
+                    "savepoints can only be used inside transactions",
+                )));
+            }
+        }
+
+        // VACUUM moves pages that other statements of the transaction might
+        // have modified, so it needs its own transaction.
+        if let Exec::Statement(Statement::Vacuum) = exec {
+            if self.db.active_transaction() {
+                return Err(DbError::Other(String::from(
+                    "VACUUM cannot run inside a transaction",
+                )));
+            }
+        }
+
         // Single statement with no transaction. Start a transaction
         // automatically and close it when the statement is done executing.
         if !self.db.active_transaction() {
@@ -1103,15 +1890,23 @@ impl<'d, F: Seek + Read + Write + FileOps> PreparedStatement<'d, F> {
                     Statement::Rollback => {
                         self.db.rollback()?;
                     }
-                    Statement::Create(_) | Statement::Drop(_) => {
-                        match vm::statement::exec(statement, self.db) {
-                            Ok(rows) => affected_rows = rows,
-                            Err(e) => {
-                                self.abort_transaction()?;
-                                return Err(e);
-                            }
-                        }
+                    Statement::Savepoint(name) => {
+                        self.db.pager.borrow_mut().savepoint(name);
                     }
+                    Statement::RollbackTo(name) => {
+                        self.db.rollback_to_savepoint(&name)?;
+                    }
+                    Statement::Create(_)
+                    | Statement::Drop(_)
+                    | Statement::Alter(_)
+                    | Statement::Analyze(_)
+                    | Statement::Vacuum => match vm::statement::exec(statement, self.db) {
+                        Ok(rows) => affected_rows = rows,
+                        Err(e) => {
+                            self.abort_transaction()?;
+                            return Err(e);
+                        }
+                    },
                     _ => unreachable!(),
                 };
 
@@ -1125,32 +1920,79 @@ impl<'d, F: Seek + Read + Write + FileOps> PreparedStatement<'d, F> {
                 Some(vec![Value::Number(affected_rows as i128)])
             }
 
-            Exec::Plan(plan) => match plan.try_next() {
-                Ok(tuple) => tuple,
+            Exec::Plan(plan) => {
+                let result = plan.try_next();
 
-                Err(e) => {
-                    // The iterator ends here, rollback and return the error.
-                    self.exec.take();
-                    self.abort_transaction()?;
-                    return Err(e);
-                }
-            },
+                // Sorts spill while collecting their input, which happens
+                // before the first tuple comes out. Record them right away
+                // since the plan might fail later or the client might not read
+                // the rest of the tuples.
+                self.db.last_plan_stats.spills = plan.spills();
 
-            Exec::Explain(lines) => {
-                let line = lines.pop_front().map(|line| vec![Value::String(line)]);
+                match result {
+                    Ok(tuple) => {
+                        if tuple.is_some() {
+                            self.db.last_plan_stats.rows_returned += 1;
+                        }
 
-                if line.is_none() {
+                        tuple
+                    }
+
+                    Err(e) => {
+                        // The iterator ends here, rollback and return the error.
+                        self.exec.take();
+                        self.abort_transaction()?;
+                        return Err(e);
+                    }
+                }
+            }
+
+            Exec::ExplainAnalyze(plan) => {
+                // Run the plan until completion and discard the tuples, we
+                // only care about the stats.
+                loop {
+                    match plan.try_next() {
+                        Ok(Some(_)) => continue,
+                        Ok(None) => break,
+                        Err(e) => {
+                            self.exec.take();
+                            self.abort_transaction()?;
+                            return Err(e);
+                        }
+                    }
+                }
+
+                self.db.last_plan_stats.record_scans(plan.scan_stats());
+
+                let analysis = plan.to_string();
+                *exec = Exec::Explain(None);
+
+                Some(vec![Value::String(analysis)])
+            }
+
+            Exec::Explain(plan) => {
+                let row = plan.take().map(|plan| vec![Value::String(plan)]);
+
+                if row.is_none() {
                     self.exec.take();
                 }
 
-                line
+                row
             }
         };
 
         // If this block runs then everything executed successfully. End the
         // iterator and auto commit if necessary.
         if tuple.is_none() || self.exec.is_none() {
-            self.exec.take();
+            if let Some(Exec::Plan(plan)) = self.exec.take() {
+                self.db.last_plan_stats.record_scans(plan.scan_stats());
+            }
+
+            // Don't count the IO done by the commit.
+            let pager_stats = self.db.pager.borrow().stats;
+            self.db.last_plan_stats.pages_read = pager_stats.pages_read;
+            self.db.last_plan_stats.cache_hits = pager_stats.cache_hits;
+
             if self.auto_commit {
                 self.db.commit()?;
             }
@@ -1160,6 +2002,80 @@ impl<'d, F: Seek + Read + Write + FileOps> PreparedStatement<'d, F> {
     }
 }
 
+/// Rows produced by [`Database::query`].
+///
+/// Can be consumed with a regular `for` loop or turned into a [`RowStream`]
+/// once the [`Schema`] is no longer needed.
+pub struct QueryResult<'d, F: Seek + Read + Write + FileOps> {
+    /// Schema of the rows.
+    schema: Schema,
+    /// Warnings produced while planning the query. See [`Database::warnings`].
+    warnings: Vec<String>,
+    /// Cursor that produces the rows.
+    rows: RowStream<'d, F>,
+}
+
+impl<'d, F: Seek + Read + Write + FileOps> QueryResult<'d, F> {
+    /// Schema of the rows returned by the query.
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// Warnings about the query plan, like full scans of large tables.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Discards the schema and returns the underlying [`RowStream`].
+    pub fn into_rows(self) -> RowStream<'d, F> {
+        self.rows
+    }
+}
+
+impl<'d, F: Seek + Read + Write + FileOps> Iterator for QueryResult<'d, F> {
+    type Item = Result<Vec<Value>, DbError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rows.next()
+    }
+}
+
+/// Cursor that pulls tuples out of the [`Plan`] tree one at a time.
+///
+/// Nothing is buffered here, each call to [`Iterator::next`] runs the plan
+/// until it produces the next tuple. Whether the whole result set ends up in
+/// memory or not depends only on the plan: scans stream straight from the
+/// BTree pages while sorting or aggregating has to see every row first.
+///
+/// The stream borrows the [`Database`] mutably, so nothing else can run until
+/// it's dropped. Holding the stream also pins the transaction: if the client
+/// didn't start one, the transaction opened on the first call to
+/// [`Iterator::next`] is only committed once the stream is fully consumed. A
+/// stream dropped halfway through rolls that transaction back instead of
+/// leaving it open. Transactions started with `START TRANSACTION` are left
+/// untouched either way.
+pub struct RowStream<'d, F: Seek + Read + Write + FileOps> {
+    statement: PreparedStatement<'d, F>,
+}
+
+impl<'d, F: Seek + Read + Write + FileOps> Iterator for RowStream<'d, F> {
+    type Item = Result<Vec<Value>, DbError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.statement.try_next().transpose()
+    }
+}
+
+impl<'d, F: Seek + Read + Write + FileOps> Drop for RowStream<'d, F> {
+    fn drop(&mut self) {
+        if self.statement.exec.is_some() && self.statement.auto_commit {
+            // Nothing we can do about errors here, the next statement will
+            // find them anyway.
+            let _ = self.statement.db.rollback();
+        }
+    }
+}
+
 // TODO: We can probably create a separate directory for integration tests and
 // move all these tests there, although we're using some internal functions that
 // shouldn't be exposed in `lib.rs`. We should also add tests with real files
@@ -1175,21 +2091,22 @@ mod tests {
         rc::Rc,
     };
 
-    use super::{Database, DbError, DEFAULT_PAGE_SIZE};
+    use super::{Database, DatabaseContext, DbError, DbOptions, PlanStats, DEFAULT_PAGE_SIZE};
     use crate::{
         db::{mkdb_meta_schema, QuerySet, Schema, SqlError, TypeError},
         paging::{
             cache::{Cache, DEFAULT_MAX_CACHE_SIZE},
-            io::{FileOps, MemBuf},
-            pager::Pager,
+            io::{CorruptPage, FileOps, MemBuf},
+            pager::{PageNumber, Pager},
         },
         sql::{
-            analyzer::AnalyzerError,
+            analyzer::{AnalyzerError, Clause},
             parser::Parser,
-            statement::{Column, DataType, Expression, Value},
+            statement::{AggregateFunction, Column, DataType, Expression, ScalarFunction, Value},
+            Limits,
         },
         storage::{reassemble_payload, tuple, Cursor},
-        vm::VmDataType,
+        vm::{plan::ScanStats, VmDataType, VmError},
     };
 
     impl PartialEq for DbError {
@@ -1212,9 +2129,39 @@ mod tests {
         let mut pager = Pager::<MemBuf>::builder()
             .page_size(conf.page_size)
             .cache(Cache::with_max_size(conf.cache_size))
-            .wrap(io::Cursor::new(Vec::<u8>::new()));
+            .wrap(MemBuf::default());
+
+        pager.init()?;
+
+        Ok(Database::new(Rc::new(RefCell::new(pager)), PathBuf::new()))
+    }
+
+    /// Opens the database stored at `path` in the [`MemBuf`] file system or
+    /// creates it if it doesn't exist, same as [`Database::init`].
+    fn open_database_at(path: &str, conf: DbConf) -> Result<Database<MemBuf>, DbError> {
+        open_database_with(path, conf, DbOptions::default())
+    }
+
+    /// Same as [`open_database_at`] with custom [`DbOptions`].
+    fn open_database_with(
+        path: &str,
+        conf: DbConf,
+        options: DbOptions,
+    ) -> Result<Database<MemBuf>, DbError> {
+        let file = match MemBuf::exists(path) {
+            true => MemBuf::open(path)?,
+            false => MemBuf::create(path)?,
+        };
+
+        let mut pager = options
+            .pager_builder()
+            .page_size(conf.page_size)
+            .cache(Cache::with_max_size(conf.cache_size))
+            .journal_file_path(PathBuf::from(format!("{path}.journal")))
+            .wrap(file);
 
         pager.init()?;
+        pager.rollback()?;
 
         Ok(Database::new(Rc::new(RefCell::new(pager)), PathBuf::new()))
     }
@@ -1234,13 +2181,18 @@ mod tests {
         let index = db.index_metadata(name)?;
 
         let mut pager = db.pager.borrow_mut();
+        let version = pager.format_version;
         let mut cursor = Cursor::new(index.root, 0);
 
         let mut entries = Vec::new();
 
         while let Some((page, slot)) = cursor.try_next(&mut pager)? {
             let entry = reassemble_payload(&mut pager, page, slot)?;
-            entries.push(tuple::deserialize(entry.as_ref(), &index.schema));
+            entries.push(tuple::deserialize_with(
+                version,
+                entry.as_ref(),
+                &index.schema,
+            )?);
         }
 
         assert_eq!(entries, expected_entries);
@@ -1264,7 +2216,7 @@ mod tests {
                 Value::String("users".into()),
                 Value::Number(1),
                 Value::String("users".into()),
-                Value::String(Parser::new(sql).parse_statement()?.to_string())
+                Value::String(Parser::new(sql).parse_statement()?.to_sql_safe())
             ]])
         );
 
@@ -1288,7 +2240,7 @@ mod tests {
                     Value::String("users".into()),
                     Value::Number(1),
                     Value::String("users".into()),
-                    Value::String(Parser::new(sql).parse_statement()?.to_string())
+                    Value::String(Parser::new(sql).parse_statement()?.to_sql_safe())
                 ],
                 vec![
                     Value::String("index".into()),
@@ -1298,7 +2250,7 @@ mod tests {
                     Value::String(
                         Parser::new("CREATE UNIQUE INDEX users_pk_index ON users(id);")
                             .parse_statement()?
-                            .to_string()
+                            .to_sql_safe()
                     )
                 ]
             ])
@@ -1331,21 +2283,21 @@ mod tests {
                     Value::String("users".into()),
                     Value::Number(1),
                     Value::String("users".into()),
-                    Value::String(Parser::new(t1).parse_statement()?.to_string())
+                    Value::String(Parser::new(t1).parse_statement()?.to_sql_safe())
                 ],
                 vec![
                     Value::String("table".into()),
                     Value::String("tasks".into()),
                     Value::Number(2),
                     Value::String("tasks".into()),
-                    Value::String(Parser::new(t2).parse_statement()?.to_string())
+                    Value::String(Parser::new(t2).parse_statement()?.to_sql_safe())
                 ],
                 vec![
                     Value::String("table".into()),
                     Value::String("products".into()),
                     Value::Number(3),
                     Value::String("products".into()),
-                    Value::String(Parser::new(t3).parse_statement()?.to_string())
+                    Value::String(Parser::new(t3).parse_statement()?.to_sql_safe())
                 ],
             ]
         });
@@ -1382,42 +2334,42 @@ mod tests {
                     Value::String("users".into()),
                     Value::Number(1),
                     Value::String("users".into()),
-                    Value::String(Parser::new(t1).parse_statement()?.to_string())
+                    Value::String(Parser::new(t1).parse_statement()?.to_sql_safe())
                 ],
                 vec![
                     Value::String("index".into()),
                     Value::String("users_pk_index".into()),
                     Value::Number(2),
                     Value::String("users".into()),
-                    Value::String(Parser::new(i1).parse_statement()?.to_string())
+                    Value::String(Parser::new(i1).parse_statement()?.to_sql_safe())
                 ],
                 vec![
                     Value::String("table".into()),
                     Value::String("tasks".into()),
                     Value::Number(3),
                     Value::String("tasks".into()),
-                    Value::String(Parser::new(t2).parse_statement()?.to_string())
+                    Value::String(Parser::new(t2).parse_statement()?.to_sql_safe())
                 ],
                 vec![
                     Value::String("index".into()),
                     Value::String("tasks_pk_index".into()),
                     Value::Number(4),
                     Value::String("tasks".into()),
-                    Value::String(Parser::new(i2).parse_statement()?.to_string())
+                    Value::String(Parser::new(i2).parse_statement()?.to_sql_safe())
                 ],
                 vec![
                     Value::String("table".into()),
                     Value::String("products".into()),
                     Value::Number(5),
                     Value::String("products".into()),
-                    Value::String(Parser::new(t3).parse_statement()?.to_string())
+                    Value::String(Parser::new(t3).parse_statement()?.to_sql_safe())
                 ],
                 vec![
                     Value::String("index".into()),
                     Value::String("products_pk_index".into()),
                     Value::Number(6),
                     Value::String("products".into()),
-                    Value::String(Parser::new(i3).parse_statement()?.to_string())
+                    Value::String(Parser::new(i3).parse_statement()?.to_sql_safe())
                 ],
             ]
         });
@@ -1450,7 +2402,7 @@ mod tests {
                 Value::String(table_name.clone()),
                 Value::Number(0),
                 Value::String(table_name.clone()),
-                Value::String(Parser::new(&table_sql).parse_statement()?.to_string()),
+                Value::String(Parser::new(&table_sql).parse_statement()?.to_sql_safe()),
             ]);
 
             expected.push(vec![
@@ -1458,7 +2410,7 @@ mod tests {
                 Value::String(index_name),
                 Value::Number(0),
                 Value::String(table_name),
-                Value::String(Parser::new(&index_sql).parse_statement()?.to_string()),
+                Value::String(Parser::new(&index_sql).parse_statement()?.to_sql_safe()),
             ]);
         }
 
@@ -1681,420 +2633,433 @@ mod tests {
     }
 
     #[test]
-    fn select_where() -> Result<(), DbError> {
+    fn insert_with_implicit_casts() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
 
-        let query = db.exec("SELECT * FROM users WHERE age > 18;")?;
+        let insert = "INSERT INTO users(id, name) VALUES ('123', 456);";
+
+        assert_eq!(
+            db.exec(insert),
+            Err(DbError::Sql(SqlError::TypeError(TypeError::ExpectedType {
+                expected: VmDataType::Number,
+                found: Expression::Value(Value::String("123".into()))
+            })))
+        );
+
+        db.implicit_casts = true;
+        db.exec(insert)?;
+
+        assert_eq!(
+            db.exec("INSERT INTO users(id, name) VALUES ('abc', 'John');"),
+            Err(DbError::Sql(SqlError::TypeError(TypeError::ExpectedType {
+                expected: VmDataType::Number,
+                found: Expression::Value(Value::String("abc".into()))
+            })))
+        );
+
+        let query = db.exec("SELECT * FROM users;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
                 Column::new("name", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
             ]),
-            tuples: vec![
-                vec![
-                    Value::Number(2),
-                    Value::String("Jane Doe".into()),
-                    Value::Number(22)
-                ],
-                vec![
-                    Value::Number(3),
-                    Value::String("Some Dude".into()),
-                    Value::Number(24)
-                ],
-            ]
+            tuples: vec![vec![Value::Number(123), Value::String("456".into())]],
         });
 
         Ok(())
     }
 
     #[test]
-    fn select_disordered_columns() -> Result<(), DbError> {
+    fn insert_and_select_doubles() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT, is_admin BOOL);",
-        )?;
-        db.exec("INSERT INTO users(id, name, age, is_admin) VALUES (1, 'John Doe', 18, TRUE);")?;
-        db.exec("INSERT INTO users(id, name, age, is_admin) VALUES (2, 'Jane Doe', 22, FALSE);")?;
+        db.exec("CREATE TABLE products (id INT PRIMARY KEY, price DOUBLE UNIQUE);")?;
+        db.exec("INSERT INTO products(id, price) VALUES (1, 9.99);")?;
+        db.exec("INSERT INTO products(id, price) VALUES (2, -1.5);")?;
+        db.exec("INSERT INTO products(id, price) VALUES (3, 10);")?;
+        db.exec("INSERT INTO products(id, price) VALUES (4, 0.25);")?;
 
-        let query = db.exec("SELECT age, name, id, is_admin FROM users;")?;
+        assert_eq!(
+            db.exec("INSERT INTO products(id, price) VALUES (2.5, 1.0);"),
+            Err(TypeError::ExpectedType {
+                expected: VmDataType::Number,
+                found: Expression::Value(Value::Float(2.5)),
+            }
+            .into())
+        );
 
+        let query = db.exec("SELECT * FROM products ORDER BY price;")?;
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
-                Column::new("age", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
                 Column::primary_key("id", DataType::Int),
-                Column::new("is_admin", DataType::Bool),
+                Column::unique("price", DataType::Double),
             ]),
             tuples: vec![
-                vec![
-                    Value::Number(18),
-                    Value::String("John Doe".into()),
-                    Value::Number(1),
-                    Value::Bool(true),
-                ],
-                vec![
-                    Value::Number(22),
-                    Value::String("Jane Doe".into()),
-                    Value::Number(2),
-                    Value::Bool(false),
-                ],
+                vec![Value::Number(2), Value::Float(-1.5)],
+                vec![Value::Number(4), Value::Float(0.25)],
+                vec![Value::Number(1), Value::Float(9.99)],
+                vec![Value::Number(3), Value::Float(10.0)],
             ]
         });
 
+        // Range scan over the UNIQUE index.
+        let query = db.exec("SELECT id, price * 2 FROM products WHERE price > 0.25;")?;
+        assert_eq!(query.tuples, vec![
+            vec![Value::Number(1), Value::Float(19.98)],
+            vec![Value::Number(3), Value::Float(20.0)],
+        ]);
+
         Ok(())
     }
 
     #[test]
-    fn select_expressions() -> Result<(), DbError> {
+    fn insert_char_fixed_width() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE products (id INT PRIMARY KEY, price INT, discount INT);")?;
-        db.exec("INSERT INTO products(id, price, discount) VALUES (1, 100, 5);")?;
-        db.exec("INSERT INTO products(id, price, discount) VALUES (2, 250, 10);")?;
+        db.exec("CREATE TABLE codes (id INT PRIMARY KEY, code CHAR(5), name VARCHAR(10));")?;
+        db.exec("INSERT INTO codes(id, code, name) VALUES (1, 'ab', 'x');")?;
+        db.exec("INSERT INTO codes(id, code, name) VALUES (2, 'abcde', 'yz');")?;
 
-        let query = db.exec("SELECT id, price / 10, discount * 100 FROM products;")?;
+        assert_eq!(
+            db.exec("INSERT INTO codes(id, code, name) VALUES (3, 'abcdef', 'z');"),
+            Err(AnalyzerError::ValueTooLong("abcdef".into(), DataType::Char(5)).into())
+        );
+
+        let root = db.table_metadata("codes")?.root;
+        let mut pager = db.pager.borrow_mut();
+        let mut cursor = Cursor::new(root, 0);
+        let mut codes = Vec::new();
+
+        // Every CHAR(5) value takes exactly 5 bytes, so the VARCHAR length
+        // prefix that follows is always found at the same offset.
+        while let Some((page, slot)) = cursor.try_next(&mut pager)? {
+            let entry = reassemble_payload(&mut pager, page, slot)?;
+            codes.push((entry.as_ref()[4..9].to_vec(), entry.as_ref()[9]));
+        }
+
+        drop(pager);
+
+        assert_eq!(codes, vec![(b"ab   ".to_vec(), 1), (b"abcde".to_vec(), 2)]);
+
+        let query = db.exec("SELECT * FROM codes WHERE code = 'ab';")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
-                Column::new("price / 10", DataType::BigInt),
-                Column::new("discount * 100", DataType::BigInt),
+                Column::new("code", DataType::Char(5)),
+                Column::new("name", DataType::Varchar(10)),
             ]),
-            tuples: vec![
-                vec![Value::Number(1), Value::Number(10), Value::Number(500),],
-                vec![Value::Number(2), Value::Number(25), Value::Number(1000),],
-            ]
+            tuples: vec![vec![
+                Value::Number(1),
+                Value::String("ab".into()),
+                Value::String("x".into())
+            ]],
         });
 
         Ok(())
     }
 
-    #[cfg(not(miri))]
     #[test]
-    fn select_many() -> Result<(), DbError> {
-        let mut db = init_database_with(DbConf {
-            page_size: 96,
-            cache_size: 1024,
-        })?;
+    fn describe_select() -> Result<(), DbError> {
+        let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT UNSIGNED);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John', 18);")?;
 
-        let mut expected = Vec::new();
+        let column_types = |schema: Schema| {
+            schema
+                .columns
+                .into_iter()
+                .map(|col| (col.name, col.data_type))
+                .collect::<Vec<_>>()
+        };
 
-        for i in 1..=500 {
-            expected.push(vec![Value::Number(i), Value::String(format!("User{i}"))]);
-        }
+        assert_eq!(column_types(db.describe("SELECT * FROM users;")?), vec![
+            (String::from("id"), DataType::Int),
+            (String::from("name"), DataType::Varchar(255)),
+            (String::from("age"), DataType::UnsignedInt),
+        ]);
 
-        for user in expected.iter().rev() {
-            db.exec(&format!(
-                "INSERT INTO users(id, name) VALUES ({}, {});",
-                user[0], user[1]
-            ))?;
-        }
+        assert_eq!(
+            column_types(db.describe("SELECT age, id FROM users WHERE id > 0;")?),
+            vec![
+                (String::from("age"), DataType::UnsignedInt),
+                (String::from("id"), DataType::Int),
+            ]
+        );
 
-        let query = db.exec("SELECT * FROM users;")?;
+        assert_eq!(
+            column_types(db.describe("SELECT name, age * 2 AS double_age, id < 5 FROM users;")?),
+            vec![
+                (String::from("name"), DataType::Varchar(255)),
+                (String::from("double_age"), DataType::BigInt),
+                (String::from("id < 5"), DataType::Bool),
+            ]
+        );
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-            ]),
-            tuples: expected,
-        });
+        // Nothing is executed.
+        assert_eq!(db.describe("DELETE FROM users;")?, Schema::empty());
+        assert_eq!(db.exec("SELECT id FROM users;")?.tuples, vec![vec![
+            Value::Number(1)
+        ]]);
 
         Ok(())
     }
 
     #[test]
-    fn select_order_by() -> Result<(), DbError> {
+    fn last_plan_stats() -> Result<(), DbError> {
         let mut db = init_database()?;
 
         db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'John Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
-
-        let query = db.exec("SELECT * FROM users ORDER BY name, age;")?;
+        for i in 1..=10 {
+            db.exec(&format!(
+                "INSERT INTO users(id, name, age) VALUES ({i}, 'User {i}', {});",
+                i + 15
+            ))?;
+        }
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
-            ]),
-            tuples: vec![
-                vec![
-                    Value::Number(1),
-                    Value::String("John Doe".into()),
-                    Value::Number(18)
-                ],
-                vec![
-                    Value::Number(2),
-                    Value::String("John Doe".into()),
-                    Value::Number(22)
-                ],
-                vec![
-                    Value::Number(3),
-                    Value::String("Some Dude".into()),
-                    Value::Number(24)
-                ]
-            ]
-        });
+        let query = db.exec("SELECT * FROM users WHERE age > 20;")?;
+        let stats = db.last_plan_stats.clone();
+
+        assert_eq!(query.tuples.len(), 5);
+        assert_eq!(stats.rows_scanned, 10);
+        assert_eq!(stats.scans, vec![ScanStats {
+            node: String::from("SeqScan on table 'users'"),
+            rows: 10,
+        }]);
+        assert_eq!(stats.rows_returned, 5);
+        assert_eq!(stats.spills, 0);
+        assert!(stats.pages_read + stats.cache_hits > 0);
+
+        db.exec("SELECT * FROM users WHERE id = 3;")?;
+
+        assert_eq!(db.last_plan_stats.rows_scanned, 1);
+        assert_eq!(db.last_plan_stats.rows_returned, 1);
+
+        db.exec("CREATE UNIQUE INDEX age_idx ON users(age);")?;
+        db.exec("SELECT * FROM users WHERE age >= 23;")?;
+
+        // The index scan counts entries and the key scan table rows.
+        assert_eq!(db.last_plan_stats.rows_scanned, 6);
+        assert_eq!(db.last_plan_stats.scans, vec![
+            ScanStats {
+                node: String::from("KeyScan (id) on table 'users'"),
+                rows: 3,
+            },
+            ScanStats {
+                node: String::from("RangeScan (age >= 23) on index 'age_idx' (age)"),
+                rows: 3,
+            },
+        ]);
 
         Ok(())
     }
 
-    // We skip sorting when ordering only by primary key since the BTree is
-    // already sorted by key.
     #[test]
-    fn select_order_by_primary_key() -> Result<(), DbError> {
+    fn index_only_scan_skips_table() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'John Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);")?;
+        for i in 1..=10 {
+            db.exec(&format!(
+                "INSERT INTO users(id, name, email) VALUES ({i}, 'User {i}', 'user{i:02}@email.com');"
+            ))?;
+        }
 
-        let query = db.exec("SELECT * FROM users ORDER BY id;")?;
+        let emails = |ids: &[i32]| {
+            ids.iter()
+                .map(|id| vec![Value::String(format!("user{id:02}@email.com"))])
+                .collect::<Vec<_>>()
+        };
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
-            ]),
-            tuples: vec![
-                vec![
-                    Value::Number(1),
-                    Value::String("John Doe".into()),
-                    Value::Number(18)
-                ],
-                vec![
-                    Value::Number(2),
-                    Value::String("John Doe".into()),
-                    Value::Number(22)
-                ],
-                vec![
-                    Value::Number(3),
-                    Value::String("Some Dude".into()),
-                    Value::Number(24)
-                ]
-            ]
-        });
+        let query = db.exec("SELECT email FROM users WHERE email > 'user05@email.com';")?;
+        assert_eq!(query.tuples, emails(&[6, 7, 8, 9, 10]));
+        assert_eq!(db.last_plan_stats.rows_scanned, 5);
+
+        // Has to read the table rows to get the names.
+        db.exec("SELECT name FROM users WHERE email > 'user05@email.com';")?;
+        assert_eq!(db.last_plan_stats.rows_scanned, 10);
+
+        let query = db.exec("SELECT id, email FROM users WHERE email = 'user03@email.com';")?;
+        assert_eq!(query.tuples, vec![vec![
+            Value::Number(3),
+            Value::String("user03@email.com".into())
+        ]]);
+        assert_eq!(db.last_plan_stats.rows_scanned, 1);
 
         Ok(())
     }
 
-    // Force the external merge sort algorithm to do some real work.
-    #[cfg(not(miri))]
     #[test]
-    fn select_order_by_many() -> Result<(), DbError> {
-        let mut db = init_database_with(DbConf {
-            page_size: 96,
-            cache_size: 1024,
-        })?;
-
-        db.exec("CREATE TABLE users (name VARCHAR(255));")?;
-
-        let mut expected = Vec::new();
+    fn analyze_table() -> Result<(), DbError> {
+        let mut db = init_database()?;
 
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, age INT, name VARCHAR(255));")?;
+        db.exec("CREATE UNIQUE INDEX age_name ON users(age, name);")?;
         for i in 1..=500 {
-            let name = format!("User{i:03}");
-            expected.push(vec![Value::String(name)]);
+            db.exec(&format!(
+                "INSERT INTO users(id, email, age, name) VALUES ({i}, 'user{i}@email.com', {}, 'User {i}');",
+                i % 20
+            ))?;
         }
 
-        for user in expected.iter().rev() {
-            db.exec(&format!("INSERT INTO users(name) VALUES ({});", user[0]))?;
-        }
+        assert_eq!(db.table_metadata("users")?.stats, None);
 
-        let query = db.exec("SELECT * FROM users ORDER BY name;")?;
+        db.exec("ANALYZE users;")?;
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![Column::new("name", DataType::Varchar(255)),]),
-            tuples: expected,
-        });
+        // Make sure the statistics are read back from disk.
+        db.context.clear();
+        let stats = db.table_metadata("users")?.stats.clone().unwrap();
+
+        let approx_eq = |count: usize, expected: usize| count.abs_diff(expected) * 10 <= expected;
+
+        assert_eq!(stats.rows, 500);
+        assert_eq!(stats.distinct.len(), 3);
+        assert!(approx_eq(stats.distinct["email"], 500), "{stats:?}");
+        assert!(approx_eq(stats.distinct["name"], 500), "{stats:?}");
+        assert!(approx_eq(stats.distinct["age"], 20), "{stats:?}");
+
+        // Statistics are not updated until the next ANALYZE.
+        db.exec("DELETE FROM users WHERE id > 100;")?;
+        db.exec(
+            "INSERT INTO users(id, email, age, name) VALUES (1000, 'new@email.com', 50, 'New');",
+        )?;
+        assert_eq!(
+            db.table_metadata("users")?.stats.as_ref().unwrap().rows,
+            500
+        );
+
+        db.exec("ANALYZE users;")?;
+        let stats = db.table_metadata("users")?.stats.clone().unwrap();
+        assert_eq!(stats.rows, 101);
+        assert!(approx_eq(stats.distinct["age"], 21), "{stats:?}");
+
+        // Only one row of statistics per table.
+        let query = db.exec("SELECT name FROM mkdb_meta WHERE type = 'stats';")?;
+        assert_eq!(query.tuples, vec![vec![Value::String("users".into())]]);
+
+        // Schema changes discard the statistics.
+        db.exec("ALTER TABLE users RENAME COLUMN name TO full_name;")?;
+        assert_eq!(db.table_metadata("users")?.stats, None);
+
+        db.exec("ANALYZE users;")?;
+        db.exec("DROP TABLE users;")?;
+        assert!(db.exec("SELECT * FROM mkdb_meta;")?.is_empty());
 
         Ok(())
     }
 
-    // The default page size won't be enough to store these tuples.
     #[test]
-    fn select_order_by_large_tuples() -> Result<(), DbError> {
+    fn range_scan_skips_pages_outside_bounds() -> Result<(), DbError> {
         let mut db = init_database_with(DbConf {
             page_size: 96,
             cache_size: 1024,
         })?;
 
-        db.exec("CREATE TABLE users (id BIGINT, name VARCHAR(255), email VARCHAR(255), age INT);")?;
-
-        let mut expected = Vec::new();
-
-        for i in 1..=10 {
-            let name = format!("Very Long User{i} Name").repeat(10);
-            let mut email = format!("very_long_user_email{i}").repeat(10);
-            email.push_str("@email.com");
-            expected.push(vec![
-                Value::Number(i),
-                Value::String(name),
-                Value::String(email),
-                Value::Number(i + 20),
-            ]);
-        }
-
-        for user in expected.iter().rev() {
+        db.exec("CREATE TABLE events (id INT PRIMARY KEY, name VARCHAR(255));")?;
+        for i in 1..=200 {
             db.exec(&format!(
-                "INSERT INTO users(id, name, email, age) VALUES ({});",
-                user.iter()
-                    .map(|v| v.to_string())
-                    .collect::<Vec<_>>()
-                    .join(",")
+                "INSERT INTO events(id, name) VALUES ({i}, 'Event {i}');"
             ))?;
         }
 
-        expected.sort_by(|user1, user2| {
-            user1[1]
-                .partial_cmp(&user2[1])
-                .or(user1[2].partial_cmp(&user2[2]).or_else(|| {
-                    let (Value::Number(age1), Value::Number(age2)) = (&user1[3], &user2[3]) else {
-                        unreachable!();
-                    };
+        db.exec("SELECT * FROM events;")?;
+        let full_scan = db.last_plan_stats.clone();
 
-                    (age1 + 10).partial_cmp(&(age2 + 10))
-                }))
-                .unwrap_or(Ordering::Equal)
-        });
+        let query = db.exec("SELECT * FROM events WHERE id >= 100 AND id < 105;")?;
+        let range_scan = db.last_plan_stats.clone();
 
-        let query = db.exec("SELECT * FROM users ORDER BY name, email, age + 10;")?;
+        assert_eq!(query.tuples.len(), 5);
+        assert_eq!(full_scan.rows_scanned, 200);
+        // The scan has to read the first key past the end bound to stop.
+        assert_eq!(range_scan.rows_scanned, 6);
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::new("id", DataType::BigInt),
-                Column::new("name", DataType::Varchar(255)),
-                Column::new("email", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
-            ],),
-            tuples: expected,
-        });
+        let pages_visited = |stats: PlanStats| stats.pages_read + stats.cache_hits;
+        assert!(pages_visited(range_scan) * 10 < pages_visited(full_scan));
 
         Ok(())
     }
 
-    // Test for tuples of different sizes.
     #[test]
-    fn select_order_by_var_length() -> Result<(), DbError> {
+    fn last_plan_stats_sort_spills() -> Result<(), DbError> {
         let mut db = init_database_with(DbConf {
             page_size: 96,
             cache_size: 1024,
         })?;
 
-        db.exec("CREATE TABLE var_len (name VARCHAR(255));")?;
-
-        // Purposefully crafted to cause issues when sorting since the var
-        // length data will not produce the same amount of pages on each run.
-        let mut var_length_records = vec![
-            format!("04 {}", "var_length_tuple_".repeat(5)),
-            format!("09 {}", "var_length_tuple_".repeat(2)),
-            format!("06 {}", "var_length_tuple_".repeat(7)),
-            format!("00 {}", "var_length_tuple_".repeat(1)),
-            format!("08 {}", "var_length_tuple_".repeat(1)),
-            format!("03 {}", "var_length_tuple_".repeat(4)),
-            format!("01 {}", "var_length_tuple_".repeat(2)),
-            format!("02 {}", "var_length_tuple_".repeat(3)),
-            format!("05 {}", "var_length_tuple_".repeat(6)),
-            format!("07 {}", "var_length_tuple_".repeat(8)),
-        ];
-
-        for record in var_length_records.iter() {
-            db.exec(&format!("INSERT INTO var_len VALUES ('{}');", record))?;
+        db.exec("CREATE TABLE users (id INT, name VARCHAR(255));")?;
+        for i in 1..=10 {
+            let name = format!("Very Long User{i} Name").repeat(10);
+            db.exec(&format!(
+                "INSERT INTO users(id, name) VALUES ({i}, '{name}');"
+            ))?;
         }
 
-        var_length_records.sort();
+        db.exec("SELECT * FROM users ORDER BY name;")?;
 
-        let query = db.exec("SELECT name FROM var_len ORDER BY name;")?;
+        assert_eq!(db.last_plan_stats.rows_scanned, 10);
+        assert_eq!(db.last_plan_stats.rows_returned, 10);
+        assert!(db.last_plan_stats.spills > 0);
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![Column::new("name", DataType::Varchar(255))]),
-            tuples: var_length_records
-                .into_iter()
-                .map(|record| vec![Value::String(record)])
-                .collect(),
-        });
+        // Reading only one tuple.
+        let mut rows = db.query("SELECT * FROM users ORDER BY name;")?.into_rows();
+        rows.next().transpose()?;
+        drop(rows);
+
+        assert_eq!(db.last_plan_stats.rows_returned, 1);
+        assert!(db.last_plan_stats.spills > 0);
 
         Ok(())
     }
 
     #[test]
-    fn select_order_by_expressions() -> Result<(), DbError> {
-        let mut db = init_database_with(DbConf {
-            page_size: 96,
-            cache_size: 1024,
-        })?;
-
-        db.exec("CREATE TABLE products (id INT, name VARCHAR(255), price INT, discount INT);")?;
-
-        let mut products = vec![
-            (1, "mouse", 20, 10),
-            (2, "mouse", 20, 20),
-            (3, "keyboard", 40, 25),
-            (4, "keyboard", 20, 50),
-            (5, "speakers", 40, 10),
-        ];
+    fn select_limit_offset() -> Result<(), DbError> {
+        let mut db = init_database()?;
 
-        for (id, name, price, discount) in &products {
-            db.exec(&format!("INSERT INTO products(id, name, price, discount) VALUES ({id}, '{name}', {price}, {discount});"))?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        for i in 1..=20 {
+            db.exec(&format!(
+                "INSERT INTO users(id, name, age) VALUES ({i}, 'User {i}', {});",
+                (i * 7) % 20
+            ))?;
         }
 
-        products.sort_by(
-            |(_, name1, price1, discount1), (_, name2, price2, discount2)| {
-                let mut ordering = name1.cmp(name2);
+        let query = db.exec("SELECT id FROM users LIMIT 3 OFFSET 5;")?;
+        assert_eq!(query.tuples, vec![
+            vec![Value::Number(6)],
+            vec![Value::Number(7)],
+            vec![Value::Number(8)],
+        ]);
 
-                if ordering == Ordering::Equal {
-                    ordering = (price1 - price1 * discount1 / 100)
-                        .cmp(&(price2 - price2 * discount2 / 100));
-                }
+        // The scan stops as soon as the limit is reached.
+        assert_eq!(db.last_plan_stats.rows_scanned, 8);
 
-                if ordering == Ordering::Equal {
-                    ordering = price1.cmp(price2);
-                }
+        let query = db.exec("SELECT id, age FROM users ORDER BY age LIMIT 3;")?;
+        assert_eq!(query.tuples, vec![
+            vec![Value::Number(20), Value::Number(0)],
+            vec![Value::Number(3), Value::Number(1)],
+            vec![Value::Number(6), Value::Number(2)],
+        ]);
 
-                ordering
-            },
-        );
+        let query = db.exec("SELECT id, age FROM users ORDER BY age LIMIT 5 OFFSET 18;")?;
+        assert_eq!(query.tuples, vec![
+            vec![Value::Number(14), Value::Number(18)],
+            vec![Value::Number(17), Value::Number(19)],
+        ]);
 
-        let query = db
-            .exec("SELECT * FROM products ORDER BY name, price - price * discount / 100, price;")?;
+        let query = db.exec("SELECT id FROM users OFFSET 19;")?;
+        assert_eq!(query.tuples, vec![vec![Value::Number(20)]]);
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::new("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::new("price", DataType::Int),
-                Column::new("discount", DataType::Int),
-            ]),
-            tuples: products
-                .into_iter()
-                .map(|(id, name, price, discount)| vec![
-                    Value::Number(id),
-                    Value::String(name.into()),
-                    Value::Number(price),
-                    Value::Number(discount)
-                ])
-                .collect(),
-        });
+        assert!(db.exec("SELECT id FROM users LIMIT 0;")?.tuples.is_empty());
 
         Ok(())
     }
 
     #[test]
-    fn select_where_auto_index_exact() -> Result<(), DbError> {
+    fn select_where() -> Result<(), DbError> {
         let mut db = init_database()?;
 
         db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
@@ -2102,7 +3067,7 @@ mod tests {
         db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
         db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
 
-        let query = db.exec("SELECT * FROM users WHERE id = 2;")?;
+        let query = db.exec("SELECT * FROM users WHERE age > 18;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
@@ -2110,161 +3075,195 @@ mod tests {
                 Column::new("name", DataType::Varchar(255)),
                 Column::new("age", DataType::Int),
             ]),
-            tuples: vec![vec![
-                Value::Number(2),
-                Value::String("Jane Doe".into()),
-                Value::Number(22)
-            ],]
+            tuples: vec![
+                vec![
+                    Value::Number(2),
+                    Value::String("Jane Doe".into()),
+                    Value::Number(22)
+                ],
+                vec![
+                    Value::Number(3),
+                    Value::String("Some Dude".into()),
+                    Value::Number(24)
+                ],
+            ]
         });
 
         Ok(())
     }
 
     #[test]
-    fn select_where_indexed_column_exact() -> Result<(), DbError> {
+    fn select_disordered_columns() -> Result<(), DbError> {
         let mut db = init_database()?;
 
         db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT, is_admin BOOL);",
         )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+        db.exec("INSERT INTO users(id, name, age, is_admin) VALUES (1, 'John Doe', 18, TRUE);")?;
+        db.exec("INSERT INTO users(id, name, age, is_admin) VALUES (2, 'Jane Doe', 22, FALSE);")?;
 
-        let query = db.exec("SELECT * FROM users WHERE email = 'bob@email.com';")?;
+        let query = db.exec("SELECT age, name, id, is_admin FROM users;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
+                Column::new("age", DataType::Int),
                 Column::new("name", DataType::Varchar(255)),
-                Column::unique("email", DataType::Varchar(255)),
+                Column::primary_key("id", DataType::Int),
+                Column::new("is_admin", DataType::Bool),
             ]),
-            tuples: vec![vec![
-                Value::Number(2),
-                Value::String("Bob".into()),
-                Value::String("bob@email.com".into()),
-            ],]
+            tuples: vec![
+                vec![
+                    Value::Number(18),
+                    Value::String("John Doe".into()),
+                    Value::Number(1),
+                    Value::Bool(true),
+                ],
+                vec![
+                    Value::Number(22),
+                    Value::String("Jane Doe".into()),
+                    Value::Number(2),
+                    Value::Bool(false),
+                ],
+            ]
         });
 
         Ok(())
     }
 
     #[test]
-    fn select_where_auto_index_exact_not_found() -> Result<(), DbError> {
+    fn select_expressions() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (10, 'John Doe', 18);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (20, 'Jane Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (30, 'Some Dude', 24);")?;
+        db.exec("CREATE TABLE products (id INT PRIMARY KEY, price INT, discount INT);")?;
+        db.exec("INSERT INTO products(id, price, discount) VALUES (1, 100, 5);")?;
+        db.exec("INSERT INTO products(id, price, discount) VALUES (2, 250, 10);")?;
 
-        let query = db.exec("SELECT * FROM users WHERE id = 15;")?;
+        let query = db.exec("SELECT id, price / 10, discount * 100 FROM products;")?;
 
-        assert!(query.is_empty());
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("price / 10", DataType::BigInt),
+                Column::new("discount * 100", DataType::BigInt),
+            ]),
+            tuples: vec![
+                vec![Value::Number(1), Value::Number(10), Value::Number(500),],
+                vec![Value::Number(2), Value::Number(25), Value::Number(1000),],
+            ]
+        });
 
         Ok(())
     }
 
     #[test]
-    fn select_where_indexed_column_exact_not_found() -> Result<(), DbError> {
+    fn select_concat() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
-        )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, first VARCHAR(10), last CHAR(20));")?;
+        db.exec("INSERT INTO users(id, first, last) VALUES (1, 'John', 'Doe');")?;
+        db.exec("INSERT INTO users(id, first, last) VALUES (2, 'Jane', 'Smith');")?;
 
-        let query = db.exec("SELECT * FROM users WHERE email = 'carla@email.com';")?;
+        let query =
+            db.exec("SELECT first || ' ' || last FROM users WHERE last || '!' = 'Doe!';")?;
 
-        assert!(query.is_empty());
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![Column::new(
+                "first || \" \" || last",
+                DataType::Varchar(31)
+            )]),
+            tuples: vec![vec![Value::String("John Doe".into())]],
+        });
 
         Ok(())
     }
 
+    #[cfg(not(miri))]
     #[test]
-    fn select_where_auto_index_less_than() -> Result<(), DbError> {
-        let mut db = init_database()?;
+    fn select_many() -> Result<(), DbError> {
+        let mut db = init_database_with(DbConf {
+            page_size: 96,
+            cache_size: 1024,
+        })?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (4, 'Another Dude', 30);")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
 
-        let query = db.exec("SELECT * FROM users WHERE id < 3;")?;
+        let mut expected = Vec::new();
+
+        for i in 1..=500 {
+            expected.push(vec![Value::Number(i), Value::String(format!("User{i}"))]);
+        }
+
+        for user in expected.iter().rev() {
+            db.exec(&format!(
+                "INSERT INTO users(id, name) VALUES ({}, {});",
+                user[0], user[1]
+            ))?;
+        }
+
+        let query = db.exec("SELECT * FROM users;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
                 Column::new("name", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
             ]),
-            tuples: vec![
-                vec![
-                    Value::Number(1),
-                    Value::String("John Doe".into()),
-                    Value::Number(18)
-                ],
-                vec![
-                    Value::Number(2),
-                    Value::String("Jane Doe".into()),
-                    Value::Number(22)
-                ],
-            ]
+            tuples: expected,
         });
 
         Ok(())
     }
 
     #[test]
-    fn select_where_indexed_column_less_than() -> Result<(), DbError> {
+    fn select_order_by() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
-        )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'John Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
 
-        let query = db.exec("SELECT * FROM users WHERE email < 'david@email.com';")?;
+        let query = db.exec("SELECT * FROM users ORDER BY name, age;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
                 Column::new("name", DataType::Varchar(255)),
-                Column::unique("email", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
             ]),
             tuples: vec![
                 vec![
                     Value::Number(1),
-                    Value::String("Alex".into()),
-                    Value::String("alex@email.com".into()),
+                    Value::String("John Doe".into()),
+                    Value::Number(18)
                 ],
                 vec![
                     Value::Number(2),
-                    Value::String("Bob".into()),
-                    Value::String("bob@email.com".into()),
+                    Value::String("John Doe".into()),
+                    Value::Number(22)
                 ],
+                vec![
+                    Value::Number(3),
+                    Value::String("Some Dude".into()),
+                    Value::Number(24)
+                ]
             ]
         });
 
         Ok(())
     }
 
+    // We skip sorting when ordering only by primary key since the BTree is
+    // already sorted by key.
     #[test]
-    fn select_where_auto_index_less_than_not_found() -> Result<(), DbError> {
+    fn select_order_by_primary_key() -> Result<(), DbError> {
         let mut db = init_database()?;
 
         db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (10, 'John Doe', 18);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (20, 'Jane Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (30, 'Some Dude', 24);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (40, 'Another Dude', 30);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'John Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
 
-        let query = db.exec("SELECT * FROM users WHERE id < 35;")?;
+        let query = db.exec("SELECT * FROM users ORDER BY id;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
@@ -2274,20 +3273,20 @@ mod tests {
             ]),
             tuples: vec![
                 vec![
-                    Value::Number(10),
+                    Value::Number(1),
                     Value::String("John Doe".into()),
                     Value::Number(18)
                 ],
                 vec![
-                    Value::Number(20),
-                    Value::String("Jane Doe".into()),
+                    Value::Number(2),
+                    Value::String("John Doe".into()),
                     Value::Number(22)
                 ],
                 vec![
-                    Value::Number(30),
+                    Value::Number(3),
                     Value::String("Some Dude".into()),
                     Value::Number(24)
-                ],
+                ]
             ]
         });
 
@@ -2295,345 +3294,479 @@ mod tests {
     }
 
     #[test]
-    fn select_where_indexed_column_less_than_not_found() -> Result<(), DbError> {
+    fn select_order_by_desc() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
-        )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Some Dude', 24);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'John Doe', 22);")?;
 
-        let query = db.exec("SELECT * FROM users WHERE email < 'carla@email.com';")?;
+        let schema = Schema::new(vec![
+            Column::primary_key("id", DataType::Int),
+            Column::new("name", DataType::Varchar(255)),
+            Column::new("age", DataType::Int),
+        ]);
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::unique("email", DataType::Varchar(255)),
-            ]),
-            tuples: vec![
-                vec![
-                    Value::Number(1),
-                    Value::String("Alex".into()),
-                    Value::String("alex@email.com".into()),
-                ],
-                vec![
-                    Value::Number(2),
-                    Value::String("Bob".into()),
-                    Value::String("bob@email.com".into()),
-                ],
-            ]
-        });
+        let row = |id: usize| match id {
+            1 => vec![
+                Value::Number(1),
+                Value::String("John Doe".into()),
+                Value::Number(18),
+            ],
+            2 => vec![
+                Value::Number(2),
+                Value::String("Some Dude".into()),
+                Value::Number(24),
+            ],
+            _ => vec![
+                Value::Number(3),
+                Value::String("John Doe".into()),
+                Value::Number(22),
+            ],
+        };
+
+        // Primary key DESC can't skip the sorter.
+        assert_eq!(
+            db.exec("SELECT * FROM users ORDER BY id DESC;")?,
+            QuerySet {
+                schema: schema.clone(),
+                tuples: vec![row(3), row(2), row(1)],
+            }
+        );
+
+        // Mixed directions.
+        assert_eq!(
+            db.exec("SELECT * FROM users ORDER BY name DESC, age ASC;")?,
+            QuerySet {
+                schema: schema.clone(),
+                tuples: vec![row(2), row(1), row(3)],
+            }
+        );
+
+        // Goes through the TopN plan.
+        assert_eq!(
+            db.exec("SELECT * FROM users ORDER BY age DESC LIMIT 2;")?,
+            QuerySet {
+                schema,
+                tuples: vec![row(2), row(3)],
+            }
+        );
 
         Ok(())
     }
 
     #[test]
-    fn select_where_auto_index_greather_than() -> Result<(), DbError> {
+    fn select_aggregates() -> Result<(), DbError> {
         let mut db = init_database()?;
 
         db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
         db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (4, 'Another Dude', 30);")?;
-
-        let query = db.exec("SELECT * FROM users WHERE id > 2;")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Some Dude', 24);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Jane Doe', 22);")?;
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
-            ]),
-            tuples: vec![
-                vec![
-                    Value::Number(3),
-                    Value::String("Some Dude".into()),
-                    Value::Number(24)
-                ],
-                vec![
-                    Value::Number(4),
-                    Value::String("Another Dude".into()),
-                    Value::Number(30)
-                ],
-            ]
-        });
+        assert_eq!(
+            db.exec(
+                "SELECT COUNT(*), SUM(age), AVG(age), MIN(name), MAX(age) FROM users WHERE id > 1;"
+            )?,
+            QuerySet {
+                schema: Schema::new(vec![
+                    Column::new("COUNT(*)", DataType::BigInt),
+                    Column::new("SUM(age)", DataType::BigInt),
+                    Column::new("AVG(age)", DataType::Double),
+                    Column::new("MIN(name)", DataType::Varchar(255)),
+                    Column::new("MAX(age)", DataType::Int),
+                ]),
+                tuples: vec![vec![
+                    Value::Number(2),
+                    Value::Number(46),
+                    Value::Float(23.0),
+                    Value::String("Jane Doe".into()),
+                    Value::Number(24),
+                ]],
+            }
+        );
 
         Ok(())
     }
 
     #[test]
-    fn select_where_auto_index_greater_than_not_found() -> Result<(), DbError> {
+    fn select_aggregates_from_empty_table() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (10, 'John Doe', 18);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (20, 'Jane Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (30, 'Some Dude', 24);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (40, 'Another Dude', 30);")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, age INT);")?;
 
-        let query = db.exec("SELECT * FROM users WHERE id > 25;")?;
+        assert_eq!(
+            db.exec("SELECT COUNT(*), COUNT(id) + 1 FROM users;")?,
+            QuerySet {
+                schema: Schema::new(vec![
+                    Column::new("COUNT(*)", DataType::BigInt),
+                    Column::new("COUNT(id) + 1", DataType::BigInt),
+                ]),
+                tuples: vec![vec![Value::Number(0), Value::Number(1)]],
+            }
+        );
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
-            ]),
-            tuples: vec![
-                vec![
-                    Value::Number(30),
-                    Value::String("Some Dude".into()),
-                    Value::Number(24)
-                ],
-                vec![
-                    Value::Number(40),
-                    Value::String("Another Dude".into()),
-                    Value::Number(30)
-                ],
-            ]
-        });
+        // These would be NULL.
+        for (sql, func) in [
+            (
+                "SELECT COUNT(*), SUM(age) FROM users;",
+                AggregateFunction::Sum,
+            ),
+            ("SELECT AVG(age) FROM users;", AggregateFunction::Avg),
+            ("SELECT MIN(age) FROM users;", AggregateFunction::Min),
+            ("SELECT MAX(age) FROM users;", AggregateFunction::Max),
+        ] {
+            assert_eq!(
+                db.exec(sql),
+                Err(DbError::Sql(SqlError::VmError(VmError::EmptyAggregate(
+                    func
+                )))),
+                "{sql}"
+            );
+        }
+
+        // Grouping empty tables doesn't produce any group.
+        assert_eq!(
+            db.exec("SELECT age, MAX(id) FROM users GROUP BY age;")?
+                .tuples,
+            Vec::<Vec<Value>>::new()
+        );
+
+        db.exec("INSERT INTO users(id, age) VALUES (1, 20);")?;
+        assert_eq!(
+            db.exec("SELECT MIN(age) FROM users WHERE age > 20;"),
+            Err(DbError::Sql(SqlError::VmError(VmError::EmptyAggregate(
+                AggregateFunction::Min
+            ))))
+        );
 
         Ok(())
     }
 
     #[test]
-    fn select_where_indexed_column_greater_than() -> Result<(), DbError> {
+    fn provably_empty_queries() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
-        )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, age INT);")?;
+        db.exec("INSERT INTO users(id, age) VALUES (1, 20), (2, 30);")?;
 
-        let query = db.exec("SELECT * FROM users WHERE email > 'bob@email.com';")?;
+        assert_eq!(db.exec("SELECT * FROM users WHERE 1 = 1;")?.tuples.len(), 2);
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::unique("email", DataType::Varchar(255)),
-            ]),
-            tuples: vec![vec![
-                Value::Number(3),
-                Value::String("David".into()),
-                Value::String("david@email.com".into()),
-            ],]
+        assert_eq!(
+            db.exec("SELECT COUNT(*) FROM users WHERE 2 > 3;")?.tuples,
+            vec![vec![Value::Number(0)]]
+        );
+
+        assert_eq!(
+            db.exec("SELECT age, id FROM users WHERE id IN () OR (age > 40 AND age < 30);")?,
+            QuerySet {
+                schema: Schema::new(vec![
+                    Column::new("age", DataType::Int),
+                    Column::primary_key("id", DataType::Int),
+                ]),
+                tuples: vec![],
+            }
+        );
+
+        assert_eq!(db.exec("SELECT age FROM users LIMIT 0;")?, QuerySet {
+            schema: Schema::new(vec![Column::new("age", DataType::Int)]),
+            tuples: vec![],
         });
 
+        db.exec("DELETE FROM users WHERE id = 1 AND 1 = 2;")?;
+        db.exec("UPDATE users SET age = 0 WHERE 1 = 2;")?;
+
+        assert_eq!(db.exec("SELECT * FROM users;")?.tuples, vec![
+            vec![Value::Number(1), Value::Number(20)],
+            vec![Value::Number(2), Value::Number(30)],
+        ]);
+
         Ok(())
     }
 
     #[test]
-    fn select_where_indexed_column_greater_than_not_found() -> Result<(), DbError> {
+    fn select_count_distinct() -> Result<(), DbError> {
         let mut db = init_database()?;
 
         db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+            "CREATE TABLE visits (id INT PRIMARY KEY, page VARCHAR(255), email VARCHAR(255));",
+        )?;
+        db.exec(
+            "INSERT INTO visits (id, page, email) VALUES
+                (1, 'home', 'john@email.com'),
+                (2, 'home', 'mary@email.com'),
+                (3, 'home', 'john@email.com'),
+                (4, 'shop', 'john@email.com'),
+                (5, 'shop', 'john@email.com'),
+                (6, 'home', 'bob@email.com');",
         )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
 
-        let query = db.exec("SELECT * FROM users WHERE email > 'carla@email.com';")?;
+        let query = db.exec(
+            "SELECT page, COUNT(DISTINCT email), COUNT(email) FROM visits GROUP BY page ORDER BY page;",
+        )?;
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::unique("email", DataType::Varchar(255)),
-            ]),
-            tuples: vec![vec![
+        assert_eq!(query.schema.columns[1].name, "COUNT(DISTINCT email)");
+        assert_eq!(query.tuples, vec![
+            vec![
+                Value::String("home".into()),
                 Value::Number(3),
-                Value::String("David".into()),
-                Value::String("david@email.com".into()),
-            ]]
-        });
+                Value::Number(4)
+            ],
+            vec![
+                Value::String("shop".into()),
+                Value::Number(1),
+                Value::Number(2)
+            ],
+        ]);
+
+        assert_eq!(
+            db.exec("SELECT COUNT(DISTINCT email), SUM(DISTINCT id / 2) FROM visits;")?
+                .tuples,
+            vec![vec![Value::Number(3), Value::Number(6)]]
+        );
 
         Ok(())
     }
 
     #[test]
-    fn select_where_auto_index_less_than_or_equal() -> Result<(), DbError> {
+    fn select_group_by() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (4, 'Another Dude', 30);")?;
+        db.exec("CREATE TABLE emp (id INT PRIMARY KEY, dept VARCHAR(255), salary INT);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (1, 'sales', 100);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (2, 'eng', 300);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (3, 'sales', 150);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (4, 'hr', 120);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (5, 'eng', 200);")?;
 
-        let query = db.exec("SELECT * FROM users WHERE id <= 3;")?;
+        let schema = Schema::new(vec![
+            Column::new("dept", DataType::Varchar(255)),
+            Column::new("COUNT(*)", DataType::BigInt),
+            Column::new("SUM(salary)", DataType::BigInt),
+        ]);
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
-            ]),
-            tuples: vec![
-                vec![
-                    Value::Number(1),
-                    Value::String("John Doe".into()),
-                    Value::Number(18)
-                ],
-                vec![
-                    Value::Number(2),
-                    Value::String("Jane Doe".into()),
-                    Value::Number(22)
-                ],
-                vec![
-                    Value::Number(3),
-                    Value::String("Some Dude".into()),
-                    Value::Number(24)
-                ],
+        let group = |dept: &str, count, sum| {
+            vec![
+                Value::String(dept.into()),
+                Value::Number(count),
+                Value::Number(sum),
             ]
-        });
+        };
+
+        assert_eq!(
+            db.exec("SELECT dept, COUNT(*), SUM(salary) FROM emp GROUP BY dept ORDER BY dept;")?,
+            QuerySet {
+                schema: schema.clone(),
+                tuples: vec![
+                    group("eng", 2, 500),
+                    group("hr", 1, 120),
+                    group("sales", 2, 250)
+                ],
+            }
+        );
+
+        // LIMIT applies to groups, not to rows.
+        assert_eq!(
+            db.exec(
+                "SELECT dept, COUNT(*), SUM(salary) FROM emp GROUP BY dept ORDER BY dept DESC LIMIT 2;"
+            )?,
+            QuerySet {
+                schema,
+                tuples: vec![group("sales", 2, 250), group("hr", 1, 120)],
+            }
+        );
+
+        assert_eq!(
+            db.exec("SELECT dept FROM emp WHERE salary > 1000 GROUP BY dept;")?,
+            QuerySet {
+                schema: Schema::new(vec![Column::new("dept", DataType::Varchar(255))]),
+                tuples: vec![],
+            }
+        );
 
         Ok(())
     }
 
     #[test]
-    fn select_where_auto_index_less_than_or_equal_not_found() -> Result<(), DbError> {
+    fn select_group_by_order_by_aggregate() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (10, 'John Doe', 18);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (20, 'Jane Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (30, 'Some Dude', 24);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (40, 'Another Dude', 30);")?;
-
-        let query = db.exec("SELECT * FROM users WHERE id <= 25;")?;
+        db.exec("CREATE TABLE emp (id INT PRIMARY KEY, dept VARCHAR(255), salary INT);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (1, 'sales', 100);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (2, 'eng', 300);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (3, 'sales', 150);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (4, 'hr', 120);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (5, 'eng', 200);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (6, 'sales', 90);")?;
+
+        let depts = |depts: &[&str]| {
+            depts
+                .iter()
+                .map(|dept| vec![Value::String(String::from(*dept))])
+                .collect::<Vec<_>>()
+        };
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
-            ]),
-            tuples: vec![
-                vec![
-                    Value::Number(10),
-                    Value::String("John Doe".into()),
-                    Value::Number(18)
-                ],
-                vec![
-                    Value::Number(20),
-                    Value::String("Jane Doe".into()),
-                    Value::Number(22)
-                ],
-            ]
-        });
+        for (sql, expected) in [
+            (
+                "SELECT dept FROM emp GROUP BY dept ORDER BY COUNT(*) DESC, dept;",
+                depts(&["sales", "eng", "hr"]),
+            ),
+            (
+                "SELECT dept FROM emp GROUP BY dept ORDER BY SUM(salary);",
+                depts(&["hr", "sales", "eng"]),
+            ),
+            (
+                "SELECT dept FROM emp GROUP BY dept HAVING COUNT(*) > 1 ORDER BY MAX(salary) LIMIT 1;",
+                depts(&["sales"]),
+            ),
+            (
+                "SELECT dept FROM emp GROUP BY dept ORDER BY MIN(salary) + 1 DESC LIMIT 2 OFFSET 1;",
+                depts(&["hr", "sales"]),
+            ),
+        ] {
+            assert_eq!(db.exec(sql)?.tuples, expected, "{sql}");
+        }
 
         Ok(())
     }
 
     #[test]
-    fn select_where_indexed_column_less_than_or_equal() -> Result<(), DbError> {
+    fn select_join() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
-        )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
+        db.exec("CREATE TABLE orders (id INT PRIMARY KEY, user_id INT, total INT);")?;
+        db.exec("CREATE TABLE notes (content VARCHAR(255), order_id INT);")?;
 
-        let query = db.exec("SELECT * FROM users WHERE email <= 'bob@email.com';")?;
+        db.exec("INSERT INTO users(id, name) VALUES (1, 'John Doe');")?;
+        db.exec("INSERT INTO users(id, name) VALUES (2, 'Jane Doe');")?;
+        db.exec("INSERT INTO users(id, name) VALUES (3, 'Nobody');")?;
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::unique("email", DataType::Varchar(255)),
-            ]),
-            tuples: vec![
-                vec![
-                    Value::Number(1),
-                    Value::String("Alex".into()),
-                    Value::String("alex@email.com".into()),
+        db.exec("INSERT INTO orders(id, user_id, total) VALUES (10, 2, 100);")?;
+        db.exec("INSERT INTO orders(id, user_id, total) VALUES (20, 1, 50);")?;
+        db.exec("INSERT INTO orders(id, user_id, total) VALUES (30, 2, 25);")?;
+
+        db.exec("INSERT INTO notes(content, order_id) VALUES ('gift', 30);")?;
+
+        assert_eq!(
+            db.exec(
+                "SELECT users.name, orders.total FROM users JOIN orders ON users.id = orders.user_id;"
+            )?,
+            QuerySet {
+                schema: Schema::new(vec![
+                    Column::new("users.name", DataType::Varchar(255)),
+                    Column::new("orders.total", DataType::Int),
+                ]),
+                tuples: vec![
+                    vec![Value::String("John Doe".into()), Value::Number(50)],
+                    vec![Value::String("Jane Doe".into()), Value::Number(100)],
+                    vec![Value::String("Jane Doe".into()), Value::Number(25)],
                 ],
-                vec![
+            }
+        );
+
+        assert_eq!(
+            db.exec(
+                "SELECT users.name, SUM(orders.total) FROM users INNER JOIN orders ON users.id = orders.user_id WHERE orders.total > 30 GROUP BY users.name ORDER BY users.name;"
+            )?,
+            QuerySet {
+                schema: Schema::new(vec![
+                    Column::new("users.name", DataType::Varchar(255)),
+                    Column::new("SUM(orders.total)", DataType::BigInt),
+                ]),
+                tuples: vec![
+                    vec![Value::String("Jane Doe".into()), Value::Number(100)],
+                    vec![Value::String("John Doe".into()), Value::Number(50)],
+                ],
+            }
+        );
+
+        // Wildcards don't include the row ID of tables without primary key.
+        assert_eq!(
+            db.exec(
+                "SELECT * FROM orders JOIN notes ON notes.order_id = orders.id JOIN users ON users.id = orders.user_id;"
+            )?,
+            QuerySet {
+                schema: Schema::new(vec![
+                    Column::primary_key("orders.id", DataType::Int),
+                    Column::new("orders.user_id", DataType::Int),
+                    Column::new("orders.total", DataType::Int),
+                    Column::new("notes.content", DataType::Varchar(255)),
+                    Column::new("notes.order_id", DataType::Int),
+                    Column::primary_key("users.id", DataType::Int),
+                    Column::new("users.name", DataType::Varchar(255)),
+                ]),
+                tuples: vec![vec![
+                    Value::Number(30),
                     Value::Number(2),
-                    Value::String("Bob".into()),
-                    Value::String("bob@email.com".into()),
-                ]
-            ]
-        });
+                    Value::Number(25),
+                    Value::String("gift".into()),
+                    Value::Number(30),
+                    Value::Number(2),
+                    Value::String("Jane Doe".into()),
+                ]],
+            }
+        );
 
         Ok(())
     }
 
     #[test]
-    fn select_where_indexed_column_less_than_or_equal_not_found() -> Result<(), DbError> {
+    fn select_qualified_identifiers() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
-        )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
+        db.exec("CREATE TABLE orders (id INT PRIMARY KEY, user_id INT, total INT);")?;
 
-        let query = db.exec("SELECT * FROM users WHERE email <= 'carla@email.com';")?;
+        db.exec("INSERT INTO users(id, name) VALUES (1, 'John Doe');")?;
+        db.exec("INSERT INTO users(id, name) VALUES (2, 'Jane Doe');")?;
+        db.exec("INSERT INTO orders(id, user_id, total) VALUES (10, 2, 100);")?;
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::unique("email", DataType::Varchar(255)),
-            ]),
-            tuples: vec![
-                vec![
-                    Value::Number(1),
-                    Value::String("Alex".into()),
-                    Value::String("alex@email.com".into()),
-                ],
-                vec![
-                    Value::Number(2),
-                    Value::String("Bob".into()),
-                    Value::String("bob@email.com".into()),
-                ]
-            ]
-        });
+        assert_eq!(
+            db.exec("SELECT users.name FROM users WHERE users.id = 2;")?,
+            QuerySet {
+                schema: Schema::new(vec![Column::new("name", DataType::Varchar(255))]),
+                tuples: vec![vec![Value::String("Jane Doe".into())]],
+            }
+        );
+
+        assert_eq!(
+            db.exec("SELECT name, total FROM users JOIN orders ON users.id = user_id;")?,
+            QuerySet {
+                schema: Schema::new(vec![
+                    Column::new("users.name", DataType::Varchar(255)),
+                    Column::new("orders.total", DataType::Int),
+                ]),
+                tuples: vec![vec![Value::String("Jane Doe".into()), Value::Number(100)]],
+            }
+        );
 
         Ok(())
     }
 
     #[test]
-    fn select_where_auto_index_greather_than_or_equal() -> Result<(), DbError> {
+    fn select_aliases() -> Result<(), DbError> {
         let mut db = init_database()?;
 
         db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
         db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
         db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (4, 'Another Dude', 30);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 20);")?;
 
-        let query = db.exec("SELECT * FROM users WHERE id >= 3;")?;
+        let query = db.exec(
+            "SELECT name AS username, age * 2 AS double_age FROM users ORDER BY double_age DESC;",
+        )?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
+                Column::new("username", DataType::Varchar(255)),
+                Column::new("double_age", DataType::BigInt),
             ]),
             tuples: vec![
-                vec![
-                    Value::Number(3),
-                    Value::String("Some Dude".into()),
-                    Value::Number(24)
-                ],
-                vec![
-                    Value::Number(4),
-                    Value::String("Another Dude".into()),
-                    Value::Number(30)
-                ],
+                vec![Value::String("Jane Doe".into()), Value::Number(44)],
+                vec![Value::String("Some Dude".into()), Value::Number(40)],
+                vec![Value::String("John Doe".into()), Value::Number(36)],
             ]
         });
 
@@ -2641,75 +3774,1869 @@ mod tests {
     }
 
     #[test]
-    fn select_where_auto_index_greather_than_or_equal_not_found() -> Result<(), DbError> {
+    fn select_table_aliases() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (10, 'John Doe', 18);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (20, 'Jane Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (30, 'Some Dude', 24);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (40, 'Another Dude', 30);")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
+        db.exec("CREATE TABLE orders (id INT PRIMARY KEY, user_id INT, total INT);")?;
 
-        let query = db.exec("SELECT * FROM users WHERE id >= 15;")?;
+        db.exec("INSERT INTO users(id, name) VALUES (1, 'John Doe');")?;
+        db.exec("INSERT INTO users(id, name) VALUES (2, 'Jane Doe');")?;
+        db.exec("INSERT INTO orders(id, user_id, total) VALUES (10, 2, 100);")?;
+        db.exec("INSERT INTO orders(id, user_id, total) VALUES (20, 1, 50);")?;
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
-            ]),
-            tuples: vec![
-                vec![
-                    Value::Number(20),
-                    Value::String("Jane Doe".into()),
-                    Value::Number(22)
-                ],
-                vec![
-                    Value::Number(30),
-                    Value::String("Some Dude".into()),
-                    Value::Number(24)
-                ],
-                vec![
-                    Value::Number(40),
-                    Value::String("Another Dude".into()),
-                    Value::Number(30)
+        assert_eq!(
+            db.exec("SELECT u.name username FROM users u WHERE u.id = 2;")?,
+            QuerySet {
+                schema: Schema::new(vec![Column::new("username", DataType::Varchar(255))]),
+                tuples: vec![vec![Value::String("Jane Doe".into())]],
+            }
+        );
+
+        assert_eq!(
+            db.exec(
+                "SELECT u.name, o.total FROM users AS u JOIN orders o ON u.id = o.user_id ORDER BY o.total;"
+            )?,
+            QuerySet {
+                schema: Schema::new(vec![
+                    Column::new("users.name", DataType::Varchar(255)),
+                    Column::new("orders.total", DataType::Int),
+                ]),
+                tuples: vec![
+                    vec![Value::String("John Doe".into()), Value::Number(50)],
+                    vec![Value::String("Jane Doe".into()), Value::Number(100)],
                 ],
-            ]
-        });
+            }
+        );
 
         Ok(())
     }
 
     #[test]
-    fn select_where_indexed_column_greater_than_or_equal() -> Result<(), DbError> {
+    fn select_where_like() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
-        )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255) UNIQUE);")?;
+        db.exec("INSERT INTO users(id, name) VALUES (1, 'John');")?;
+        db.exec("INSERT INTO users(id, name) VALUES (2, 'Joan');")?;
+        db.exec("INSERT INTO users(id, name) VALUES (3, 'Jo');")?;
+        db.exec("INSERT INTO users(id, name) VALUES (4, 'Jp');")?;
+        db.exec("INSERT INTO users(id, name) VALUES (5, 'Mary Jo');")?;
 
-        let query = db.exec("SELECT * FROM users WHERE email >= 'bob@email.com';")?;
+        let schema = Schema::new(vec![Column::primary_key("id", DataType::Int)]);
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::unique("email", DataType::Varchar(255)),
-            ]),
-            tuples: vec![
-                vec![
-                    Value::Number(2),
-                    Value::String("Bob".into()),
-                    Value::String("bob@email.com".into()),
-                ],
-                vec![
-                    Value::Number(3),
+        // Uses a range scan on the index.
+        assert_eq!(
+            db.exec("SELECT id FROM users WHERE name LIKE 'Jo%' ORDER BY id;")?,
+            QuerySet {
+                schema: schema.clone(),
+                tuples: vec![vec![Value::Number(1)], vec![Value::Number(2)], vec![
+                    Value::Number(3)
+                ],],
+            }
+        );
+
+        assert_eq!(
+            db.exec("SELECT id FROM users WHERE name LIKE '%Jo' OR name LIKE 'J_a_';")?,
+            QuerySet {
+                schema,
+                tuples: vec![vec![Value::Number(2)], vec![Value::Number(3)], vec![
+                    Value::Number(5)
+                ],],
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_substr_prefix() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255) UNIQUE);")?;
+        db.exec("INSERT INTO users(id, name) VALUES (1, 'John');")?;
+        db.exec("INSERT INTO users(id, name) VALUES (2, 'Joan');")?;
+        db.exec("INSERT INTO users(id, name) VALUES (3, 'Jo');")?;
+        db.exec("INSERT INTO users(id, name) VALUES (4, 'Jp');")?;
+        db.exec("INSERT INTO users(id, name) VALUES (5, 'Mary Jo');")?;
+
+        let ids = |ids: &[i128]| {
+            ids.iter()
+                .map(|id| vec![Value::Number(*id)])
+                .collect::<Vec<_>>()
+        };
+
+        for (sql, expected) in [
+            (
+                "SELECT id FROM users WHERE SUBSTR(name, 1, 2) = 'Jo' ORDER BY id;",
+                ids(&[1, 2, 3]),
+            ),
+            (
+                "SELECT id FROM users WHERE SUBSTR(name, 1, 3) = 'Jo' ORDER BY id;",
+                ids(&[3]),
+            ),
+            (
+                "SELECT id FROM users WHERE SUBSTR(name, 1, 1) = 'Jo' ORDER BY id;",
+                ids(&[]),
+            ),
+            (
+                "SELECT id FROM users WHERE SUBSTR(name, 1, 0) = '' ORDER BY id;",
+                ids(&[1, 2, 3, 4, 5]),
+            ),
+        ] {
+            assert_eq!(db.exec(sql)?.tuples, expected, "{sql}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_in() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255) UNIQUE, age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Mary', 20);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Bob', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (4, 'Alice', 25);")?;
+
+        let ids = |ids: &[i128]| {
+            ids.iter()
+                .map(|id| vec![Value::Number(*id)])
+                .collect::<Vec<_>>()
+        };
+
+        for (sql, expected) in [
+            (
+                "SELECT id FROM users WHERE id IN (4, 2, 2, 9);",
+                ids(&[2, 4]),
+            ),
+            (
+                "SELECT id FROM users WHERE name IN ('Bob', 'John') ORDER BY id;",
+                ids(&[1, 3]),
+            ),
+            (
+                "SELECT id FROM users WHERE age IN (18, 25) AND id NOT IN (1);",
+                ids(&[3, 4]),
+            ),
+            ("SELECT id FROM users WHERE id IN ();", ids(&[])),
+            (
+                "SELECT id FROM users WHERE id NOT IN ();",
+                ids(&[1, 2, 3, 4]),
+            ),
+        ] {
+            assert_eq!(db.exec(sql)?.tuples, expected, "{sql}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_functions() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(10));")?;
+        db.exec("INSERT INTO users(id, name) VALUES (1, ' John '), (2, 'mary');")?;
+
+        let query = db.exec(
+            "SELECT UPPER(TRIM(name)), LENGTH(name), SUBSTR(name, 1, 2) FROM users WHERE LOWER(name) != 'john' ORDER BY id;",
+        )?;
+
+        assert_eq!(
+            query
+                .schema
+                .columns
+                .iter()
+                .map(|column| column.data_type)
+                .collect::<Vec<_>>(),
+            vec![
+                DataType::Varchar(30),
+                DataType::BigInt,
+                DataType::Varchar(10)
+            ]
+        );
+
+        assert_eq!(query.tuples, vec![
+            vec![
+                Value::String("JOHN".into()),
+                Value::Number(6),
+                Value::String(" J".into())
+            ],
+            vec![
+                Value::String("MARY".into()),
+                Value::Number(4),
+                Value::String("ma".into())
+            ],
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_numeric_functions() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE points (id INT PRIMARY KEY, x INT, y DOUBLE);")?;
+        db.exec("INSERT INTO points(id, x, y) VALUES (1, -3, 1.5), (2, 4, -2.0), (3, 5, 0.5);")?;
+
+        let query = db.exec("SELECT ABS(x), MOD(x, 2), POWER(x, 2), POWER(y, 2) FROM points;")?;
+
+        assert_eq!(
+            query
+                .schema
+                .columns
+                .iter()
+                .map(|column| column.data_type)
+                .collect::<Vec<_>>(),
+            vec![
+                DataType::BigInt,
+                DataType::BigInt,
+                DataType::BigInt,
+                DataType::Double
+            ]
+        );
+
+        assert_eq!(query.tuples, vec![
+            vec![
+                Value::Number(3),
+                Value::Number(-1),
+                Value::Number(9),
+                Value::Float(2.25)
+            ],
+            vec![
+                Value::Number(4),
+                Value::Number(0),
+                Value::Number(16),
+                Value::Float(4.0)
+            ],
+            vec![
+                Value::Number(5),
+                Value::Number(1),
+                Value::Number(25),
+                Value::Float(0.25)
+            ],
+        ]);
+
+        for (sql, expected) in [
+            ("SELECT id FROM points WHERE ABS(x) > 3;", vec![2, 3]),
+            ("SELECT id FROM points WHERE MOD(x, 2) = 0;", vec![2]),
+            ("SELECT id FROM points WHERE POWER(x, 2) < 20;", vec![1, 2]),
+            ("SELECT id FROM points WHERE ABS(y) >= 1.5;", vec![1, 2]),
+        ] {
+            let ids = expected
+                .into_iter()
+                .map(|id| vec![Value::Number(id)])
+                .collect::<Vec<_>>();
+            assert_eq!(db.exec(sql)?.tuples, ids, "{sql}");
+        }
+
+        assert_eq!(
+            db.exec("SELECT POWER(x, 100) FROM points;"),
+            Err(VmError::IntegerOverflow(Expression::Function {
+                func: ScalarFunction::Power,
+                args: vec![
+                    Expression::Value(Value::Number(-3)),
+                    Expression::Value(Value::Number(100))
+                ],
+            })
+            .into())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn query_returns_typed_rows() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
+        db.exec("INSERT INTO users (id, name) VALUES (1, 'John'), (2, 'Mary');")?;
+
+        let result = db.query("SELECT name, id FROM users ORDER BY id;")?;
+        assert_eq!(result.schema().index_of("name"), Some(0));
+        assert_eq!(result.schema().index_of("id"), Some(1));
+        assert_eq!(result.schema().columns[0].data_type, DataType::Varchar(255));
+
+        let rows = result.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(rows, vec![
+            vec![Value::String("John".into()), Value::Number(1)],
+            vec![Value::String("Mary".into()), Value::Number(2)],
+        ]);
+
+        assert!(db.query("DELETE FROM users;").is_err());
+        assert_eq!(db.exec("SELECT * FROM users;")?.tuples.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_large_table_lazily() -> Result<(), DbError> {
+        let mut db = init_database_with(DbConf {
+            page_size: 256,
+            cache_size: 16,
+        })?;
+
+        db.exec("CREATE TABLE nums (id INT PRIMARY KEY, name VARCHAR(32));")?;
+        for id in 1..=500 {
+            db.exec(&format!(
+                "INSERT INTO nums (id, name) VALUES ({id}, 'num {id}');"
+            ))?;
+        }
+
+        let mut rows = db.query("SELECT id FROM nums;")?.into_rows();
+        for id in 1..=3 {
+            assert_eq!(rows.next().transpose()?, Some(vec![Value::Number(id)]));
+        }
+        drop(rows);
+
+        // Only pulled what we asked for and didn't leave the transaction open.
+        assert_eq!(db.last_plan_stats.rows_returned, 3);
+        assert!(!db.active_transaction());
+
+        let mut count = 0;
+        for row in db.query("SELECT id FROM nums;")? {
+            count += 1;
+            assert_eq!(row?, vec![Value::Number(count)]);
+        }
+        assert_eq!(count, 500);
+
+        Ok(())
+    }
+
+    #[test]
+    fn exec_script() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        let results = db
+            .exec_script(
+                "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));
+                INSERT INTO users (id, name) VALUES (1, 'John'), (2, 'Mary');
+                SELECT name FROM users ORDER BY id;",
+            )
+            .map_err(|err| *err.error)?;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[2].tuples, vec![
+            vec![Value::String("John".into())],
+            vec![Value::String("Mary".into())],
+        ]);
+
+        let err = db
+            .exec_script("INSERT INTO users (id, name) VALUES (3, 'Bob'); SELECT x FROM users;")
+            .unwrap_err();
+        assert_eq!(err.index, 1);
+        assert!(matches!(
+            *err.error,
+            DbError::Sql(SqlError::AnalyzerError(_))
+        ));
+        assert_eq!(
+            db.exec("SELECT id FROM users WHERE id = 3;")?.tuples.len(),
+            1
+        );
+
+        // Syntax errors don't run anything.
+        let err = db
+            .exec_script("DELETE FROM users; SELECT FROM users;")
+            .unwrap_err();
+        assert_eq!(err.index, 1);
+        assert!(matches!(*err.error, DbError::Parser(_)));
+        assert_eq!(db.exec("SELECT * FROM users;")?.tuples.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sql_limits() -> Result<(), DbError> {
+        let mut db = init_database()?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
+
+        db.sql_limits = Limits {
+            max_input_len: Some(32),
+            max_tokens: None,
+        };
+
+        let sql = "SELECT * FROM users WHERE id = 1 OR id = 2;";
+
+        db.exec("SELECT * FROM users;")?;
+        assert!(matches!(db.exec(sql), Err(DbError::Parser(_))));
+        assert!(matches!(db.prepare(sql), Err(DbError::Parser(_))));
+        assert!(matches!(
+            db.exec_script(sql).map_err(|err| *err.error),
+            Err(DbError::Parser(_))
+        ));
+
+        db.sql_limits = Limits {
+            max_input_len: None,
+            max_tokens: None,
+        };
+
+        assert!(db.exec(sql).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_without_from() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        let query = db.exec("SELECT 1 + 1, UPPER('hi') AS greeting, 2.5;")?;
+
+        assert_eq!(query.schema.columns[1].name, "greeting");
+        assert_eq!(query.tuples, vec![vec![
+            Value::Number(2),
+            Value::String("HI".into()),
+            Value::Float(2.5)
+        ]]);
+
+        let query = db.exec("SELECT 1 WHERE 1 > 2;")?;
+        assert!(query.tuples.is_empty());
+
+        let query = db.exec("SELECT 'a' ORDER BY 1 LIMIT 5;")?;
+        assert_eq!(query.tuples, vec![vec![Value::String("a".into())]]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_case() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John', 17), (2, 'Mary', 18);")?;
+
+        let query = db.exec(
+            "SELECT id, CASE WHEN age >= 18 THEN 'adult' ELSE 'minor' END FROM users ORDER BY id;",
+        )?;
+
+        assert_eq!(query.schema.columns[1].data_type, DataType::Varchar(5));
+        assert_eq!(query.tuples, vec![
+            vec![Value::Number(1), Value::String("minor".into())],
+            vec![Value::Number(2), Value::String("adult".into())],
+        ]);
+
+        let query =
+            db.exec("SELECT name FROM users WHERE CASE WHEN id = 1 THEN age > 20 ELSE TRUE END;")?;
+        assert_eq!(query.tuples, vec![vec![Value::String("Mary".into())]]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_tuple() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec(
+            "INSERT INTO users(id, name, age) VALUES (1, 'John', 18), (2, 'Mary', 18), (3, 'John', 20);",
+        )?;
+
+        let query = db.exec("SELECT id FROM users WHERE (name, age) = ('John', 18);")?;
+        assert_eq!(query.tuples, vec![vec![Value::Number(1)]]);
+
+        let query = db.exec("SELECT id FROM users WHERE (id, name) = (2, 'Mary');")?;
+        assert_eq!(query.tuples, vec![vec![Value::Number(2)]]);
+
+        let query =
+            db.exec("SELECT id FROM users WHERE (name, age) != ('John', 18) ORDER BY id;")?;
+        assert_eq!(query.tuples, vec![vec![Value::Number(2)], vec![
+            Value::Number(3)
+        ]]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_is_null_not_supported() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John', 18), (2, 'Mary', 20);")?;
+
+        assert_eq!(
+            db.exec("SELECT id FROM users WHERE name IS NULL OR id = 2;"),
+            Err(DbError::Sql(SqlError::AnalyzerError(
+                AnalyzerError::NullPredicate(Expression::IsNull {
+                    expr: Box::new(Expression::Identifier("name".into())),
+                    negated: false,
+                })
+            )))
+        );
+
+        assert_eq!(
+            db.exec("SELECT NOT (age IS NOT NULL) FROM users;"),
+            Err(DbError::Sql(SqlError::AnalyzerError(
+                AnalyzerError::NullPredicate(Expression::IsNull {
+                    expr: Box::new(Expression::Identifier("age".into())),
+                    negated: true,
+                })
+            )))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_between() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        for (id, name, age) in [
+            (1, "John", 17),
+            (2, "Mary", 18),
+            (3, "Bob", 40),
+            (4, "Alice", 70),
+        ] {
+            db.exec(&format!(
+                "INSERT INTO users(id, name, age) VALUES ({id}, '{name}', {age});"
+            ))?;
+        }
+
+        let ids = |ids: &[i128]| {
+            ids.iter()
+                .map(|id| vec![Value::Number(*id)])
+                .collect::<Vec<_>>()
+        };
+
+        for (sql, expected) in [
+            (
+                "SELECT id FROM users WHERE age BETWEEN 18 AND 65;",
+                ids(&[2, 3]),
+            ),
+            (
+                "SELECT id FROM users WHERE age NOT BETWEEN 18 AND 65;",
+                ids(&[1, 4]),
+            ),
+            (
+                "SELECT id FROM users WHERE id BETWEEN 2 AND 3 AND age > 20;",
+                ids(&[3]),
+            ),
+            (
+                "SELECT id FROM users WHERE id NOT BETWEEN 2 AND 3;",
+                ids(&[1, 4]),
+            ),
+            (
+                "SELECT id FROM users WHERE name BETWEEN 'B' AND 'K';",
+                ids(&[1, 3]),
+            ),
+            ("SELECT id FROM users WHERE id BETWEEN 3 AND 2;", ids(&[])),
+        ] {
+            assert_eq!(db.exec(sql)?.tuples, expected, "{sql}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_not() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), is_admin BOOL);")?;
+        db.exec("INSERT INTO users(id, name, is_admin) VALUES (1, 'John', TRUE);")?;
+        db.exec("INSERT INTO users(id, name, is_admin) VALUES (2, 'Mary', FALSE);")?;
+        db.exec("INSERT INTO users(id, name, is_admin) VALUES (3, 'Bob', FALSE);")?;
+
+        let ids = |ids: &[i128]| {
+            ids.iter()
+                .map(|id| vec![Value::Number(*id)])
+                .collect::<Vec<_>>()
+        };
+
+        for (sql, expected) in [
+            ("SELECT id FROM users WHERE NOT is_admin;", ids(&[2, 3])),
+            (
+                "SELECT id FROM users WHERE NOT (id = 2 OR is_admin);",
+                ids(&[3]),
+            ),
+            ("SELECT id FROM users WHERE NOT id > 1;", ids(&[1])),
+            (
+                "SELECT id FROM users WHERE NOT name LIKE 'M%' AND NOT NOT is_admin;",
+                ids(&[1]),
+            ),
+        ] {
+            assert_eq!(db.exec(sql)?.tuples, expected, "{sql}");
+        }
+
+        assert_eq!(
+            db.exec("SELECT NOT is_admin FROM users WHERE id = 1;")?,
+            QuerySet {
+                schema: Schema::new(vec![Column::new("NOT is_admin", DataType::Bool)]),
+                tuples: vec![vec![Value::Bool(false)]],
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_default_values() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (name VARCHAR(255) DEFAULT 'Anonymous', active BOOL DEFAULT TRUE, score INT DEFAULT 2 * 5);")?;
+        db.exec("INSERT INTO users (name) VALUES ('John Doe');")?;
+        db.exec("INSERT INTO users () VALUES ();")?;
+        db.exec("INSERT INTO users (score, active) VALUES (1, FALSE);")?;
+
+        assert_eq!(db.exec("SELECT * FROM users;")?.tuples, vec![
+            vec![
+                Value::String("John Doe".into()),
+                Value::Bool(true),
+                Value::Number(10)
+            ],
+            vec![
+                Value::String("Anonymous".into()),
+                Value::Bool(true),
+                Value::Number(10)
+            ],
+            vec![
+                Value::String("Anonymous".into()),
+                Value::Bool(false),
+                Value::Number(1)
+            ],
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_having() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE emp (id INT PRIMARY KEY, dept VARCHAR(255), salary INT);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (1, 'sales', 100);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (2, 'eng', 300);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (3, 'sales', 150);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (4, 'hr', 120);")?;
+        db.exec("INSERT INTO emp(id, dept, salary) VALUES (5, 'eng', 200);")?;
+
+        assert_eq!(
+            db.exec(
+                "SELECT dept, SUM(salary) FROM emp GROUP BY dept HAVING COUNT(*) > 1 ORDER BY dept;"
+            )?,
+            QuerySet {
+                schema: Schema::new(vec![
+                    Column::new("dept", DataType::Varchar(255)),
+                    Column::new("SUM(salary)", DataType::BigInt),
+                ]),
+                tuples: vec![
+                    vec![Value::String("eng".into()), Value::Number(500)],
+                    vec![Value::String("sales".into()), Value::Number(250)],
+                ],
+            }
+        );
+
+        assert_eq!(
+            db.exec("SELECT COUNT(*) FROM emp HAVING MAX(salary) > 1000;")?,
+            QuerySet {
+                schema: Schema::new(vec![Column::new("COUNT(*)", DataType::BigInt)]),
+                tuples: vec![],
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_distinct() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE emp (id INT PRIMARY KEY, dept VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO emp(id, dept, age) VALUES (1, 'sales', 30);")?;
+        db.exec("INSERT INTO emp(id, dept, age) VALUES (2, 'eng', 25);")?;
+        db.exec("INSERT INTO emp(id, dept, age) VALUES (3, 'sales', 30);")?;
+        db.exec("INSERT INTO emp(id, dept, age) VALUES (4, 'hr', 40);")?;
+        db.exec("INSERT INTO emp(id, dept, age) VALUES (5, 'eng', 25);")?;
+        db.exec("INSERT INTO emp(id, dept, age) VALUES (6, 'sales', 35);")?;
+
+        assert_eq!(db.exec("SELECT DISTINCT dept FROM emp;")?, QuerySet {
+            schema: Schema::new(vec![Column::new("dept", DataType::Varchar(255))]),
+            tuples: vec![
+                vec![Value::String("eng".into())],
+                vec![Value::String("hr".into())],
+                vec![Value::String("sales".into())],
+            ],
+        });
+
+        assert_eq!(
+            db.exec("SELECT DISTINCT dept, age FROM emp ORDER BY age DESC LIMIT 3;")?,
+            QuerySet {
+                schema: Schema::new(vec![
+                    Column::new("dept", DataType::Varchar(255)),
+                    Column::new("age", DataType::Int),
+                ]),
+                tuples: vec![
+                    vec![Value::String("hr".into()), Value::Number(40)],
+                    vec![Value::String("sales".into()), Value::Number(35)],
+                    vec![Value::String("sales".into()), Value::Number(30)],
+                ],
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_order_by_with_deterministic_order() -> Result<(), DbError> {
+        let mut db = init_database()?;
+        db.deterministic_order = true;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255));")?;
+        db.exec("CREATE UNIQUE INDEX email_uq ON users(email);")?;
+
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'John', 'd@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Jane', 'c@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'John', 'b@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (4, 'John', 'a@email.com');")?;
+
+        // The index scan returns the rows sorted by email, the ties between
+        // names should still be sorted by primary key.
+        let query = db.exec("SELECT id, name FROM users WHERE email < 'z' ORDER BY name;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+            ]),
+            tuples: vec![
+                vec![Value::Number(2), Value::String("Jane".into())],
+                vec![Value::Number(1), Value::String("John".into())],
+                vec![Value::Number(3), Value::String("John".into())],
+                vec![Value::Number(4), Value::String("John".into())],
+            ]
+        });
+
+        Ok(())
+    }
+
+    // Force the external merge sort algorithm to do some real work.
+    #[cfg(not(miri))]
+    #[test]
+    fn select_order_by_many() -> Result<(), DbError> {
+        let mut db = init_database_with(DbConf {
+            page_size: 96,
+            cache_size: 1024,
+        })?;
+
+        db.exec("CREATE TABLE users (name VARCHAR(255));")?;
+
+        let mut expected = Vec::new();
+
+        for i in 1..=500 {
+            let name = format!("User{i:03}");
+            expected.push(vec![Value::String(name)]);
+        }
+
+        for user in expected.iter().rev() {
+            db.exec(&format!("INSERT INTO users(name) VALUES ({});", user[0]))?;
+        }
+
+        let query = db.exec("SELECT * FROM users ORDER BY name;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![Column::new("name", DataType::Varchar(255)),]),
+            tuples: expected,
+        });
+
+        Ok(())
+    }
+
+    // The default page size won't be enough to store these tuples.
+    #[test]
+    fn select_order_by_large_tuples() -> Result<(), DbError> {
+        let mut db = init_database_with(DbConf {
+            page_size: 96,
+            cache_size: 1024,
+        })?;
+
+        db.exec("CREATE TABLE users (id BIGINT, name VARCHAR(255), email VARCHAR(255), age INT);")?;
+
+        let mut expected = Vec::new();
+
+        for i in 1..=10 {
+            let name = format!("Very Long User{i} Name").repeat(10);
+            let mut email = format!("very_long_user_email{i}").repeat(10);
+            email.push_str("@email.com");
+            expected.push(vec![
+                Value::Number(i),
+                Value::String(name),
+                Value::String(email),
+                Value::Number(i + 20),
+            ]);
+        }
+
+        for user in expected.iter().rev() {
+            db.exec(&format!(
+                "INSERT INTO users(id, name, email, age) VALUES ({});",
+                user.iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ))?;
+        }
+
+        expected.sort_by(|user1, user2| {
+            user1[1]
+                .partial_cmp(&user2[1])
+                .or(user1[2].partial_cmp(&user2[2]).or_else(|| {
+                    let (Value::Number(age1), Value::Number(age2)) = (&user1[3], &user2[3]) else {
+                        unreachable!();
+                    };
+
+                    (age1 + 10).partial_cmp(&(age2 + 10))
+                }))
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let query = db.exec("SELECT * FROM users ORDER BY name, email, age + 10;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::new("id", DataType::BigInt),
+                Column::new("name", DataType::Varchar(255)),
+                Column::new("email", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
+            ],),
+            tuples: expected,
+        });
+
+        Ok(())
+    }
+
+    // Test for tuples of different sizes.
+    #[test]
+    fn select_order_by_var_length() -> Result<(), DbError> {
+        let mut db = init_database_with(DbConf {
+            page_size: 96,
+            cache_size: 1024,
+        })?;
+
+        db.exec("CREATE TABLE var_len (name VARCHAR(255));")?;
+
+        // Purposefully crafted to cause issues when sorting since the var
+        // length data will not produce the same amount of pages on each run.
+        let mut var_length_records = vec![
+            format!("04 {}", "var_length_tuple_".repeat(5)),
+            format!("09 {}", "var_length_tuple_".repeat(2)),
+            format!("06 {}", "var_length_tuple_".repeat(7)),
+            format!("00 {}", "var_length_tuple_".repeat(1)),
+            format!("08 {}", "var_length_tuple_".repeat(1)),
+            format!("03 {}", "var_length_tuple_".repeat(4)),
+            format!("01 {}", "var_length_tuple_".repeat(2)),
+            format!("02 {}", "var_length_tuple_".repeat(3)),
+            format!("05 {}", "var_length_tuple_".repeat(6)),
+            format!("07 {}", "var_length_tuple_".repeat(8)),
+        ];
+
+        for record in var_length_records.iter() {
+            db.exec(&format!("INSERT INTO var_len VALUES ('{}');", record))?;
+        }
+
+        var_length_records.sort();
+
+        let query = db.exec("SELECT name FROM var_len ORDER BY name;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![Column::new("name", DataType::Varchar(255))]),
+            tuples: var_length_records
+                .into_iter()
+                .map(|record| vec![Value::String(record)])
+                .collect(),
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_order_by_expressions() -> Result<(), DbError> {
+        let mut db = init_database_with(DbConf {
+            page_size: 96,
+            cache_size: 1024,
+        })?;
+
+        db.exec("CREATE TABLE products (id INT, name VARCHAR(255), price INT, discount INT);")?;
+
+        let mut products = vec![
+            (1, "mouse", 20, 10),
+            (2, "mouse", 20, 20),
+            (3, "keyboard", 40, 25),
+            (4, "keyboard", 20, 50),
+            (5, "speakers", 40, 10),
+        ];
+
+        for (id, name, price, discount) in &products {
+            db.exec(&format!("INSERT INTO products(id, name, price, discount) VALUES ({id}, '{name}', {price}, {discount});"))?;
+        }
+
+        products.sort_by(
+            |(_, name1, price1, discount1), (_, name2, price2, discount2)| {
+                let mut ordering = name1.cmp(name2);
+
+                if ordering == Ordering::Equal {
+                    ordering = (price1 - price1 * discount1 / 100)
+                        .cmp(&(price2 - price2 * discount2 / 100));
+                }
+
+                if ordering == Ordering::Equal {
+                    ordering = price1.cmp(price2);
+                }
+
+                ordering
+            },
+        );
+
+        let query = db
+            .exec("SELECT * FROM products ORDER BY name, price - price * discount / 100, price;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::new("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::new("price", DataType::Int),
+                Column::new("discount", DataType::Int),
+            ]),
+            tuples: products
+                .into_iter()
+                .map(|(id, name, price, discount)| vec![
+                    Value::Number(id),
+                    Value::String(name.into()),
+                    Value::Number(price),
+                    Value::Number(discount)
+                ])
+                .collect(),
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_order_by_expression_not_in_projection() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 20);")?;
+
+        let query = db.exec("SELECT name FROM users ORDER BY age + 1;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![Column::new("name", DataType::Varchar(255))]),
+            tuples: vec![
+                vec![Value::String("John Doe".into())],
+                vec![Value::String("Some Dude".into())],
+                vec![Value::String("Jane Doe".into())],
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_auto_index_exact() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
+
+        let query = db.exec("SELECT * FROM users WHERE id = 2;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
+            ]),
+            tuples: vec![vec![
+                Value::Number(2),
+                Value::String("Jane Doe".into()),
+                Value::Number(22)
+            ],]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_indexed_column_exact() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec(
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+        )?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+
+        let query = db.exec("SELECT * FROM users WHERE email = 'bob@email.com';")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::unique("email", DataType::Varchar(255)),
+            ]),
+            tuples: vec![vec![
+                Value::Number(2),
+                Value::String("Bob".into()),
+                Value::String("bob@email.com".into()),
+            ],]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_auto_index_exact_not_found() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (10, 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (20, 'Jane Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (30, 'Some Dude', 24);")?;
+
+        let query = db.exec("SELECT * FROM users WHERE id = 15;")?;
+
+        assert!(query.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_indexed_column_exact_not_found() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec(
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+        )?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+
+        let query = db.exec("SELECT * FROM users WHERE email = 'carla@email.com';")?;
+
+        assert!(query.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_auto_index_less_than() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (4, 'Another Dude', 30);")?;
+
+        let query = db.exec("SELECT * FROM users WHERE id < 3;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(1),
+                    Value::String("John Doe".into()),
+                    Value::Number(18)
+                ],
+                vec![
+                    Value::Number(2),
+                    Value::String("Jane Doe".into()),
+                    Value::Number(22)
+                ],
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_indexed_column_less_than() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec(
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+        )?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+
+        let query = db.exec("SELECT * FROM users WHERE email < 'david@email.com';")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::unique("email", DataType::Varchar(255)),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(1),
+                    Value::String("Alex".into()),
+                    Value::String("alex@email.com".into()),
+                ],
+                vec![
+                    Value::Number(2),
+                    Value::String("Bob".into()),
+                    Value::String("bob@email.com".into()),
+                ],
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_auto_index_less_than_not_found() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (10, 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (20, 'Jane Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (30, 'Some Dude', 24);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (40, 'Another Dude', 30);")?;
+
+        let query = db.exec("SELECT * FROM users WHERE id < 35;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(10),
+                    Value::String("John Doe".into()),
+                    Value::Number(18)
+                ],
+                vec![
+                    Value::Number(20),
+                    Value::String("Jane Doe".into()),
+                    Value::Number(22)
+                ],
+                vec![
+                    Value::Number(30),
+                    Value::String("Some Dude".into()),
+                    Value::Number(24)
+                ],
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_indexed_column_less_than_not_found() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec(
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+        )?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+
+        let query = db.exec("SELECT * FROM users WHERE email < 'carla@email.com';")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::unique("email", DataType::Varchar(255)),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(1),
+                    Value::String("Alex".into()),
+                    Value::String("alex@email.com".into()),
+                ],
+                vec![
+                    Value::Number(2),
+                    Value::String("Bob".into()),
+                    Value::String("bob@email.com".into()),
+                ],
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_auto_index_greather_than() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (4, 'Another Dude', 30);")?;
+
+        let query = db.exec("SELECT * FROM users WHERE id > 2;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(3),
+                    Value::String("Some Dude".into()),
+                    Value::Number(24)
+                ],
+                vec![
+                    Value::Number(4),
+                    Value::String("Another Dude".into()),
+                    Value::Number(30)
+                ],
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_auto_index_greater_than_not_found() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (10, 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (20, 'Jane Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (30, 'Some Dude', 24);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (40, 'Another Dude', 30);")?;
+
+        let query = db.exec("SELECT * FROM users WHERE id > 25;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(30),
+                    Value::String("Some Dude".into()),
+                    Value::Number(24)
+                ],
+                vec![
+                    Value::Number(40),
+                    Value::String("Another Dude".into()),
+                    Value::Number(30)
+                ],
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_indexed_column_greater_than() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec(
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+        )?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+
+        let query = db.exec("SELECT * FROM users WHERE email > 'bob@email.com';")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::unique("email", DataType::Varchar(255)),
+            ]),
+            tuples: vec![vec![
+                Value::Number(3),
+                Value::String("David".into()),
+                Value::String("david@email.com".into()),
+            ],]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_indexed_column_greater_than_not_found() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec(
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+        )?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+
+        let query = db.exec("SELECT * FROM users WHERE email > 'carla@email.com';")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::unique("email", DataType::Varchar(255)),
+            ]),
+            tuples: vec![vec![
+                Value::Number(3),
+                Value::String("David".into()),
+                Value::String("david@email.com".into()),
+            ]]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_auto_index_less_than_or_equal() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (4, 'Another Dude', 30);")?;
+
+        let query = db.exec("SELECT * FROM users WHERE id <= 3;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(1),
+                    Value::String("John Doe".into()),
+                    Value::Number(18)
+                ],
+                vec![
+                    Value::Number(2),
+                    Value::String("Jane Doe".into()),
+                    Value::Number(22)
+                ],
+                vec![
+                    Value::Number(3),
+                    Value::String("Some Dude".into()),
+                    Value::Number(24)
+                ],
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_auto_index_less_than_or_equal_not_found() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (10, 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (20, 'Jane Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (30, 'Some Dude', 24);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (40, 'Another Dude', 30);")?;
+
+        let query = db.exec("SELECT * FROM users WHERE id <= 25;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(10),
+                    Value::String("John Doe".into()),
+                    Value::Number(18)
+                ],
+                vec![
+                    Value::Number(20),
+                    Value::String("Jane Doe".into()),
+                    Value::Number(22)
+                ],
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_indexed_column_less_than_or_equal() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec(
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+        )?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+
+        let query = db.exec("SELECT * FROM users WHERE email <= 'bob@email.com';")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::unique("email", DataType::Varchar(255)),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(1),
+                    Value::String("Alex".into()),
+                    Value::String("alex@email.com".into()),
+                ],
+                vec![
+                    Value::Number(2),
+                    Value::String("Bob".into()),
+                    Value::String("bob@email.com".into()),
+                ]
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_indexed_column_less_than_or_equal_not_found() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec(
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+        )?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+
+        let query = db.exec("SELECT * FROM users WHERE email <= 'carla@email.com';")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::unique("email", DataType::Varchar(255)),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(1),
+                    Value::String("Alex".into()),
+                    Value::String("alex@email.com".into()),
+                ],
+                vec![
+                    Value::Number(2),
+                    Value::String("Bob".into()),
+                    Value::String("bob@email.com".into()),
+                ]
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_auto_index_greather_than_or_equal() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (4, 'Another Dude', 30);")?;
+
+        let query = db.exec("SELECT * FROM users WHERE id >= 3;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(3),
+                    Value::String("Some Dude".into()),
+                    Value::Number(24)
+                ],
+                vec![
+                    Value::Number(4),
+                    Value::String("Another Dude".into()),
+                    Value::Number(30)
+                ],
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_auto_index_greather_than_or_equal_not_found() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (10, 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (20, 'Jane Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (30, 'Some Dude', 24);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (40, 'Another Dude', 30);")?;
+
+        let query = db.exec("SELECT * FROM users WHERE id >= 15;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(20),
+                    Value::String("Jane Doe".into()),
+                    Value::Number(22)
+                ],
+                vec![
+                    Value::Number(30),
+                    Value::String("Some Dude".into()),
+                    Value::Number(24)
+                ],
+                vec![
+                    Value::Number(40),
+                    Value::String("Another Dude".into()),
+                    Value::Number(30)
+                ],
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_indexed_column_greater_than_or_equal() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec(
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+        )?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+
+        let query = db.exec("SELECT * FROM users WHERE email >= 'bob@email.com';")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::unique("email", DataType::Varchar(255)),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(2),
+                    Value::String("Bob".into()),
+                    Value::String("bob@email.com".into()),
+                ],
+                vec![
+                    Value::Number(3),
+                    Value::String("David".into()),
+                    Value::String("david@email.com".into()),
+                ],
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_indexed_column_greater_than_or_equal_not_found() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec(
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+        )?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (4, 'John', 'john@email.com');")?;
+
+        let query = db.exec("SELECT * FROM users WHERE email >= 'carla@email.com';")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::unique("email", DataType::Varchar(255)),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(3),
+                    Value::String("David".into()),
+                    Value::String("david@email.com".into()),
+                ],
+                vec![
+                    Value::Number(4),
+                    Value::String("John".into()),
+                    Value::String("john@email.com".into()),
+                ],
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_multiple_key_ranges() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec(
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+        )?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (4, 'Mark', 'mark@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (5, 'Carla', 'carla@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (6, 'Mia', 'mia@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (7, 'Jay', 'jay@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (8, 'Lauren', 'lauren@email.com');")?;
+
+        let query = db.exec(
+            "SELECT * FROM users WHERE (id >= 2 AND id <= 3) OR (id > 4 AND id < 7) OR id >= 8;",
+        )?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::unique("email", DataType::Varchar(255)),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(2),
+                    Value::String("Bob".into()),
+                    Value::String("bob@email.com".into()),
+                ],
+                vec![
+                    Value::Number(3),
+                    Value::String("David".into()),
+                    Value::String("david@email.com".into()),
+                ],
+                vec![
+                    Value::Number(5),
+                    Value::String("Carla".into()),
+                    Value::String("carla@email.com".into()),
+                ],
+                vec![
+                    Value::Number(6),
+                    Value::String("Mia".into()),
+                    Value::String("mia@email.com".into()),
+                ],
+                vec![
+                    Value::Number(8),
+                    Value::String("Lauren".into()),
+                    Value::String("lauren@email.com".into()),
+                ],
+            ]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_negative_key_range() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE t (id INT PRIMARY KEY, score BIGINT UNIQUE);")?;
+        for id in [250, -2, 1, -300, 0, -1, 7] {
+            db.exec(&format!(
+                "INSERT INTO t(id, score) VALUES ({id}, {});",
+                id * 1000
+            ))?;
+        }
+
+        let ids = |ids: &[i128]| {
+            ids.iter()
+                .map(|id| vec![Value::Number(*id)])
+                .collect::<Vec<_>>()
+        };
+
+        for (sql, expected) in [
+            ("SELECT id FROM t;", ids(&[-300, -2, -1, 0, 1, 7, 250])),
+            (
+                "SELECT id FROM t WHERE id > -3 AND id < 2;",
+                ids(&[-2, -1, 0, 1]),
+            ),
+            ("SELECT id FROM t WHERE id <= -1;", ids(&[-300, -2, -1])),
+            (
+                "SELECT id FROM t WHERE score BETWEEN -5000 AND 5000;",
+                ids(&[-2, -1, 0, 1]),
+            ),
+            (
+                "SELECT score FROM t WHERE score >= -2000 ORDER BY score;",
+                ids(&[-2000, -1000, 0, 1000, 7000, 250000]),
+            ),
+        ] {
+            assert_eq!(db.exec(sql)?.tuples, expected, "{sql}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_float_bounds_on_integer_index() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE t (id INT PRIMARY KEY, age INT UNIQUE);")?;
+        for id in [-3, 0, 1, 2, 3, 25, 26, 30, 31] {
+            db.exec(&format!("INSERT INTO t(id, age) VALUES ({id}, {id});"))?;
+        }
+
+        let ids = |ids: &[i128]| {
+            ids.iter()
+                .map(|id| vec![Value::Number(*id)])
+                .collect::<Vec<_>>()
+        };
+
+        for col in ["id", "age"] {
+            for (filter, expected) in [
+                (format!("{col} = 1.5"), ids(&[])),
+                (format!("{col} = 2.0"), ids(&[2])),
+                (format!("{col} > 1.5"), ids(&[2, 3, 25, 26, 30, 31])),
+                (format!("{col} >= 25.5"), ids(&[26, 30, 31])),
+                (format!("{col} < 1.5"), ids(&[-3, 0, 1])),
+                (format!("{col} <= -2.5"), ids(&[-3])),
+                (format!("2.5 > {col}"), ids(&[-3, 0, 1, 2])),
+                (format!("{col} IN (1.5, 2)"), ids(&[2])),
+                (format!("{col} BETWEEN 25.5 AND 30"), ids(&[26, 30])),
+                (format!("{col} = 2 OR {col} < 1.5"), ids(&[-3, 0, 1, 2])),
+            ] {
+                let sql = format!("SELECT id FROM t WHERE {filter} ORDER BY id;");
+                assert_eq!(db.exec(&sql)?.tuples, expected, "{sql}");
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn negative_zero_float_key_is_duplicated() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE t (id DOUBLE PRIMARY KEY, score DOUBLE UNIQUE);")?;
+        db.exec("INSERT INTO t(id, score) VALUES (0.0, 1.0);")?;
+        db.exec("INSERT INTO t(id, score) VALUES (1.0, 0.0);")?;
+
+        assert_eq!(
+            db.exec("INSERT INTO t(id, score) VALUES (-0.0, 2.0);"),
+            Err(DbError::Sql(SqlError::DuplicatedKey {
+                table: "t".into(),
+                column: "id".into(),
+                value: Value::Float(-0.0),
+            }))
+        );
+
+        assert_eq!(
+            db.exec("INSERT INTO t(id, score) VALUES (2.0, -0.0);"),
+            Err(DbError::Sql(SqlError::DuplicatedKey {
+                table: "t".into(),
+                column: "score".into(),
+                value: Value::Float(-0.0),
+            }))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_ranges_cancel() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec(
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+        )?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (4, 'Mark', 'mark@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (5, 'Carla', 'carla@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (6, 'Mia', 'mia@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (7, 'Jay', 'jay@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (8, 'Lauren', 'lauren@email.com');")?;
+
+        let query = db.exec(
+            "SELECT * FROM users WHERE (id >= 2 AND id <= 3) AND (id > 4 AND id < 7) AND id >= 8;",
+        )?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::unique("email", DataType::Varchar(255)),
+            ]),
+            tuples: vec![]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_multiple_indexes_exact_match_and_ranges() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec(
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255) UNIQUE, email VARCHAR(255) UNIQUE);",
+        )?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (4, 'Mark', 'mark@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (5, 'Carla', 'carla@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (6, 'Mia', 'mia@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (7, 'Jay', 'jay@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (8, 'Lauren', 'lauren@email.com');")?;
+
+        let query = db.exec(
+            "SELECT * FROM users WHERE id < 4 OR email = 'mia@email.com' OR name = 'Lauren';",
+        )?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::unique("name", DataType::Varchar(255)),
+                Column::unique("email", DataType::Varchar(255)),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(1),
+                    Value::String("Alex".into()),
+                    Value::String("alex@email.com".into()),
+                ],
+                vec![
+                    Value::Number(2),
+                    Value::String("Bob".into()),
+                    Value::String("bob@email.com".into()),
+                ],
+                vec![
+                    Value::Number(3),
                     Value::String("David".into()),
                     Value::String("david@email.com".into()),
                 ],
+                vec![
+                    Value::Number(6),
+                    Value::String("Mia".into()),
+                    Value::String("mia@email.com".into()),
+                ],
+                vec![
+                    Value::Number(8),
+                    Value::String("Lauren".into()),
+                    Value::String("lauren@email.com".into()),
+                ],
             ]
         });
 
@@ -2717,18 +5644,22 @@ mod tests {
     }
 
     #[test]
-    fn select_where_indexed_column_greater_than_or_equal_not_found() -> Result<(), DbError> {
+    fn select_where_multiple_indexes_ranges_only() -> Result<(), DbError> {
         let mut db = init_database()?;
 
         db.exec(
             "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
         )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (4, 'John', 'john@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', '1@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', '2@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', '3@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (4, 'Mark', '4@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (5, 'Carla', '5@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (6, 'Mia', '6@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (7, 'Jay', '7@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (8, 'Lauren', '8@email.com');")?;
 
-        let query = db.exec("SELECT * FROM users WHERE email >= 'carla@email.com';")?;
+        let query = db.exec("SELECT * FROM users WHERE id >= 7 OR email < '3@email.com';")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
@@ -2738,14 +5669,24 @@ mod tests {
             ]),
             tuples: vec![
                 vec![
-                    Value::Number(3),
-                    Value::String("David".into()),
-                    Value::String("david@email.com".into()),
+                    Value::Number(1),
+                    Value::String("Alex".into()),
+                    Value::String("1@email.com".into()),
                 ],
                 vec![
-                    Value::Number(4),
-                    Value::String("John".into()),
-                    Value::String("john@email.com".into()),
+                    Value::Number(2),
+                    Value::String("Bob".into()),
+                    Value::String("2@email.com".into()),
+                ],
+                vec![
+                    Value::Number(7),
+                    Value::String("Jay".into()),
+                    Value::String("7@email.com".into()),
+                ],
+                vec![
+                    Value::Number(8),
+                    Value::String("Lauren".into()),
+                    Value::String("8@email.com".into()),
                 ],
             ]
         });
@@ -2754,24 +5695,86 @@ mod tests {
     }
 
     #[test]
-    fn select_where_multiple_key_ranges() -> Result<(), DbError> {
+    fn select_where_multiple_indexes_intersection() -> Result<(), DbError> {
         let mut db = init_database()?;
 
         db.exec(
             "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
         )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (4, 'Mark', 'mark@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (5, 'Carla', 'carla@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (6, 'Mia', 'mia@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (7, 'Jay', 'jay@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (8, 'Lauren', 'lauren@email.com');")?;
 
-        let query = db.exec(
-            "SELECT * FROM users WHERE (id >= 2 AND id <= 3) OR (id > 4 AND id < 7) OR id >= 8;",
+        // Emails go in the opposite order of IDs so that the index returns
+        // keys in descending order.
+        for id in 1..=8 {
+            db.exec(&format!(
+                "INSERT INTO users(id, name, email) VALUES ({id}, 'User {id}', '{}@email.com');",
+                9 - id
+            ))?;
+        }
+
+        let user = |id: i128| {
+            vec![
+                Value::Number(id),
+                Value::String(format!("User {id}")),
+                Value::String(format!("{}@email.com", 9 - id)),
+            ]
+        };
+
+        let schema = Schema::new(vec![
+            Column::primary_key("id", DataType::Int),
+            Column::new("name", DataType::Varchar(255)),
+            Column::unique("email", DataType::Varchar(255)),
+        ]);
+
+        let query = db.exec("SELECT * FROM users WHERE id < 7 AND email < '6@email.com';")?;
+
+        assert_eq!(query, QuerySet {
+            schema: schema.clone(),
+            tuples: vec![user(4), user(5), user(6)],
+        });
+
+        let query =
+            db.exec("SELECT * FROM users WHERE (id < 3 OR id > 6) AND email > '2@email.com';")?;
+
+        assert_eq!(query, QuerySet {
+            schema: schema.clone(),
+            tuples: vec![user(1), user(2)],
+        });
+
+        let query = db.exec("SELECT * FROM users WHERE id > 6 AND email > '5@email.com';")?;
+
+        assert_eq!(query, QuerySet {
+            schema,
+            tuples: vec![],
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_where_multiple_indexes_multiple_ranges() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec(
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
         )?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', '1@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', '2@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', '3@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (4, 'Mark', '4@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (5, 'Carla', '5@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (6, 'Mia', '6@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (7, 'Jay', '7@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (8, 'Lauren', '8@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (9, 'Jack', '9@email.com');")?;
+
+        #[rustfmt::skip]
+        let query = db.exec("
+            SELECT * FROM users
+            WHERE (id >= 4 AND id < 6)
+            OR email < '3@email.com'
+            OR (id > 6 AND id < 9)
+            OR email = '9@email.com';
+        ")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
@@ -2780,30 +5783,40 @@ mod tests {
                 Column::unique("email", DataType::Varchar(255)),
             ]),
             tuples: vec![
+                vec![
+                    Value::Number(1),
+                    Value::String("Alex".into()),
+                    Value::String("1@email.com".into()),
+                ],
                 vec![
                     Value::Number(2),
                     Value::String("Bob".into()),
-                    Value::String("bob@email.com".into()),
+                    Value::String("2@email.com".into()),
                 ],
                 vec![
-                    Value::Number(3),
-                    Value::String("David".into()),
-                    Value::String("david@email.com".into()),
+                    Value::Number(4),
+                    Value::String("Mark".into()),
+                    Value::String("4@email.com".into()),
                 ],
                 vec![
                     Value::Number(5),
                     Value::String("Carla".into()),
-                    Value::String("carla@email.com".into()),
+                    Value::String("5@email.com".into()),
                 ],
                 vec![
-                    Value::Number(6),
-                    Value::String("Mia".into()),
-                    Value::String("mia@email.com".into()),
+                    Value::Number(7),
+                    Value::String("Jay".into()),
+                    Value::String("7@email.com".into()),
                 ],
                 vec![
                     Value::Number(8),
                     Value::String("Lauren".into()),
-                    Value::String("lauren@email.com".into()),
+                    Value::String("8@email.com".into()),
+                ],
+                vec![
+                    Value::Number(9),
+                    Value::String("Jack".into()),
+                    Value::String("9@email.com".into()),
                 ],
             ]
         });
@@ -2811,113 +5824,175 @@ mod tests {
         Ok(())
     }
 
+    #[cfg(not(miri))]
     #[test]
-    fn select_where_ranges_cancel() -> Result<(), DbError> {
-        let mut db = init_database()?;
+    fn large_auto_index_scan() -> Result<(), DbError> {
+        let mut db = init_database_with(DbConf {
+            page_size: 96,
+            cache_size: 1024,
+        })?;
 
-        db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
-        )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (4, 'Mark', 'mark@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (5, 'Carla', 'carla@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (6, 'Mia', 'mia@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (7, 'Jay', 'jay@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (8, 'Lauren', 'lauren@email.com');")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
 
-        let query = db.exec(
-            "SELECT * FROM users WHERE (id >= 2 AND id <= 3) AND (id > 4 AND id < 7) AND id >= 8;",
-        )?;
+        let mut users = Vec::new();
+
+        for i in 1..=400 {
+            let name = format!("User{i:03}");
+            users.push(vec![Value::Number(i), Value::String(name)]);
+        }
+
+        for user in &users {
+            db.exec(&format!(
+                "INSERT INTO users(id, name) VALUES ({}, {});",
+                user[0], user[1]
+            ))?;
+        }
+
+        let total_users = users.len();
+        users.drain(..users.len() / 2);
+
+        let query = db.exec(&format!(
+            "SELECT * FROM users WHERE id > {};",
+            total_users / 2
+        ))?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+            ]),
+            tuples: users,
+        });
+
+        Ok(())
+    }
+
+    #[cfg(not(miri))]
+    #[test]
+    fn large_auto_index_scan_key_not_found() -> Result<(), DbError> {
+        let mut db = init_database_with(DbConf {
+            page_size: 96,
+            cache_size: 1024,
+        })?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
+
+        let mut users = Vec::new();
+
+        let dont_insert_key = 200;
+
+        for i in 1..=400 {
+            if i != dont_insert_key {
+                let name = format!("User{i:03}");
+                db.exec(&format!(
+                    "INSERT INTO users(id, name) VALUES ({i}, '{name}');"
+                ))?;
+
+                if i > dont_insert_key {
+                    users.push(vec![Value::Number(i), Value::String(name)]);
+                }
+            }
+        }
+
+        let query = db.exec(&format!(
+            "SELECT * FROM users WHERE id > {dont_insert_key};"
+        ))?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
                 Column::new("name", DataType::Varchar(255)),
-                Column::unique("email", DataType::Varchar(255)),
             ]),
-            tuples: vec![]
+            tuples: users,
         });
 
         Ok(())
     }
 
+    // Binary search will yield "should be at index 0".
     #[test]
-    fn select_where_multiple_indexes_exact_match_and_ranges() -> Result<(), DbError> {
+    fn select_where_index_key_should_be_first_on_page() -> Result<(), DbError> {
         let mut db = init_database()?;
 
         db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255) UNIQUE, email VARCHAR(255) UNIQUE);",
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
         )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', 'alex@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', 'bob@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', 'david@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (4, 'Mark', 'mark@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (5, 'Carla', 'carla@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (6, 'Mia', 'mia@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (7, 'Jay', 'jay@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (8, 'Lauren', 'lauren@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Alex', 'alex@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'Bob', 'bob@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (4, 'David', 'david@email.com');")?;
 
-        let query = db.exec(
-            "SELECT * FROM users WHERE id < 4 OR email = 'mia@email.com' OR name = 'Lauren';",
-        )?;
+        let query = db.exec("SELECT * FROM users WHERE id > 1;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
-                Column::unique("name", DataType::Varchar(255)),
+                Column::new("name", DataType::Varchar(255)),
                 Column::unique("email", DataType::Varchar(255)),
             ]),
             tuples: vec![
                 vec![
-                    Value::Number(1),
+                    Value::Number(2),
                     Value::String("Alex".into()),
                     Value::String("alex@email.com".into()),
                 ],
                 vec![
-                    Value::Number(2),
+                    Value::Number(3),
                     Value::String("Bob".into()),
                     Value::String("bob@email.com".into()),
                 ],
                 vec![
-                    Value::Number(3),
+                    Value::Number(4),
                     Value::String("David".into()),
                     Value::String("david@email.com".into()),
                 ],
-                vec![
-                    Value::Number(6),
-                    Value::String("Mia".into()),
-                    Value::String("mia@email.com".into()),
-                ],
-                vec![
-                    Value::Number(8),
-                    Value::String("Lauren".into()),
-                    Value::String("lauren@email.com".into()),
-                ],
             ]
         });
 
         Ok(())
     }
 
+    // BTree is configured to store at least 4 keys in each page by default. If
+    // we store large rows in a small page we'll be guaranteed to only have 4
+    // keys in that page because we can't fit anymore. We'll use that to test
+    // the edge case where a binary search results in out of bounds index.
     #[test]
-    fn select_where_multiple_indexes_ranges_only() -> Result<(), DbError> {
-        let mut db = init_database()?;
+    fn select_where_index_key_should_be_last_on_page() -> Result<(), DbError> {
+        let mut db = init_database_with(DbConf {
+            page_size: 96,
+            cache_size: 256,
+        })?;
+
+        let num_pages = 3;
+        let mut users = Vec::new();
+
+        let mut id = 1;
+
+        for _ in 0..num_pages {
+            for _ in 0..4 {
+                users.push(vec![
+                    Value::Number(id),
+                    Value::String(format!("User{id}").repeat(20)),
+                    Value::String(format!("user{id}.com")),
+                ]);
+                id += 1;
+            }
+            // Skip IDs to query one that doesn't exist later.
+            id += 1;
+        }
 
         db.exec(
             "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
         )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', '1@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', '2@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', '3@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (4, 'Mark', '4@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (5, 'Carla', '5@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (6, 'Mia', '6@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (7, 'Jay', '7@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (8, 'Lauren', '8@email.com');")?;
 
-        let query = db.exec("SELECT * FROM users WHERE id >= 7 OR email < '3@email.com';")?;
+        for user in &users {
+            db.exec(&format!(
+                "INSERT INTO users (id, name, email) VALUES ({}, {}, {});",
+                user[0], user[1], user[2]
+            ))?;
+        }
+
+        users.drain(..4);
+        let query = db.exec("SELECT * FROM users WHERE id > 5;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
@@ -2925,1034 +6000,1471 @@ mod tests {
                 Column::new("name", DataType::Varchar(255)),
                 Column::unique("email", DataType::Varchar(255)),
             ]),
-            tuples: vec![
-                vec![
-                    Value::Number(1),
-                    Value::String("Alex".into()),
-                    Value::String("1@email.com".into()),
-                ],
-                vec![
-                    Value::Number(2),
-                    Value::String("Bob".into()),
-                    Value::String("2@email.com".into()),
-                ],
-                vec![
-                    Value::Number(7),
-                    Value::String("Jay".into()),
-                    Value::String("7@email.com".into()),
-                ],
-                vec![
-                    Value::Number(8),
-                    Value::String("Lauren".into()),
-                    Value::String("8@email.com".into()),
-                ],
-            ]
+            tuples: users
         });
 
         Ok(())
     }
 
     #[test]
-    fn select_where_multiple_indexes_multiple_ranges() -> Result<(), DbError> {
+    fn delete_all() -> Result<(), DbError> {
         let mut db = init_database()?;
 
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE, age INT);")?;
         db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+            "INSERT INTO users(id, name, email, age) VALUES (1, 'John Doe', 'john@doe.com', 18);",
+        )?;
+        db.exec(
+            "INSERT INTO users(id, name, email, age) VALUES (2, 'Jane Doe', 'jane@doe.com', 22);",
+        )?;
+        db.exec(
+            "INSERT INTO users(id, name, email, age) VALUES (3, 'Some Dude', 'some@dude.com', 24);",
         )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (1, 'Alex', '1@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Bob', '2@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'David', '3@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (4, 'Mark', '4@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (5, 'Carla', '5@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (6, 'Mia', '6@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (7, 'Jay', '7@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (8, 'Lauren', '8@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (9, 'Jack', '9@email.com');")?;
 
-        #[rustfmt::skip]
-        let query = db.exec("
-            SELECT * FROM users
-            WHERE (id >= 4 AND id < 6)
-            OR email < '3@email.com'
-            OR (id > 6 AND id < 9)
-            OR email = '9@email.com';
-        ")?;
+        db.exec("DELETE FROM users;")?;
+
+        let query = db.exec("SELECT * FROM users;")?;
+
+        assert!(query.tuples.is_empty());
+
+        assert_index_contains(&mut db, "users_email_uq_index", &[])
+    }
+
+    #[test]
+    fn delete_where() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, age INT);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (1, 'john@email.com', 18);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (2, 'jane@email.com', 22);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (3, 'some_dude@email.com', 24);")?;
+
+        db.exec("DELETE FROM users WHERE age > 18;")?;
+
+        let query = db.exec("SELECT * FROM users;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::unique("email", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
+            ]),
+            tuples: vec![vec![
+                Value::Number(1),
+                Value::String("john@email.com".into()),
+                Value::Number(18)
+            ]]
+        });
+
+        assert_index_contains(&mut db, "users_email_uq_index", &[vec![
+            Value::String("john@email.com".into()),
+            Value::Number(1),
+        ]])
+    }
+
+    // If buffering doesn't work correctly the delete should destroy the cursor
+    // of the scan plan.
+    #[test]
+    fn delete_where_auto_index_exact() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, age INT);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (1, 'john@email.com', 18);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (2, 'jane@email.com', 22);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (3, 'some_dude@email.com', 24);")?;
+
+        db.exec("DELETE FROM users WHERE id = 2;")?;
+
+        let query = db.exec("SELECT * FROM users;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
                 Column::unique("email", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
             ]),
             tuples: vec![
                 vec![
                     Value::Number(1),
-                    Value::String("Alex".into()),
-                    Value::String("1@email.com".into()),
-                ],
-                vec![
-                    Value::Number(2),
-                    Value::String("Bob".into()),
-                    Value::String("2@email.com".into()),
-                ],
-                vec![
-                    Value::Number(4),
-                    Value::String("Mark".into()),
-                    Value::String("4@email.com".into()),
-                ],
-                vec![
-                    Value::Number(5),
-                    Value::String("Carla".into()),
-                    Value::String("5@email.com".into()),
-                ],
-                vec![
-                    Value::Number(7),
-                    Value::String("Jay".into()),
-                    Value::String("7@email.com".into()),
-                ],
-                vec![
-                    Value::Number(8),
-                    Value::String("Lauren".into()),
-                    Value::String("8@email.com".into()),
+                    Value::String("john@email.com".into()),
+                    Value::Number(18)
                 ],
                 vec![
-                    Value::Number(9),
-                    Value::String("Jack".into()),
-                    Value::String("9@email.com".into()),
-                ],
+                    Value::Number(3),
+                    Value::String("some_dude@email.com".into()),
+                    Value::Number(24)
+                ]
             ]
         });
 
-        Ok(())
-    }
-
-    #[cfg(not(miri))]
-    #[test]
-    fn large_auto_index_scan() -> Result<(), DbError> {
-        let mut db = init_database_with(DbConf {
-            page_size: 96,
-            cache_size: 1024,
-        })?;
-
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
-
-        let mut users = Vec::new();
+        assert_index_contains(&mut db, "users_email_uq_index", &[
+            vec![Value::String("john@email.com".into()), Value::Number(1)],
+            vec![
+                Value::String("some_dude@email.com".into()),
+                Value::Number(3),
+            ],
+        ])
+    }
 
-        for i in 1..=400 {
-            let name = format!("User{i:03}");
-            users.push(vec![Value::Number(i), Value::String(name)]);
-        }
+    // Again, if buffering doesn't work this will fail.
+    #[test]
+    fn delete_where_auto_index_ranged() -> Result<(), DbError> {
+        let mut db = init_database()?;
 
-        for user in &users {
-            db.exec(&format!(
-                "INSERT INTO users(id, name) VALUES ({}, {});",
-                user[0], user[1]
-            ))?;
-        }
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, age INT);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (1, 'john@email.com', 18);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (2, 'jane@email.com', 22);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (3, 'some_dude@email.com', 24);")?;
 
-        let total_users = users.len();
-        users.drain(..users.len() / 2);
+        db.exec("DELETE FROM users WHERE id >= 2;")?;
 
-        let query = db.exec(&format!(
-            "SELECT * FROM users WHERE id > {};",
-            total_users / 2
-        ))?;
+        let query = db.exec("SELECT * FROM users;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
+                Column::unique("email", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
             ]),
-            tuples: users,
+            tuples: vec![vec![
+                Value::Number(1),
+                Value::String("john@email.com".into()),
+                Value::Number(18)
+            ],]
         });
 
-        Ok(())
+        assert_index_contains(&mut db, "users_email_uq_index", &[vec![
+            Value::String("john@email.com".into()),
+            Value::Number(1),
+        ]])
     }
 
-    #[cfg(not(miri))]
     #[test]
-    fn large_auto_index_scan_key_not_found() -> Result<(), DbError> {
-        let mut db = init_database_with(DbConf {
-            page_size: 96,
-            cache_size: 1024,
-        })?;
-
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
-
-        let mut users = Vec::new();
-
-        let dont_insert_key = 200;
+    fn delete_where_indexed_column_ranged() -> Result<(), DbError> {
+        let mut db = init_database()?;
 
-        for i in 1..=400 {
-            if i != dont_insert_key {
-                let name = format!("User{i:03}");
-                db.exec(&format!(
-                    "INSERT INTO users(id, name) VALUES ({i}, '{name}');"
-                ))?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, age INT);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (1, 'john@email.com', 18);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (2, 'jane@email.com', 22);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (3, 'some_dude@email.com', 24);")?;
 
-                if i > dont_insert_key {
-                    users.push(vec![Value::Number(i), Value::String(name)]);
-                }
-            }
-        }
+        db.exec("DELETE FROM users WHERE email >= 'john@email.com';")?;
 
-        let query = db.exec(&format!(
-            "SELECT * FROM users WHERE id > {dont_insert_key};"
-        ))?;
+        let query = db.exec("SELECT * FROM users;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
+                Column::unique("email", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
             ]),
-            tuples: users,
+            tuples: vec![vec![
+                Value::Number(2),
+                Value::String("jane@email.com".into()),
+                Value::Number(22)
+            ]]
         });
 
-        Ok(())
+        assert_index_contains(&mut db, "users_email_uq_index", &[vec![
+            Value::String("jane@email.com".into()),
+            Value::Number(2),
+        ]])
     }
 
-    // Binary search will yield "should be at index 0".
     #[test]
-    fn select_where_index_key_should_be_first_on_page() -> Result<(), DbError> {
+    fn delete_where_auto_index_key_no_match() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
-        )?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (2, 'Alex', 'alex@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (3, 'Bob', 'bob@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (4, 'David', 'david@email.com');")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, age INT);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (1, 'john@email.com', 18);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (2, 'jane@email.com', 22);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (3, 'some_dude@email.com', 24);")?;
 
-        let query = db.exec("SELECT * FROM users WHERE id > 1;")?;
+        db.exec("DELETE FROM users WHERE id >= 4;")?;
+
+        let query = db.exec("SELECT * FROM users;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
                 Column::unique("email", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
             ]),
             tuples: vec![
+                vec![
+                    Value::Number(1),
+                    Value::String("john@email.com".into()),
+                    Value::Number(18)
+                ],
                 vec![
                     Value::Number(2),
-                    Value::String("Alex".into()),
-                    Value::String("alex@email.com".into()),
+                    Value::String("jane@email.com".into()),
+                    Value::Number(22)
                 ],
                 vec![
                     Value::Number(3),
-                    Value::String("Bob".into()),
-                    Value::String("bob@email.com".into()),
+                    Value::String("some_dude@email.com".into()),
+                    Value::Number(24)
+                ]
+            ]
+        });
+
+        assert_index_contains(&mut db, "users_email_uq_index", &[
+            vec![Value::String("jane@email.com".into()), Value::Number(2)],
+            vec![Value::String("john@email.com".into()), Value::Number(1)],
+            vec![
+                Value::String("some_dude@email.com".into()),
+                Value::Number(3),
+            ],
+        ])
+    }
+
+    // Top level LogicalOrScan plan.
+    #[test]
+    fn delete_where_auto_index_multiple_ranges() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, age INT);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (1, 'john@email.com', 18);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (2, 'jane@email.com', 22);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (3, 'some_dude@email.com', 24);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (4, 'another_dude@email.com', 24);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (5, 'dude_five@email.com', 24);")?;
+        db.exec("INSERT INTO users(id, email, age) VALUES (6, 'dude_six@email.com', 24);")?;
+
+        db.exec("DELETE FROM users WHERE id >= 2 AND id <= 3 OR id > 4;")?;
+
+        let query = db.exec("SELECT * FROM users;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::unique("email", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(1),
+                    Value::String("john@email.com".into()),
+                    Value::Number(18)
                 ],
                 vec![
                     Value::Number(4),
-                    Value::String("David".into()),
-                    Value::String("david@email.com".into()),
-                ],
+                    Value::String("another_dude@email.com".into()),
+                    Value::Number(24)
+                ]
             ]
         });
 
-        Ok(())
+        assert_index_contains(&mut db, "users_email_uq_index", &[
+            vec![
+                Value::String("another_dude@email.com".into()),
+                Value::Number(4),
+            ],
+            vec![Value::String("john@email.com".into()), Value::Number(1)],
+        ])
     }
 
-    // BTree is configured to store at least 4 keys in each page by default. If
-    // we store large rows in a small page we'll be guaranteed to only have 4
-    // keys in that page because we can't fit anymore. We'll use that to test
-    // the edge case where a binary search results in out of bounds index.
     #[test]
-    fn select_where_index_key_should_be_last_on_page() -> Result<(), DbError> {
-        let mut db = init_database_with(DbConf {
-            page_size: 96,
-            cache_size: 256,
-        })?;
+    fn delete_from_multiple_indexes() -> Result<(), DbError> {
+        let mut db = init_database()?;
 
-        let num_pages = 3;
-        let mut users = Vec::new();
+        db.exec(
+            "CREATE TABLE users (email VARCHAR(255) UNIQUE, name VARCHAR(64) UNIQUE, id INT PRIMARY KEY);",
+        )?;
 
-        let mut id = 1;
+        db.exec("INSERT INTO users(id, name, email) VALUES (100, 'John Doe', 'john@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (200, 'Jane Doe', 'jane@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES (300, 'Some Dude', 'some@dude.com');")?;
 
-        for _ in 0..num_pages {
-            for _ in 0..4 {
-                users.push(vec![
-                    Value::Number(id),
-                    Value::String(format!("User{id}").repeat(20)),
-                    Value::String(format!("user{id}.com")),
-                ]);
-                id += 1;
-            }
-            // Skip IDs to query one that doesn't exist later.
-            id += 1;
-        }
+        db.exec("DELETE FROM users WHERE name >= 'John Doe';")?;
+
+        assert_index_contains(&mut db, "users_email_uq_index", &[vec![
+            Value::String("jane@email.com".into()),
+            Value::Number(2),
+        ]])?;
+
+        assert_index_contains(&mut db, "users_pk_index", &[vec![
+            Value::Number(200),
+            Value::Number(2),
+        ]])?;
+
+        assert_index_contains(&mut db, "users_name_uq_index", &[vec![
+            Value::String("Jane Doe".into()),
+            Value::Number(2),
+        ]])
+    }
+
+    #[test]
+    fn delete_from_empty_table() -> Result<(), DbError> {
+        let mut db = init_database()?;
 
         db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+            "CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, name VARCHAR(64));",
         )?;
+        db.exec("CREATE UNIQUE INDEX name_idx ON users(name);")?;
 
-        for user in &users {
+        db.exec("DELETE FROM users WHERE id >= 500;")?;
+
+        let query = db.exec("SELECT * FROM users;")?;
+
+        assert!(query.is_empty());
+
+        assert_index_contains(&mut db, "users_email_uq_index", &[])?;
+        assert_index_contains(&mut db, "name_idx", &[])
+    }
+
+    // Test the collect plan.
+    #[cfg(not(miri))]
+    #[test]
+    fn delete_many() -> Result<(), DbError> {
+        let mut db = init_database_with(DbConf {
+            page_size: 96,
+            cache_size: 512,
+        })?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+
+        for i in 1..=500 {
+            let name = format!("User{i}");
             db.exec(&format!(
-                "INSERT INTO users (id, name, email) VALUES ({}, {}, {});",
-                user[0], user[1], user[2]
+                "INSERT INTO users(id, name, age) VALUES ({i}, '{name}', 18);"
             ))?;
         }
 
-        users.drain(..4);
-        let query = db.exec("SELECT * FROM users WHERE id > 5;")?;
+        db.exec("DELETE FROM users WHERE id != 355;")?;
+
+        let query = db.exec("SELECT * FROM users;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
                 Column::new("name", DataType::Varchar(255)),
-                Column::unique("email", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
             ]),
-            tuples: users
+            tuples: vec![vec![
+                Value::Number(355),
+                Value::String("User355".into()),
+                Value::Number(18)
+            ]]
         });
 
         Ok(())
     }
 
     #[test]
-    fn delete_all() -> Result<(), DbError> {
+    fn update() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE, age INT);")?;
-        db.exec(
-            "INSERT INTO users(id, name, email, age) VALUES (1, 'John Doe', 'john@doe.com', 18);",
-        )?;
-        db.exec(
-            "INSERT INTO users(id, name, email, age) VALUES (2, 'Jane Doe', 'jane@doe.com', 22);",
-        )?;
-        db.exec(
-            "INSERT INTO users(id, name, email, age) VALUES (3, 'Some Dude', 'some@dude.com', 24);",
-        )?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
 
-        db.exec("DELETE FROM users;")?;
+        db.exec("UPDATE users SET age = 20;")?;
 
         let query = db.exec("SELECT * FROM users;")?;
 
-        assert!(query.tuples.is_empty());
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::new("age", DataType::Int),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(1),
+                    Value::String("John Doe".into()),
+                    Value::Number(20)
+                ],
+                vec![
+                    Value::Number(2),
+                    Value::String("Jane Doe".into()),
+                    Value::Number(20)
+                ],
+                vec![
+                    Value::Number(3),
+                    Value::String("Some Dude".into()),
+                    Value::Number(20)
+                ]
+            ]
+        });
 
-        assert_index_contains(&mut db, "users_email_uq_index", &[])
+        Ok(())
     }
 
     #[test]
-    fn delete_where() -> Result<(), DbError> {
+    fn update_where() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, age INT);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (1, 'john@email.com', 18);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (2, 'jane@email.com', 22);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (3, 'some_dude@email.com', 24);")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
 
-        db.exec("DELETE FROM users WHERE age > 18;")?;
+        db.exec("UPDATE users SET age = 20, name = 'Updated Name' WHERE age > 18;")?;
 
         let query = db.exec("SELECT * FROM users;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
-                Column::unique("email", DataType::Varchar(255)),
+                Column::new("name", DataType::Varchar(255)),
                 Column::new("age", DataType::Int),
             ]),
-            tuples: vec![vec![
-                Value::Number(1),
-                Value::String("john@email.com".into()),
-                Value::Number(18)
-            ]]
+            tuples: vec![
+                vec![
+                    Value::Number(1),
+                    Value::String("John Doe".into()),
+                    Value::Number(18)
+                ],
+                vec![
+                    Value::Number(2),
+                    Value::String("Updated Name".into()),
+                    Value::Number(20)
+                ],
+                vec![
+                    Value::Number(3),
+                    Value::String("Updated Name".into()),
+                    Value::Number(20)
+                ]
+            ]
         });
 
-        assert_index_contains(&mut db, "users_email_uq_index", &[vec![
-            Value::String("john@email.com".into()),
-            Value::Number(1),
-        ]])
+        Ok(())
     }
 
-    // If buffering doesn't work correctly the delete should destroy the cursor
-    // of the scan plan.
     #[test]
-    fn delete_where_auto_index_exact() -> Result<(), DbError> {
+    fn update_no_match() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, age INT);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (1, 'john@email.com', 18);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (2, 'jane@email.com', 22);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (3, 'some_dude@email.com', 24);")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
 
-        db.exec("DELETE FROM users WHERE id = 2;")?;
+        db.exec("UPDATE users SET age = 20, name = 'Updated Name' WHERE age > 50;")?;
 
         let query = db.exec("SELECT * FROM users;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
-                Column::unique("email", DataType::Varchar(255)),
+                Column::new("name", DataType::Varchar(255)),
                 Column::new("age", DataType::Int),
             ]),
             tuples: vec![
                 vec![
                     Value::Number(1),
-                    Value::String("john@email.com".into()),
+                    Value::String("John Doe".into()),
                     Value::Number(18)
                 ],
+                vec![
+                    Value::Number(2),
+                    Value::String("Jane Doe".into()),
+                    Value::Number(22)
+                ],
                 vec![
                     Value::Number(3),
-                    Value::String("some_dude@email.com".into()),
+                    Value::String("Some Dude".into()),
                     Value::Number(24)
                 ]
             ]
         });
 
-        assert_index_contains(&mut db, "users_email_uq_index", &[
-            vec![Value::String("john@email.com".into()), Value::Number(1)],
-            vec![
-                Value::String("some_dude@email.com".into()),
-                Value::Number(3),
-            ],
-        ])
+        Ok(())
     }
 
-    // Again, if buffering doesn't work this will fail.
     #[test]
-    fn delete_where_auto_index_ranged() -> Result<(), DbError> {
+    fn update_with_expression_referencing_old_value() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, age INT);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (1, 'john@email.com', 18);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (2, 'jane@email.com', 22);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (3, 'some_dude@email.com', 24);")?;
+        db.exec("CREATE TABLE products (id INT PRIMARY KEY, category VARCHAR(255), price INT);")?;
+        db.exec("INSERT INTO products(id, category, price) VALUES (1, 'x', 10);")?;
+        db.exec("INSERT INTO products(id, category, price) VALUES (2, 'y', 20);")?;
+        db.exec("INSERT INTO products(id, category, price) VALUES (3, 'x', 30);")?;
 
-        db.exec("DELETE FROM users WHERE id >= 2;")?;
+        db.exec("UPDATE products SET price = price * 2 WHERE category = 'x';")?;
 
-        let query = db.exec("SELECT * FROM users;")?;
+        let query = db.exec("SELECT id, price FROM products;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
-                Column::unique("email", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
+                Column::new("price", DataType::Int),
             ]),
-            tuples: vec![vec![
-                Value::Number(1),
-                Value::String("john@email.com".into()),
-                Value::Number(18)
-            ],]
+            tuples: vec![
+                vec![Value::Number(1), Value::Number(20)],
+                vec![Value::Number(2), Value::Number(20)],
+                vec![Value::Number(3), Value::Number(60)],
+            ]
         });
 
-        assert_index_contains(&mut db, "users_email_uq_index", &[vec![
-            Value::String("john@email.com".into()),
-            Value::Number(1),
-        ]])
+        Ok(())
     }
 
+    // All the assignments are evaluated using the row as it was before the
+    // update, so this swaps the values.
     #[test]
-    fn delete_where_indexed_column_ranged() -> Result<(), DbError> {
+    fn update_interdependent_assignments() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, age INT);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (1, 'john@email.com', 18);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (2, 'jane@email.com', 22);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (3, 'some_dude@email.com', 24);")?;
+        db.exec("CREATE TABLE t (id INT PRIMARY KEY, a INT, b INT);")?;
+        db.exec("INSERT INTO t(id, a, b) VALUES (1, 10, 20);")?;
 
-        db.exec("DELETE FROM users WHERE email >= 'john@email.com';")?;
+        db.exec("UPDATE t SET a = b, b = a;")?;
+
+        let query = db.exec("SELECT * FROM t;")?;
+
+        assert_eq!(query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("a", DataType::Int),
+                Column::new("b", DataType::Int),
+            ]),
+            tuples: vec![vec![Value::Number(1), Value::Number(20), Value::Number(10)]]
+        });
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_self_referential_assignments() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, first VARCHAR(255), full VARCHAR(255), counter INT);")?;
+        db.exec("INSERT INTO users(id, first, full, counter) VALUES (1, 'John', '', 0);")?;
+        db.exec("INSERT INTO users(id, first, full, counter) VALUES (2, 'Mary', '', 5);")?;
+
+        db.exec("UPDATE users SET counter = counter + 1;")?;
+        db.exec("UPDATE users SET counter = counter + 1, full = first WHERE id = 2;")?;
+
+        let query = db.exec("SELECT first, full, counter FROM users;")?;
+
+        assert_eq!(query.tuples, vec![
+            vec![
+                Value::String("John".into()),
+                Value::String("".into()),
+                Value::Number(1)
+            ],
+            vec![
+                Value::String("Mary".into()),
+                Value::String("Mary".into()),
+                Value::Number(7)
+            ],
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn computed_integer_overflow() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE t (id INT PRIMARY KEY, x INT UNIQUE);")?;
+        db.exec("INSERT INTO t(id, x) VALUES (1, 5), (2, 2147483647);")?;
+
+        let overflow = |num| {
+            Err(DbError::Sql(SqlError::AnalyzerError(
+                AnalyzerError::IntegerOutOfRange(num, DataType::Int),
+            )))
+        };
+
+        let too_big = i32::MAX as i128 + 1;
+
+        for (sql, expected) in [
+            (
+                "INSERT INTO t(id, x) VALUES (3, 2147483647 + 1);",
+                overflow(too_big),
+            ),
+            ("UPDATE t SET x = x + 1;", overflow(too_big)),
+            (
+                "INSERT INTO t(id, x) SELECT id + 10, x * 2 FROM t;",
+                overflow(i32::MAX as i128 * 2),
+            ),
+            (
+                "SELECT * FROM t WHERE id = 5000000000;",
+                overflow(5000000000),
+            ),
+        ] {
+            assert_eq!(db.exec(sql).map(|_| ()), expected, "{sql}");
+        }
+
+        let query = db.exec("SELECT * FROM t;")?;
+        assert_eq!(query.tuples, vec![
+            vec![Value::Number(1), Value::Number(5)],
+            vec![Value::Number(2), Value::Number(i32::MAX.into())],
+        ]);
+
+        // Literals get the narrowest type that fits them.
+        let query = db.exec("SELECT 1, 5000000000, 10000000000000000000;")?;
+        assert_eq!(
+            query.schema,
+            Schema::new(vec![
+                Column::new("1", DataType::Int),
+                Column::new("5000000000", DataType::BigInt),
+                Column::new("10000000000000000000", DataType::UnsignedBigInt),
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn update_in_place_keeps_page_layout() -> Result<(), DbError> {
+        let mut db = init_database_with(DbConf {
+            page_size: 256,
+            cache_size: 1024,
+        })?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        for i in 1..=100 {
+            db.exec(&format!(
+                "INSERT INTO users (id, name, age) VALUES ({i}, 'User{i}', 20);"
+            ))?;
+        }
+
+        fn layout(
+            db: &mut Database<MemBuf>,
+        ) -> Result<Vec<(PageNumber, u16, Vec<PageNumber>)>, DbError> {
+            let root = db.table_metadata("users")?.root;
+            let mut pager = db.pager.borrow_mut();
+            let mut layout = Vec::new();
+            let mut stack = vec![root];
+
+            while let Some(page_number) = stack.pop() {
+                let page = pager.get(page_number)?;
+                let children = page.iter_children().collect::<Vec<_>>();
+                layout.push((page_number, page.len(), children.clone()));
+                stack.extend(children);
+            }
+
+            Ok(layout)
+        }
+
+        let before = layout(&mut db)?;
+        assert!(before.len() > 1);
+
+        db.exec("UPDATE users SET age = age + 1;")?;
+        assert_eq!(layout(&mut db)?, before);
+
+        db.exec("UPDATE users SET name = 'A much longer name than before' WHERE id <= 5;")?;
+
+        let query = db.exec("SELECT * FROM users;")?;
+
+        assert_eq!(query.tuples.len(), 100);
+        for (i, tuple) in query.tuples.iter().enumerate() {
+            let id = i as i128 + 1;
+            let name = if id <= 5 {
+                String::from("A much longer name than before")
+            } else {
+                format!("User{id}")
+            };
+            assert_eq!(tuple, &vec![
+                Value::Number(id),
+                Value::String(name),
+                Value::Number(21)
+            ]);
+        }
+
+        Ok(())
+    }
+
+    // Test the collect plan.
+    #[cfg(not(miri))]
+    #[test]
+    fn update_many() -> Result<(), DbError> {
+        let mut db = init_database_with(DbConf {
+            page_size: 96,
+            cache_size: 512,
+        })?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+
+        let pk_range = 1..=500;
+
+        for i in pk_range.clone() {
+            let name = format!("User{i}");
+            db.exec(&format!(
+                "INSERT INTO users(id, name, age) VALUES ({i}, '{name}', 18);"
+            ))?;
+        }
+
+        db.exec("UPDATE users SET name = 'Updated Name', age = 20;")?;
 
         let query = db.exec("SELECT * FROM users;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
-                Column::unique("email", DataType::Varchar(255)),
+                Column::new("name", DataType::Varchar(255)),
                 Column::new("age", DataType::Int),
             ]),
-            tuples: vec![vec![
-                Value::Number(2),
-                Value::String("jane@email.com".into()),
-                Value::Number(22)
-            ]]
+            tuples: pk_range
+                .map(|pk| vec![
+                    Value::Number(pk),
+                    Value::String("Updated Name".into()),
+                    Value::Number(20)
+                ])
+                .collect()
         });
 
-        assert_index_contains(&mut db, "users_email_uq_index", &[vec![
-            Value::String("jane@email.com".into()),
-            Value::Number(2),
-        ]])
+        Ok(())
     }
 
     #[test]
-    fn delete_where_auto_index_key_no_match() -> Result<(), DbError> {
+    fn update_primary_key() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, age INT);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (1, 'john@email.com', 18);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (2, 'jane@email.com', 22);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (3, 'some_dude@email.com', 24);")?;
+        db.exec("CREATE TABLE users (name VARCHAR(255), age INT, id INT PRIMARY KEY, email VARCHAR(255) UNIQUE);")?;
+        db.exec(
+            "INSERT INTO users(id, name, email, age) VALUES (1, 'John Doe', 'john@doe.com', 18);",
+        )?;
+        db.exec(
+            "INSERT INTO users(id, name, email, age) VALUES (2, 'Jane Doe', 'jane@doe.com', 22);",
+        )?;
+        db.exec(
+            "INSERT INTO users(id, name, email, age) VALUES (3, 'Some Dude', 'some@dude.com', 24);",
+        )?;
 
-        db.exec("DELETE FROM users WHERE id >= 4;")?;
+        db.exec("UPDATE users SET id = 10 where id = 1;")?;
 
-        let query = db.exec("SELECT * FROM users;")?;
+        let query = db.exec("SELECT id, name, email, age FROM users;")?;
 
         assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
                 Column::unique("email", DataType::Varchar(255)),
                 Column::new("age", DataType::Int),
             ]),
             tuples: vec![
                 vec![
-                    Value::Number(1),
-                    Value::String("john@email.com".into()),
+                    Value::Number(10),
+                    Value::String("John Doe".into()),
+                    Value::String("john@doe.com".into()),
                     Value::Number(18)
                 ],
                 vec![
                     Value::Number(2),
-                    Value::String("jane@email.com".into()),
+                    Value::String("Jane Doe".into()),
+                    Value::String("jane@doe.com".into()),
                     Value::Number(22)
                 ],
                 vec![
                     Value::Number(3),
-                    Value::String("some_dude@email.com".into()),
+                    Value::String("Some Dude".into()),
+                    Value::String("some@dude.com".into()),
                     Value::Number(24)
                 ]
             ]
         });
 
+        assert_index_contains(&mut db, "users_pk_index", &[
+            vec![Value::Number(2), Value::Number(2)],
+            vec![Value::Number(3), Value::Number(3)],
+            vec![Value::Number(10), Value::Number(1)],
+        ])?;
+
         assert_index_contains(&mut db, "users_email_uq_index", &[
-            vec![Value::String("jane@email.com".into()), Value::Number(2)],
-            vec![Value::String("john@email.com".into()), Value::Number(1)],
-            vec![
-                Value::String("some_dude@email.com".into()),
-                Value::Number(3),
-            ],
+            vec![Value::String("jane@doe.com".into()), Value::Number(2)],
+            vec![Value::String("john@doe.com".into()), Value::Number(1)],
+            vec![Value::String("some@dude.com".into()), Value::Number(3)],
         ])
     }
 
-    // Top level LogicalOrScan plan.
     #[test]
-    fn delete_where_auto_index_multiple_ranges() -> Result<(), DbError> {
+    fn update_pk_index_on_insert() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, age INT);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (1, 'john@email.com', 18);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (2, 'jane@email.com', 22);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (3, 'some_dude@email.com', 24);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (4, 'another_dude@email.com', 24);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (5, 'dude_five@email.com', 24);")?;
-        db.exec("INSERT INTO users(id, email, age) VALUES (6, 'dude_six@email.com', 24);")?;
+        db.exec("CREATE TABLE users (id VARCHAR(3) PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES ('abc', 'John Doe', 18);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES ('def', 'Jane Doe', 22);")?;
+        db.exec("INSERT INTO users(id, name, age) VALUES ('ghi', 'Some Dude', 24);")?;
 
-        db.exec("DELETE FROM users WHERE id >= 2 AND id <= 3 OR id > 4;")?;
+        assert_index_contains(&mut db, "users_pk_index", &[
+            vec![Value::String("abc".into()), Value::Number(1)],
+            vec![Value::String("def".into()), Value::Number(2)],
+            vec![Value::String("ghi".into()), Value::Number(3)],
+        ])
+    }
 
-        let query = db.exec("SELECT * FROM users;")?;
+    #[test]
+    fn update_multiple_indexes_on_insert() -> Result<(), DbError> {
+        let mut db = init_database()?;
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::unique("email", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
-            ]),
-            tuples: vec![
-                vec![
-                    Value::Number(1),
-                    Value::String("john@email.com".into()),
-                    Value::Number(18)
-                ],
-                vec![
-                    Value::Number(4),
-                    Value::String("another_dude@email.com".into()),
-                    Value::Number(24)
-                ]
-            ]
-        });
+        db.exec(
+            "CREATE TABLE users (id VARCHAR(3) PRIMARY KEY, email VARCHAR(255) UNIQUE, name VARCHAR(64));",
+        )?;
+        db.exec("CREATE UNIQUE INDEX name_idx ON users(name);")?;
+
+        db.exec("INSERT INTO users(id, name, email) VALUES ('ab', 'John Doe', 'john@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES ('cd', 'Jane Doe', 'jane@email.com');")?;
+        db.exec("INSERT INTO users(id, name, email) VALUES ('ef', 'Some Dude', 'some@dude.com');")?;
+
+        assert_index_contains(&mut db, "users_pk_index", &[
+            vec![Value::String("ab".into()), Value::Number(1)],
+            vec![Value::String("cd".into()), Value::Number(2)],
+            vec![Value::String("ef".into()), Value::Number(3)],
+        ])?;
 
         assert_index_contains(&mut db, "users_email_uq_index", &[
-            vec![
-                Value::String("another_dude@email.com".into()),
-                Value::Number(4),
-            ],
+            vec![Value::String("jane@email.com".into()), Value::Number(2)],
             vec![Value::String("john@email.com".into()), Value::Number(1)],
+            vec![Value::String("some@dude.com".into()), Value::Number(3)],
+        ])?;
+
+        assert_index_contains(&mut db, "name_idx", &[
+            vec![Value::String("Jane Doe".into()), Value::Number(2)],
+            vec![Value::String("John Doe".into()), Value::Number(1)],
+            vec![Value::String("Some Dude".into()), Value::Number(3)],
         ])
     }
 
     #[test]
-    fn delete_from_multiple_indexes() -> Result<(), DbError> {
+    fn update_indexed_columns_on_sql_update_statement() -> Result<(), DbError> {
         let mut db = init_database()?;
 
         db.exec(
-            "CREATE TABLE users (email VARCHAR(255) UNIQUE, name VARCHAR(64) UNIQUE, id INT PRIMARY KEY);",
+            "CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, name VARCHAR(64));",
         )?;
+        db.exec("CREATE UNIQUE INDEX name_idx ON users(name);")?;
 
         db.exec("INSERT INTO users(id, name, email) VALUES (100, 'John Doe', 'john@email.com');")?;
         db.exec("INSERT INTO users(id, name, email) VALUES (200, 'Jane Doe', 'jane@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (300, 'Some Dude', 'some@dude.com');")?;
+        db.exec(
+            "INSERT INTO users(id, name, email) VALUES (300, 'Some Dude', 'some_dude@email.com');",
+        )?;
 
-        db.exec("DELETE FROM users WHERE name >= 'John Doe';")?;
+        db.exec("UPDATE users SET id = 3, email = 'some@dude.com' WHERE id = 300;")?;
+        db.exec("UPDATE users SET email = 'updated@email.com' WHERE id = 200;")?;
 
-        assert_index_contains(&mut db, "users_email_uq_index", &[vec![
-            Value::String("jane@email.com".into()),
-            Value::Number(2),
-        ]])?;
+        assert_index_contains(&mut db, "users_email_uq_index", &[
+            vec![Value::String("john@email.com".into()), Value::Number(100)],
+            vec![Value::String("some@dude.com".into()), Value::Number(3)],
+            vec![
+                Value::String("updated@email.com".into()),
+                Value::Number(200),
+            ],
+        ])?;
 
-        assert_index_contains(&mut db, "users_pk_index", &[vec![
-            Value::Number(200),
-            Value::Number(2),
-        ]])?;
+        assert_index_contains(&mut db, "name_idx", &[
+            vec![Value::String("Jane Doe".into()), Value::Number(200)],
+            vec![Value::String("John Doe".into()), Value::Number(100)],
+            vec![Value::String("Some Dude".into()), Value::Number(3)],
+        ])
+    }
 
-        assert_index_contains(&mut db, "users_name_uq_index", &[vec![
-            Value::String("Jane Doe".into()),
-            Value::Number(2),
-        ]])
+    #[test]
+    fn build_up_index_on_create_index_statement() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255));")?;
+
+        db.exec("INSERT INTO users(id, email) VALUES (100, 'john@email.com');")?;
+        db.exec("INSERT INTO users(id, email) VALUES (200, 'jane@email.com');")?;
+        db.exec("INSERT INTO users(id, email) VALUES (300, 'some@dude.com');")?;
+
+        db.exec("CREATE UNIQUE INDEX email_uq ON users(email);")?;
+
+        assert_index_contains(&mut db, "email_uq", &[
+            vec![Value::String("jane@email.com".into()), Value::Number(200)],
+            vec![Value::String("john@email.com".into()), Value::Number(100)],
+            vec![Value::String("some@dude.com".into()), Value::Number(300)],
+        ])
     }
 
     #[test]
-    fn delete_from_empty_table() -> Result<(), DbError> {
+    fn create_and_drop_if_exists() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, name VARCHAR(64));",
-        )?;
+        let create_users =
+            "CREATE TABLE IF NOT EXISTS users (id INT PRIMARY KEY, name VARCHAR(255));";
+        db.exec(create_users)?;
+        db.exec("INSERT INTO users (id, name) VALUES (1, 'John');")?;
+
+        // Running the same script again doesn't touch the table.
+        db.exec(create_users)?;
+        assert_eq!(db.exec("SELECT * FROM users;")?.tuples, vec![vec![
+            Value::Number(1),
+            Value::String("John".into())
+        ]]);
+
+        // The stored definition doesn't need the modifier.
+        assert_eq!(
+            db.exec("SELECT sql FROM mkdb_meta WHERE name = 'users';")?
+                .tuples,
+            vec![vec![Value::String(
+                "CREATE TABLE `users` (`id` INT PRIMARY KEY, `name` VARCHAR(255));".into()
+            )]]
+        );
+
         db.exec("CREATE UNIQUE INDEX name_idx ON users(name);")?;
+        db.exec("DROP INDEX IF EXISTS name_idx;")?;
+        db.exec("DROP INDEX IF EXISTS name_idx;")?;
+        assert!(db.exec("DROP INDEX name_idx;").is_err());
 
-        db.exec("DELETE FROM users WHERE id >= 500;")?;
+        db.exec("DROP TABLE IF EXISTS users;")?;
+        db.exec("DROP TABLE IF EXISTS users;")?;
+        assert!(db.exec("DROP TABLE users;").is_err());
+        assert!(db.exec("SELECT * FROM mkdb_meta;")?.is_empty());
 
-        let query = db.exec("SELECT * FROM users;")?;
+        Ok(())
+    }
+
+    #[test]
+    fn drop_table() -> Result<(), DbError> {
+        let page_size = 1024;
+
+        let mut db = init_database_with(DbConf {
+            page_size,
+            cache_size: page_size,
+        })?;
+
+        let create_products_table = "CREATE TABLE products (id INT PRIMARY KEY, name VARCHAR(255), slug VARCHAR(255) UNIQUE);";
+        db.exec(create_products_table)?;
+
+        // Add some stuff to the products table to verify that it is not tampered with.
+        db.exec("INSERT INTO products (id, name, slug) VALUES (1, 'Test', 'test');")?;
+        db.exec("INSERT INTO products (id, name, slug) VALUES (2, 'Mouse', 'mouse');")?;
+        db.exec("INSERT INTO products (id, name, slug) VALUES (3, 'Keyboard', 'keyboard');")?;
+
+        let expected_used_pages = db.pager.borrow_mut().read_header()?.total_pages;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(1024), email VARCHAR(255) UNIQUE);")?;
+        db.exec("CREATE UNIQUE INDEX name_idx ON users(name);")?;
+
+        // 4 large entries with overflow pages for each page. 10 BTree pages total.
+        for i in 1..=(4 * 10) {
+            let name = format!("User{i}{}", String::from("_").repeat(page_size / 4));
+            db.exec(&format!(
+                "INSERT INTO users (id, name, email) VALUES ({i}, '{name}', 'user{i}@email.com');"
+            ))?;
+        }
+
+        db.exec("DROP TABLE users;")?;
+
+        let mkdb_meta_query = db.exec("SELECT * FROM mkdb_meta;")?;
+        let products_query = db.exec("SELECT * FROM products;")?;
+
+        let db_header = db.pager.borrow_mut().read_header()?;
+        assert_eq!(
+            expected_used_pages,
+            db_header.total_pages - db_header.free_pages
+        );
+
+        assert_eq!(
+            mkdb_meta_query,
+            QuerySet::new(mkdb_meta_schema(), vec![
+                vec![
+                    Value::String("table".into()),
+                    Value::String("products".into()),
+                    Value::Number(1),
+                    Value::String("products".into()),
+                    Value::String(
+                        Parser::new(create_products_table)
+                            .parse_statement()?
+                            .to_sql_safe()
+                    )
+                ],
+                vec![
+                    Value::String("index".into()),
+                    Value::String("products_slug_uq_index".into()),
+                    Value::Number(2),
+                    Value::String("products".into()),
+                    Value::String(
+                        Parser::new(
+                            "CREATE UNIQUE INDEX products_slug_uq_index ON products(slug);"
+                        )
+                        .parse_statement()?
+                        .to_sql_safe()
+                    )
+                ]
+            ])
+        );
 
-        assert!(query.is_empty());
+        assert_eq!(products_query, QuerySet {
+            schema: Schema::new(vec![
+                Column::primary_key("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+                Column::unique("slug", DataType::Varchar(255)),
+            ]),
+            tuples: vec![
+                vec![
+                    Value::Number(1),
+                    Value::String("Test".into()),
+                    Value::String("test".into()),
+                ],
+                vec![
+                    Value::Number(2),
+                    Value::String("Mouse".into()),
+                    Value::String("mouse".into()),
+                ],
+                vec![
+                    Value::Number(3),
+                    Value::String("Keyboard".into()),
+                    Value::String("keyboard".into()),
+                ],
+            ]
+        });
 
-        assert_index_contains(&mut db, "users_email_uq_index", &[])?;
-        assert_index_contains(&mut db, "name_idx", &[])
+        Ok(())
     }
 
-    // Test the collect plan.
-    #[cfg(not(miri))]
     #[test]
-    fn delete_many() -> Result<(), DbError> {
+    fn alter_table_drop_column() -> Result<(), DbError> {
         let mut db = init_database_with(DbConf {
             page_size: 96,
-            cache_size: 512,
+            cache_size: 1024,
         })?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT, email VARCHAR(255) UNIQUE);")?;
 
-        for i in 1..=500 {
-            let name = format!("User{i}");
+        for id in 1..=20 {
             db.exec(&format!(
-                "INSERT INTO users(id, name, age) VALUES ({i}, '{name}', 18);"
+                "INSERT INTO users (id, name, age, email) VALUES ({id}, 'User {id}', {}, 'user{id}@email.com');",
+                id + 20
             ))?;
         }
 
-        db.exec("DELETE FROM users WHERE id != 355;")?;
-
-        let query = db.exec("SELECT * FROM users;")?;
+        db.exec("ALTER TABLE users DROP COLUMN name;")?;
+        db.exec("INSERT INTO users (id, age, email) VALUES (21, 41, 'user21@email.com');")?;
 
-        assert_eq!(query, QuerySet {
+        assert_eq!(db.exec("SELECT * FROM users;")?, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
                 Column::new("age", DataType::Int),
+                Column::unique("email", DataType::Varchar(255)),
             ]),
-            tuples: vec![vec![
-                Value::Number(355),
-                Value::String("User355".into()),
-                Value::Number(18)
-            ]]
+            tuples: (1..=21)
+                .map(|id| {
+                    vec![
+                        Value::Number(id),
+                        Value::Number(id + 20),
+                        Value::String(format!("user{id}@email.com")),
+                    ]
+                })
+                .collect(),
         });
 
+        // The index still points to the rewritten rows.
+        assert_eq!(
+            db.exec("SELECT id FROM users WHERE email = 'user7@email.com';")?,
+            QuerySet {
+                schema: Schema::new(vec![Column::primary_key("id", DataType::Int)]),
+                tuples: vec![vec![Value::Number(7)]],
+            }
+        );
+
+        assert_eq!(
+            db.exec("SELECT sql FROM mkdb_meta WHERE name = 'users';")?
+                .tuples,
+            vec![vec![Value::String(
+                Parser::new(
+                    "CREATE TABLE users (id INT PRIMARY KEY, age INT, email VARCHAR(255) UNIQUE);"
+                )
+                .parse_statement()?
+                .to_sql_safe()
+            )]]
+        );
+
         Ok(())
     }
 
     #[test]
-    fn update() -> Result<(), DbError> {
+    fn alter_table_drop_column_with_row_id() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
-
-        db.exec("UPDATE users SET age = 20;")?;
+        db.exec("CREATE TABLE tags (name VARCHAR(255), color VARCHAR(255), priority INT);")?;
+        db.exec("INSERT INTO tags (name, color, priority) VALUES ('bug', 'red', 1);")?;
+        db.exec("INSERT INTO tags (name, color, priority) VALUES ('docs', 'blue', 2);")?;
 
-        let query = db.exec("SELECT * FROM users;")?;
+        db.exec("ALTER TABLE tags DROP color;")?;
+        db.exec("INSERT INTO tags (name, priority) VALUES ('test', 3);")?;
 
-        assert_eq!(query, QuerySet {
+        assert_eq!(db.exec("SELECT * FROM tags;")?, QuerySet {
             schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
                 Column::new("name", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
+                Column::new("priority", DataType::Int),
             ]),
             tuples: vec![
-                vec![
-                    Value::Number(1),
-                    Value::String("John Doe".into()),
-                    Value::Number(20)
-                ],
-                vec![
-                    Value::Number(2),
-                    Value::String("Jane Doe".into()),
-                    Value::Number(20)
-                ],
-                vec![
-                    Value::Number(3),
-                    Value::String("Some Dude".into()),
-                    Value::Number(20)
-                ]
-            ]
+                vec![Value::String("bug".into()), Value::Number(1)],
+                vec![Value::String("docs".into()), Value::Number(2)],
+                vec![Value::String("test".into()), Value::Number(3)],
+            ],
         });
 
         Ok(())
     }
 
     #[test]
-    fn update_where() -> Result<(), DbError> {
+    fn alter_table_rename() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);")?;
+        db.exec("INSERT INTO users (id, name, email) VALUES (1, 'John', 'john@email.com');")?;
 
-        db.exec("UPDATE users SET age = 20, name = 'Updated Name' WHERE age > 18;")?;
-
-        let query = db.exec("SELECT * FROM users;")?;
+        db.exec("ALTER TABLE users RENAME COLUMN email TO mail;")?;
+        db.exec("ALTER TABLE users RENAME TO customers;")?;
+        db.exec("INSERT INTO customers (id, name, mail) VALUES (2, 'Jane', 'jane@email.com');")?;
 
-        assert_eq!(query, QuerySet {
+        assert_eq!(db.exec("SELECT * FROM customers;")?, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
                 Column::new("name", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
+                Column::unique("mail", DataType::Varchar(255)),
             ]),
             tuples: vec![
                 vec![
                     Value::Number(1),
-                    Value::String("John Doe".into()),
-                    Value::Number(18)
+                    Value::String("John".into()),
+                    Value::String("john@email.com".into()),
                 ],
                 vec![
                     Value::Number(2),
-                    Value::String("Updated Name".into()),
-                    Value::Number(20)
+                    Value::String("Jane".into()),
+                    Value::String("jane@email.com".into()),
                 ],
-                vec![
-                    Value::Number(3),
-                    Value::String("Updated Name".into()),
-                    Value::Number(20)
-                ]
-            ]
+            ],
         });
 
-        Ok(())
-    }
-
-    #[test]
-    fn update_no_match() -> Result<(), DbError> {
-        let mut db = init_database()?;
-
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (1, 'John Doe', 18);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (2, 'Jane Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES (3, 'Some Dude', 24);")?;
-
-        db.exec("UPDATE users SET age = 20, name = 'Updated Name' WHERE age > 50;")?;
+        // The index follows the renamed column and table.
+        assert!(db
+            .exec("INSERT INTO customers (id, name, mail) VALUES (3, 'Jim', 'john@email.com');")
+            .is_err());
 
-        let query = db.exec("SELECT * FROM users;")?;
+        assert_eq!(
+            db.exec("SELECT * FROM users;"),
+            Err(SqlError::InvalidTable("users".into()).into())
+        );
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
-            ]),
-            tuples: vec![
+        assert_eq!(
+            db.exec("SELECT name, table_name, sql FROM mkdb_meta;")?.tuples,
+            vec![
                 vec![
-                    Value::Number(1),
-                    Value::String("John Doe".into()),
-                    Value::Number(18)
+                    Value::String("customers".into()),
+                    Value::String("customers".into()),
+                    Value::String(
+                        Parser::new("CREATE TABLE customers (id INT PRIMARY KEY, name VARCHAR(255), mail VARCHAR(255) UNIQUE);")
+                            .parse_statement()?
+                            .to_sql_safe()
+                    ),
                 ],
                 vec![
-                    Value::Number(2),
-                    Value::String("Jane Doe".into()),
-                    Value::Number(22)
+                    Value::String("users_email_uq_index".into()),
+                    Value::String("customers".into()),
+                    Value::String(
+                        Parser::new("CREATE UNIQUE INDEX users_email_uq_index ON customers(mail);")
+                            .parse_statement()?
+                            .to_sql_safe()
+                    ),
                 ],
-                vec![
-                    Value::Number(3),
-                    Value::String("Some Dude".into()),
-                    Value::Number(24)
-                ]
             ]
-        });
+        );
 
         Ok(())
     }
 
-    // Test the collect plan.
-    #[cfg(not(miri))]
     #[test]
-    fn update_many() -> Result<(), DbError> {
+    fn multi_column_index() -> Result<(), DbError> {
         let mut db = init_database_with(DbConf {
-            page_size: 96,
-            cache_size: 512,
+            page_size: 256,
+            cache_size: 1024,
         })?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
-
-        let pk_range = 1..=500;
+        db.exec("CREATE TABLE items (id INT PRIMARY KEY, a INT, b VARCHAR(16));")?;
+        db.exec("CREATE UNIQUE INDEX items_a_b_index ON items(a, b);")?;
 
-        for i in pk_range.clone() {
-            let name = format!("User{i}");
+        for id in 1..=100 {
             db.exec(&format!(
-                "INSERT INTO users(id, name, age) VALUES ({i}, '{name}', 18);"
+                "INSERT INTO items (id, a, b) VALUES ({id}, {}, 'b{}');",
+                id / 10,
+                id % 10
             ))?;
         }
 
-        db.exec("UPDATE users SET name = 'Updated Name', age = 20;")?;
-
-        let query = db.exec("SELECT * FROM users;")?;
-
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
-            ]),
-            tuples: pk_range
-                .map(|pk| vec![
-                    Value::Number(pk),
-                    Value::String("Updated Name".into()),
-                    Value::Number(20)
-                ])
-                .collect()
-        });
-
-        Ok(())
-    }
-
-    #[test]
-    fn update_primary_key() -> Result<(), DbError> {
-        let mut db = init_database()?;
-
-        db.exec("CREATE TABLE users (name VARCHAR(255), age INT, id INT PRIMARY KEY, email VARCHAR(255) UNIQUE);")?;
-        db.exec(
-            "INSERT INTO users(id, name, email, age) VALUES (1, 'John Doe', 'john@doe.com', 18);",
-        )?;
-        db.exec(
-            "INSERT INTO users(id, name, email, age) VALUES (2, 'Jane Doe', 'jane@doe.com', 22);",
-        )?;
-        db.exec(
-            "INSERT INTO users(id, name, email, age) VALUES (3, 'Some Dude', 'some@dude.com', 24);",
-        )?;
+        let ids = |db: &mut Database<MemBuf>, sql: &str| -> Result<Vec<Value>, DbError> {
+            Ok(db
+                .exec(sql)?
+                .tuples
+                .into_iter()
+                .map(|row| row[0].clone())
+                .collect())
+        };
 
-        db.exec("UPDATE users SET id = 10 where id = 1;")?;
+        let numbers = |range: std::ops::RangeInclusive<i128>| -> Vec<Value> {
+            range.map(Value::Number).collect()
+        };
 
-        let query = db.exec("SELECT id, name, email, age FROM users;")?;
+        // Only the leading column is used to scan the index.
+        assert_eq!(
+            ids(&mut db, "SELECT id FROM items WHERE a = 5;")?,
+            numbers(50..=59)
+        );
+        assert_eq!(
+            ids(&mut db, "SELECT id FROM items WHERE a > 3 AND a <= 5;")?,
+            numbers(40..=59)
+        );
+        assert_eq!(
+            ids(&mut db, "SELECT id FROM items WHERE a = 5 AND b = 'b7';")?,
+            vec![Value::Number(57)]
+        );
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::unique("email", DataType::Varchar(255)),
-                Column::new("age", DataType::Int),
-            ]),
-            tuples: vec![
-                vec![
-                    Value::Number(10),
-                    Value::String("John Doe".into()),
-                    Value::String("john@doe.com".into()),
-                    Value::Number(18)
-                ],
-                vec![
-                    Value::Number(2),
-                    Value::String("Jane Doe".into()),
-                    Value::String("jane@doe.com".into()),
-                    Value::Number(22)
-                ],
-                vec![
-                    Value::Number(3),
-                    Value::String("Some Dude".into()),
-                    Value::String("some@dude.com".into()),
-                    Value::Number(24)
-                ]
-            ]
-        });
+        // The combination of columns must be unique, not each column.
+        assert_eq!(
+            db.exec("INSERT INTO items (id, a, b) VALUES (101, 5, 'b5');"),
+            Err(SqlError::DuplicatedKey {
+                table: "items".into(),
+                column: "(a, b)".into(),
+                value: Value::String("(5, \"b5\")".into()),
+            }
+            .into())
+        );
+        db.exec("INSERT INTO items (id, a, b) VALUES (101, 5, 'b10');")?;
 
-        assert_index_contains(&mut db, "users_pk_index", &[
-            vec![Value::Number(2), Value::Number(2)],
-            vec![Value::Number(3), Value::Number(3)],
-            vec![Value::Number(10), Value::Number(1)],
-        ])?;
+        db.exec("UPDATE items SET a = 20 WHERE id = 55;")?;
+        assert_eq!(ids(&mut db, "SELECT id FROM items WHERE a = 20;")?, vec![
+            Value::Number(55)
+        ]);
+        db.exec("INSERT INTO items (id, a, b) VALUES (102, 5, 'b5');")?;
 
-        assert_index_contains(&mut db, "users_email_uq_index", &[
-            vec![Value::String("jane@doe.com".into()), Value::Number(2)],
-            vec![Value::String("john@doe.com".into()), Value::Number(1)],
-            vec![Value::String("some@dude.com".into()), Value::Number(3)],
-        ])
+        db.exec("DELETE FROM items WHERE a = 5;")?;
+        assert!(ids(&mut db, "SELECT id FROM items WHERE a = 5;")?.is_empty());
+        assert_eq!(
+            ids(&mut db, "SELECT id FROM items WHERE a >= 4 AND a < 7;")?,
+            [numbers(40..=49), numbers(60..=69)].concat()
+        );
+
+        Ok(())
     }
 
     #[test]
-    fn update_pk_index_on_insert() -> Result<(), DbError> {
-        let mut db = init_database()?;
+    fn order_by_using_indexes() -> Result<(), DbError> {
+        let mut db = init_database_with(DbConf {
+            page_size: 256,
+            cache_size: 1024,
+        })?;
 
-        db.exec("CREATE TABLE users (id VARCHAR(3) PRIMARY KEY, name VARCHAR(255), age INT);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES ('abc', 'John Doe', 18);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES ('def', 'Jane Doe', 22);")?;
-        db.exec("INSERT INTO users(id, name, age) VALUES ('ghi', 'Some Dude', 24);")?;
+        db.exec("CREATE TABLE items (id INT PRIMARY KEY, price INT, name VARCHAR(16));")?;
+        db.exec("CREATE UNIQUE INDEX items_price_desc ON items(price DESC);")?;
+        db.exec("CREATE UNIQUE INDEX items_name_id ON items(name, id);")?;
 
-        assert_index_contains(&mut db, "users_pk_index", &[
-            vec![Value::String("abc".into()), Value::Number(1)],
-            vec![Value::String("def".into()), Value::Number(2)],
-            vec![Value::String("ghi".into()), Value::Number(3)],
-        ])
-    }
+        for id in 1..=100 {
+            db.exec(&format!(
+                "INSERT INTO items (id, price, name) VALUES ({id}, {}, 'item{}');",
+                id * 10,
+                id % 7
+            ))?;
+        }
 
-    #[test]
-    fn update_multiple_indexes_on_insert() -> Result<(), DbError> {
-        let mut db = init_database()?;
+        let ids = |db: &mut Database<MemBuf>, sql: &str| -> Result<Vec<i128>, DbError> {
+            Ok(db
+                .exec(sql)?
+                .tuples
+                .into_iter()
+                .map(|row| match row[0] {
+                    Value::Number(id) => id,
+                    _ => unreachable!(),
+                })
+                .collect())
+        };
 
-        db.exec(
-            "CREATE TABLE users (id VARCHAR(3) PRIMARY KEY, email VARCHAR(255) UNIQUE, name VARCHAR(64));",
-        )?;
-        db.exec("CREATE UNIQUE INDEX name_idx ON users(name);")?;
+        // Table BTree scanned backwards.
+        assert_eq!(
+            ids(&mut db, "SELECT id FROM items ORDER BY id DESC;")?,
+            Vec::from_iter((1..=100).rev())
+        );
+        assert_eq!(
+            ids(
+                &mut db,
+                "SELECT id FROM items WHERE id > 20 AND id <= 50 ORDER BY id DESC;"
+            )?,
+            Vec::from_iter((21..=50).rev())
+        );
 
-        db.exec("INSERT INTO users(id, name, email) VALUES ('ab', 'John Doe', 'john@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES ('cd', 'Jane Doe', 'jane@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES ('ef', 'Some Dude', 'some@dude.com');")?;
+        // DESC index scanned forwards and backwards.
+        assert_eq!(
+            ids(&mut db, "SELECT id FROM items ORDER BY price DESC LIMIT 5;")?,
+            vec![100, 99, 98, 97, 96]
+        );
+        assert_eq!(
+            ids(&mut db, "SELECT id FROM items ORDER BY price;")?,
+            Vec::from_iter(1..=100)
+        );
+        assert_eq!(
+            ids(
+                &mut db,
+                "SELECT id FROM items WHERE price >= 200 AND price < 300 ORDER BY price DESC;"
+            )?,
+            Vec::from_iter((20..30).rev())
+        );
+        assert_eq!(
+            ids(
+                &mut db,
+                "SELECT price FROM items WHERE price < 30 OR price > 980 ORDER BY price DESC;"
+            )?,
+            vec![1000, 990, 20, 10]
+        );
 
-        assert_index_contains(&mut db, "users_pk_index", &[
-            vec![Value::String("ab".into()), Value::Number(1)],
-            vec![Value::String("cd".into()), Value::Number(2)],
-            vec![Value::String("ef".into()), Value::Number(3)],
-        ])?;
+        // Prefix of a multi-column index.
+        let mut expected = Vec::from_iter(1..=100);
+        expected.sort_by_key(|id| (format!("item{}", id % 7), *id));
+        assert_eq!(
+            ids(&mut db, "SELECT id FROM items ORDER BY name, id;")?,
+            expected
+        );
 
-        assert_index_contains(&mut db, "users_email_uq_index", &[
-            vec![Value::String("jane@email.com".into()), Value::Number(2)],
-            vec![Value::String("john@email.com".into()), Value::Number(1)],
-            vec![Value::String("some@dude.com".into()), Value::Number(3)],
-        ])?;
+        expected.reverse();
+        assert_eq!(
+            ids(&mut db, "SELECT id FROM items ORDER BY name DESC, id DESC;")?,
+            expected
+        );
 
-        assert_index_contains(&mut db, "name_idx", &[
-            vec![Value::String("Jane Doe".into()), Value::Number(2)],
-            vec![Value::String("John Doe".into()), Value::Number(1)],
-            vec![Value::String("Some Dude".into()), Value::Number(3)],
-        ])
+        Ok(())
     }
 
     #[test]
-    fn update_indexed_columns_on_sql_update_statement() -> Result<(), DbError> {
+    fn explain_analyze() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec(
-            "CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE, name VARCHAR(64));",
-        )?;
-        db.exec("CREATE UNIQUE INDEX name_idx ON users(name);")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
 
-        db.exec("INSERT INTO users(id, name, email) VALUES (100, 'John Doe', 'john@email.com');")?;
-        db.exec("INSERT INTO users(id, name, email) VALUES (200, 'Jane Doe', 'jane@email.com');")?;
-        db.exec(
-            "INSERT INTO users(id, name, email) VALUES (300, 'Some Dude', 'some_dude@email.com');",
-        )?;
+        for id in 1..=10 {
+            db.exec(&format!(
+                "INSERT INTO users (id, name, age) VALUES ({id}, 'user{id}', {});",
+                id * 10
+            ))?;
+        }
 
-        db.exec("UPDATE users SET id = 3, email = 'some@dude.com' WHERE id = 300;")?;
-        db.exec("UPDATE users SET email = 'updated@email.com' WHERE id = 200;")?;
+        // Timings are not deterministic, compare everything else.
+        let analyze = |db: &mut Database<MemBuf>, sql: &str| -> Result<Vec<String>, DbError> {
+            let query = db.exec(sql)?;
+            assert_eq!(query.tuples.len(), 1);
+            let Value::String(plan) = &query.tuples[0][0] else {
+                unreachable!();
+            };
 
-        assert_index_contains(&mut db, "users_email_uq_index", &[
-            vec![Value::String("john@email.com".into()), Value::Number(100)],
-            vec![Value::String("some@dude.com".into()), Value::Number(3)],
-            vec![
-                Value::String("updated@email.com".into()),
-                Value::Number(200),
-            ],
-        ])?;
+            Ok(plan
+                .lines()
+                .map(|line| {
+                    let (node, time) = line.split_once(" time=").unwrap();
+                    assert!(time.ends_with("ms)"));
+                    node.to_owned()
+                })
+                .collect())
+        };
 
-        assert_index_contains(&mut db, "name_idx", &[
-            vec![Value::String("Jane Doe".into()), Value::Number(200)],
-            vec![Value::String("John Doe".into()), Value::Number(100)],
-            vec![Value::String("Some Dude".into()), Value::Number(3)],
-        ])
+        assert_eq!(
+            analyze(
+                &mut db,
+                "EXPLAIN ANALYZE SELECT name FROM users WHERE age > 50;"
+            )?,
+            [
+                "Project (name) (actual rows=5",
+                "    -> Filter (age > 50) (actual rows=5",
+                "        -> SeqScan on table 'users' (actual rows=10",
+            ]
+        );
+
+        // The statement is actually executed.
+        assert_eq!(
+            analyze(&mut db, "EXPLAIN ANALYZE DELETE FROM users WHERE id <= 3;")?,
+            [
+                "Delete from table 'users' (actual rows=3",
+                "    -> Collect (id, name, age) (actual rows=3",
+                "        -> RangeScan (id <= 3) on table 'users' (actual rows=3",
+            ]
+        );
+        assert_eq!(db.exec("SELECT * FROM users;")?.tuples.len(), 7);
+
+        Ok(())
     }
 
     #[test]
-    fn build_up_index_on_create_index_statement() -> Result<(), DbError> {
+    fn explain_plan_tree() -> Result<(), DbError> {
         let mut db = init_database()?;
 
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255));")?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        db.exec("CREATE UNIQUE INDEX users_name_age ON users(name, age DESC);")?;
+        db.exec("CREATE TABLE orders (id INT PRIMARY KEY, user_id INT);")?;
+
+        let explain = |db: &mut Database<MemBuf>, sql: &str| -> Result<String, DbError> {
+            let query = db.exec(sql)?;
+            assert_eq!(query.tuples.len(), 1);
+            match &query.tuples[0][0] {
+                Value::String(plan) => Ok(plan.clone()),
+                _ => unreachable!(),
+            }
+        };
 
-        db.exec("INSERT INTO users(id, email) VALUES (100, 'john@email.com');")?;
-        db.exec("INSERT INTO users(id, email) VALUES (200, 'jane@email.com');")?;
-        db.exec("INSERT INTO users(id, email) VALUES (300, 'some@dude.com');")?;
+        assert_eq!(
+            explain(&mut db, "EXPLAIN SELECT name FROM users WHERE age > 20;")?,
+            concat!(
+                "Project (name)\n",
+                "    -> Filter (age > 20)\n",
+                "        -> SeqScan on table 'users'\n",
+            )
+        );
 
-        db.exec("CREATE UNIQUE INDEX email_uq ON users(email);")?;
+        assert_eq!(
+            explain(
+                &mut db,
+                "EXPLAIN SELECT id FROM users WHERE id < 10 OR name = 'John';"
+            )?,
+            concat!(
+                "Project (id)\n",
+                "    -> Filter (id < 10 OR name = \"John\")\n",
+                "        -> KeyScan (id) on table 'users'\n",
+                "            -> Sort (id)\n",
+                "                -> LogicalOrScan\n",
+                "                    -> RangeScan (id < 10) on table 'users'\n",
+                "                    -> RangeScan (name = \"John\") on index 'users_name_age' (name, age DESC)\n",
+            )
+        );
 
-        assert_index_contains(&mut db, "email_uq", &[
-            vec![Value::String("jane@email.com".into()), Value::Number(200)],
-            vec![Value::String("john@email.com".into()), Value::Number(100)],
-            vec![Value::String("some@dude.com".into()), Value::Number(300)],
-        ])
+        assert_eq!(
+            explain(
+                &mut db,
+                "EXPLAIN SELECT users.name, orders.id FROM users JOIN orders ON users.id = orders.user_id;"
+            )?,
+            concat!(
+                "Project (users.name, orders.id)\n",
+                "    -> NestedLoopJoin (users.id = orders.user_id)\n",
+                "        -> SeqScan on table 'users'\n",
+                "        -> SeqScan on table 'orders'\n",
+            )
+        );
+
+        Ok(())
     }
 
     #[test]
-    fn drop_table() -> Result<(), DbError> {
-        let page_size = 1024;
-
+    fn reuse_free_pages() -> Result<(), DbError> {
         let mut db = init_database_with(DbConf {
-            page_size,
-            cache_size: page_size,
+            page_size: 96,
+            cache_size: 1024,
         })?;
 
-        let create_products_table = "CREATE TABLE products (id INT PRIMARY KEY, name VARCHAR(255), slug VARCHAR(255) UNIQUE);";
-        db.exec(create_products_table)?;
-
-        // Add some stuff to the products table to verify that it is not tampered with.
-        db.exec("INSERT INTO products (id, name, slug) VALUES (1, 'Test', 'test');")?;
-        db.exec("INSERT INTO products (id, name, slug) VALUES (2, 'Mouse', 'mouse');")?;
-        db.exec("INSERT INTO products (id, name, slug) VALUES (3, 'Keyboard', 'keyboard');")?;
-
-        let expected_used_pages = db.pager.borrow_mut().read_header()?.total_pages;
-
+        // Populate pages with some data.
         db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(1024), email VARCHAR(255) UNIQUE);")?;
-        db.exec("CREATE UNIQUE INDEX name_idx ON users(name);")?;
-
-        // 4 large entries with overflow pages for each page. 10 BTree pages total.
-        for i in 1..=(4 * 10) {
-            let name = format!("User{i}{}", String::from("_").repeat(page_size / 4));
+        for i in 1..=5 {
             db.exec(&format!(
-                "INSERT INTO users (id, name, email) VALUES ({i}, '{name}', 'user{i}@email.com');"
+                "INSERT INTO users (id, name, email) VALUES ({i}, 'User{i}', 'user{i}@email.com');"
             ))?;
         }
 
+        // Free everything.
         db.exec("DROP TABLE users;")?;
 
-        let mkdb_meta_query = db.exec("SELECT * FROM mkdb_meta;")?;
-        let products_query = db.exec("SELECT * FROM products;")?;
-
-        let db_header = db.pager.borrow_mut().read_header()?;
-        assert_eq!(
-            expected_used_pages,
-            db_header.total_pages - db_header.free_pages
-        );
+        // Now verify that we can reuse free pages.
+        db.exec("CREATE TABLE products (id INT PRIMARY KEY, name VARCHAR(255), slug VARCHAR(255) UNIQUE);")?;
+        db.exec("INSERT INTO products (id, name, slug) VALUES (1, 'Test', 'test');")?;
+        db.exec("INSERT INTO products (id, name, slug) VALUES (2, 'Mouse', 'mouse');")?;
+        db.exec("INSERT INTO products (id, name, slug) VALUES (3, 'Keyboard', 'keyboard');")?;
 
-        assert_eq!(
-            mkdb_meta_query,
-            QuerySet::new(mkdb_meta_schema(), vec![
-                vec![
-                    Value::String("table".into()),
-                    Value::String("products".into()),
-                    Value::Number(1),
-                    Value::String("products".into()),
-                    Value::String(
-                        Parser::new(create_products_table)
-                            .parse_statement()?
-                            .to_string()
-                    )
-                ],
-                vec![
-                    Value::String("index".into()),
-                    Value::String("products_slug_uq_index".into()),
-                    Value::Number(2),
-                    Value::String("products".into()),
-                    Value::String(
-                        Parser::new(
-                            "CREATE UNIQUE INDEX products_slug_uq_index ON products(slug);"
-                        )
-                        .parse_statement()?
-                        .to_string()
-                    )
-                ]
-            ])
-        );
+        let query = db.exec("SELECT * FROM products;")?;
 
-        assert_eq!(products_query, QuerySet {
+        assert_eq!(query, QuerySet {
             schema: Schema::new(vec![
                 Column::primary_key("id", DataType::Int),
                 Column::new("name", DataType::Varchar(255)),
@@ -3981,55 +7493,183 @@ mod tests {
     }
 
     #[test]
-    fn reuse_free_pages() -> Result<(), DbError> {
+    fn page_stats_after_delete() -> Result<(), DbError> {
+        let mut db = init_database_with(DbConf {
+            page_size: 96,
+            cache_size: 1024,
+        })?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);")?;
+        for i in 1..=100 {
+            db.exec(&format!(
+                "INSERT INTO users (id, name, email) VALUES ({i}, 'User{i}', 'user{i}@email.com');"
+            ))?;
+        }
+
+        let before = db.page_stats()?;
+
+        db.exec("DELETE FROM users WHERE id > 10;")?;
+
+        let after = db.page_stats()?;
+
+        assert_eq!(before.total_pages, after.total_pages);
+        assert!(after.free_pages > before.free_pages);
+        assert_eq!(before.tables.len(), 1);
+        assert_eq!(after.tables.len(), 1);
+        assert_eq!(after.tables[0].0, "users");
+        assert!(after.tables[0].1 < before.tables[0].1);
+
+        // Doesn't run queries behind the scenes.
+        db.full_scan_warning_threshold = Some(1);
+        db.exec("SELECT * FROM users WHERE name = 'User1';")?;
+        let (warnings, stats) = (db.warnings.clone(), db.last_plan_stats.clone());
+
+        db.page_stats()?;
+        assert_eq!(db.warnings, warnings);
+        assert_eq!(db.last_plan_stats, stats);
+
+        Ok(())
+    }
+
+    #[test]
+    fn vacuum_after_delete() -> Result<(), DbError> {
+        let mut db = init_database_with(DbConf {
+            page_size: 96,
+            cache_size: 1024,
+        })?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);")?;
+        for i in 1..=100 {
+            db.exec(&format!(
+                "INSERT INTO users (id, name, email) VALUES ({i}, 'User{i}', 'user{i}@email.com');"
+            ))?;
+        }
+
+        // Created last so that its pages, including overflow pages, are at the
+        // end of the file and have to be moved.
+        db.exec("CREATE TABLE posts (id INT PRIMARY KEY, body VARCHAR(255));")?;
+        for i in 1..=20 {
+            let body = format!("Post{i}").repeat(20);
+            db.exec(&format!(
+                "INSERT INTO posts (id, body) VALUES ({i}, '{body}');"
+            ))?;
+        }
+
+        db.exec("DELETE FROM users WHERE id > 10;")?;
+
+        let queries = [
+            "SELECT * FROM users;",
+            "SELECT * FROM users WHERE email = 'user5@email.com';",
+            "SELECT * FROM posts;",
+        ];
+
+        let results = queries
+            .iter()
+            .map(|query| db.exec(query))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let before = db.page_stats()?;
+
+        db.exec("VACUUM;")?;
+
+        let after = db.page_stats()?;
+
+        assert_eq!(after.total_pages, before.total_pages - before.free_pages);
+        assert_eq!(after.free_pages, 0);
+        assert_eq!(after.tables, before.tables);
+
+        for (query, result) in queries.iter().zip(results) {
+            assert_eq!(db.exec(query)?, result);
+        }
+
+        // The database is still usable.
+        db.exec("INSERT INTO users (id, name, email) VALUES (11, 'User11', 'user11@email.com');")?;
+        assert_eq!(db.exec("SELECT * FROM users;")?.tuples.len(), 11);
+
+        db.exec("START TRANSACTION;")?;
+        assert!(matches!(db.exec("VACUUM;"), Err(DbError::Other(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn delete_all_frees_pages_for_reuse() -> Result<(), DbError> {
         let mut db = init_database_with(DbConf {
             page_size: 96,
             cache_size: 1024,
         })?;
 
-        // Populate pages with some data.
-        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(1024), email VARCHAR(255) UNIQUE);")?;
-        for i in 1..=5 {
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);")?;
+
+        let insert_users = |db: &mut Database<MemBuf>| -> Result<(), DbError> {
+            for i in 1..=100 {
+                db.exec(&format!(
+                    "INSERT INTO users (id, name, email) VALUES ({i}, 'User{i}', 'user{i}@email.com');"
+                ))?;
+            }
+            Ok(())
+        };
+
+        insert_users(&mut db)?;
+        let full = db.page_stats()?;
+
+        db.exec("DELETE FROM users;")?;
+        let empty = db.page_stats()?;
+
+        // Only the roots of the table BTree and the index BTree are left.
+        assert_eq!(empty.tables, vec![(String::from("users"), 2)]);
+        assert_eq!(empty.total_pages, full.total_pages);
+        assert_eq!(empty.free_pages, full.free_pages + full.tables[0].1 - 2);
+
+        // Inserting the same rows again shouldn't grow the file.
+        insert_users(&mut db)?;
+        let refilled = db.page_stats()?;
+
+        assert_eq!(refilled.total_pages, full.total_pages);
+        assert_eq!(refilled.tables, full.tables);
+        assert_eq!(db.exec("SELECT * FROM users;")?.tuples.len(), 100);
+
+        Ok(())
+    }
+
+    #[test]
+    fn warn_full_scan_on_large_table() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);")?;
+        for i in 1..=20 {
             db.exec(&format!(
-                "INSERT INTO users (id, name, email) VALUES ({i}, 'User{i}', 'user{i}@email.com');"
+                "INSERT INTO users (id, name, age) VALUES ({i}, 'User{i}', {});",
+                i + 10
             ))?;
         }
 
-        // Free everything.
-        db.exec("DROP TABLE users;")?;
+        db.full_scan_warning_threshold = Some(10);
 
-        // Now verify that we can reuse free pages.
-        db.exec("CREATE TABLE products (id INT PRIMARY KEY, name VARCHAR(255), slug VARCHAR(255) UNIQUE);")?;
-        db.exec("INSERT INTO products (id, name, slug) VALUES (1, 'Test', 'test');")?;
-        db.exec("INSERT INTO products (id, name, slug) VALUES (2, 'Mouse', 'mouse');")?;
-        db.exec("INSERT INTO products (id, name, slug) VALUES (3, 'Keyboard', 'keyboard');")?;
+        // Rows are only known after ANALYZE.
+        db.exec("SELECT * FROM users WHERE age > 18;")?;
+        assert!(db.warnings.is_empty());
 
-        let query = db.exec("SELECT * FROM products;")?;
+        db.exec("ANALYZE users;")?;
 
-        assert_eq!(query, QuerySet {
-            schema: Schema::new(vec![
-                Column::primary_key("id", DataType::Int),
-                Column::new("name", DataType::Varchar(255)),
-                Column::unique("slug", DataType::Varchar(255)),
-            ]),
-            tuples: vec![
-                vec![
-                    Value::Number(1),
-                    Value::String("Test".into()),
-                    Value::String("test".into()),
-                ],
-                vec![
-                    Value::Number(2),
-                    Value::String("Mouse".into()),
-                    Value::String("mouse".into()),
-                ],
-                vec![
-                    Value::Number(3),
-                    Value::String("Keyboard".into()),
-                    Value::String("keyboard".into()),
-                ],
-            ]
-        });
+        db.exec("SELECT * FROM users WHERE age > 18;")?;
+        assert_eq!(db.warnings, vec![String::from(
+            "full scan of 'users' (20 rows); consider an index on 'age'"
+        )]);
+
+        let result = db.query("SELECT * FROM users WHERE age > 18;")?;
+        assert_eq!(result.warnings(), [String::from(
+            "full scan of 'users' (20 rows); consider an index on 'age'"
+        )]);
+        assert_eq!(result.count(), 12);
+
+        db.exec("SELECT * FROM users WHERE id = 5;")?;
+        assert!(db.warnings.is_empty());
+
+        db.full_scan_warning_threshold = Some(20);
+
+        db.exec("SELECT * FROM users WHERE age > 18;")?;
+        assert!(db.warnings.is_empty());
 
         Ok(())
     }
@@ -4043,8 +7683,11 @@ mod tests {
 
         assert_eq!(
             db.exec("SELECT incorrect_col, id, name FROM users;"),
-            Err(DbError::Sql(SqlError::InvalidColumn(
-                "incorrect_col".into()
+            Err(DbError::Sql(SqlError::AnalyzerError(
+                AnalyzerError::UnknownColumn {
+                    column: "incorrect_col".into(),
+                    clause: Clause::Select,
+                }
             )))
         );
 
@@ -4083,6 +7726,103 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn insert_null_into_not_null_column() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255) NOT NULL);")?;
+
+        assert_eq!(
+            db.exec("INSERT INTO users(id) VALUES (1);"),
+            Err(DbError::Sql(SqlError::AnalyzerError(
+                AnalyzerError::NullConstraintViolation("name".into())
+            )))
+        );
+
+        db.exec("INSERT INTO users(id, name) VALUES (1, 'John Doe');")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_multiple_rows() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
+        db.exec("INSERT INTO users(id, name) VALUES (2, 'Jane Doe'), (1, 'John Doe'), (3, 'Some Dude');")?;
+
+        assert_eq!(db.exec("SELECT * FROM users;")?.tuples, vec![
+            vec![Value::Number(1), Value::String("John Doe".into())],
+            vec![Value::Number(2), Value::String("Jane Doe".into())],
+            vec![Value::Number(3), Value::String("Some Dude".into())],
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_select() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE orders (id INT PRIMARY KEY, total INT, done BOOL);")?;
+        db.exec("CREATE TABLE archive (id INT PRIMARY KEY, total DOUBLE, source VARCHAR(255) DEFAULT 'orders');")?;
+        db.exec("INSERT INTO orders (id, total, done) VALUES (1, 10, TRUE), (2, 20, FALSE), (3, 30, TRUE);")?;
+
+        db.exec(
+            "INSERT INTO archive (id, total) SELECT id, total * 2 FROM orders WHERE done = TRUE;",
+        )?;
+
+        assert_eq!(db.exec("SELECT * FROM archive;")?.tuples, vec![
+            vec![
+                Value::Number(1),
+                Value::Float(20.0),
+                Value::String("orders".into())
+            ],
+            vec![
+                Value::Number(3),
+                Value::Float(60.0),
+                Value::String("orders".into())
+            ],
+        ]);
+
+        db.exec("INSERT INTO archive (total, id) SELECT SUM(total), COUNT(*) + 10 FROM orders;")?;
+
+        assert_eq!(
+            db.exec("SELECT * FROM archive WHERE id = 13;")?.tuples,
+            vec![vec![
+                Value::Number(13),
+                Value::Float(60.0),
+                Value::String("orders".into())
+            ]]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn insert_select_from_same_table() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE numbers (n INT);")?;
+        db.exec("INSERT INTO numbers (n) VALUES (1), (2), (3);")?;
+
+        // Would never end if the inserted rows were visible to the SELECT.
+        db.exec("INSERT INTO numbers SELECT n + 10 FROM numbers;")?;
+        db.exec("INSERT INTO numbers (n) VALUES (100);")?;
+
+        assert_eq!(db.exec("SELECT row_id, n FROM numbers;")?.tuples, vec![
+            vec![Value::Number(1), Value::Number(1)],
+            vec![Value::Number(2), Value::Number(2)],
+            vec![Value::Number(3), Value::Number(3)],
+            vec![Value::Number(4), Value::Number(11)],
+            vec![Value::Number(5), Value::Number(12)],
+            vec![Value::Number(6), Value::Number(13)],
+            vec![Value::Number(7), Value::Number(100)],
+        ]);
+
+        Ok(())
+    }
+
     #[test]
     fn insert_column_count_mismatch() -> Result<(), DbError> {
         let mut db = init_database()?;
@@ -4129,7 +7869,11 @@ mod tests {
 
         assert_eq!(
             dup,
-            Err(DbError::Sql(SqlError::DuplicatedKey(Value::Number(2))))
+            Err(DbError::Sql(SqlError::DuplicatedKey {
+                table: "users".into(),
+                column: "id".into(),
+                value: Value::Number(2),
+            }))
         );
 
         assert_eq!(query, QuerySet {
@@ -4171,9 +7915,11 @@ mod tests {
 
         assert_eq!(
             dup,
-            Err(DbError::Sql(SqlError::DuplicatedKey(Value::String(
-                "dup@email.com".into()
-            ))))
+            Err(DbError::Sql(SqlError::DuplicatedKey {
+                table: "users".into(),
+                column: "email".into(),
+                value: Value::String("dup@email.com".into()),
+            }))
         );
 
         assert_eq!(query, QuerySet {
@@ -4183,10 +7929,338 @@ mod tests {
                 Value::String("users".into()),
                 Value::Number(1),
                 Value::String("users".into()),
-                Value::String(Parser::new(create_table).parse_statement()?.to_string())
+                Value::String(Parser::new(create_table).parse_statement()?.to_sql_safe())
             ]]
         });
 
         Ok(())
     }
+
+    #[test]
+    fn unique_violation_reports_column_and_value() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE);")?;
+        db.exec("INSERT INTO users(id, email) VALUES (1, 'john@doe.com');")?;
+        db.exec("INSERT INTO users(id, email) VALUES (2, 'taken@x.com');")?;
+
+        let insert = db.exec("INSERT INTO users(id, email) VALUES (3, 'taken@x.com');");
+        let update = db.exec("UPDATE users SET email = 'john@doe.com' WHERE id = 2;");
+
+        let expected = SqlError::DuplicatedKey {
+            table: "users".into(),
+            column: "email".into(),
+            value: Value::String("taken@x.com".into()),
+        };
+
+        assert_eq!(
+            expected.to_string(),
+            "UNIQUE violation on users.email: \"taken@x.com\" already exists"
+        );
+        assert_eq!(insert, Err(DbError::Sql(expected)));
+        assert_eq!(
+            update,
+            Err(DbError::Sql(SqlError::DuplicatedKey {
+                table: "users".into(),
+                column: "email".into(),
+                value: Value::String("john@doe.com".into()),
+            }))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn committed_changes_survive_reopen() -> Result<(), DbError> {
+        // Small cache forces the pager to write pages before the transaction
+        // ends, big cache keeps everything in memory until then.
+        for (path, cache_size) in [("small_cache.mkdb", 3), ("big_cache.mkdb", 1024)] {
+            let conf = || DbConf {
+                page_size: 96,
+                cache_size,
+            };
+
+            let mut db = open_database_at(path, conf())?;
+            db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
+
+            let insert = |db: &mut Database<MemBuf>, ids: std::ops::Range<i128>| {
+                for id in ids {
+                    db.exec(&format!("INSERT INTO users VALUES ({id}, 'User {id}');"))?;
+                }
+
+                Ok::<_, DbError>(())
+            };
+
+            db.exec("START TRANSACTION;")?;
+            insert(&mut db, 1..20)?;
+            db.exec("COMMIT;")?;
+
+            db.exec("START TRANSACTION;")?;
+            insert(&mut db, 20..40)?;
+            db.exec("ROLLBACK;")?;
+
+            // Never committed, lost when the database is dropped.
+            db.exec("START TRANSACTION;")?;
+            insert(&mut db, 40..60)?;
+            drop(db);
+
+            let mut db = open_database_at(path, conf())?;
+
+            assert_eq!(
+                db.exec("SELECT id FROM users;")?.tuples,
+                (1..20)
+                    .map(|id| vec![Value::Number(id)])
+                    .collect::<Vec<_>>(),
+                "cache size {cache_size}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn compressed_database_survives_reopen() -> Result<(), DbError> {
+        let path = "compressed.mkdb";
+        let conf = || DbConf {
+            page_size: DEFAULT_PAGE_SIZE,
+            cache_size: DEFAULT_MAX_CACHE_SIZE,
+        };
+        let options = Database::options().compression(true);
+
+        let mut db = open_database_with(path, conf(), options)?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
+
+        db.exec("START TRANSACTION;")?;
+        for id in 1..200 {
+            db.exec(&format!("INSERT INTO users VALUES ({id}, 'User {id}');"))?;
+        }
+        db.exec("COMMIT;")?;
+
+        db.exec("UPDATE users SET name = 'Updated' WHERE id <= 100;")?;
+        db.exec("DELETE FROM users WHERE id > 150;")?;
+
+        let total_pages = db.pager.borrow_mut().read_header()?.total_pages as u64;
+        drop(db);
+
+        // Mostly empty pages, they should compress pretty well.
+        let file_len = MemBuf::open(path)?.seek(io::SeekFrom::End(0))?;
+        assert!(file_len < total_pages * DEFAULT_PAGE_SIZE as u64 / 2);
+
+        let mut db = open_database_with(path, conf(), options)?;
+
+        let expected = (1..=150)
+            .map(|id| {
+                let name = match id <= 100 {
+                    true => String::from("Updated"),
+                    false => format!("User {id}"),
+                };
+                vec![Value::Number(id), Value::String(name)]
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(db.exec("SELECT * FROM users;")?.tuples, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn queries_with_page_checksums() -> Result<(), DbError> {
+        let path = "checksums.mkdb";
+
+        let open = || {
+            let conf = DbConf {
+                page_size: 128,
+                cache_size: 3,
+            };
+
+            open_database_with(path, conf, Database::options().checksums(true))
+        };
+
+        let mut db = open()?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT UNIQUE);")?;
+
+        db.exec("START TRANSACTION;")?;
+        for id in 1..100 {
+            db.exec(&format!(
+                "INSERT INTO users VALUES ({id}, 'User {id}', {});",
+                id + 100
+            ))?;
+        }
+        db.exec("COMMIT;")?;
+
+        db.exec("START TRANSACTION;")?;
+        db.exec("DELETE FROM users WHERE id > 50;")?;
+        db.exec("ROLLBACK;")?;
+
+        db.exec("UPDATE users SET name = 'Updated' WHERE age < 110;")?;
+        db.exec("DELETE FROM users WHERE id >= 90;")?;
+
+        let query = "SELECT id, name FROM users WHERE age BETWEEN 105 AND 112;";
+        let expected = (5..=12)
+            .map(|id| {
+                let name = match id < 10 {
+                    true => String::from("Updated"),
+                    false => format!("User {id}"),
+                };
+                vec![Value::Number(id), Value::String(name)]
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(db.exec(query)?.tuples, expected);
+        drop(db);
+
+        let mut db = open()?;
+        assert_eq!(db.exec(query)?.tuples, expected);
+        assert_eq!(db.exec("SELECT COUNT(*) FROM users;")?.tuples, vec![vec![
+            Value::Number(89)
+        ]]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn corrupted_page_fails_checksum() -> Result<(), DbError> {
+        let path = "corrupted.mkdb";
+        let conf = || DbConf {
+            page_size: 128,
+            cache_size: 3,
+        };
+        let options = Database::options().checksums(true);
+
+        let mut db = open_database_with(path, conf(), options)?;
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
+        db.exec("INSERT INTO users VALUES (1, 'John Doe');")?;
+        let root = db.table_metadata("users")?.root;
+        drop(db);
+
+        // Flip one byte of the table root on disk.
+        let mut file = MemBuf::open(path)?;
+        let offset = (root as u64 + 1) * 128 - 32;
+        let mut byte = [0];
+        file.seek(io::SeekFrom::Start(offset))?;
+        file.read_exact(&mut byte)?;
+        file.seek(io::SeekFrom::Start(offset))?;
+        file.write_all(&[!byte[0]])?;
+
+        let mut db = open_database_with(path, conf(), options)?;
+        let error = db.exec("SELECT * FROM users;").unwrap_err();
+
+        assert!(matches!(
+            error,
+            DbError::Io(ref e) if e.get_ref().and_then(|e| e.downcast_ref::<CorruptPage>())
+                == Some(&CorruptPage { page_number: root })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rollback_to_savepoint() -> Result<(), DbError> {
+        // Small cache forces the pager to write pages modified after the
+        // savepoint before rolling back to it.
+        for cache_size in [3, 1024] {
+            let mut db = init_database_with(DbConf {
+                page_size: 96,
+                cache_size,
+            })?;
+
+            db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
+
+            let insert = |db: &mut Database<MemBuf>, ids: std::ops::Range<i128>| {
+                for id in ids {
+                    db.exec(&format!("INSERT INTO users VALUES ({id}, 'User {id}');"))?;
+                }
+
+                Ok::<_, DbError>(())
+            };
+
+            let ids = |ids: std::ops::Range<i128>| {
+                ids.map(|id| vec![Value::Number(id)]).collect::<Vec<_>>()
+            };
+
+            db.exec("START TRANSACTION;")?;
+            insert(&mut db, 1..10)?;
+            db.exec("SAVEPOINT first;")?;
+            insert(&mut db, 10..20)?;
+            db.exec("SAVEPOINT second;")?;
+            insert(&mut db, 20..30)?;
+
+            db.exec("ROLLBACK TO second;")?;
+            assert_eq!(db.exec("SELECT id FROM users;")?.tuples, ids(1..20));
+
+            // Rolling back to an older savepoint releases the newer ones.
+            db.exec("ROLLBACK TO SAVEPOINT first;")?;
+            assert_eq!(db.exec("SELECT id FROM users;")?.tuples, ids(1..10));
+            assert!(db.exec("ROLLBACK TO second;").is_err());
+
+            // Errors abort the transaction, rolling back to a savepoint
+            // makes it usable again.
+            insert(&mut db, 10..15)?;
+            assert!(db
+                .exec("INSERT INTO users VALUES (1, 'Duplicated');")
+                .is_err());
+            assert!(db.exec("SELECT id FROM users;").is_err());
+            db.exec("ROLLBACK TO first;")?;
+
+            db.exec("COMMIT;")?;
+            assert_eq!(db.exec("SELECT id FROM users;")?.tuples, ids(1..10));
+            assert!(db.exec("SAVEPOINT first;").is_err());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_computed_strings_that_dont_fit() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(10));")?;
+        db.exec("CREATE TABLE copies (id INT PRIMARY KEY, name VARCHAR(10));")?;
+        db.exec("INSERT INTO users (id, name) VALUES (1, 'abcdef');")?;
+
+        let too_long = || {
+            Err(DbError::from(AnalyzerError::ValueTooLong(
+                "abcdefabcdef".into(),
+                DataType::Varchar(10),
+            )))
+        };
+
+        assert_eq!(db.exec("UPDATE users SET name = name || name;"), too_long());
+        assert_eq!(
+            db.exec("INSERT INTO copies (id, name) SELECT id, name || name FROM users;"),
+            too_long()
+        );
+
+        assert_eq!(db.exec("SELECT name FROM users;")?.tuples, vec![vec![
+            Value::String("abcdef".into())
+        ]]);
+        assert!(db.exec("SELECT * FROM copies;")?.tuples.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn report_corrupted_strings_instead_of_panicking() -> Result<(), DbError> {
+        let mut db = init_database()?;
+
+        db.exec("CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));")?;
+        db.exec("INSERT INTO users (id, name) VALUES (1, 'hello');")?;
+
+        let root = db.table_metadata("users")?.root;
+
+        // Invalid UTF-8 bytes can't come from SQL, so write them directly to
+        // the page.
+        let mut pager = db.pager.borrow_mut();
+        let content = &mut pager.get_mut(root)?.cell_mut(0).content;
+        let start = content.windows(5).position(|w| w == b"hello").unwrap();
+        content[start] = 0xFF;
+        drop(pager);
+
+        assert_eq!(
+            db.exec("SELECT * FROM users;"),
+            Err(DbError::Io(io::Error::from(io::ErrorKind::InvalidData)))
+        );
+
+        Ok(())
+    }
 }
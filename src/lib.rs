@@ -26,8 +26,10 @@ mod vm;
 
 pub mod tcp;
 
-pub use db::{DbError, QuerySet};
-pub use sql::statement::Value;
+pub use db::{
+    Database, DbError, DbOptions, PageStats, QueryResult, QuerySet, RowStream, Schema, ScriptError,
+};
+pub use sql::{statement::Value, Limits};
 pub use storage::tuple::deserialize;
 
 pub type Result<T> = std::result::Result<T, DbError>;
@@ -0,0 +1,182 @@
+//! Tiny LZ77 style compression used for pages.
+//!
+//! The main project doesn't use any dependencies, so instead of pulling LZ4 or
+//! something similar we have our own dumb version of it. It's only meant to
+//! get rid of repeated bytes (free space in pages is usually zeroed and text
+//! columns tend to repeat a lot), not to compete with real algorithms.
+//!
+//! The compressed format is a sequence of instructions, each one starting with
+//! a control byte:
+//!
+//! ```text
+//! LITERALS                          MATCH
+//! +---------+---------------+       +---------+-----------------+
+//! | 0LLLLLLL | L + 1 bytes  |       | 1LLLLLLL | DISTANCE (u16) |
+//! +---------+---------------+       +---------+-----------------+
+//! ```
+//!
+//! Literals are copied as is. Matches copy `L + MIN_MATCH` bytes starting
+//! `DISTANCE` bytes behind the current output position. The source and the
+//! destination can overlap, which is how long runs of the same byte are
+//! encoded.
+
+use std::io;
+
+/// Minimum length of a match. Anything shorter is cheaper as literals.
+const MIN_MATCH: usize = 4;
+
+/// Maximum length of a match that fits in the control byte.
+const MAX_MATCH: usize = 0x7F + MIN_MATCH;
+
+/// Maximum number of literals that fit in the control byte.
+const MAX_LITERALS: usize = 0x80;
+
+/// Matches can't be further away than this.
+const MAX_DISTANCE: usize = u16::MAX as usize;
+
+/// Number of bits of the hash used to find previous occurrences.
+const HASH_BITS: u32 = 12;
+
+/// Hashes the first [`MIN_MATCH`] bytes of `bytes`.
+fn hash(bytes: &[u8]) -> usize {
+    let word = u32::from_le_bytes(bytes[..MIN_MATCH].try_into().unwrap());
+    (word.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+}
+
+/// Appends literal instructions for all the `literals` to `output`.
+fn write_literals(literals: &[u8], output: &mut Vec<u8>) {
+    for chunk in literals.chunks(MAX_LITERALS) {
+        output.push((chunk.len() - 1) as u8);
+        output.extend_from_slice(chunk);
+    }
+}
+
+/// Compresses `input` and appends the result to `output`.
+pub(super) fn compress(input: &[u8], output: &mut Vec<u8>) {
+    // Last position where each hash was found.
+    let mut positions = vec![usize::MAX; 1 << HASH_BITS];
+    let mut literals_start = 0;
+    let mut i = 0;
+
+    while i + MIN_MATCH <= input.len() {
+        let hash = hash(&input[i..]);
+        let candidate = positions[hash];
+        positions[hash] = i;
+
+        let is_match = candidate != usize::MAX
+            && i - candidate <= MAX_DISTANCE
+            && input[candidate..candidate + MIN_MATCH] == input[i..i + MIN_MATCH];
+
+        if !is_match {
+            i += 1;
+            continue;
+        }
+
+        let mut len = MIN_MATCH;
+        while len < MAX_MATCH && i + len < input.len() && input[candidate + len] == input[i + len] {
+            len += 1;
+        }
+
+        write_literals(&input[literals_start..i], output);
+        output.push(0x80 | (len - MIN_MATCH) as u8);
+        output.extend_from_slice(&((i - candidate) as u16).to_le_bytes());
+
+        i += len;
+        literals_start = i;
+    }
+
+    write_literals(&input[literals_start..], output);
+}
+
+/// Decompresses `input`, which must expand to exactly `len` bytes.
+pub(super) fn decompress(input: &[u8], len: usize) -> io::Result<Vec<u8>> {
+    let corrupted = || io::Error::new(io::ErrorKind::InvalidData, "corrupted compressed page");
+
+    let mut output = Vec::with_capacity(len);
+    let mut i = 0;
+
+    while i < input.len() {
+        let control = input[i] as usize;
+        i += 1;
+
+        if control & 0x80 == 0 {
+            let literals = input.get(i..i + control + 1).ok_or_else(corrupted)?;
+            output.extend_from_slice(literals);
+            i += literals.len();
+        } else {
+            let distance = input.get(i..i + 2).ok_or_else(corrupted)?;
+            let distance = u16::from_le_bytes(distance.try_into().unwrap()) as usize;
+            i += 2;
+
+            if distance == 0 || distance > output.len() {
+                return Err(corrupted());
+            }
+
+            // Byte by byte because the source can overlap the destination.
+            let start = output.len() - distance;
+            for j in 0..(control & 0x7F) + MIN_MATCH {
+                output.push(output[start + j]);
+            }
+        }
+
+        if output.len() > len {
+            return Err(corrupted());
+        }
+    }
+
+    if output.len() != len {
+        return Err(corrupted());
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::{compress, decompress};
+
+    fn round_trip(input: &[u8]) -> io::Result<Vec<u8>> {
+        let mut compressed = Vec::new();
+        compress(input, &mut compressed);
+
+        assert_eq!(decompress(&compressed, input.len())?, input);
+
+        Ok(compressed)
+    }
+
+    #[test]
+    fn compress_repeated_bytes() -> io::Result<()> {
+        let mut page = vec![0; 4096];
+        page[..26].copy_from_slice(b"abcdefghijklmnopqrstuvwxyz");
+        page[2048..2048 + 26].copy_from_slice(b"abcdefghijklmnopqrstuvwxyz");
+
+        assert!(round_trip(&page)?.len() < 256);
+
+        Ok(())
+    }
+
+    #[test]
+    fn compress_incompressible_bytes() -> io::Result<()> {
+        let input = (0..1000)
+            .map(|i: u32| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect::<Vec<u8>>();
+
+        round_trip(&input)?;
+        round_trip(&[])?;
+        round_trip(b"abc")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_corrupted_input() {
+        let mut compressed = Vec::new();
+        compress(&[7; 512], &mut compressed);
+
+        assert!(decompress(&compressed, 256).is_err());
+        assert!(decompress(&compressed[..compressed.len() - 1], 512).is_err());
+        assert!(decompress(&[0x80, 1, 0], 4).is_err());
+    }
+}
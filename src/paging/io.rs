@@ -1,16 +1,21 @@
 //! Block size based IO reading and writing.
 
 use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    fmt::{self, Display},
     fs::{self, File},
     io::{self, Read, Seek, SeekFrom, Write},
+    mem,
     path::Path,
 };
 
-use super::pager::PageNumber;
+use super::{compression, pager::PageNumber};
+use crate::storage::page::CELL_ALIGNMENT;
 
 /// Some common operations that we need to execute on files and are not provided
 /// by traits in [`std::io`]
-pub(crate) trait FileOps {
+pub trait FileOps {
     /// Creates a file on the filesystem at the given `path`.
     ///
     /// If the file already exists it should be truncated and if the parent
@@ -27,6 +32,9 @@ pub(crate) trait FileOps {
     /// Removes the file located at `path`.
     fn remove(path: impl AsRef<Path>) -> io::Result<()>;
 
+    /// Returns `true` if there's a file located at `path`.
+    fn exists(path: impl AsRef<Path>) -> bool;
+
     /// Truncates the file to 0 length.
     fn truncate(&mut self) -> io::Result<()>;
 
@@ -64,6 +72,10 @@ impl FileOps for File {
         fs::remove_file(path)
     }
 
+    fn exists(path: impl AsRef<Path>) -> bool {
+        path.as_ref().is_file()
+    }
+
     fn truncate(&mut self) -> io::Result<()> {
         self.set_len(0)
     }
@@ -74,36 +86,154 @@ impl FileOps for File {
     }
 }
 
-/// In-memory buffer with the same trait implementations as a normal disk file.
-///
-/// Used mainly for tests, although we could use this to simulate an in-memory
-/// database.
-pub(crate) type MemBuf = io::Cursor<Vec<u8>>;
+#[cfg(test)]
+pub(crate) use mem_buf::MemBuf;
+
+/// Simulated file system only used for tests.
+#[cfg(test)]
+mod mem_buf {
+    use std::{
+        cell::RefCell,
+        collections::HashMap,
+        io::{self, Read, Seek, SeekFrom, Write},
+        path::{Path, PathBuf},
+        rc::Rc,
+    };
+
+    use super::FileOps;
 
-impl FileOps for MemBuf {
-    fn create(_path: impl AsRef<Path>) -> io::Result<Self> {
-        Ok(io::Cursor::new(Vec::new()))
+    /// In-memory buffer with the same trait implementations as a normal disk
+    /// file.
+    ///
+    /// Only used for tests at the moment, although we could use this to
+    /// simulate an in-memory database.
+    ///
+    /// Buffers created through [`FileOps`] live in a simulated file system
+    /// (see [`MEM_FS`]), so [`FileOps::open`] returns a handle to the same
+    /// contents that were written by the handle obtained from
+    /// [`FileOps::create`]. Buffers created with [`MemBuf::default`] or
+    /// [`From<Vec<u8>>`] are anonymous, they don't belong to any path.
+    #[derive(Debug, Default, PartialEq)]
+    pub(crate) struct MemBuf {
+        /// File contents, shared by all the handles that point to the same path.
+        data: Rc<RefCell<Vec<u8>>>,
+        /// Cursor position of this handle.
+        position: u64,
     }
 
-    // TODO: HashMap of Path -> Cursor.
-    // That would allow us to simulate a file system for tests.
-    fn open(_path: impl AsRef<Path>) -> io::Result<Self> {
-        Ok(io::Cursor::new(Vec::new()))
+    thread_local! {
+        /// Simulated file system for [`MemBuf`].
+        ///
+        /// [`Rc`] can't be shared between threads so each thread gets its own file
+        /// system, which also means that tests running in parallel don't see each
+        /// other's files.
+        static MEM_FS: RefCell<HashMap<PathBuf, Rc<RefCell<Vec<u8>>>>> = RefCell::new(HashMap::new());
     }
 
-    fn truncate(&mut self) -> io::Result<()> {
-        self.set_position(0);
-        self.get_mut().clear();
+    impl MemBuf {
+        /// Returns a new handle to the given contents starting at position 0.
+        fn handle(data: &Rc<RefCell<Vec<u8>>>) -> Self {
+            Self {
+                data: Rc::clone(data),
+                position: 0,
+            }
+        }
 
-        Ok(())
+        /// Returns a copy of the file contents.
+        pub fn contents(&self) -> Vec<u8> {
+            self.data.borrow().clone()
+        }
     }
 
-    fn remove(_path: impl AsRef<Path>) -> io::Result<()> {
-        Ok(())
+    impl From<Vec<u8>> for MemBuf {
+        fn from(data: Vec<u8>) -> Self {
+            Self {
+                data: Rc::new(RefCell::new(data)),
+                position: 0,
+            }
+        }
     }
 
-    fn sync(&self) -> io::Result<()> {
-        Ok(())
+    impl Read for MemBuf {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let data = self.data.borrow();
+            let mut cursor = io::Cursor::new(data.as_slice());
+            cursor.set_position(self.position);
+
+            let read = cursor.read(buf)?;
+            self.position = cursor.position();
+
+            Ok(read)
+        }
+    }
+
+    impl Write for MemBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let mut data = self.data.borrow_mut();
+            let mut cursor = io::Cursor::new(&mut *data);
+            cursor.set_position(self.position);
+
+            let written = cursor.write(buf)?;
+            self.position = cursor.position();
+
+            Ok(written)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for MemBuf {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            let data = self.data.borrow();
+            let mut cursor = io::Cursor::new(data.as_slice());
+            cursor.set_position(self.position);
+
+            self.position = cursor.seek(pos)?;
+
+            Ok(self.position)
+        }
+    }
+
+    impl FileOps for MemBuf {
+        fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+            MEM_FS.with_borrow_mut(|fs| {
+                let data = fs.entry(path.as_ref().to_path_buf()).or_default();
+                data.borrow_mut().clear();
+
+                Ok(Self::handle(data))
+            })
+        }
+
+        fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+            MEM_FS.with_borrow(|fs| match fs.get(path.as_ref()) {
+                Some(data) => Ok(Self::handle(data)),
+                None => Err(io::Error::from(io::ErrorKind::NotFound)),
+            })
+        }
+
+        fn truncate(&mut self) -> io::Result<()> {
+            self.position = 0;
+            self.data.borrow_mut().clear();
+
+            Ok(())
+        }
+
+        fn remove(path: impl AsRef<Path>) -> io::Result<()> {
+            MEM_FS.with_borrow_mut(|fs| match fs.remove(path.as_ref()) {
+                Some(_) => Ok(()),
+                None => Err(io::Error::from(io::ErrorKind::NotFound)),
+            })
+        }
+
+        fn exists(path: impl AsRef<Path>) -> bool {
+            MEM_FS.with_borrow(|fs| fs.contains_key(path.as_ref()))
+        }
+
+        fn sync(&self) -> io::Result<()> {
+            Ok(())
+        }
     }
 }
 
@@ -136,6 +266,9 @@ impl FileOps for MemBuf {
 /// wraps an IO handle and operates on top of it, but instead of buffering
 /// reads and writes it returns full pages abstracting the blocks.
 ///
+/// The only exception is when blocks are bigger than pages. In that case the
+/// most recently used blocks are kept in memory, see [`BlockCache`].
+///
 /// See [`BlockIo::read`] for more details on how it works.
 #[derive(Debug, PartialEq)]
 pub(super) struct BlockIo<I> {
@@ -145,25 +278,445 @@ pub(super) struct BlockIo<I> {
     pub block_size: usize,
     /// High level page size.
     pub page_size: usize,
+    /// Blocks read recently when `block_size > page_size`.
+    blocks: BlockCache,
+    /// Store a checksum at the end of every page when writing and verify it
+    /// when reading. See [`Self::with_checksums`].
+    checksums: bool,
+    /// Location of compressed pages if compression is enabled. See
+    /// [`Self::with_compression`].
+    compression: Option<PageMap>,
+}
+
+/// Size of the checksum stored at the end of each page when checksums are
+/// enabled. See [`BlockIo::with_checksums`].
+pub(crate) const PAGE_CHECKSUM_SIZE: usize = mem::size_of::<u32>();
+
+/// Bytes reserved at the end of each page for the checksum.
+///
+/// The checksum only needs [`PAGE_CHECKSUM_SIZE`] bytes but the rest of the
+/// page must remain a multiple of [`CELL_ALIGNMENT`].
+pub(crate) const PAGE_RESERVED_SIZE: usize = CELL_ALIGNMENT;
+
+/// Error returned by [`BlockIo::read`] when the checksum of a page doesn't
+/// match its content, which means that the page was not written completely or
+/// the file was modified by someone else.
+///
+/// It's wrapped in an [`io::Error`] of kind [`io::ErrorKind::InvalidData`],
+/// use [`io::Error::get_ref`] and downcast it to find the corrupted page.
+#[derive(Debug, PartialEq)]
+pub(crate) struct CorruptPage {
+    pub page_number: PageNumber,
+}
+
+impl Display for CorruptPage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "page {} corrupt: checksum mismatch", self.page_number)
+    }
+}
+
+impl Error for CorruptPage {}
+
+/// Size of the superblock located at the beginning of compressed files. See
+/// [`BlockIo::with_compression`].
+const SUPERBLOCK_SIZE: usize = mem::size_of::<u64>() + mem::size_of::<u32>();
+
+/// Size of the header that precedes each compressed page. See
+/// [`BlockIo::with_compression`].
+const FRAME_HEADER_SIZE: usize = 4 * mem::size_of::<u32>() + mem::size_of::<u64>();
+
+/// The capacity of new frames is rounded up to a multiple of this so that
+/// pages can grow a little without moving somewhere else.
+const FRAME_ALIGNMENT: usize = 64;
+
+/// Page number of the frame that stores the [`PageMap`] itself.
+const PAGE_MAP_FRAME: PageNumber = PageNumber::MAX;
+
+/// Header of a compressed frame. See [`BlockIo::with_compression`].
+#[derive(Debug, PartialEq)]
+struct FrameHeader {
+    /// CRC-32 of everything that follows it, header and content.
+    checksum: u32,
+    /// Page stored in this frame.
+    page_number: PageNumber,
+    /// Bytes reserved for the content, which can be more than `len`.
+    capacity: usize,
+    /// Length of the compressed content.
+    len: usize,
+    /// When many frames of the same page are found the one with the highest
+    /// version is the most recent.
+    version: u64,
+}
+
+impl FrameHeader {
+    fn to_bytes(&self) -> [u8; FRAME_HEADER_SIZE] {
+        let mut bytes = [0; FRAME_HEADER_SIZE];
+
+        bytes[..4].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.page_number.to_le_bytes());
+        bytes[8..12].copy_from_slice(&(self.capacity as u32).to_le_bytes());
+        bytes[12..16].copy_from_slice(&(self.len as u32).to_le_bytes());
+        bytes[16..].copy_from_slice(&self.version.to_le_bytes());
+
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+        Self {
+            checksum: u32_at(0),
+            page_number: u32_at(4),
+            capacity: u32_at(8) as usize,
+            len: u32_at(12) as usize,
+            version: u64::from_le_bytes(bytes[16..FRAME_HEADER_SIZE].try_into().unwrap()),
+        }
+    }
+}
+
+/// Location of a frame in a compressed file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Slot {
+    /// Offset of the frame header.
+    offset: u64,
+    /// Bytes available for the content after the header.
+    capacity: usize,
+}
+
+/// Size of each page entry in the serialized [`PageMap`].
+const PAGE_MAP_ENTRY_SIZE: usize =
+    mem::size_of::<u32>() + mem::size_of::<u64>() + mem::size_of::<u32>();
+
+/// Size of each free slot in the serialized [`PageMap`].
+const FREE_SLOT_SIZE: usize = mem::size_of::<u64>() + mem::size_of::<u32>();
+
+/// Maps page numbers to their compressed content in the file.
+#[derive(Debug, Default, PartialEq)]
+struct PageMap {
+    /// Slot that holds the latest frame of each page.
+    frames: HashMap<PageNumber, Slot>,
+    /// Slots that don't hold the latest frame of any page and can be reused.
+    free: Vec<Slot>,
+    /// Slot of the frame that stores this map in the file, if any.
+    slot: Option<Slot>,
+    /// Offset where the next new slot will be located.
+    end: u64,
+    /// Version of the next frame written.
+    version: u64,
+    /// Whether the map has been loaded from the file already.
+    loaded: bool,
+    /// Whether the superblock doesn't point to an up to date copy of the map.
+    dirty: bool,
+}
+
+impl PageMap {
+    /// Finds a slot that can hold `len` bytes of content.
+    ///
+    /// Free slots are reused if possible, picking the smallest one that fits.
+    /// Otherwise a new slot is located at the end of the file.
+    fn alloc(&mut self, len: usize) -> Slot {
+        let best_fit = self
+            .free
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.capacity >= len)
+            .min_by_key(|(_, slot)| slot.capacity)
+            .map(|(index, _)| index);
+
+        if let Some(index) = best_fit {
+            return self.free.remove(index);
+        }
+
+        let slot = Slot {
+            offset: self.end,
+            capacity: len.next_multiple_of(FRAME_ALIGNMENT),
+        };
+
+        self.end += (FRAME_HEADER_SIZE + slot.capacity) as u64;
+
+        slot
+    }
+
+    /// Returns the slot where the next frame of `page_number` should be
+    /// written.
+    ///
+    /// If the current slot of the page is big enough the frame is overwritten
+    /// in place, otherwise the page moves to another slot and the current one
+    /// becomes free.
+    fn slot_for(&mut self, page_number: PageNumber, len: usize) -> Slot {
+        let current = self.frames.get(&page_number).copied();
+
+        if let Some(slot) = current.filter(|slot| slot.capacity >= len) {
+            return slot;
+        }
+
+        let slot = self.alloc(len);
+        self.free.extend(current);
+        self.frames.insert(page_number, slot);
+
+        slot
+    }
+
+    /// Serializes the map so that it can be stored in its own frame.
+    ///
+    /// ```text
+    /// +-----------+------------+--------------------------+------------------+
+    /// |    END    | PAGE COUNT |       PAGE ENTRIES       |    FREE SLOTS    |
+    /// | (8 bytes) | (4 bytes)  | NUMBER, OFFSET, CAPACITY | OFFSET, CAPACITY |
+    /// +-----------+------------+--------------------------+------------------+
+    /// ```
+    ///
+    /// The slot of the map itself is not included, it's found through the
+    /// superblock instead.
+    fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            mem::size_of::<u64>()
+                + mem::size_of::<u32>()
+                + self.frames.len() * PAGE_MAP_ENTRY_SIZE
+                + self.free.len() * FREE_SLOT_SIZE,
+        );
+
+        bytes.extend_from_slice(&self.end.to_le_bytes());
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+
+        for (page_number, slot) in &self.frames {
+            bytes.extend_from_slice(&page_number.to_le_bytes());
+            bytes.extend_from_slice(&slot.offset.to_le_bytes());
+            bytes.extend_from_slice(&(slot.capacity as u32).to_le_bytes());
+        }
+
+        for slot in &self.free {
+            bytes.extend_from_slice(&slot.offset.to_le_bytes());
+            bytes.extend_from_slice(&(slot.capacity as u32).to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Inverse of [`Self::serialize`]. `slot` and `header` belong to the frame
+    /// where the map was found.
+    fn deserialize(bytes: &[u8], slot: Slot, header: &FrameHeader) -> Self {
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+
+        let end = u64_at(0);
+        let total_pages = u32_at(8) as usize;
+        let entries_start = mem::size_of::<u64>() + mem::size_of::<u32>();
+        let free_start = entries_start + total_pages * PAGE_MAP_ENTRY_SIZE;
+
+        let frames = (0..total_pages)
+            .map(|i| {
+                let entry = entries_start + i * PAGE_MAP_ENTRY_SIZE;
+                (u32_at(entry), Slot {
+                    offset: u64_at(entry + 4),
+                    capacity: u32_at(entry + 12) as usize,
+                })
+            })
+            .collect();
+
+        // The map is serialized before finding a slot for it, so the list
+        // could still contain the slot it ended up in.
+        let free = (free_start..bytes.len())
+            .step_by(FREE_SLOT_SIZE)
+            .map(|entry| Slot {
+                offset: u64_at(entry),
+                capacity: u32_at(entry + 8) as usize,
+            })
+            .filter(|free| *free != slot)
+            .collect();
+
+        // Same as above, the slot could be new. The map is always the last
+        // frame written, so its version is the highest one.
+        Self {
+            frames,
+            free,
+            slot: Some(slot),
+            end: end.max(slot.offset + (FRAME_HEADER_SIZE + slot.capacity) as u64),
+            version: header.version + 1,
+            loaded: true,
+            dirty: false,
+        }
+    }
+}
+
+/// Lookup table for [`crc32`] computed at compile time.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < table.len() {
+        let mut crc = i as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+};
+
+/// Standard CRC-32 (the one used by zlib, PNG, etc) of the given bytes.
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, byte| {
+        CRC32_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Stores the checksum of the page content in its reserved region.
+fn write_checksum(page: &mut [u8]) {
+    let (content, reserved) = page.split_at_mut(page.len() - PAGE_RESERVED_SIZE);
+    reserved[..PAGE_CHECKSUM_SIZE].copy_from_slice(&crc32(content).to_le_bytes());
+}
+
+/// Fails with [`CorruptPage`] if the stored checksum doesn't match the
+/// content of the page.
+///
+/// Pages that were never written are all zeroes and don't have a checksum,
+/// so they're considered valid.
+fn verify_checksum(page_number: PageNumber, page: &[u8]) -> io::Result<()> {
+    let (content, reserved) = page.split_at(page.len() - PAGE_RESERVED_SIZE);
+    let checksum = &reserved[..PAGE_CHECKSUM_SIZE];
+
+    if u32::from_le_bytes(checksum.try_into().unwrap()) == crc32(content)
+        || page.iter().all(|byte| *byte == 0)
+    {
+        return Ok(());
+    }
+
+    Err(io::Error::new(io::ErrorKind::InvalidData, CorruptPage {
+        page_number,
+    }))
 }
 
+/// Default value of [`BlockCache::max_blocks`].
+pub(crate) const DEFAULT_MAX_CACHED_BLOCKS: usize = 8;
+
 impl<I> BlockIo<I> {
     pub fn new(io: I, page_size: usize, block_size: usize) -> Self {
         Self {
             io,
             block_size,
             page_size,
+            blocks: BlockCache::new(DEFAULT_MAX_CACHED_BLOCKS),
+            checksums: false,
+            compression: None,
+        }
+    }
+
+    /// Compresses pages before writing them to disk.
+    ///
+    /// Compressed pages don't have a fixed size, so they can't be located at
+    /// `page_number * page_size` anymore. Instead, each page is stored in a
+    /// frame somewhere in the file and a [`PageMap`] keeps track of where the
+    /// frame of each page is located:
+    ///
+    /// ```text
+    /// +------------+---------+---------+---------+-----
+    /// | SUPERBLOCK | FRAME 0 | FRAME 1 | FRAME 2 | ...
+    /// +------------+---------+---------+---------+-----
+    ///
+    ///                                  FRAME
+    /// +-----------+-----------+-----------+-----------+-----------+------------+--------+
+    /// | CHECKSUM  |   PAGE    | CAPACITY  |  LENGTH   |  VERSION  | COMPRESSED | UNUSED |
+    /// | (4 bytes) | (4 bytes) | (4 bytes) | (4 bytes) | (8 bytes) |  CONTENT   |        |
+    /// +-----------+-----------+-----------+-----------+-----------+------------+--------+
+    /// ```
+    ///
+    /// Each frame reserves some capacity for its content. When a page is
+    /// written again and the new content still fits in its frame it's
+    /// overwritten in place, otherwise the page moves to the smallest free
+    /// frame that can hold it or to a new frame at the end of the file, and
+    /// the old frame becomes free. Overwriting in place has the same problems
+    /// as writing uncompressed pages in place, torn frames are restored by the
+    /// journal.
+    ///
+    /// The map itself is stored in its own frame every time the file is
+    /// flushed, and the superblock at the beginning of the file points to it:
+    ///
+    /// ```text
+    /// +--------------------+-----------+
+    /// | PAGE MAP OFFSET    | CHECKSUM  |
+    /// |     (8 bytes)      | (4 bytes) |
+    /// +--------------------+-----------+
+    /// ```
+    ///
+    /// The first write after flushing zeroes the superblock, so if the
+    /// program crashes before the next flush the stored map is ignored. In
+    /// that case the map is rebuilt by scanning all the frames of the file.
+    /// Frames whose CRC-32 checksum doesn't match are considered free, and if
+    /// many frames contain the same page the one with the highest version
+    /// wins. Blocks are ignored in this mode, frames are not aligned to
+    /// anything.
+    ///
+    /// Uncompressed files can't be read in this mode and vice versa.
+    pub fn with_compression(mut self) -> Self {
+        self.compression = Some(PageMap::default());
+        self
+    }
+
+    /// Stores a CRC-32 checksum of every page when writing it and verifies it
+    /// when reading it back, which detects torn writes and corrupted files.
+    ///
+    /// The checksum is stored in the last [`PAGE_RESERVED_SIZE`] bytes of each
+    /// page on disk, so the buffers given to [`Self::read`] and [`Self::write`]
+    /// must be [`Self::content_size`] bytes long instead of [`Self::page_size`]:
+    ///
+    /// ```text
+    /// +--------------------------------------------+----------+
+    /// |               PAGE CONTENT                 | CHECKSUM |
+    /// |   (page_size - PAGE_RESERVED_SIZE bytes)   |          |
+    /// +--------------------------------------------+----------+
+    /// ```
+    ///
+    /// Same as compression, files written with checksums can't be read
+    /// without them and vice versa.
+    pub fn with_checksums(mut self) -> Self {
+        self.checksums = true;
+        self
+    }
+
+    /// Number of bytes at the end of each page that can't be used by the
+    /// upper layers.
+    pub fn reserved_size(&self) -> usize {
+        if self.checksums {
+            PAGE_RESERVED_SIZE
+        } else {
+            0
         }
     }
 
+    /// Size of the buffers that [`Self::read`] and [`Self::write`] work with.
+    pub fn content_size(&self) -> usize {
+        self.page_size - self.reserved_size()
+    }
+
+    /// Sets the maximum number of blocks kept in memory. `0` disables block
+    /// caching.
+    pub fn with_max_cached_blocks(mut self, max_cached_blocks: usize) -> Self {
+        self.blocks = BlockCache::new(max_cached_blocks);
+        self
+    }
+
     /// Some sanity checks for development.
     fn debug_assert_args_are_correct(&self, page_number: PageNumber, buf: &[u8]) {
         // We should always read and write an entire page.
         debug_assert!(
-            buf.len() == self.page_size,
+            buf.len() == self.content_size(),
             "buffer of incorrect length {} given for page size {}",
             buf.len(),
-            self.page_size
+            self.content_size()
         );
 
         // Used for development/debugging in case we mess up. Don't wanna create
@@ -175,6 +728,27 @@ impl<I> BlockIo<I> {
             self.page_size,
         );
     }
+
+    /// Computes the size of the buffer needed to read or write the given page,
+    /// the offset where that buffer starts in the file and the offset of the
+    /// page within the buffer.
+    ///
+    /// See [`Self::read`] for the alignment details.
+    fn offsets(&self, page_number: PageNumber) -> (usize, usize, usize) {
+        let page_number = page_number as usize;
+        let Self {
+            page_size,
+            block_size,
+            ..
+        } = *self;
+
+        if page_size >= block_size {
+            (page_size, page_size * page_number, 0)
+        } else {
+            let offset = (page_number * page_size) & !(block_size - 1);
+            (block_size, offset, page_number * page_size - offset)
+        }
+    }
 }
 
 impl<I: Seek + Read> BlockIo<I> {
@@ -252,70 +826,455 @@ impl<I: Seek + Read> BlockIo<I> {
     /// and a bitmask. Check [address alignment] for more details.
     ///
     /// [address alignment]: https://os.phil-opp.com/allocator-designs/#address-alignment
+    ///
+    /// If checksums are enabled the page is verified after reading it, see
+    /// [`Self::with_checksums`] and [`CorruptPage`].
     pub fn read(&mut self, page_number: PageNumber, buf: &mut [u8]) -> io::Result<usize> {
         self.debug_assert_args_are_correct(page_number, buf);
 
-        // Compute block offset and inner page offset.
-        let (capacity, block_offset, inner_offset) = {
-            let page_number = page_number as usize;
-            let Self {
-                page_size,
-                block_size,
-                ..
-            } = *self;
-
-            if page_size >= block_size {
-                (page_size, page_size * page_number, 0)
-            } else {
-                let offset = (page_number * page_size) & !(block_size - 1);
-                (block_size, offset, page_number * page_size - offset)
-            }
-        };
+        if !self.checksums {
+            return self.read_page(page_number, buf);
+        }
 
-        // Spin the disk... or let SSD transistors go brrr.
-        self.io.seek(SeekFrom::Start(block_offset as u64))?;
+        let mut page = vec![0; self.page_size];
+        let bytes_read = self.read_page(page_number, &mut page)?;
+
+        // Nothing to verify if the page doesn't exist yet.
+        if bytes_read > 0 {
+            verify_checksum(page_number, &page)?;
+        }
+
+        buf.copy_from_slice(&page[..buf.len()]);
+
+        Ok(bytes_read.min(buf.len()))
+    }
+
+    /// Same as [`Self::read`] but doesn't verify the checksum.
+    ///
+    /// Used to read the database header before knowing the actual page size
+    /// of the file, since the checksum can't be located without it.
+    pub fn read_unverified(
+        &mut self,
+        page_number: PageNumber,
+        buf: &mut [u8],
+    ) -> io::Result<usize> {
+        self.debug_assert_args_are_correct(page_number, buf);
+
+        let mut page = vec![0; self.page_size];
+        let bytes_read = self.read_page(page_number, &mut page)?;
+        buf.copy_from_slice(&page[..buf.len()]);
+
+        Ok(bytes_read.min(buf.len()))
+    }
+
+    /// Reads the entire page as stored on disk, including the reserved
+    /// region if any.
+    fn read_page(&mut self, page_number: PageNumber, buf: &mut [u8]) -> io::Result<usize> {
+        if self.compression.is_some() {
+            return self.read_compressed(page_number, buf);
+        }
+
+        // Compute block offset and inner page offset.
+        let (capacity, block_offset, inner_offset) = self.offsets(page_number);
 
         // Read page into memory.
         if self.page_size >= self.block_size {
+            // Spin the disk... or let SSD transistors go brrr.
+            self.io.seek(SeekFrom::Start(block_offset as u64))?;
             return self.io.read(buf);
         }
 
         // If the block size is greater than page size, we're reading multiple
-        // pages in one call. TODO: Find a way to cache all the pages, not just
-        // one.
-        let mut block = vec![0; capacity];
-        let _ = self.io.read(&mut block)?;
+        // pages in one call. Keep the block in memory so that we don't have to
+        // read it again when the next page is requested.
+        let block = match self.blocks.remove(block_offset) {
+            Some(block) => block,
+            None => self.read_block(block_offset, capacity)?,
+        };
+
         buf.copy_from_slice(&block[inner_offset..inner_offset + self.page_size]);
+        self.blocks.insert(block_offset, block);
+
+        Ok(self.page_size)
+    }
+
+    /// Loads the [`PageMap`] of a compressed file if it wasn't loaded
+    /// already.
+    ///
+    /// The map is read from the frame that the superblock points to. If the
+    /// superblock is not valid the map is rebuilt with [`Self::scan_frames`].
+    fn load_page_map(&mut self) -> io::Result<()> {
+        if self.compression.as_ref().is_some_and(|map| map.loaded) {
+            return Ok(());
+        }
+
+        let file_len = self.io.seek(SeekFrom::End(0))?;
+
+        if file_len >= SUPERBLOCK_SIZE as u64 {
+            let mut superblock = [0; SUPERBLOCK_SIZE];
+            self.io.seek(SeekFrom::Start(0))?;
+            self.io.read_exact(&mut superblock)?;
+
+            let (offset, checksum) = superblock.split_at(mem::size_of::<u64>());
+
+            if crc32(offset) == u32::from_le_bytes(checksum.try_into().unwrap()) {
+                let offset = u64::from_le_bytes(offset.try_into().unwrap());
+
+                if let Some((header, frame)) = self.read_frame(offset, file_len)? {
+                    if header.page_number == PAGE_MAP_FRAME && crc32(&frame[4..]) == header.checksum
+                    {
+                        let slot = Slot {
+                            offset,
+                            capacity: header.capacity,
+                        };
+
+                        let map = PageMap::deserialize(&frame[FRAME_HEADER_SIZE..], slot, &header);
+                        self.compression = Some(map);
+
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        self.scan_frames(file_len)
+    }
+
+    /// Rebuilds the [`PageMap`] by scanning all the frames in the file.
+    ///
+    /// The scan stops at the first frame that is incomplete, everything from
+    /// there onwards is overwritten by the next new frame.
+    fn scan_frames(&mut self, file_len: u64) -> io::Result<()> {
+        let mut map = PageMap {
+            loaded: true,
+            dirty: true,
+            ..Default::default()
+        };
+
+        let mut versions = HashMap::new();
+        let mut offset = SUPERBLOCK_SIZE as u64;
+
+        while let Some((header, frame)) = self.read_frame(offset, file_len)? {
+            if header.capacity == 0 {
+                break;
+            }
+
+            let slot = Slot {
+                offset,
+                capacity: header.capacity,
+            };
+
+            let is_latest = header.page_number != PAGE_MAP_FRAME
+                && crc32(&frame[4..]) == header.checksum
+                && versions
+                    .get(&header.page_number)
+                    .is_none_or(|version| header.version > *version);
+
+            if is_latest {
+                versions.insert(header.page_number, header.version);
+                map.free.extend(map.frames.insert(header.page_number, slot));
+            } else {
+                map.free.push(slot);
+            }
+
+            map.version = map.version.max(header.version + 1);
+            offset += (FRAME_HEADER_SIZE + header.capacity) as u64;
+        }
+
+        map.end = offset;
+        self.compression = Some(map);
+
+        Ok(())
+    }
+
+    /// Reads the header and content of the frame located at `offset`. Returns
+    /// [`None`] if the frame is not complete.
+    fn read_frame(
+        &mut self,
+        offset: u64,
+        file_len: u64,
+    ) -> io::Result<Option<(FrameHeader, Vec<u8>)>> {
+        if offset + FRAME_HEADER_SIZE as u64 > file_len {
+            return Ok(None);
+        }
+
+        let mut frame = vec![0; FRAME_HEADER_SIZE];
+        self.io.seek(SeekFrom::Start(offset))?;
+        self.io.read_exact(&mut frame)?;
+
+        let header = FrameHeader::from_bytes(&frame);
+
+        if header.len > header.capacity
+            || offset + (FRAME_HEADER_SIZE + header.len) as u64 > file_len
+        {
+            return Ok(None);
+        }
+
+        frame.resize(FRAME_HEADER_SIZE + header.len, 0);
+        self.io.read_exact(&mut frame[FRAME_HEADER_SIZE..])?;
+
+        Ok(Some((header, frame)))
+    }
+
+    /// Reads and decompresses the frame of the given page. See
+    /// [`Self::with_compression`].
+    fn read_compressed(&mut self, page_number: PageNumber, buf: &mut [u8]) -> io::Result<usize> {
+        self.load_page_map()?;
+
+        let map = self.compression.as_ref().unwrap();
+
+        // Same as reading past the end of an uncompressed file.
+        let Some(slot) = map.frames.get(&page_number).copied() else {
+            return Ok(0);
+        };
+
+        let file_len = self.io.seek(SeekFrom::End(0))?;
+
+        let frame = match self.read_frame(slot.offset, file_len)? {
+            Some((header, frame)) if crc32(&frame[4..]) == header.checksum => frame,
+            _ => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, CorruptPage {
+                    page_number,
+                }))
+            }
+        };
+
+        buf.copy_from_slice(&compression::decompress(
+            &frame[FRAME_HEADER_SIZE..],
+            self.page_size,
+        )?);
 
         Ok(self.page_size)
     }
+
+    /// Reads the entire block located at `block_offset`.
+    ///
+    /// The block might not exist yet or it could be the last one in the file
+    /// and be partially written. Whatever we can't read is zeroed.
+    fn read_block(&mut self, block_offset: usize, capacity: usize) -> io::Result<Box<[u8]>> {
+        self.io.seek(SeekFrom::Start(block_offset as u64))?;
+
+        let mut block = vec![0; capacity].into_boxed_slice();
+        let mut bytes_read = 0;
+        while bytes_read < capacity {
+            match self.io.read(&mut block[bytes_read..])? {
+                0 => break,
+                n => bytes_read += n,
+            }
+        }
+
+        Ok(block)
+    }
 }
 
-impl<I: Seek + Write> BlockIo<I> {
+impl<I: Seek + Read + Write> BlockIo<I> {
     /// Writes the page to disk. See also [`Self::read`] for more details.
+    ///
+    /// When the block size is greater than the page size we always write entire
+    /// blocks, otherwise some filesystems could end up with a "torn" block
+    /// that's only partially written if something fails. That requires reading
+    /// the block first in order to preserve the rest of pages that are stored
+    /// in it, so a single page write is actually a read-modify-write cycle.
+    /// The "read" part is skipped if the block is cached, and the cached block
+    /// is updated with the new page.
+    ///
+    /// If checksums are enabled the checksum of `buf` is written after it,
+    /// see [`Self::with_checksums`].
     pub fn write(&mut self, page_number: PageNumber, buf: &[u8]) -> io::Result<usize> {
         self.debug_assert_args_are_correct(page_number, buf);
 
-        // TODO: Just like [`Self::read`], when the block size is greater than
-        // the page size we should be writing multiple pages at once.
-        let offset = self.page_size * page_number as usize;
-        self.io.seek(SeekFrom::Start(offset as u64))?;
+        if !self.checksums {
+            return self.write_page(page_number, buf);
+        }
+
+        let mut page = vec![0; self.page_size];
+        page[..buf.len()].copy_from_slice(buf);
+        write_checksum(&mut page);
+
+        let written = self.write_page(page_number, &page)?;
+
+        Ok(written.min(buf.len()))
+    }
+
+    /// Writes the entire page as stored on disk, including the reserved
+    /// region if any.
+    fn write_page(&mut self, page_number: PageNumber, buf: &[u8]) -> io::Result<usize> {
+        if self.compression.is_some() {
+            return self.write_compressed(page_number, buf);
+        }
+
+        let (capacity, block_offset, inner_offset) = self.offsets(page_number);
 
         // TODO: If page_size > block_size check if all blocks need to be written
-        self.io.write(buf)
+        if self.page_size >= self.block_size {
+            self.io.seek(SeekFrom::Start(block_offset as u64))?;
+            return self.io.write(buf);
+        }
+
+        let mut block = match self.blocks.remove(block_offset) {
+            Some(block) => block,
+            None => self.read_block(block_offset, capacity)?,
+        };
+
+        let page = &mut block[inner_offset..inner_offset + self.page_size];
+        page.copy_from_slice(buf);
+
+        self.io.seek(SeekFrom::Start(block_offset as u64))?;
+        self.io.write_all(&block)?;
+
+        self.blocks.insert(block_offset, block);
+
+        Ok(self.page_size)
+    }
+
+    /// Compresses the page and writes its frame. See
+    /// [`Self::with_compression`].
+    fn write_compressed(&mut self, page_number: PageNumber, buf: &[u8]) -> io::Result<usize> {
+        self.load_page_map()?;
+
+        let mut content = Vec::with_capacity(self.page_size);
+        compression::compress(buf, &mut content);
+
+        // The stored map won't be up to date anymore after this write.
+        if !self.compression.as_ref().unwrap().dirty {
+            self.io.seek(SeekFrom::Start(0))?;
+            self.io.write_all(&[0; SUPERBLOCK_SIZE])?;
+            self.compression.as_mut().unwrap().dirty = true;
+        }
+
+        let slot = self
+            .compression
+            .as_mut()
+            .unwrap()
+            .slot_for(page_number, content.len());
+
+        self.write_frame(page_number, slot, &content)?;
+
+        Ok(self.page_size)
     }
 }
 
-impl<I: Write> BlockIo<I> {
+impl<I: Seek + Write> BlockIo<I> {
+    /// Writes a frame with the given content in `slot`.
+    fn write_frame(
+        &mut self,
+        page_number: PageNumber,
+        slot: Slot,
+        content: &[u8],
+    ) -> io::Result<()> {
+        let map = self.compression.as_mut().unwrap();
+
+        let mut header = FrameHeader {
+            checksum: 0,
+            page_number,
+            capacity: slot.capacity,
+            len: content.len(),
+            version: map.version,
+        };
+
+        map.version += 1;
+
+        let mut frame = Vec::from(header.to_bytes());
+        frame.extend_from_slice(content);
+        header.checksum = crc32(&frame[4..]);
+        frame[..FRAME_HEADER_SIZE].copy_from_slice(&header.to_bytes());
+
+        self.io.seek(SeekFrom::Start(slot.offset))?;
+        self.io.write_all(&frame)
+    }
+
+    /// Stores the [`PageMap`] of a compressed file in its own frame and
+    /// points the superblock to it. See [`Self::with_compression`].
+    fn write_page_map(&mut self) -> io::Result<()> {
+        let Some(map) = self.compression.as_mut().filter(|map| map.dirty) else {
+            return Ok(());
+        };
+
+        // The superblock is already invalid, so the previous copy of the map
+        // is not needed anymore.
+        map.free.extend(map.slot.take());
+        let content = map.serialize();
+        let slot = map.alloc(content.len());
+        map.slot = Some(slot);
+
+        self.write_frame(PAGE_MAP_FRAME, slot, &content)?;
+
+        let offset = slot.offset.to_le_bytes();
+        let mut superblock = [0; SUPERBLOCK_SIZE];
+        superblock[..offset.len()].copy_from_slice(&offset);
+        superblock[offset.len()..].copy_from_slice(&crc32(&offset).to_le_bytes());
+
+        self.io.seek(SeekFrom::Start(0))?;
+        self.io.write_all(&superblock)?;
+
+        self.compression.as_mut().unwrap().dirty = false;
+
+        Ok(())
+    }
+
     /// Flush buffered contents.
     ///
     /// This does not guarantee that the contents reach the filesystem. Use
     /// [`Self::sync`] after flushing.
+    ///
+    /// Compressed files also store their [`PageMap`] at this point.
     pub fn flush(&mut self) -> io::Result<()> {
+        self.write_page_map()?;
         self.io.flush()
     }
 }
 
+/// Small LRU cache of raw blocks used by [`BlockIo`] when the block size is
+/// greater than the page size.
+///
+/// Unlike [`super::cache::Cache`], which stores pages that are about to be
+/// parsed and modified, this only avoids reading the same block from disk over
+/// and over again when its pages are requested one by one. It's meant to hold
+/// just a handful of blocks, so a linear search is fine.
+#[derive(Debug, PartialEq)]
+struct BlockCache {
+    /// Blocks and their offset in the file. The most recently used block is
+    /// the last one.
+    blocks: VecDeque<(usize, Box<[u8]>)>,
+    /// Maximum number of blocks in the cache.
+    max_blocks: usize,
+}
+
+impl BlockCache {
+    fn new(max_blocks: usize) -> Self {
+        Self {
+            blocks: VecDeque::with_capacity(max_blocks),
+            max_blocks,
+        }
+    }
+
+    /// Takes the block located at `offset` out of the cache. Blocks are
+    /// inserted back with [`Self::insert`] once used, which makes them the
+    /// most recently used ones.
+    fn remove(&mut self, offset: usize) -> Option<Box<[u8]>> {
+        let index = self
+            .blocks
+            .iter()
+            .position(|(cached, _)| *cached == offset)?;
+        self.blocks.remove(index).map(|(_, block)| block)
+    }
+
+    /// Adds the block to the cache evicting the least recently used one if
+    /// the cache is full. The block must have been removed before with
+    /// [`Self::remove`] if it was already cached.
+    fn insert(&mut self, offset: usize, block: Box<[u8]>) {
+        if self.max_blocks == 0 {
+            return;
+        }
+
+        if self.blocks.len() >= self.max_blocks {
+            self.blocks.pop_front();
+        }
+
+        self.blocks.push_back((offset, block));
+    }
+}
+
 impl<I: FileOps> BlockIo<I> {
     /// See [`Sync`] for details.
     pub fn sync(&self) -> io::Result<()> {
@@ -325,9 +1284,50 @@ impl<I: FileOps> BlockIo<I> {
 
 #[cfg(test)]
 mod tests {
-    use std::io;
+    use std::io::{self, Read, Seek, SeekFrom, Write};
+
+    use super::{crc32, BlockIo, CorruptPage, FileOps, MemBuf, PAGE_RESERVED_SIZE};
 
-    use super::BlockIo;
+    /// Counts the calls to the underlying IO resource.
+    #[derive(Default)]
+    struct CountingIo {
+        inner: io::Cursor<Vec<u8>>,
+        seeks: usize,
+        reads: usize,
+    }
+
+    impl Read for CountingIo {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reads += 1;
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for CountingIo {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.seeks += 1;
+            self.inner.seek(pos)
+        }
+    }
+
+    impl Write for CountingIo {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.inner.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.inner.flush()
+        }
+    }
+
+    /// Creates a file with 2 blocks of 4 pages each filled with the page
+    /// number + 1.
+    fn counting_io_with_two_blocks() -> CountingIo {
+        CountingIo {
+            inner: io::Cursor::new(Vec::from_iter((0..8).flat_map(|page| [page + 1; 4]))),
+            ..Default::default()
+        }
+    }
 
     #[test]
     fn block_io() -> io::Result<()> {
@@ -352,4 +1352,349 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn write_preserves_other_pages_in_the_same_block() -> io::Result<()> {
+        let (page_size, block_size) = (4, 16);
+        let pages_per_block = block_size / page_size;
+
+        let mut io = BlockIo::new(io::Cursor::new(Vec::new()), page_size, block_size);
+
+        // Write the pages of the first two blocks in reverse order so that
+        // every write lands on a block that already contains other pages.
+        for i in (0..pages_per_block * 2).rev() {
+            assert_eq!(
+                io.write(i as _, &vec![(i + 1) as u8; page_size])?,
+                page_size
+            );
+        }
+
+        // Overwrite one page in the middle of the first block.
+        io.write(1, &[0xFF; 4])?;
+
+        for i in 0..pages_per_block * 2 {
+            let expected = if i == 1 {
+                vec![0xFF; page_size]
+            } else {
+                vec![(i + 1) as u8; page_size]
+            };
+
+            let mut buf = vec![0; page_size];
+            io.read(i as _, &mut buf)?;
+            assert_eq!(buf, expected, "page {i} was clobbered");
+        }
+
+        // Only full blocks are written.
+        assert_eq!(io.io.get_ref().len(), block_size * 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_sibling_pages_from_cached_block() -> io::Result<()> {
+        let mut io = BlockIo::new(counting_io_with_two_blocks(), 4, 16);
+        let mut buf = vec![0; 4];
+
+        io.read(0, &mut buf)?;
+        assert_eq!(buf, [1; 4]);
+        io.read(1, &mut buf)?;
+        assert_eq!(buf, [2; 4]);
+
+        assert_eq!((io.io.seeks, io.io.reads), (1, 1));
+
+        // Different block.
+        io.read(4, &mut buf)?;
+        assert_eq!(buf, [5; 4]);
+        assert_eq!((io.io.seeks, io.io.reads), (2, 2));
+
+        // Writes update the cached block instead of reading it again.
+        io.write(2, &[0xFF; 4])?;
+        io.read(2, &mut buf)?;
+        assert_eq!(buf, [0xFF; 4]);
+        assert_eq!(io.io.reads, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn evict_least_recently_used_block() -> io::Result<()> {
+        let mut io = BlockIo::new(counting_io_with_two_blocks(), 4, 16).with_max_cached_blocks(1);
+        let mut buf = vec![0; 4];
+
+        for (page, expected_reads) in [(0, 1), (1, 1), (4, 2), (0, 3)] {
+            io.read(page, &mut buf)?;
+            assert_eq!(buf, [page as u8 + 1; 4]);
+            assert_eq!(io.io.reads, expected_reads);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn disable_block_cache() -> io::Result<()> {
+        let mut io = BlockIo::new(counting_io_with_two_blocks(), 4, 16).with_max_cached_blocks(0);
+        let mut buf = vec![0; 4];
+
+        io.read(0, &mut buf)?;
+        io.read(1, &mut buf)?;
+        assert_eq!(buf, [2; 4]);
+        assert_eq!(io.io.reads, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn open_mem_buf_created_at_the_same_path() -> io::Result<()> {
+        let mut file = MemBuf::create("db/file")?;
+        file.write_all(&[1, 2, 3, 4])?;
+
+        let mut reopened = MemBuf::open("db/file")?;
+        let mut buf = Vec::new();
+        reopened.read_to_end(&mut buf)?;
+        assert_eq!(buf, [1, 2, 3, 4]);
+
+        // Both handles share the contents but not the cursor position.
+        file.write_all(&[5])?;
+        assert_eq!(reopened.contents(), [1, 2, 3, 4, 5]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn truncate_existing_mem_buf_on_create() -> io::Result<()> {
+        MemBuf::create("file")?.write_all(&[1, 2, 3, 4])?;
+        MemBuf::create("file")?;
+
+        assert_eq!(MemBuf::open("file")?.contents(), []);
+
+        Ok(())
+    }
+
+    #[test]
+    fn remove_mem_buf() -> io::Result<()> {
+        MemBuf::create("file")?;
+        assert!(MemBuf::exists("file"));
+
+        MemBuf::remove("file")?;
+        assert!(!MemBuf::exists("file"));
+        assert_eq!(
+            MemBuf::open("file").map_err(|e| e.kind()),
+            Err(io::ErrorKind::NotFound)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    /// Writes pages 0 and 1 with checksums enabled, flips a byte of page 1 on
+    /// "disk" and returns the error obtained when reading it back.
+    fn read_corrupted_page(page_size: usize, block_size: usize) -> io::Result<io::Error> {
+        let mut io = BlockIo::new(io::Cursor::new(Vec::new()), page_size, block_size)
+            .with_max_cached_blocks(0)
+            .with_checksums();
+
+        let content_size = page_size - PAGE_RESERVED_SIZE;
+
+        io.write(0, &vec![1; content_size])?;
+        io.write(1, &vec![2; content_size])?;
+
+        // The content is not modified, the checksum goes after it.
+        assert_eq!(io.io.get_ref()[..content_size], vec![1; content_size]);
+
+        let mut buf = vec![0; content_size];
+        io.read(0, &mut buf)?;
+        assert_eq!(buf, vec![1; content_size]);
+
+        io.io.get_mut()[page_size + 6] ^= 0xFF;
+
+        Ok(io.read(1, &mut buf).unwrap_err())
+    }
+
+    #[test]
+    fn detect_corrupted_page() -> io::Result<()> {
+        for (page_size, block_size) in [(16, 16), (16, 4), (16, 64)] {
+            let error = read_corrupted_page(page_size, block_size)?;
+
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(
+                error.get_ref().unwrap().downcast_ref::<CorruptPage>(),
+                Some(&CorruptPage { page_number: 1 })
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn dont_verify_unwritten_pages() -> io::Result<()> {
+        let mut io = BlockIo::new(io::Cursor::new(Vec::new()), 16, 64).with_checksums();
+
+        io.write(0, &[1; 8])?;
+
+        let mut buf = vec![0; 8];
+        io.read(1, &mut buf)?;
+        assert_eq!(buf, [0; 8]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_and_write_compressed_pages() -> io::Result<()> {
+        let mut io = BlockIo::new(io::Cursor::new(Vec::new()), 64, 64).with_compression();
+
+        io.write(0, &[1; 64])?;
+        io.write(1, &[2; 64])?;
+        io.write(0, &[3; 64])?;
+
+        let mut buf = vec![0; 64];
+        io.read(0, &mut buf)?;
+        assert_eq!(buf, [3; 64]);
+        io.read(1, &mut buf)?;
+        assert_eq!(buf, [2; 64]);
+        assert_eq!(io.read(2, &mut buf)?, 0);
+
+        assert!(io.io.get_ref().len() < 3 * 64);
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_compressed_pages_from_existing_file() -> io::Result<()> {
+        let mut io = BlockIo::new(io::Cursor::new(Vec::new()), 64, 64).with_compression();
+        io.write(0, &[1; 64])?;
+        io.write(1, &[2; 64])?;
+
+        // Doesn't fit in the frame of page 1, so it goes to a new one.
+        let noise: Vec<u8> = (0..64).map(|i| (i * 97 % 251) as u8).collect();
+        io.write(1, &noise)?;
+
+        // Simulate a crash while writing the last frame.
+        let mut file = io.io.into_inner();
+        file.truncate(file.len() - 1);
+
+        let mut io = BlockIo::new(io::Cursor::new(file), 64, 64).with_compression();
+        let mut buf = vec![0; 64];
+
+        io.read(0, &mut buf)?;
+        assert_eq!(buf, [1; 64]);
+        io.read(1, &mut buf)?;
+        assert_eq!(buf, [2; 64]);
+
+        // The incomplete frame should be overwritten.
+        io.write(2, &[4; 64])?;
+        let mut io = BlockIo::new(io::Cursor::new(io.io.into_inner()), 64, 64).with_compression();
+        io.read(2, &mut buf)?;
+        assert_eq!(buf, [4; 64]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ignore_torn_last_compressed_frame() -> io::Result<()> {
+        let mut io = BlockIo::new(io::Cursor::new(Vec::new()), 64, 64).with_compression();
+        io.write(0, &[1; 64])?;
+        io.write(1, &[2; 64])?;
+
+        // Doesn't compress well, so the frame is larger than the next one.
+        let noise: Vec<u8> = (0..64).map(|i| (i * 97 % 251) as u8).collect();
+        io.write(1, &noise)?;
+
+        // Simulate a crash that wrote the frame header but not all the
+        // content, so the length still fits in the file.
+        let mut file = io.io.into_inner();
+        let len = file.len();
+        file[len - 8..].fill(0);
+
+        let mut io = BlockIo::new(io::Cursor::new(file), 64, 64).with_compression();
+        let mut buf = vec![0; 64];
+
+        io.read(1, &mut buf)?;
+        assert_eq!(buf, [2; 64]);
+
+        // The new frame is shorter than the torn one, what remains of the torn
+        // frame after it must not be loaded.
+        io.write(2, &[4; 64])?;
+        let mut io = BlockIo::new(io::Cursor::new(io.io.into_inner()), 64, 64).with_compression();
+
+        for (page_number, content) in [(0, [1; 64]), (1, [2; 64]), (2, [4; 64])] {
+            io.read(page_number, &mut buf)?;
+            assert_eq!(buf, content);
+        }
+
+        assert_eq!(io.compression.as_ref().unwrap().frames.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reuse_compressed_frames() -> io::Result<()> {
+        let mut io = BlockIo::new(io::Cursor::new(Vec::new()), 64, 64).with_compression();
+        let noise: Vec<u8> = (0..64).map(|i| (i * 97 % 251) as u8).collect();
+
+        io.write(0, &noise)?;
+        let file_len = io.io.get_ref().len();
+
+        // Fits in the frame of the noise, overwritten in place.
+        io.write(0, &[1; 64])?;
+        assert_eq!(io.io.get_ref().len(), file_len);
+
+        // Page 1 doesn't fit in its first frame, so it moves to a new one.
+        io.write(1, &[2; 64])?;
+        io.write(1, &noise)?;
+
+        // The stale frame of page 1 must not be loaded.
+        let mut io = BlockIo::new(io::Cursor::new(io.io.into_inner()), 64, 64).with_compression();
+        let mut buf = vec![0; 64];
+        io.read(1, &mut buf)?;
+        assert_eq!(buf, noise);
+
+        // Reuses the first frame of page 1.
+        let file_len = io.io.get_ref().len();
+        io.write(2, &[3; 64])?;
+        assert_eq!(io.io.get_ref().len(), file_len);
+
+        for (page_number, content) in [(0, vec![1; 64]), (1, noise), (2, vec![3; 64])] {
+            io.read(page_number, &mut buf)?;
+            assert_eq!(buf, content);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_stored_page_map() -> io::Result<()> {
+        let mut io = BlockIo::new(io::Cursor::new(Vec::new()), 64, 64).with_compression();
+        let noise: Vec<u8> = (0..64).map(|i| (i * 97 % 251) as u8).collect();
+
+        io.write(0, &[1; 64])?;
+        io.write(1, &[2; 64])?;
+        io.write(1, &noise)?;
+        io.flush()?;
+
+        // Scanning the frames would put the frame of the map in the free list
+        // instead.
+        let mut reopened =
+            BlockIo::new(io::Cursor::new(io.io.get_ref().clone()), 64, 64).with_compression();
+        let mut buf = vec![0; 64];
+        reopened.read(1, &mut buf)?;
+        assert_eq!(buf, noise);
+        assert_eq!(reopened.compression, io.compression);
+
+        // Writes after flushing invalidate the stored map.
+        io.write(2, &[3; 64])?;
+        let mut io = BlockIo::new(io::Cursor::new(io.io.into_inner()), 64, 64).with_compression();
+
+        for (page_number, content) in [(0, vec![1; 64]), (1, noise), (2, vec![3; 64])] {
+            io.read(page_number, &mut buf)?;
+            assert_eq!(buf, content);
+        }
+
+        assert!(io.compression.as_ref().unwrap().slot.is_none());
+
+        Ok(())
+    }
 }
@@ -7,5 +7,7 @@
 
 pub(super) mod io;
 
+mod compression;
+
 pub(crate) mod cache;
 pub(crate) mod pager;
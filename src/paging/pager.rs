@@ -8,7 +8,7 @@
 
 use std::{
     cmp::Reverse,
-    collections::{BinaryHeap, HashSet},
+    collections::{BinaryHeap, HashMap, HashSet},
     fmt::Debug,
     io::{self, Read, Seek, Write},
     mem,
@@ -17,11 +17,16 @@ use std::{
 
 use super::{
     cache::{Cache, FrameId},
-    io::{BlockIo, FileOps},
+    io::{BlockIo, FileOps, DEFAULT_MAX_CACHED_BLOCKS},
 };
 use crate::{
     db::{DbError, DEFAULT_PAGE_SIZE},
-    storage::page::{DbHeader, FreePage, MemPage, Page, PageTypeConversion, PageZero, MAGIC},
+    storage::{
+        page::{
+            DbHeader, FreePage, MemPage, Page, PageTypeConversion, PageZero, MAGIC, MAGIC_MASK,
+        },
+        tuple::FormatVersion,
+    },
 };
 
 /// Are we gonna have more than 4 billion pages? Probably not ¯\_(ツ)_/¯
@@ -66,6 +71,39 @@ pub(crate) struct Pager<F> {
     journal: Journal<F>,
     /// Keeps track of pages written to the journal file.
     journal_pages: HashSet<PageNumber>,
+    /// Savepoints of the current transaction, oldest first.
+    savepoints: Vec<Savepoint>,
+    /// IO counters. See [`PagerStats`].
+    pub stats: PagerStats,
+    /// Tuple serialization format used in this file.
+    pub format_version: FormatVersion,
+}
+
+/// Named point inside a transaction that can be rolled back to without
+/// discarding the entire transaction.
+///
+/// The journal only stores the original version of each page, the one prior
+/// to the transaction. Savepoints need the version of the page at the moment
+/// they were created, so they keep their own copies in memory.
+#[derive(Debug, PartialEq)]
+struct Savepoint {
+    /// Name given by the user.
+    name: String,
+    /// Contents of the pages right before they were first modified while this
+    /// savepoint was the most recent one.
+    pages: HashMap<PageNumber, Box<[u8]>>,
+}
+
+/// Cheap counters updated while the [`Pager`] is being used.
+///
+/// The pager never resets them, whoever wants to measure something should
+/// reset them first and read them back later.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct PagerStats {
+    /// Pages that were not cached and had to be read from disk.
+    pub pages_read: usize,
+    /// Page requests served directly from the cache.
+    pub cache_hits: usize,
 }
 
 // The derive Debug impl for the Pager prints too much stuff (the internal
@@ -86,6 +124,9 @@ impl<F> Debug for Pager<F> {
 /// There's nothing in this project that's easy to "build" for some reason.
 pub(crate) struct Builder {
     block_size: Option<usize>,
+    max_cached_blocks: usize,
+    compression: bool,
+    checksums: bool,
     page_size: usize,
     cache: Option<Cache>,
     journal_file_path: PathBuf,
@@ -97,6 +138,9 @@ impl Builder {
     pub fn new() -> Self {
         Self {
             block_size: None,
+            max_cached_blocks: DEFAULT_MAX_CACHED_BLOCKS,
+            compression: false,
+            checksums: false,
             page_size: DEFAULT_PAGE_SIZE,
             cache: None,
             journal_file_path: PathBuf::new(),
@@ -116,6 +160,33 @@ impl Builder {
         self
     }
 
+    /// Maximum number of blocks that the underlying [`BlockIo`] instance keeps
+    /// in memory when the block size is greater than the page size.
+    pub fn max_cached_blocks(mut self, max_cached_blocks: usize) -> Self {
+        self.max_cached_blocks = max_cached_blocks;
+        self
+    }
+
+    /// Compresses pages before writing them to disk. Disabled by default.
+    ///
+    /// See [`BlockIo::with_compression`] for details.
+    pub fn compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Stores a checksum of every page on disk and verifies it when reading
+    /// the page back. Disabled by default.
+    ///
+    /// The checksum takes [`super::io::PAGE_RESERVED_SIZE`] bytes of every
+    /// page, so the page size seen by the [`Pager`] and everything above it is
+    /// smaller than the one given to [`Self::page_size`]. See
+    /// [`BlockIo::with_checksums`].
+    pub fn checksums(mut self, checksums: bool) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
     /// Uses this cache for the [`Pager`].
     pub fn cache(mut self, cache: Cache) -> Self {
         self.cache = Some(cache);
@@ -142,6 +213,9 @@ impl Builder {
     pub fn wrap<F>(self, file: F) -> Pager<F> {
         let Builder {
             block_size,
+            max_cached_blocks,
+            compression,
+            checksums,
             page_size,
             cache,
             journal_file_path,
@@ -150,6 +224,20 @@ impl Builder {
 
         let block_size = block_size.unwrap_or(page_size);
 
+        let mut file =
+            BlockIo::new(file, page_size, block_size).with_max_cached_blocks(max_cached_blocks);
+
+        if compression {
+            file = file.with_compression();
+        }
+
+        if checksums {
+            file = file.with_checksums();
+        }
+
+        // Pages lose the bytes reserved for checksums, if any.
+        let page_size = file.content_size();
+
         // This one allocates a bunch of stuff so we evaluate it lazily.
         let mut cache = cache.unwrap_or_else(|| Cache::with_page_size(page_size));
 
@@ -157,12 +245,15 @@ impl Builder {
         cache.page_size = page_size;
 
         Pager {
-            file: BlockIo::new(file, self.page_size, block_size),
+            file,
             block_size,
             page_size,
             cache,
             dirty_pages: HashSet::new(),
             journal_pages: HashSet::new(),
+            savepoints: Vec::new(),
+            stats: PagerStats::default(),
+            format_version: FormatVersion::CURRENT,
             journal: Journal::new(JournalConfig {
                 file_path: journal_file_path,
                 max_pages: max_journal_buffered_pages,
@@ -177,6 +268,24 @@ impl<F> Pager<F> {
     pub fn builder() -> Builder {
         Builder::new()
     }
+
+    /// Creates a new savepoint called `name` in the current transaction.
+    ///
+    /// Savepoints with the same name are allowed, the most recent one hides
+    /// the others. See [`Self::rollback_to_savepoint`].
+    pub fn savepoint(&mut self, name: String) {
+        self.savepoints.push(Savepoint {
+            name,
+            pages: HashMap::new(),
+        });
+    }
+
+    /// `true` if the current transaction has a savepoint called `name`.
+    pub fn has_savepoint(&self, name: &str) -> bool {
+        self.savepoints
+            .iter()
+            .any(|savepoint| savepoint.name == name)
+    }
 }
 
 impl<F: Seek + Read> Pager<F> {
@@ -188,7 +297,7 @@ impl<F: Seek + Read> Pager<F> {
     }
 }
 
-impl<F: Seek + Write> Pager<F> {
+impl<F: Seek + Read + Write> Pager<F> {
     /// Manually write a page to disk.
     ///
     /// Unlike normal writes there is no use of the cache/buffer pool. The page
@@ -210,11 +319,18 @@ impl<F: Write + FileOps> Pager<F> {
             self.journal_pages.insert(page_number);
         }
 
+        if let Some(savepoint) = self.savepoints.last_mut() {
+            savepoint
+                .pages
+                .entry(page_number)
+                .or_insert_with(|| Box::from(self.cache[index].as_ref()));
+        }
+
         Ok(())
     }
 }
 
-impl<F: Seek + Write + FileOps> Pager<F> {
+impl<F: Seek + Read + Write + FileOps> Pager<F> {
     /// Writes all the pages present in the dirty queue and marks them as clean.
     ///
     /// Changes will most likely not be persisted to disk until [`Self::commit`]
@@ -243,9 +359,11 @@ impl<F: Seek + Write + FileOps> Pager<F> {
         Ok(())
     }
 
-    /// If this succeeds then we can tell the client/user that data is
-    /// persisted on disk.
+    /// Writes the dirty pages, flushes the file and syncs it. If this succeeds
+    /// then we can tell the client/user that data is persisted on disk.
     pub fn commit(&mut self) -> io::Result<()> {
+        self.savepoints.clear();
+
         // If there are no pages in the journal it means we didn't modify
         // anything. The transaction was read-only.
         if self.journal_pages.is_empty() {
@@ -265,7 +383,7 @@ impl<F: Seek + Write + FileOps> Pager<F> {
     }
 }
 
-impl<F: Write> Pager<F> {
+impl<F: Seek + Write> Pager<F> {
     /// Flush buffered writes.
     ///
     /// See [`FileOps`] for details.
@@ -297,7 +415,10 @@ impl<F: Seek + Read + Write + FileOps> Pager<F> {
         // TODO: create another function alloc_zeroed() or something.
         page_zero.as_mut().fill(0);
 
-        self.file.read(0, page_zero.as_mut())?;
+        // The page size of the file might not be the configured one, so the
+        // checksum can't be located yet. Page zero is verified later when it's
+        // loaded through the cache.
+        self.file.read_unverified(0, page_zero.as_mut())?;
 
         let magic = page_zero.header().magic;
         let page_size = page_zero.header().page_size as usize;
@@ -307,11 +428,29 @@ impl<F: Seek + Read + Write + FileOps> Pager<F> {
         //
         // TODO: This is getting out of hand, we need a centralized place
         // to access the page size (and ideally not a global variable).
-        if magic == MAGIC {
+        if magic & MAGIC_MASK == MAGIC {
+            let version = page_zero.header().format_version();
+
+            self.format_version = FormatVersion::try_from(version).map_err(|version| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unsupported database format version {version}"),
+                )
+            })?;
+
+            // Older versions didn't store the checksum.
+            if self.format_version.has_header_checksum() && !page_zero.header().has_valid_checksum()
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "database header checksum mismatch, the file is corrupted",
+                ));
+            }
+
             self.page_size = page_size;
             self.cache.page_size = page_size;
             self.journal.page_size = page_size;
-            self.file.page_size = page_size;
+            self.file.page_size = page_size + self.file.reserved_size();
             return Ok(());
         }
 
@@ -322,10 +461,19 @@ impl<F: Seek + Read + Write + FileOps> Pager<F> {
         // we could just implement some functionality for dumping the SQL
         // insert statements just like MySQL or any other database does and not
         // deal with flipping bits around.
-        if magic.swap_bytes() == MAGIC {
+        if magic.swap_bytes() & MAGIC_MASK == MAGIC {
             panic!("the database file has been created using a different endianness than the one used by this machine");
         }
 
+        // No magic number but there's something written in the file. Whatever
+        // it is, it's not ours, so don't overwrite it.
+        if page_zero.as_ref().iter().any(|byte| *byte != 0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an mkdb database file",
+            ));
+        }
+
         // Initialize page zero.
         let page_zero = PageZero::alloc(self.page_size);
         self.write(0, page_zero.as_ref())?;
@@ -337,11 +485,31 @@ impl<F: Seek + Read + Write + FileOps> Pager<F> {
     ///
     /// See the journal file format in the documentation of [`Pager`] to have
     /// an understanding of what's going on here.
+    ///
+    /// If the journal was never written to its file then the database file
+    /// was not modified either, because [`Self::write_dirty_pages`] always
+    /// persists the journal first. In that case the modified pages only live
+    /// in the cache, so we discard them and skip the disk entirely.
     pub fn rollback(&mut self) -> Result<usize, DbError> {
+        self.savepoints.clear();
+
         // No-op if already open. Only necessary for the initial rollback on
         // startup.
         self.journal.open_if_exists()?;
 
+        if self.journal.file.is_none() {
+            let num_pages_rolled_back = self.journal_pages.len();
+
+            for page_number in self.journal_pages.drain() {
+                self.cache.invalidate(page_number);
+                self.dirty_pages.remove(&page_number);
+            }
+
+            self.journal.clear();
+
+            return Ok(num_pages_rolled_back);
+        }
+
         let mut num_pages_rolled_back = 0;
         let mut journal_pages = self.journal.iter()?;
 
@@ -361,6 +529,54 @@ impl<F: Seek + Read + Write + FileOps> Pager<F> {
         Ok(num_pages_rolled_back)
     }
 
+    /// Restores the pages to the state they had when the savepoint `name` was
+    /// created. Returns the number of pages restored.
+    ///
+    /// Each savepoint only stores the pages modified while it was the most
+    /// recent one, so we have to go through the savepoints that came after
+    /// `name` as well, from newest to oldest. That way the oldest copy of each
+    /// page is the last one written. Savepoints created after `name` are
+    /// released while `name` remains usable.
+    ///
+    /// Pages modified within the transaction are already in the journal, so
+    /// once the journal is persisted the copies can be written straight to the
+    /// database file the same way [`Self::rollback`] does it. A full rollback
+    /// still restores the pages prior to the transaction.
+    pub fn rollback_to_savepoint(&mut self, name: &str) -> Result<usize, DbError> {
+        let Some(position) = self
+            .savepoints
+            .iter()
+            .rposition(|savepoint| savepoint.name == name)
+        else {
+            return Err(DbError::Other(format!("savepoint {name} does not exist")));
+        };
+
+        let newer = self.savepoints.split_off(position + 1);
+        let pages = mem::take(&mut self.savepoints[position].pages);
+
+        if pages.is_empty() && newer.iter().all(|savepoint| savepoint.pages.is_empty()) {
+            return Ok(0);
+        }
+
+        self.journal.persist()?;
+
+        let mut restored = HashSet::new();
+
+        for (page_number, content) in newer
+            .into_iter()
+            .rev()
+            .flat_map(|savepoint| savepoint.pages)
+            .chain(pages)
+        {
+            self.file.write(page_number, &content)?;
+            self.cache.invalidate(page_number);
+            self.dirty_pages.remove(&page_number);
+            restored.insert(page_number);
+        }
+
+        Ok(restored.len())
+    }
+
     /// Returns a page as a concrete type.
     ///
     /// # Panics
@@ -496,9 +712,12 @@ impl<F: Seek + Read + Write + FileOps> Pager<F> {
         page_number: PageNumber,
     ) -> io::Result<usize> {
         if let Some(index) = self.cache.get(page_number) {
+            self.stats.cache_hits += 1;
             return Ok(index);
         }
 
+        self.stats.pages_read += 1;
+
         // We always know the type of page zero. See [`Self::get_as`] for
         // details on page types.
         if page_number == 0 {
@@ -612,7 +831,7 @@ impl<F: Seek + Read + Write + FileOps> Pager<F> {
             header.first_free_page = page_number;
         } else {
             // Grab the last free and make it point to the new last free.
-            let last_free = self.get_mut_as::<FreePage>(page_number)?;
+            let last_free = self.get_mut_as::<FreePage>(header.last_free_page)?;
             last_free.header_mut().next = page_number;
         }
 
@@ -622,6 +841,52 @@ impl<F: Seek + Read + Write + FileOps> Pager<F> {
         self.write_header(header)
     }
 
+    /// Copies the contents of `page_number` into `destination`.
+    ///
+    /// The destination must be a free page whose contents are no longer
+    /// needed. This is used by `VACUUM` to move the pages at the end of the
+    /// file into the holes left by free pages, so the caller is responsible for
+    /// updating every pointer to `page_number`. See [`Self::truncate`].
+    pub(crate) fn copy_page<P: PageTypeConversion + AsMut<[u8]>>(
+        &mut self,
+        page_number: PageNumber,
+        destination: PageNumber,
+    ) -> io::Result<()> {
+        let index = self.lookup::<P>(page_number)?;
+        let contents = Box::<[u8]>::from(self.cache[index].as_ref());
+
+        // Same as [`Self::free_page`], journal the original contents first.
+        let index = self.lookup::<FreePage>(destination)?;
+        self.push_to_write_queue(destination, index)?;
+
+        self.cache[index].reinit_as::<P>();
+        self.cache[index].as_mut().copy_from_slice(&contents);
+
+        Ok(())
+    }
+
+    /// Drops the free list and every page at or after `total_pages`.
+    ///
+    /// All the pages that are still in use must be located before
+    /// `total_pages` by now, see [`Self::copy_page`]. The file itself is not
+    /// truncated since [`FileOps`] can't shrink files, but the pages past the
+    /// end are never read again and get overwritten as the database grows.
+    pub(crate) fn truncate(&mut self, total_pages: PageNumber) -> io::Result<()> {
+        let mut header = self.read_header()?;
+
+        for page_number in total_pages..header.total_pages {
+            self.cache.invalidate(page_number);
+            self.dirty_pages.remove(&page_number);
+        }
+
+        header.total_pages = total_pages;
+        header.free_pages = 0;
+        header.first_free_page = 0;
+        header.last_free_page = 0;
+
+        self.write_header(header)
+    }
+
     /// Returns a copy of the DB header.
     ///
     /// Since the header is small it's gonna be faster to copy it once, modify
@@ -633,7 +898,8 @@ impl<F: Seek + Read + Write + FileOps> Pager<F> {
     }
 
     /// Writes the header back to page zero. See [`Self::read_header`].
-    fn write_header(&mut self, header: DbHeader) -> io::Result<()> {
+    fn write_header(&mut self, mut header: DbHeader) -> io::Result<()> {
+        header.update_checksum();
         *self.get_mut_as::<PageZero>(0)?.header_mut() = header;
         Ok(())
     }
@@ -821,11 +1087,7 @@ impl<F: FileOps> Journal<F> {
             return Ok(());
         }
 
-        // Miri doesn't work with system calls. All the tests run in memory
-        // anyway and won't actually open any file. This is only needed when
-        // the program runs on top of a real file system.
-        #[cfg(not(miri))]
-        if self.file_path.is_file() {
+        if F::exists(&self.file_path) {
             self.file = Some(F::open(&self.file_path)?);
         }
 
@@ -1073,14 +1335,17 @@ mod tests {
         db::DbError,
         paging::{
             cache::Cache,
-            io::MemBuf,
+            io::{FileOps, MemBuf, PAGE_RESERVED_SIZE},
             pager::{journal_chunk_size, PageNumber},
         },
-        storage::page::{Cell, OverflowPage, Page},
+        storage::{
+            page::{Cell, OverflowPage, Page, PageZero, MAGIC},
+            tuple::FormatVersion,
+        },
     };
 
     fn init_pager(builder: Builder) -> io::Result<Pager<MemBuf>> {
-        let mut pager = builder.wrap(io::Cursor::new(Vec::new()));
+        let mut pager = builder.wrap(MemBuf::default());
 
         pager.init()?;
 
@@ -1099,6 +1364,199 @@ mod tests {
         init_pager_with_cache(Cache::builder().page_size(64).max_size(64).build())
     }
 
+    #[test]
+    fn read_format_version_from_header() -> io::Result<()> {
+        let pager = init_default_pager()?;
+        assert_eq!(pager.format_version, FormatVersion::CURRENT);
+
+        // Files created before the version byte existed only have the magic.
+        let mut page_zero = PageZero::alloc(64);
+        page_zero.header_mut().magic = MAGIC;
+
+        let mut pager = Pager::<MemBuf>::builder()
+            .page_size(64)
+            .wrap(MemBuf::from(page_zero.as_ref().to_vec()));
+        pager.init()?;
+
+        assert_eq!(pager.format_version, FormatVersion::V1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn dont_verify_header_checksum_of_older_versions() -> io::Result<()> {
+        // Files created before the checksum existed have something else there.
+        let mut page_zero = PageZero::alloc(64);
+        page_zero.header_mut().magic = MAGIC | (FormatVersion::V3 as u32) << 24;
+        page_zero.header_mut().checksum = 0;
+
+        let mut pager = Pager::<MemBuf>::builder()
+            .page_size(64)
+            .wrap(MemBuf::from(page_zero.as_ref().to_vec()));
+        pager.init()?;
+
+        assert_eq!(pager.format_version, FormatVersion::V3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_unknown_format_version() {
+        let mut page_zero = PageZero::alloc(64);
+        page_zero.header_mut().magic = MAGIC | 0xFF << 24;
+
+        let mut pager = Pager::<MemBuf>::builder()
+            .page_size(64)
+            .wrap(MemBuf::from(page_zero.as_ref().to_vec()));
+
+        assert_eq!(
+            pager.init().map_err(|e| e.kind()),
+            Err(io::ErrorKind::InvalidData)
+        );
+    }
+
+    #[test]
+    fn reject_non_mkdb_file() {
+        let mut pager = Pager::<MemBuf>::builder()
+            .page_size(64)
+            .wrap(MemBuf::from(b"definitely not a database file".to_vec()));
+
+        assert_eq!(
+            pager.init().map_err(|e| e.kind()),
+            Err(io::ErrorKind::InvalidData)
+        );
+    }
+
+    #[test]
+    fn reopen_valid_file() -> io::Result<()> {
+        let mut pager = init_default_pager()?;
+        let page_number = pager.alloc_disk_page()?;
+        pager.commit()?;
+
+        let mut page_zero = vec![0; 64];
+        pager.file.read(0, &mut page_zero)?;
+
+        let mut pager = Pager::<MemBuf>::builder()
+            .page_size(64)
+            .wrap(MemBuf::from(page_zero));
+        pager.init()?;
+
+        assert_eq!(pager.read_header()?.total_pages, page_number + 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reopen_file_by_path() -> io::Result<()> {
+        let mut pager = Pager::<MemBuf>::builder()
+            .page_size(64)
+            .wrap(MemBuf::create("db.mkdb")?);
+        pager.init()?;
+
+        let page_number = pager.alloc_disk_page()?;
+        pager
+            .get_mut_as::<OverflowPage>(page_number)?
+            .content_mut()
+            .fill(7);
+        pager.commit()?;
+
+        let mut pager = Pager::<MemBuf>::builder()
+            .page_size(64)
+            .wrap(MemBuf::open("db.mkdb")?);
+        pager.init()?;
+
+        let mut expected = OverflowPage::alloc(64);
+        expected.content_mut().fill(7);
+
+        assert_eq!(pager.read_header()?.total_pages, page_number + 1);
+        assert_eq!(pager.get_as::<OverflowPage>(page_number)?, &expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reopen_compressed_file() -> io::Result<()> {
+        let builder = || Pager::<MemBuf>::builder().page_size(64).compression(true);
+
+        let mut pager = builder().wrap(MemBuf::create("db.mkdb")?);
+        pager.init()?;
+
+        let page_number = pager.alloc_disk_page()?;
+        pager
+            .get_mut_as::<OverflowPage>(page_number)?
+            .content_mut()
+            .fill(7);
+        pager.commit()?;
+
+        let mut pager = builder().wrap(MemBuf::open("db.mkdb")?);
+        pager.init()?;
+
+        let mut expected = OverflowPage::alloc(64);
+        expected.content_mut().fill(7);
+
+        assert_eq!(pager.read_header()?.total_pages, page_number + 1);
+        assert_eq!(pager.get_as::<OverflowPage>(page_number)?, &expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reopen_file_with_checksums() -> io::Result<()> {
+        let builder = || Pager::<MemBuf>::builder().page_size(64).checksums(true);
+
+        let mut pager = builder().wrap(MemBuf::create("db.mkdb")?);
+        pager.init()?;
+
+        assert_eq!(pager.page_size, 64 - PAGE_RESERVED_SIZE);
+
+        let page_number = pager.alloc_disk_page()?;
+        pager
+            .get_mut_as::<OverflowPage>(page_number)?
+            .content_mut()
+            .fill(7);
+        pager.commit()?;
+
+        let mut pager = builder().wrap(MemBuf::open("db.mkdb")?);
+        pager.init()?;
+
+        let mut expected = OverflowPage::alloc(64 - PAGE_RESERVED_SIZE);
+        expected.content_mut().fill(7);
+
+        assert_eq!(pager.read_header()?.total_pages, page_number + 1);
+        assert_eq!(pager.get_as::<OverflowPage>(page_number)?, &expected);
+
+        // Flip one byte of the page content on disk.
+        let mut contents = MemBuf::open("db.mkdb")?.contents();
+        contents[page_number as usize * 64 + 20] ^= 0xFF;
+
+        let mut pager = builder().wrap(MemBuf::from(contents));
+        pager.init()?;
+
+        assert_eq!(
+            pager
+                .get_as::<OverflowPage>(page_number)
+                .map_err(|e| e.kind()),
+            Err(io::ErrorKind::InvalidData)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn detect_tampered_header_checksum() {
+        let mut page_zero = PageZero::alloc(64);
+        page_zero.header_mut().total_pages = 1000;
+
+        let mut pager = Pager::<MemBuf>::builder()
+            .page_size(64)
+            .wrap(MemBuf::from(page_zero.as_ref().to_vec()));
+
+        assert_eq!(
+            pager.init().map_err(|e| e.kind()),
+            Err(io::ErrorKind::InvalidData)
+        );
+    }
+
     #[test]
     fn alloc_disk_page() -> io::Result<()> {
         let mut pager = init_default_pager()?;
@@ -1138,6 +1596,33 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn alloc_reuses_all_free_pages() -> io::Result<()> {
+        let mut pager = init_default_pager()?;
+
+        for _ in 1..=10 {
+            pager.alloc_disk_page()?;
+        }
+
+        for p in [5, 7, 9] {
+            pager.free_page(p)?;
+        }
+
+        // Free pages are reused in FIFO order before growing the file.
+        for p in [5, 7, 9, 11] {
+            assert_eq!(pager.alloc_disk_page()?, p);
+        }
+
+        let header = pager.read_header()?;
+
+        assert_eq!(header.first_free_page, 0);
+        assert_eq!(header.last_free_page, 0);
+        assert_eq!(header.free_pages, 0);
+        assert_eq!(header.total_pages, 12);
+
+        Ok(())
+    }
+
     #[test]
     fn write_queue() -> io::Result<()> {
         let mut pager = init_default_pager()?;
@@ -1324,7 +1809,7 @@ mod tests {
         // reading and rolling back... so... TODO.
         assert!(pager.journal.file.is_some());
         assert_eq!(
-            pager.journal.file.unwrap().into_inner().len(),
+            pager.journal.file.unwrap().contents().len(),
             journal_chunk_size(pager.page_size, modified_pages as usize)
         );
 
@@ -1352,7 +1837,7 @@ mod tests {
             journal_chunk_size(pager.page_size, 1)
         );
         assert_eq!(
-            pager.journal.file.unwrap().into_inner().len(),
+            pager.journal.file.unwrap().contents().len(),
             journal_chunk_size(pager.page_size, buffered_pages)
         );
 
@@ -1379,7 +1864,7 @@ mod tests {
 
         // 2 complete chunks should be written to the file.
         assert_eq!(
-            pager.journal.file.unwrap().into_inner().len(),
+            pager.journal.file.unwrap().contents().len(),
             journal_chunk_size(pager.page_size, buffered_pages) * 2
         );
 
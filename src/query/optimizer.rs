@@ -6,23 +6,29 @@ use std::{
     cmp::{self, Ordering},
     collections::{HashMap, HashSet, VecDeque},
     io::{Read, Seek, Write},
-    mem,
+    iter, mem,
     ops::{Bound, RangeBounds},
     ptr,
     rc::Rc,
 };
 
 use crate::{
-    db::{Database, DatabaseContext, DbError, IndexMetadata, Relation},
+    db::{
+        Database, DatabaseContext, DbError, IndexMetadata, Relation, Schema, SqlError,
+        TableMetadata, TableStats,
+    },
     paging::io::FileOps,
     sql::{
         parser::Parser,
-        statement::{BinaryOperator, Expression, Value},
+        statement::{
+            join, BinaryOperator, DataType, Direction, Expression, OrderBy, ScalarFunction, Value,
+        },
     },
     storage::{tuple, Cursor},
     vm::plan::{
-        Collect, CollectConfig, ExactMatch, Filter, KeyScan, LogicalOrScan, Plan, RangeScan,
-        RangeScanConfig, SeqScan, Sort, SortConfig, TuplesComparator, DEFAULT_SORT_INPUT_BUFFERS,
+        Collect, CollectConfig, Empty, ExactMatch, Filter, KeyScan, LogicalAndScan, LogicalOrScan,
+        Plan, RangeScan, RangeScanConfig, SeqScan, Sort, SortConfig, TuplesComparator,
+        DEFAULT_SORT_INPUT_BUFFERS,
     },
 };
 
@@ -34,10 +40,44 @@ pub(crate) fn generate_scan_plan<F: Seek + Read + Write + FileOps>(
     mut filter: Option<Expression>,
     db: &mut Database<F>,
 ) -> Result<Plan<F>, DbError> {
-    let source = if let Some(optimized_scan) = generate_optimized_scan_plan(table, db, &mut filter)?
-    {
+    // Matches everything, no need to evaluate the filter.
+    if filter.as_ref().is_some_and(always_matches) {
+        filter = None;
+    }
+
+    // Don't even bother opening a cursor.
+    if filter.as_ref().is_some_and(never_matches) {
+        return Ok(Plan::Empty(Empty {
+            schema: db.table_metadata(table)?.schema.clone(),
+        }));
+    }
+
+    // The optimized scan consumes parts of the filter, so keep the original
+    // in case a sequential scan turns out to be cheaper.
+    let stats = db.table_metadata(table)?.stats.clone();
+    let original_filter = stats.as_ref().and(filter.clone());
+
+    let mut optimized_scan = generate_optimized_scan_plan(table, db, &mut filter)?;
+
+    let seq_scan_by_cost = stats.as_ref().is_some_and(|stats| {
+        optimized_scan
+            .as_ref()
+            .is_some_and(|scan| seq_scan_is_cheaper(scan, stats))
+    });
+
+    if seq_scan_by_cost {
+        optimized_scan = None;
+        filter = original_filter;
+    }
+
+    let source = if let Some(optimized_scan) = optimized_scan {
         optimized_scan
     } else {
+        // No point in warning about scans that we chose on purpose.
+        if let Some(expr) = filter.as_ref().filter(|_| !seq_scan_by_cost) {
+            warn_full_scan(table, expr, db)?;
+        }
+
         generate_sequential_scan_plan(table, db)?
     };
 
@@ -52,6 +92,444 @@ pub(crate) fn generate_scan_plan<F: Seek + Read + Write + FileOps>(
     }))
 }
 
+/// Cost of reading one row through an external index relative to reading it
+/// with a sequential scan. Each key found in the index requires a BTree search
+/// on the table, which reads pages in random order.
+const INDEX_LOOKUP_COST: f64 = 4.0;
+
+/// Fraction of the rows assumed to fall within each bound of a range.
+const RANGE_BOUND_SELECTIVITY: f64 = 1.0 / 3.0;
+
+/// Decides whether a [`SeqScan`] is cheaper than the given optimized scan plan
+/// using the statistics of the table.
+///
+/// # Cost Model
+///
+/// Costs are measured in rows read sequentially, so a [`SeqScan`] costs as
+/// many rows as the table has. Scans of the table BTree never cost more than
+/// that because they read a subset of the same BTree in the same order, so
+/// they're always used. Plans that end up in a [`KeyScan`] cost
+/// [`INDEX_LOOKUP_COST`] per key found in the indexes, and the number of keys
+/// is estimated using only the row count of the table:
+///
+/// - [`ExactMatch`] finds at most one key.
+///
+/// - [`RangeScan`] finds [`RANGE_BOUND_SELECTIVITY`] of the rows for each
+///   bound of the range, so `x > 5` returns a third of the table and
+///   `x > 5 AND x < 10` a ninth.
+///
+/// - [`LogicalOrScan`] finds the keys of all its scans and [`LogicalAndScan`]
+///   finds at most the keys of its smallest side.
+///
+/// Estimations are never less than one key. This means that external indexes
+/// are only worth it when they discard most of the table, which is never the
+/// case for tiny tables.
+///
+/// TODO: [`index_only_scan`] doesn't need the table lookups, but we only know
+/// whether the index covers the query after choosing the scan.
+fn seq_scan_is_cheaper<F>(plan: &Plan<F>, stats: &TableStats) -> bool {
+    let Plan::KeyScan(KeyScan { source, .. }) = plan else {
+        return false;
+    };
+
+    estimated_keys(source, stats.rows) * INDEX_LOOKUP_COST >= stats.rows as f64
+}
+
+/// Estimates how many keys the given key only scan plan returns when scanning
+/// a table of `rows` rows. See [`seq_scan_is_cheaper`].
+fn estimated_keys<F>(plan: &Plan<F>, rows: usize) -> f64 {
+    let estimation = match plan {
+        Plan::ExactMatch(_) => 1.0,
+
+        Plan::RangeScan(range_scan) => {
+            let (start, end) = &range_scan.range;
+            let bounds = [start, end]
+                .into_iter()
+                .filter(|bound| !matches!(bound, Bound::Unbounded))
+                .count();
+
+            rows as f64 * RANGE_BOUND_SELECTIVITY.powi(bounds as i32)
+        }
+
+        Plan::LogicalOrScan(or_scan) => or_scan
+            .scans
+            .iter()
+            .map(|scan| estimated_keys(scan, rows))
+            .sum(),
+
+        Plan::LogicalAndScan(and_scan) => {
+            estimated_keys(&and_scan.left, rows).min(estimated_keys(&and_scan.right, rows))
+        }
+
+        other => match other.child() {
+            Some(child) => estimated_keys(child, rows),
+            None => rows as f64,
+        },
+    };
+
+    estimation.max(1.0).min(rows as f64)
+}
+
+/// Returns `true` if the filter is known to match every tuple.
+///
+/// Constant expressions like `WHERE 1 = 1` have already been reduced to `TRUE`
+/// by [`crate::sql::optimizer`].
+pub(crate) fn always_matches(filter: &Expression) -> bool {
+    *filter == Expression::Value(Value::Bool(true))
+}
+
+/// Returns `true` if the filter can't possibly match any tuple.
+///
+/// That's the case of constant expressions like `WHERE 1 = 2`, which have
+/// already been reduced to `FALSE` by [`crate::sql::optimizer`], or
+/// contradictory comparisons like `WHERE id < 5 AND id > 10`. See
+/// [`required_ranges`].
+pub(crate) fn never_matches(filter: &Expression) -> bool {
+    *filter == Expression::Value(Value::Bool(false)) || required_ranges(filter).is_none()
+}
+
+/// Ranges of values that each column must fall into.
+type ColumnRanges<'e> = HashMap<&'e str, Vec<IndexRangeBounds<'e>>>;
+
+/// Computes the ranges of values that columns must fall into for `expr` to
+/// evaluate to `true`. Returns [`None`] if there are no such values.
+///
+/// This is similar to [`find_index_paths`] but it works with all the columns,
+/// not only indexed columns, and it never gives up on `AND` expressions. For
+/// example, `(id < 5 OR id > 10) AND id = 7` requires `id` to be in
+/// `[..5, 10..]` and in `[7..7]` at the same time, which can't happen.
+///
+/// The result is an approximation. Expressions that we don't understand don't
+/// restrict any column, so if this function returns [`None`] the expression is
+/// definitely a contradiction but otherwise it's not guaranteed to match.
+fn required_ranges(expr: &Expression) -> Option<ColumnRanges<'_>> {
+    match expr {
+        Expression::BinaryOperation {
+            left,
+            operator,
+            right,
+        } => match (&**left, operator, &**right) {
+            (Expression::Identifier(col), _, Expression::Value(_))
+            | (Expression::Value(_), _, Expression::Identifier(col))
+                if matches!(
+                    operator,
+                    BinaryOperator::Eq
+                        | BinaryOperator::Lt
+                        | BinaryOperator::LtEq
+                        | BinaryOperator::Gt
+                        | BinaryOperator::GtEq
+                ) =>
+            {
+                Some(HashMap::from([(col.as_str(), vec![determine_bounds(
+                    expr,
+                )])]))
+            }
+
+            // Intersect the ranges of columns that appear on both sides.
+            (left, BinaryOperator::And, right) => {
+                let mut ranges = required_ranges(left)?;
+
+                for (col, right_ranges) in required_ranges(right)? {
+                    let intersection = match ranges.remove(col) {
+                        None => right_ranges,
+                        Some(left_ranges) => left_ranges
+                            .iter()
+                            .flat_map(|left| {
+                                right_ranges
+                                    .iter()
+                                    .filter_map(|right| range_intersection(*left, *right))
+                            })
+                            .collect(),
+                    };
+
+                    if intersection.is_empty() {
+                        return None;
+                    }
+
+                    ranges.insert(col, intersection);
+                }
+
+                Some(ranges)
+            }
+
+            // Columns that only appear on one side are not restricted anymore
+            // because the other side can match any value.
+            (left, BinaryOperator::Or, right) => {
+                match (required_ranges(left), required_ranges(right)) {
+                    (Some(left), Some(mut right)) => Some(
+                        left.into_iter()
+                            .filter_map(|(col, mut ranges)| {
+                                ranges.append(&mut right.remove(col)?);
+                                Some((col, ranges))
+                            })
+                            .collect(),
+                    ),
+
+                    (left, right) => left.or(right),
+                }
+            }
+
+            _ => Some(HashMap::new()),
+        },
+
+        Expression::Nested(inner) => required_ranges(inner),
+
+        _ => Some(HashMap::new()),
+    }
+}
+
+/// Makes the scan plan returned by [`generate_scan_plan`] produce tuples in
+/// the order requested by `ORDER BY` if the table BTree or one of its indexes
+/// already stores them that way. Returns `true` if the tuples don't need to be
+/// sorted anymore.
+///
+/// Only sequential scans and scans of the table BTree are rewritten:
+///
+/// - Ordering by the table key flips the direction of the table scan.
+///
+/// - Ordering by the columns of an index replaces a [`SeqScan`] with a
+///   [`KeyScan`] on top of a [`RangeScan`] of the entire index, which goes
+///   forwards or backwards depending on the index directions.
+///
+/// Scans on external indexes already sort their keys to do sequential IO on
+/// the table, so they are left alone.
+pub(crate) fn scan_in_order<F: Seek + Read + Write + FileOps>(
+    table: &str,
+    source: &mut Plan<F>,
+    order_by: &[OrderBy],
+    db: &mut Database<F>,
+) -> Result<bool, DbError> {
+    let mut keys = Vec::with_capacity(order_by.len());
+
+    for OrderBy { expr, direction } in order_by {
+        let Expression::Identifier(col) = expr else {
+            return Ok(false);
+        };
+        keys.push((col.as_str(), *direction));
+    }
+
+    let table = db.table_metadata(table)?.clone();
+
+    let table_key = [(table.schema.columns[0].name.as_str(), Direction::Asc)];
+
+    let mut relations = iter::once((Relation::Table(table.clone()), Vec::from(table_key))).chain(
+        table.indexes.iter().map(|index| {
+            let columns = index.columns.iter().map(|col| col.name.as_str());
+            let key = columns.zip(index.directions.iter().copied()).collect();
+            (Relation::Index(index.clone()), key)
+        }),
+    );
+
+    let Some((relation, reverse)) = relations
+        .find_map(|(relation, key)| provides_order(&key, &keys).map(|reverse| (relation, reverse)))
+    else {
+        return Ok(false);
+    };
+
+    let scan = match source {
+        Plan::Filter(filter) => &mut *filter.source,
+        other => other,
+    };
+
+    match (relation, scan) {
+        // At most one tuple, nothing to sort.
+        (_, Plan::ExactMatch(_) | Plan::Empty(_)) => {}
+
+        (_, Plan::KeyScan(KeyScan { source, .. })) if matches!(**source, Plan::ExactMatch(_)) => {}
+
+        (Relation::Table(_), Plan::SeqScan(seq_scan)) => {
+            if reverse {
+                seq_scan.cursor = Cursor::new(table.root, 0).reverse();
+            }
+        }
+
+        (Relation::Table(_), Plan::RangeScan(range_scan))
+            if matches!(range_scan.relation, Relation::Table(_)) =>
+        {
+            range_scan.reverse = reverse;
+        }
+
+        (relation @ Relation::Index(_), scan @ Plan::SeqScan(_)) => {
+            let mut index_scan = RangeScan::from(RangeScanConfig {
+                relation,
+                pager: Rc::clone(&db.pager),
+                range: (Bound::Unbounded, Bound::Unbounded),
+                expr: Expression::Wildcard,
+                emit_table_key_only: true,
+            });
+            index_scan.reverse = reverse;
+
+            *scan = Plan::KeyScan(KeyScan {
+                comparator: table.comparator(),
+                pager: Rc::clone(&db.pager),
+                source: Box::new(Plan::RangeScan(index_scan)),
+                table,
+                rows_scanned: 0,
+            });
+        }
+
+        _ => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+/// Replaces the [`KeyScan`] on top of an external index scan with the index
+/// scan itself when the index stores every column that the query needs.
+/// Returns the schema of the index tuples that the scan produces now and their
+/// order or [`None`] if the plan was left alone.
+///
+/// Index entries contain the index key columns followed by the table key (see
+/// [`IndexMetadata::schema`]), so queries like this one:
+///
+/// ```sql
+/// SELECT email FROM users WHERE email > 'b';
+/// ```
+///
+/// don't need to read the table BTree at all. `exprs` are the expressions that
+/// will be evaluated on top of the scan, except for the remaining `WHERE`
+/// conditions which are found in the [`Filter`] plan. Conditions consumed by
+/// the index scan only reference the index column.
+///
+/// The keys were only sorted to do sequential IO on the table, so without the
+/// table lookups the sort is dropped and the tuples come out in index order
+/// instead, which saves another sort for `ORDER BY` on the index columns.
+pub(crate) fn index_only_scan<'e, F: Seek + Read + Write + FileOps>(
+    source: &mut Plan<F>,
+    exprs: impl IntoIterator<Item = &'e Expression>,
+) -> Option<(Schema, Vec<OrderBy>)> {
+    let (key_scan, filter) = match &*source {
+        Plan::Filter(filter) => (&*filter.source, Some(&filter.filter)),
+        other => (other, None),
+    };
+
+    let Plan::KeyScan(KeyScan { source: keys, .. }) = key_scan else {
+        return None;
+    };
+
+    // Keys returned by range scans are sorted before doing the table lookups.
+    // See [`sort_keys`].
+    let index_scan = match &**keys {
+        sort @ Plan::Sort(_) => sort.child()?,
+        other => other,
+    };
+
+    let index = scanned_index(index_scan)?;
+
+    let mut columns = Vec::new();
+    for expr in exprs {
+        collect_identifiers(expr, &mut columns);
+    }
+    if let Some(expr) = filter {
+        collect_identifiers(expr, &mut columns);
+    }
+
+    if !columns
+        .iter()
+        .all(|col| index.schema.index_of(col).is_some())
+    {
+        return None;
+    }
+
+    let schema = index.schema.clone();
+
+    let order = Vec::from_iter(index.columns.iter().zip(&index.directions).map(
+        |(col, direction)| OrderBy {
+            expr: Expression::Identifier(col.name.clone()),
+            direction: *direction,
+        },
+    ));
+
+    let key_scan = match source {
+        Plan::Filter(filter) => {
+            filter.schema = schema.clone();
+            &mut *filter.source
+        }
+        other => other,
+    };
+
+    let Plan::KeyScan(KeyScan { source: keys, .. }) = mem::replace(
+        key_scan,
+        Plan::Empty(Empty {
+            schema: Schema::empty(),
+        }),
+    ) else {
+        unreachable!();
+    };
+
+    let mut index_scan = match *keys {
+        Plan::Sort(sort) => sort.into_source(),
+        other => other,
+    };
+
+    let scans = match &mut index_scan {
+        // Ranges are sorted in ascending order but DESC indexes return the
+        // greatest keys first, so the last range goes first.
+        Plan::LogicalOrScan(or_scan) => {
+            if order[0].direction == Direction::Desc {
+                or_scan.scans.make_contiguous().reverse();
+            }
+            Vec::from_iter(&mut or_scan.scans)
+        }
+        other => vec![other],
+    };
+
+    for scan in scans {
+        match scan {
+            Plan::RangeScan(range_scan) => range_scan.emit_table_key_only = false,
+            Plan::ExactMatch(exact_match) => exact_match.emit_table_key_only = false,
+            _ => unreachable!(),
+        }
+    }
+
+    *key_scan = index_scan;
+
+    Some((schema, order))
+}
+
+/// Returns the external index read by a key only scan plan or [`None`] if the
+/// plan reads the table BTree or more than one index.
+fn scanned_index<F>(plan: &Plan<F>) -> Option<&IndexMetadata> {
+    match plan {
+        Plan::RangeScan(RangeScan {
+            relation: Relation::Index(index),
+            ..
+        })
+        | Plan::ExactMatch(ExactMatch {
+            relation: Relation::Index(index),
+            ..
+        }) => Some(index),
+
+        Plan::LogicalOrScan(or_scan) => {
+            let index = scanned_index(&or_scan.scans[0])?;
+            or_scan
+                .scans
+                .iter()
+                .all(|scan| scanned_index(scan).is_some_and(|other| other.name == index.name))
+                .then_some(index)
+        }
+
+        _ => None,
+    }
+}
+
+/// Checks if a BTree whose entries are sorted by `key` can return them in the
+/// order given by `order_by`. Returns whether the BTree has to be traversed
+/// backwards or [`None`] if it can't be used.
+///
+/// The `ORDER BY` columns must be a prefix of the key or the key must be a
+/// prefix of the `ORDER BY` columns, which works because BTree keys are unique
+/// so the rest of columns don't matter. All the directions must be the same as
+/// the key directions or all the opposite.
+fn provides_order(key: &[(&str, Direction)], order_by: &[(&str, Direction)]) -> Option<bool> {
+    let reverse = key.first()?.1 != order_by.first()?.1;
+
+    key.iter()
+        .zip(order_by)
+        .all(|((key_col, key_dir), (col, dir))| key_col == col && (key_dir != dir) == reverse)
+        .then_some(reverse)
+}
+
 /// Constructs a [`Plan::SeqScan`] instance.
 fn generate_sequential_scan_plan<F: Seek + Read + Write + FileOps>(
     table: &str,
@@ -63,9 +541,119 @@ fn generate_sequential_scan_plan<F: Seek + Read + Write + FileOps>(
         cursor: Cursor::new(metadata.root, 0),
         table: metadata.clone(),
         pager: Rc::clone(&db.pager),
+        rows_scanned: 0,
     }))
 }
 
+/// Adds a warning to [`Database::warnings`] if the given filtered query has to
+/// scan a table that has more rows than
+/// [`Database::full_scan_warning_threshold`].
+///
+/// The number of rows comes from the [`TableStats`] collected by `ANALYZE`.
+/// Counting them here would cost as much as the scan we're warning about, so
+/// tables without stats never produce warnings.
+fn warn_full_scan<F: Seek + Read + Write + FileOps>(
+    table: &str,
+    filter: &Expression,
+    db: &mut Database<F>,
+) -> Result<(), DbError> {
+    let Some(threshold) = db.full_scan_warning_threshold else {
+        return Ok(());
+    };
+
+    let Some(rows) = db
+        .table_metadata(table)?
+        .stats
+        .as_ref()
+        .map(|stats| stats.rows)
+    else {
+        return Ok(());
+    };
+
+    if rows <= threshold {
+        return Ok(());
+    }
+
+    let mut columns = Vec::new();
+    collect_identifiers(filter, &mut columns);
+
+    let mut warning = format!("full scan of '{table}' ({rows} rows)");
+
+    if !columns.is_empty() {
+        let columns = columns
+            .iter()
+            .map(|col| format!("'{col}'"))
+            .collect::<Vec<_>>();
+        warning.push_str(&format!("; consider an index on {}", join(&columns, ", ")));
+    }
+
+    db.warnings.push(warning);
+
+    Ok(())
+}
+
+/// Appends the distinct column names referenced by `expr` to `columns`.
+fn collect_identifiers<'e>(expr: &'e Expression, columns: &mut Vec<&'e str>) {
+    match expr {
+        Expression::Identifier(col) => {
+            if !columns.contains(&col.as_str()) {
+                columns.push(col);
+            }
+        }
+
+        Expression::BinaryOperation { left, right, .. } => {
+            collect_identifiers(left, columns);
+            collect_identifiers(right, columns);
+        }
+
+        Expression::UnaryOperation { expr, .. }
+        | Expression::Nested(expr)
+        | Expression::IsNull { expr, .. }
+        | Expression::Aggregate { arg: expr, .. } => {
+            collect_identifiers(expr, columns);
+        }
+
+        Expression::InList { expr, list, .. } => {
+            collect_identifiers(expr, columns);
+            list.iter()
+                .for_each(|item| collect_identifiers(item, columns));
+        }
+
+        Expression::Between {
+            expr, low, high, ..
+        } => {
+            collect_identifiers(expr, columns);
+            collect_identifiers(low, columns);
+            collect_identifiers(high, columns);
+        }
+
+        Expression::Function { args, .. } | Expression::Tuple(args) => {
+            args.iter()
+                .for_each(|arg| collect_identifiers(arg, columns));
+        }
+
+        Expression::Case {
+            branches,
+            else_branch,
+        } => {
+            for (condition, result) in branches {
+                collect_identifiers(condition, columns);
+                collect_identifiers(result, columns);
+            }
+
+            if let Some(else_branch) = else_branch {
+                collect_identifiers(else_branch, columns);
+            }
+        }
+
+        Expression::QualifiedIdentifier { .. } => {
+            unreachable!("qualified identifiers should be resolved at this point")
+        }
+
+        Expression::Value(_) | Expression::Wildcard => {}
+    }
+}
+
 /// Attempts to generate a scan plan that uses indexes to search for tuples.
 ///
 /// The generated plan will be one of the following:
@@ -95,9 +683,20 @@ fn generate_optimized_scan_plan<F: Seek + Read + Write + FileOps>(
 
     let table = db.table_metadata(table_name)?.clone();
 
+    rewrite_index_predicates(
+        expr,
+        &table.schema,
+        &table.schema.columns[0].name,
+        &indexed_columns(&table),
+    );
+
+    if let Some(intersection) = generate_index_intersection_plan(db, &table, expr)? {
+        return Ok(Some(intersection));
+    }
+
     let paths = find_index_paths(
         &table.schema.columns[0].name,
-        &HashSet::from_iter(table.indexes.iter().map(|index| index.column.name.as_str())),
+        &indexed_columns(&table),
         expr,
         &mut HashSet::new(),
     );
@@ -109,65 +708,16 @@ fn generate_optimized_scan_plan<F: Seek + Read + Write + FileOps>(
     };
 
     // Map index column name to index metadata.
-    let indexes = table
-        .indexes
-        .iter()
-        .filter(|index| paths.contains_key(index.column.name.as_str()))
-        .map(|index| (index.column.name.as_str(), index))
+    let indexes = paths
+        .keys()
+        .filter_map(|col| Some((*col, leading_column_index(&table, col)?)))
         .collect::<HashMap<&str, &IndexMetadata>>();
 
     // Turn the paths map into a list of plan nodes. We'll sort the list later.
     let mut index_scans: Vec<(&str, VecDeque<Plan<F>>)> = paths
         .into_iter()
-        .map(|(col, ranges)| {
-            let relation = if let Some(index) = indexes.get(col).copied() {
-                Relation::Index(index.clone())
-            } else {
-                Relation::Table(table.clone())
-            };
-
-            let col_position = table.schema.index_of(col).unwrap();
-            let data_type = table.schema.columns[col_position].data_type;
-
-            let bounds = ranges.iter().map(|range| {
-                let start = range
-                    .start_bound()
-                    .map(|value| tuple::serialize_key(&data_type, value));
-
-                let end = range
-                    .end_bound()
-                    .map(|value| tuple::serialize_key(&data_type, value));
-
-                let expr = range_to_expr(col, *range);
-                let pager = Rc::clone(&db.pager.clone());
-                let relation = relation.clone();
-
-                if is_exact_match(*range) {
-                    let Bound::Included(key) = start else {
-                        unreachable!();
-                    };
-                    Plan::ExactMatch(ExactMatch {
-                        key,
-                        relation,
-                        expr,
-                        pager,
-                        emit_table_key_only: true,
-                        done: false,
-                    })
-                } else {
-                    Plan::RangeScan(RangeScan::from(RangeScanConfig {
-                        range: (start, end),
-                        relation,
-                        expr,
-                        pager,
-                        emit_table_key_only: true,
-                    }))
-                }
-            });
-
-            (col, bounds.collect())
-        })
-        .collect();
+        .map(|(col, ranges)| Ok((col, generate_index_scans(db, &table, col, ranges)?)))
+        .collect::<Result<_, SqlError>>()?;
 
     // Scans are sorted by the root of their index. The primary key direct table
     // index will always be first.
@@ -228,39 +778,589 @@ fn generate_optimized_scan_plan<F: Seek + Read + Write + FileOps>(
         return Ok(Some(source));
     }
 
-    let work_dir = db.work_dir.clone();
-    let page_size = db.pager.borrow().page_size;
-
     // Add sorter if we're scanning external indexes and we're going to return
     // more than one key.
     if let Plan::RangeScan(_) | Plan::LogicalOrScan(_) = source {
-        source = Plan::Sort(Sort::from(SortConfig {
-            page_size,
-            work_dir: work_dir.clone(),
-            collection: Collect::from(CollectConfig {
-                source: Box::new(source),
-                work_dir,
-                schema: table.key_only_schema(),
-                mem_buf_size: page_size,
-            }),
-            comparator: TuplesComparator {
-                schema: table.key_only_schema(),
-                sort_schema: table.key_only_schema(),
-                sort_keys_indexes: vec![0],
-            },
-            input_buffers: DEFAULT_SORT_INPUT_BUFFERS,
-        }));
+        source = sort_keys(db, table.key_only_schema(), 0, source);
     };
 
     // Finally add the [`KeyScan`] plan on top of everything.
     Ok(Some(Plan::KeyScan(KeyScan {
-        comparator: table.comparator()?,
+        comparator: table.comparator(),
         pager: Rc::clone(&db.pager),
         source: Box::new(source),
         table,
+        rows_scanned: 0,
     })))
 }
 
+/// Rewrites predicates on indexed columns that [`find_index_paths`] doesn't
+/// understand into equivalent comparisons that it does:
+///
+/// - `col LIKE 'prefix%'` becomes `col >= 'prefix' AND col < 'prefiy'`, which
+///   is a range scan. Strings that start with the prefix are exactly those that
+///   fall in that range, so the original expression is not needed anymore.
+///   Patterns with any other wildcard can't use indexes and are left alone.
+///
+/// - `col IN (1, 2, 3)` becomes `col = 1 OR col = 2 OR col = 3`, which is a
+///   [`LogicalOrScan`] of [`ExactMatch`] plans. `NOT IN` can't use indexes and
+///   empty lists don't match anything, so they're left alone as well.
+///
+/// - `col BETWEEN 1 AND 5` becomes `col >= 1 AND col <= 5` and `NOT BETWEEN`
+///   becomes `col < 1 OR col > 5`, both of which are range scans.
+///
+/// - Integer columns compared to floats like `col > 1.5` become `col >= 2`,
+///   since index keys can only be built from values of the column type. See
+///   [`coerce_float_bound`].
+///
+/// - `SUBSTR(col, 1, 3) = 'bob'` becomes `col >= 'bob' AND col < 'boc'`, the
+///   same range as `col LIKE 'bob%'`. See [`substr_prefix_predicate`].
+fn rewrite_index_predicates(
+    expr: &mut Expression,
+    schema: &Schema,
+    key_col: &str,
+    indexes: &HashSet<&str>,
+) {
+    match expr {
+        Expression::BinaryOperation {
+            left,
+            operator: BinaryOperator::Like,
+            right,
+        } => {
+            let (Expression::Identifier(col), Expression::Value(Value::String(pattern))) =
+                (&**left, &**right)
+            else {
+                return;
+            };
+
+            if !indexes.contains(col.as_str()) && col != key_col {
+                return;
+            }
+
+            let Some((start, end)) = like_prefix_range(pattern) else {
+                return;
+            };
+
+            let bound = |operator, value| Expression::BinaryOperation {
+                left: Box::new(Expression::Identifier(col.clone())),
+                operator,
+                right: Box::new(Expression::Value(Value::String(value))),
+            };
+
+            *expr = Expression::BinaryOperation {
+                left: Box::new(bound(BinaryOperator::GtEq, start)),
+                operator: BinaryOperator::And,
+                right: Box::new(bound(BinaryOperator::Lt, end)),
+            };
+        }
+
+        Expression::InList {
+            expr: inner,
+            list,
+            negated: false,
+        } => {
+            let Expression::Identifier(col) = &**inner else {
+                return;
+            };
+
+            if !indexes.contains(col.as_str()) && col != key_col
+                || list.is_empty()
+                || !list.iter().all(|item| matches!(item, Expression::Value(_)))
+            {
+                return;
+            }
+
+            let eq = |item: &Expression| Expression::BinaryOperation {
+                left: inner.clone(),
+                operator: BinaryOperator::Eq,
+                right: Box::new(item.clone()),
+            };
+
+            *expr = list
+                .iter()
+                .map(eq)
+                .reduce(|left, right| Expression::BinaryOperation {
+                    left: Box::new(left),
+                    operator: BinaryOperator::Or,
+                    right: Box::new(right),
+                })
+                .unwrap();
+
+            rewrite_index_predicates(expr, schema, key_col, indexes);
+        }
+
+        Expression::Between {
+            expr: inner,
+            low,
+            high,
+            negated,
+        } => {
+            let (Expression::Identifier(col), Expression::Value(_), Expression::Value(_)) =
+                (&**inner, &**low, &**high)
+            else {
+                return;
+            };
+
+            if !indexes.contains(col.as_str()) && col != key_col {
+                return;
+            }
+
+            let (low_operator, operator, high_operator) = if *negated {
+                (BinaryOperator::Lt, BinaryOperator::Or, BinaryOperator::Gt)
+            } else {
+                (
+                    BinaryOperator::GtEq,
+                    BinaryOperator::And,
+                    BinaryOperator::LtEq,
+                )
+            };
+
+            let bound = |operator, value: &Expression| Expression::BinaryOperation {
+                left: inner.clone(),
+                operator,
+                right: Box::new(value.clone()),
+            };
+
+            *expr = Expression::BinaryOperation {
+                left: Box::new(bound(low_operator, low)),
+                operator,
+                right: Box::new(bound(high_operator, high)),
+            };
+
+            rewrite_index_predicates(expr, schema, key_col, indexes);
+        }
+
+        Expression::BinaryOperation {
+            left,
+            operator: BinaryOperator::And | BinaryOperator::Or,
+            right,
+        } => {
+            rewrite_index_predicates(left, schema, key_col, indexes);
+            rewrite_index_predicates(right, schema, key_col, indexes);
+        }
+
+        Expression::BinaryOperation {
+            left,
+            operator: BinaryOperator::Eq,
+            right,
+        } if matches!(**left, Expression::Function { .. })
+            || matches!(**right, Expression::Function { .. }) =>
+        {
+            let (function, value) = match **left {
+                Expression::Function { .. } => (&**left, &**right),
+                _ => (&**right, &**left),
+            };
+
+            if let Some(predicate) = substr_prefix_predicate(function, value, key_col, indexes) {
+                *expr = predicate;
+            }
+        }
+
+        Expression::BinaryOperation {
+            left,
+            operator:
+                operator @ (BinaryOperator::Eq
+                | BinaryOperator::Lt
+                | BinaryOperator::LtEq
+                | BinaryOperator::Gt
+                | BinaryOperator::GtEq),
+            right,
+        } => {
+            // Value on the right side, flip the operator otherwise.
+            let (col, float, operator) = match (&**left, &**right) {
+                (Expression::Identifier(col), Expression::Value(Value::Float(float))) => {
+                    (col, *float, *operator)
+                }
+                (Expression::Value(Value::Float(float)), Expression::Identifier(col)) => {
+                    let flipped = match operator {
+                        BinaryOperator::Lt => BinaryOperator::Gt,
+                        BinaryOperator::LtEq => BinaryOperator::GtEq,
+                        BinaryOperator::Gt => BinaryOperator::Lt,
+                        BinaryOperator::GtEq => BinaryOperator::LtEq,
+                        other => *other,
+                    };
+                    (col, *float, flipped)
+                }
+                _ => return,
+            };
+
+            if !indexes.contains(col.as_str()) && col != key_col {
+                return;
+            }
+
+            let Some(index) = schema.index_of(col) else {
+                return;
+            };
+
+            if !matches!(
+                schema.columns[index].data_type,
+                DataType::Int | DataType::UnsignedInt | DataType::BigInt | DataType::UnsignedBigInt
+            ) {
+                return;
+            }
+
+            *expr = coerce_float_bound(col.clone(), operator, float);
+        }
+
+        Expression::Nested(inner) => rewrite_index_predicates(inner, schema, key_col, indexes),
+
+        _ => {}
+    }
+}
+
+/// Rewrites `col <operator> float` on an integer column into an equivalent
+/// comparison with an integer.
+///
+/// Ranges are rounded towards the inside: `col > 1.5` and `col >= 1.5` both
+/// become `col >= 2` while `col < 1.5` and `col <= 1.5` become `col <= 1`.
+/// Integers can't be equal to a float with a fractional part, so `col = 1.5`
+/// becomes `FALSE`, and so does any comparison with `NaN`.
+fn coerce_float_bound(col: String, operator: BinaryOperator, float: f64) -> Expression {
+    if float.is_nan() || operator == BinaryOperator::Eq && float.fract() != 0.0 {
+        return Expression::Value(Value::Bool(false));
+    }
+
+    let (operator, bound) = match operator {
+        _ if float.fract() == 0.0 => (operator, float),
+        BinaryOperator::Gt | BinaryOperator::GtEq => (BinaryOperator::GtEq, float.ceil()),
+        _ => (BinaryOperator::LtEq, float.floor()),
+    };
+
+    Expression::BinaryOperation {
+        left: Box::new(Expression::Identifier(col)),
+        operator,
+        right: Box::new(Expression::Value(Value::Number(bound as i128))),
+    }
+}
+
+/// Rewrites `SUBSTR(col, 1, n) = 'string'` on an indexed column into an
+/// equivalent predicate that [`find_index_paths`] understands.
+///
+/// `SUBSTR` returns at most `n` characters, so:
+///
+/// - If the string has exactly `n` characters then `col` must start with it,
+///   which is the same range that [`like_prefix_range`] computes.
+/// - If it has less than `n` characters then `col` must be equal to it.
+/// - If it has more than `n` characters nothing matches.
+///
+/// Returns [`None`] if the expression doesn't have that shape.
+fn substr_prefix_predicate(
+    function: &Expression,
+    value: &Expression,
+    key_col: &str,
+    indexes: &HashSet<&str>,
+) -> Option<Expression> {
+    let (
+        Expression::Function {
+            func: ScalarFunction::Substr,
+            args,
+        },
+        Expression::Value(Value::String(string)),
+    ) = (function, value)
+    else {
+        return None;
+    };
+
+    let [Expression::Identifier(col), Expression::Value(Value::Number(1)), Expression::Value(Value::Number(length))] =
+        &args[..]
+    else {
+        return None;
+    };
+
+    // SUBSTR(col, 1, 0) is always an empty string.
+    if !indexes.contains(col.as_str()) && col != key_col || *length <= 0 {
+        return None;
+    }
+
+    let bound = |operator, value: &str| Expression::BinaryOperation {
+        left: Box::new(Expression::Identifier(col.clone())),
+        operator,
+        right: Box::new(Expression::Value(Value::String(value.into()))),
+    };
+
+    Some(match (string.chars().count() as i128).cmp(length) {
+        Ordering::Greater => Expression::Value(Value::Bool(false)),
+        Ordering::Less => bound(BinaryOperator::Eq, string),
+        Ordering::Equal => {
+            let (start, end) = prefix_range(string)?;
+            Expression::BinaryOperation {
+                left: Box::new(bound(BinaryOperator::GtEq, &start)),
+                operator: BinaryOperator::And,
+                right: Box::new(bound(BinaryOperator::Lt, &end)),
+            }
+        }
+    })
+}
+
+/// Returns the range of strings that match a `LIKE` pattern composed of a
+/// fixed prefix followed by a single `%`.
+fn like_prefix_range(pattern: &str) -> Option<(String, String)> {
+    let prefix = pattern.strip_suffix('%')?;
+
+    if prefix.contains(['%', '_']) {
+        return None;
+    }
+
+    prefix_range(prefix)
+}
+
+/// Returns the range of strings that start with `prefix`.
+///
+/// The end of the range is the prefix with its last character replaced by the
+/// next one, so `'abc'` becomes `'abc'..'abd'`.
+fn prefix_range(prefix: &str) -> Option<(String, String)> {
+    if prefix.is_empty() {
+        return None;
+    }
+
+    let mut end = String::from(prefix);
+    let last = end.pop()?;
+    // Skips surrogates, there are no characters between them.
+    end.push((last as u32 + 1..=char::MAX as u32).find_map(char::from_u32)?);
+
+    Some((String::from(prefix), end))
+}
+
+/// Attempts to generate a [`LogicalAndScan`] for AND expressions whose
+/// branches can be computed using different indexes.
+///
+/// Consider this example:
+///
+/// ```sql
+/// CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE);
+///
+/// SELECT * FROM users WHERE id > 5 AND email < 'm';
+/// ```
+///
+/// [`find_index_paths`] would only scan one of the branches and use the other
+/// one as a filter. Instead, we can scan both, sort the keys returned by each
+/// branch and keep only those that appear in both streams. The tuples are
+/// fetched from the table afterwards, so tuples that only match one of the
+/// branches are never read. The original expression still acts as a filter
+/// on top of the plan since index keys don't tell us anything about other
+/// columns.
+///
+/// This only applies to top level AND expressions where each branch uses
+/// exactly one column and the columns are different. If either branch is
+/// only composed of exact matches it's cheaper to fetch those tuples directly
+/// and filter them, so we don't intersect anything in that case either.
+fn generate_index_intersection_plan<F: Seek + Read + Write + FileOps>(
+    db: &mut Database<F>,
+    table: &TableMetadata,
+    expr: &Expression,
+) -> Result<Option<Plan<F>>, DbError> {
+    let Expression::BinaryOperation {
+        left,
+        operator: BinaryOperator::And,
+        right,
+    } = expr
+    else {
+        return Ok(None);
+    };
+
+    let key_col = &table.schema.columns[0].name;
+    let indexes = indexed_columns(table);
+
+    let left_paths = find_index_paths(key_col, &indexes, left, &mut HashSet::new());
+    let right_paths = find_index_paths(key_col, &indexes, right, &mut HashSet::new());
+
+    let (Some((left_col, left_ranges)), Some((right_col, right_ranges))) =
+        (single_path(left_paths), single_path(right_paths))
+    else {
+        return Ok(None);
+    };
+
+    if left_col == right_col
+        || left_ranges.iter().copied().all(is_exact_match)
+        || right_ranges.iter().copied().all(is_exact_match)
+    {
+        return Ok(None);
+    }
+
+    let [left, right] =
+        [(left_col, left_ranges), (right_col, right_ranges)].map(|(col, ranges)| {
+            let mut scans = generate_index_scans(db, table, col, ranges)?;
+
+            let source = if scans.len() == 1 {
+                scans.pop_front().unwrap()
+            } else {
+                Plan::LogicalOrScan(LogicalOrScan { scans })
+            };
+
+            // The table BTree already returns its keys in order.
+            Ok::<_, SqlError>(
+                if col == key_col && !matches!(source, Plan::LogicalOrScan(_)) {
+                    source
+                } else {
+                    sort_keys(db, table.key_only_schema(), 0, source)
+                },
+            )
+        });
+
+    let mut branches = [left?, right?];
+
+    // The table key branch always goes first regardless of where it appears
+    // in the AND expression. Makes plans easier to predict.
+    if right_col == key_col {
+        branches.reverse();
+    }
+
+    let [left, right] = branches;
+
+    Ok(Some(Plan::KeyScan(KeyScan {
+        comparator: table.comparator(),
+        pager: Rc::clone(&db.pager),
+        source: Box::new(Plan::LogicalAndScan(LogicalAndScan {
+            left: Box::new(left),
+            right: Box::new(right),
+        })),
+        table: table.clone(),
+        rows_scanned: 0,
+    })))
+}
+
+/// Returns the only column and ranges of a path or [`None`] if the path
+/// contains zero or more than one column.
+fn single_path<'e>(
+    paths: HashMap<&'e str, VecDeque<IndexRangeBounds<'e>>>,
+) -> Option<(&'e str, VecDeque<IndexRangeBounds<'e>>)> {
+    if paths.len() != 1 {
+        return None;
+    }
+
+    paths.into_iter().next()
+}
+
+/// Columns that can drive index scans.
+///
+/// TODO: Only the first column of multi-column indexes is used to scan them.
+/// Conditions on the rest of the columns are checked by the filter on top of
+/// the scan, but they could be used to narrow down the range as well.
+fn indexed_columns(table: &TableMetadata) -> HashSet<&str> {
+    HashSet::from_iter(
+        table
+            .indexes
+            .iter()
+            .map(|index| index.columns[0].name.as_str()),
+    )
+}
+
+/// Returns the index whose first column is `col`.
+///
+/// If multiple indexes start with the same column the one with fewer columns
+/// is preferred because equality conditions on single column indexes can use
+/// [`ExactMatch`] plans.
+fn leading_column_index<'t>(table: &'t TableMetadata, col: &str) -> Option<&'t IndexMetadata> {
+    table
+        .indexes
+        .iter()
+        .filter(|index| index.columns[0].name == col)
+        .min_by_key(|index| index.columns.len())
+}
+
+/// Builds the key only [`ExactMatch`] and [`RangeScan`] plans that visit the
+/// given ranges of a column.
+///
+/// The table BTree is used if the column is the table key, otherwise the index
+/// of the column is used. Multi-column indexes are always scanned with
+/// [`RangeScan`] plans because many entries can share the same first column.
+fn generate_index_scans<F>(
+    db: &Database<F>,
+    table: &TableMetadata,
+    col: &str,
+    ranges: VecDeque<IndexRangeBounds>,
+) -> Result<VecDeque<Plan<F>>, SqlError> {
+    let (relation, direction) = match leading_column_index(table, col) {
+        Some(index) => (Relation::Index(index.clone()), index.directions[0]),
+        None => (Relation::Table(table.clone()), Direction::Asc),
+    };
+
+    let version = db.pager.borrow().format_version;
+    let data_type = table.schema.columns[table.schema.index_of(col).unwrap()].data_type;
+
+    ranges
+        .into_iter()
+        .map(|range| {
+            let serialize = |bound: Bound<&Value>| match bound {
+                Bound::Included(value) => Ok(Bound::Included(tuple::serialize_key_with(
+                    version, &data_type, value,
+                )?)),
+                Bound::Excluded(value) => Ok(Bound::Excluded(tuple::serialize_key_with(
+                    version, &data_type, value,
+                )?)),
+                Bound::Unbounded => Ok::<_, SqlError>(Bound::Unbounded),
+            };
+
+            let start = serialize(range.start_bound())?;
+            let end = serialize(range.end_bound())?;
+
+            let expr = range_to_expr(col, range);
+            let pager = Rc::clone(&db.pager);
+            let relation = relation.clone();
+
+            Ok(if is_exact_match(range) && !relation.has_composite_key() {
+                let Bound::Included(key) = start else {
+                    unreachable!();
+                };
+                Plan::ExactMatch(ExactMatch {
+                    key,
+                    relation,
+                    expr,
+                    pager,
+                    emit_table_key_only: true,
+                    done: false,
+                    rows_scanned: 0,
+                })
+            } else {
+                // DESC indexes store the greatest keys first, so the range
+                // starts at the upper bound.
+                let range = match direction {
+                    Direction::Asc => (start, end),
+                    Direction::Desc => (end, start),
+                };
+
+                Plan::RangeScan(RangeScan::from(RangeScanConfig {
+                    range,
+                    relation,
+                    expr,
+                    pager,
+                    emit_table_key_only: true,
+                }))
+            })
+        })
+        .collect()
+}
+
+/// Sorts the tuples returned by the given source by the table key, which is
+/// located at `key_index` in `schema`.
+fn sort_keys<F: Seek + Read + Write + FileOps>(
+    db: &Database<F>,
+    schema: Schema,
+    key_index: usize,
+    source: Plan<F>,
+) -> Plan<F> {
+    let work_dir = db.work_dir.clone();
+    let page_size = db.pager.borrow().page_size;
+
+    Plan::Sort(Sort::from(SortConfig {
+        page_size,
+        work_dir: work_dir.clone(),
+        collection: Collect::from(CollectConfig {
+            source: Box::new(source),
+            work_dir,
+            schema: schema.clone(),
+            mem_buf_size: page_size,
+        }),
+        comparator: TuplesComparator {
+            schema: schema.clone(),
+            sort_schema: schema,
+            sort_keys_indexes: vec![key_index],
+            directions: vec![Direction::Asc],
+        },
+        input_buffers: DEFAULT_SORT_INPUT_BUFFERS,
+    }))
+}
+
 /// Reference to bounds in the [`Expression`] tree.
 ///
 /// We use this to avoid cloning/serializing keys until the last moment.
@@ -504,13 +1604,12 @@ type IndexRangeBounds<'v> = (Bound<&'v Value>, Bound<&'v Value>);
 /// (id < 20) OR (id > 40 AND id < 60) OR (id > 100) OR (email > 20 AND email <= 25)
 /// ```
 ///
-/// There is no representation of `AND` clauses because we never scan two
-/// indexes when there's an `AND` statement. Postgres for example does that
-/// sometimes through their BitmapAnd plan, where they scan two indexes and then
-/// scan only the pages where both are true. We could do that as well, but
-/// supporting OR statements is already complex enough for a toy database. In
-/// the case of AND statements on separate indexes we'll just scan one of them
-/// and call it a day.
+/// There is no representation of `AND` clauses because this function never
+/// scans two indexes when there's an `AND` statement. Postgres for example does
+/// that sometimes through their BitmapAnd plan, where they scan two indexes and
+/// then scan only the pages where both are true. We only do something similar
+/// for top level `AND` expressions, see [`generate_index_intersection_plan`].
+/// Everywhere else we'll just scan one of the indexes and call it a day.
 fn find_index_paths<'e>(
     key_col: &str,
     indexes: &HashSet<&str>,
@@ -902,16 +2001,13 @@ fn cmp_start_bounds(bound1: &Bound<&Value>, bound2: &Bound<&Value>) -> Ordering
             Bound::Excluded(value1) | Bound::Included(value1),
             Bound::Excluded(value2) | Bound::Included(value2),
         ) => {
-            let ordering = value1.partial_cmp(value2).unwrap_or_else(|| {
-                panic!(
-                    "Type errors at this point should be impossible: cmp {value1} against {value2}"
-                )
-            });
+            let ordering = cmp_bound_values(value1, value2);
 
             if ordering != Ordering::Equal {
                 return ordering;
             }
 
+            // x >= 5 starts before x > 5.
             match (bound1, bound2) {
                 (Bound::Included(_), Bound::Excluded(_)) => Ordering::Less,
                 (Bound::Excluded(_), Bound::Included(_)) => Ordering::Greater,
@@ -926,22 +2022,74 @@ fn cmp_start_bounds(bound1: &Bound<&Value>, bound2: &Bound<&Value>) -> Ordering
 /// [`Bound::Unbounded`] is treated as "infinite" or "last value", which means
 /// it's always [`Ordering::Greater`] than anything else.
 fn cmp_end_bounds(bound1: &Bound<&Value>, bound2: &Bound<&Value>) -> Ordering {
-    let ordering = cmp_start_bounds(bound1, bound2);
+    match (bound1, bound2) {
+        (Bound::Unbounded, Bound::Unbounded) => Ordering::Equal,
+        (Bound::Unbounded, _) => Ordering::Greater,
+        (_, Bound::Unbounded) => Ordering::Less,
+        (
+            Bound::Excluded(value1) | Bound::Included(value1),
+            Bound::Excluded(value2) | Bound::Included(value2),
+        ) => {
+            let ordering = cmp_bound_values(value1, value2);
+
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
 
-    if let (Bound::Unbounded, _) | (_, Bound::Unbounded) = (bound1, bound2) {
-        return ordering.reverse();
+            // x < 5 ends before x <= 5.
+            match (bound1, bound2) {
+                (Bound::Excluded(_), Bound::Included(_)) => Ordering::Less,
+                (Bound::Included(_), Bound::Excluded(_)) => Ordering::Greater,
+                _ => Ordering::Equal,
+            }
+        }
     }
+}
 
-    ordering
+/// Compares the values of two bounds on the same column.
+fn cmp_bound_values(value1: &Value, value2: &Value) -> Ordering {
+    value1.partial_cmp(value2).unwrap_or_else(|| {
+        panic!("Type errors at this point should be impossible: cmp {value1} against {value2}")
+    })
 }
 
-/// Compares a "start bound" with an "end bound".
-fn cmp_start_end_bounds(start: &Bound<&Value>, end: &Bound<&Value>) -> Ordering {
-    if *start == Bound::Unbounded {
-        return Ordering::Less;
+/// Returns `true` if there are no values that satisfy both the `start` and
+/// `end` bounds. For example, `x > 5 AND x <= 5` or `x >= 5 AND x < 5`.
+fn is_empty_range(start: &Bound<&Value>, end: &Bound<&Value>) -> bool {
+    let (
+        Bound::Excluded(start_value) | Bound::Included(start_value),
+        Bound::Excluded(end_value) | Bound::Included(end_value),
+    ) = (start, end)
+    else {
+        return false;
+    };
+
+    match cmp_bound_values(start_value, end_value) {
+        Ordering::Less => false,
+        Ordering::Greater => true,
+        Ordering::Equal => !matches!((start, end), (Bound::Included(_), Bound::Included(_))),
     }
+}
+
+/// Returns `true` if there are values after the `end` bound of one range and
+/// before the `start` bound of the next range.
+///
+/// Overlapping ranges don't leave any gap and neither do adjacent ranges like
+/// `x < 5` and `x >= 5`. However, `x < 5` and `x > 5` leave out the value `5`.
+fn has_gap_between(end: &Bound<&Value>, start: &Bound<&Value>) -> bool {
+    let (
+        Bound::Excluded(end_value) | Bound::Included(end_value),
+        Bound::Excluded(start_value) | Bound::Included(start_value),
+    ) = (end, start)
+    else {
+        return false;
+    };
 
-    cmp_end_bounds(start, end)
+    match cmp_bound_values(end_value, start_value) {
+        Ordering::Less => true,
+        Ordering::Greater => false,
+        Ordering::Equal => matches!((end, start), (Bound::Excluded(_), Bound::Excluded(_))),
+    }
 }
 
 /// Compares two complete ranges and returns an [`Ordering`] variant.
@@ -976,7 +2124,7 @@ fn range_intersection<'v>(
     let intersection_start = cmp::max_by(start1, start2, cmp_start_bounds);
     let intersection_end = cmp::min_by(end1, end2, cmp_end_bounds);
 
-    if cmp_start_end_bounds(&intersection_start, &intersection_end) == Ordering::Greater {
+    if is_empty_range(&intersection_start, &intersection_end) {
         return None;
     }
 
@@ -989,7 +2137,8 @@ fn range_intersection<'v>(
 /// If they can't be joined together then the union is simply both of them. For
 /// example, the ranges `[5..15, 10..25]` can be merged to produce `[5..25]`,
 /// but the ranges `[5..15, 20..30]` cannot be merged to produce a new one.
-/// Ranges must already be sorted.
+/// Adjacent ranges such as `[5..10), [10..15]` are also merged. Ranges must
+/// already be sorted.
 fn range_union<'v>(
     (start1, end1): (Bound<&'v Value>, Bound<&'v Value>),
     (start2, end2): (Bound<&'v Value>, Bound<&'v Value>),
@@ -1001,7 +2150,7 @@ fn range_union<'v>(
         (start2, end2),
     );
 
-    if cmp_start_end_bounds(&start2, &end1) == Ordering::Greater {
+    if has_gap_between(&end1, &start2) {
         return None;
     }
 
@@ -1146,6 +2295,51 @@ mod tests {
         })
     }
 
+    #[test]
+    fn merge_adjacent_or_key_paths() {
+        assert_find_index_path(IndexPath {
+            pk: "id",
+            indexes: &[],
+            expr: "id < 10 OR id = 10",
+            expected: HashMap::from([(
+                "id",
+                VecDeque::from([(Bound::Unbounded, Bound::Included(&Value::Number(10)))]),
+            )]),
+        })
+    }
+
+    #[test]
+    fn dont_merge_or_key_paths_with_gap() {
+        assert_find_index_path(IndexPath {
+            pk: "id",
+            indexes: &[],
+            expr: "id < 10 OR (id > 10 AND id < 20)",
+            expected: HashMap::from([(
+                "id",
+                VecDeque::from([
+                    (Bound::Unbounded, Bound::Excluded(&Value::Number(10))),
+                    (
+                        Bound::Excluded(&Value::Number(10)),
+                        Bound::Excluded(&Value::Number(20)),
+                    ),
+                ]),
+            )]),
+        })
+    }
+
+    #[test]
+    fn intersect_ranges_with_same_end_value() {
+        assert_find_index_path(IndexPath {
+            pk: "id",
+            indexes: &[],
+            expr: "id <= 10 AND id < 10",
+            expected: HashMap::from([(
+                "id",
+                VecDeque::from([(Bound::Unbounded, Bound::Excluded(&Value::Number(10)))]),
+            )]),
+        })
+    }
+
     #[test]
     fn short_circuit_when_and_never_evaluates_to_true() {
         assert_find_index_path(IndexPath {
@@ -6,21 +6,29 @@
 use std::{
     collections::VecDeque,
     io::{Read, Seek, Write},
+    iter, mem,
+    path::PathBuf,
     rc::Rc,
 };
 
 use super::optimizer;
 use crate::{
-    db::{Database, DatabaseContext, DbError, Schema, SqlError},
+    db::{Database, DatabaseContext, DbError, Schema, SqlError, ROW_ID_COL},
     paging,
     sql::{
         analyzer,
-        statement::{Column, DataType, Expression, Statement},
+        statement::{
+            AggregateFunction, BinaryOperator, Column, DataType, Direction, Expression,
+            InsertSource, Join, OrderBy, ScalarFunction, SelectItem, Statement, Value,
+        },
     },
+    storage::{tuple, Cursor},
     vm::{
         plan::{
-            Collect, CollectConfig, Delete, Insert, Plan, Project, Sort, SortConfig, SortKeysGen,
-            TuplesComparator, Update, Values, DEFAULT_SORT_INPUT_BUFFERS,
+            Collect, CollectConfig, Delete, Distinct, Empty, Filter, GroupBy, GroupByConfig,
+            Insert, Limit, NestedLoopJoin, NestedLoopJoinConfig, Plan, Project, SeqScan, Sort,
+            SortConfig, SortKeysGen, TopN, TopNConfig, TuplesComparator, Update, Values,
+            DEFAULT_SORT_INPUT_BUFFERS, TOP_N_MAX_LIMIT,
         },
         VmDataType,
     },
@@ -37,121 +45,417 @@ pub(crate) fn generate_plan<F: Seek + Read + Write + paging::io::FileOps>(
             columns,
             values,
         } => {
-            let source = Box::new(Plan::Values(Values {
-                values: VecDeque::from([values]),
-            }));
+            let (source, needs_row_ids) = match values {
+                InsertSource::Values(values) => {
+                    let values = Plan::Values(Values {
+                        values: VecDeque::from(values),
+                    });
+
+                    (values, false)
+                }
+
+                InsertSource::Select(select) => {
+                    let select = generate_plan(*select, db)?;
+                    let work_dir = db.work_dir.clone();
+                    let page_size = db.pager.borrow().page_size;
+
+                    // The SELECT might read from the same table we're
+                    // inserting into, so collect all the rows before inserting
+                    // anything. Otherwise the inserts would destroy the scan
+                    // cursor and we might end up reading our own inserts.
+                    let collect = Plan::Collect(Collect::from(CollectConfig {
+                        schema: select.schema().unwrap(),
+                        source: Box::new(select),
+                        work_dir,
+                        mem_buf_size: page_size,
+                    }));
+
+                    (collect, true)
+                }
+            };
 
             let table = db.table_metadata(&into)?.clone();
+            let generate_row_ids = needs_row_ids && table.schema.columns[0].name == ROW_ID_COL;
+
+            // The Insert plan generates row IDs on its own copy of the table
+            // metadata, so the cached row ID counter will be out of date after
+            // the plan runs. Next time the table is used it will be loaded from
+            // disk again.
+            if generate_row_ids {
+                db.context.invalidate(&into);
+            }
 
             Plan::Insert(Insert {
-                source,
-                comparator: table.comparator()?,
-                table: db.table_metadata(&into)?.clone(),
+                source: Box::new(source),
+                comparator: table.comparator(),
+                table,
                 pager: Rc::clone(&db.pager),
+                generate_row_ids,
             })
         }
 
         Statement::Select {
+            distinct,
             columns,
             from,
+            joins,
             r#where,
-            order_by,
+            group_by,
+            having,
+            mut order_by,
+            limit,
+            offset,
         } => {
-            let mut source = optimizer::generate_scan_plan(&from, r#where, db)?;
+            let single_table = joins.is_empty();
+
+            let (mut source, schema, table_keys, scan_order) = match &from {
+                // Constant queries work with one empty row. WHERE can still
+                // discard it.
+                None => {
+                    let mut source = Plan::Values(Values {
+                        values: VecDeque::from([vec![]]),
+                    });
+
+                    if let Some(filter) = r#where {
+                        source = Plan::Filter(Filter {
+                            source: Box::new(source),
+                            schema: Schema::empty(),
+                            filter,
+                        });
+                    }
+
+                    (source, Schema::empty(), vec![], None)
+                }
+
+                Some(from) if single_table => {
+                    let mut source = optimizer::generate_scan_plan(from, r#where, db)?;
+                    let table_schema = db.table_metadata(from)?.schema.clone();
+                    let key = table_schema.columns[0].name.clone();
+
+                    let exprs = columns
+                        .iter()
+                        .map(|item| &item.expr)
+                        .chain(&group_by)
+                        .chain(&having)
+                        .chain(order_by.iter().map(|order| &order.expr));
+
+                    let (schema, scan_order) = match optimizer::index_only_scan(&mut source, exprs)
+                    {
+                        Some((index_schema, index_order)) => (index_schema, Some(index_order)),
+                        None => (table_schema, None),
+                    };
+
+                    (source, schema, vec![key], scan_order)
+                }
+
+                Some(from) => {
+                    let mut table_keys = Vec::with_capacity(joins.len() + 1);
+                    for table in iter::once(from).chain(joins.iter().map(|join| &join.table)) {
+                        let key = &db.table_metadata(table)?.schema.columns[0].name;
+                        table_keys.push(format!("{table}.{key}"));
+                    }
+                    let (source, schema) = generate_join_plan(from, joins, r#where, db)?;
+                    (source, schema, table_keys, None)
+                }
+            };
 
             let page_size = db.pager.borrow().page_size;
 
             let work_dir = db.work_dir.clone();
-            let table = db.table_metadata(&from)?;
 
-            if !order_by.is_empty()
-                && order_by != [Expression::Identifier(table.schema.columns[0].name.clone())]
-            {
-                let mut sort_schema = table.schema.clone();
-                let mut sort_keys_indexes = Vec::with_capacity(order_by.len());
-
-                // Precompute all the sort keys indexes so that the sorter
-                // doesn't waste time figuring out where the columns are.
-                for expr in &order_by {
-                    let index = match expr {
-                        Expression::Identifier(col) => table.schema.index_of(col).unwrap(),
-
-                        _ => {
-                            let index = sort_schema.len();
-                            let data_type = resolve_unknown_type(&table.schema, expr)?;
-                            let col = Column::new(&format!("{expr}"), data_type);
-                            sort_schema.push(col);
-
-                            index
-                        }
-                    };
+            // Set to true if the sorting plan already takes care of LIMIT.
+            let mut limit_applied = false;
+
+            // Tables are already sorted by their key in ascending order, so
+            // there's nothing to do in that case. Other orders might still be
+            // provided by the scan, see [`optimizer::scan_in_order`].
+            //
+            // Index only scans return the tuples in index order instead.
+            let key = match single_table {
+                true => table_keys.first().cloned(),
+                false => schema.columns.first().map(|key| key.name.clone()),
+            };
+            let scan_order = scan_order.unwrap_or_else(|| {
+                Vec::from_iter(key.map(|key| OrderBy {
+                    expr: Expression::Identifier(key),
+                    direction: Direction::Asc,
+                }))
+            });
+
+            // Only the key of a single table is unique, joins can repeat it.
+            let unique_key = match single_table {
+                true => table_keys.first().cloned(),
+                false => None,
+            };
+
+            let aggregate = !group_by.is_empty()
+                || having.is_some()
+                || columns.iter().any(|item| item.expr.contains_aggregate());
+
+            // In aggregate queries ORDER BY can only reference grouping keys
+            // and aggregate functions. When it only references grouping keys
+            // we sort the input before grouping and GroupBy returns the groups
+            // in the order it finds them, so they come out sorted without
+            // another Sort node on top. Aggregate functions can only be
+            // computed after grouping, so in that case we sort the groups.
+            let mut groups_order =
+                match aggregate && order_by.iter().any(|order| order.expr.contains_aggregate()) {
+                    true => mem::take(&mut order_by),
+                    false => vec![],
+                };
 
-                    sort_keys_indexes.push(index);
+            // Groups are unique, so the grouping keys break all the ties.
+            if db.deterministic_order && !groups_order.is_empty() {
+                for expr in &group_by {
+                    if !groups_order.iter().any(|order| order.expr == *expr) {
+                        groups_order.push(OrderBy {
+                            expr: expr.clone(),
+                            direction: Direction::Asc,
+                        });
+                    }
                 }
+            }
 
-                // If there are no expressions that need to be evaluated for
-                // sorting then just skip the sort key generation completely,
-                // we already have all the sort keys we need.
-                let collect_source = if sort_schema.len() > table.schema.len() {
-                    Plan::SortKeysGen(SortKeysGen {
-                        source: Box::new(source),
-                        schema: table.schema.clone(),
-                        gen_exprs: order_by
-                            .into_iter()
-                            .filter(|expr| !matches!(expr, Expression::Identifier(_)))
-                            .collect(),
-                    })
-                } else {
-                    source
-                };
+            // Table keys are unique, so using them as the last sort keys breaks
+            // all the ties between rows with equal ORDER BY keys.
+            //
+            // DISTINCT sorts by all the output columns anyway, which are unique
+            // after removing duplicates, so ties are already broken there.
+            if db.deterministic_order && !order_by.is_empty() && !distinct {
+                for key in table_keys {
+                    let expr = Expression::Identifier(key);
+                    if !order_by.iter().any(|order| order.expr == expr) {
+                        order_by.push(OrderBy {
+                            expr,
+                            direction: Direction::Asc,
+                        });
+                    }
+                }
+            }
 
-                source = Plan::Sort(Sort::from(SortConfig {
+            // DISTINCT queries can only sort by expressions of the SELECT list,
+            // which is done by the Distinct plan after projecting the rows.
+            // Position of each ORDER BY key in the output tuples.
+            let distinct_order = if distinct {
+                Vec::from_iter(order_by.drain(..).map(|order| {
+                    let index = columns.iter().position(|item| item.expr == order.expr);
+                    (index.unwrap(), order.direction)
+                }))
+            } else {
+                vec![]
+            };
+
+            let sorted_by_source = provides_order(&scan_order, &order_by, unique_key.is_some());
+
+            // Order of the tuples that reach the GroupBy plan, whether they
+            // are sorted below or returned in that order by the scan.
+            let input_order = match order_by.is_empty() {
+                true => scan_order.clone(),
+                false => order_by.clone(),
+            };
+
+            // The table BTree or one of its indexes might already return the
+            // tuples in the requested order.
+            let sorted_by_scan = match &from {
+                Some(table) if single_table && !order_by.is_empty() && !sorted_by_source => {
+                    optimizer::scan_in_order(table, &mut source, &order_by, db)?
+                }
+                _ => false,
+            };
+
+            // With a small LIMIT we only need to remember the first N tuples,
+            // no need to sort the entire table. OFFSET tuples are also part of
+            // the top N, the Limit plan will skip them later.
+            let top_n = limit
+                .map(|limit| limit.saturating_add(offset.unwrap_or(0)))
+                .filter(|top_n| *top_n <= TOP_N_MAX_LIMIT);
+
+            if !order_by.is_empty() && !sorted_by_source && !sorted_by_scan {
+                // LIMIT applies to groups, not to the tuples that we're
+                // sorting here, so aggregate queries need all of them.
+                let top_n = top_n.filter(|_| !aggregate);
+                limit_applied = top_n.is_some() && offset.is_none();
+
+                source = generate_sort_plan(
+                    source,
+                    &schema,
+                    order_by,
+                    top_n,
                     page_size,
-                    work_dir: work_dir.clone(),
-                    collection: Collect::from(CollectConfig {
-                        source: Box::new(collect_source),
-                        work_dir,
-                        schema: sort_schema.clone(),
-                        mem_buf_size: page_size,
-                    }),
-                    comparator: TuplesComparator {
-                        schema: table.schema.clone(),
-                        sort_schema,
-                        sort_keys_indexes,
-                    },
-                    input_buffers: DEFAULT_SORT_INPUT_BUFFERS,
-                }));
+                    work_dir.clone(),
+                )?;
             }
 
             let mut output_schema = Schema::empty();
 
-            for expr in &columns {
-                match expr {
-                    Expression::Identifier(ident) => output_schema
-                        .push(table.schema.columns[table.schema.index_of(ident).unwrap()].clone()),
+            for SelectItem { expr, alias } in &columns {
+                let mut col = match expr {
+                    Expression::Identifier(ident) => {
+                        schema.columns[schema.index_of(ident).unwrap()].clone()
+                    }
+
+                    _ => Column {
+                        name: expr.to_string(),
+                        data_type: resolve_unknown_type(&schema, expr)?,
+                        constraints: vec![],
+                        default: None,
+                    },
+                };
 
-                    _ => {
-                        output_schema.push(Column {
-                            name: expr.to_string(), // TODO: AS alias
-                            data_type: resolve_unknown_type(&table.schema, expr)?,
-                            constraints: vec![],
-                        });
+                if let Some(alias) = alias {
+                    col.name = alias.clone();
+                }
+
+                output_schema.push(col);
+            }
+
+            let mut input_schema = schema.clone();
+            let mut projection: Vec<Expression> =
+                columns.into_iter().map(|item| item.expr).collect();
+
+            if aggregate {
+                let mut aggregates = Vec::new();
+                for expr in projection
+                    .iter()
+                    .chain(&having)
+                    .chain(groups_order.iter().map(|order| &order.expr))
+                    .flat_map(Expression::aggregates)
+                {
+                    if !aggregates.contains(expr) {
+                        aggregates.push(expr.clone());
                     }
                 }
+
+                let mut group_schema = Schema::empty();
+
+                for expr in group_by.iter().chain(&aggregates) {
+                    group_schema.push(match expr {
+                        Expression::Identifier(ident) => {
+                            schema.columns[schema.index_of(ident).unwrap()].clone()
+                        }
+
+                        _ => Column::new(&expr.to_string(), resolve_unknown_type(&schema, expr)?),
+                    });
+                }
+
+                // The projection runs on top of the groups, so grouping keys
+                // and aggregate functions become references to the group
+                // columns.
+                projection = projection
+                    .into_iter()
+                    .map(|expr| replace_grouped_expressions(expr, &group_by, &aggregates))
+                    .collect();
+
+                // HAVING filters the groups, so it also works with references
+                // to the group columns.
+                let having =
+                    having.map(|expr| replace_grouped_expressions(expr, &group_by, &aggregates));
+
+                // Same for ORDER BY when sorting the groups.
+                let groups_order: Vec<_> = groups_order
+                    .into_iter()
+                    .map(|order| OrderBy {
+                        expr: replace_grouped_expressions(order.expr, &group_by, &aggregates),
+                        direction: order.direction,
+                    })
+                    .collect();
+
+                source = Plan::GroupBy(GroupBy::from(GroupByConfig {
+                    source: Box::new(source),
+                    input_schema: schema.clone(),
+                    output_schema: group_schema.clone(),
+                    sorted: groups_are_contiguous(&input_order, &group_by, unique_key.as_deref()),
+                    group_by,
+                    aggregates,
+                }));
+
+                if let Some(filter) = having {
+                    source = Plan::Filter(Filter {
+                        source: Box::new(source),
+                        schema: group_schema.clone(),
+                        filter,
+                    });
+                }
+
+                if !groups_order.is_empty() {
+                    limit_applied = top_n.is_some() && offset.is_none();
+
+                    source = generate_sort_plan(
+                        source,
+                        &group_schema,
+                        groups_order,
+                        top_n,
+                        page_size,
+                        work_dir.clone(),
+                    )?;
+                }
+
+                input_schema = group_schema;
             }
 
             // No need to project if the output schema is the exact same as the
-            // table schema.
-            if table.schema == output_schema {
-                return Ok(source);
+            // input schema.
+            if input_schema != output_schema {
+                source = Plan::Project(Project {
+                    input_schema,
+                    output_schema: output_schema.clone(),
+                    projection,
+                    source: Box::new(source),
+                });
             }
 
-            Plan::Project(Project {
-                input_schema: table.schema.clone(),
-                output_schema,
-                projection: columns,
-                source: Box::new(source),
-            })
+            // Duplicates are removed by sorting the output tuples so that equal
+            // tuples end up next to each other. ORDER BY keys go first to get
+            // the requested order and the rest of the columns break the ties.
+            if distinct {
+                let (mut sort_keys_indexes, mut directions): (Vec<_>, Vec<_>) =
+                    distinct_order.into_iter().unzip();
+
+                for index in 0..output_schema.len() {
+                    if !sort_keys_indexes.contains(&index) {
+                        sort_keys_indexes.push(index);
+                        directions.push(Direction::Asc);
+                    }
+                }
+
+                source = Plan::Distinct(Distinct {
+                    source: Box::new(Plan::Sort(Sort::from(SortConfig {
+                        page_size,
+                        work_dir: work_dir.clone(),
+                        collection: Collect::from(CollectConfig {
+                            source: Box::new(source),
+                            work_dir,
+                            schema: output_schema.clone(),
+                            mem_buf_size: page_size,
+                        }),
+                        comparator: TuplesComparator {
+                            schema: output_schema.clone(),
+                            sort_schema: output_schema,
+                            sort_keys_indexes,
+                            directions,
+                        },
+                        input_buffers: DEFAULT_SORT_INPUT_BUFFERS,
+                    }))),
+                    previous: None,
+                });
+            }
+
+            // LIMIT 0 never returns anything, no need to scan the tables.
+            if limit == Some(0) {
+                return Ok(Plan::Empty(Empty {
+                    schema: source.schema().unwrap_or_else(Schema::empty),
+                }));
+            }
+
+            if (limit.is_some() && !limit_applied) || offset.is_some() {
+                source = Plan::Limit(Limit {
+                    source: Box::new(source),
+                    limit,
+                    offset: offset.unwrap_or(0),
+                });
+            }
+
+            source
         }
 
         Statement::Update {
@@ -182,7 +486,7 @@ pub(crate) fn generate_plan<F: Seek + Read + Write + paging::io::FileOps>(
             }
 
             Plan::Update(Update {
-                comparator: metadata.comparator()?,
+                comparator: metadata.comparator(),
                 table: metadata.clone(),
                 assignments: columns,
                 pager: Rc::clone(&db.pager),
@@ -206,7 +510,7 @@ pub(crate) fn generate_plan<F: Seek + Read + Write + paging::io::FileOps>(
             }
 
             Plan::Delete(Delete {
-                comparator: metadata.comparator()?,
+                comparator: metadata.comparator(),
                 table: metadata.clone(),
                 pager: Rc::clone(&db.pager),
                 source: Box::new(source),
@@ -221,22 +525,84 @@ pub(crate) fn generate_plan<F: Seek + Read + Write + paging::io::FileOps>(
     })
 }
 
+/// Generates the plan that scans and joins all the tables of `SELECT`
+/// statements with `JOIN` clauses.
+///
+/// The first table is the outer source of a [`NestedLoopJoin`] and each `JOIN`
+/// clause wraps the previous plan in a new [`NestedLoopJoin`] that scans the
+/// joined table. Returns the plan along with the schema of its tuples, where
+/// all the columns are qualified with their table name. The `WHERE` clause is
+/// applied on top of all the joins, indexes are not used at all for now.
+fn generate_join_plan<F: Seek + Read + Write + paging::io::FileOps>(
+    from: &str,
+    joins: Vec<Join>,
+    filter: Option<Expression>,
+    db: &mut Database<F>,
+) -> Result<(Plan<F>, Schema), DbError> {
+    let mut source = optimizer::generate_scan_plan(from, None, db)?;
+    let mut schema = db.table_metadata(from)?.schema.qualified(from);
+
+    for Join { table, on } in joins {
+        let metadata = db.table_metadata(&table)?.clone();
+
+        for col in metadata.schema.qualified(&table).columns {
+            schema.push(col);
+        }
+
+        source = Plan::NestedLoopJoin(NestedLoopJoin::from(NestedLoopJoinConfig {
+            outer: Box::new(source),
+            inner: SeqScan {
+                cursor: Cursor::new(metadata.root, 0),
+                pager: Rc::clone(&db.pager),
+                table: metadata,
+                rows_scanned: 0,
+            },
+            schema: schema.clone(),
+            on,
+        }));
+    }
+
+    match filter {
+        // Nothing to join if the filter never matches.
+        Some(filter) if optimizer::never_matches(&filter) => {
+            source = Plan::Empty(Empty {
+                schema: schema.clone(),
+            });
+        }
+
+        Some(filter) if !optimizer::always_matches(&filter) => {
+            source = Plan::Filter(Filter {
+                source: Box::new(source),
+                schema: schema.clone(),
+                filter,
+            });
+        }
+
+        _ => {}
+    }
+
+    Ok((source, schema))
+}
+
 /// Returns a concrete [`DataType`] for an expression that hasn't been executed
 /// yet.
 ///
-/// TODO: There are no expressions that can evaluate to strings as of right now
-/// since we didn't implement `CONCAT()` or any other similar function, so
-/// strings can only come from identifiers. The [`analyzer`] should never return
-/// [`VmDataType::String`], so it doesn't matter what type we return in that
-/// case.
+/// Strings can come from identifiers, literals, `MIN()` and `MAX()` over them,
+/// the `||` operator, `CASE` expressions or scalar functions. Concatenations
+/// can't be longer than the sum of their operands, so that's the maximum
+/// length of the resulting `VARCHAR`. Functions are computed from the length
+/// of their string argument. Strings
+/// whose length we can't figure out default to the biggest `VARCHAR` size that
+/// we can store.
 ///
 /// The real problem is when expressions evaluate to numbers becase we don't
-/// know the exact kind of number. An expression with a raw value like
-/// 4294967296 should evaluate to [`DataType::UnsignedBigInt`] but -65536 should
-/// probably evaluate to [`DataType::Int`]. Expressions that have identifiers in
-/// them should probably evaluate to the type of the identifier, but what if
-/// there are multiple identifiers of different integer types? Not gonna worry
-/// about this for now, this is a toy database after all :)
+/// know the exact kind of number. Literals like -65536 are easy, they get the
+/// narrowest integer type that can store them, [`DataType::Int`] in this case.
+/// Expressions that have identifiers in them should probably evaluate to the
+/// type of the identifier, but what if there are multiple identifiers of
+/// different integer types? Not gonna worry about this for now, this is a toy
+/// database after all :) They default to [`DataType::BigInt`] and values that
+/// don't fit fail when serialized.
 fn resolve_unknown_type(schema: &Schema, expr: &Expression) -> Result<DataType, SqlError> {
     Ok(match expr {
         Expression::Identifier(col) => {
@@ -244,14 +610,331 @@ fn resolve_unknown_type(schema: &Schema, expr: &Expression) -> Result<DataType,
             schema.columns[index].data_type
         }
 
+        // MIN and MAX return one of the values in the column.
+        Expression::Aggregate {
+            func: AggregateFunction::Min | AggregateFunction::Max,
+            arg,
+            ..
+        } if matches!(**arg, Expression::Identifier(_)) => resolve_unknown_type(schema, arg)?,
+
+        Expression::Value(Value::String(string)) => DataType::Varchar(string.chars().count()),
+
+        // Literal numbers get the narrowest type that can store them.
+        Expression::Value(Value::Number(num)) => {
+            [DataType::Int, DataType::BigInt, DataType::UnsignedBigInt]
+                .into_iter()
+                .find(|data_type| tuple::integer_is_within_range(num, data_type))
+                .unwrap_or(DataType::BigInt)
+        }
+
+        Expression::Nested(expr) => resolve_unknown_type(schema, expr)?,
+
+        // Case mapping can turn one character into up to three, for example
+        // 'ß' becomes 'SS'. Trimming and substrings never make strings longer.
+        // Numeric functions are handled by the analyzer below.
+        Expression::Function {
+            func:
+                func @ (ScalarFunction::Upper
+                | ScalarFunction::Lower
+                | ScalarFunction::Trim
+                | ScalarFunction::Substr),
+            args,
+        } => match resolve_unknown_type(schema, &args[0])? {
+            DataType::Varchar(length) | DataType::Char(length) => match func {
+                ScalarFunction::Upper | ScalarFunction::Lower => DataType::Varchar(length * 3),
+                _ => DataType::Varchar(length),
+            },
+            _ => DataType::Varchar(65535),
+        },
+
+        // Strings can be as long as the longest result. Other types are only
+        // kept if all the results agree on them.
+        Expression::Case {
+            branches,
+            else_branch,
+        } => {
+            let mut data_types = Vec::new();
+            for result in branches
+                .iter()
+                .map(|(_, result)| result)
+                .chain(else_branch.as_deref())
+            {
+                data_types.push(resolve_unknown_type(schema, result)?);
+            }
+
+            let max_length = data_types
+                .iter()
+                .try_fold(0, |max, data_type| match data_type {
+                    DataType::Varchar(length) | DataType::Char(length) => Some(max.max(*length)),
+                    _ => None,
+                });
+
+            match max_length {
+                Some(length) => DataType::Varchar(length),
+                None if data_types
+                    .iter()
+                    .all(|data_type| *data_type == data_types[0]) =>
+                {
+                    data_types[0]
+                }
+                None => match analyzer::analyze_expression(schema, None, expr)? {
+                    VmDataType::Bool => DataType::Bool,
+                    VmDataType::Number => DataType::BigInt,
+                    VmDataType::Float => DataType::Double,
+                    VmDataType::String => DataType::Varchar(65535),
+                },
+            }
+        }
+
+        Expression::BinaryOperation {
+            left,
+            operator: BinaryOperator::Concat,
+            right,
+        } => match (
+            resolve_unknown_type(schema, left)?,
+            resolve_unknown_type(schema, right)?,
+        ) {
+            (
+                DataType::Varchar(left) | DataType::Char(left),
+                DataType::Varchar(right) | DataType::Char(right),
+            ) => DataType::Varchar(left + right),
+            _ => DataType::Varchar(65535),
+        },
+
         _ => match analyzer::analyze_expression(schema, None, expr)? {
             VmDataType::Bool => DataType::Bool,
             VmDataType::Number => DataType::BigInt,
+            VmDataType::Float => DataType::Double,
             VmDataType::String => DataType::Varchar(65535),
         },
     })
 }
 
+/// Sorts the tuples of `source`, which are described by `schema`.
+///
+/// Keys that are not simple columns are computed by a [`SortKeysGen`] plan
+/// before sorting. If `top_n` is given only that many tuples are returned, see
+/// [`TopN`].
+fn generate_sort_plan<F>(
+    source: Plan<F>,
+    schema: &Schema,
+    order_by: Vec<OrderBy>,
+    top_n: Option<u64>,
+    page_size: usize,
+    work_dir: PathBuf,
+) -> Result<Plan<F>, DbError> {
+    let mut sort_schema = schema.clone();
+    let mut sort_keys_indexes = Vec::with_capacity(order_by.len());
+    let directions = order_by.iter().map(|order| order.direction).collect();
+
+    // Precompute all the sort keys indexes so that the sorter doesn't waste
+    // time figuring out where the columns are.
+    for OrderBy { expr, .. } in &order_by {
+        let index = match expr {
+            Expression::Identifier(col) => schema.index_of(col).unwrap(),
+
+            _ => {
+                let index = sort_schema.len();
+                let data_type = resolve_unknown_type(schema, expr)?;
+                let col = Column::new(&format!("{expr}"), data_type);
+                sort_schema.push(col);
+
+                index
+            }
+        };
+
+        sort_keys_indexes.push(index);
+    }
+
+    // If there are no expressions that need to be evaluated for sorting then
+    // just skip the sort key generation completely, we already have all the
+    // sort keys we need.
+    let collect_source = if sort_schema.len() > schema.len() {
+        Plan::SortKeysGen(SortKeysGen {
+            source: Box::new(source),
+            schema: schema.clone(),
+            gen_exprs: order_by
+                .into_iter()
+                .map(|order| order.expr)
+                .filter(|expr| !matches!(expr, Expression::Identifier(_)))
+                .collect(),
+        })
+    } else {
+        source
+    };
+
+    let comparator = TuplesComparator {
+        schema: schema.clone(),
+        sort_schema: sort_schema.clone(),
+        sort_keys_indexes,
+        directions,
+    };
+
+    Ok(match top_n {
+        Some(top_n) => Plan::TopN(TopN::from(TopNConfig {
+            source: Box::new(collect_source),
+            comparator,
+            limit: top_n as usize,
+        })),
+
+        None => Plan::Sort(Sort::from(SortConfig {
+            page_size,
+            work_dir: work_dir.clone(),
+            collection: Collect::from(CollectConfig {
+                source: Box::new(collect_source),
+                work_dir,
+                schema: sort_schema,
+                mem_buf_size: page_size,
+            }),
+            comparator,
+            input_buffers: DEFAULT_SORT_INPUT_BUFFERS,
+        })),
+    })
+}
+
+/// Returns `true` if tuples returned in `scan_order` are already sorted by
+/// `order_by`.
+///
+/// Tuples of a single table never repeat the `scan_order` keys, since it's
+/// either the table key or a unique index, so `order_by` can have more keys
+/// after those and they'll never be compared. Joins can repeat any of the keys,
+/// so in that case `order_by` can't go any further than `scan_order`.
+fn provides_order(scan_order: &[OrderBy], order_by: &[OrderBy], unique: bool) -> bool {
+    order_by
+        .iter()
+        .zip(scan_order)
+        .all(|(order, scan)| order == scan)
+        && (unique || order_by.len() <= scan_order.len())
+}
+
+/// Returns `true` if tuples sorted by `order` have equal `group_by` keys next
+/// to each other, which allows [`GroupBy`] to stream the groups.
+///
+/// That's the case when the first keys of `order` are exactly the grouping
+/// keys in any order. Grouping by a `unique` column also works as long as the
+/// keys before it are grouping keys, since every group is a single tuple.
+fn groups_are_contiguous(order: &[OrderBy], group_by: &[Expression], unique: Option<&str>) -> bool {
+    if group_by.is_empty() {
+        return false;
+    }
+
+    for (i, OrderBy { expr, .. }) in order.iter().enumerate() {
+        if !group_by.contains(expr) {
+            return false;
+        }
+
+        let prefix_covers_keys = group_by
+            .iter()
+            .all(|key| order[..=i].iter().any(|order| order.expr == *key));
+
+        let is_unique = matches!(expr, Expression::Identifier(col) if Some(col.as_str()) == unique);
+
+        if prefix_covers_keys || is_unique {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Replaces the subexpressions of `expr` that match one of the grouping keys
+/// or aggregate functions with an identifier that references the column
+/// computed by [`GroupBy`].
+fn replace_grouped_expressions(
+    expr: Expression,
+    group_by: &[Expression],
+    aggregates: &[Expression],
+) -> Expression {
+    if group_by.contains(&expr) || aggregates.contains(&expr) {
+        return Expression::Identifier(expr.to_string());
+    }
+
+    let replace =
+        |expr: Box<Expression>| Box::new(replace_grouped_expressions(*expr, group_by, aggregates));
+
+    match expr {
+        Expression::BinaryOperation {
+            left,
+            operator,
+            right,
+        } => Expression::BinaryOperation {
+            left: replace(left),
+            operator,
+            right: replace(right),
+        },
+
+        Expression::UnaryOperation { operator, expr } => Expression::UnaryOperation {
+            operator,
+            expr: replace(expr),
+        },
+
+        Expression::Nested(expr) => Expression::Nested(replace(expr)),
+
+        Expression::Tuple(items) => Expression::Tuple(
+            items
+                .into_iter()
+                .map(|item| replace_grouped_expressions(item, group_by, aggregates))
+                .collect(),
+        ),
+
+        Expression::InList {
+            expr,
+            list,
+            negated,
+        } => Expression::InList {
+            expr: replace(expr),
+            list: list
+                .into_iter()
+                .map(|item| replace_grouped_expressions(item, group_by, aggregates))
+                .collect(),
+            negated,
+        },
+
+        Expression::Between {
+            expr,
+            low,
+            high,
+            negated,
+        } => Expression::Between {
+            expr: replace(expr),
+            low: replace(low),
+            high: replace(high),
+            negated,
+        },
+
+        Expression::IsNull { expr, negated } => Expression::IsNull {
+            expr: replace(expr),
+            negated,
+        },
+
+        Expression::Function { func, args } => Expression::Function {
+            func,
+            args: args
+                .into_iter()
+                .map(|arg| replace_grouped_expressions(arg, group_by, aggregates))
+                .collect(),
+        },
+
+        Expression::Case {
+            branches,
+            else_branch,
+        } => Expression::Case {
+            branches: branches
+                .into_iter()
+                .map(|(condition, result)| {
+                    (
+                        replace_grouped_expressions(condition, group_by, aggregates),
+                        replace_grouped_expressions(result, group_by, aggregates),
+                    )
+                })
+                .collect(),
+            else_branch: else_branch.map(replace),
+        },
+
+        other => other,
+    }
+}
+
 /// Returns `true` if the given plan needs collection to avoid destroying its
 /// cursor.
 fn needs_collection<F>(plan: &Plan<F>) -> bool {
@@ -260,6 +943,8 @@ fn needs_collection<F>(plan: &Plan<F>) -> bool {
         // KeyScan has a sorter behind it which buffers all the tuples and
         // ExactMatch only returns one tuple.
         Plan::KeyScan(_) | Plan::ExactMatch(_) => false,
+        // Doesn't even have a cursor.
+        Plan::Empty(_) => false,
         // Top-level SeqScan, RangeScan and LogicalOrScan will need collection
         // to preserve their cursor state.
         Plan::SeqScan(_) | Plan::RangeScan(_) | Plan::LogicalOrScan(_) => true,
@@ -282,21 +967,27 @@ mod tests {
     };
 
     use crate::{
-        db::{Database, DatabaseContext, IndexMetadata, Relation, Schema, TableMetadata},
+        db::{
+            Database, DatabaseContext, IndexMetadata, Relation, Schema, TableMetadata, TableStats,
+        },
         paging::{io::MemBuf, pager::Pager},
         sql::{
             self,
             parser::Parser,
-            statement::{Column, Create, DataType, Expression, Statement, Value},
+            statement::{
+                AggregateFunction, BinaryOperator, Column, Create, DataType, Direction, Expression,
+                Statement, Value,
+            },
         },
         storage::{
             tuple::{self, byte_length_of_integer_type},
             Cursor, FixedSizeMemCmp,
         },
         vm::plan::{
-            Collect, CollectConfig, ExactMatch, Filter, KeyScan, LogicalOrScan, Plan, Project,
-            RangeScan, RangeScanConfig, SeqScan, Sort, SortConfig, SortKeysGen, TuplesComparator,
-            DEFAULT_SORT_INPUT_BUFFERS,
+            Collect, CollectConfig, Delete, Empty, ExactMatch, Filter, GroupBy, GroupByConfig,
+            KeyScan, Limit, LogicalAndScan, LogicalOrScan, NestedLoopJoin, NestedLoopJoinConfig,
+            Plan, Project, RangeScan, RangeScanConfig, SeqScan, Sort, SortConfig, SortKeysGen,
+            TopN, TopNConfig, TuplesComparator, DEFAULT_SORT_INPUT_BUFFERS, TOP_N_MAX_LIMIT,
         },
         DbError,
     };
@@ -323,7 +1014,7 @@ mod tests {
     }
 
     fn init_db(ctx: &[&str]) -> Result<DbCtx, DbError> {
-        let mut pager = Pager::<MemBuf>::builder().wrap(io::Cursor::new(Vec::<u8>::new()));
+        let mut pager = Pager::<MemBuf>::builder().wrap(MemBuf::default());
         pager.init()?;
 
         let mut db = Database::new(Rc::new(RefCell::new(pager)), PathBuf::new());
@@ -361,7 +1052,7 @@ mod tests {
     }
 
     fn gen_plan(db: &mut DbCtx, query: &str) -> Result<Plan<MemBuf>, DbError> {
-        let statement = sql::pipeline(query, &mut db.inner)?;
+        let statement = sql::pipeline(Parser::new(query).parse_statement()?, &mut db.inner)?;
         super::generate_plan(statement, &mut db.inner)
     }
 
@@ -382,6 +1073,7 @@ mod tests {
                 pager: db.pager(),
                 cursor: Cursor::new(db.tables["users"].root, 0),
                 table: db.tables["users"].to_owned(),
+                rows_scanned: 0,
             })
         );
 
@@ -402,6 +1094,7 @@ mod tests {
                     pager: db.pager(),
                     cursor: Cursor::new(db.tables["users"].root, 0),
                     table: db.tables["users"].to_owned(),
+                    rows_scanned: 0,
                 }))
             })
         );
@@ -430,6 +1123,7 @@ mod tests {
                     pager: db.pager(),
                     cursor: Cursor::new(db.tables["users"].root, 0),
                     table: db.tables["users"].to_owned(),
+                    rows_scanned: 0,
                 }))
             })
         );
@@ -459,7 +1153,8 @@ mod tests {
                 source: Box::new(Plan::SeqScan(SeqScan {
                     cursor: Cursor::new(db.tables["users"].root, 0),
                     table: db.tables["users"].to_owned(),
-                    pager: db.pager()
+                    pager: db.pager(),
+                    rows_scanned: 0,
                 }))
             })
         );
@@ -484,7 +1179,8 @@ mod tests {
                     source: Box::new(Plan::SeqScan(SeqScan {
                         cursor: Cursor::new(db.tables["users"].root, 0),
                         table: db.tables["users"].to_owned(),
-                        pager: db.pager()
+                        pager: db.pager(),
+                        rows_scanned: 0,
                     }))
                 }))
             })
@@ -501,11 +1197,12 @@ mod tests {
             gen_plan(&mut db, "SELECT * FROM users WHERE id = 5;")?,
             Plan::ExactMatch(ExactMatch {
                 emit_table_key_only: false,
-                key: tuple::serialize_key(&DataType::Int, &Value::Number(5)),
+                key: tuple::serialize_key(&DataType::Int, &Value::Number(5))?,
                 expr: parse_expr("id = 5"),
                 pager: db.pager(),
                 relation: Relation::Table(db.tables["users"].to_owned()),
                 done: false,
+                rows_scanned: 0,
             })
         );
 
@@ -514,8 +1211,9 @@ mod tests {
 
     #[test]
     fn generate_exact_match_on_external_index() -> Result<(), DbError> {
-        let mut db =
-            init_db(&["CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE);"])?;
+        let mut db = init_db(&[
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+        ])?;
 
         assert_eq!(
             gen_plan(
@@ -534,8 +1232,42 @@ mod tests {
                     key: tuple::serialize_key(
                         &DataType::Varchar(255),
                         &Value::String("bob@email.com".into())
-                    ),
+                    )?,
                     done: false,
+                    rows_scanned: 0,
+                })),
+                rows_scanned: 0,
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn fallback_to_seq_scan_after_drop_index() -> Result<(), DbError> {
+        let mut db = init_db(&[
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255));",
+            "CREATE UNIQUE INDEX email_idx ON users(email);",
+        ])?;
+
+        let query = "SELECT * FROM users WHERE email = 'bob@email.com';";
+
+        assert!(matches!(gen_plan(&mut db, query)?, Plan::KeyScan(_)));
+
+        db.inner.exec("DROP INDEX email_idx;")?;
+        let table = db.inner.table_metadata("users")?.to_owned();
+        assert!(table.indexes.is_empty());
+
+        assert_eq!(
+            gen_plan(&mut db, query)?,
+            Plan::Filter(Filter {
+                filter: parse_expr("email = 'bob@email.com'"),
+                schema: table.schema.to_owned(),
+                source: Box::new(Plan::SeqScan(SeqScan {
+                    pager: db.pager(),
+                    cursor: Cursor::new(table.root, 0),
+                    table,
+                    rows_scanned: 0,
                 }))
             })
         );
@@ -543,6 +1275,69 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn choose_seq_scan_by_cost() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"])?;
+
+        let mut gen_plan_with_rows = |rows, sql| -> Result<Plan<MemBuf>, DbError> {
+            db.inner.table_metadata("users")?.stats = Some(TableStats {
+                rows,
+                distinct: HashMap::new(),
+            });
+
+            gen_plan(&mut db, sql)
+        };
+
+        let scan = |plan: Plan<MemBuf>| match plan {
+            Plan::Filter(Filter { source, .. }) => *source,
+            other => other,
+        };
+
+        let exact_match = "SELECT * FROM users WHERE email = 'bob@email.com';";
+        assert!(matches!(
+            scan(gen_plan_with_rows(3, exact_match)?),
+            Plan::SeqScan(_)
+        ));
+        assert!(matches!(
+            scan(gen_plan_with_rows(1000, exact_match)?),
+            Plan::KeyScan(_)
+        ));
+
+        // A third of the table for each bound.
+        let range = "SELECT * FROM users WHERE email > 'bob@email.com';";
+        assert!(matches!(
+            scan(gen_plan_with_rows(1000, range)?),
+            Plan::SeqScan(_)
+        ));
+
+        let range = "SELECT * FROM users WHERE email > 'a' AND email < 'c';";
+        assert!(matches!(
+            scan(gen_plan_with_rows(4, range)?),
+            Plan::SeqScan(_)
+        ));
+        assert!(matches!(
+            scan(gen_plan_with_rows(1000, range)?),
+            Plan::KeyScan(_)
+        ));
+
+        // Scans of the table BTree are always cheaper.
+        let key_range = "SELECT * FROM users WHERE id > 5;";
+        assert!(matches!(
+            gen_plan_with_rows(1, key_range)?,
+            Plan::RangeScan(_)
+        ));
+
+        // The filter must not lose the conditions consumed by the index.
+        let sql = "SELECT * FROM users WHERE email IN ('a', 'b') AND name = 'Bob';";
+        let Plan::Filter(Filter { filter, source, .. }) = gen_plan_with_rows(5, sql)? else {
+            panic!("expected filter on top of the sequential scan");
+        };
+        assert!(matches!(*source, Plan::SeqScan(_)));
+        assert_eq!(filter, parse_expr("email IN ('a', 'b') AND name = 'Bob'"));
+
+        Ok(())
+    }
+
     #[test]
     fn generate_range_on_auto_index() -> Result<(), DbError> {
         let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
@@ -554,8 +1349,50 @@ mod tests {
                 expr: parse_expr("id > 5 AND id < 10"),
                 pager: db.pager(),
                 range: (
-                    Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(5))),
-                    Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(10))),
+                    Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(5))?),
+                    Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(10))?),
+                ),
+                relation: Relation::Table(db.tables["users"].to_owned())
+            }))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_range_scan_for_between() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users WHERE id BETWEEN 5 AND 10;")?,
+            Plan::RangeScan(RangeScan::from(RangeScanConfig {
+                emit_table_key_only: false,
+                expr: parse_expr("id >= 5 AND id <= 10"),
+                pager: db.pager(),
+                range: (
+                    Bound::Included(tuple::serialize_key(&DataType::Int, &Value::Number(5))?),
+                    Bound::Included(tuple::serialize_key(&DataType::Int, &Value::Number(10))?),
+                ),
+                relation: Relation::Table(db.tables["users"].to_owned())
+            }))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_range_scan_for_negated_comparison() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users WHERE NOT (id <= 5);")?,
+            Plan::RangeScan(RangeScan::from(RangeScanConfig {
+                emit_table_key_only: false,
+                expr: parse_expr("id > 5"),
+                pager: db.pager(),
+                range: (
+                    Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(5))?),
+                    Bound::Unbounded,
                 ),
                 relation: Relation::Table(db.tables["users"].to_owned())
             }))
@@ -587,6 +1424,7 @@ mod tests {
                         schema: key_only_schema.clone(),
                         sort_schema: key_only_schema.clone(),
                         sort_keys_indexes: vec![0],
+                        directions: vec![Direction::Asc],
                     },
                     collection: Collect::from(CollectConfig {
                         mem_buf_size: db.page_size(),
@@ -601,14 +1439,15 @@ mod tests {
                                 Bound::Included(tuple::serialize_key(
                                     &DataType::Varchar(255),
                                     &Value::String("test@test.com".into())
-                                )),
+                                )?),
                             ),
                             relation: Relation::Index(
                                 db.indexes["users_email_uq_index"].to_owned()
                             )
                         })))
                     })
-                })))
+                }))),
+                rows_scanned: 0,
             })
         );
 
@@ -616,20 +1455,127 @@ mod tests {
     }
 
     #[test]
-    fn skip_filter_on_simple_range_scan() -> Result<(), DbError> {
-        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+    fn generate_index_only_scan() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"])?;
+
+        let index = db.indexes["users_email_uq_index"].to_owned();
 
         assert_eq!(
-            gen_plan(&mut db, "SELECT * FROM users WHERE id < 5;")?,
-            Plan::RangeScan(RangeScan::from(RangeScanConfig {
-                emit_table_key_only: false,
-                pager: db.pager(),
-                relation: Relation::Table(db.tables["users"].to_owned()),
-                expr: parse_expr("id < 5"),
-                range: (
-                    Bound::Unbounded,
-                    Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(5)))
-                ),
+            gen_plan(
+                &mut db,
+                "SELECT email FROM users WHERE email > 'test@test.com';"
+            )?,
+            Plan::Project(Project {
+                input_schema: index.schema.clone(),
+                output_schema: Schema::new(vec![index.schema.columns[0].clone()]),
+                projection: vec![Expression::Identifier("email".into())],
+                source: Box::new(Plan::RangeScan(RangeScan::from(RangeScanConfig {
+                    emit_table_key_only: false,
+                    expr: parse_expr("email > 'test@test.com'"),
+                    pager: db.pager(),
+                    range: (
+                        Bound::Excluded(tuple::serialize_key(
+                            &DataType::Varchar(255),
+                            &Value::String("test@test.com".into())
+                        )?),
+                        Bound::Unbounded,
+                    ),
+                    relation: Relation::Index(index),
+                })))
+            })
+        );
+
+        // Index tuples are already sorted by email.
+        assert!(matches!(
+            gen_plan(
+                &mut db,
+                "SELECT email FROM users WHERE email > 'test@test.com' ORDER BY email;"
+            )?,
+            Plan::Project(Project { source, .. }) if matches!(*source, Plan::RangeScan(_))
+        ));
+
+        // But not by the table key.
+        assert!(matches!(
+            gen_plan(
+                &mut db,
+                "SELECT email FROM users WHERE email > 'test@test.com' ORDER BY id;"
+            )?,
+            Plan::Project(Project { source, .. }) if matches!(*source, Plan::Sort(_))
+        ));
+
+        // The name is only stored in the table BTree.
+        assert!(matches!(
+            gen_plan(
+                &mut db,
+                "SELECT name FROM users WHERE email > 'test@test.com';"
+            )?,
+            Plan::Project(Project { source, .. }) if matches!(*source, Plan::KeyScan(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_range_scan_on_multi_column_index() -> Result<(), DbError> {
+        let mut db = init_db(&[
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255), age INT);",
+            "CREATE UNIQUE INDEX name_email ON users(name, email);",
+        ])?;
+
+        let key_only_schema = db.tables["users"].key_only_schema();
+        let key = tuple::serialize_key(&DataType::Varchar(255), &Value::String("John".into()))?;
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users WHERE name = 'John';")?,
+            Plan::KeyScan(KeyScan {
+                comparator: FixedSizeMemCmp(byte_length_of_integer_type(&DataType::Int)),
+                table: db.tables["users"].to_owned(),
+                pager: db.pager(),
+                source: Box::new(Plan::Sort(Sort::from(SortConfig {
+                    input_buffers: DEFAULT_SORT_INPUT_BUFFERS,
+                    page_size: db.page_size(),
+                    work_dir: db.work_dir(),
+                    comparator: TuplesComparator {
+                        schema: key_only_schema.clone(),
+                        sort_schema: key_only_schema.clone(),
+                        sort_keys_indexes: vec![0],
+                        directions: vec![Direction::Asc],
+                    },
+                    collection: Collect::from(CollectConfig {
+                        mem_buf_size: db.page_size(),
+                        schema: key_only_schema,
+                        work_dir: db.work_dir(),
+                        source: Box::new(Plan::RangeScan(RangeScan::from(RangeScanConfig {
+                            emit_table_key_only: true,
+                            expr: parse_expr("name = 'John'"),
+                            pager: db.pager(),
+                            range: (Bound::Included(key.clone()), Bound::Included(key)),
+                            relation: Relation::Index(db.indexes["name_email"].to_owned())
+                        })))
+                    })
+                }))),
+                rows_scanned: 0,
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn skip_filter_on_simple_range_scan() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users WHERE id < 5;")?,
+            Plan::RangeScan(RangeScan::from(RangeScanConfig {
+                emit_table_key_only: false,
+                pager: db.pager(),
+                relation: Relation::Table(db.tables["users"].to_owned()),
+                expr: parse_expr("id < 5"),
+                range: (
+                    Bound::Unbounded,
+                    Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(5))?)
+                ),
             }))
         );
 
@@ -655,7 +1601,7 @@ mod tests {
                     relation: Relation::Table(db.tables["users"].to_owned()),
                     range: (
                         Bound::Unbounded,
-                        Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(5)))
+                        Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(5))?)
                     )
                 })))
             })
@@ -683,7 +1629,7 @@ mod tests {
                     expr: parse_expr("id < 10"),
                     range: (
                         Bound::Unbounded,
-                        Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(10)))
+                        Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(10))?)
                     ),
                 }))),
             })
@@ -692,6 +1638,51 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn merge_overlapping_ranges_into_one_scan() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users WHERE id > 5 OR id > 10;")?,
+            Plan::RangeScan(RangeScan::from(RangeScanConfig {
+                emit_table_key_only: false,
+                expr: parse_expr("id > 5"),
+                pager: db.pager(),
+                range: (
+                    Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(5))?),
+                    Bound::Unbounded,
+                ),
+                relation: Relation::Table(db.tables["users"].to_owned())
+            }))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_adjacent_ranges_into_one_scan() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        assert_eq!(
+            gen_plan(
+                &mut db,
+                "SELECT * FROM users WHERE (id >= 5 AND id < 10) OR (id >= 10 AND id <= 20);"
+            )?,
+            Plan::RangeScan(RangeScan::from(RangeScanConfig {
+                emit_table_key_only: false,
+                expr: parse_expr("id >= 5 AND id <= 20"),
+                pager: db.pager(),
+                range: (
+                    Bound::Included(tuple::serialize_key(&DataType::Int, &Value::Number(5))?),
+                    Bound::Included(tuple::serialize_key(&DataType::Int, &Value::Number(20))?),
+                ),
+                relation: Relation::Table(db.tables["users"].to_owned())
+            }))
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn fallback_to_seq_scan_when_union_of_ranges_is_fully_unbounded() -> Result<(), DbError> {
         let mut db =
@@ -709,6 +1700,7 @@ mod tests {
                     pager: db.pager(),
                     cursor: Cursor::new(db.tables["users"].root, 0),
                     table: db.tables["users"].to_owned(),
+                    rows_scanned: 0,
                 }))
             })
         );
@@ -717,22 +1709,44 @@ mod tests {
     }
 
     #[test]
-    fn fallback_to_seq_scan_when_intersection_of_ranges_cancels_out() -> Result<(), DbError> {
+    fn generate_empty_plan_when_intersection_of_ranges_cancels_out() -> Result<(), DbError> {
+        let mut db =
+            init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);"])?;
+
+        for query in [
+            "SELECT * FROM users WHERE (id < 5 OR id > 10) AND id = 7;",
+            "SELECT * FROM users WHERE age > 20 AND name = 'Bob' AND age <= 20;",
+            "SELECT * FROM users WHERE (age < 5 AND age > 10) OR (id = 1 AND id = 2);",
+        ] {
+            assert_eq!(
+                gen_plan(&mut db, query)?,
+                Plan::Empty(Empty {
+                    schema: db.tables["users"].schema.to_owned(),
+                })
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn dont_generate_empty_plan_when_only_one_branch_cancels_out() -> Result<(), DbError> {
         let mut db =
             init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);"])?;
 
         assert_eq!(
             gen_plan(
                 &mut db,
-                "SELECT * FROM users WHERE (id < 5 OR id > 10) AND id = 7;"
+                "SELECT * FROM users WHERE (age < 5 AND age > 10) OR name = 'Bob';"
             )?,
             Plan::Filter(Filter {
-                filter: parse_expr("(id < 5 OR id > 10) AND id = 7"),
+                filter: parse_expr("(age < 5 AND age > 10) OR name = 'Bob'"),
                 schema: db.tables["users"].schema.to_owned(),
                 source: Box::new(Plan::SeqScan(SeqScan {
                     pager: db.pager(),
                     cursor: Cursor::new(db.tables["users"].root, 0),
                     table: db.tables["users"].to_owned(),
+                    rows_scanned: 0,
                 }))
             })
         );
@@ -740,6 +1754,89 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn generate_empty_plan_for_empty_in_list() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users WHERE id IN ();")?,
+            Plan::Empty(Empty {
+                schema: db.tables["users"].schema.to_owned(),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn project_empty_plan() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT name FROM users WHERE 1 = 2;")?,
+            Plan::Project(Project {
+                input_schema: db.tables["users"].schema.to_owned(),
+                output_schema: Schema::new(vec![Column::new("name", DataType::Varchar(255))]),
+                projection: vec![Expression::Identifier("name".into())],
+                source: Box::new(Plan::Empty(Empty {
+                    schema: db.tables["users"].schema.to_owned(),
+                })),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_empty_plan_for_limit_zero() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT name FROM users ORDER BY name LIMIT 0;")?,
+            Plan::Empty(Empty {
+                schema: Schema::new(vec![Column::new("name", DataType::Varchar(255))]),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn drop_filter_when_where_is_always_true() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users WHERE 1 = 1;")?,
+            Plan::SeqScan(SeqScan {
+                pager: db.pager(),
+                cursor: Cursor::new(db.tables["users"].root, 0),
+                table: db.tables["users"].to_owned(),
+                rows_scanned: 0,
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_empty_plan_when_where_is_always_false() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        for query in [
+            "SELECT * FROM users WHERE 1 = 2;",
+            "SELECT * FROM users WHERE id > 5 AND 2 > 3;",
+        ] {
+            assert_eq!(
+                gen_plan(&mut db, query)?,
+                Plan::Empty(Empty {
+                    schema: db.tables["users"].schema.to_owned(),
+                })
+            );
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn generate_simple_sort_plan() -> Result<(), DbError> {
         let mut db =
@@ -755,6 +1852,7 @@ mod tests {
                     schema: db.tables["users"].schema.to_owned(),
                     sort_schema: db.tables["users"].schema.to_owned(),
                     sort_keys_indexes: vec![1, 2],
+                    directions: vec![Direction::Asc; 2],
                 },
                 collection: Collect::from(CollectConfig {
                     mem_buf_size: db.page_size(),
@@ -764,6 +1862,7 @@ mod tests {
                         pager: db.pager(),
                         cursor: Cursor::new(db.tables["users"].root, 0),
                         table: db.tables["users"].to_owned(),
+                        rows_scanned: 0,
                     }))
                 })
             }))
@@ -795,6 +1894,7 @@ mod tests {
                     schema: db.tables["users"].schema.to_owned(),
                     sort_schema: sort_schema.clone(),
                     sort_keys_indexes: vec![1, 4, 5],
+                    directions: vec![Direction::Asc; 3],
                 },
                 collection: Collect::from(CollectConfig {
                     mem_buf_size: db.page_size(),
@@ -807,6 +1907,7 @@ mod tests {
                             pager: db.pager(),
                             cursor: Cursor::new(db.tables["users"].root, 0),
                             table: db.tables["users"].to_owned(),
+                            rows_scanned: 0,
                         }))
                     }))
                 })
@@ -816,6 +1917,54 @@ mod tests {
         Ok(())
     }
 
+    // The sort key is computed from the full table schema and dropped by the
+    // projection after sorting.
+    #[test]
+    fn generate_sort_plan_with_expression_not_in_projection() -> Result<(), DbError> {
+        let mut db =
+            init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);"])?;
+
+        let mut sort_schema = db.tables["users"].schema.to_owned();
+        sort_schema.push(Column::new("age + 1", DataType::BigInt));
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT name FROM users ORDER BY age + 1;")?,
+            Plan::Project(Project {
+                input_schema: db.tables["users"].schema.to_owned(),
+                output_schema: Schema::new(vec![Column::new("name", DataType::Varchar(255))]),
+                projection: vec![Expression::Identifier("name".into())],
+                source: Box::new(Plan::Sort(Sort::from(SortConfig {
+                    page_size: db.page_size(),
+                    work_dir: db.work_dir(),
+                    input_buffers: DEFAULT_SORT_INPUT_BUFFERS,
+                    comparator: TuplesComparator {
+                        schema: db.tables["users"].schema.to_owned(),
+                        sort_schema: sort_schema.clone(),
+                        sort_keys_indexes: vec![3],
+                        directions: vec![Direction::Asc],
+                    },
+                    collection: Collect::from(CollectConfig {
+                        mem_buf_size: db.page_size(),
+                        schema: sort_schema.clone(),
+                        work_dir: db.work_dir(),
+                        source: Box::new(Plan::SortKeysGen(SortKeysGen {
+                            gen_exprs: vec![parse_expr("age + 1")],
+                            schema: db.tables["users"].schema.to_owned(),
+                            source: Box::new(Plan::SeqScan(SeqScan {
+                                pager: db.pager(),
+                                cursor: Cursor::new(db.tables["users"].root, 0),
+                                table: db.tables["users"].to_owned(),
+                                rows_scanned: 0,
+                            }))
+                        }))
+                    })
+                })))
+            })
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn skip_sorting_when_order_by_key_only() -> Result<(), DbError> {
         let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
@@ -826,6 +1975,7 @@ mod tests {
                 pager: db.pager(),
                 cursor: Cursor::new(db.tables["users"].root, 0),
                 table: db.tables["users"].to_owned(),
+                rows_scanned: 0,
             })
         );
 
@@ -833,33 +1983,675 @@ mod tests {
     }
 
     #[test]
-    fn generate_logical_or_scan_plan() -> Result<(), DbError> {
-        let mut db =
-            init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"])?;
+    fn reverse_scan_when_order_by_key_desc() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
 
-        #[rustfmt::skip]
-        let expr = "
-            (id > 5 AND id <= 10)
-            OR (id >= 50 AND id < 60)
-            OR id = 100
-            OR email < 'b@b.com'
-            OR email > 't@t.com'
-            OR email = 'f@f.com'
-        ";
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users ORDER BY id DESC;")?,
+            Plan::SeqScan(SeqScan {
+                pager: db.pager(),
+                cursor: Cursor::new(db.tables["users"].root, 0).reverse(),
+                table: db.tables["users"].to_owned(),
+                rows_scanned: 0,
+            })
+        );
 
-        let sql = format!("SELECT * FROM users WHERE {expr};");
+        Ok(())
+    }
 
-        let key_only_schema = db.tables["users"].key_only_schema();
+    #[test]
+    fn scan_index_when_order_by_indexed_column() -> Result<(), DbError> {
+        let mut db = init_db(&[
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255));",
+            "CREATE UNIQUE INDEX email_desc ON users(email DESC);",
+        ])?;
 
-        let expected_scans = [
-            Plan::RangeScan(RangeScan::from(RangeScanConfig {
-                emit_table_key_only: true,
-                expr: parse_expr("id > 5 AND id <= 10"),
+        let mut index_scan = RangeScan::from(RangeScanConfig {
+            emit_table_key_only: true,
+            expr: Expression::Wildcard,
+            pager: db.pager(),
+            range: (Bound::Unbounded, Bound::Unbounded),
+            relation: Relation::Index(db.indexes["email_desc"].to_owned()),
+        });
+        index_scan.reverse = true;
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users ORDER BY email;")?,
+            Plan::KeyScan(KeyScan {
+                comparator: FixedSizeMemCmp(byte_length_of_integer_type(&DataType::Int)),
+                table: db.tables["users"].to_owned(),
                 pager: db.pager(),
-                relation: Relation::Table(db.tables["users"].to_owned()),
-                range: (
-                    Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(5))),
-                    Bound::Included(tuple::serialize_key(&DataType::Int, &Value::Number(10))),
+                source: Box::new(Plan::RangeScan(index_scan)),
+                rows_scanned: 0,
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn break_sort_ties_with_table_key() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+        db.inner.deterministic_order = true;
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users ORDER BY name DESC;")?,
+            Plan::Sort(Sort::from(SortConfig {
+                page_size: db.page_size(),
+                work_dir: db.work_dir(),
+                input_buffers: DEFAULT_SORT_INPUT_BUFFERS,
+                comparator: TuplesComparator {
+                    schema: db.tables["users"].schema.to_owned(),
+                    sort_schema: db.tables["users"].schema.to_owned(),
+                    sort_keys_indexes: vec![1, 0],
+                    directions: vec![Direction::Desc, Direction::Asc],
+                },
+                collection: Collect::from(CollectConfig {
+                    mem_buf_size: db.page_size(),
+                    schema: db.tables["users"].schema.to_owned(),
+                    work_dir: db.work_dir(),
+                    source: Box::new(Plan::SeqScan(SeqScan {
+                        pager: db.pager(),
+                        cursor: Cursor::new(db.tables["users"].root, 0),
+                        table: db.tables["users"].to_owned(),
+                        rows_scanned: 0,
+                    })),
+                }),
+            }))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reuse_table_comparator() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        let expected = FixedSizeMemCmp::try_from(&DataType::Int).unwrap();
+        assert_eq!(db.tables["users"].comparator(), expected);
+
+        for id in 1..=2 {
+            let sql = format!("INSERT INTO users (id, name) VALUES ({id}, 'John Doe');");
+            let Plan::Insert(insert) = gen_plan(&mut db, &sql)? else {
+                panic!("expected insert plan for {sql}");
+            };
+            assert_eq!(insert.comparator, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_range_scan_for_like_prefix() -> Result<(), DbError> {
+        let mut db = init_db(&[
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+        ])?;
+
+        let key_only_schema = db.tables["users"].key_only_schema();
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users WHERE email LIKE 'jo%';")?,
+            Plan::KeyScan(KeyScan {
+                comparator: FixedSizeMemCmp(byte_length_of_integer_type(&DataType::Int)),
+                table: db.tables["users"].to_owned(),
+                pager: db.pager(),
+                source: Box::new(Plan::Sort(Sort::from(SortConfig {
+                    input_buffers: DEFAULT_SORT_INPUT_BUFFERS,
+                    page_size: db.page_size(),
+                    work_dir: db.work_dir(),
+                    comparator: TuplesComparator {
+                        schema: key_only_schema.clone(),
+                        sort_schema: key_only_schema.clone(),
+                        sort_keys_indexes: vec![0],
+                        directions: vec![Direction::Asc],
+                    },
+                    collection: Collect::from(CollectConfig {
+                        mem_buf_size: db.page_size(),
+                        schema: key_only_schema,
+                        work_dir: db.work_dir(),
+                        source: Box::new(Plan::RangeScan(RangeScan::from(RangeScanConfig {
+                            emit_table_key_only: true,
+                            expr: parse_expr("email >= 'jo' AND email < 'jp'"),
+                            pager: db.pager(),
+                            range: (
+                                Bound::Included(tuple::serialize_key(
+                                    &DataType::Varchar(255),
+                                    &Value::String("jo".into())
+                                )?),
+                                Bound::Excluded(tuple::serialize_key(
+                                    &DataType::Varchar(255),
+                                    &Value::String("jp".into())
+                                )?),
+                            ),
+                            relation: Relation::Index(
+                                db.indexes["users_email_uq_index"].to_owned()
+                            )
+                        })))
+                    })
+                }))),
+                rows_scanned: 0,
+            })
+        );
+
+        // Other patterns can't use the index.
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users WHERE email LIKE 'jo%@%';")?,
+            Plan::Filter(Filter {
+                filter: parse_expr("email LIKE 'jo%@%'"),
+                schema: db.tables["users"].schema.clone(),
+                source: Box::new(Plan::SeqScan(SeqScan {
+                    pager: db.pager(),
+                    cursor: Cursor::new(db.tables["users"].root, 0),
+                    table: db.tables["users"].to_owned(),
+                    rows_scanned: 0,
+                })),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_range_scan_for_substr_prefix() -> Result<(), DbError> {
+        let mut db = init_db(&[
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+        ])?;
+
+        // Same prefix range as LIKE 'jo%'.
+        assert_eq!(
+            gen_plan(
+                &mut db,
+                "SELECT * FROM users WHERE SUBSTR(email, 1, 2) = 'jo';"
+            )?,
+            gen_plan(&mut db, "SELECT * FROM users WHERE email LIKE 'jo%';")?,
+        );
+
+        assert_eq!(
+            gen_plan(
+                &mut db,
+                "SELECT * FROM users WHERE 'jo' = SUBSTR(email, 1, 2);"
+            )?,
+            gen_plan(&mut db, "SELECT * FROM users WHERE email LIKE 'jo%';")?,
+        );
+
+        // Shorter strings are only equal to the whole column.
+        assert_eq!(
+            gen_plan(
+                &mut db,
+                "SELECT * FROM users WHERE SUBSTR(email, 1, 5) = 'jo';"
+            )?,
+            gen_plan(&mut db, "SELECT * FROM users WHERE email = 'jo';")?,
+        );
+
+        // Other positions and unindexed columns can't use the index.
+        assert_eq!(
+            gen_plan(
+                &mut db,
+                "SELECT * FROM users WHERE SUBSTR(email, 2, 2) = 'jo';"
+            )?,
+            Plan::Filter(Filter {
+                filter: parse_expr("SUBSTR(email, 2, 2) = 'jo'"),
+                schema: db.tables["users"].schema.clone(),
+                source: Box::new(Plan::SeqScan(SeqScan {
+                    pager: db.pager(),
+                    cursor: Cursor::new(db.tables["users"].root, 0),
+                    table: db.tables["users"].to_owned(),
+                    rows_scanned: 0,
+                })),
+            })
+        );
+
+        assert_eq!(
+            gen_plan(
+                &mut db,
+                "SELECT * FROM users WHERE SUBSTR(name, 1, 2) = 'jo';"
+            )?,
+            Plan::Filter(Filter {
+                filter: parse_expr("SUBSTR(name, 1, 2) = 'jo'"),
+                schema: db.tables["users"].schema.clone(),
+                source: Box::new(Plan::SeqScan(SeqScan {
+                    pager: db.pager(),
+                    cursor: Cursor::new(db.tables["users"].root, 0),
+                    table: db.tables["users"].to_owned(),
+                    rows_scanned: 0,
+                })),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_limit_plan() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users LIMIT 10 OFFSET 5;")?,
+            Plan::Limit(Limit {
+                limit: Some(10),
+                offset: 5,
+                source: Box::new(Plan::SeqScan(SeqScan {
+                    pager: db.pager(),
+                    cursor: Cursor::new(db.tables["users"].root, 0),
+                    table: db.tables["users"].to_owned(),
+                    rows_scanned: 0,
+                }))
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_aggregate_plan() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        let count = Expression::Aggregate {
+            func: AggregateFunction::Count,
+            arg: Box::new(Expression::Wildcard),
+            distinct: false,
+        };
+
+        let max = Expression::Aggregate {
+            func: AggregateFunction::Max,
+            arg: Box::new(Expression::Identifier("name".into())),
+            distinct: false,
+        };
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT COUNT(*), MAX(name) FROM users LIMIT 1;")?,
+            Plan::Limit(Limit {
+                limit: Some(1),
+                offset: 0,
+                source: Box::new(Plan::GroupBy(GroupBy::from(GroupByConfig {
+                    input_schema: db.tables["users"].schema.to_owned(),
+                    output_schema: Schema::new(vec![
+                        Column::new("COUNT(*)", DataType::BigInt),
+                        Column::new("MAX(name)", DataType::Varchar(255)),
+                    ]),
+                    group_by: vec![],
+                    aggregates: vec![count, max],
+                    sorted: false,
+                    source: Box::new(Plan::SeqScan(SeqScan {
+                        pager: db.pager(),
+                        cursor: Cursor::new(db.tables["users"].root, 0),
+                        table: db.tables["users"].to_owned(),
+                        rows_scanned: 0,
+                    })),
+                })))
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_group_by_plan() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE emp (id INT PRIMARY KEY, dept VARCHAR(255));"])?;
+
+        let count = Expression::Aggregate {
+            func: AggregateFunction::Count,
+            arg: Box::new(Expression::Wildcard),
+            distinct: false,
+        };
+
+        let group_schema = Schema::new(vec![
+            Column::new("dept", DataType::Varchar(255)),
+            Column::new("COUNT(*)", DataType::BigInt),
+        ]);
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT dept, COUNT(*) + 1 FROM emp GROUP BY dept;")?,
+            Plan::Project(Project {
+                input_schema: group_schema.clone(),
+                output_schema: Schema::new(vec![
+                    Column::new("dept", DataType::Varchar(255)),
+                    Column::new("COUNT(*) + 1", DataType::BigInt),
+                ]),
+                projection: vec![
+                    Expression::Identifier("dept".into()),
+                    Expression::BinaryOperation {
+                        left: Box::new(Expression::Identifier("COUNT(*)".into())),
+                        operator: BinaryOperator::Plus,
+                        right: Box::new(Expression::Value(Value::Number(1))),
+                    },
+                ],
+                source: Box::new(Plan::GroupBy(GroupBy::from(GroupByConfig {
+                    input_schema: db.tables["emp"].schema.to_owned(),
+                    output_schema: group_schema,
+                    group_by: vec![Expression::Identifier("dept".into())],
+                    aggregates: vec![count],
+                    sorted: false,
+                    source: Box::new(Plan::SeqScan(SeqScan {
+                        pager: db.pager(),
+                        cursor: Cursor::new(db.tables["emp"].root, 0),
+                        table: db.tables["emp"].to_owned(),
+                        rows_scanned: 0,
+                    })),
+                }))),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_having_plan() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE emp (id INT PRIMARY KEY, dept VARCHAR(255));"])?;
+
+        let count = Expression::Aggregate {
+            func: AggregateFunction::Count,
+            arg: Box::new(Expression::Wildcard),
+            distinct: false,
+        };
+
+        let group_schema = Schema::new(vec![
+            Column::new("dept", DataType::Varchar(255)),
+            Column::new("COUNT(*)", DataType::BigInt),
+        ]);
+
+        assert_eq!(
+            gen_plan(
+                &mut db,
+                "SELECT dept FROM emp GROUP BY dept HAVING COUNT(*) > 3;"
+            )?,
+            Plan::Project(Project {
+                input_schema: group_schema.clone(),
+                output_schema: Schema::new(vec![Column::new("dept", DataType::Varchar(255))]),
+                projection: vec![Expression::Identifier("dept".into())],
+                source: Box::new(Plan::Filter(Filter {
+                    schema: group_schema.clone(),
+                    filter: Expression::BinaryOperation {
+                        left: Box::new(Expression::Identifier("COUNT(*)".into())),
+                        operator: BinaryOperator::Gt,
+                        right: Box::new(Expression::Value(Value::Number(3))),
+                    },
+                    source: Box::new(Plan::GroupBy(GroupBy::from(GroupByConfig {
+                        input_schema: db.tables["emp"].schema.to_owned(),
+                        output_schema: group_schema,
+                        group_by: vec![Expression::Identifier("dept".into())],
+                        aggregates: vec![count],
+                        sorted: false,
+                        source: Box::new(Plan::SeqScan(SeqScan {
+                            pager: db.pager(),
+                            cursor: Cursor::new(db.tables["emp"].root, 0),
+                            table: db.tables["emp"].to_owned(),
+                            rows_scanned: 0,
+                        })),
+                    }))),
+                })),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_streaming_group_by_plan() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE emp (id INT PRIMARY KEY, dept VARCHAR(255));"])?;
+
+        let count = Expression::Aggregate {
+            func: AggregateFunction::Count,
+            arg: Box::new(Expression::Wildcard),
+            distinct: false,
+        };
+
+        let group_schema = Schema::new(vec![
+            Column::primary_key("id", DataType::Int),
+            Column::new("COUNT(*)", DataType::BigInt),
+        ]);
+
+        let expected = Plan::GroupBy(GroupBy::from(GroupByConfig {
+            input_schema: db.tables["emp"].schema.to_owned(),
+            output_schema: group_schema,
+            group_by: vec![Expression::Identifier("id".into())],
+            aggregates: vec![count],
+            sorted: true,
+            source: Box::new(Plan::SeqScan(SeqScan {
+                pager: db.pager(),
+                cursor: Cursor::new(db.tables["emp"].root, 0),
+                table: db.tables["emp"].to_owned(),
+                rows_scanned: 0,
+            })),
+        }));
+
+        // The table is already sorted by its key, no Sort needed.
+        for query in [
+            "SELECT id, COUNT(*) FROM emp GROUP BY id;",
+            "SELECT id, COUNT(*) FROM emp GROUP BY id ORDER BY id;",
+        ] {
+            assert_eq!(gen_plan(&mut db, query)?, expected, "{query}");
+        }
+
+        Ok(())
+    }
+
+    /// Names of the nodes from the root of the plan down to its source.
+    fn plan_nodes(plan: &Plan<MemBuf>) -> Vec<String> {
+        std::iter::successors(Some(plan), |plan| plan.child())
+            .map(|plan| plan.to_string().split(' ').next().unwrap().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn sort_groups_only_when_ordering_by_aggregates() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, city VARCHAR(255));"])?;
+
+        for (query, expected) in [
+            (
+                "SELECT city, COUNT(*) FROM users GROUP BY city ORDER BY city;",
+                vec!["StreamingGroupBy", "Sort", "SeqScan"],
+            ),
+            (
+                "SELECT city, COUNT(*) FROM users GROUP BY city ORDER BY COUNT(*);",
+                vec!["Sort", "GroupBy", "SeqScan"],
+            ),
+            (
+                "SELECT city FROM users GROUP BY city ORDER BY MAX(id) DESC LIMIT 2;",
+                vec!["Project", "TopN", "GroupBy", "SeqScan"],
+            ),
+        ] {
+            assert_eq!(plan_nodes(&gen_plan(&mut db, query)?), expected, "{query}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_nested_loop_join_plan() -> Result<(), DbError> {
+        let mut db = init_db(&[
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));",
+            "CREATE TABLE orders (id INT PRIMARY KEY, user_id INT);",
+        ])?;
+
+        let mut schema = db.tables["users"].schema.qualified("users");
+        for col in db.tables["orders"].schema.qualified("orders").columns {
+            schema.push(col);
+        }
+
+        assert_eq!(
+            gen_plan(
+                &mut db,
+                "SELECT users.name, orders.id FROM users JOIN orders ON users.id = orders.user_id WHERE orders.id > 5;"
+            )?,
+            Plan::Project(Project {
+                input_schema: schema.clone(),
+                output_schema: Schema::new(vec![
+                    Column::new("users.name", DataType::Varchar(255)),
+                    Column::primary_key("orders.id", DataType::Int),
+                ]),
+                projection: vec![
+                    Expression::Identifier("users.name".into()),
+                    Expression::Identifier("orders.id".into()),
+                ],
+                source: Box::new(Plan::Filter(Filter {
+                    filter: Expression::BinaryOperation {
+                        left: Box::new(Expression::Identifier("orders.id".into())),
+                        operator: BinaryOperator::Gt,
+                        right: Box::new(Expression::Value(Value::Number(5))),
+                    },
+                    schema: schema.clone(),
+                    source: Box::new(Plan::NestedLoopJoin(NestedLoopJoin::from(
+                        NestedLoopJoinConfig {
+                            outer: Box::new(Plan::SeqScan(SeqScan {
+                                pager: db.pager(),
+                                cursor: Cursor::new(db.tables["users"].root, 0),
+                                table: db.tables["users"].to_owned(),
+                                rows_scanned: 0,
+                            })),
+                            inner: SeqScan {
+                                pager: db.pager(),
+                                cursor: Cursor::new(db.tables["orders"].root, 0),
+                                table: db.tables["orders"].to_owned(),
+                                rows_scanned: 0,
+                            },
+                            schema,
+                            on: Expression::BinaryOperation {
+                                left: Box::new(Expression::Identifier("users.id".into())),
+                                operator: BinaryOperator::Eq,
+                                right: Box::new(Expression::Identifier("orders.user_id".into())),
+                            },
+                        }
+                    ))),
+                })),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_top_n_plan_for_small_limit() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        let comparator = |db: &DbCtx| TuplesComparator {
+            schema: db.tables["users"].schema.to_owned(),
+            sort_schema: db.tables["users"].schema.to_owned(),
+            sort_keys_indexes: vec![1],
+            directions: vec![Direction::Asc],
+        };
+
+        let seq_scan = |db: &DbCtx| {
+            Box::new(Plan::SeqScan(SeqScan {
+                pager: db.pager(),
+                cursor: Cursor::new(db.tables["users"].root, 0),
+                table: db.tables["users"].to_owned(),
+                rows_scanned: 0,
+            }))
+        };
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users ORDER BY name LIMIT 10;")?,
+            Plan::TopN(TopN::from(TopNConfig {
+                source: seq_scan(&db),
+                comparator: comparator(&db),
+                limit: 10,
+            }))
+        );
+
+        // OFFSET tuples are part of the top N.
+        assert_eq!(
+            gen_plan(
+                &mut db,
+                "SELECT * FROM users ORDER BY name LIMIT 10 OFFSET 5;"
+            )?,
+            Plan::Limit(Limit {
+                limit: Some(10),
+                offset: 5,
+                source: Box::new(Plan::TopN(TopN::from(TopNConfig {
+                    source: seq_scan(&db),
+                    comparator: comparator(&db),
+                    limit: 15,
+                })))
+            })
+        );
+
+        // Large limits go through the regular sorting algorithm.
+        let sql = format!("SELECT * FROM users ORDER BY name LIMIT {TOP_N_MAX_LIMIT}1;");
+        assert_eq!(
+            gen_plan(&mut db, &sql)?,
+            Plan::Limit(Limit {
+                limit: Some(TOP_N_MAX_LIMIT * 10 + 1),
+                offset: 0,
+                source: Box::new(Plan::Sort(Sort::from(SortConfig {
+                    page_size: db.page_size(),
+                    work_dir: db.work_dir(),
+                    input_buffers: DEFAULT_SORT_INPUT_BUFFERS,
+                    comparator: comparator(&db),
+                    collection: Collect::from(CollectConfig {
+                        mem_buf_size: db.page_size(),
+                        schema: db.tables["users"].schema.clone(),
+                        work_dir: db.work_dir(),
+                        source: seq_scan(&db),
+                    })
+                })))
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn fuse_limit_and_sort_into_top_n() -> Result<(), DbError> {
+        let mut db =
+            init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);"])?;
+
+        let large_limit = format!(
+            "SELECT * FROM users ORDER BY age LIMIT {};",
+            TOP_N_MAX_LIMIT + 1
+        );
+
+        for (query, expected) in [
+            ("SELECT name FROM users ORDER BY age LIMIT 3;", vec![
+                "Project", "TopN", "SeqScan",
+            ]),
+            (
+                "SELECT * FROM users WHERE age > 20 ORDER BY name DESC LIMIT 3;",
+                vec!["TopN", "Filter", "SeqScan"],
+            ),
+            (
+                "SELECT id FROM users ORDER BY age + 1 LIMIT 3 OFFSET 2;",
+                vec!["Limit", "Project", "TopN", "SortKeysGen", "SeqScan"],
+            ),
+            // Nothing to sort.
+            ("SELECT * FROM users LIMIT 3;", vec!["Limit", "SeqScan"]),
+            // Too many tuples to keep in a heap, see [`TOP_N_MAX_LIMIT`].
+            (large_limit.as_str(), vec!["Limit", "Sort", "SeqScan"]),
+        ] {
+            assert_eq!(plan_nodes(&gen_plan(&mut db, query)?), expected, "{query}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_logical_or_scan_plan() -> Result<(), DbError> {
+        let mut db =
+            init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"])?;
+
+        #[rustfmt::skip]
+        let expr = "
+            (id > 5 AND id <= 10)
+            OR (id >= 50 AND id < 60)
+            OR id = 100
+            OR email < 'b@b.com'
+            OR email > 't@t.com'
+            OR email = 'f@f.com'
+        ";
+
+        let sql = format!("SELECT * FROM users WHERE {expr};");
+
+        let key_only_schema = db.tables["users"].key_only_schema();
+
+        let expected_scans = [
+            Plan::RangeScan(RangeScan::from(RangeScanConfig {
+                emit_table_key_only: true,
+                expr: parse_expr("id > 5 AND id <= 10"),
+                pager: db.pager(),
+                relation: Relation::Table(db.tables["users"].to_owned()),
+                range: (
+                    Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(5))?),
+                    Bound::Included(tuple::serialize_key(&DataType::Int, &Value::Number(10))?),
                 ),
             })),
             Plan::RangeScan(RangeScan::from(RangeScanConfig {
@@ -868,17 +2660,18 @@ mod tests {
                 pager: db.pager(),
                 relation: Relation::Table(db.tables["users"].to_owned()),
                 range: (
-                    Bound::Included(tuple::serialize_key(&DataType::Int, &Value::Number(50))),
-                    Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(60))),
+                    Bound::Included(tuple::serialize_key(&DataType::Int, &Value::Number(50))?),
+                    Bound::Excluded(tuple::serialize_key(&DataType::Int, &Value::Number(60))?),
                 ),
             })),
             Plan::ExactMatch(ExactMatch {
                 emit_table_key_only: true,
                 done: false,
                 expr: parse_expr("id = 100"),
-                key: tuple::serialize_key(&DataType::Int, &Value::Number(100)),
+                key: tuple::serialize_key(&DataType::Int, &Value::Number(100))?,
                 pager: db.pager(),
                 relation: Relation::Table(db.tables["users"].to_owned()),
+                rows_scanned: 0,
             }),
             Plan::RangeScan(RangeScan::from(RangeScanConfig {
                 emit_table_key_only: true,
@@ -890,7 +2683,7 @@ mod tests {
                     Bound::Excluded(tuple::serialize_key(
                         &DataType::Varchar(255),
                         &Value::String("b@b.com".into()),
-                    )),
+                    )?),
                 ),
             })),
             Plan::ExactMatch(ExactMatch {
@@ -900,9 +2693,10 @@ mod tests {
                 key: tuple::serialize_key(
                     &DataType::Varchar(255),
                     &Value::String("f@f.com".into()),
-                ),
+                )?,
                 pager: db.pager(),
                 relation: Relation::Index(db.indexes["users_email_uq_index"].to_owned()),
+                rows_scanned: 0,
             }),
             Plan::RangeScan(RangeScan::from(RangeScanConfig {
                 emit_table_key_only: true,
@@ -913,7 +2707,7 @@ mod tests {
                     Bound::Excluded(tuple::serialize_key(
                         &DataType::Varchar(255),
                         &Value::String("t@t.com".into()),
-                    )),
+                    )?),
                     Bound::Unbounded,
                 ),
             })),
@@ -933,6 +2727,7 @@ mod tests {
                             schema: key_only_schema.clone(),
                             sort_schema: key_only_schema.clone(),
                             sort_keys_indexes: vec![0],
+                            directions: vec![Direction::Asc],
                         },
                         input_buffers: DEFAULT_SORT_INPUT_BUFFERS,
                         work_dir: db.work_dir(),
@@ -945,7 +2740,149 @@ mod tests {
                                 scans: VecDeque::from(expected_scans)
                             }))
                         })
-                    })))
+                    }))),
+                    rows_scanned: 0,
+                }))
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_logical_or_scan_for_in_list() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        let plan = gen_plan(&mut db, "SELECT * FROM users WHERE id IN (7, 3, 5);")?;
+
+        let exact_match = |id| {
+            Ok::<_, DbError>(Plan::ExactMatch(ExactMatch {
+                emit_table_key_only: false,
+                done: false,
+                expr: parse_expr(&format!("id = {id}")),
+                key: tuple::serialize_key(&DataType::Int, &Value::Number(id))?,
+                pager: db.pager(),
+                relation: Relation::Table(db.tables["users"].to_owned()),
+                rows_scanned: 0,
+            }))
+        };
+
+        assert_eq!(
+            plan,
+            Plan::LogicalOrScan(LogicalOrScan {
+                scans: VecDeque::from([exact_match(3)?, exact_match(5)?, exact_match(7)?])
+            })
+        );
+
+        // Can't use the index.
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users WHERE id NOT IN (3, 5);")?,
+            Plan::Filter(Filter {
+                filter: parse_expr("id NOT IN (3, 5)"),
+                schema: db.tables["users"].schema.clone(),
+                source: Box::new(Plan::SeqScan(SeqScan {
+                    pager: db.pager(),
+                    cursor: Cursor::new(db.tables["users"].root, 0),
+                    table: db.tables["users"].to_owned(),
+                    rows_scanned: 0,
+                })),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn generate_logical_and_scan_plan() -> Result<(), DbError> {
+        let mut db =
+            init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"])?;
+
+        let key_only_schema = db.tables["users"].key_only_schema();
+
+        assert_eq!(
+            gen_plan(&mut db, "SELECT * FROM users WHERE email < 'm' AND id > 5;")?,
+            Plan::Filter(Filter {
+                filter: parse_expr("email < 'm' AND id > 5"),
+                schema: db.tables["users"].schema.to_owned(),
+                source: Box::new(Plan::KeyScan(KeyScan {
+                    comparator: FixedSizeMemCmp(byte_length_of_integer_type(&DataType::Int)),
+                    table: db.tables["users"].to_owned(),
+                    pager: db.pager(),
+                    source: Box::new(Plan::LogicalAndScan(LogicalAndScan {
+                        left: Box::new(Plan::RangeScan(RangeScan::from(RangeScanConfig {
+                            emit_table_key_only: true,
+                            expr: parse_expr("id > 5"),
+                            pager: db.pager(),
+                            relation: Relation::Table(db.tables["users"].to_owned()),
+                            range: (
+                                Bound::Excluded(tuple::serialize_key(
+                                    &DataType::Int,
+                                    &Value::Number(5)
+                                )?),
+                                Bound::Unbounded,
+                            ),
+                        }))),
+                        right: Box::new(Plan::Sort(Sort::from(SortConfig {
+                            comparator: TuplesComparator {
+                                schema: key_only_schema.clone(),
+                                sort_schema: key_only_schema.clone(),
+                                sort_keys_indexes: vec![0],
+                                directions: vec![Direction::Asc],
+                            },
+                            input_buffers: DEFAULT_SORT_INPUT_BUFFERS,
+                            work_dir: db.work_dir(),
+                            page_size: db.page_size(),
+                            collection: Collect::from(CollectConfig {
+                                mem_buf_size: db.page_size(),
+                                work_dir: db.work_dir(),
+                                schema: key_only_schema,
+                                source: Box::new(Plan::RangeScan(RangeScan::from(
+                                    RangeScanConfig {
+                                        emit_table_key_only: true,
+                                        expr: parse_expr("email < 'm'"),
+                                        pager: db.pager(),
+                                        relation: Relation::Index(
+                                            db.indexes["users_email_uq_index"].to_owned()
+                                        ),
+                                        range: (
+                                            Bound::Unbounded,
+                                            Bound::Excluded(tuple::serialize_key(
+                                                &DataType::Varchar(255),
+                                                &Value::String("m".into()),
+                                            )?),
+                                        ),
+                                    }
+                                )))
+                            })
+                        }))),
+                    })),
+                    rows_scanned: 0,
+                }))
+            })
+        );
+
+        Ok(())
+    }
+
+    // ExactMatch returns only one tuple so it doesn't need collection.
+    #[test]
+    fn generate_delete_by_primary_key_without_collection() -> Result<(), DbError> {
+        let mut db = init_db(&["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"])?;
+
+        assert_eq!(
+            gen_plan(&mut db, "DELETE FROM users WHERE id = 5;")?,
+            Plan::Delete(Delete {
+                comparator: FixedSizeMemCmp(byte_length_of_integer_type(&DataType::Int)),
+                table: db.tables["users"].to_owned(),
+                pager: db.pager(),
+                source: Box::new(Plan::ExactMatch(ExactMatch {
+                    emit_table_key_only: false,
+                    key: tuple::serialize_key(&DataType::Int, &Value::Number(5))?,
+                    expr: parse_expr("id = 5"),
+                    pager: db.pager(),
+                    relation: Relation::Table(db.tables["users"].to_owned()),
+                    done: false,
+                    rows_scanned: 0,
                 }))
             })
         );
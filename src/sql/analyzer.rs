@@ -8,10 +8,13 @@
 
 use std::{collections::HashSet, fmt::Display};
 
-use super::statement::{Drop, UnaryOperator};
+use super::statement::{Alter, Drop, UnaryOperator};
 use crate::{
-    db::{DatabaseContext, DbError, Schema, SqlError, TableMetadata, MKDB_META, ROW_ID_COL},
-    sql::statement::{BinaryOperator, Constraint, Create, DataType, Expression, Statement, Value},
+    db::{DatabaseContext, DbError, Schema, SqlError, MKDB_META, ROW_ID_COL},
+    sql::statement::{
+        AggregateFunction, BinaryOperator, Constraint, Create, DataType, Expression, IndexColumn,
+        InsertSource, Join, ScalarFunction, SelectItem, Statement, Value,
+    },
     storage::tuple,
     vm::{TypeError, VmDataType},
 };
@@ -22,7 +25,7 @@ use crate::{
 pub(crate) enum AnalyzerError {
     /// Insert statements where the number of columns doesn't match that of values.
     ColumnValueCountMismatch,
-    /// Insert statements that don't specify all the columns in the table.
+    /// Insert statements that omit columns without a default value.
     MissingColumns,
     /// Column specified more than once.
     DuplicatedColumn(String),
@@ -31,19 +34,88 @@ pub(crate) enum AnalyzerError {
     /// Table or index already exists.
     AlreadyExists(AlreadyExists),
     /// Number of characters exceeds `VARCHAR(max)`.
-    ValueTooLong(String, usize),
+    ValueTooLong(String, DataType),
+    /// `VARCHAR(max)` where `max` is greater than [`tuple::MAX_VARCHAR_LENGTH`].
+    LengthTooLarge(DataType),
     /// Integer data type can't store this value.
     IntegerOutOfRange(i128, DataType),
     /// Attempt to change the special Row ID column manually.
     RowIdAssignment,
     /// Attempt to modify the internal [`MKDB_META`] table.
     MkdbMetaModification,
+    /// `SELECT *` without a table to take the columns from.
+    WildcardWithoutFrom,
+    /// `ROLLBACK TO` a savepoint that doesn't exist.
+    UnknownSavepoint(String),
+    /// Aggregate function used in a clause that doesn't allow them.
+    AggregateNotAllowed(&'static str),
+    /// Column referenced outside of aggregate functions in an aggregate query.
+    UngroupedColumn(String),
+    /// `SELECT DISTINCT` sorted by an expression that is not in the list.
+    OrderByNotInDistinct(String),
+    /// Column referenced in a clause doesn't exist in the table.
+    UnknownColumn { column: String, clause: Clause },
+    /// Same table joined more than once.
+    DuplicatedTable(String),
+    /// Unqualified column name that exists in more than one joined table.
+    AmbiguousColumn(String),
+    /// `NOT NULL` column that would end up storing `NULL`.
+    NullConstraintViolation(String),
+    /// `ALTER TABLE DROP COLUMN` on a column that the table can't lose.
+    CannotDropColumn { column: String, reason: String },
+    /// `DROP INDEX` on an index that doesn't exist.
+    UnknownIndex(String),
+    /// `DROP INDEX` on an index created automatically for a constraint.
+    CannotDropIndex { index: String, reason: String },
+    /// `CASE` expression without `ELSE`, which would evaluate to `NULL`.
+    CaseWithoutElse,
+    /// `IS NULL` or `IS NOT NULL`, which can't be evaluated until values can
+    /// be `NULL`.
+    NullPredicate(Expression),
+    /// Tuples compared with something other than tuples or with operators
+    /// other than `=` and `!=`.
+    UnsupportedTuple(Expression),
+    /// Tuples of different length compared with each other.
+    TupleLengthMismatch { left: usize, right: usize },
+    /// Scalar function called with the wrong number of arguments.
+    ArgumentCountMismatch {
+        func: ScalarFunction,
+        expected: usize,
+        found: usize,
+    },
+}
+
+/// Clauses of a statement that can reference table columns.
+///
+/// Only used to give some context in error messages.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Clause {
+    Select,
+    On,
+    Where,
+    GroupBy,
+    Having,
+    OrderBy,
+}
+
+impl Display for Clause {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Select => "SELECT",
+            Self::On => "ON",
+            Self::Where => "WHERE",
+            Self::GroupBy => "GROUP BY",
+            Self::Having => "HAVING",
+            Self::OrderBy => "ORDER BY",
+        })
+    }
 }
 
 #[derive(Debug, PartialEq)]
 pub(crate) enum AlreadyExists {
     Table(String),
     Index(String),
+    Column(String),
 }
 
 impl Display for AlreadyExists {
@@ -51,6 +123,7 @@ impl Display for AlreadyExists {
         match self {
             Self::Index(index) => write!(f, "index {index} already exists"),
             Self::Table(table) => write!(f, "table {table} already exists"),
+            Self::Column(column) => write!(f, "column {column} already exists"),
         }
     }
 }
@@ -61,13 +134,19 @@ impl Display for AnalyzerError {
             Self::ColumnValueCountMismatch => f.write_str("number of columns doesn't match values"),
             Self::MultiplePrimaryKeys => f.write_str("only one primary key per table is allowed"),
             Self::MissingColumns => {
-                f.write_str("default values are not supported, all columns must be specified")
+                f.write_str("columns without a default value must be specified")
             }
             Self::DuplicatedColumn(col) => write!(f, "column '{col}' specified more than once"),
+            Self::NullConstraintViolation(col) => write!(f, "column '{col}' cannot be NULL"),
             Self::AlreadyExists(already_exists) => write!(f, "{already_exists}"),
-            Self::ValueTooLong(string, max) => {
-                write!(f, "string '{string}' too long for type VARCHAR({max})")
+            Self::ValueTooLong(string, data_type) => {
+                write!(f, "string '{string}' too long for type {data_type}")
             }
+            Self::LengthTooLarge(data_type) => write!(
+                f,
+                "{data_type} exceeds the maximum length of {} characters",
+                tuple::MAX_VARCHAR_LENGTH
+            ),
             Self::IntegerOutOfRange(num, data_type) => {
                 write!(f, "integer {num} out of range for data type {data_type}")
             }
@@ -79,6 +158,58 @@ impl Display for AnalyzerError {
                 f,
                 "table '{MKDB_META}' is reserved for internal use, it cannot be manually changed or created"
             ),
+            Self::WildcardWithoutFrom => f.write_str("SELECT * needs a FROM clause"),
+            Self::UnknownSavepoint(name) => write!(f, "savepoint {name} does not exist"),
+            Self::AggregateNotAllowed(clause) => {
+                write!(f, "aggregate functions are not allowed in {clause}")
+            }
+            Self::UngroupedColumn(col) => write!(
+                f,
+                "column '{col}' must appear in GROUP BY or be used in an aggregate function"
+            ),
+            Self::OrderByNotInDistinct(expr) => write!(
+                f,
+                "ORDER BY expression '{expr}' must appear in the SELECT DISTINCT list"
+            ),
+            Self::UnknownColumn { column, clause } => {
+                write!(f, "unknown column '{column}' in {clause}")
+            }
+            Self::DuplicatedTable(table) => {
+                write!(f, "table '{table}' can only be used once in FROM and JOIN")
+            }
+            Self::CannotDropColumn { column, reason } => {
+                write!(f, "cannot drop column '{column}' because {reason}")
+            }
+            Self::UnknownIndex(index) => write!(f, "index {index} does not exist"),
+            Self::CannotDropIndex { index, reason } => {
+                write!(f, "cannot drop index '{index}' because {reason}")
+            }
+            Self::UnsupportedTuple(expr) => write!(
+                f,
+                "unsupported expression {expr}, tuples can only be compared to other tuples with = or !="
+            ),
+            Self::TupleLengthMismatch { left, right } => write!(
+                f,
+                "cannot compare tuple of {left} elements with tuple of {right} elements"
+            ),
+            Self::ArgumentCountMismatch {
+                func,
+                expected,
+                found,
+            } => write!(
+                f,
+                "function {func}() takes {expected} arguments but {found} were given"
+            ),
+            Self::CaseWithoutElse => f.write_str(
+                "CASE expressions must have an ELSE branch, NULL values are not supported",
+            ),
+            Self::NullPredicate(expr) => {
+                write!(f, "cannot evaluate '{expr}', NULL values are not supported")
+            }
+            Self::AmbiguousColumn(col) => write!(
+                f,
+                "column '{col}' is ambiguous, qualify it with the table name"
+            ),
         }
     }
 }
@@ -90,13 +221,34 @@ pub(crate) fn analyze(
     statement: &Statement,
     ctx: &mut impl DatabaseContext,
 ) -> Result<(), DbError> {
+    // Column references are analyzed with the same names that the prepare
+    // step is going to use later. See [`resolve_column_references`].
+    let resolved;
+    let statement = match statement {
+        Statement::Select { .. } | Statement::Update { .. } | Statement::Delete { .. } => {
+            let mut statement = statement.clone();
+            resolve_column_references(&mut statement, ctx)?;
+            resolved = statement;
+            &resolved
+        }
+
+        _ => statement,
+    };
+
     match statement {
-        Statement::Create(Create::Table { columns, name }) => {
+        Statement::Create(Create::Table {
+            columns,
+            name,
+            if_not_exists,
+        }) => {
             match ctx.table_metadata(name) {
                 Err(DbError::Sql(SqlError::InvalidTable(_))) => {
                     // Table doesn't exist, we can create it.
                 }
 
+                // Nothing to do, the VM will skip this statement.
+                Ok(_) if *if_not_exists => return Ok(()),
+
                 Ok(_) => {
                     return Err(DbError::from(AnalyzerError::AlreadyExists(
                         AlreadyExists::Table(name.clone()),
@@ -108,6 +260,7 @@ pub(crate) fn analyze(
 
             let mut found_primary_key = false;
             let mut duplicates = HashSet::new();
+            let schema = Schema::from(columns);
 
             for col in columns {
                 if !duplicates.insert(&col.name) {
@@ -118,12 +271,33 @@ pub(crate) fn analyze(
                     return Err(AnalyzerError::RowIdAssignment.into());
                 }
 
+                if let DataType::Varchar(max) = col.data_type {
+                    if max > tuple::MAX_VARCHAR_LENGTH {
+                        return Err(AnalyzerError::LengthTooLarge(col.data_type).into());
+                    }
+                }
+
                 if col.constraints.contains(&Constraint::PrimaryKey) {
                     if found_primary_key {
                         return Err(AnalyzerError::MultiplePrimaryKeys.into());
                     }
                     found_primary_key = true;
                 }
+
+                if let Some(default) = &col.default {
+                    let cast = if ctx.implicit_casts() {
+                        implicit_cast(default, &col.data_type)
+                    } else {
+                        None
+                    };
+
+                    analyze_assignment(
+                        &schema,
+                        &col.name,
+                        cast.as_ref().unwrap_or(default),
+                        false,
+                    )?;
+                }
             }
         }
 
@@ -131,7 +305,7 @@ pub(crate) fn analyze(
             table,
             unique,
             name,
-            ..
+            columns,
         }) => {
             if !unique {
                 return Err(DbError::Sql(SqlError::Other(
@@ -149,6 +323,17 @@ pub(crate) fn analyze(
                     AnalyzerError::AlreadyExists(AlreadyExists::Index(name.clone())).into(),
                 );
             }
+
+            let mut duplicates = HashSet::new();
+
+            for IndexColumn { name: col, .. } in columns {
+                if metadata.schema.index_of(col).is_none() {
+                    return Err(DbError::Sql(SqlError::InvalidColumn(col.clone())));
+                }
+                if !duplicates.insert(col) {
+                    return Err(AnalyzerError::DuplicatedColumn(col.into()).into());
+                }
+            }
         }
 
         Statement::Insert {
@@ -156,6 +341,17 @@ pub(crate) fn analyze(
             columns,
             values,
         } => {
+            // Rows produced by `INSERT INTO table SELECT ...` and their types.
+            let select_output = match values {
+                InsertSource::Select(select) => {
+                    analyze(select, ctx)?;
+                    Some(analyze_select_output(select, ctx)?)
+                }
+
+                InsertSource::Values(_) => None,
+            };
+
+            let implicit_casts = ctx.implicit_casts();
             let metadata = ctx.table_metadata(into)?;
 
             if into == MKDB_META {
@@ -164,10 +360,16 @@ pub(crate) fn analyze(
 
             let mut columns = columns.as_slice();
 
-            // In case the user didn't specify any columns.
+            // In case the user didn't specify any columns. Empty values mean
+            // that all the columns take their default value instead.
             let schema_column_names_copy: Vec<String>;
 
-            if columns.is_empty() {
+            let has_values = match values {
+                InsertSource::Values(rows) => rows.iter().any(|row| !row.is_empty()),
+                InsertSource::Select(_) => true,
+            };
+
+            if columns.is_empty() && has_values {
                 schema_column_names_copy = metadata.schema.column_identifiers();
                 columns = schema_column_names_copy.as_slice();
                 // User can't set row ID manually so remove it.
@@ -176,7 +378,15 @@ pub(crate) fn analyze(
                 }
             }
 
-            if columns.len() != values.len() {
+            let count_mismatch = match (values, &select_output) {
+                (InsertSource::Values(rows), _) => {
+                    rows.iter().any(|row| row.len() != columns.len())
+                }
+                (InsertSource::Select(_), Some(output)) => output.len() != columns.len(),
+                _ => unreachable!(),
+            };
+
+            if count_mismatch {
                 return Err(AnalyzerError::ColumnValueCountMismatch.into());
             }
 
@@ -194,40 +404,137 @@ pub(crate) fn analyze(
                 }
             }
 
-            // The user can't manually set the special "row_id" the column.
-            let schema_len = if metadata.schema.columns[0].name == ROW_ID_COL {
-                metadata.schema.len() - 1
-            } else {
-                metadata.schema.len()
-            };
+            // Omitted columns take their default value. Without a default
+            // there's nothing to store, since we don't support NULL yet. The
+            // row ID is generated internally.
+            for col in &metadata.schema.columns {
+                if col.name == ROW_ID_COL || col.default.is_some() || columns.contains(&col.name) {
+                    continue;
+                }
+
+                if col.is_not_null() {
+                    return Err(AnalyzerError::NullConstraintViolation(col.name.clone()).into());
+                }
 
-            if schema_len != columns.len() {
                 return Err(AnalyzerError::MissingColumns.into());
             }
 
-            for (expr, col) in values.iter().zip(columns) {
-                analyze_assignment(metadata, col, expr, false)?;
+            let rows = match values {
+                InsertSource::Values(rows) => rows.as_slice(),
+                InsertSource::Select(_) => &[],
+            };
+
+            for (expr, col) in rows.iter().flat_map(|row| row.iter().zip(columns)) {
+                let cast = if implicit_casts {
+                    let index = metadata.schema.index_of(col).unwrap();
+                    implicit_cast(expr, &metadata.schema.columns[index].data_type)
+                } else {
+                    None
+                };
+
+                analyze_assignment(&metadata.schema, col, cast.as_ref().unwrap_or(expr), false)?;
+            }
+
+            // Values computed by the SELECT statement can't be checked
+            // against VARCHAR limits or integer ranges, only the types.
+            for ((expr, found), col) in select_output.iter().flatten().zip(columns) {
+                let index = metadata.schema.index_of(col).unwrap();
+                let expected = VmDataType::from(metadata.schema.columns[index].data_type);
+
+                // Integers can be stored in float columns, not the other way around.
+                let integer_into_float =
+                    expected == VmDataType::Float && *found == VmDataType::Number;
+
+                if expected != *found && !integer_into_float {
+                    return Err(TypeError::ExpectedType {
+                        expected,
+                        found: expr.clone(),
+                    }
+                    .into());
+                }
             }
         }
 
         Statement::Select {
+            distinct,
             from,
+            joins,
             columns,
             r#where,
+            group_by,
+            having,
             order_by,
+            ..
         } => {
-            let metadata = ctx.table_metadata(from)?;
+            if from.is_none() && columns.iter().any(|item| item.expr == Expression::Wildcard) {
+                return Err(AnalyzerError::WildcardWithoutFrom.into());
+            }
 
-            for expr in columns {
+            let schema = analyze_joins(ctx, from.as_deref(), joins)?;
+
+            for SelectItem { expr, .. } in columns {
                 if expr != &Expression::Wildcard {
-                    analyze_expression(&metadata.schema, None, expr)?;
+                    analyze_clause_expression(&schema, Clause::Select, expr)?;
                 }
             }
 
-            analyze_where(&metadata.schema, r#where)?;
+            analyze_where(&schema, r#where)?;
+
+            for expr in group_by {
+                if expr.contains_aggregate() {
+                    return Err(AnalyzerError::AggregateNotAllowed("GROUP BY").into());
+                }
+                analyze_clause_expression(&schema, Clause::GroupBy, expr)?;
+            }
+
+            if let Some(expr) = having {
+                analyze_predicate(&schema, Clause::Having, expr)?;
+            }
+
+            let aggregate = !group_by.is_empty()
+                || having.is_some()
+                || columns.iter().any(|item| item.expr.contains_aggregate());
 
-            for expr in order_by {
-                analyze_expression(&metadata.schema, None, expr)?;
+            // Aggregate queries sort the groups, which can be sorted by the
+            // results of aggregate functions as well.
+            for order in order_by {
+                if order.expr.contains_aggregate() && !aggregate {
+                    return Err(AnalyzerError::AggregateNotAllowed("ORDER BY").into());
+                }
+                analyze_clause_expression(&schema, Clause::OrderBy, &order.expr)?;
+            }
+
+            // Aggregate queries produce only one row per group, so all the
+            // columns must be either grouping keys or wrapped in aggregate
+            // functions.
+            if aggregate {
+                for expr in columns
+                    .iter()
+                    .map(|item| &item.expr)
+                    .chain(having)
+                    .chain(order_by.iter().map(|order| &order.expr))
+                {
+                    analyze_ungrouped_columns(expr, group_by)?;
+                }
+            }
+
+            // Duplicates are removed after projecting the rows, so there's
+            // nothing else to sort by. The wildcard is not expanded yet, but
+            // it includes every column except the row ID (qualified or not).
+            if *distinct {
+                let wildcard = columns.iter().any(|item| item.expr == Expression::Wildcard);
+
+                for order in order_by {
+                    let in_list = columns.iter().any(|item| item.expr == order.expr)
+                        || wildcard
+                            && matches!(&order.expr, Expression::Identifier(col) if col.rsplit('.').next() != Some(ROW_ID_COL));
+
+                    if !in_list {
+                        return Err(
+                            AnalyzerError::OrderByNotInDistinct(order.expr.to_string()).into()
+                        );
+                    }
+                }
             }
         }
 
@@ -253,348 +560,1915 @@ pub(crate) fn analyze(
             }
 
             for col in columns {
-                analyze_assignment(metadata, &col.identifier, &col.value, true)?;
+                analyze_assignment(&metadata.schema, &col.identifier, &col.value, true)?;
             }
 
             analyze_where(&metadata.schema, r#where)?;
         }
 
-        Statement::Explain(inner) => {
-            analyze(inner, ctx)?;
+        Statement::Explain { statement, .. } => {
+            analyze(statement, ctx)?;
         }
 
-        Statement::Drop(Drop::Table(table)) => {
+        Statement::Analyze(table) => {
+            if table == MKDB_META {
+                return Err(AnalyzerError::MkdbMetaModification.into());
+            }
+
             ctx.table_metadata(table)?;
         }
 
-        _ => {
-            // Rest of statements that we support don't require any analysis.
+        Statement::RollbackTo(name) => {
+            if !ctx.has_savepoint(name) {
+                return Err(AnalyzerError::UnknownSavepoint(name.clone()).into());
+            }
         }
-    };
-
-    Ok(())
-}
 
-/// Makes sure that the given expression is valid and evaluates to a boolean.
-fn analyze_where(schema: &Schema, r#where: &Option<Expression>) -> Result<(), DbError> {
-    let Some(expr) = r#where else {
-        return Ok(());
-    };
+        Statement::Drop(Drop::Table { name, if_exists }) => match ctx.table_metadata(name) {
+            Err(DbError::Sql(SqlError::InvalidTable(_))) if *if_exists => {}
+            result => {
+                result?;
+            }
+        },
 
-    if let VmDataType::Bool = analyze_expression(schema, None, expr)? {
-        return Ok(());
-    };
+        Statement::Drop(Drop::Index {
+            name,
+            table,
+            if_exists,
+        }) => {
+            let table = match table {
+                Some(table) => table.clone(),
+                None => match ctx.index_table(name)? {
+                    Some(table) => table,
+                    None if *if_exists => return Ok(()),
+                    None => return Err(AnalyzerError::UnknownIndex(name.clone()).into()),
+                },
+            };
 
-    Err(TypeError::ExpectedType {
-        expected: VmDataType::Bool,
-        found: expr.clone(),
-    })?
-}
+            if table == MKDB_META {
+                return Err(AnalyzerError::MkdbMetaModification.into());
+            }
 
-/// Makes sure that the expression will evaluate to a data type that can be
-/// assigned to the given column.
-///
-/// Performs some additional checks such as VARCHAR(max) character limits.
-fn analyze_assignment(
-    table: &TableMetadata,
-    column: &str,
-    value: &Expression,
-    allow_identifiers: bool,
-) -> Result<(), SqlError> {
-    if column == ROW_ID_COL {
-        return Err(AnalyzerError::RowIdAssignment.into());
-    }
+            let metadata = match ctx.table_metadata(&table) {
+                Err(DbError::Sql(SqlError::InvalidTable(_))) if *if_exists => return Ok(()),
+                result => result?,
+            };
 
-    let index = table
-        .schema
-        .index_of(column)
-        .ok_or(SqlError::InvalidColumn(column.into()))?;
+            if !metadata.indexes.iter().any(|index| &index.name == name) {
+                if *if_exists {
+                    return Ok(());
+                }
 
-    let data_type = table.schema.columns[index].data_type;
+                return Err(AnalyzerError::UnknownIndex(name.clone()).into());
+            }
 
-    let expected_data_type = VmDataType::from(data_type);
-    let pre_eval_data_type = if allow_identifiers {
-        analyze_expression(&table.schema, Some(&data_type), value)?
-    } else {
-        analyze_expression(&Schema::empty(), Some(&data_type), value)?
-    };
+            // Indexes created by CREATE TABLE enforce the constraints, dropping
+            // them would silently disable the constraint checks.
+            let constraint = metadata.schema.columns.iter().find_map(|col| {
+                col.constraints
+                    .iter()
+                    .find_map(|constraint| match constraint {
+                        Constraint::PrimaryKey if *name == format!("{table}_pk_index") => {
+                            Some(format!("it enforces the primary key of table {table}"))
+                        }
+                        Constraint::Unique if *name == format!("{table}_{}_uq_index", col.name) => {
+                            Some(format!(
+                                "it enforces the unique constraint of column {}",
+                                col.name
+                            ))
+                        }
+                        _ => None,
+                    })
+            });
+
+            if let Some(reason) = constraint {
+                return Err(AnalyzerError::CannotDropIndex {
+                    index: name.clone(),
+                    reason,
+                }
+                .into());
+            }
+        }
 
-    if expected_data_type != pre_eval_data_type {
-        return Err(SqlError::TypeError(TypeError::ExpectedType {
-            expected: expected_data_type,
-            found: value.clone(),
-        }));
-    }
+        Statement::Alter(Alter::DropColumn { table, column }) => {
+            if table == MKDB_META {
+                return Err(AnalyzerError::MkdbMetaModification.into());
+            }
 
-    if let DataType::Varchar(max) = data_type {
-        if let Expression::Value(Value::String(string)) = value {
-            if string.chars().count() > max {
-                return Err(AnalyzerError::ValueTooLong(string.clone(), max).into());
+            if column == ROW_ID_COL {
+                return Err(AnalyzerError::RowIdAssignment.into());
             }
-        };
-    }
 
-    Ok(())
-}
+            let metadata = ctx.table_metadata(table)?;
 
-/// Predetermines the type that an expression will evaluate to.
-///
-/// The expression resolver can also do that because it actually evaluates the
-/// expression, but in the cases of statements with `WHERE` clauses it won't be
-/// called until we loaded tuples into memory, which requires IO, so it's better
-/// to do this check now.
-///
-/// If there are type errors or unknown columns not present in the given
-/// schema then an error is returned.
-pub(crate) fn analyze_expression(
-    schema: &Schema,
-    col_data_type: Option<&DataType>,
-    expr: &Expression,
-) -> Result<VmDataType, SqlError> {
-    Ok(match expr {
-        Expression::Value(value) => match value {
-            Value::Bool(_) => VmDataType::Bool,
-            Value::String(_) => VmDataType::String,
-            Value::Number(num) => {
-                if let Some(data_type) = col_data_type {
-                    analyze_integer_range(num, data_type)?;
-                }
+            let col = metadata
+                .schema
+                .index_of(column)
+                .map(|index| &metadata.schema.columns[index])
+                .ok_or(SqlError::InvalidColumn(column.clone()))?;
+
+            // TODO: We could drop the index together with the column instead
+            // of making the user do it with DROP INDEX.
+            let reason = if col.constraints.contains(&Constraint::PrimaryKey) {
+                Some(String::from("it is the primary key"))
+            } else if let Some(index) = metadata
+                .indexes
+                .iter()
+                .find(|i| i.columns.iter().any(|c| &c.name == column))
+            {
+                Some(format!("index {} depends on it", index.name))
+            } else if metadata
+                .schema
+                .columns
+                .iter()
+                .filter(|c| c.name != ROW_ID_COL)
+                .count()
+                == 1
+            {
+                Some(format!("it is the only column of table {table}"))
+            } else {
+                None
+            };
 
-                VmDataType::Number
+            if let Some(reason) = reason {
+                return Err(AnalyzerError::CannotDropColumn {
+                    column: column.clone(),
+                    reason,
+                }
+                .into());
             }
-        },
-
-        Expression::Identifier(ident) => {
-            let index = schema
-                .index_of(ident)
-                .ok_or(SqlError::InvalidColumn(ident.clone()))?;
+        }
 
-            match schema.columns[index].data_type {
-                DataType::Bool => VmDataType::Bool,
-                DataType::Varchar(_) => VmDataType::String,
-                _ => VmDataType::Number,
+        Statement::Alter(Alter::RenameColumn {
+            table,
+            column,
+            new_name,
+        }) => {
+            if table == MKDB_META {
+                return Err(AnalyzerError::MkdbMetaModification.into());
             }
-        }
 
-        Expression::UnaryOperation { operator, expr } => {
-            // Precompute negative numbers since the optimizer hasn't run yet.
-            if let (Some(data_type), UnaryOperator::Minus, Expression::Value(Value::Number(num))) =
-                (col_data_type, *operator, &**expr)
-            {
-                analyze_integer_range(&-num, data_type)?;
-                return Ok(VmDataType::Number);
+            if column == ROW_ID_COL || new_name == ROW_ID_COL {
+                return Err(AnalyzerError::RowIdAssignment.into());
             }
 
-            match analyze_expression(schema, col_data_type, expr)? {
-                VmDataType::Number => VmDataType::Number,
+            let schema = &ctx.table_metadata(table)?.schema;
 
-                _ => Err(TypeError::ExpectedType {
-                    expected: VmDataType::Number,
-                    found: *expr.clone(),
-                })?,
+            if schema.index_of(column).is_none() {
+                return Err(SqlError::InvalidColumn(column.clone()).into());
             }
-        }
-
-        Expression::BinaryOperation {
-            left,
-            operator,
-            right,
-        } => {
-            let left_data_type = analyze_expression(schema, col_data_type, left)?;
-            let right_data_type = analyze_expression(schema, col_data_type, right)?;
 
-            // TODO: We're lazily evaluating this because we have to clone.
-            // Figure out if we can refactor this module to avoid cloning
-            // for errors.
-            let mismatched_types = || {
-                SqlError::TypeError(TypeError::CannotApplyBinary {
-                    left: *left.clone(),
-                    operator: *operator,
-                    right: *right.clone(),
-                })
-            };
+            if schema.index_of(new_name).is_some() {
+                return Err(
+                    AnalyzerError::AlreadyExists(AlreadyExists::Column(new_name.clone())).into(),
+                );
+            }
+        }
 
-            if left_data_type != right_data_type {
-                return Err(mismatched_types());
+        Statement::Alter(Alter::RenameTable { table, new_name }) => {
+            if table == MKDB_META || new_name == MKDB_META {
+                return Err(AnalyzerError::MkdbMetaModification.into());
             }
 
-            match operator {
-                BinaryOperator::Eq
-                | BinaryOperator::Neq
-                | BinaryOperator::Lt
-                | BinaryOperator::LtEq
-                | BinaryOperator::Gt
-                | BinaryOperator::GtEq => VmDataType::Bool,
+            ctx.table_metadata(table)?;
 
-                BinaryOperator::And | BinaryOperator::Or if left_data_type == VmDataType::Bool => {
-                    VmDataType::Bool
+            match ctx.table_metadata(new_name) {
+                Err(DbError::Sql(SqlError::InvalidTable(_))) => {}
+
+                Ok(_) => {
+                    return Err(AnalyzerError::AlreadyExists(AlreadyExists::Table(
+                        new_name.clone(),
+                    ))
+                    .into());
                 }
 
-                BinaryOperator::Plus
-                | BinaryOperator::Minus
-                | BinaryOperator::Div
-                | BinaryOperator::Mul
-                    if left_data_type == VmDataType::Number =>
+                Err(e) => return Err(e),
+            }
+        }
+
+        _ => {
+            // Rest of statements that we support don't require any analysis.
+        }
+    };
+
+    Ok(())
+}
+
+/// Rewrites the column references of `SELECT`, `UPDATE` and `DELETE`
+/// statements using the exact column names of the schema they work with.
+///
+/// After this [`Expression::QualifiedIdentifier`] is gone and all the columns
+/// are plain [`Expression::Identifier`] variants that can be found with
+/// [`Schema::index_of`]. See [`resolve_identifiers`] for the rules.
+pub(crate) fn resolve_column_references(
+    statement: &mut Statement,
+    ctx: &mut impl DatabaseContext,
+) -> Result<(), DbError> {
+    match statement {
+        Statement::Select {
+            columns,
+            from,
+            joins,
+            r#where,
+            group_by,
+            having,
+            order_by,
+            ..
+        } => {
+            // ORDER BY can reference the aliases of the SELECT list, they
+            // stand for the aliased expressions.
+            for order in order_by.iter_mut() {
+                let Expression::Identifier(ident) = &order.expr else {
+                    continue;
+                };
+
+                if let Some(item) = columns
+                    .iter()
+                    .find(|item| item.alias.as_ref() == Some(ident))
                 {
-                    VmDataType::Number
+                    order.expr = item.expr.clone();
+                }
+            }
+
+            let (schema, table) = match from {
+                // Constant queries can't reference any column.
+                None => (Schema::empty(), None),
+
+                Some(from) if joins.is_empty() => {
+                    let schema = ctx.table_metadata(from)?.schema.clone();
+                    (schema, Some(from.as_str()))
                 }
 
-                _ => Err(mismatched_types())?,
+                Some(from) => {
+                    let mut schema = ctx.table_metadata(from)?.schema.qualified(from);
+
+                    // Each ON clause only sees the tables that come before it.
+                    for Join { table, on } in joins {
+                        for col in ctx.table_metadata(table)?.schema.qualified(table).columns {
+                            schema.push(col);
+                        }
+                        resolve_identifiers(on, &schema, None)?;
+                    }
+
+                    (schema, None)
+                }
+            };
+
+            for expr in columns
+                .iter_mut()
+                .map(|item| &mut item.expr)
+                .chain(r#where)
+                .chain(group_by)
+                .chain(having)
+                .chain(order_by.iter_mut().map(|order| &mut order.expr))
+            {
+                resolve_identifiers(expr, &schema, table)?;
             }
         }
 
-        Expression::Nested(expr) => analyze_expression(schema, col_data_type, expr)?,
+        Statement::Update {
+            table,
+            columns,
+            r#where,
+        } => {
+            let schema = &ctx.table_metadata(table)?.schema;
 
-        Expression::Wildcard => {
-            return Err(SqlError::Other("unexpected wildcard expression (*)".into()))
+            for expr in columns.iter_mut().map(|col| &mut col.value).chain(r#where) {
+                resolve_identifiers(expr, schema, Some(table))?;
+            }
         }
-    })
+
+        Statement::Delete {
+            from,
+            r#where: Some(expr),
+        } => {
+            resolve_identifiers(expr, &ctx.table_metadata(from)?.schema, Some(from))?;
+        }
+
+        _ => {}
+    }
+
+    Ok(())
 }
 
-/// Returns an error if the integer is out of range for the given data type.
-fn analyze_integer_range(integer: &i128, data_type: &DataType) -> Result<(), AnalyzerError> {
-    if let DataType::BigInt | DataType::Int | DataType::UnsignedBigInt | DataType::UnsignedInt =
-        data_type
-    {
-        if !tuple::integer_is_within_range(integer, data_type) {
-            return Err(AnalyzerError::IntegerOutOfRange(*integer, *data_type));
+/// Resolves the column references found in `expr`.
+///
+/// When `table` is [`Some`] the schema is the schema of that table, so
+/// `users.id` becomes `id` if `table` is "users". Otherwise the schema comes
+/// from joined tables and all its columns are qualified (see
+/// [`Schema::qualified`]). In that case `users.id` is kept as is and
+/// unqualified names like `id` are qualified if only one of the tables has
+/// such a column.
+///
+/// Columns that don't exist are left alone, the analyzer reports them later.
+fn resolve_identifiers(
+    expr: &mut Expression,
+    schema: &Schema,
+    table: Option<&str>,
+) -> Result<(), AnalyzerError> {
+    match expr {
+        Expression::QualifiedIdentifier {
+            table: qualifier,
+            column,
+        } => {
+            *expr = match table {
+                Some(table) if table == qualifier => Expression::Identifier(column.clone()),
+                _ => Expression::Identifier(format!("{qualifier}.{column}")),
+            };
+        }
+
+        Expression::Identifier(ident) if table.is_none() && schema.index_of(ident).is_none() => {
+            let mut matches = schema.columns.iter().filter(|col| {
+                col.name
+                    .split_once('.')
+                    .is_some_and(|(_, column)| column == ident)
+            });
+
+            if let Some(col) = matches.next() {
+                if matches.next().is_some() {
+                    return Err(AnalyzerError::AmbiguousColumn(ident.clone()));
+                }
+                *ident = col.name.clone();
+            }
+        }
+
+        Expression::BinaryOperation { left, right, .. } => {
+            resolve_identifiers(left, schema, table)?;
+            resolve_identifiers(right, schema, table)?;
+        }
+
+        Expression::UnaryOperation { expr, .. }
+        | Expression::Nested(expr)
+        | Expression::IsNull { expr, .. }
+        | Expression::Aggregate { arg: expr, .. } => resolve_identifiers(expr, schema, table)?,
+
+        Expression::InList { expr, list, .. } => {
+            resolve_identifiers(expr, schema, table)?;
+            for item in list {
+                resolve_identifiers(item, schema, table)?;
+            }
         }
+
+        Expression::Between {
+            expr, low, high, ..
+        } => {
+            resolve_identifiers(expr, schema, table)?;
+            resolve_identifiers(low, schema, table)?;
+            resolve_identifiers(high, schema, table)?;
+        }
+
+        Expression::Function { args, .. } | Expression::Tuple(args) => {
+            for arg in args {
+                resolve_identifiers(arg, schema, table)?;
+            }
+        }
+
+        Expression::Case {
+            branches,
+            else_branch,
+        } => {
+            for (condition, result) in branches {
+                resolve_identifiers(condition, schema, table)?;
+                resolve_identifiers(result, schema, table)?;
+            }
+
+            if let Some(else_branch) = else_branch {
+                resolve_identifiers(else_branch, schema, table)?;
+            }
+        }
+
+        Expression::Identifier(_) | Expression::Value(_) | Expression::Wildcard => {}
     }
 
     Ok(())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::{AlreadyExists, AnalyzerError};
-    use crate::{
-        db::{Context, DbError, SqlError},
-        sql::{
-            analyzer::analyze,
-            parser::Parser,
-            statement::{BinaryOperator, DataType, Expression, Value},
-        },
-        vm::{TypeError, VmDataType},
+/// Makes sure that the given expression is valid and evaluates to a boolean.
+fn analyze_where(schema: &Schema, r#where: &Option<Expression>) -> Result<(), DbError> {
+    let Some(expr) = r#where else {
+        return Ok(());
     };
 
-    struct Analyze<'s> {
-        sql: &'s str,
-        ctx: &'s [&'s str],
-        expected: Result<(), DbError>,
+    if expr.contains_aggregate() {
+        return Err(AnalyzerError::AggregateNotAllowed("WHERE").into());
+    }
+
+    analyze_predicate(schema, Clause::Where, expr)
+}
+
+/// Returns the schema of the tuples that `SELECT` statements work with.
+///
+/// Without joins that's just the table schema. Otherwise the columns of all the
+/// tables are qualified with their table name and each `ON` clause can only
+/// reference the tables that come before it (including its own). Constant
+/// queries without `FROM` have no columns at all.
+fn analyze_joins(
+    ctx: &mut impl DatabaseContext,
+    from: Option<&str>,
+    joins: &[Join],
+) -> Result<Schema, DbError> {
+    let Some(from) = from else {
+        return Ok(Schema::empty());
+    };
+
+    let schema = &ctx.table_metadata(from)?.schema;
+
+    if joins.is_empty() {
+        return Ok(schema.clone());
+    }
+
+    let mut schema = schema.qualified(from);
+    let mut tables = HashSet::from([from]);
+
+    for Join { table, on } in joins {
+        if !tables.insert(table) {
+            return Err(AnalyzerError::DuplicatedTable(table.clone()).into());
+        }
+
+        for col in ctx.table_metadata(table)?.schema.qualified(table).columns {
+            schema.push(col);
+        }
+
+        if on.contains_aggregate() {
+            return Err(AnalyzerError::AggregateNotAllowed("ON").into());
+        }
+
+        analyze_predicate(&schema, Clause::On, on)?;
+    }
+
+    Ok(schema)
+}
+
+/// Returns the expressions that a `SELECT` statement produces along with the
+/// data type of each one.
+///
+/// Wildcards are expanded the same way [`super::prepare`] does it.
+fn analyze_select_output(
+    select: &Statement,
+    ctx: &mut impl DatabaseContext,
+) -> Result<Vec<(Expression, VmDataType)>, DbError> {
+    let mut select = select.clone();
+    resolve_column_references(&mut select, ctx)?;
+
+    let Statement::Select {
+        columns,
+        from,
+        joins,
+        ..
+    } = &select
+    else {
+        unreachable!("analyze_select_output() called with {select}");
+    };
+
+    let schema = analyze_joins(ctx, from.as_deref(), joins)?;
+    let mut output = Vec::new();
+
+    for SelectItem { expr, .. } in columns {
+        if expr != &Expression::Wildcard {
+            output.push((expr.clone(), analyze_expression(&schema, None, expr)?));
+            continue;
+        }
+
+        for table in from.iter().chain(joins.iter().map(|join| &join.table)) {
+            for col in &ctx.table_metadata(table)?.schema.columns {
+                if col.name != ROW_ID_COL {
+                    let ident = Expression::Identifier(col.name.clone());
+                    output.push((ident, VmDataType::from(col.data_type)));
+                }
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Makes sure that the expression of a `WHERE`, `HAVING` or `ON` clause
+/// evaluates to a boolean.
+fn analyze_predicate(schema: &Schema, clause: Clause, expr: &Expression) -> Result<(), DbError> {
+    if let VmDataType::Bool = analyze_clause_expression(schema, clause, expr)? {
+        return Ok(());
+    };
+
+    Err(TypeError::ExpectedType {
+        expected: VmDataType::Bool,
+        found: expr.clone(),
+    })?
+}
+
+/// Same as [`analyze_expression`] but unknown columns are reported along with
+/// the clause in which they were found.
+fn analyze_clause_expression(
+    schema: &Schema,
+    clause: Clause,
+    expr: &Expression,
+) -> Result<VmDataType, SqlError> {
+    analyze_expression(schema, None, expr).map_err(|err| match err {
+        SqlError::InvalidColumn(column) => AnalyzerError::UnknownColumn { column, clause }.into(),
+        other => other,
+    })
+}
+
+/// Makes sure that the expression will evaluate to a data type that can be
+/// assigned to the given column.
+///
+/// Performs some additional checks such as VARCHAR(max) and CHAR(n) limits.
+fn analyze_assignment(
+    schema: &Schema,
+    column: &str,
+    value: &Expression,
+    allow_identifiers: bool,
+) -> Result<(), SqlError> {
+    if column == ROW_ID_COL {
+        return Err(AnalyzerError::RowIdAssignment.into());
+    }
+
+    let index = schema
+        .index_of(column)
+        .ok_or(SqlError::InvalidColumn(column.into()))?;
+
+    let data_type = schema.columns[index].data_type;
+
+    if value.contains_aggregate() {
+        return Err(AnalyzerError::AggregateNotAllowed("assignments").into());
+    }
+
+    let expected_data_type = VmDataType::from(data_type);
+    let pre_eval_data_type = if allow_identifiers {
+        analyze_expression(schema, Some(&data_type), value)?
+    } else {
+        analyze_expression(&Schema::empty(), Some(&data_type), value)?
+    };
+
+    // Integers can be stored in float columns, not the other way around.
+    let integer_into_float =
+        expected_data_type == VmDataType::Float && pre_eval_data_type == VmDataType::Number;
+
+    if expected_data_type != pre_eval_data_type && !integer_into_float {
+        return Err(SqlError::TypeError(TypeError::ExpectedType {
+            expected: expected_data_type,
+            found: value.clone(),
+        }));
+    }
+
+    if let Expression::Value(value) = value {
+        check_length(value, &data_type)?;
+    }
+
+    Ok(())
+}
+
+/// Fails with [`AnalyzerError::ValueTooLong`] if `value` is a string that
+/// doesn't fit in `data_type`.
+///
+/// Literals are checked while analyzing statements, but strings computed at
+/// runtime (`UPDATE` expressions, `INSERT INTO ... SELECT`, etc) have to be
+/// checked by the VM before writing them.
+pub(crate) fn check_length(value: &Value, data_type: &DataType) -> Result<(), AnalyzerError> {
+    let Value::String(string) = value else {
+        return Ok(());
+    };
+
+    let too_long = match *data_type {
+        DataType::Varchar(max) => string.chars().count() > max,
+        // CHAR length is measured in bytes. See [`DataType::Char`].
+        DataType::Char(length) => string.len() > length,
+        _ => false,
+    };
+
+    if too_long {
+        return Err(AnalyzerError::ValueTooLong(string.clone(), *data_type));
+    }
+
+    Ok(())
+}
+
+/// Converts literal values that don't match `data_type` when the conversion
+/// is safe.
+///
+/// Only numeric strings into integer or `DOUBLE` columns and numbers into
+/// `VARCHAR` or `CHAR` columns are supported. Returns [`None`] if there's nothing to convert or
+/// the conversion is not possible, in which case the original expression
+/// should be analyzed as usual and fail with a type error if necessary.
+pub(crate) fn implicit_cast(expr: &Expression, data_type: &DataType) -> Option<Expression> {
+    let Expression::Value(value) = expr else {
+        return None;
+    };
+
+    match (value, data_type) {
+        (Value::String(_), DataType::Varchar(_) | DataType::Char(_) | DataType::Bool) => None,
+        (Value::String(string), DataType::Double) => string.parse().ok().map(Value::Float),
+        (Value::String(string), _) => string.parse().ok().map(Value::Number),
+        (Value::Number(_) | Value::Float(_), DataType::Varchar(_) | DataType::Char(_)) => {
+            Some(Value::String(value.to_string()))
+        }
+        _ => None,
+    }
+    .map(Expression::Value)
+}
+
+/// Predetermines the type that an expression will evaluate to.
+///
+/// The expression resolver can also do that because it actually evaluates the
+/// expression, but in the cases of statements with `WHERE` clauses it won't be
+/// called until we loaded tuples into memory, which requires IO, so it's better
+/// to do this check now.
+///
+/// If there are type errors or unknown columns not present in the given
+/// schema then an error is returned.
+pub(crate) fn analyze_expression(
+    schema: &Schema,
+    col_data_type: Option<&DataType>,
+    expr: &Expression,
+) -> Result<VmDataType, SqlError> {
+    Ok(match expr {
+        Expression::Value(value) => match value {
+            Value::Bool(_) => VmDataType::Bool,
+            Value::String(_) => VmDataType::String,
+            Value::Number(num) => {
+                if let Some(data_type) = col_data_type {
+                    analyze_integer_range(num, data_type)?;
+                }
+
+                VmDataType::Number
+            }
+            Value::Float(_) => VmDataType::Float,
+        },
+
+        Expression::Identifier(ident) => {
+            let index = schema
+                .index_of(ident)
+                .ok_or(SqlError::InvalidColumn(ident.clone()))?;
+
+            match schema.columns[index].data_type {
+                DataType::Bool => VmDataType::Bool,
+                DataType::Varchar(_) | DataType::Char(_) => VmDataType::String,
+                DataType::Double => VmDataType::Float,
+                _ => VmDataType::Number,
+            }
+        }
+
+        // Only statements that go through [`resolve_column_references`] can
+        // reference other tables.
+        Expression::QualifiedIdentifier { .. } => Err(SqlError::InvalidColumn(expr.to_string()))?,
+
+        Expression::UnaryOperation { operator, expr } => {
+            // Precompute negative numbers since the optimizer hasn't run yet.
+            if let (Some(data_type), UnaryOperator::Minus, Expression::Value(Value::Number(num))) =
+                (col_data_type, *operator, &**expr)
+            {
+                analyze_integer_range(&-num, data_type)?;
+                return Ok(VmDataType::Number);
+            }
+
+            match (operator, analyze_expression(schema, col_data_type, expr)?) {
+                (UnaryOperator::Not, VmDataType::Bool) => VmDataType::Bool,
+
+                (
+                    UnaryOperator::Plus | UnaryOperator::Minus,
+                    numeric @ (VmDataType::Number | VmDataType::Float),
+                ) => numeric,
+
+                _ => Err(TypeError::ExpectedType {
+                    expected: match operator {
+                        UnaryOperator::Not => VmDataType::Bool,
+                        _ => VmDataType::Number,
+                    },
+                    found: *expr.clone(),
+                })?,
+            }
+        }
+
+        // Tuples are compared element by element, so each pair of elements
+        // must be comparable.
+        Expression::BinaryOperation {
+            left,
+            operator,
+            right,
+        } if matches!(**left, Expression::Tuple(_)) || matches!(**right, Expression::Tuple(_)) => {
+            let (
+                Expression::Tuple(left_items),
+                BinaryOperator::Eq | BinaryOperator::Neq,
+                Expression::Tuple(right_items),
+            ) = (&**left, operator, &**right)
+            else {
+                return Err(AnalyzerError::UnsupportedTuple(expr.clone()).into());
+            };
+
+            if left_items.len() != right_items.len() {
+                return Err(AnalyzerError::TupleLengthMismatch {
+                    left: left_items.len(),
+                    right: right_items.len(),
+                }
+                .into());
+            }
+
+            for (left, right) in left_items.iter().zip(right_items) {
+                let left_data_type = analyze_expression(schema, None, left)?;
+                let right_data_type = analyze_expression(schema, None, right)?;
+                let numeric =
+                    |data_type| matches!(data_type, VmDataType::Number | VmDataType::Float);
+
+                if left_data_type != right_data_type
+                    && !(numeric(left_data_type) && numeric(right_data_type))
+                {
+                    return Err(TypeError::CannotApplyBinary {
+                        left: left.clone(),
+                        operator: *operator,
+                        right: right.clone(),
+                    }
+                    .into());
+                }
+            }
+
+            VmDataType::Bool
+        }
+
+        Expression::Tuple(_) => Err(AnalyzerError::UnsupportedTuple(expr.clone()))?,
+
+        Expression::BinaryOperation {
+            left,
+            operator,
+            right,
+        } => {
+            let left_data_type = analyze_expression(schema, col_data_type, left)?;
+            let right_data_type = analyze_expression(schema, col_data_type, right)?;
+
+            // TODO: We're lazily evaluating this because we have to clone.
+            // Figure out if we can refactor this module to avoid cloning
+            // for errors.
+            let mismatched_types = || {
+                SqlError::TypeError(TypeError::CannotApplyBinary {
+                    left: *left.clone(),
+                    operator: *operator,
+                    right: *right.clone(),
+                })
+            };
+
+            // Integers and floats can be mixed, the result is a float.
+            let numeric_data_type = match (left_data_type, right_data_type) {
+                (VmDataType::Number, VmDataType::Number) => Some(VmDataType::Number),
+                (
+                    VmDataType::Number | VmDataType::Float,
+                    VmDataType::Number | VmDataType::Float,
+                ) => Some(VmDataType::Float),
+                _ => None,
+            };
+
+            if left_data_type != right_data_type && numeric_data_type.is_none() {
+                return Err(mismatched_types());
+            }
+
+            match operator {
+                BinaryOperator::Eq
+                | BinaryOperator::Neq
+                | BinaryOperator::Lt
+                | BinaryOperator::LtEq
+                | BinaryOperator::Gt
+                | BinaryOperator::GtEq => VmDataType::Bool,
+
+                BinaryOperator::And | BinaryOperator::Or if left_data_type == VmDataType::Bool => {
+                    VmDataType::Bool
+                }
+
+                BinaryOperator::Like if left_data_type == VmDataType::String => VmDataType::Bool,
+
+                BinaryOperator::Concat if left_data_type == VmDataType::String => {
+                    VmDataType::String
+                }
+
+                BinaryOperator::Plus
+                | BinaryOperator::Minus
+                | BinaryOperator::Div
+                | BinaryOperator::Mul
+                    if numeric_data_type.is_some() =>
+                {
+                    numeric_data_type.unwrap()
+                }
+
+                _ => Err(mismatched_types())?,
+            }
+        }
+
+        Expression::Nested(expr) => analyze_expression(schema, col_data_type, expr)?,
+
+        // All the elements in the list must be comparable to the expression.
+        Expression::InList { expr, list, .. } => {
+            let expected = analyze_expression(schema, col_data_type, expr)?;
+
+            for item in list {
+                let found = analyze_expression(schema, col_data_type, item)?;
+                let numeric =
+                    |data_type| matches!(data_type, VmDataType::Number | VmDataType::Float);
+
+                if found != expected && !(numeric(found) && numeric(expected)) {
+                    return Err(TypeError::ExpectedType {
+                        expected,
+                        found: item.clone(),
+                    }
+                    .into());
+                }
+            }
+
+            VmDataType::Bool
+        }
+
+        // Same as above, both bounds must be comparable to the expression.
+        Expression::Between {
+            expr, low, high, ..
+        } => {
+            let expected = analyze_expression(schema, col_data_type, expr)?;
+
+            for bound in [low, high] {
+                let found = analyze_expression(schema, col_data_type, bound)?;
+                let numeric =
+                    |data_type| matches!(data_type, VmDataType::Number | VmDataType::Float);
+
+                if found != expected && !(numeric(found) && numeric(expected)) {
+                    return Err(TypeError::ExpectedType {
+                        expected,
+                        found: *bound.clone(),
+                    }
+                    .into());
+                }
+            }
+
+            VmDataType::Bool
+        }
+
+        // There's no way to store NULL values yet, so these would always
+        // evaluate to the same result.
+        Expression::IsNull { .. } => {
+            return Err(AnalyzerError::NullPredicate(expr.clone()).into());
+        }
+
+        Expression::Function { func, args } => {
+            let (params, return_type) = function_signature(*func);
+
+            if args.len() != params.len() {
+                return Err(AnalyzerError::ArgumentCountMismatch {
+                    func: *func,
+                    expected: params.len(),
+                    found: args.len(),
+                }
+                .into());
+            }
+
+            let mut floats = false;
+
+            for (arg, expected) in args.iter().zip(params) {
+                match (*expected, analyze_expression(schema, None, arg)?) {
+                    (VmDataType::Float, VmDataType::Float) => floats = true,
+
+                    (VmDataType::Float, VmDataType::Number) => {}
+
+                    (expected, found) if expected == found => {}
+
+                    (expected, _) => Err(TypeError::ExpectedType {
+                        expected: match expected {
+                            VmDataType::Float => VmDataType::Number,
+                            other => other,
+                        },
+                        found: arg.clone(),
+                    })?,
+                }
+            }
+
+            match return_type {
+                VmDataType::Number if floats => VmDataType::Float,
+                other => other,
+            }
+        }
+
+        // Conditions must be booleans and all the results must have the same
+        // type, except for integers and floats which can be mixed. In that
+        // case the result is a float.
+        Expression::Case {
+            branches,
+            else_branch,
+        } => {
+            let Some(else_branch) = else_branch else {
+                return Err(AnalyzerError::CaseWithoutElse.into());
+            };
+
+            for (condition, _) in branches {
+                if analyze_expression(schema, None, condition)? != VmDataType::Bool {
+                    return Err(TypeError::ExpectedType {
+                        expected: VmDataType::Bool,
+                        found: condition.clone(),
+                    }
+                    .into());
+                }
+            }
+
+            let mut data_type = analyze_expression(schema, col_data_type, else_branch)?;
+
+            for (_, result) in branches {
+                data_type = match (
+                    data_type,
+                    analyze_expression(schema, col_data_type, result)?,
+                ) {
+                    (expected, found) if expected == found => expected,
+
+                    (
+                        VmDataType::Number | VmDataType::Float,
+                        VmDataType::Number | VmDataType::Float,
+                    ) => VmDataType::Float,
+
+                    (expected, _) => Err(TypeError::ExpectedType {
+                        expected,
+                        found: result.clone(),
+                    })?,
+                };
+            }
+
+            data_type
+        }
+
+        Expression::Aggregate {
+            func,
+            arg,
+            distinct,
+        } => {
+            if arg.contains_aggregate() {
+                return Err(
+                    AnalyzerError::AggregateNotAllowed("aggregate function arguments").into(),
+                );
+            }
+
+            // COUNT(*) is the only aggregate that accepts a wildcard. There's
+            // nothing to compare with COUNT(DISTINCT *) though, so that one
+            // falls through and fails like any other wildcard.
+            if *func == AggregateFunction::Count && **arg == Expression::Wildcard && !distinct {
+                return Ok(VmDataType::Number);
+            }
+
+            let arg_data_type = analyze_expression(schema, None, arg)?;
+
+            match func {
+                AggregateFunction::Count => VmDataType::Number,
+
+                AggregateFunction::Min | AggregateFunction::Max => arg_data_type,
+
+                AggregateFunction::Sum | AggregateFunction::Avg => match arg_data_type {
+                    VmDataType::Number | VmDataType::Float if *func == AggregateFunction::Avg => {
+                        VmDataType::Float
+                    }
+
+                    numeric @ (VmDataType::Number | VmDataType::Float) => numeric,
+
+                    _ => Err(TypeError::ExpectedType {
+                        expected: VmDataType::Number,
+                        found: *arg.clone(),
+                    })?,
+                },
+            }
+        }
+
+        Expression::Wildcard => {
+            return Err(SqlError::Other("unexpected wildcard expression (*)".into()))
+        }
+    })
+}
+
+/// Returns the parameter types and the return type of a scalar function.
+///
+/// [`VmDataType::Float`] parameters accept any number. Numeric functions are
+/// declared as returning [`VmDataType::Number`], which becomes
+/// [`VmDataType::Float`] when any of their arguments is a float, just like
+/// arithmetic operators.
+fn function_signature(func: ScalarFunction) -> (&'static [VmDataType], VmDataType) {
+    match func {
+        ScalarFunction::Upper | ScalarFunction::Lower | ScalarFunction::Trim => {
+            (&[VmDataType::String], VmDataType::String)
+        }
+
+        ScalarFunction::Length => (&[VmDataType::String], VmDataType::Number),
+
+        ScalarFunction::Substr => (
+            &[VmDataType::String, VmDataType::Number, VmDataType::Number],
+            VmDataType::String,
+        ),
+
+        ScalarFunction::Abs => (&[VmDataType::Float], VmDataType::Number),
+
+        ScalarFunction::Mod | ScalarFunction::Power => {
+            (&[VmDataType::Float, VmDataType::Float], VmDataType::Number)
+        }
+    }
+}
+
+/// Returns an error if the aggregate query has column references outside of
+/// aggregate functions that are not part of the `GROUP BY` clause.
+///
+/// Grouping expressions can be used as a whole or through the columns that
+/// they reference directly, so `GROUP BY age / 10` allows `age / 10` but not
+/// `age`.
+fn analyze_ungrouped_columns(
+    expr: &Expression,
+    group_by: &[Expression],
+) -> Result<(), AnalyzerError> {
+    if group_by.contains(expr) {
+        return Ok(());
+    }
+
+    match expr {
+        Expression::Identifier(ident) => Err(AnalyzerError::UngroupedColumn(ident.clone())),
+
+        Expression::QualifiedIdentifier { .. } | Expression::Wildcard => {
+            Err(AnalyzerError::UngroupedColumn(expr.to_string()))
+        }
+
+        Expression::BinaryOperation { left, right, .. } => {
+            analyze_ungrouped_columns(left, group_by)?;
+            analyze_ungrouped_columns(right, group_by)
+        }
+
+        Expression::UnaryOperation { expr, .. }
+        | Expression::Nested(expr)
+        | Expression::IsNull { expr, .. } => analyze_ungrouped_columns(expr, group_by),
+
+        Expression::InList { expr, list, .. } => {
+            analyze_ungrouped_columns(expr, group_by)?;
+            list.iter()
+                .try_for_each(|item| analyze_ungrouped_columns(item, group_by))
+        }
+
+        Expression::Between {
+            expr, low, high, ..
+        } => {
+            analyze_ungrouped_columns(expr, group_by)?;
+            analyze_ungrouped_columns(low, group_by)?;
+            analyze_ungrouped_columns(high, group_by)
+        }
+
+        Expression::Function { args, .. } | Expression::Tuple(args) => args
+            .iter()
+            .try_for_each(|arg| analyze_ungrouped_columns(arg, group_by)),
+
+        Expression::Case {
+            branches,
+            else_branch,
+        } => {
+            for (condition, result) in branches {
+                analyze_ungrouped_columns(condition, group_by)?;
+                analyze_ungrouped_columns(result, group_by)?;
+            }
+
+            else_branch
+                .iter()
+                .try_for_each(|else_branch| analyze_ungrouped_columns(else_branch, group_by))
+        }
+
+        Expression::Aggregate { .. } | Expression::Value(_) => Ok(()),
+    }
+}
+
+/// Returns an error if the integer is out of range for the given data type.
+fn analyze_integer_range(integer: &i128, data_type: &DataType) -> Result<(), AnalyzerError> {
+    if let DataType::BigInt | DataType::Int | DataType::UnsignedBigInt | DataType::UnsignedInt =
+        data_type
+    {
+        if !tuple::integer_is_within_range(integer, data_type) {
+            return Err(AnalyzerError::IntegerOutOfRange(*integer, *data_type));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AlreadyExists, AnalyzerError, Clause};
+    use crate::{
+        db::{Context, DbError, SqlError},
+        sql::{
+            analyzer::analyze,
+            parser::Parser,
+            statement::{
+                AggregateFunction, BinaryOperator, DataType, Expression, ScalarFunction, Statement,
+                Value,
+            },
+        },
+        storage::tuple,
+        vm::{TypeError, VmDataType},
+    };
+
+    struct Analyze<'s> {
+        sql: &'s str,
+        ctx: &'s [&'s str],
+        expected: Result<(), DbError>,
+    }
+
+    fn assert_analyze(Analyze { sql, ctx, expected }: Analyze) -> Result<(), DbError> {
+        let statement = Parser::new(sql).parse_statement()?;
+        let mut ctx = Context::try_from(ctx)?;
+
+        assert_eq!(analyze(&statement, &mut ctx), expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rollback_to_unknown_savepoint() -> Result<(), DbError> {
+        assert_analyze(Analyze {
+            ctx: &[],
+            sql: "ROLLBACK TO SAVEPOINT before_insert;",
+            expected: Err(AnalyzerError::UnknownSavepoint("before_insert".into()).into()),
+        })
+    }
+
+    #[test]
+    fn select_from_invalid_table() -> Result<(), DbError> {
+        assert_analyze(Analyze {
+            ctx: &[],
+            sql: "SELECT * FROM users;",
+            expected: Err(SqlError::InvalidTable("users".into()).into()),
+        })
+    }
+
+    #[test]
+    fn select_without_from() -> Result<(), DbError> {
+        assert_analyze(Analyze {
+            ctx: &[],
+            sql: "SELECT 1 + 1, UPPER('hi') WHERE 2 > 1;",
+            expected: Ok(()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx: &[],
+            sql: "SELECT id + 1;",
+            expected: Err(AnalyzerError::UnknownColumn {
+                column: "id".into(),
+                clause: Clause::Select,
+            }
+            .into()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx: &[],
+            sql: "SELECT *;",
+            expected: Err(AnalyzerError::WildcardWithoutFrom.into()),
+        })
+    }
+
+    #[test]
+    fn insert_into_invalid_table() -> Result<(), DbError> {
+        assert_analyze(Analyze {
+            ctx: &["CREATE TABLE users (id INT PRIMARY KEY);"],
+            sql: "INSERT INTO tasks (id, title) VALUES (1, 'Test');",
+            expected: Err(SqlError::InvalidTable("tasks".into()).into()),
+        })
+    }
+
+    #[test]
+    fn multiple_primary_keys() -> Result<(), DbError> {
+        assert_analyze(Analyze {
+            ctx: &[],
+            sql: "CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) PRIMARY KEY);",
+            expected: Err(AnalyzerError::MultiplePrimaryKeys.into()),
+        })
+    }
+
+    #[test]
+    fn insert_count_mismatch() -> Result<(), DbError> {
+        assert_analyze(Analyze {
+            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"],
+            sql: "INSERT INTO users (id, name, email) VALUES (1, 'John Doe');",
+            expected: Err(AnalyzerError::ColumnValueCountMismatch.into()),
+        })
+    }
+
+    #[test]
+    fn insert_multiple_rows() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"];
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "INSERT INTO users (id, name) VALUES (1, 'John'), (2, 'Jane');",
+            expected: Ok(()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "INSERT INTO users (id, name) VALUES (1, 'John'), (2);",
+            expected: Err(AnalyzerError::ColumnValueCountMismatch.into()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "INSERT INTO users VALUES (1, 'John'), ('Jane', 2);",
+            expected: Err(DbError::from(TypeError::ExpectedType {
+                expected: VmDataType::Number,
+                found: Expression::Value(Value::String("Jane".into())),
+            })),
+        })
+    }
+
+    #[test]
+    fn insert_select() -> Result<(), DbError> {
+        let ctx = &[
+            "CREATE TABLE orders (id INT PRIMARY KEY, total DOUBLE, done BOOL);",
+            "CREATE TABLE archive (id INT PRIMARY KEY, total DOUBLE);",
+        ];
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "INSERT INTO archive SELECT id, total FROM orders WHERE done = TRUE;",
+            expected: Ok(()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "INSERT INTO archive (total, id) SELECT id, id FROM orders;",
+            expected: Ok(()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "INSERT INTO archive SELECT * FROM orders;",
+            expected: Err(AnalyzerError::ColumnValueCountMismatch.into()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "INSERT INTO archive (id, total) SELECT total, id FROM orders;",
+            expected: Err(DbError::from(TypeError::ExpectedType {
+                expected: VmDataType::Number,
+                found: Expression::Identifier("total".into()),
+            })),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "INSERT INTO archive SELECT id, total FROM orders WHERE unknown = 1;",
+            expected: Err(AnalyzerError::UnknownColumn {
+                column: "unknown".into(),
+                clause: Clause::Where,
+            }
+            .into()),
+        })
+    }
+
+    #[test]
+    fn insert_missing_columns() -> Result<(), DbError> {
+        assert_analyze(Analyze {
+            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"],
+            sql: "INSERT INTO users (id, name) VALUES (1, 'John Doe');",
+            expected: Err(AnalyzerError::MissingColumns.into()),
+        })
+    }
+    #[test]
+    fn insert_duplicate_columns() -> Result<(), DbError> {
+        assert_analyze(Analyze {
+            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"],
+            sql: "INSERT INTO users (id, name, name) VALUES (1, 'John Doe', 'John Doe');",
+            expected: Err(AnalyzerError::DuplicatedColumn("name".into()).into()),
+        })
+    }
+
+    #[test]
+    fn insert_wrong_data_types() -> Result<(), DbError> {
+        assert_analyze(Analyze {
+            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"],
+            sql: "INSERT INTO users (id, name, email) VALUES ('string', 5, 6);",
+            expected: Err(DbError::from(TypeError::ExpectedType {
+                expected: VmDataType::Number,
+                found: Expression::Value(Value::String("string".into()))
+            })),
+        })
+    }
+
+    #[test]
+    fn insert_wrong_data_types_without_specifying_columns() -> Result<(), DbError> {
+        assert_analyze(Analyze {
+            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"],
+            sql: "INSERT INTO users VALUES ('string', 5, 6);",
+            expected: Err(DbError::from(TypeError::ExpectedType {
+                expected: VmDataType::Number,
+                found: Expression::Value(Value::String("string".into()))
+            })),
+        })
+    }
+
+    #[test]
+    fn select_where_invalid_expression() -> Result<(), DbError> {
+        assert_analyze(Analyze {
+            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"],
+            sql: "SELECT * FROM users WHERE id = 'string';",
+            expected: Err(DbError::from(TypeError::CannotApplyBinary {
+                left: Expression::Identifier("id".into()),
+                operator: BinaryOperator::Eq,
+                right: Expression::Value(Value::String("string".into()))
+            })),
+        })
+    }
+
+    #[test]
+    fn select_where_doesnt_eval_to_bool() -> Result<(), DbError> {
+        assert_analyze(Analyze {
+            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"],
+            sql: "SELECT * FROM users WHERE 2 + 2;",
+            expected: Err(DbError::from(TypeError::ExpectedType {
+                expected: VmDataType::Bool,
+                found: Expression::BinaryOperation {
+                    left: Box::new(Expression::Value(Value::Number(2))),
+                    operator: BinaryOperator::Plus,
+                    right: Box::new(Expression::Value(Value::Number(2))),
+                }
+             })),
+        })
+    }
+
+    #[test]
+    fn like_requires_strings() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"];
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT * FROM users WHERE name LIKE 'Jo_n%';",
+            expected: Ok(()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT * FROM users WHERE id LIKE 1;",
+            expected: Err(DbError::from(TypeError::CannotApplyBinary {
+                left: Expression::Identifier("id".into()),
+                operator: BinaryOperator::Like,
+                right: Expression::Value(Value::Number(1)),
+            })),
+        })
+    }
+
+    #[test]
+    fn concat_requires_strings() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"];
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT name || '!' FROM users WHERE name || name = 'JohnJohn';",
+            expected: Ok(()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT name || id FROM users;",
+            expected: Err(DbError::from(TypeError::CannotApplyBinary {
+                left: Expression::Identifier("name".into()),
+                operator: BinaryOperator::Concat,
+                right: Expression::Identifier("id".into()),
+            })),
+        })
+    }
+
+    #[test]
+    fn in_list_elements_must_match_type() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"];
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT * FROM users WHERE id IN (1, 2 + 3, 4.5) AND name NOT IN ();",
+            expected: Ok(()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT * FROM users WHERE id IN (1, 'two');",
+            expected: Err(DbError::from(TypeError::ExpectedType {
+                expected: VmDataType::Number,
+                found: Expression::Value(Value::String("two".into())),
+            })),
+        })
+    }
+
+    #[test]
+    fn between_operands_must_match_type() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"];
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT * FROM users WHERE id BETWEEN 1 AND 2.5 OR name NOT BETWEEN 'a' AND 'm';",
+            expected: Ok(()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT * FROM users WHERE id BETWEEN 1 AND 'z';",
+            expected: Err(DbError::from(TypeError::ExpectedType {
+                expected: VmDataType::Number,
+                found: Expression::Value(Value::String("z".into())),
+            })),
+        })
+    }
+
+    #[test]
+    fn is_null_not_supported() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"];
+
+        let is_null = |column: &str, negated| {
+            Err(AnalyzerError::NullPredicate(Expression::IsNull {
+                expr: Box::new(Expression::Identifier(column.into())),
+                negated,
+            })
+            .into())
+        };
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT id IS NULL FROM users;",
+            expected: is_null("id", false),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT * FROM users WHERE id > 1 AND name IS NOT NULL;",
+            expected: is_null("name", true),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "UPDATE users SET name = 'x' WHERE name IS NULL;",
+            expected: is_null("name", false),
+        })
+    }
+
+    #[test]
+    fn function_arguments() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"];
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT UPPER(name), LENGTH(TRIM(name)) + 1, SUBSTR(name, id, 2) FROM users;",
+            expected: Ok(()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT LOWER(id) FROM users;",
+            expected: Err(DbError::from(TypeError::ExpectedType {
+                expected: VmDataType::String,
+                found: Expression::Identifier("id".into()),
+            })),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT * FROM users WHERE UPPER(name) = 1;",
+            expected: Err(DbError::from(TypeError::CannotApplyBinary {
+                left: Expression::Function {
+                    func: ScalarFunction::Upper,
+                    args: vec![Expression::Identifier("name".into())],
+                },
+                operator: BinaryOperator::Eq,
+                right: Expression::Value(Value::Number(1)),
+            })),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT SUBSTR(name, 1) FROM users;",
+            expected: Err(AnalyzerError::ArgumentCountMismatch {
+                func: ScalarFunction::Substr,
+                expected: 3,
+                found: 2,
+            }
+            .into()),
+        })
+    }
+
+    #[test]
+    fn numeric_function_arguments() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), score DOUBLE);"];
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT ABS(id), MOD(id, 2), POWER(score, id) FROM users WHERE ABS(score) > 1;",
+            expected: Ok(()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT MOD(name, 2) FROM users;",
+            expected: Err(DbError::from(TypeError::ExpectedType {
+                expected: VmDataType::Number,
+                found: Expression::Identifier("name".into()),
+            })),
+        })?;
+
+        // POWER() of a DOUBLE is a float, so it can't be concatenated.
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT name || POWER(score, 2) FROM users;",
+            expected: Err(DbError::from(TypeError::CannotApplyBinary {
+                left: Expression::Identifier("name".into()),
+                operator: BinaryOperator::Concat,
+                right: Expression::Function {
+                    func: ScalarFunction::Power,
+                    args: vec![
+                        Expression::Identifier("score".into()),
+                        Expression::Value(Value::Number(2)),
+                    ],
+                },
+            })),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT ABS(id, 1) FROM users;",
+            expected: Err(AnalyzerError::ArgumentCountMismatch {
+                func: ScalarFunction::Abs,
+                expected: 1,
+                found: 2,
+            }
+            .into()),
+        })
+    }
+
+    #[test]
+    fn case_branches_must_match_type() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"];
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT CASE WHEN id > 1 THEN 1 WHEN name = 'a' THEN 2.5 ELSE id END FROM users;",
+            expected: Ok(()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT CASE WHEN id THEN 'a' ELSE 'b' END FROM users;",
+            expected: Err(DbError::from(TypeError::ExpectedType {
+                expected: VmDataType::Bool,
+                found: Expression::Identifier("id".into()),
+            })),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT CASE WHEN id > 1 THEN 'a' ELSE 2 END FROM users;",
+            expected: Err(DbError::from(TypeError::ExpectedType {
+                expected: VmDataType::Number,
+                found: Expression::Value(Value::String("a".into())),
+            })),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT CASE WHEN id > 1 THEN name END FROM users;",
+            expected: Err(AnalyzerError::CaseWithoutElse.into()),
+        })
+    }
+
+    #[test]
+    fn tuple_comparisons() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"];
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT * FROM users WHERE (id, name) = (1.5, 'John') OR (id, name) != (2, 'a');",
+            expected: Ok(()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT * FROM users WHERE (id, name) = (1, 'John', 2);",
+            expected: Err(AnalyzerError::TupleLengthMismatch { left: 2, right: 3 }.into()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT * FROM users WHERE (id, name) = ('John', 1);",
+            expected: Err(TypeError::CannotApplyBinary {
+                left: Expression::Identifier("id".into()),
+                operator: BinaryOperator::Eq,
+                right: Expression::Value(Value::String("John".into())),
+            }
+            .into()),
+        })?;
+
+        let unsupported = |sql: &str| {
+            let Statement::Select { r#where, .. } = Parser::new(sql).parse_statement().unwrap()
+            else {
+                unreachable!();
+            };
+
+            Err(AnalyzerError::UnsupportedTuple(r#where.unwrap()).into())
+        };
+
+        for sql in [
+            "SELECT * FROM users WHERE (id, name) < (1, 'John');",
+            "SELECT * FROM users WHERE (id, name) = 1;",
+        ] {
+            assert_analyze(Analyze {
+                ctx,
+                sql,
+                expected: unsupported(sql),
+            })?;
+        }
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "SELECT (id, name) FROM users;",
+            expected: Err(AnalyzerError::UnsupportedTuple(Expression::Tuple(vec![
+                Expression::Identifier("id".into()),
+                Expression::Identifier("name".into()),
+            ]))
+            .into()),
+        })
+    }
+
+    #[test]
+    fn alter_table_drop_column() -> Result<(), DbError> {
+        let ctx = &[
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+            "CREATE TABLE tags (name VARCHAR(255));",
+        ];
+
+        let cannot_drop = |column: &str, reason: &str| {
+            Err(AnalyzerError::CannotDropColumn {
+                column: column.into(),
+                reason: reason.into(),
+            }
+            .into())
+        };
+
+        for (sql, expected) in [
+            ("ALTER TABLE users DROP COLUMN name;", Ok(())),
+            (
+                "ALTER TABLE users DROP COLUMN id;",
+                cannot_drop("id", "it is the primary key"),
+            ),
+            (
+                "ALTER TABLE users DROP COLUMN email;",
+                cannot_drop("email", "index users_email_uq_index depends on it"),
+            ),
+            (
+                "ALTER TABLE tags DROP COLUMN name;",
+                cannot_drop("name", "it is the only column of table tags"),
+            ),
+            (
+                "ALTER TABLE users DROP COLUMN row_id;",
+                Err(AnalyzerError::RowIdAssignment.into()),
+            ),
+            (
+                "ALTER TABLE users DROP COLUMN age;",
+                Err(SqlError::InvalidColumn("age".into()).into()),
+            ),
+            (
+                "ALTER TABLE mkdb_meta DROP COLUMN sql;",
+                Err(AnalyzerError::MkdbMetaModification.into()),
+            ),
+        ] {
+            assert_analyze(Analyze { ctx, sql, expected })?;
+        }
+
+        Ok(())
     }
 
-    fn assert_analyze(Analyze { sql, ctx, expected }: Analyze) -> Result<(), DbError> {
-        let statement = Parser::new(sql).parse_statement()?;
-        let mut ctx = Context::try_from(ctx)?;
+    #[test]
+    fn drop_index() -> Result<(), DbError> {
+        let ctx = &[
+            "CREATE TABLE users (id INT, name VARCHAR(255), email VARCHAR(255) UNIQUE);",
+            "CREATE TABLE products (code VARCHAR(10) PRIMARY KEY, name VARCHAR(255));",
+            "CREATE UNIQUE INDEX name_idx ON users(name);",
+        ];
+
+        let cannot_drop = |index: &str, reason: &str| {
+            Err(AnalyzerError::CannotDropIndex {
+                index: index.into(),
+                reason: reason.into(),
+            }
+            .into())
+        };
 
-        assert_eq!(analyze(&statement, &mut ctx), expected);
+        for (sql, expected) in [
+            ("DROP INDEX name_idx;", Ok(())),
+            ("DROP INDEX name_idx ON users;", Ok(())),
+            (
+                "DROP INDEX name_idx ON products;",
+                Err(AnalyzerError::UnknownIndex("name_idx".into()).into()),
+            ),
+            (
+                "DROP INDEX age_idx;",
+                Err(AnalyzerError::UnknownIndex("age_idx".into()).into()),
+            ),
+            (
+                "DROP INDEX users_email_uq_index;",
+                cannot_drop(
+                    "users_email_uq_index",
+                    "it enforces the unique constraint of column email",
+                ),
+            ),
+            (
+                "DROP INDEX products_pk_index ON products;",
+                cannot_drop(
+                    "products_pk_index",
+                    "it enforces the primary key of table products",
+                ),
+            ),
+        ] {
+            assert_analyze(Analyze { ctx, sql, expected })?;
+        }
 
         Ok(())
     }
 
     #[test]
-    fn select_from_invalid_table() -> Result<(), DbError> {
-        assert_analyze(Analyze {
-            ctx: &[],
-            sql: "SELECT * FROM users;",
-            expected: Err(SqlError::InvalidTable("users".into()).into()),
-        })
+    fn analyze_table() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"];
+
+        for (sql, expected) in [
+            ("ANALYZE users;", Ok(())),
+            (
+                "ANALYZE products;",
+                Err(SqlError::InvalidTable("products".into()).into()),
+            ),
+            (
+                "ANALYZE mkdb_meta;",
+                Err(AnalyzerError::MkdbMetaModification.into()),
+            ),
+        ] {
+            assert_analyze(Analyze { ctx, sql, expected })?;
+        }
+
+        Ok(())
     }
 
     #[test]
-    fn insert_into_invalid_table() -> Result<(), DbError> {
-        assert_analyze(Analyze {
-            ctx: &["CREATE TABLE users (id INT PRIMARY KEY);"],
-            sql: "INSERT INTO tasks (id, title) VALUES (1, 'Test');",
-            expected: Err(SqlError::InvalidTable("tasks".into()).into()),
-        })
+    fn if_exists_modifiers() -> Result<(), DbError> {
+        let ctx = &[
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));",
+            "CREATE UNIQUE INDEX name_idx ON users(name);",
+        ];
+
+        for (sql, expected) in [
+            ("CREATE TABLE IF NOT EXISTS users (id INT);", Ok(())),
+            ("DROP TABLE IF EXISTS products;", Ok(())),
+            ("DROP INDEX IF EXISTS age_idx;", Ok(())),
+            ("DROP INDEX IF EXISTS name_idx ON products;", Ok(())),
+            (
+                "CREATE TABLE users (id INT);",
+                Err(AnalyzerError::AlreadyExists(AlreadyExists::Table("users".into())).into()),
+            ),
+            (
+                "DROP TABLE products;",
+                Err(SqlError::InvalidTable("products".into()).into()),
+            ),
+            (
+                "DROP INDEX age_idx;",
+                Err(AnalyzerError::UnknownIndex("age_idx".into()).into()),
+            ),
+        ] {
+            assert_analyze(Analyze { ctx, sql, expected })?;
+        }
+
+        Ok(())
     }
 
     #[test]
-    fn multiple_primary_keys() -> Result<(), DbError> {
-        assert_analyze(Analyze {
-            ctx: &[],
-            sql: "CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) PRIMARY KEY);",
-            expected: Err(AnalyzerError::MultiplePrimaryKeys.into()),
-        })
+    fn alter_table_rename() -> Result<(), DbError> {
+        let ctx = &[
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255));",
+            "CREATE TABLE tags (name VARCHAR(255));",
+        ];
+
+        for (sql, expected) in [
+            ("ALTER TABLE users RENAME COLUMN email TO mail;", Ok(())),
+            ("ALTER TABLE users RENAME TO customers;", Ok(())),
+            (
+                "ALTER TABLE users RENAME COLUMN email TO name;",
+                Err(AnalyzerError::AlreadyExists(AlreadyExists::Column("name".into())).into()),
+            ),
+            (
+                "ALTER TABLE users RENAME COLUMN age TO years;",
+                Err(SqlError::InvalidColumn("age".into()).into()),
+            ),
+            (
+                "ALTER TABLE users RENAME COLUMN name TO row_id;",
+                Err(AnalyzerError::RowIdAssignment.into()),
+            ),
+            (
+                "ALTER TABLE users RENAME TO tags;",
+                Err(AnalyzerError::AlreadyExists(AlreadyExists::Table("tags".into())).into()),
+            ),
+            (
+                "ALTER TABLE users RENAME TO mkdb_meta;",
+                Err(AnalyzerError::MkdbMetaModification.into()),
+            ),
+            (
+                "ALTER TABLE mkdb_meta RENAME COLUMN sql TO definition;",
+                Err(AnalyzerError::MkdbMetaModification.into()),
+            ),
+        ] {
+            assert_analyze(Analyze { ctx, sql, expected })?;
+        }
+
+        Ok(())
     }
 
     #[test]
-    fn insert_count_mismatch() -> Result<(), DbError> {
-        assert_analyze(Analyze {
-            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"],
-            sql: "INSERT INTO users (id, name, email) VALUES (1, 'John Doe');",
-            expected: Err(AnalyzerError::ColumnValueCountMismatch.into()),
-        })
+    fn create_multi_column_index() -> Result<(), DbError> {
+        let ctx =
+            &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255));"];
+
+        for (sql, expected) in [
+            (
+                "CREATE UNIQUE INDEX name_email ON users(name, email);",
+                Ok(()),
+            ),
+            (
+                "CREATE UNIQUE INDEX name_email ON users(name, age);",
+                Err(SqlError::InvalidColumn("age".into()).into()),
+            ),
+            (
+                "CREATE UNIQUE INDEX name_email ON users(name, email, name);",
+                Err(AnalyzerError::DuplicatedColumn("name".into()).into()),
+            ),
+        ] {
+            assert_analyze(Analyze { ctx, sql, expected })?;
+        }
+
+        Ok(())
     }
 
     #[test]
-    fn insert_missing_columns() -> Result<(), DbError> {
+    fn insert_default_values_without_defaults() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT, name VARCHAR(255));"];
+
         assert_analyze(Analyze {
-            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"],
-            sql: "INSERT INTO users (id, name) VALUES (1, 'John Doe');",
+            ctx,
+            sql: "INSERT INTO users () VALUES ();",
             expected: Err(AnalyzerError::MissingColumns.into()),
-        })
-    }
-    #[test]
-    fn insert_duplicate_columns() -> Result<(), DbError> {
+        })?;
+
         assert_analyze(Analyze {
-            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"],
-            sql: "INSERT INTO users (id, name, name) VALUES (1, 'John Doe', 'John Doe');",
-            expected: Err(AnalyzerError::DuplicatedColumn("name".into()).into()),
+            ctx,
+            sql: "INSERT INTO users (id) VALUES ();",
+            expected: Err(AnalyzerError::ColumnValueCountMismatch.into()),
         })
     }
 
     #[test]
-    fn insert_wrong_data_types() -> Result<(), DbError> {
+    fn not_requires_bool() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, is_admin BOOL);"];
+
         assert_analyze(Analyze {
-            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"],
-            sql: "INSERT INTO users (id, name, email) VALUES ('string', 5, 6);",
-            expected: Err(DbError::from(TypeError::ExpectedType {
-                expected: VmDataType::Number,
-                found: Expression::Value(Value::String("string".into()))
-            })),
-        })
-    }
+            ctx,
+            sql: "SELECT * FROM users WHERE NOT is_admin AND NOT id > 5;",
+            expected: Ok(()),
+        })?;
 
-    #[test]
-    fn insert_wrong_data_types_without_specifying_columns() -> Result<(), DbError> {
         assert_analyze(Analyze {
-            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"],
-            sql: "INSERT INTO users VALUES ('string', 5, 6);",
+            ctx,
+            sql: "SELECT * FROM users WHERE NOT id;",
             expected: Err(DbError::from(TypeError::ExpectedType {
-                expected: VmDataType::Number,
-                found: Expression::Value(Value::String("string".into()))
+                expected: VmDataType::Bool,
+                found: Expression::Identifier("id".into()),
             })),
         })
     }
 
     #[test]
-    fn select_where_invalid_expression() -> Result<(), DbError> {
+    fn insert_omitting_not_null_columns() -> Result<(), DbError> {
+        let ctx =
+            &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255) NOT NULL, age INT);"];
+
         assert_analyze(Analyze {
-            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"],
-            sql: "SELECT * FROM users WHERE id = 'string';",
-            expected: Err(DbError::from(TypeError::CannotApplyBinary {
-                left: Expression::Identifier("id".into()),
-                operator: BinaryOperator::Eq,
-                right: Expression::Value(Value::String("string".into()))
-            })),
+            ctx,
+            sql: "INSERT INTO users (id, age) VALUES (1, 20);",
+            expected: Err(AnalyzerError::NullConstraintViolation("name".into()).into()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "INSERT INTO users (name, age) VALUES ('John', 20);",
+            expected: Err(AnalyzerError::NullConstraintViolation("id".into()).into()),
         })
     }
 
     #[test]
-    fn select_where_doesnt_eval_to_bool() -> Result<(), DbError> {
+    fn default_values() -> Result<(), DbError> {
         assert_analyze(Analyze {
-            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), email VARCHAR(255) UNIQUE);"],
-            sql: "SELECT * FROM users WHERE 2 + 2;",
+            ctx: &[],
+            sql: "CREATE TABLE users (id INT PRIMARY KEY, active BOOL DEFAULT 5);",
             expected: Err(DbError::from(TypeError::ExpectedType {
                 expected: VmDataType::Bool,
-                found: Expression::BinaryOperation {
-                    left: Box::new(Expression::Value(Value::Number(2))),
-                    operator: BinaryOperator::Plus,
-                    right: Box::new(Expression::Value(Value::Number(2))),
-                }
-             })),
+                found: Expression::Value(Value::Number(5)),
+            })),
+        })?;
+
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255) NOT NULL DEFAULT 'Anonymous', active BOOL DEFAULT TRUE);"];
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "INSERT INTO users (id) VALUES (1);",
+            expected: Ok(()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "INSERT INTO users () VALUES ();",
+            expected: Err(AnalyzerError::NullConstraintViolation("id".into()).into()),
         })
     }
 
@@ -610,6 +2484,35 @@ mod tests {
         })
     }
 
+    #[test]
+    fn update_self_referential_assignments() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, first VARCHAR(255), full VARCHAR(255), counter INT);"];
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "UPDATE users SET counter = counter + 1, full = first || ' ' || full;",
+            expected: Ok(()),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "UPDATE users SET full = counter;",
+            expected: Err(DbError::from(TypeError::ExpectedType {
+                expected: VmDataType::String,
+                found: Expression::Identifier("counter".into()),
+            })),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx,
+            sql: "UPDATE users SET counter = first;",
+            expected: Err(DbError::from(TypeError::ExpectedType {
+                expected: VmDataType::Number,
+                found: Expression::Identifier("first".into()),
+            })),
+        })
+    }
+
     #[test]
     fn table_already_exists() -> Result<(), DbError> {
         assert_analyze(Analyze {
@@ -644,8 +2547,27 @@ mod tests {
             sql: "INSERT INTO users (id, name) VALUES (1, '123456789');",
             expected: Err(DbError::from(AnalyzerError::ValueTooLong(
                 "123456789".into(),
-                8,
+                DataType::Varchar(8),
+            ))),
+        })
+    }
+
+    #[test]
+    fn varchar_length_too_large() -> Result<(), DbError> {
+        let length = tuple::MAX_VARCHAR_LENGTH + 1;
+
+        assert_analyze(Analyze {
+            ctx: &[],
+            sql: &format!("CREATE TABLE users (id INT, name VARCHAR({length}));"),
+            expected: Err(DbError::from(AnalyzerError::LengthTooLarge(
+                DataType::Varchar(length),
             ))),
+        })?;
+
+        assert_analyze(Analyze {
+            ctx: &[],
+            sql: &format!("CREATE TABLE users (id INT, name VARCHAR({}));", length - 1),
+            expected: Ok(()),
         })
     }
 
@@ -662,4 +2584,326 @@ mod tests {
             ))),
         })
     }
+
+    #[test]
+    fn select_aggregates() -> Result<(), DbError> {
+        assert_analyze(Analyze {
+            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);"],
+            sql: "SELECT COUNT(*), SUM(age), AVG(age) + 1, MIN(name), MAX(id) FROM users;",
+            expected: Ok(()),
+        })
+    }
+
+    #[test]
+    fn sum_of_non_numeric_column() -> Result<(), DbError> {
+        assert_analyze(Analyze {
+            ctx: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"],
+            sql: "SELECT SUM(name) FROM users;",
+            expected: Err(TypeError::ExpectedType {
+                expected: VmDataType::Number,
+                found: Expression::Identifier("name".into()),
+            }
+            .into()),
+        })
+    }
+
+    #[test]
+    fn ungrouped_column_in_aggregate_query() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);"];
+
+        for (sql, expected) in [
+            (
+                "SELECT name, COUNT(*) FROM users;",
+                Err(AnalyzerError::UngroupedColumn("name".into()).into()),
+            ),
+            (
+                "SELECT MAX(age), age + 1 FROM users;",
+                Err(AnalyzerError::UngroupedColumn("age".into()).into()),
+            ),
+            ("SELECT COUNT(*) FROM users;", Ok(())),
+            ("SELECT name, COUNT(*) FROM users GROUP BY name;", Ok(())),
+            ("SELECT name, age FROM users;", Ok(())),
+        ] {
+            assert_analyze(Analyze { ctx, sql, expected })?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_distinct_order_by() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE emp (id INT PRIMARY KEY, dept VARCHAR(255), age INT);"];
+
+        for (sql, expected) in [
+            ("SELECT DISTINCT dept, age FROM emp ORDER BY age;", Ok(())),
+            ("SELECT DISTINCT age + 1 FROM emp ORDER BY age + 1;", Ok(())),
+            ("SELECT DISTINCT * FROM emp ORDER BY dept;", Ok(())),
+            (
+                "SELECT DISTINCT dept FROM emp ORDER BY age;",
+                Err(AnalyzerError::OrderByNotInDistinct("age".into()).into()),
+            ),
+            (
+                "SELECT DISTINCT * FROM emp ORDER BY age + 1;",
+                Err(AnalyzerError::OrderByNotInDistinct("age + 1".into()).into()),
+            ),
+        ] {
+            assert_analyze(Analyze { ctx, sql, expected })?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn group_by() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE emp (id INT PRIMARY KEY, dept VARCHAR(255), age INT);"];
+
+        for (sql, expected) in [
+            (
+                "SELECT dept, COUNT(*), MAX(age) FROM emp GROUP BY dept ORDER BY dept;",
+                Ok(()),
+            ),
+            (
+                "SELECT age / 10, COUNT(*) FROM emp GROUP BY age / 10;",
+                Ok(()),
+            ),
+            (
+                "SELECT dept, COUNT(DISTINCT age), SUM(DISTINCT age) FROM emp GROUP BY dept;",
+                Ok(()),
+            ),
+            (
+                "SELECT COUNT(DISTINCT *) FROM emp;",
+                Err(SqlError::Other("unexpected wildcard expression (*)".into()).into()),
+            ),
+            (
+                "SELECT dept, age FROM emp GROUP BY dept;",
+                Err(AnalyzerError::UngroupedColumn("age".into()).into()),
+            ),
+            (
+                "SELECT age FROM emp GROUP BY age / 10;",
+                Err(AnalyzerError::UngroupedColumn("age".into()).into()),
+            ),
+            (
+                "SELECT dept FROM emp GROUP BY dept ORDER BY id;",
+                Err(AnalyzerError::UngroupedColumn("id".into()).into()),
+            ),
+            (
+                "SELECT dept FROM emp GROUP BY dept ORDER BY COUNT(*), MAX(age) + 1;",
+                Ok(()),
+            ),
+            (
+                "SELECT dept FROM emp GROUP BY dept ORDER BY MAX(id) + age;",
+                Err(AnalyzerError::UngroupedColumn("age".into()).into()),
+            ),
+            (
+                "SELECT COUNT(*) FROM emp GROUP BY COUNT(*);",
+                Err(AnalyzerError::AggregateNotAllowed("GROUP BY").into()),
+            ),
+            (
+                "SELECT COUNT(*) FROM emp GROUP BY nonexistent;",
+                Err(AnalyzerError::UnknownColumn {
+                    column: "nonexistent".into(),
+                    clause: Clause::GroupBy,
+                }
+                .into()),
+            ),
+        ] {
+            assert_analyze(Analyze { ctx, sql, expected })?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn having() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE emp (id INT PRIMARY KEY, dept VARCHAR(255), age INT);"];
+
+        for (sql, expected) in [
+            (
+                "SELECT dept FROM emp GROUP BY dept HAVING COUNT(*) > 3 AND dept != 'IT';",
+                Ok(()),
+            ),
+            ("SELECT COUNT(*) FROM emp HAVING MAX(age) > 30;", Ok(())),
+            (
+                "SELECT dept FROM emp GROUP BY dept HAVING age > 30;",
+                Err(AnalyzerError::UngroupedColumn("age".into()).into()),
+            ),
+            (
+                "SELECT dept FROM emp GROUP BY dept HAVING COUNT(*);",
+                Err(TypeError::ExpectedType {
+                    expected: VmDataType::Bool,
+                    found: Expression::Aggregate {
+                        func: AggregateFunction::Count,
+                        arg: Box::new(Expression::Wildcard),
+                        distinct: false,
+                    },
+                }
+                .into()),
+            ),
+            (
+                "SELECT dept FROM emp GROUP BY dept HAVING MAX(nonexistent) > 1;",
+                Err(AnalyzerError::UnknownColumn {
+                    column: "nonexistent".into(),
+                    clause: Clause::Having,
+                }
+                .into()),
+            ),
+        ] {
+            assert_analyze(Analyze { ctx, sql, expected })?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn join() -> Result<(), DbError> {
+        let ctx = &[
+            "CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));",
+            "CREATE TABLE orders (id INT PRIMARY KEY, user_id INT, total INT);",
+        ];
+
+        for (sql, expected) in [
+            (
+                "SELECT users.name, orders.total FROM users JOIN orders ON users.id = orders.user_id WHERE orders.total > 10 ORDER BY users.name;",
+                Ok(()),
+            ),
+            (
+                "SELECT * FROM users JOIN orders ON orders.total;",
+                Err(TypeError::ExpectedType {
+                    expected: VmDataType::Bool,
+                    found: Expression::Identifier("orders.total".into()),
+                }
+                .into()),
+            ),
+            (
+                "SELECT * FROM users JOIN orders ON users.id = orders.customer_id;",
+                Err(AnalyzerError::UnknownColumn {
+                    column: "orders.customer_id".into(),
+                    clause: Clause::On,
+                }
+                .into()),
+            ),
+            (
+                "SELECT name, total FROM users JOIN orders ON users.id = user_id WHERE name = 'Bob';",
+                Ok(()),
+            ),
+            (
+                "SELECT * FROM users JOIN orders ON users.id = orders.user_id WHERE id = 1;",
+                Err(AnalyzerError::AmbiguousColumn("id".into()).into()),
+            ),
+            (
+                "SELECT * FROM users JOIN orders ON users.id = orders.user_id WHERE email = 'Bob';",
+                Err(AnalyzerError::UnknownColumn {
+                    column: "email".into(),
+                    clause: Clause::Where,
+                }
+                .into()),
+            ),
+            (
+                "SELECT * FROM users JOIN users ON users.id = users.id;",
+                Err(AnalyzerError::DuplicatedTable("users".into()).into()),
+            ),
+            (
+                "SELECT * FROM users JOIN orders ON COUNT(*) > 1;",
+                Err(AnalyzerError::AggregateNotAllowed("ON").into()),
+            ),
+        ] {
+            assert_analyze(Analyze { ctx, sql, expected })?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn qualified_identifiers() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"];
+
+        for (sql, expected) in [
+            (
+                "SELECT users.id, name FROM users WHERE users.name = 'Bob' ORDER BY users.id;",
+                Ok(()),
+            ),
+            (
+                "UPDATE users SET name = users.name WHERE users.id = 1;",
+                Ok(()),
+            ),
+            ("DELETE FROM users WHERE users.id = 1;", Ok(())),
+            (
+                "SELECT orders.id FROM users;",
+                Err(AnalyzerError::UnknownColumn {
+                    column: "orders.id".into(),
+                    clause: Clause::Select,
+                }
+                .into()),
+            ),
+            (
+                "SELECT users.name, COUNT(*) FROM users GROUP BY name;",
+                Ok(()),
+            ),
+        ] {
+            assert_analyze(Analyze { ctx, sql, expected })?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn aggregates_not_allowed() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, age INT);"];
+
+        for (sql, clause) in [
+            ("SELECT * FROM users WHERE COUNT(*) > 1;", "WHERE"),
+            ("SELECT id FROM users ORDER BY MAX(age);", "ORDER BY"),
+            ("UPDATE users SET age = SUM(age);", "assignments"),
+            (
+                "SELECT MAX(COUNT(*)) FROM users;",
+                "aggregate function arguments",
+            ),
+        ] {
+            assert_analyze(Analyze {
+                ctx,
+                sql,
+                expected: Err(AnalyzerError::AggregateNotAllowed(clause).into()),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_column_reports_clause() -> Result<(), DbError> {
+        let ctx = &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255));"];
+
+        for (sql, clause, message) in [
+            (
+                "SELECT id, nonexistent FROM users;",
+                Clause::Select,
+                "unknown column 'nonexistent' in SELECT",
+            ),
+            (
+                "SELECT * FROM users WHERE nonexistent = 1;",
+                Clause::Where,
+                "unknown column 'nonexistent' in WHERE",
+            ),
+            (
+                "SELECT * FROM users ORDER BY nonexistent;",
+                Clause::OrderBy,
+                "unknown column 'nonexistent' in ORDER BY",
+            ),
+        ] {
+            let error = AnalyzerError::UnknownColumn {
+                column: "nonexistent".into(),
+                clause,
+            };
+
+            assert_eq!(error.to_string(), message);
+
+            assert_analyze(Analyze {
+                ctx,
+                sql,
+                expected: Err(error.into()),
+            })?;
+        }
+
+        Ok(())
+    }
 }
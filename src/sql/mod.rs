@@ -9,18 +9,20 @@ pub(crate) mod parser;
 pub(crate) mod prepare;
 pub(crate) mod statement;
 
-use self::{
-    analyzer::analyze, optimizer::optimize, parser::Parser, prepare::prepare, statement::Statement,
-};
+pub use self::tokenizer::Limits;
+use self::{analyzer::analyze, optimizer::optimize, prepare::prepare, statement::Statement};
 use crate::db::{DatabaseContext, DbError};
 
-/// Passes the given text input through all the SQL pipeline stages.
+/// Passes the given parsed statement through all the remaining SQL pipeline
+/// stages.
 ///
 /// Then end result is a [`Statement`] instance ready to go through the query
-/// plan generation final stage.
-pub(crate) fn pipeline(input: &str, db: &mut impl DatabaseContext) -> Result<Statement, DbError> {
-    let mut statement = Parser::new(input).parse_statement()?;
-
+/// plan generation final stage. Parsing is done separately with [`Parser`]
+/// because some inputs contain multiple statements.
+pub(crate) fn pipeline(
+    mut statement: Statement,
+    db: &mut impl DatabaseContext,
+) -> Result<Statement, DbError> {
     analyze(&statement, db)?;
     optimize(&mut statement)?;
     prepare(&mut statement, db)?;
@@ -5,25 +5,32 @@
 
 use std::mem;
 
-use super::statement::{BinaryOperator, Expression, Statement, UnaryOperator, Value};
+use super::statement::{BinaryOperator, Expression, InsertSource, Statement, UnaryOperator, Value};
 use crate::{db::SqlError, vm};
 
 /// Takes a statement and transforms it into an equivalent, optimized one.
 pub(crate) fn optimize(statement: &mut Statement) -> Result<(), SqlError> {
     match statement {
-        Statement::Insert { values, .. } => {
-            simplify_all(values.iter_mut())?;
-        }
+        Statement::Insert { values, .. } => match values {
+            InsertSource::Values(values) => simplify_all(values.iter_mut().flatten())?,
+            InsertSource::Select(select) => optimize(select)?,
+        },
 
         Statement::Select {
             columns,
+            joins,
             r#where,
+            group_by,
+            having,
             order_by,
             ..
         } => {
-            simplify_all(columns.iter_mut())?;
+            simplify_all(columns.iter_mut().map(|item| &mut item.expr))?;
+            simplify_all(joins.iter_mut().map(|join| &mut join.on))?;
             simplfy_where(r#where)?;
-            simplify_all(order_by.iter_mut())?;
+            simplify_all(group_by.iter_mut())?;
+            simplfy_where(having)?;
+            simplify_all(order_by.iter_mut().map(|order| &mut order.expr))?;
         }
 
         Statement::Delete { r#where, .. } => simplfy_where(r#where)?,
@@ -35,8 +42,8 @@ pub(crate) fn optimize(statement: &mut Statement) -> Result<(), SqlError> {
             simplify_all(columns.iter_mut().map(|col| &mut col.value))?;
         }
 
-        Statement::Explain(inner) => {
-            optimize(&mut *inner)?;
+        Statement::Explain { statement, .. } => {
+            optimize(&mut *statement)?;
         }
 
         _ => {}
@@ -105,6 +112,18 @@ fn simplify_all<'e>(
 /// that's about to get dropped. Rust borrow checker and stuff ¯\_(ツ)_/¯
 pub(crate) fn simplify(expression: &mut Expression) -> Result<(), SqlError> {
     match expression {
+        Expression::UnaryOperation {
+            operator: UnaryOperator::Not,
+            expr,
+        } => {
+            simplify(expr)?;
+            if let Expression::Value(_) = expr.as_ref() {
+                *expression = resolve_literal_expression(expression)?
+            } else {
+                *expression = negate(mem::replace(expr.as_mut(), Expression::Wildcard));
+            }
+        }
+
         Expression::UnaryOperation { expr, .. } => {
             simplify(expr)?;
             if let Expression::Value(_) = expr.as_ref() {
@@ -126,6 +145,30 @@ pub(crate) fn simplify(expression: &mut Expression) -> Result<(), SqlError> {
                     *expression = resolve_literal_expression(expression)?;
                 }
 
+                // Resolve logical operations with one constant side:
+                // x AND TRUE  => x
+                // x AND FALSE => FALSE
+                // x OR TRUE   => TRUE
+                // x OR FALSE  => x
+                (
+                    constant @ Expression::Value(Value::Bool(_)),
+                    operator @ (BinaryOperator::And | BinaryOperator::Or),
+                    variable,
+                )
+                | (
+                    variable,
+                    operator @ (BinaryOperator::And | BinaryOperator::Or),
+                    constant @ Expression::Value(Value::Bool(_)),
+                ) => {
+                    let short_circuits = match operator {
+                        BinaryOperator::And => *constant == Expression::Value(Value::Bool(false)),
+                        _ => *constant == Expression::Value(Value::Bool(true)),
+                    };
+
+                    let result = if short_circuits { constant } else { variable };
+                    *expression = mem::replace(result, Expression::Wildcard);
+                }
+
                 // Resolve these expressions to "x":
                 // 1 * x
                 // x * 1
@@ -216,6 +259,45 @@ pub(crate) fn simplify(expression: &mut Expression) -> Result<(), SqlError> {
                     mem::swap(variable, literal);
                 }
 
+                // Expand tuple comparisons into regular comparisons:
+                // (a, b) = (1, 2)  => a = 1 AND b = 2
+                // (a, b) != (1, 2) => a != 1 OR b != 2
+                //
+                // This allows the query planner to use indexes on any of the
+                // columns. Matching an entire composite key at once is not
+                // possible yet since indexes only cover one column.
+                (
+                    Expression::Tuple(left_items),
+                    operator @ (BinaryOperator::Eq | BinaryOperator::Neq),
+                    Expression::Tuple(right_items),
+                ) if left_items.len() == right_items.len() => {
+                    let connector = match operator {
+                        BinaryOperator::Eq => BinaryOperator::And,
+                        _ => BinaryOperator::Or,
+                    };
+
+                    let operator = *operator;
+
+                    let mut comparisons = mem::take(left_items)
+                        .into_iter()
+                        .zip(mem::take(right_items))
+                        .map(|(left, right)| Expression::BinaryOperation {
+                            left: Box::new(left),
+                            operator,
+                            right: Box::new(right),
+                        });
+
+                    let first = comparisons.next().unwrap();
+                    *expression =
+                        comparisons.fold(first, |chain, comparison| Expression::BinaryOperation {
+                            left: Box::new(chain),
+                            operator: connector,
+                            right: Box::new(comparison),
+                        });
+
+                    simplify(expression)?;
+                }
+
                 _other => {}
             }
         }
@@ -238,12 +320,181 @@ pub(crate) fn simplify(expression: &mut Expression) -> Result<(), SqlError> {
             *expression = mem::replace(nested.as_mut(), Expression::Wildcard);
         }
 
+        Expression::Aggregate { arg, .. } => simplify(arg.as_mut())?,
+
+        Expression::InList { expr, list, .. } => {
+            simplify(expr)?;
+            simplify_all(list.iter_mut())?;
+
+            let is_value = |expr: &Expression| matches!(expr, Expression::Value(_));
+            if is_value(expr) && list.iter().all(is_value) {
+                *expression = resolve_literal_expression(expression)?;
+            } else if list.is_empty() {
+                // Nothing is in the empty list: x IN () => FALSE.
+                let Expression::InList { negated, .. } = expression else {
+                    unreachable!();
+                };
+                *expression = Expression::Value(Value::Bool(*negated));
+            }
+        }
+
+        Expression::Between {
+            expr, low, high, ..
+        } => {
+            simplify(expr)?;
+            simplify(low)?;
+            simplify(high)?;
+
+            if let (Expression::Value(_), Expression::Value(_), Expression::Value(_)) =
+                (expr.as_ref(), low.as_ref(), high.as_ref())
+            {
+                *expression = resolve_literal_expression(expression)?;
+            }
+        }
+
+        // Values are never NULL, so literals can be resolved right away. Columns
+        // are left alone and end up in a filter.
+        Expression::IsNull { expr, negated } => {
+            simplify(expr)?;
+
+            if let Expression::Value(_) = expr.as_ref() {
+                *expression = Expression::Value(Value::Bool(*negated));
+            }
+        }
+
+        Expression::Tuple(items) => simplify_all(items.iter_mut())?,
+
+        Expression::Function { args, .. } => {
+            simplify_all(args.iter_mut())?;
+
+            if args.iter().all(|arg| matches!(arg, Expression::Value(_))) {
+                *expression = resolve_literal_expression(expression)?;
+            }
+        }
+
+        // Branches that can never be taken are removed and the first branch
+        // that is always taken replaces the entire expression.
+        Expression::Case {
+            branches,
+            else_branch,
+        } => {
+            for (condition, result) in branches.iter_mut() {
+                simplify(condition)?;
+                simplify(result)?;
+            }
+
+            if let Some(else_branch) = else_branch {
+                simplify(else_branch)?;
+            }
+
+            branches.retain(|(condition, _)| *condition != Expression::Value(Value::Bool(false)));
+
+            if let Some((Expression::Value(Value::Bool(true)), result)) = branches.first_mut() {
+                *expression = mem::replace(result, Expression::Wildcard);
+            } else if branches.is_empty() {
+                if let Some(else_branch) = else_branch {
+                    *expression = mem::replace(else_branch.as_mut(), Expression::Wildcard);
+                }
+            }
+        }
+
         _other => {}
     };
 
     Ok(())
 }
 
+/// Returns the negation of an already simplified expression.
+///
+/// `NOT` is pushed down the tree whenever possible. Comparisons are inverted
+/// and De Morgan's laws are applied to `AND` and `OR`, so something like
+/// `NOT (id = 5 OR id > 10)` becomes `id != 5 AND id <= 10`, which can still
+/// be used by [`crate::query::optimizer`] to plan index scans. Expressions that
+/// can't be inverted are simply wrapped in [`UnaryOperator::Not`].
+fn negate(expr: Expression) -> Expression {
+    match expr {
+        Expression::BinaryOperation {
+            left,
+            operator,
+            right,
+        } => {
+            let inverse = match operator {
+                BinaryOperator::Eq => BinaryOperator::Neq,
+                BinaryOperator::Neq => BinaryOperator::Eq,
+                BinaryOperator::Lt => BinaryOperator::GtEq,
+                BinaryOperator::LtEq => BinaryOperator::Gt,
+                BinaryOperator::Gt => BinaryOperator::LtEq,
+                BinaryOperator::GtEq => BinaryOperator::Lt,
+
+                BinaryOperator::And | BinaryOperator::Or => {
+                    return Expression::BinaryOperation {
+                        left: Box::new(negate(*left)),
+                        operator: match operator {
+                            BinaryOperator::And => BinaryOperator::Or,
+                            _ => BinaryOperator::And,
+                        },
+                        right: Box::new(negate(*right)),
+                    };
+                }
+
+                _ => {
+                    return Expression::UnaryOperation {
+                        operator: UnaryOperator::Not,
+                        expr: Box::new(Expression::BinaryOperation {
+                            left,
+                            operator,
+                            right,
+                        }),
+                    };
+                }
+            };
+
+            Expression::BinaryOperation {
+                left,
+                operator: inverse,
+                right,
+            }
+        }
+
+        Expression::UnaryOperation {
+            operator: UnaryOperator::Not,
+            expr,
+        } => *expr,
+
+        Expression::InList {
+            expr,
+            list,
+            negated,
+        } => Expression::InList {
+            expr,
+            list,
+            negated: !negated,
+        },
+
+        Expression::Between {
+            expr,
+            low,
+            high,
+            negated,
+        } => Expression::Between {
+            expr,
+            low,
+            high,
+            negated: !negated,
+        },
+
+        Expression::IsNull { expr, negated } => Expression::IsNull {
+            expr,
+            negated: !negated,
+        },
+
+        other => Expression::UnaryOperation {
+            operator: UnaryOperator::Not,
+            expr: Box::new(other),
+        },
+    }
+}
+
 /// Resolves an expression that doesn't contain variables into [`Expression::Value`].
 ///
 /// This function is the only reason we need to return [`Result`] in this
@@ -407,6 +658,68 @@ mod tests {
         })
     }
 
+    #[test]
+    fn simplify_not() -> Result<(), DbError> {
+        for (raw_input, optimized) in [
+            ("NOT (x = 5 OR x > 10)", "x != 5 AND x <= 10"),
+            (
+                "NOT (x < 5 AND (x >= 1 OR y != 2))",
+                "x >= 5 OR x < 1 AND y = 2",
+            ),
+            ("NOT NOT active", "active"),
+            ("NOT (x IN (1, 2))", "x NOT IN (1, 2)"),
+            ("NOT x BETWEEN 1 AND 5", "x NOT BETWEEN 1 AND 5"),
+            ("NOT (name LIKE 'a%')", "NOT name LIKE 'a%'"),
+            ("NOT (1 > 2)", "TRUE"),
+        ] {
+            assert_optimize_expr(Opt {
+                raw_input,
+                optimized,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn simplify_constant_logical_operations() -> Result<(), DbError> {
+        for (raw_input, optimized) in [
+            ("1 = 1", "TRUE"),
+            ("2 > 3", "FALSE"),
+            ("x > 5 AND 1 = 1", "x > 5"),
+            ("x > 5 AND 1 = 2", "FALSE"),
+            ("1 = 1 OR x > 5", "TRUE"),
+            ("1 = 2 OR x > 5", "x > 5"),
+            ("(x > 5 OR 2 > 3) AND (y < 2 OR 1 = 1)", "x > 5"),
+            ("x IN ()", "FALSE"),
+            ("x NOT IN ()", "TRUE"),
+        ] {
+            assert_optimize_expr(Opt {
+                raw_input,
+                optimized,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn simplify_tuple_comparisons() -> Result<(), DbError> {
+        for (raw_input, optimized) in [
+            ("(a, b) = (1, 2)", "a = 1 AND b = 2"),
+            ("(a, b, c) != (1, 1 + 1, 3)", "a != 1 OR b != 2 OR c != 3"),
+            ("(a, 1) = (2, 1)", "a = 2"),
+            ("(1, 2) = (1, 3)", "FALSE"),
+        ] {
+            assert_optimize_expr(Opt {
+                raw_input,
+                optimized,
+            })?;
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn optimize_update() -> Result<(), DbError> {
         assert_optimize_sql(Opt {
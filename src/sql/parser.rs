@@ -1,20 +1,25 @@
 //! SQL parsers. Produces [`Statement`] variants from UTF-8 strings.
 
 use core::iter::Peekable;
-use std::fmt::Display;
+use std::{fmt::Display, iter};
 
 use super::{
     statement::{
-        Assignment, BinaryOperator, Column, Constraint, Create, DataType, Drop, Expression,
-        Statement, UnaryOperator, Value,
+        AggregateFunction, Alter, Assignment, BinaryOperator, Column, Constraint, Create, DataType,
+        Direction, Drop, Expression, IndexColumn, InsertSource, Join, OrderBy, ScalarFunction,
+        SelectItem, Statement, UnaryOperator, Value,
     },
     token::{Keyword, Token},
-    tokenizer::{self, Location, TokenWithLocation, Tokenizer, TokenizerError},
+    tokenizer::{self, Limits, Location, TokenWithLocation, Tokenizer, TokenizerError},
 };
 
 /// See [`Parser::get_next_precedence`] for details.
 const UNARY_ARITHMETIC_OPERATOR_PRECEDENCE: u8 = 50;
 
+/// `NOT` binds tighter than `AND` but looser than comparisons, so
+/// `NOT x = 1 AND y` means `(NOT (x = 1)) AND y`.
+const UNARY_LOGICAL_OPERATOR_PRECEDENCE: u8 = 15;
+
 /// Parser error kind.
 #[derive(Debug, PartialEq)]
 pub(crate) enum ErrorKind {
@@ -30,6 +35,8 @@ pub(crate) enum ErrorKind {
 
     IntegerOutOfRange(String),
 
+    UnknownFunction(String),
+
     Other(String),
 }
 
@@ -91,6 +98,8 @@ impl Display for ErrorKind {
                 "number too big, none of the supported SQL data types can store this: {integer}"
             ),
 
+            ErrorKind::UnknownFunction(name) => write!(f, "unknown function {name}()"),
+
             ErrorKind::Other(message) => f.write_str(message),
         }
     }
@@ -107,23 +116,13 @@ pub(crate) struct ParserError {
 
 impl Display for ParserError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        writeln!(
+        write!(
             f,
             "Parse Error at line {} column {}: {}",
             self.location.line, self.location.col, self.kind,
         )?;
 
-        let white_spaces = if let Some(line) = self.input.lines().nth(self.location.line - 1) {
-            f.write_str(line)?;
-            self.location.col - 1
-        } else {
-            // Unexpected EOF
-            let line = self.input.lines().last().unwrap();
-            f.write_str(line)?;
-            line.chars().count()
-        };
-
-        write!(f, "\n{}^", String::from(" ").repeat(white_spaces))
+        tokenizer::write_snippet(f, &self.input, self.location)
     }
 }
 
@@ -166,24 +165,70 @@ pub(crate) struct Parser<'i> {
 impl<'i> Parser<'i> {
     /// Creates a new parser for the given `input` string.
     pub fn new(input: &'i str) -> Self {
+        Self::with_limits(input, Limits::default())
+    }
+
+    /// Same as [`Parser::new`] but the tokenizer enforces the given [`Limits`]
+    /// instead of the default ones.
+    pub fn with_limits(input: &'i str, limits: Limits) -> Self {
         Self {
             input,
-            tokenizer: Tokenizer::new(input).into_iter().peekable(),
+            tokenizer: Tokenizer::with_limits(input, limits).into_iter().peekable(),
             location: Location::default(),
         }
     }
 
-    /// Attempts to parse the `input` string into a list of [`Statement`]
-    /// instances.
-    pub fn try_parse(&mut self) -> ParseResult<Vec<Statement>> {
+    /// Parses a script of multiple statements separated by `;` into a list of
+    /// [`Statement`] instances.
+    pub fn parse_script(&mut self) -> ParseResult<Vec<Statement>> {
+        iter::from_fn(|| self.parse_next_statement()).collect()
+    }
+
+    /// Same as [`Self::parse_script`] but doesn't stop at the first syntax
+    /// error.
+    ///
+    /// When a statement can't be parsed the rest of it is skipped up to the
+    /// next `;` and parsing continues from there, so all the broken statements
+    /// are reported at once. Tokenizer errors still end the script because
+    /// there's no reliable way to find the next token after them.
+    pub fn parse_script_collecting_errors(&mut self) -> Result<Vec<Statement>, Vec<ParserError>> {
         let mut statements = Vec::new();
+        let mut errors = Vec::new();
 
-        loop {
-            match self.peek_token() {
-                Some(Ok(Token::Eof)) | None => return Ok(statements),
-                _ => statements.push(self.parse_statement()?),
+        while let Some(result) = self.parse_next_statement() {
+            match result {
+                Ok(statement) => statements.push(statement),
+
+                Err(error) => {
+                    let recoverable = !matches!(error.kind, ErrorKind::TokenizerError(_));
+                    errors.push(error);
+
+                    if !recoverable {
+                        break;
+                    }
+
+                    if let Err(error) = self.skip_to_next_statement() {
+                        errors.push(error);
+                        break;
+                    }
+                }
             }
         }
+
+        match errors.is_empty() {
+            true => Ok(statements),
+            false => Err(errors),
+        }
+    }
+
+    /// Parses the next statement of a script.
+    ///
+    /// Returns [`None`] once there are no more statements in the input.
+    pub fn parse_next_statement(&mut self) -> Option<ParseResult<Statement>> {
+        match self.peek_token() {
+            Some(Ok(Token::Eof)) | None => None,
+            _ => Some(self.parse_statement()),
+        }
     }
 
     /// Parses a single SQL statement in the input string.
@@ -191,21 +236,7 @@ impl<'i> Parser<'i> {
     /// If the statement terminator is not found then it returns [`Err`].
     pub fn parse_statement(&mut self) -> ParseResult<Statement> {
         let statement = match self.expect_one_of(&Self::supported_statements())? {
-            Keyword::Select => {
-                let columns = self.parse_comma_separated_expressions()?;
-                self.expect_keyword(Keyword::From)?;
-
-                let (from, r#where) = self.parse_from_and_optional_where()?;
-
-                let order_by = self.parse_optional_order_by()?;
-
-                Statement::Select {
-                    columns,
-                    from,
-                    r#where,
-                    order_by,
-                }
-            }
+            Keyword::Select => self.parse_select()?,
 
             Keyword::Create => {
                 let keyword = self.expect_one_of(&[
@@ -218,10 +249,15 @@ impl<'i> Parser<'i> {
                 Statement::Create(match keyword {
                     Keyword::Database => Create::Database(self.parse_identifier()?),
 
-                    Keyword::Table => Create::Table {
-                        name: self.parse_identifier()?,
-                        columns: self.parse_column_definitions()?,
-                    },
+                    Keyword::Table => {
+                        let if_not_exists = self.parse_if_not_exists()?;
+
+                        Create::Table {
+                            name: self.parse_identifier()?,
+                            columns: self.parse_column_definitions()?,
+                            if_not_exists,
+                        }
+                    }
 
                     Keyword::Unique | Keyword::Index => {
                         let unique = keyword == Keyword::Unique;
@@ -234,14 +270,12 @@ impl<'i> Parser<'i> {
                         self.expect_keyword(Keyword::On)?;
                         let table = self.parse_identifier()?;
 
-                        self.expect_token(Token::LeftParen)?;
-                        let column = self.parse_identifier()?;
-                        self.expect_token(Token::RightParen)?;
+                        let columns = self.parse_comma_separated(Self::parse_index_column, true)?;
 
                         Create::Index {
                             name,
                             table,
-                            column,
+                            columns,
                             unique,
                         }
                     }
@@ -269,8 +303,16 @@ impl<'i> Parser<'i> {
                 let into = self.parse_identifier()?;
                 let columns = self.parse_optional_identifier_list()?;
 
-                self.expect_keyword(Keyword::Values)?;
-                let values = self.parse_comma_separated(Self::parse_expression, true)?;
+                let values = match self.expect_one_of(&[Keyword::Values, Keyword::Select])? {
+                    // `INSERT INTO t () VALUES ()` uses default values for all
+                    // the columns.
+                    Keyword::Values => InsertSource::Values(self.parse_comma_separated(
+                        |parser| parser.parse_possibly_empty_list(Self::parse_expression),
+                        false,
+                    )?),
+
+                    _ => InsertSource::Select(Box::new(self.parse_select()?)),
+                };
 
                 Statement::Insert {
                     into,
@@ -287,16 +329,67 @@ impl<'i> Parser<'i> {
             }
 
             Keyword::Drop => {
-                let keyword = self.expect_one_of(&[Keyword::Database, Keyword::Table])?;
+                let keyword =
+                    self.expect_one_of(&[Keyword::Database, Keyword::Table, Keyword::Index])?;
+
+                let if_exists = match keyword {
+                    Keyword::Table | Keyword::Index => self.parse_if_exists()?,
+                    _ => false,
+                };
+
                 let identifier = self.parse_identifier()?;
 
                 Statement::Drop(match keyword {
                     Keyword::Database => Drop::Database(identifier),
-                    Keyword::Table => Drop::Table(identifier),
+                    Keyword::Table => Drop::Table {
+                        name: identifier,
+                        if_exists,
+                    },
+                    Keyword::Index => Drop::Index {
+                        name: identifier,
+                        table: match self.consume_optional_keyword(Keyword::On) {
+                            true => Some(self.parse_identifier()?),
+                            false => None,
+                        },
+                        if_exists,
+                    },
                     _ => unreachable!(),
                 })
             }
 
+            Keyword::Alter => {
+                self.expect_keyword(Keyword::Table)?;
+                let table = self.parse_identifier()?;
+
+                Statement::Alter(
+                    match self.expect_one_of(&[Keyword::Drop, Keyword::Rename])? {
+                        Keyword::Drop => {
+                            self.consume_optional_keyword(Keyword::Column);
+                            let column = self.parse_identifier()?;
+
+                            Alter::DropColumn { table, column }
+                        }
+
+                        _ if self.consume_optional_keyword(Keyword::To) => Alter::RenameTable {
+                            table,
+                            new_name: self.parse_identifier()?,
+                        },
+
+                        _ => {
+                            self.consume_optional_keyword(Keyword::Column);
+                            let column = self.parse_identifier()?;
+                            self.expect_keyword(Keyword::To)?;
+
+                            Alter::RenameColumn {
+                                table,
+                                column,
+                                new_name: self.parse_identifier()?,
+                            }
+                        }
+                    },
+                )
+            }
+
             Keyword::Start => {
                 self.expect_keyword(Keyword::Transaction)?;
                 Statement::StartTransaction
@@ -304,9 +397,25 @@ impl<'i> Parser<'i> {
 
             Keyword::Commit => Statement::Commit,
 
-            Keyword::Rollback => Statement::Rollback,
+            Keyword::Rollback => match self.consume_optional_keyword(Keyword::To) {
+                true => {
+                    self.consume_optional_keyword(Keyword::Savepoint);
+                    Statement::RollbackTo(self.parse_identifier()?)
+                }
+                false => Statement::Rollback,
+            },
+
+            Keyword::Savepoint => Statement::Savepoint(self.parse_identifier()?),
+
+            Keyword::Explain => {
+                let analyze = self.consume_optional_keyword(Keyword::Analyze);
+                let statement = Box::new(self.parse_statement()?);
+                return Ok(Statement::Explain { statement, analyze });
+            }
+
+            Keyword::Analyze => Statement::Analyze(self.parse_identifier()?),
 
-            Keyword::Explain => return Ok(Statement::Explain(Box::new(self.parse_statement()?))),
+            Keyword::Vacuum => Statement::Vacuum,
 
             _ => unreachable!(),
         };
@@ -315,6 +424,71 @@ impl<'i> Parser<'i> {
         Ok(statement)
     }
 
+    /// Parses a `SELECT` statement without the terminator.
+    ///
+    /// The `SELECT` keyword has already been consumed. Table aliases like
+    /// `FROM users u` are replaced with the table name in all the clauses, so
+    /// the rest of the system never sees them.
+    fn parse_select(&mut self) -> ParseResult<Statement> {
+        let distinct = self.consume_optional_keyword(Keyword::Distinct);
+
+        if let Some(Ok(Token::Keyword(Keyword::From))) = self.peek_token() {
+            self.next_token()?;
+            return Err(self.error(ErrorKind::Other(
+                "SELECT needs at least one column or expression".into(),
+            )));
+        }
+
+        let mut columns = self.parse_comma_separated(Self::parse_select_item, false)?;
+
+        let mut aliases = Vec::new();
+
+        // Constant queries like `SELECT 1 + 1` don't need a table.
+        let (from, mut joins) = if self.consume_optional_keyword(Keyword::From) {
+            let from = self.parse_table_reference(&mut aliases)?;
+            (Some(from), self.parse_joins(&mut aliases)?)
+        } else {
+            (None, Vec::new())
+        };
+
+        let mut r#where = self.parse_optional_where()?;
+
+        let mut group_by = self.parse_optional_group_by()?;
+        let mut having = self.parse_optional_having()?;
+        let mut order_by = self.parse_optional_order_by()?;
+
+        let limit = self.parse_optional_clause_number(Keyword::Limit)?;
+        let offset = self.parse_optional_clause_number(Keyword::Offset)?;
+
+        if !aliases.is_empty() {
+            let exprs = columns
+                .iter_mut()
+                .map(|item| &mut item.expr)
+                .chain(joins.iter_mut().map(|join| &mut join.on))
+                .chain(r#where.as_mut())
+                .chain(group_by.iter_mut())
+                .chain(having.as_mut())
+                .chain(order_by.iter_mut().map(|key| &mut key.expr));
+
+            for expr in exprs {
+                replace_table_aliases(expr, &aliases);
+            }
+        }
+
+        Ok(Statement::Select {
+            distinct,
+            columns,
+            from,
+            joins,
+            r#where,
+            group_by,
+            having,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
     /// Starts the TDOP recursive descent.
     ///
     /// TDOP consists of 3 functions that call each other recursively:
@@ -348,12 +522,21 @@ impl<'i> Parser<'i> {
     /// Parses the beginning of an expression.
     fn parse_prefix(&mut self) -> ParseResult<Expression> {
         match self.next_token()? {
-            Token::Identifier(ident) => Ok(Expression::Identifier(ident)),
+            Token::Identifier(ident) => match self.peek_token() {
+                Some(Ok(Token::LeftParen)) => self.parse_function(ident),
+                _ => self.parse_optional_qualified_identifier(ident),
+            },
+
             Token::Mul => Ok(Expression::Wildcard),
 
             Token::String(string) => Ok(Expression::Value(Value::String(string))),
             Token::Keyword(Keyword::True) => Ok(Expression::Value(Value::Bool(true))),
             Token::Keyword(Keyword::False) => Ok(Expression::Value(Value::Bool(false))),
+            Token::Number(num) if num.contains('.') => Ok(Expression::Value(Value::Float(
+                num.parse()
+                    .map_err(|_| self.error(ErrorKind::Other(format!("invalid float {num}"))))?,
+            ))),
+
             Token::Number(num) => Ok(Expression::Value(Value::Number(
                 num.parse()
                     .map_err(|_| self.error(ErrorKind::IntegerOutOfRange(num)))?,
@@ -371,12 +554,61 @@ impl<'i> Parser<'i> {
                 Ok(Expression::UnaryOperation { operator, expr })
             }
 
+            Token::Keyword(Keyword::Not) => Ok(Expression::UnaryOperation {
+                operator: UnaryOperator::Not,
+                expr: Box::new(self.parse_expr(UNARY_LOGICAL_OPERATOR_PRECEDENCE)?),
+            }),
+
+            Token::Keyword(
+                keyword @ (Keyword::Count
+                | Keyword::Sum
+                | Keyword::Avg
+                | Keyword::Min
+                | Keyword::Max),
+            ) => {
+                // Aggregate function names are non-reserved, so without
+                // parenthesis they're just column names.
+                if !self.consume_optional_token(Token::LeftParen) {
+                    return Ok(Expression::Identifier(keyword.to_string().to_lowercase()));
+                }
+
+                let func = match keyword {
+                    Keyword::Count => AggregateFunction::Count,
+                    Keyword::Sum => AggregateFunction::Sum,
+                    Keyword::Avg => AggregateFunction::Avg,
+                    Keyword::Min => AggregateFunction::Min,
+                    Keyword::Max => AggregateFunction::Max,
+                    _ => unreachable!(),
+                };
+
+                let distinct = self.consume_optional_keyword(Keyword::Distinct);
+                let arg = Box::new(self.parse_expression()?);
+                self.expect_token(Token::RightParen)?;
+
+                Ok(Expression::Aggregate {
+                    func,
+                    arg,
+                    distinct,
+                })
+            }
+
             Token::LeftParen => {
                 let expr = self.parse_expression()?;
+
+                if !self.consume_optional_token(Token::Comma) {
+                    self.expect_token(Token::RightParen)?;
+                    return Ok(Expression::Nested(Box::new(expr)));
+                }
+
+                let mut items = vec![expr];
+                items.extend(self.parse_comma_separated(Self::parse_expression, false)?);
                 self.expect_token(Token::RightParen)?;
-                Ok(Expression::Nested(Box::new(expr)))
+
+                Ok(Expression::Tuple(items))
             }
 
+            Token::Keyword(Keyword::Case) => self.parse_case(),
+
             unexpected => Err(self.error(ErrorKind::ExpectedOneOf {
                 expected: vec![
                     Token::Identifier(Default::default()),
@@ -400,6 +632,7 @@ impl<'i> Parser<'i> {
             Token::Minus => BinaryOperator::Minus,
             Token::Div => BinaryOperator::Div,
             Token::Mul => BinaryOperator::Mul,
+            Token::Concat => BinaryOperator::Concat,
             Token::Eq => BinaryOperator::Eq,
             Token::Neq => BinaryOperator::Neq,
             Token::Gt => BinaryOperator::Gt,
@@ -408,6 +641,31 @@ impl<'i> Parser<'i> {
             Token::LtEq => BinaryOperator::LtEq,
             Token::Keyword(Keyword::And) => BinaryOperator::And,
             Token::Keyword(Keyword::Or) => BinaryOperator::Or,
+            Token::Keyword(Keyword::Like) => BinaryOperator::Like,
+
+            Token::Keyword(keyword @ (Keyword::In | Keyword::Between | Keyword::Not)) => {
+                let negated = keyword == Keyword::Not;
+                let keyword = if negated {
+                    self.expect_one_of(&[Keyword::In, Keyword::Between])?
+                } else {
+                    keyword
+                };
+
+                return match keyword {
+                    Keyword::In => self.parse_in_list(left, negated),
+                    _ => self.parse_between(left, negated, precedence),
+                };
+            }
+
+            Token::Keyword(Keyword::Is) => {
+                let negated = self.consume_optional_keyword(Keyword::Not);
+                self.expect_keyword(Keyword::Null)?;
+
+                return Ok(Expression::IsNull {
+                    expr: Box::new(left),
+                    negated,
+                });
+            }
 
             unexpected => Err(self.error(ErrorKind::ExpectedOneOf {
                 expected: Self::supported_operators(),
@@ -431,13 +689,103 @@ impl<'i> Parser<'i> {
         match token {
             Token::Keyword(Keyword::Or) => 5,
             Token::Keyword(Keyword::And) => 10,
-            Token::Eq | Token::Neq | Token::Gt | Token::GtEq | Token::Lt | Token::LtEq => 20,
-            Token::Plus | Token::Minus => 30,
+            Token::Eq
+            | Token::Neq
+            | Token::Gt
+            | Token::GtEq
+            | Token::Lt
+            | Token::LtEq
+            | Token::Keyword(
+                Keyword::Like | Keyword::In | Keyword::Between | Keyword::Not | Keyword::Is,
+            ) => 20,
+            Token::Plus | Token::Minus | Token::Concat => 30,
             Token::Mul | Token::Div => 40,
             _ => 0,
         }
     }
 
+    /// Parses the parenthesized list of `expr IN (list)`.
+    ///
+    /// The `IN` keyword has already been consumed. The list can be empty.
+    fn parse_in_list(&mut self, expr: Expression, negated: bool) -> ParseResult<Expression> {
+        Ok(Expression::InList {
+            expr: Box::new(expr),
+            list: self.parse_possibly_empty_list(Self::parse_expression)?,
+            negated,
+        })
+    }
+
+    /// Parses the bounds of `expr BETWEEN low AND high`.
+    ///
+    /// The `BETWEEN` keyword has already been consumed. The bounds are parsed
+    /// with the precedence of `BETWEEN` itself so that the `AND` keyword that
+    /// separates them is not mistaken for a logical operator.
+    fn parse_between(
+        &mut self,
+        expr: Expression,
+        negated: bool,
+        precedence: u8,
+    ) -> ParseResult<Expression> {
+        let low = self.parse_expr(precedence)?;
+        self.expect_keyword(Keyword::And)?;
+        let high = self.parse_expr(precedence)?;
+
+        Ok(Expression::Between {
+            expr: Box::new(expr),
+            low: Box::new(low),
+            high: Box::new(high),
+            negated,
+        })
+    }
+
+    /// Parses the arguments of a scalar function call like `UPPER(name)`.
+    ///
+    /// The function `name` has already been consumed and is case insensitive.
+    /// Functions that don't exist in [`ScalarFunction::from_name`] are
+    /// rejected right away.
+    fn parse_function(&mut self, name: String) -> ParseResult<Expression> {
+        let func = ScalarFunction::from_name(&name.to_uppercase())
+            .ok_or_else(|| self.error(ErrorKind::UnknownFunction(name)))?;
+
+        Ok(Expression::Function {
+            func,
+            args: self.parse_possibly_empty_list(Self::parse_expression)?,
+        })
+    }
+
+    /// Parses the branches of `CASE WHEN condition THEN result ... END`.
+    ///
+    /// The `CASE` keyword has already been consumed. There must be at least
+    /// one `WHEN` branch, `ELSE` is optional.
+    fn parse_case(&mut self) -> ParseResult<Expression> {
+        let mut branches = Vec::new();
+
+        self.expect_keyword(Keyword::When)?;
+
+        loop {
+            let condition = self.parse_expression()?;
+            self.expect_keyword(Keyword::Then)?;
+            branches.push((condition, self.parse_expression()?));
+
+            if !self.consume_optional_keyword(Keyword::When) {
+                break;
+            }
+        }
+
+        let else_branch = if self.consume_optional_keyword(Keyword::Else) {
+            Some(Box::new(self.parse_expression()?))
+        } else {
+            None
+        };
+
+        self.expect_keyword(Keyword::End)?;
+
+        Ok(Expression::Case {
+            branches,
+            else_branch,
+        })
+    }
+
     /// Parses a column definition for `CREATE TABLE` statements.
     fn parse_column(&mut self) -> ParseResult<Column> {
         let name = self.parse_identifier()?;
@@ -454,14 +802,21 @@ impl<'i> Parser<'i> {
                 }
             }
 
-            Keyword::Varchar => {
+            // Some dialects put UNSIGNED first: UNSIGNED INT, UNSIGNED BIGINT.
+            Keyword::Unsigned => match self.expect_one_of(&[Keyword::Int, Keyword::BigInt])? {
+                Keyword::Int => DataType::UnsignedInt,
+                Keyword::BigInt => DataType::UnsignedBigInt,
+                _ => unreachable!(),
+            },
+
+            string @ (Keyword::Varchar | Keyword::Char) => {
                 self.expect_token(Token::LeftParen)?;
 
                 let length = match self.next_token()? {
                     Token::Number(num) => num.parse().map_err(|_| {
-                        self.error(ErrorKind::Other(
-                            "incorrect VARCHAR length definition".into(),
-                        ))
+                        self.error(ErrorKind::Other(format!(
+                            "incorrect {string} length definition"
+                        )))
                     })?,
                     unexpected => Err(self.error(ErrorKind::Expected {
                         expected: Token::Number(Default::default()),
@@ -470,18 +825,30 @@ impl<'i> Parser<'i> {
                 };
 
                 self.expect_token(Token::RightParen)?;
-                DataType::Varchar(length)
+
+                match string {
+                    Keyword::Varchar => DataType::Varchar(length),
+                    _ => DataType::Char(length),
+                }
             }
 
             Keyword::Bool => DataType::Bool,
 
+            Keyword::Double | Keyword::Float => DataType::Double,
+
             _ => unreachable!(),
         };
 
         let mut constraints = Vec::new();
+        let mut default = None;
 
         while let Some(constraint) = self
-            .consume_one_of(&[Keyword::Primary, Keyword::Unique])
+            .consume_one_of(&[
+                Keyword::Primary,
+                Keyword::Unique,
+                Keyword::Not,
+                Keyword::Default,
+            ])
             .as_option()
         {
             match constraint {
@@ -492,6 +859,15 @@ impl<'i> Parser<'i> {
 
                 Keyword::Unique => constraints.push(Constraint::Unique),
 
+                Keyword::Not => {
+                    self.expect_keyword(Keyword::Null)?;
+                    constraints.push(Constraint::NotNull);
+                }
+
+                // Comparisons and logical operators must be parenthesized,
+                // otherwise `DEFAULT TRUE NOT NULL` would be ambiguous.
+                Keyword::Default => default = Some(self.parse_expr(20)?),
+
                 _ => unreachable!(),
             }
         }
@@ -500,6 +876,7 @@ impl<'i> Parser<'i> {
             name,
             data_type,
             constraints,
+            default,
         })
     }
 
@@ -518,6 +895,16 @@ impl<'i> Parser<'i> {
         self.next_token().and_then(|token| match token {
             Token::Identifier(ident) => Ok(ident),
 
+            // Non-reserved keywords are downgraded to identifiers. The
+            // tokenizer doesn't keep the original spelling of keywords, so
+            // these are always lowercase. START is displayed as BEGIN, so it
+            // can't go through the Display impl.
+            Token::Keyword(Keyword::Start) => Ok(String::from("start")),
+
+            Token::Keyword(keyword) if !keyword.is_reserved() => {
+                Ok(keyword.to_string().to_lowercase())
+            }
+
             _ => Err(self.error(ErrorKind::Expected {
                 expected: Token::Identifier(Default::default()),
                 found: token,
@@ -525,6 +912,43 @@ impl<'i> Parser<'i> {
         })
     }
 
+    /// Parses the optional `IF EXISTS` modifier of `DROP` statements.
+    fn parse_if_exists(&mut self) -> ParseResult<bool> {
+        if !self.consume_optional_keyword(Keyword::If) {
+            return Ok(false);
+        }
+
+        self.expect_keyword(Keyword::Exists)?;
+
+        Ok(true)
+    }
+
+    /// Parses the optional `IF NOT EXISTS` modifier of `CREATE` statements.
+    fn parse_if_not_exists(&mut self) -> ParseResult<bool> {
+        if !self.consume_optional_keyword(Keyword::If) {
+            return Ok(false);
+        }
+
+        self.expect_keyword(Keyword::Not)?;
+        self.expect_keyword(Keyword::Exists)?;
+
+        Ok(true)
+    }
+
+    /// Parses column names qualified with their table name like `users.id`.
+    ///
+    /// The `table` identifier has already been consumed. If there's no dot
+    /// after it then it's just a regular [`Expression::Identifier`].
+    fn parse_optional_qualified_identifier(&mut self, table: String) -> ParseResult<Expression> {
+        if !self.consume_optional_token(Token::Dot) {
+            return Ok(Expression::Identifier(table));
+        }
+
+        let column = self.parse_identifier()?;
+
+        Ok(Expression::QualifiedIdentifier { table, column })
+    }
+
     /// Takes a `subparser` as input and calls it after every instance of
     /// [`Token::Comma`].
     fn parse_comma_separated<T>(
@@ -548,7 +972,7 @@ impl<'i> Parser<'i> {
         Ok(results)
     }
 
-    /// Used to parse the expressions after `SELECT`, `WHERE`, `SET` or `ORDER BY`.
+    /// Used to parse the expressions after `SELECT`, `WHERE` or `SET`.
     fn parse_comma_separated_expressions(&mut self) -> ParseResult<Vec<Expression>> {
         self.parse_comma_separated(Self::parse_expression, false)
     }
@@ -558,15 +982,29 @@ impl<'i> Parser<'i> {
         self.parse_comma_separated(Self::parse_column, true)
     }
 
-    /// Expects a list of identifiers, not complete expressions.
-    fn parse_identifier_list(&mut self) -> ParseResult<Vec<String>> {
-        self.parse_comma_separated(Self::parse_identifier, true)
+    /// Same as [`Self::parse_comma_separated`] with required parenthesis but
+    /// the list can be empty: `()`.
+    fn parse_possibly_empty_list<T>(
+        &mut self,
+        subparser: impl FnMut(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<Vec<T>> {
+        self.expect_token(Token::LeftParen)?;
+
+        if self.consume_optional_token(Token::RightParen) {
+            return Ok(vec![]);
+        }
+
+        let results = self.parse_comma_separated(subparser, false)?;
+        self.expect_token(Token::RightParen)?;
+
+        Ok(results)
     }
 
-    /// Expects a list of identifiers, not complete expressions.
+    /// Expects a list of identifiers, not complete expressions. The list can
+    /// be omitted or empty.
     fn parse_optional_identifier_list(&mut self) -> ParseResult<Vec<String>> {
         if let Some(Ok(Token::LeftParen)) = self.peek_token() {
-            Ok(self.parse_identifier_list()?)
+            self.parse_possibly_empty_list(Self::parse_identifier)
         } else {
             Ok(vec![])
         }
@@ -598,11 +1036,64 @@ impl<'i> Parser<'i> {
         Ok((from, r#where))
     }
 
-    /// Parses the `ORDER BY` clause at the end of `SELECT` statements.
+    /// Parses all the `JOIN` clauses of `SELECT` statements.
     ///
-    /// It only works with identifiers (not expressions) for now.
-    fn parse_optional_order_by(&mut self) -> ParseResult<Vec<Expression>> {
-        if self.consume_optional_keyword(Keyword::Order) {
+    /// Only inner joins are supported, see [`Join`].
+    fn parse_joins(&mut self, aliases: &mut Vec<(String, String)>) -> ParseResult<Vec<Join>> {
+        let mut joins = Vec::new();
+
+        loop {
+            if self.consume_optional_keyword(Keyword::Inner) {
+                self.expect_keyword(Keyword::Join)?;
+            } else if !self.consume_optional_keyword(Keyword::Join) {
+                break;
+            }
+
+            let table = self.parse_table_reference(aliases)?;
+            self.expect_keyword(Keyword::On)?;
+            let on = self.parse_expression()?;
+
+            joins.push(Join { table, on });
+        }
+
+        Ok(joins)
+    }
+
+    /// Parses a table name of the `FROM` or `JOIN` clauses followed by an
+    /// optional alias like `users AS u` or `users u`.
+    ///
+    /// The alias is pushed to `aliases` along with the table name.
+    fn parse_table_reference(
+        &mut self,
+        aliases: &mut Vec<(String, String)>,
+    ) -> ParseResult<String> {
+        let table = self.parse_identifier()?;
+
+        if let Some(alias) = self.parse_optional_alias()? {
+            aliases.push((alias, table.clone()));
+        }
+
+        Ok(table)
+    }
+
+    /// Parses `AS alias` or just `alias`.
+    ///
+    /// Without `AS` only plain identifiers are accepted. Keywords like `FROM`
+    /// or `WHERE` start the next clause instead.
+    fn parse_optional_alias(&mut self) -> ParseResult<Option<String>> {
+        if self.consume_optional_keyword(Keyword::As) {
+            return Ok(Some(self.parse_identifier()?));
+        }
+
+        match self.peek_token() {
+            Some(Ok(Token::Identifier(_))) => self.parse_identifier().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Parses the `GROUP BY` clause of `SELECT` statements.
+    fn parse_optional_group_by(&mut self) -> ParseResult<Vec<Expression>> {
+        if self.consume_optional_keyword(Keyword::Group) {
             self.expect_keyword(Keyword::By)?;
             self.parse_comma_separated_expressions()
         } else {
@@ -610,6 +1101,84 @@ impl<'i> Parser<'i> {
         }
     }
 
+    /// Parses the `HAVING` clause of `SELECT` statements.
+    fn parse_optional_having(&mut self) -> ParseResult<Option<Expression>> {
+        if self.consume_optional_keyword(Keyword::Having) {
+            Ok(Some(self.parse_expression()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Parses the `ORDER BY` clause at the end of `SELECT` statements.
+    fn parse_optional_order_by(&mut self) -> ParseResult<Vec<OrderBy>> {
+        if self.consume_optional_keyword(Keyword::Order) {
+            self.expect_keyword(Keyword::By)?;
+            self.parse_comma_separated(Self::parse_order_by_key, false)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Parses a single expression of the `SELECT` column list along with its
+    /// optional alias.
+    fn parse_select_item(&mut self) -> ParseResult<SelectItem> {
+        let expr = self.parse_expression()?;
+
+        // Wildcards expand to many columns, they can't have a single alias.
+        let alias = if expr != Expression::Wildcard {
+            self.parse_optional_alias()?
+        } else {
+            None
+        };
+
+        Ok(SelectItem { expr, alias })
+    }
+
+    /// Parses a single `ORDER BY` key with its optional `ASC` or `DESC`
+    /// direction.
+    fn parse_order_by_key(&mut self) -> ParseResult<OrderBy> {
+        let expr = self.parse_expression()?;
+        let direction = self.parse_direction();
+
+        Ok(OrderBy { expr, direction })
+    }
+
+    /// Parses a column of a `CREATE INDEX` statement like `price DESC`.
+    fn parse_index_column(&mut self) -> ParseResult<IndexColumn> {
+        let name = self.parse_identifier()?;
+        let direction = self.parse_direction();
+
+        Ok(IndexColumn { name, direction })
+    }
+
+    /// Parses the optional `ASC` or `DESC` keyword after a sort key.
+    fn parse_direction(&mut self) -> Direction {
+        match self.consume_one_of(&[Keyword::Asc, Keyword::Desc]) {
+            Keyword::Desc => Direction::Desc,
+            _ => Direction::Asc,
+        }
+    }
+
+    /// Parses clauses like `LIMIT 10` or `OFFSET 20` that consist of a keyword
+    /// followed by a non-negative integer.
+    fn parse_optional_clause_number(&mut self, keyword: Keyword) -> ParseResult<Option<u64>> {
+        if !self.consume_optional_keyword(keyword) {
+            return Ok(None);
+        }
+
+        match self.next_token()? {
+            Token::Number(num) => num.parse().map(Some).map_err(|_| {
+                self.error(ErrorKind::Other(format!("incorrect {keyword} value {num}")))
+            }),
+
+            unexpected => Err(self.error(ErrorKind::Expected {
+                expected: Token::Number(Default::default()),
+                found: unexpected,
+            })),
+        }
+    }
+
     /// Same as [`Self::expect_token`] but takes [`Keyword`] variants instead.
     fn expect_keyword(&mut self, expected: Keyword) -> ParseResult<Keyword> {
         self.expect_token(Token::Keyword(expected))
@@ -701,6 +1270,29 @@ impl<'i> Parser<'i> {
         }
     }
 
+    /// Consumes tokens up to and including the next [`Token::SemiColon`] in
+    /// order to resynchronize after a syntax error.
+    ///
+    /// If the error was caused by the `;` itself then that token has already
+    /// been consumed and we're sitting at the beginning of the next statement,
+    /// so nothing must be skipped. [`Self::location`] points to the last token
+    /// consumed, which is how we tell the difference.
+    fn skip_to_next_statement(&mut self) -> ParseResult<()> {
+        if self.input[self.location.byte_offset..].starts_with(';') {
+            return Ok(());
+        }
+
+        loop {
+            if let Some(Ok(Token::Eof)) | None = self.peek_token() {
+                return Ok(());
+            }
+
+            if self.next_token()? == Token::SemiColon {
+                return Ok(());
+            }
+        }
+    }
+
     /// Skips all instances of [`Token::Whitespace`] in the stream.
     fn skip_white_spaces(&mut self) {
         while let Some(Ok(Token::Whitespace(_))) = self.peek_token_in_stream() {
@@ -773,10 +1365,14 @@ impl<'i> Parser<'i> {
             Keyword::Insert,
             Keyword::Delete,
             Keyword::Drop,
+            Keyword::Alter,
             Keyword::Start,
             Keyword::Rollback,
             Keyword::Commit,
+            Keyword::Savepoint,
             Keyword::Explain,
+            Keyword::Analyze,
+            Keyword::Vacuum,
         ]
     }
 
@@ -784,12 +1380,17 @@ impl<'i> Parser<'i> {
     fn supported_data_types() -> Vec<Keyword> {
         // For integers types the unsigned version doesn't need to be here.
         // Specifying the initial keyword (INT, BIGINT) takes care of the
-        // optional UNSIGNED that follows.
+        // optional UNSIGNED that follows. UNSIGNED can also come first, in
+        // which case INT or BIGINT must follow.
         vec![
             Keyword::Int,
             Keyword::BigInt,
+            Keyword::Unsigned,
             Keyword::Bool,
             Keyword::Varchar,
+            Keyword::Char,
+            Keyword::Double,
+            Keyword::Float,
         ]
     }
 
@@ -800,6 +1401,7 @@ impl<'i> Parser<'i> {
             Token::Minus,
             Token::Div,
             Token::Mul,
+            Token::Concat,
             Token::Eq,
             Token::Neq,
             Token::Gt,
@@ -808,28 +1410,124 @@ impl<'i> Parser<'i> {
             Token::LtEq,
             Token::Keyword(Keyword::And),
             Token::Keyword(Keyword::Or),
+            Token::Keyword(Keyword::Like),
+            Token::Keyword(Keyword::In),
+            Token::Keyword(Keyword::Between),
+            Token::Keyword(Keyword::Is),
         ]
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Replaces `alias.column` with `table.column` in `expr`.
+///
+/// `aliases` contains `(alias, table)` pairs.
+fn replace_table_aliases(expr: &mut Expression, aliases: &[(String, String)]) {
+    match expr {
+        Expression::QualifiedIdentifier { table, .. } => {
+            if let Some((_, name)) = aliases.iter().find(|(alias, _)| alias == table) {
+                table.clone_from(name);
+            }
+        }
 
-    #[test]
-    fn parse_simple_select() {
-        let sql = "SELECT id, name FROM users;";
+        Expression::BinaryOperation { left, right, .. } => {
+            replace_table_aliases(left, aliases);
+            replace_table_aliases(right, aliases);
+        }
+
+        Expression::UnaryOperation { expr, .. }
+        | Expression::Nested(expr)
+        | Expression::IsNull { expr, .. }
+        | Expression::Aggregate { arg: expr, .. } => replace_table_aliases(expr, aliases),
+
+        Expression::InList { expr, list, .. } => {
+            replace_table_aliases(expr, aliases);
+            for item in list {
+                replace_table_aliases(item, aliases);
+            }
+        }
+
+        Expression::Between {
+            expr, low, high, ..
+        } => {
+            replace_table_aliases(expr, aliases);
+            replace_table_aliases(low, aliases);
+            replace_table_aliases(high, aliases);
+        }
+
+        Expression::Function { args, .. } | Expression::Tuple(args) => {
+            for arg in args {
+                replace_table_aliases(arg, aliases);
+            }
+        }
+
+        Expression::Case {
+            branches,
+            else_branch,
+        } => {
+            for (condition, result) in branches {
+                replace_table_aliases(condition, aliases);
+                replace_table_aliases(result, aliases);
+            }
+
+            if let Some(else_branch) = else_branch {
+                replace_table_aliases(else_branch, aliases);
+            }
+        }
+
+        Expression::Identifier(_) | Expression::Value(_) | Expression::Wildcard => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_simple_select() {
+        let sql = "SELECT id, name FROM users;";
 
         assert_eq!(
             Parser::new(sql).parse_statement(),
             Ok(Statement::Select {
+                distinct: false,
                 columns: vec![
-                    Expression::Identifier("id".into()),
-                    Expression::Identifier("name".into())
+                    Expression::Identifier("id".into()).into(),
+                    Expression::Identifier("name".into()).into()
                 ],
-                from: "users".into(),
+                from: Some("users".into()),
+                joins: vec![],
                 r#where: None,
-                order_by: vec![]
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            })
+        )
+    }
+
+    #[test]
+    fn parse_select_without_from() {
+        let sql = "SELECT 1 + 1 WHERE TRUE;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Select {
+                distinct: false,
+                columns: vec![Expression::BinaryOperation {
+                    left: Box::new(Expression::Value(Value::Number(1))),
+                    operator: BinaryOperator::Plus,
+                    right: Box::new(Expression::Value(Value::Number(1))),
+                }
+                .into()],
+                from: None,
+                joins: vec![],
+                r#where: Some(Expression::Value(Value::Bool(true))),
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
             })
         )
     }
@@ -841,10 +1539,16 @@ mod tests {
         assert_eq!(
             Parser::new(sql).parse_statement(),
             Ok(Statement::Select {
-                columns: vec![Expression::Wildcard],
-                from: "users".into(),
+                distinct: false,
+                columns: vec![Expression::Wildcard.into()],
+                from: Some("users".into()),
+                joins: vec![],
                 r#where: None,
-                order_by: vec![]
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
             })
         )
     }
@@ -856,18 +1560,24 @@ mod tests {
         assert_eq!(
             Parser::new(sql).parse_statement(),
             Ok(Statement::Select {
+                distinct: false,
                 columns: vec![
-                    Expression::Identifier("id".into()),
-                    Expression::Identifier("price".into()),
-                    Expression::Identifier("discount".into())
+                    Expression::Identifier("id".into()).into(),
+                    Expression::Identifier("price".into()).into(),
+                    Expression::Identifier("discount".into()).into()
                 ],
-                from: "products".into(),
+                from: Some("products".into()),
+                joins: vec![],
                 r#where: Some(Expression::BinaryOperation {
                     left: Box::new(Expression::Identifier("price".into())),
                     operator: BinaryOperator::GtEq,
                     right: Box::new(Expression::Value(Value::Number(100)))
                 }),
-                order_by: vec![]
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
             })
         )
     }
@@ -883,10 +1593,11 @@ mod tests {
         assert_eq!(
             Parser::new(sql).parse_statement(),
             Ok(Statement::Select {
+                distinct: false,
                 columns: vec![
-                    Expression::Identifier("id".into()),
-                    Expression::Identifier("price".into()),
-                    Expression::Identifier("discount".into()),
+                    Expression::Identifier("id".into()).into(),
+                    Expression::Identifier("price".into()).into(),
+                    Expression::Identifier("discount".into()).into(),
                     Expression::BinaryOperation {
                         left: Box::new(Expression::BinaryOperation {
                             left: Box::new(Expression::Identifier("price".into())),
@@ -896,8 +1607,10 @@ mod tests {
                         operator: BinaryOperator::Div,
                         right: Box::new(Expression::Value(Value::Number(100))),
                     }
+                    .into()
                 ],
-                from: "products".into(),
+                from: Some("products".into()),
+                joins: vec![],
                 r#where: Some(Expression::BinaryOperation {
                     left: Box::new(Expression::BinaryOperation {
                         left: Box::new(Expression::BinaryOperation {
@@ -929,7 +1642,11 @@ mod tests {
                         }),
                     })
                 }),
+                group_by: vec![],
+                having: None,
                 order_by: vec![],
+                limit: None,
+                offset: None,
             })
         )
     }
@@ -941,50 +1658,898 @@ mod tests {
         assert_eq!(
             Parser::new(sql).parse_statement(),
             Ok(Statement::Select {
+                distinct: false,
+                columns: vec![
+                    Expression::Identifier("name".into()).into(),
+                    Expression::Identifier("email".into()).into()
+                ],
+                from: Some("users".into()),
+                joins: vec![],
+                r#where: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![OrderBy {
+                    expr: Expression::Identifier("email".into()),
+                    direction: Direction::Asc,
+                }],
+                limit: None,
+                offset: None,
+            })
+        )
+    }
+
+    #[test]
+    fn parse_select_aggregates() {
+        let sql = "SELECT COUNT(*), MAX(age) + 1, count FROM users;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Select {
+                distinct: false,
+                columns: vec![
+                    Expression::Aggregate {
+                        func: AggregateFunction::Count,
+                        arg: Box::new(Expression::Wildcard),
+                        distinct: false,
+                    }
+                    .into(),
+                    Expression::BinaryOperation {
+                        left: Box::new(Expression::Aggregate {
+                            func: AggregateFunction::Max,
+                            arg: Box::new(Expression::Identifier("age".into())),
+                            distinct: false,
+                        }),
+                        operator: BinaryOperator::Plus,
+                        right: Box::new(Expression::Value(Value::Number(1))),
+                    }
+                    .into(),
+                    Expression::Identifier("count".into()).into(),
+                ],
+                from: Some("users".into()),
+                joins: vec![],
+                r#where: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            })
+        )
+    }
+
+    #[test]
+    fn parse_aggregate_distinct() {
+        assert_eq!(
+            Parser::new("COUNT(DISTINCT email)").parse_expression(),
+            Ok(Expression::Aggregate {
+                func: AggregateFunction::Count,
+                arg: Box::new(Expression::Identifier("email".into())),
+                distinct: true,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_select_group_by() {
+        let sql = "SELECT dept, COUNT(*) FROM emp WHERE age > 18 GROUP BY dept ORDER BY dept;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Select {
+                distinct: false,
+                columns: vec![
+                    Expression::Identifier("dept".into()).into(),
+                    Expression::Aggregate {
+                        func: AggregateFunction::Count,
+                        arg: Box::new(Expression::Wildcard),
+                        distinct: false,
+                    }
+                    .into(),
+                ],
+                from: Some("emp".into()),
+                joins: vec![],
+                r#where: Some(Expression::BinaryOperation {
+                    left: Box::new(Expression::Identifier("age".into())),
+                    operator: BinaryOperator::Gt,
+                    right: Box::new(Expression::Value(Value::Number(18))),
+                }),
+                group_by: vec![Expression::Identifier("dept".into())],
+                having: None,
+                order_by: vec![OrderBy {
+                    expr: Expression::Identifier("dept".into()),
+                    direction: Direction::Asc,
+                }],
+                limit: None,
+                offset: None,
+            })
+        )
+    }
+
+    #[test]
+    fn parse_select_distinct() {
+        let sql = "SELECT DISTINCT dept, age FROM emp ORDER BY age DESC;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Select {
+                distinct: true,
+                columns: vec![
+                    Expression::Identifier("dept".into()).into(),
+                    Expression::Identifier("age".into()).into(),
+                ],
+                from: Some("emp".into()),
+                joins: vec![],
+                r#where: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![OrderBy {
+                    expr: Expression::Identifier("age".into()),
+                    direction: Direction::Desc,
+                }],
+                limit: None,
+                offset: None,
+            })
+        )
+    }
+
+    #[test]
+    fn parse_select_having() {
+        let sql = "SELECT dept FROM emp GROUP BY dept HAVING COUNT(*) > 3;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Select {
+                distinct: false,
+                columns: vec![Expression::Identifier("dept".into()).into()],
+                from: Some("emp".into()),
+                joins: vec![],
+                r#where: None,
+                group_by: vec![Expression::Identifier("dept".into())],
+                having: Some(Expression::BinaryOperation {
+                    left: Box::new(Expression::Aggregate {
+                        func: AggregateFunction::Count,
+                        arg: Box::new(Expression::Wildcard),
+                        distinct: false,
+                    }),
+                    operator: BinaryOperator::Gt,
+                    right: Box::new(Expression::Value(Value::Number(3))),
+                }),
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            })
+        )
+    }
+
+    #[test]
+    fn parse_select_join() {
+        let sql = "SELECT users.name, total FROM users JOIN orders ON users.id = orders.user_id INNER JOIN items ON items.order_id = orders.id WHERE total > 10;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Select {
+                distinct: false,
+                columns: vec![
+                    Expression::QualifiedIdentifier {
+                        table: "users".into(),
+                        column: "name".into(),
+                    }
+                    .into(),
+                    Expression::Identifier("total".into()).into(),
+                ],
+                from: Some("users".into()),
+                joins: vec![
+                    Join {
+                        table: "orders".into(),
+                        on: Expression::BinaryOperation {
+                            left: Box::new(Expression::QualifiedIdentifier {
+                                table: "users".into(),
+                                column: "id".into(),
+                            }),
+                            operator: BinaryOperator::Eq,
+                            right: Box::new(Expression::QualifiedIdentifier {
+                                table: "orders".into(),
+                                column: "user_id".into(),
+                            }),
+                        },
+                    },
+                    Join {
+                        table: "items".into(),
+                        on: Expression::BinaryOperation {
+                            left: Box::new(Expression::QualifiedIdentifier {
+                                table: "items".into(),
+                                column: "order_id".into(),
+                            }),
+                            operator: BinaryOperator::Eq,
+                            right: Box::new(Expression::QualifiedIdentifier {
+                                table: "orders".into(),
+                                column: "id".into(),
+                            }),
+                        },
+                    },
+                ],
+                r#where: Some(Expression::BinaryOperation {
+                    left: Box::new(Expression::Identifier("total".into())),
+                    operator: BinaryOperator::Gt,
+                    right: Box::new(Expression::Value(Value::Number(10))),
+                }),
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            })
+        )
+    }
+
+    #[test]
+    fn parse_select_aliases() {
+        let sql = "SELECT id AS key, age + 1 AS next_age, name FROM users ORDER BY next_age;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Select {
+                distinct: false,
+                columns: vec![
+                    SelectItem {
+                        expr: Expression::Identifier("id".into()),
+                        alias: Some("key".into()),
+                    },
+                    SelectItem {
+                        expr: Expression::BinaryOperation {
+                            left: Box::new(Expression::Identifier("age".into())),
+                            operator: BinaryOperator::Plus,
+                            right: Box::new(Expression::Value(Value::Number(1))),
+                        },
+                        alias: Some("next_age".into()),
+                    },
+                    Expression::Identifier("name".into()).into(),
+                ],
+                from: Some("users".into()),
+                joins: vec![],
+                r#where: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![OrderBy {
+                    expr: Expression::Identifier("next_age".into()),
+                    direction: Direction::Asc,
+                }],
+                limit: None,
+                offset: None,
+            })
+        )
+    }
+
+    #[test]
+    fn parse_select_aliases_without_as() {
+        let sql = "SELECT age a, name FROM users ORDER BY a;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Select {
+                distinct: false,
                 columns: vec![
-                    Expression::Identifier("name".into()),
-                    Expression::Identifier("email".into())
+                    SelectItem {
+                        expr: Expression::Identifier("age".into()),
+                        alias: Some("a".into()),
+                    },
+                    Expression::Identifier("name".into()).into(),
                 ],
-                from: "users".into(),
+                from: Some("users".into()),
+                joins: vec![],
+                r#where: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![OrderBy {
+                    expr: Expression::Identifier("a".into()),
+                    direction: Direction::Asc,
+                }],
+                limit: None,
+                offset: None,
+            })
+        )
+    }
+
+    #[test]
+    fn parse_select_table_aliases() {
+        let with_as = "SELECT u.name, o.price AS p FROM users AS u JOIN orders AS o ON u.id = o.user_id ORDER BY u.name;";
+        let without_as = "SELECT u.name, o.price p FROM users u JOIN orders o ON u.id = o.user_id ORDER BY u.name;";
+
+        let expected = Statement::Select {
+            distinct: false,
+            columns: vec![
+                Expression::QualifiedIdentifier {
+                    table: "users".into(),
+                    column: "name".into(),
+                }
+                .into(),
+                SelectItem {
+                    expr: Expression::QualifiedIdentifier {
+                        table: "orders".into(),
+                        column: "price".into(),
+                    },
+                    alias: Some("p".into()),
+                },
+            ],
+            from: Some("users".into()),
+            joins: vec![Join {
+                table: "orders".into(),
+                on: Expression::BinaryOperation {
+                    left: Box::new(Expression::QualifiedIdentifier {
+                        table: "users".into(),
+                        column: "id".into(),
+                    }),
+                    operator: BinaryOperator::Eq,
+                    right: Box::new(Expression::QualifiedIdentifier {
+                        table: "orders".into(),
+                        column: "user_id".into(),
+                    }),
+                },
+            }],
+            r#where: None,
+            group_by: vec![],
+            having: None,
+            order_by: vec![OrderBy {
+                expr: Expression::QualifiedIdentifier {
+                    table: "users".into(),
+                    column: "name".into(),
+                },
+                direction: Direction::Asc,
+            }],
+            limit: None,
+            offset: None,
+        };
+
+        assert_eq!(Parser::new(with_as).parse_statement(), Ok(expected.clone()));
+        assert_eq!(Parser::new(without_as).parse_statement(), Ok(expected));
+    }
+
+    #[test]
+    fn parse_select_alias_followed_by_clause_keyword() {
+        let sql = "SELECT age FROM users WHERE age > 18;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Select {
+                distinct: false,
+                columns: vec![Expression::Identifier("age".into()).into()],
+                from: Some("users".into()),
+                joins: vec![],
+                r#where: Some(Expression::BinaryOperation {
+                    left: Box::new(Expression::Identifier("age".into())),
+                    operator: BinaryOperator::Gt,
+                    right: Box::new(Expression::Value(Value::Number(18))),
+                }),
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            })
+        )
+    }
+
+    #[test]
+    fn parse_select_order_by_direction() {
+        let sql = "SELECT * FROM users ORDER BY age DESC, name ASC, id;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Select {
+                distinct: false,
+                columns: vec![Expression::Wildcard.into()],
+                from: Some("users".into()),
+                joins: vec![],
                 r#where: None,
-                order_by: vec![Expression::Identifier("email".into())]
+                group_by: vec![],
+                having: None,
+                order_by: vec![
+                    OrderBy {
+                        expr: Expression::Identifier("age".into()),
+                        direction: Direction::Desc,
+                    },
+                    OrderBy {
+                        expr: Expression::Identifier("name".into()),
+                        direction: Direction::Asc,
+                    },
+                    OrderBy {
+                        expr: Expression::Identifier("id".into()),
+                        direction: Direction::Asc,
+                    },
+                ],
+                limit: None,
+                offset: None,
             })
         )
     }
 
     #[test]
-    fn parse_create_database() {
-        let sql = "CREATE DATABASE test;";
+    fn parse_select_limit_offset() {
+        let sql = "SELECT * FROM users ORDER BY email LIMIT 10 OFFSET 20;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Select {
+                distinct: false,
+                columns: vec![Expression::Wildcard.into()],
+                from: Some("users".into()),
+                joins: vec![],
+                r#where: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![OrderBy {
+                    expr: Expression::Identifier("email".into()),
+                    direction: Direction::Asc,
+                }],
+                limit: Some(10),
+                offset: Some(20),
+            })
+        );
+
+        assert_eq!(
+            Parser::new("SELECT * FROM users OFFSET 5;").parse_statement(),
+            Ok(Statement::Select {
+                distinct: false,
+                columns: vec![Expression::Wildcard.into()],
+                from: Some("users".into()),
+                joins: vec![],
+                r#where: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: Some(5),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_like_expression() {
+        assert_eq!(
+            Parser::new("name LIKE 'Jo%' AND id > 1").parse_expression(),
+            Ok(Expression::BinaryOperation {
+                left: Box::new(Expression::BinaryOperation {
+                    left: Box::new(Expression::Identifier("name".into())),
+                    operator: BinaryOperator::Like,
+                    right: Box::new(Expression::Value(Value::String("Jo%".into()))),
+                }),
+                operator: BinaryOperator::And,
+                right: Box::new(Expression::BinaryOperation {
+                    left: Box::new(Expression::Identifier("id".into())),
+                    operator: BinaryOperator::Gt,
+                    right: Box::new(Expression::Value(Value::Number(1))),
+                }),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_concat_expression() {
+        assert_eq!(
+            Parser::new("first || ' ' || last = 'John Doe'").parse_expression(),
+            Ok(Expression::BinaryOperation {
+                left: Box::new(Expression::BinaryOperation {
+                    left: Box::new(Expression::BinaryOperation {
+                        left: Box::new(Expression::Identifier("first".into())),
+                        operator: BinaryOperator::Concat,
+                        right: Box::new(Expression::Value(Value::String(" ".into()))),
+                    }),
+                    operator: BinaryOperator::Concat,
+                    right: Box::new(Expression::Identifier("last".into())),
+                }),
+                operator: BinaryOperator::Eq,
+                right: Box::new(Expression::Value(Value::String("John Doe".into()))),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_in_list() {
+        let in_list = |list: Vec<i128>, negated| Expression::InList {
+            expr: Box::new(Expression::Identifier("id".into())),
+            list: list
+                .into_iter()
+                .map(|num| Expression::Value(Value::Number(num)))
+                .collect(),
+            negated,
+        };
+
+        assert_eq!(
+            Parser::new("id IN (1, 2, 3)").parse_expression(),
+            Ok(in_list(vec![1, 2, 3], false))
+        );
+
+        assert_eq!(
+            Parser::new("id NOT IN (1)").parse_expression(),
+            Ok(in_list(vec![1], true))
+        );
+
+        assert_eq!(
+            Parser::new("id IN () OR id NOT IN (5)").parse_expression(),
+            Ok(Expression::BinaryOperation {
+                left: Box::new(in_list(vec![], false)),
+                operator: BinaryOperator::Or,
+                right: Box::new(in_list(vec![5], true)),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_between() {
+        let between = |negated| Expression::Between {
+            expr: Box::new(Expression::Identifier("age".into())),
+            low: Box::new(Expression::Value(Value::Number(18))),
+            high: Box::new(Expression::BinaryOperation {
+                left: Box::new(Expression::Value(Value::Number(60))),
+                operator: BinaryOperator::Plus,
+                right: Box::new(Expression::Value(Value::Number(5))),
+            }),
+            negated,
+        };
+
+        assert_eq!(
+            Parser::new("age BETWEEN 18 AND 60 + 5 AND id NOT BETWEEN 1 AND 2").parse_expression(),
+            Ok(Expression::BinaryOperation {
+                left: Box::new(between(false)),
+                operator: BinaryOperator::And,
+                right: Box::new(Expression::Between {
+                    expr: Box::new(Expression::Identifier("id".into())),
+                    low: Box::new(Expression::Value(Value::Number(1))),
+                    high: Box::new(Expression::Value(Value::Number(2))),
+                    negated: true,
+                }),
+            })
+        );
+
+        assert_eq!(between(true).to_string(), "age NOT BETWEEN 18 AND 60 + 5");
+    }
+
+    #[test]
+    fn parse_is_null() {
+        let is_null = |column: &str, negated| Expression::IsNull {
+            expr: Box::new(Expression::Identifier(column.into())),
+            negated,
+        };
+
+        assert_eq!(
+            Parser::new("name IS NULL OR email IS NOT NULL").parse_expression(),
+            Ok(Expression::BinaryOperation {
+                left: Box::new(is_null("name", false)),
+                operator: BinaryOperator::Or,
+                right: Box::new(is_null("email", true)),
+            })
+        );
+
+        assert_eq!(is_null("email", true).to_string(), "email IS NOT NULL");
+    }
+
+    #[test]
+    fn parse_function() {
+        let sql = "upper(name) || SUBSTR(name, 1, LENGTH(name))";
+
+        assert_eq!(
+            Parser::new(sql).parse_expression(),
+            Ok(Expression::BinaryOperation {
+                left: Box::new(Expression::Function {
+                    func: ScalarFunction::Upper,
+                    args: vec![Expression::Identifier("name".into())],
+                }),
+                operator: BinaryOperator::Concat,
+                right: Box::new(Expression::Function {
+                    func: ScalarFunction::Substr,
+                    args: vec![
+                        Expression::Identifier("name".into()),
+                        Expression::Value(Value::Number(1)),
+                        Expression::Function {
+                            func: ScalarFunction::Length,
+                            args: vec![Expression::Identifier("name".into())],
+                        },
+                    ],
+                }),
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_function() {
+        let sql = "SELECT SQRT(x) FROM t;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Err(ParserError {
+                kind: ErrorKind::UnknownFunction("SQRT".into()),
+                location: Location {
+                    line: 1,
+                    col: 8,
+                    byte_offset: 7
+                },
+                input: sql.to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_case() {
+        let sql = "CASE WHEN age >= 18 THEN 'adult' WHEN age > 12 THEN 'teen' ELSE 'child' END";
+
+        let case = Expression::Case {
+            branches: vec![
+                (
+                    Expression::BinaryOperation {
+                        left: Box::new(Expression::Identifier("age".into())),
+                        operator: BinaryOperator::GtEq,
+                        right: Box::new(Expression::Value(Value::Number(18))),
+                    },
+                    Expression::Value(Value::String("adult".into())),
+                ),
+                (
+                    Expression::BinaryOperation {
+                        left: Box::new(Expression::Identifier("age".into())),
+                        operator: BinaryOperator::Gt,
+                        right: Box::new(Expression::Value(Value::Number(12))),
+                    },
+                    Expression::Value(Value::String("teen".into())),
+                ),
+            ],
+            else_branch: Some(Box::new(Expression::Value(Value::String("child".into())))),
+        };
+
+        assert_eq!(Parser::new(sql).parse_expression(), Ok(case.clone()));
+        assert_eq!(
+            case.to_string(),
+            r#"CASE WHEN age >= 18 THEN "adult" WHEN age > 12 THEN "teen" ELSE "child" END"#
+        );
+
+        assert_eq!(
+            Parser::new("CASE WHEN x THEN 1 END + 1").parse_expression(),
+            Ok(Expression::BinaryOperation {
+                left: Box::new(Expression::Case {
+                    branches: vec![(
+                        Expression::Identifier("x".into()),
+                        Expression::Value(Value::Number(1))
+                    )],
+                    else_branch: None,
+                }),
+                operator: BinaryOperator::Plus,
+                right: Box::new(Expression::Value(Value::Number(1))),
+            })
+        );
+
+        assert!(Parser::new("CASE ELSE 1 END").parse_expression().is_err());
+        assert!(Parser::new("CASE WHEN x THEN 1")
+            .parse_expression()
+            .is_err());
+    }
+
+    #[test]
+    fn parse_tuple() {
+        let sql = "(id, name) = (1, 'John')";
+
+        let expr = Expression::BinaryOperation {
+            left: Box::new(Expression::Tuple(vec![
+                Expression::Identifier("id".into()),
+                Expression::Identifier("name".into()),
+            ])),
+            operator: BinaryOperator::Eq,
+            right: Box::new(Expression::Tuple(vec![
+                Expression::Value(Value::Number(1)),
+                Expression::Value(Value::String("John".into())),
+            ])),
+        };
+
+        assert_eq!(Parser::new(sql).parse_expression(), Ok(expr.clone()));
+        assert_eq!(expr.to_string(), r#"(id, name) = (1, "John")"#);
+
+        assert_eq!(
+            Parser::new("(id)").parse_expression(),
+            Ok(Expression::Nested(Box::new(Expression::Identifier(
+                "id".into()
+            ))))
+        );
+        assert!(Parser::new("(id,)").parse_expression().is_err());
+    }
+
+    #[test]
+    fn parse_not_precedence() {
+        let not = |expr| Expression::UnaryOperation {
+            operator: UnaryOperator::Not,
+            expr: Box::new(expr),
+        };
+
+        assert_eq!(
+            Parser::new("NOT x = 1 AND NOT y").parse_expression(),
+            Ok(Expression::BinaryOperation {
+                left: Box::new(not(Expression::BinaryOperation {
+                    left: Box::new(Expression::Identifier("x".into())),
+                    operator: BinaryOperator::Eq,
+                    right: Box::new(Expression::Value(Value::Number(1))),
+                })),
+                operator: BinaryOperator::And,
+                right: Box::new(not(Expression::Identifier("y".into()))),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_create_database() {
+        let sql = "CREATE DATABASE test;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Create(Create::Database("test".into())))
+        )
+    }
+
+    #[test]
+    fn parse_create_table() {
+        let sql = r#"
+            CREATE TABLE users (
+                id INT PRIMARY KEY,
+                name VARCHAR(255),
+                email VARCHAR(255) UNIQUE
+            );
+        "#;
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Create(Create::Table {
+                name: "users".into(),
+                columns: vec![
+                    Column::primary_key("id", DataType::Int),
+                    Column::new("name", DataType::Varchar(255)),
+                    Column::unique("email", DataType::Varchar(255)),
+                ],
+                if_not_exists: false,
+            }))
+        )
+    }
+
+    #[test]
+    fn parse_not_null_constraint() {
+        let sql = "CREATE TABLE users (id INT PRIMARY KEY NOT NULL, name VARCHAR(255) NOT NULL);";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Create(Create::Table {
+                name: "users".into(),
+                columns: vec![
+                    Column {
+                        name: "id".into(),
+                        data_type: DataType::Int,
+                        constraints: vec![Constraint::PrimaryKey, Constraint::NotNull],
+                        default: None,
+                    },
+                    Column {
+                        name: "name".into(),
+                        data_type: DataType::Varchar(255),
+                        constraints: vec![Constraint::NotNull],
+                        default: None,
+                    },
+                ],
+                if_not_exists: false,
+            }))
+        )
+    }
+
+    #[test]
+    fn parse_default_values() {
+        let sql = "CREATE TABLE t (id INT PRIMARY KEY, active BOOL DEFAULT TRUE NOT NULL, n INT DEFAULT -1 + 2);";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Create(Create::Table {
+                name: "t".into(),
+                columns: vec![
+                    Column::primary_key("id", DataType::Int),
+                    Column {
+                        name: "active".into(),
+                        data_type: DataType::Bool,
+                        constraints: vec![Constraint::NotNull],
+                        default: Some(Expression::Value(Value::Bool(true))),
+                    },
+                    Column {
+                        name: "n".into(),
+                        data_type: DataType::Int,
+                        constraints: vec![],
+                        default: Some(Expression::BinaryOperation {
+                            left: Box::new(Expression::UnaryOperation {
+                                operator: UnaryOperator::Minus,
+                                expr: Box::new(Expression::Value(Value::Number(1))),
+                            }),
+                            operator: BinaryOperator::Plus,
+                            right: Box::new(Expression::Value(Value::Number(2))),
+                        }),
+                    },
+                ],
+                if_not_exists: false,
+            }))
+        )
+    }
+
+    #[test]
+    fn parse_unsigned_data_types() {
+        let sql = r#"
+            CREATE TABLE test (
+                a INT UNSIGNED,
+                b UNSIGNED INT,
+                c BIGINT UNSIGNED,
+                d UNSIGNED BIGINT
+            );
+        "#;
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Create(Create::Table {
+                name: "test".into(),
+                columns: vec![
+                    Column::new("a", DataType::UnsignedInt),
+                    Column::new("b", DataType::UnsignedInt),
+                    Column::new("c", DataType::UnsignedBigInt),
+                    Column::new("d", DataType::UnsignedBigInt),
+                ],
+                if_not_exists: false,
+            }))
+        )
+    }
+
+    #[test]
+    fn parse_statement_with_comments() {
+        let sql = "-- Users table\nCREATE TABLE users ( -- columns\n  id INT PRIMARY KEY\n); --";
 
         assert_eq!(
             Parser::new(sql).parse_statement(),
-            Ok(Statement::Create(Create::Database("test".into())))
+            Ok(Statement::Create(Create::Table {
+                name: "users".into(),
+                columns: vec![Column::primary_key("id", DataType::Int)],
+                if_not_exists: false,
+            }))
         )
     }
 
     #[test]
-    fn parse_create_table() {
-        let sql = r#"
-            CREATE TABLE users (
-                id INT PRIMARY KEY,
-                name VARCHAR(255),
-                email VARCHAR(255) UNIQUE
-            );
-        "#;
+    fn parse_char_data_type() {
+        let sql = "CREATE TABLE test (code CHAR(3), name VARCHAR(255));";
 
         assert_eq!(
             Parser::new(sql).parse_statement(),
             Ok(Statement::Create(Create::Table {
-                name: "users".into(),
+                name: "test".into(),
                 columns: vec![
-                    Column::primary_key("id", DataType::Int),
+                    Column::new("code", DataType::Char(3)),
                     Column::new("name", DataType::Varchar(255)),
-                    Column::unique("email", DataType::Varchar(255)),
-                ]
+                ],
+                if_not_exists: false,
             }))
         )
     }
 
+    #[test]
+    fn parse_double_data_type_and_float_literals() {
+        assert_eq!(
+            Parser::new("CREATE TABLE test (price DOUBLE, weight FLOAT);").parse_statement(),
+            Ok(Statement::Create(Create::Table {
+                name: "test".into(),
+                columns: vec![
+                    Column::new("price", DataType::Double),
+                    Column::new("weight", DataType::Double),
+                ],
+                if_not_exists: false,
+            }))
+        );
+
+        assert_eq!(
+            Parser::new("2.5 * -0.5").parse_expression(),
+            Ok(Expression::BinaryOperation {
+                left: Box::new(Expression::Value(Value::Float(2.5))),
+                operator: BinaryOperator::Mul,
+                right: Box::new(Expression::UnaryOperation {
+                    operator: UnaryOperator::Minus,
+                    expr: Box::new(Expression::Value(Value::Float(0.5))),
+                }),
+            })
+        );
+    }
+
     #[test]
     fn parse_create_index() {
         let sql = "CREATE INDEX test_idx ON test(some_column);";
@@ -994,7 +2559,7 @@ mod tests {
             Ok(Statement::Create(Create::Index {
                 name: "test_idx".into(),
                 table: "test".into(),
-                column: "some_column".into(),
+                columns: vec!["some_column".into()],
                 unique: false,
             }))
         )
@@ -1009,7 +2574,43 @@ mod tests {
             Ok(Statement::Create(Create::Index {
                 name: "email_uq_idx".into(),
                 table: "users".into(),
-                column: "email".into(),
+                columns: vec!["email".into()],
+                unique: true,
+            }))
+        )
+    }
+
+    #[test]
+    fn parse_create_multi_column_index() {
+        let sql = "CREATE UNIQUE INDEX name_uq_idx ON users(last_name, first_name);";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Create(Create::Index {
+                name: "name_uq_idx".into(),
+                table: "users".into(),
+                columns: vec!["last_name".into(), "first_name".into()],
+                unique: true,
+            }))
+        )
+    }
+
+    #[test]
+    fn parse_create_index_with_direction() {
+        let sql = "CREATE UNIQUE INDEX latest ON posts(created_at DESC, id ASC);";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Create(Create::Index {
+                name: "latest".into(),
+                table: "posts".into(),
+                columns: vec![
+                    IndexColumn {
+                        name: "created_at".into(),
+                        direction: Direction::Desc,
+                    },
+                    "id".into(),
+                ],
                 unique: true,
             }))
         )
@@ -1110,11 +2711,11 @@ mod tests {
             Ok(Statement::Insert {
                 into: "users".into(),
                 columns: ["id", "name", "email"].map(String::from).into(),
-                values: vec![
+                values: InsertSource::Values(vec![vec![
                     Expression::Value(Value::Number(1)),
                     Expression::Value(Value::String("Test".into())),
                     Expression::Value(Value::String("test@test.com".into())),
-                ]
+                ]])
             })
         );
     }
@@ -1128,15 +2729,103 @@ mod tests {
             Ok(Statement::Insert {
                 into: "users".into(),
                 columns: vec![],
-                values: vec![
+                values: InsertSource::Values(vec![vec![
                     Expression::Value(Value::Number(1)),
                     Expression::Value(Value::String("Test".into())),
                     Expression::Value(Value::String("test@test.com".into())),
-                ]
+                ]])
+            })
+        );
+    }
+
+    #[test]
+    fn parse_insert_multiple_rows() {
+        let sql = "INSERT INTO users (id, name) VALUES (1, 'John'), (2, 'Jane'), ();";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Insert {
+                into: "users".into(),
+                columns: ["id", "name"].map(String::from).into(),
+                values: InsertSource::Values(vec![
+                    vec![
+                        Expression::Value(Value::Number(1)),
+                        Expression::Value(Value::String("John".into())),
+                    ],
+                    vec![
+                        Expression::Value(Value::Number(2)),
+                        Expression::Value(Value::String("Jane".into())),
+                    ],
+                    vec![],
+                ])
+            })
+        );
+    }
+
+    #[test]
+    fn parse_insert_select() {
+        let sql = "INSERT INTO archive (id) SELECT id FROM orders WHERE done = TRUE;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Insert {
+                into: "archive".into(),
+                columns: vec!["id".into()],
+                values: InsertSource::Select(Box::new(Statement::Select {
+                    distinct: false,
+                    columns: vec![Expression::Identifier("id".into()).into()],
+                    from: Some("orders".into()),
+                    joins: vec![],
+                    r#where: Some(Expression::BinaryOperation {
+                        left: Box::new(Expression::Identifier("done".into())),
+                        operator: BinaryOperator::Eq,
+                        right: Box::new(Expression::Value(Value::Bool(true))),
+                    }),
+                    group_by: vec![],
+                    having: None,
+                    order_by: vec![],
+                    limit: None,
+                    offset: None,
+                })),
             })
         );
     }
 
+    #[test]
+    fn parse_insert_default_values() {
+        for sql in [
+            "INSERT INTO users () VALUES ();",
+            "INSERT INTO users VALUES ();",
+        ] {
+            assert_eq!(
+                Parser::new(sql).parse_statement(),
+                Ok(Statement::Insert {
+                    into: "users".into(),
+                    columns: vec![],
+                    values: InsertSource::Values(vec![vec![]]),
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn parse_select_without_columns() {
+        let sql = "SELECT FROM users;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Err(ParserError {
+                kind: ErrorKind::Other("SELECT needs at least one column or expression".into()),
+                location: Location {
+                    line: 1,
+                    col: 8,
+                    byte_offset: 7
+                },
+                input: sql.to_owned(),
+            })
+        )
+    }
+
     #[test]
     fn parse_drop_database() {
         let sql = "DROP DATABASE test;";
@@ -1153,10 +2842,221 @@ mod tests {
 
         assert_eq!(
             Parser::new(sql).parse_statement(),
-            Ok(Statement::Drop(Drop::Table("test".into())))
+            Ok(Statement::Drop(Drop::Table {
+                name: "test".into(),
+                if_exists: false
+            }))
         )
     }
 
+    #[test]
+    fn parse_if_exists_modifiers() {
+        assert_eq!(
+            Parser::new("CREATE TABLE IF NOT EXISTS users (id INT);").parse_statement(),
+            Ok(Statement::Create(Create::Table {
+                name: "users".into(),
+                columns: vec![Column::new("id", DataType::Int)],
+                if_not_exists: true,
+            }))
+        );
+
+        assert_eq!(
+            Parser::new("DROP TABLE IF EXISTS users;").parse_statement(),
+            Ok(Statement::Drop(Drop::Table {
+                name: "users".into(),
+                if_exists: true,
+            }))
+        );
+
+        assert_eq!(
+            Parser::new("DROP INDEX IF EXISTS name_idx ON users;").parse_statement(),
+            Ok(Statement::Drop(Drop::Index {
+                name: "name_idx".into(),
+                table: Some("users".into()),
+                if_exists: true,
+            }))
+        );
+    }
+
+    #[test]
+    fn parse_drop_index() {
+        assert_eq!(
+            Parser::new("DROP INDEX users_email_idx;").parse_statement(),
+            Ok(Statement::Drop(Drop::Index {
+                name: "users_email_idx".into(),
+                table: None,
+                if_exists: false,
+            }))
+        );
+
+        assert_eq!(
+            Parser::new("DROP INDEX users_email_idx ON users;").parse_statement(),
+            Ok(Statement::Drop(Drop::Index {
+                name: "users_email_idx".into(),
+                table: Some("users".into()),
+                if_exists: false,
+            }))
+        );
+    }
+
+    #[test]
+    fn parse_analyze() {
+        assert_eq!(
+            Parser::new("ANALYZE users;").parse_statement(),
+            Ok(Statement::Analyze("users".into()))
+        );
+
+        assert_eq!(
+            Parser::new("EXPLAIN ANALYZE SELECT * FROM users;").parse_statement(),
+            Ok(Statement::Explain {
+                statement: Box::new(
+                    Parser::new("SELECT * FROM users;")
+                        .parse_statement()
+                        .unwrap()
+                ),
+                analyze: true,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_vacuum() {
+        assert_eq!(
+            Parser::new("VACUUM;").parse_statement(),
+            Ok(Statement::Vacuum)
+        );
+    }
+
+    #[test]
+    fn parse_alter_table_drop_column() {
+        for sql in [
+            "ALTER TABLE users DROP COLUMN email;",
+            "ALTER TABLE users DROP email;",
+        ] {
+            assert_eq!(
+                Parser::new(sql).parse_statement(),
+                Ok(Statement::Alter(Alter::DropColumn {
+                    table: "users".into(),
+                    column: "email".into(),
+                }))
+            );
+        }
+    }
+
+    #[test]
+    fn parse_alter_table_rename() {
+        for sql in [
+            "ALTER TABLE users RENAME COLUMN email TO mail;",
+            "ALTER TABLE users RENAME email TO mail;",
+        ] {
+            assert_eq!(
+                Parser::new(sql).parse_statement(),
+                Ok(Statement::Alter(Alter::RenameColumn {
+                    table: "users".into(),
+                    column: "email".into(),
+                    new_name: "mail".into(),
+                }))
+            );
+        }
+
+        assert_eq!(
+            Parser::new("ALTER TABLE users RENAME TO customers;").parse_statement(),
+            Ok(Statement::Alter(Alter::RenameTable {
+                table: "users".into(),
+                new_name: "customers".into(),
+            }))
+        );
+    }
+
+    #[test]
+    fn parse_non_reserved_keywords_as_table_names() {
+        assert_eq!(
+            Parser::new("SELECT * FROM order;").parse_statement(),
+            Ok(Statement::Select {
+                distinct: false,
+                columns: vec![Expression::Wildcard.into()],
+                from: Some("order".into()),
+                joins: vec![],
+                r#where: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            })
+        );
+
+        assert_eq!(
+            Parser::new("INSERT INTO start (id) VALUES (1);").parse_statement(),
+            Ok(Statement::Insert {
+                into: "start".into(),
+                columns: vec!["id".into()],
+                values: InsertSource::Values(vec![vec![Expression::Value(Value::Number(1))]]),
+            })
+        );
+
+        assert_eq!(
+            Parser::new("UPDATE commit SET id = 1;").parse_statement(),
+            Ok(Statement::Update {
+                table: "commit".into(),
+                columns: vec![Assignment {
+                    identifier: "id".into(),
+                    value: Expression::Value(Value::Number(1)),
+                }],
+                r#where: None,
+            })
+        );
+
+        assert_eq!(
+            Parser::new("DROP TABLE transaction;").parse_statement(),
+            Ok(Statement::Drop(Drop::Table {
+                name: "transaction".into(),
+                if_exists: false
+            }))
+        );
+    }
+
+    #[test]
+    fn collect_errors_of_multiple_statements() {
+        let sql = "DELETE users WHERE id = 1; DROP TABLE test;\nDROP TABLE;";
+
+        let errors = Parser::new(sql)
+            .parse_script_collecting_errors()
+            .unwrap_err();
+
+        assert_eq!(
+            errors
+                .iter()
+                .map(|error| (&error.kind, error.location.line, error.location.col))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    &ErrorKind::Expected {
+                        expected: Token::Keyword(Keyword::From),
+                        found: Token::Identifier("users".into()),
+                    },
+                    1,
+                    8
+                ),
+                (
+                    &ErrorKind::Expected {
+                        expected: Token::Identifier(Default::default()),
+                        found: Token::SemiColon,
+                    },
+                    2,
+                    11
+                ),
+            ]
+        );
+
+        assert_eq!(
+            Parser::new("DROP TABLE test; DROP TABLE users;").parse_script_collecting_errors(),
+            Parser::new("DROP TABLE test; DROP TABLE users;")
+                .parse_script()
+                .map_err(|error| vec![error])
+        );
+    }
+
     #[test]
     fn parse_multiple_statements() {
         let sql = r#"
@@ -1166,9 +3066,12 @@ mod tests {
         "#;
 
         assert_eq!(
-            Parser::new(sql).try_parse(),
+            Parser::new(sql).parse_script(),
             Ok(vec![
-                Statement::Drop(Drop::Table("test".into())),
+                Statement::Drop(Drop::Table {
+                    name: "test".into(),
+                    if_exists: false
+                }),
                 Statement::Update {
                     table: "users".into(),
                     columns: vec![Assignment {
@@ -1178,30 +3081,79 @@ mod tests {
                     r#where: None,
                 },
                 Statement::Select {
-                    columns: vec![Expression::Wildcard],
-                    from: "products".into(),
+                    distinct: false,
+                    columns: vec![Expression::Wildcard.into()],
+                    from: Some("products".into()),
+                    joins: vec![],
                     r#where: None,
+                    group_by: vec![],
+                    having: None,
                     order_by: vec![],
+                    limit: None,
+                    offset: None,
                 }
             ])
         )
     }
 
+    #[test]
+    fn parse_savepoints() {
+        let sql = "SAVEPOINT a; ROLLBACK TO a; ROLLBACK TO SAVEPOINT a; ROLLBACK;";
+
+        assert_eq!(
+            Parser::new(sql).parse_script(),
+            Ok(vec![
+                Statement::Savepoint("a".into()),
+                Statement::RollbackTo("a".into()),
+                Statement::RollbackTo("a".into()),
+                Statement::Rollback,
+            ])
+        );
+    }
+
     #[test]
     fn parse_explain() {
         let sql = "EXPLAIN SELECT name, email FROM users ORDER BY email;";
 
         assert_eq!(
             Parser::new(sql).parse_statement(),
-            Ok(Statement::Explain(Box::new(Statement::Select {
-                columns: vec![
-                    Expression::Identifier("name".into()),
-                    Expression::Identifier("email".into())
-                ],
-                from: "users".into(),
-                r#where: None,
-                order_by: vec![Expression::Identifier("email".into())]
-            })))
+            Ok(Statement::Explain {
+                statement: Box::new(Statement::Select {
+                    distinct: false,
+                    columns: vec![
+                        Expression::Identifier("name".into()).into(),
+                        Expression::Identifier("email".into()).into()
+                    ],
+                    from: Some("users".into()),
+                    joins: vec![],
+                    r#where: None,
+                    group_by: vec![],
+                    having: None,
+                    order_by: vec![OrderBy {
+                        expr: Expression::Identifier("email".into()),
+                        direction: Direction::Asc,
+                    }],
+                    limit: None,
+                    offset: None,
+                }),
+                analyze: false,
+            })
+        )
+    }
+
+    #[test]
+    fn parse_explain_analyze() {
+        let sql = "EXPLAIN ANALYZE DELETE FROM users;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Ok(Statement::Explain {
+                statement: Box::new(Statement::Delete {
+                    from: "users".into(),
+                    r#where: None,
+                }),
+                analyze: true,
+            })
         )
     }
 
@@ -1354,7 +3306,11 @@ mod tests {
                     expected: Token::SemiColon,
                     found: Token::Eof
                 },
-                location: Location { line: 1, col: 20 },
+                location: Location {
+                    line: 1,
+                    col: 20,
+                    byte_offset: 19
+                },
                 input: sql.to_owned(),
             })
         )
@@ -1379,7 +3335,11 @@ mod tests {
                     ],
                     found: Token::Eof
                 },
-                location: Location { line: 1, col: 7 },
+                location: Location {
+                    line: 1,
+                    col: 7,
+                    byte_offset: 6
+                },
                 input: sql.to_owned(),
             })
         )
@@ -1396,7 +3356,11 @@ mod tests {
                     expected: Token::Keyword(Keyword::Into),
                     found: Token::Eof
                 },
-                location: Location { line: 1, col: 7 },
+                location: Location {
+                    line: 1,
+                    col: 7,
+                    byte_offset: 6
+                },
                 input: sql.to_owned(),
             })
         )
@@ -1413,7 +3377,11 @@ mod tests {
                     expected: Parser::tokens_from_keywords(&Parser::supported_statements()),
                     found: Token::Div,
                 },
-                location: Location { line: 1, col: 1 },
+                location: Location {
+                    line: 1,
+                    col: 1,
+                    byte_offset: 0
+                },
                 input: sql.to_owned(),
             })
         )
@@ -1430,7 +3398,11 @@ mod tests {
                     expected: Parser::tokens_from_keywords(&Parser::supported_statements()),
                     found: Token::Keyword(Keyword::Varchar),
                 },
-                location: Location { line: 1, col: 1 },
+                location: Location {
+                    line: 1,
+                    col: 1,
+                    byte_offset: 0
+                },
                 input: sql.to_owned(),
             }),
         )
@@ -1455,7 +3427,11 @@ mod tests {
                     ],
                     found: Token::RightParen,
                 },
-                location: Location { line: 1, col: 8 },
+                location: Location {
+                    line: 1,
+                    col: 8,
+                    byte_offset: 7
+                },
                 input: sql.to_owned(),
             })
         )
@@ -1463,16 +3439,20 @@ mod tests {
 
     #[test]
     fn expect_keyword() {
-        let sql = "SELECT * VALUES users";
+        let sql = "INSERT VALUES users";
 
         assert_eq!(
             Parser::new(sql).parse_statement(),
             Err(ParserError {
                 kind: ErrorKind::Expected {
-                    expected: Token::Keyword(Keyword::From),
+                    expected: Token::Keyword(Keyword::Into),
                     found: Token::Keyword(Keyword::Values)
                 },
-                location: Location { line: 1, col: 10 },
+                location: Location {
+                    line: 1,
+                    col: 8,
+                    byte_offset: 7
+                },
                 input: sql.to_owned(),
             })
         )
@@ -1489,10 +3469,15 @@ mod tests {
                     expected: vec![
                         Token::Keyword(Keyword::Database),
                         Token::Keyword(Keyword::Table),
+                        Token::Keyword(Keyword::Index),
                     ],
                     found: Token::Keyword(Keyword::Values)
                 },
-                location: Location { line: 1, col: 6 },
+                location: Location {
+                    line: 1,
+                    col: 6,
+                    byte_offset: 5
+                },
                 input: sql.to_owned(),
             })
         )
@@ -1509,7 +3494,11 @@ mod tests {
                     expected: Parser::tokens_from_keywords(&Parser::supported_data_types()),
                     found: Token::Identifier("INCORRECT".into())
                 },
-                location: Location { line: 1, col: 23 },
+                location: Location {
+                    line: 1,
+                    col: 23,
+                    byte_offset: 22
+                },
                 input: sql.to_owned(),
             })
         )
@@ -1526,7 +3515,32 @@ mod tests {
                     expected: Token::Identifier(Default::default()),
                     found: Token::Number("1".into())
                 },
-                location: Location { line: 1, col: 13 },
+                location: Location {
+                    line: 1,
+                    col: 13,
+                    byte_offset: 12
+                },
+                input: sql.to_owned(),
+            })
+        )
+    }
+
+    #[test]
+    fn reserved_keyword_is_not_identifier() {
+        let sql = "SELECT * FROM select;";
+
+        assert_eq!(
+            Parser::new(sql).parse_statement(),
+            Err(ParserError {
+                kind: ErrorKind::Expected {
+                    expected: Token::Identifier(Default::default()),
+                    found: Token::Keyword(Keyword::Select)
+                },
+                location: Location {
+                    line: 1,
+                    col: 15,
+                    byte_offset: 14
+                },
                 input: sql.to_owned(),
             })
         )
@@ -1543,7 +3557,11 @@ mod tests {
                     expected: Token::Number(Default::default()),
                     found: Token::Identifier("test".into())
                 },
-                location: Location { line: 1, col: 33 },
+                location: Location {
+                    line: 1,
+                    col: 33,
+                    byte_offset: 32
+                },
                 input: sql.to_owned(),
             })
         )
@@ -1560,7 +3578,11 @@ mod tests {
                     expected: Token::LeftParen,
                     found: Token::Identifier("id".into())
                 },
-                location: Location { line: 1, col: 20 },
+                location: Location {
+                    line: 1,
+                    col: 20,
+                    byte_offset: 19
+                },
                 input: sql.to_owned(),
             })
         )
@@ -1,7 +1,10 @@
 // Final step in the SQL pipeline before plan generation.
 
-use super::statement::{Expression, Statement, Value};
-use crate::db::{DatabaseContext, DbError, ROW_ID_COL};
+use super::{
+    analyzer::{implicit_cast, resolve_column_references},
+    statement::{Expression, InsertSource, SelectItem, Statement, Value},
+};
+use crate::db::{DatabaseContext, DbError, Schema, ROW_ID_COL};
 
 /// Takes a statement and prepares it for plan generation.
 ///
@@ -39,34 +42,51 @@ use crate::db::{DatabaseContext, DbError, ROW_ID_COL};
 /// Also prepends the "row_id" column and value in the insert statement. Not
 /// sure if we should do that now or wait until we execute the plan.
 ///
-/// Additionally, we should deal with default values and auto-increment keys or
-/// stuff like that here.
+/// Before all that, column references are resolved to the exact names of the
+/// table schema, so `users.id` becomes `id` when there are no joins. See
+/// [`resolve_column_references`].
+///
+/// Columns omitted in insert statements take their `DEFAULT` value here as
+/// well.
 pub(crate) fn prepare(
     statement: &mut Statement,
     ctx: &mut impl DatabaseContext,
 ) -> Result<(), DbError> {
+    resolve_column_references(statement, ctx)?;
+
     match statement {
-        Statement::Select { columns, from, .. }
-            if columns.iter().any(|expr| *expr == Expression::Wildcard) =>
-        {
-            let metadata = ctx.table_metadata(from)?;
-
-            let identifiers = metadata
-                .schema
-                .columns
-                .iter()
-                .filter(|&col| col.name != ROW_ID_COL)
-                .cloned()
-                .map(|col| Expression::Identifier(col.name))
-                .collect::<Vec<Expression>>();
+        Statement::Select {
+            columns,
+            from,
+            joins,
+            ..
+        } if columns.iter().any(|item| item.expr == Expression::Wildcard) => {
+            let mut identifiers = Vec::new();
+
+            // Joined tables use qualified column names. See the analyzer.
+            for table in from.iter().chain(joins.iter().map(|join| &join.table)) {
+                let columns = &ctx.table_metadata(table)?.schema.columns;
+                let mut schema = Schema::from(columns.iter().filter(|col| col.name != ROW_ID_COL));
+
+                if !joins.is_empty() {
+                    schema = schema.qualified(table);
+                }
+
+                identifiers.extend(
+                    schema
+                        .columns
+                        .into_iter()
+                        .map(|col| SelectItem::from(Expression::Identifier(col.name))),
+                );
+            }
 
             let mut resolved_wildcards = Vec::new();
 
-            for expr in columns.drain(..) {
-                if expr == Expression::Wildcard {
+            for item in columns.drain(..) {
+                if item.expr == Expression::Wildcard {
                     resolved_wildcards.extend(identifiers.iter().cloned());
                 } else {
-                    resolved_wildcards.push(expr);
+                    resolved_wildcards.push(item);
                 }
             }
 
@@ -76,33 +96,111 @@ pub(crate) fn prepare(
         Statement::Insert {
             into,
             columns,
-            values,
+            values: InsertSource::Values(values),
         } => {
+            let implicit_casts = ctx.implicit_casts();
             let metadata = ctx.table_metadata(into)?;
 
             // Columns are optional so this means the user didn't specify them.
-            // We'll replace the empty Vec with the schema columns.
-            if columns.is_empty() {
+            // We'll replace the empty Vec with the schema columns. Empty
+            // values mean that all the columns take their default value.
+            if columns.is_empty() && values.iter().any(|row| !row.is_empty()) {
                 *columns = metadata.schema.column_identifiers();
             }
 
+            // The analyzer already made sure that omitted columns have a
+            // default value.
+            for col in &metadata.schema.columns {
+                if let Some(default) = &col.default {
+                    if !columns.contains(&col.name) {
+                        columns.push(col.name.clone());
+                        values.iter_mut().for_each(|row| row.push(default.clone()));
+                    }
+                }
+            }
+
             if metadata.schema.columns[0].name == ROW_ID_COL {
-                if columns[0] != ROW_ID_COL {
+                if columns.is_empty() || columns[0] != ROW_ID_COL {
                     columns.insert(0, ROW_ID_COL.into());
                 }
-                let row_id = metadata.next_row_id();
-                values.insert(0, Expression::Value(Value::Number(row_id.into())));
+                for row in values.iter_mut() {
+                    let row_id = metadata.next_row_id();
+                    row.insert(0, Expression::Value(Value::Number(row_id.into())));
+                }
             }
 
             for current_index in 0..metadata.schema.len() {
                 let sorted_index = metadata.schema.index_of(&columns[current_index]).unwrap();
                 columns.swap(current_index, sorted_index);
-                values.swap(current_index, sorted_index);
+                values
+                    .iter_mut()
+                    .for_each(|row| row.swap(current_index, sorted_index));
+            }
+
+            // The analyzer already made sure the casts are valid.
+            if implicit_casts {
+                for row in values.iter_mut() {
+                    for (value, col) in row.iter_mut().zip(&metadata.schema.columns) {
+                        if let Some(cast) = implicit_cast(value, &col.data_type) {
+                            *value = cast;
+                        }
+                    }
+                }
             }
         }
 
-        Statement::Explain(inner) => {
-            prepare(&mut *inner, ctx)?;
+        Statement::Insert {
+            into,
+            columns,
+            values: InsertSource::Select(select),
+        } => {
+            prepare(select, ctx)?;
+
+            let implicit_casts = ctx.implicit_casts();
+            let metadata = ctx.table_metadata(into)?;
+
+            let Statement::Select { columns: items, .. } = select.as_mut() else {
+                unreachable!("INSERT source is not a SELECT statement: {select}");
+            };
+
+            let insertable_columns = Vec::from_iter(
+                metadata
+                    .schema
+                    .columns
+                    .iter()
+                    .filter(|col| col.name != ROW_ID_COL),
+            );
+
+            if columns.is_empty() {
+                *columns = Vec::from_iter(insertable_columns.iter().map(|col| col.name.clone()));
+            }
+
+            // Same as the VALUES case but reordering the SELECT list instead.
+            // Omitted columns are computed by the SELECT as well using their
+            // default value. Row IDs are generated when the rows are inserted.
+            let mut sorted_items = Vec::with_capacity(items.len());
+
+            for col in &insertable_columns {
+                let mut item = match columns.iter().position(|name| name == &col.name) {
+                    Some(index) => items[index].clone(),
+                    None => SelectItem::from(col.default.clone().unwrap()),
+                };
+
+                if implicit_casts {
+                    if let Some(cast) = implicit_cast(&item.expr, &col.data_type) {
+                        item.expr = cast;
+                    }
+                }
+
+                sorted_items.push(item);
+            }
+
+            *items = sorted_items;
+            *columns = Vec::from_iter(insertable_columns.iter().map(|col| col.name.clone()));
+        }
+
+        Statement::Explain { statement, .. } => {
+            prepare(&mut *statement, ctx)?;
         }
 
         _ => {} // Nothing to do here.
@@ -165,4 +263,34 @@ mod tests {
             prepared: "INSERT INTO users(row_id, id, name, age, email) VALUES (1, 1, 'John Doe', 20, 'john@mail.com');"
         })
     }
+
+    #[test]
+    fn prepare_insert_statement_with_multiple_rows() -> Result<(), DbError> {
+        assert_prep(Prep {
+            setup: &["CREATE TABLE users (id INT, name VARCHAR(255) DEFAULT 'Anonymous', age INT UNSIGNED);"],
+            raw_stmt: "INSERT INTO users(age, id) VALUES (20, 1), (30, 2);",
+            prepared: "INSERT INTO users(row_id, id, name, age) VALUES (1, 1, 'Anonymous', 20), (2, 2, 'Anonymous', 30);"
+        })
+    }
+
+    #[test]
+    fn prepare_insert_select_statement() -> Result<(), DbError> {
+        assert_prep(Prep {
+            setup: &[
+                "CREATE TABLE users (id INT, name VARCHAR(255) DEFAULT 'Anonymous', age INT UNSIGNED);",
+                "CREATE TABLE people (id INT, age INT UNSIGNED);",
+            ],
+            raw_stmt: "INSERT INTO users(age, id) SELECT * FROM people;",
+            prepared: "INSERT INTO users(id, name, age) SELECT age, 'Anonymous', id FROM people;",
+        })
+    }
+
+    #[test]
+    fn prepare_insert_statement_with_default_values() -> Result<(), DbError> {
+        assert_prep(Prep {
+            setup: &["CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255) DEFAULT 'Anonymous', age INT DEFAULT 18);"],
+            raw_stmt: "INSERT INTO users(age, id) VALUES (20, 1);",
+            prepared: "INSERT INTO users(row_id, id, name, age) VALUES (1, 1, 'Anonymous', 20);",
+        })
+    }
 }
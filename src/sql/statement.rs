@@ -6,15 +6,22 @@
 use std::fmt::{self, Display, Write};
 
 /// SQL statement.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub(crate) enum Statement {
     Create(Create),
 
     Select {
-        columns: Vec<Expression>,
-        from: String,
+        distinct: bool,
+        columns: Vec<SelectItem>,
+        /// [`None`] for constant queries like `SELECT 1 + 1`.
+        from: Option<String>,
+        joins: Vec<Join>,
         r#where: Option<Expression>,
-        order_by: Vec<Expression>,
+        group_by: Vec<Expression>,
+        having: Option<Expression>,
+        order_by: Vec<OrderBy>,
+        limit: Option<u64>,
+        offset: Option<u64>,
     },
 
     Delete {
@@ -31,18 +38,39 @@ pub(crate) enum Statement {
     Insert {
         into: String,
         columns: Vec<String>,
-        values: Vec<Expression>,
+        values: InsertSource,
     },
 
     Drop(Drop),
 
+    Alter(Alter),
+
     StartTransaction,
 
     Rollback,
 
     Commit,
 
-    Explain(Box<Self>),
+    /// `SAVEPOINT name` marks a point inside the current transaction.
+    Savepoint(String),
+
+    /// `ROLLBACK TO [SAVEPOINT] name` undoes everything done after the
+    /// savepoint without ending the transaction.
+    RollbackTo(String),
+
+    Explain {
+        statement: Box<Self>,
+        /// `EXPLAIN ANALYZE` executes the statement and collects stats.
+        analyze: bool,
+    },
+
+    /// `ANALYZE table` collects the statistics of a table. See
+    /// [`crate::db::TableStats`].
+    Analyze(String),
+
+    /// `VACUUM` moves the pages at the end of the database file into the free
+    /// pages left by deleted rows and drops the free list.
+    Vacuum,
 }
 
 /// Expressions used in select, update, delete and insert statements.
@@ -50,6 +78,12 @@ pub(crate) enum Statement {
 pub(crate) enum Expression {
     Identifier(String),
 
+    /// Column name qualified with its table name like `users.id`.
+    QualifiedIdentifier {
+        table: String,
+        column: String,
+    },
+
     Value(Value),
 
     Wildcard,
@@ -66,6 +100,106 @@ pub(crate) enum Expression {
     },
 
     Nested(Box<Self>),
+
+    /// Parenthesized list of two or more expressions like `(a, b)`, also
+    /// known as a row value.
+    ///
+    /// For now tuples can only be compared to other tuples of the same length
+    /// using `=` or `!=`, ordering comparisons like `(a, b) < (1, 2)` are not
+    /// supported.
+    Tuple(Vec<Self>),
+
+    /// `expr IN (list)` or `expr NOT IN (list)`. The list can be empty, in
+    /// which case the expression is always false (or true if negated).
+    InList {
+        expr: Box<Self>,
+        list: Vec<Self>,
+        negated: bool,
+    },
+
+    /// `expr BETWEEN low AND high` or `expr NOT BETWEEN low AND high`. Both
+    /// bounds are inclusive.
+    Between {
+        expr: Box<Self>,
+        low: Box<Self>,
+        high: Box<Self>,
+        negated: bool,
+    },
+
+    /// `expr IS NULL` or `expr IS NOT NULL`.
+    IsNull {
+        expr: Box<Self>,
+        negated: bool,
+    },
+
+    /// Aggregate function call like `COUNT(*)` or `MAX(age)`.
+    Aggregate {
+        func: AggregateFunction,
+        arg: Box<Self>,
+        /// `COUNT(DISTINCT email)` only feeds unique values to the function.
+        distinct: bool,
+    },
+
+    /// Built-in scalar function call like `UPPER(name)`.
+    Function {
+        func: ScalarFunction,
+        args: Vec<Self>,
+    },
+
+    /// `CASE WHEN condition THEN result [...] [ELSE result] END`.
+    ///
+    /// Evaluates to the result of the first branch whose condition is true
+    /// or to the `ELSE` result if none of them are.
+    Case {
+        branches: Vec<(Self, Self)>,
+        else_branch: Option<Box<Self>>,
+    },
+}
+
+/// Aggregate functions. See [`Expression::Aggregate`].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum AggregateFunction {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+/// Built-in scalar functions. See [`Expression::Function`].
+///
+/// Unlike [`AggregateFunction`] these are computed for every single tuple.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum ScalarFunction {
+    Upper,
+    Lower,
+    Length,
+    Trim,
+    Substr,
+    Abs,
+    Mod,
+    Power,
+}
+
+impl ScalarFunction {
+    /// Registry of built-in functions.
+    ///
+    /// Maps an uppercase function `name` to its [`ScalarFunction`] variant.
+    /// Adding a new function starts here, the compiler will point out the
+    /// rest of the places that need to know about it.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "UPPER" => Self::Upper,
+            "LOWER" => Self::Lower,
+            "LENGTH" => Self::Length,
+            "TRIM" => Self::Trim,
+            "SUBSTR" => Self::Substr,
+            "ABS" => Self::Abs,
+            "MOD" => Self::Mod,
+            "POWER" => Self::Power,
+            _ => return None,
+        })
+    }
 }
 
 /// Binary operators used in expressions.
@@ -81,8 +215,13 @@ pub(crate) enum BinaryOperator {
     Minus,
     Mul,
     Div,
+    /// String concatenation.
+    Concat,
     And,
     Or,
+    /// Pattern matching where `%` matches any sequence of characters and `_`
+    /// matches exactly one character.
+    Like,
 }
 
 /// Unary operators used in expressions.
@@ -90,6 +229,16 @@ pub(crate) enum BinaryOperator {
 pub(crate) enum UnaryOperator {
     Plus,
     Minus,
+    Not,
+}
+
+/// Rows inserted by `INSERT` statements.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum InsertSource {
+    /// `INSERT INTO table VALUES (...), (...)`.
+    Values(Vec<Vec<Expression>>),
+    /// `INSERT INTO table SELECT ...`. Always contains [`Statement::Select`].
+    Select(Box<Statement>),
 }
 
 /// SQL constraints.
@@ -97,6 +246,7 @@ pub(crate) enum UnaryOperator {
 pub(crate) enum Constraint {
     PrimaryKey,
     Unique,
+    NotNull,
 }
 
 /// SQL Data types.
@@ -108,6 +258,15 @@ pub(crate) enum DataType {
     UnsignedBigInt,
     Bool,
     Varchar(usize),
+    /// Fixed length string that always takes `n` bytes on disk.
+    ///
+    /// Shorter strings are padded with spaces and the padding is removed when
+    /// reading them back, so trailing spaces are not significant. The length
+    /// is measured in bytes, not characters, which means only ASCII strings
+    /// can make use of all the `n` characters.
+    Char(usize),
+    /// 8 byte IEEE-754 floating point number. `FLOAT` is an alias.
+    Double,
 }
 
 /// Resolved values from expressions.
@@ -134,6 +293,17 @@ pub enum Value {
     /// It's a toy database anyway, not that anyone is gonna run into integer
     /// overflow issues in production :)
     Number(i128),
+
+    /// Floating point number.
+    Float(f64),
+}
+
+/// Single expression of the `SELECT` column list with its optional alias,
+/// like `age + 1 AS next_age`.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct SelectItem {
+    pub expr: Expression,
+    pub alias: Option<String>,
 }
 
 /// Assignments found in `UPDATE` statements.
@@ -143,12 +313,47 @@ pub(crate) struct Assignment {
     pub value: Expression,
 }
 
+/// Sort direction of `ORDER BY` keys.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub(crate) enum Direction {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// `JOIN` clause of `SELECT` statements, like `JOIN orders ON users.id =
+/// orders.user_id`.
+///
+/// Only inner joins are supported for now, so `INNER JOIN` and `JOIN` mean the
+/// same thing.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Join {
+    pub table: String,
+    pub on: Expression,
+}
+
+/// Single key of an `ORDER BY` clause, like `age DESC`.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct OrderBy {
+    pub expr: Expression,
+    pub direction: Direction,
+}
+
+/// Key column of a `CREATE INDEX` statement, like `created_at DESC`.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct IndexColumn {
+    pub name: String,
+    pub direction: Direction,
+}
+
 /// Column definitions from `INSERT` statements.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Column {
     pub name: String,
     pub data_type: DataType,
     pub constraints: Vec<Constraint>,
+    /// Value used when `INSERT` statements don't specify this column.
+    pub(crate) default: Option<Expression>,
 }
 
 impl Column {
@@ -157,6 +362,7 @@ impl Column {
             name: name.into(),
             data_type,
             constraints: vec![],
+            default: None,
         }
     }
 
@@ -165,6 +371,7 @@ impl Column {
             name: name.into(),
             data_type,
             constraints: vec![Constraint::PrimaryKey],
+            default: None,
         }
     }
 
@@ -173,31 +380,140 @@ impl Column {
             name: name.into(),
             data_type,
             constraints: vec![Constraint::Unique],
+            default: None,
         }
     }
+
+    /// Returns `true` if this column can't store `NULL` values. Primary keys
+    /// are implicitly `NOT NULL`.
+    pub fn is_not_null(&self) -> bool {
+        self.constraints
+            .iter()
+            .any(|constraint| matches!(constraint, Constraint::PrimaryKey | Constraint::NotNull))
+    }
 }
 
 /// `CREATE` statement.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub(crate) enum Create {
     Database(String),
     Table {
         name: String,
         columns: Vec<Column>,
+        if_not_exists: bool,
     },
     Index {
         name: String,
         table: String,
-        column: String,
+        columns: Vec<IndexColumn>,
         unique: bool,
     },
 }
 
 /// `DROP` statement.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub(crate) enum Drop {
-    Table(String),
+    Table {
+        name: String,
+        if_exists: bool,
+    },
     Database(String),
+    Index {
+        name: String,
+        table: Option<String>,
+        if_exists: bool,
+    },
+}
+
+/// `ALTER TABLE` statement.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Alter {
+    DropColumn {
+        table: String,
+        column: String,
+    },
+    RenameColumn {
+        table: String,
+        column: String,
+        new_name: String,
+    },
+    RenameTable {
+        table: String,
+        new_name: String,
+    },
+}
+
+impl Expression {
+    /// Returns all the [`Expression::Aggregate`] nodes found in this
+    /// expression tree from left to right.
+    ///
+    /// Arguments of aggregate functions are not visited, so nested aggregates
+    /// like `MAX(COUNT(*))` only return the outer call.
+    pub fn aggregates(&self) -> Vec<&Self> {
+        let mut aggregates = Vec::new();
+        self.collect_aggregates(&mut aggregates);
+
+        aggregates
+    }
+
+    /// Returns `true` if there's at least one aggregate function call in this
+    /// expression tree.
+    pub fn contains_aggregate(&self) -> bool {
+        !self.aggregates().is_empty()
+    }
+
+    /// See [`Self::aggregates`].
+    fn collect_aggregates<'e>(&'e self, aggregates: &mut Vec<&'e Self>) {
+        match self {
+            Self::Aggregate { .. } => aggregates.push(self),
+
+            Self::BinaryOperation { left, right, .. } => {
+                left.collect_aggregates(aggregates);
+                right.collect_aggregates(aggregates);
+            }
+
+            Self::UnaryOperation { expr, .. } | Self::Nested(expr) | Self::IsNull { expr, .. } => {
+                expr.collect_aggregates(aggregates);
+            }
+
+            Self::InList { expr, list, .. } => {
+                expr.collect_aggregates(aggregates);
+                list.iter()
+                    .for_each(|item| item.collect_aggregates(aggregates));
+            }
+
+            Self::Between {
+                expr, low, high, ..
+            } => {
+                expr.collect_aggregates(aggregates);
+                low.collect_aggregates(aggregates);
+                high.collect_aggregates(aggregates);
+            }
+
+            Self::Function { args, .. } | Self::Tuple(args) => args
+                .iter()
+                .for_each(|arg| arg.collect_aggregates(aggregates)),
+
+            Self::Case {
+                branches,
+                else_branch,
+            } => {
+                for (condition, result) in branches {
+                    condition.collect_aggregates(aggregates);
+                    result.collect_aggregates(aggregates);
+                }
+
+                if let Some(else_branch) = else_branch {
+                    else_branch.collect_aggregates(aggregates);
+                }
+            }
+
+            Self::Identifier(_)
+            | Self::QualifiedIdentifier { .. }
+            | Self::Value(_)
+            | Self::Wildcard => {}
+        }
+    }
 }
 
 /// Optimized version of [`std::slice::Join`] with no intermediary [`Vec`] and
@@ -229,12 +545,18 @@ impl PartialOrd for Value {
     ///
     /// The codebases uses the [`None`] value as a "type error" when comparing
     /// values, but type errors should never happen because the
-    /// [`super::analyzer`] must catch all of them. If we add a float type
-    /// (which does not form a total order) then we should add a custom
-    /// `try_partial_cmp` method to values in order to avoid confusion.
+    /// [`super::analyzer`] must catch all of them. Integers and floats can be
+    /// compared with each other, the integer is converted to a float first.
+    ///
+    /// TODO: Floats don't form a total order, so `NaN` also returns [`None`]
+    /// here. We should add a custom `try_partial_cmp` method to values in
+    /// order to avoid confusion.
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         match (self, other) {
             (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+            (Value::Number(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
+            (Value::Float(a), Value::Number(b)) => a.partial_cmp(&(*b as f64)),
             (Value::String(a), Value::String(b)) => a.partial_cmp(b),
             (Value::Bool(a), Value::Bool(b)) => a.partial_cmp(b),
             _ => None,
@@ -246,12 +568,40 @@ impl Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Value::Number(number) => write!(f, "{number}"),
+            // Keep the decimal point so that the output can be parsed back
+            // into a float.
+            Value::Float(float) if float.is_finite() && float.fract() == 0.0 => {
+                write!(f, "{float}.0")
+            }
+            Value::Float(float) => write!(f, "{float}"),
             Value::String(string) => write!(f, "\"{string}\""),
             Value::Bool(bool) => f.write_str(if *bool { "TRUE" } else { "FALSE" }),
         }
     }
 }
 
+impl DataType {
+    /// Suggested number of characters needed to display any value of this
+    /// type.
+    ///
+    /// Integers include the sign when they can be negative and booleans are
+    /// as wide as `FALSE`. Useful for rendering tables without looking at the
+    /// rows first.
+    pub fn display_width(&self) -> usize {
+        match self {
+            Self::Int => i32::MIN.to_string().len(),
+            Self::UnsignedInt => u32::MAX.to_string().len(),
+            Self::BigInt => i64::MIN.to_string().len(),
+            Self::UnsignedBigInt => u64::MAX.to_string().len(),
+            Self::Bool => "FALSE".len(),
+            Self::Varchar(max) | Self::Char(max) => *max,
+            // Longest shortest representation of an f64, like
+            // `-2.2250738585072014e-308`.
+            Self::Double => 24,
+        }
+    }
+}
+
 impl Display for DataType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -261,6 +611,8 @@ impl Display for DataType {
             DataType::UnsignedBigInt => f.write_str("BIGINT UNSIGNED"),
             DataType::Bool => f.write_str("BOOL"),
             DataType::Varchar(max) => write!(f, "VARCHAR({max})"),
+            DataType::Char(length) => write!(f, "CHAR({length})"),
+            DataType::Double => f.write_str("DOUBLE"),
         }
     }
 }
@@ -269,11 +621,16 @@ impl Display for Column {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{} {}", self.name, self.data_type)?;
 
+        if let Some(default) = &self.default {
+            write!(f, " DEFAULT {default}")?;
+        }
+
         for constraint in &self.constraints {
             f.write_char(' ')?;
             f.write_str(match constraint {
                 Constraint::PrimaryKey => "PRIMARY KEY",
                 Constraint::Unique => "UNIQUE",
+                Constraint::NotNull => "NOT NULL",
             })?;
         }
 
@@ -287,6 +644,73 @@ impl Display for Assignment {
     }
 }
 
+impl Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+        })
+    }
+}
+
+impl From<Expression> for SelectItem {
+    fn from(expr: Expression) -> Self {
+        Self { expr, alias: None }
+    }
+}
+
+impl Display for SelectItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.expr)?;
+
+        if let Some(alias) = &self.alias {
+            write!(f, " AS {alias}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Join {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "JOIN {} ON {}", self.table, self.on)
+    }
+}
+
+impl Display for OrderBy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.expr)?;
+
+        // ASC is the default, no need to be verbose.
+        if self.direction == Direction::Desc {
+            write!(f, " {}", self.direction)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<&str> for IndexColumn {
+    fn from(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            direction: Direction::Asc,
+        }
+    }
+}
+
+impl Display for IndexColumn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)?;
+
+        if self.direction == Direction::Desc {
+            write!(f, " {}", self.direction)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl Display for BinaryOperator {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.write_str(match self {
@@ -300,17 +724,47 @@ impl Display for BinaryOperator {
             BinaryOperator::Minus => "-",
             BinaryOperator::Mul => "*",
             BinaryOperator::Div => "/",
+            BinaryOperator::Concat => "||",
             BinaryOperator::And => "AND",
             BinaryOperator::Or => "OR",
+            BinaryOperator::Like => "LIKE",
         })
     }
 }
 
 impl Display for UnaryOperator {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_char(match self {
-            UnaryOperator::Minus => '-',
-            UnaryOperator::Plus => '+',
+        f.write_str(match self {
+            UnaryOperator::Minus => "-",
+            UnaryOperator::Plus => "+",
+            UnaryOperator::Not => "NOT",
+        })
+    }
+}
+
+impl Display for ScalarFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Upper => "UPPER",
+            Self::Lower => "LOWER",
+            Self::Length => "LENGTH",
+            Self::Trim => "TRIM",
+            Self::Substr => "SUBSTR",
+            Self::Abs => "ABS",
+            Self::Mod => "MOD",
+            Self::Power => "POWER",
+        })
+    }
+}
+
+impl Display for AggregateFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Count => "COUNT",
+            Self::Sum => "SUM",
+            Self::Avg => "AVG",
+            Self::Min => "MIN",
+            Self::Max => "MAX",
         })
     }
 }
@@ -319,6 +773,7 @@ impl Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Identifier(ident) => f.write_str(ident),
+            Self::QualifiedIdentifier { table, column } => write!(f, "{table}.{column}"),
             Self::Value(value) => write!(f, "{value}"),
             Self::Wildcard => f.write_char('*'),
             Self::BinaryOperation {
@@ -328,10 +783,58 @@ impl Display for Expression {
             } => {
                 write!(f, "{left} {operator} {right}")
             }
+            Self::UnaryOperation {
+                operator: UnaryOperator::Not,
+                expr,
+            } => write!(f, "NOT {expr}"),
             Self::UnaryOperation { operator, expr } => {
                 write!(f, "{operator}{expr}")
             }
             Self::Nested(expr) => write!(f, "({expr})"),
+            Self::Tuple(items) => write!(f, "({})", join(items, ", ")),
+            Self::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let not = if *negated { "NOT " } else { "" };
+                write!(f, "{expr} {not}IN ({})", join(list, ", "))
+            }
+            Self::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => {
+                let not = if *negated { "NOT " } else { "" };
+                write!(f, "{expr} {not}BETWEEN {low} AND {high}")
+            }
+            Self::IsNull { expr, negated } => {
+                let not = if *negated { "NOT " } else { "" };
+                write!(f, "{expr} IS {not}NULL")
+            }
+            Self::Aggregate {
+                func,
+                arg,
+                distinct,
+            } => {
+                let distinct = if *distinct { "DISTINCT " } else { "" };
+                write!(f, "{func}({distinct}{arg})")
+            }
+            Self::Function { func, args } => write!(f, "{func}({})", join(args, ", ")),
+            Self::Case {
+                branches,
+                else_branch,
+            } => {
+                f.write_str("CASE")?;
+                for (condition, result) in branches {
+                    write!(f, " WHEN {condition} THEN {result}")?;
+                }
+                if let Some(else_branch) = else_branch {
+                    write!(f, " ELSE {else_branch}")?;
+                }
+                f.write_str(" END")
+            }
         }
     }
 }
@@ -340,8 +843,17 @@ impl Display for Statement {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Statement::Create(create) => match create {
-                Create::Table { name, columns } => {
-                    write!(f, "CREATE TABLE {name} ({})", join(columns, ", "))?;
+                Create::Table {
+                    name,
+                    columns,
+                    if_not_exists,
+                } => {
+                    let if_not_exists = if *if_not_exists { "IF NOT EXISTS " } else { "" };
+                    write!(
+                        f,
+                        "CREATE TABLE {if_not_exists}{name} ({})",
+                        join(columns, ", ")
+                    )?;
                 }
 
                 Create::Database(name) => {
@@ -351,27 +863,53 @@ impl Display for Statement {
                 Create::Index {
                     name,
                     table,
-                    column,
+                    columns,
                     unique,
                 } => {
                     let unique = if *unique { " UNIQUE " } else { " " };
-                    write!(f, "CREATE{unique}INDEX {name} ON {table}({column})")?;
+                    let columns = join(columns, ", ");
+                    write!(f, "CREATE{unique}INDEX {name} ON {table}({columns})")?;
                 }
             },
 
             Statement::Select {
+                distinct,
                 columns,
                 from,
+                joins,
                 r#where,
+                group_by,
+                having,
                 order_by,
+                limit,
+                offset,
             } => {
-                write!(f, "SELECT {} FROM {from}", join(columns, ", "))?;
+                let distinct = if *distinct { "DISTINCT " } else { "" };
+                write!(f, "SELECT {distinct}{}", join(columns, ", "))?;
+                if let Some(from) = from {
+                    write!(f, " FROM {from}")?;
+                }
+                for join in joins {
+                    write!(f, " {join}")?;
+                }
                 if let Some(expr) = r#where {
                     write!(f, " WHERE {expr}")?;
                 }
+                if !group_by.is_empty() {
+                    write!(f, " GROUP BY {}", join(group_by, ", "))?;
+                }
+                if let Some(expr) = having {
+                    write!(f, " HAVING {expr}")?;
+                }
                 if !order_by.is_empty() {
                     write!(f, " ORDER BY {}", join(order_by, ", "))?;
                 }
+                if let Some(limit) = limit {
+                    write!(f, " LIMIT {limit}")?;
+                }
+                if let Some(offset) = offset {
+                    write!(f, " OFFSET {offset}")?;
+                }
             }
 
             Statement::Delete { from, r#where } => {
@@ -403,20 +941,64 @@ impl Display for Statement {
                     format!(" ({}) ", join(columns, ", "))
                 };
 
-                write!(
-                    f,
-                    "INSERT INTO {into}{columns}VALUES ({})",
-                    join(values, ", ")
-                )?;
+                match values {
+                    InsertSource::Values(values) => {
+                        let rows = Vec::from_iter(
+                            values.iter().map(|row| format!("({})", join(row, ", "))),
+                        );
+
+                        write!(f, "INSERT INTO {into}{columns}VALUES {}", rows.join(", "))?;
+                    }
+
+                    // Nested statement already writes the semicolon.
+                    InsertSource::Select(select) => {
+                        return write!(f, "INSERT INTO {into}{columns}{select}");
+                    }
+                }
             }
 
             Statement::Drop(drop) => {
                 match drop {
-                    Drop::Table(name) => write!(f, "DROP TABLE {name}")?,
+                    Drop::Table { name, if_exists } => {
+                        let if_exists = if *if_exists { "IF EXISTS " } else { "" };
+                        write!(f, "DROP TABLE {if_exists}{name}")?;
+                    }
                     Drop::Database(name) => write!(f, "DROP DATABASE {name}")?,
+                    Drop::Index {
+                        name,
+                        table,
+                        if_exists,
+                    } => {
+                        let if_exists = if *if_exists { "IF EXISTS " } else { "" };
+                        write!(f, "DROP INDEX {if_exists}{name}")?;
+                        if let Some(table) = table {
+                            write!(f, " ON {table}")?;
+                        }
+                    }
                 };
             }
 
+            Statement::Alter(alter) => match alter {
+                Alter::DropColumn { table, column } => {
+                    write!(f, "ALTER TABLE {table} DROP COLUMN {column}")?;
+                }
+
+                Alter::RenameColumn {
+                    table,
+                    column,
+                    new_name,
+                } => {
+                    write!(
+                        f,
+                        "ALTER TABLE {table} RENAME COLUMN {column} TO {new_name}"
+                    )?;
+                }
+
+                Alter::RenameTable { table, new_name } => {
+                    write!(f, "ALTER TABLE {table} RENAME TO {new_name}")?;
+                }
+            },
+
             Statement::StartTransaction => {
                 f.write_str("START TRANSACTION")?;
             }
@@ -429,9 +1011,529 @@ impl Display for Statement {
                 f.write_str("ROLLBACK")?;
             }
 
-            Statement::Explain(statement) => write!(f, "EXPLAIN {statement}")?,
+            Statement::Savepoint(name) => {
+                write!(f, "SAVEPOINT {name}")?;
+            }
+
+            Statement::RollbackTo(name) => {
+                write!(f, "ROLLBACK TO SAVEPOINT {name}")?;
+            }
+
+            Statement::Explain { statement, analyze } => {
+                f.write_str("EXPLAIN ")?;
+                if *analyze {
+                    f.write_str("ANALYZE ")?;
+                }
+                write!(f, "{statement}")?;
+            }
+
+            Statement::Analyze(table) => {
+                write!(f, "ANALYZE {table}")?;
+            }
+
+            Statement::Vacuum => f.write_str("VACUUM")?,
         };
 
         f.write_char(';')
     }
 }
+
+impl Statement {
+    /// Generates SQL that can be parsed back into the exact same statement.
+    ///
+    /// Unlike the [`Display`] impl, which is only meant for humans reading
+    /// plans or errors, this quotes all identifiers with backticks and escapes
+    /// string values, so names like `order` or strings like `'it''s'` don't
+    /// break the output. Use this when the SQL is stored somewhere to be
+    /// executed again later or written to logs.
+    pub fn to_sql_safe(&self) -> String {
+        SafeSql(self).to_string()
+    }
+}
+
+/// Wrapper used to implement [`Statement::to_sql_safe`] on top of [`Display`].
+///
+/// The formatting logic mirrors the [`Display`] impls of the wrapped types,
+/// but identifiers and strings are always quoted.
+struct SafeSql<'s, T: ?Sized>(&'s T);
+
+/// Writes `string` between `quote` characters, doubling any quote found
+/// inside. This is the escape syntax understood by the tokenizer.
+fn write_quoted(f: &mut fmt::Formatter, string: &str, quote: char) -> fmt::Result {
+    f.write_char(quote)?;
+
+    for chr in string.chars() {
+        if chr == quote {
+            f.write_char(quote)?;
+        }
+        f.write_char(chr)?;
+    }
+
+    f.write_char(quote)
+}
+
+/// Same as [`join`] but wraps every value in [`SafeSql`].
+fn join_safe<'t, T>(values: &'t [T], separator: &str) -> String
+where
+    SafeSql<'t, T>: Display,
+{
+    join(&values.iter().map(SafeSql).collect::<Vec<_>>(), separator)
+}
+
+impl Display for SafeSql<'_, str> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_quoted(f, self.0, '`')
+    }
+}
+
+impl Display for SafeSql<'_, String> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", SafeSql(self.0.as_str()))
+    }
+}
+
+impl Display for SafeSql<'_, Value> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Value::String(string) => write_quoted(f, string, '\''),
+            other => write!(f, "{other}"),
+        }
+    }
+}
+
+impl Display for SafeSql<'_, Column> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", SafeSql(&self.0.name), self.0.data_type)?;
+
+        if let Some(default) = &self.0.default {
+            write!(f, " DEFAULT {}", SafeSql(default))?;
+        }
+
+        for constraint in &self.0.constraints {
+            f.write_char(' ')?;
+            f.write_str(match constraint {
+                Constraint::PrimaryKey => "PRIMARY KEY",
+                Constraint::Unique => "UNIQUE",
+                Constraint::NotNull => "NOT NULL",
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for SafeSql<'_, Assignment> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} = {}",
+            SafeSql(&self.0.identifier),
+            SafeSql(&self.0.value)
+        )
+    }
+}
+
+impl Display for SafeSql<'_, SelectItem> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", SafeSql(&self.0.expr))?;
+
+        if let Some(alias) = &self.0.alias {
+            write!(f, " AS {}", SafeSql(alias))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for SafeSql<'_, Join> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "JOIN {} ON {}",
+            SafeSql(&self.0.table),
+            SafeSql(&self.0.on)
+        )
+    }
+}
+
+impl Display for SafeSql<'_, OrderBy> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", SafeSql(&self.0.expr), self.0.direction)
+    }
+}
+
+impl Display for SafeSql<'_, IndexColumn> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", SafeSql(&self.0.name), self.0.direction)
+    }
+}
+
+impl Display for SafeSql<'_, Expression> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Expression::Identifier(ident) => write!(f, "{}", SafeSql(ident)),
+            Expression::QualifiedIdentifier { table, column } => {
+                write!(f, "{}.{}", SafeSql(table), SafeSql(column))
+            }
+            Expression::Value(value) => write!(f, "{}", SafeSql(value)),
+            Expression::Wildcard => f.write_char('*'),
+            Expression::BinaryOperation {
+                left,
+                operator,
+                right,
+            } => {
+                let (left, right) = (SafeSql(left.as_ref()), SafeSql(right.as_ref()));
+                write!(f, "{left} {operator} {right}")
+            }
+            Expression::UnaryOperation {
+                operator: UnaryOperator::Not,
+                expr,
+            } => write!(f, "NOT {}", SafeSql(expr.as_ref())),
+            Expression::UnaryOperation { operator, expr } => {
+                write!(f, "{operator}{}", SafeSql(expr.as_ref()))
+            }
+            Expression::Nested(expr) => write!(f, "({})", SafeSql(expr.as_ref())),
+            Expression::Tuple(items) => write!(f, "({})", join_safe(items, ", ")),
+            Expression::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let not = if *negated { "NOT " } else { "" };
+                write!(
+                    f,
+                    "{} {not}IN ({})",
+                    SafeSql(expr.as_ref()),
+                    join_safe(list, ", ")
+                )
+            }
+            Expression::Between {
+                expr,
+                low,
+                high,
+                negated,
+            } => {
+                let not = if *negated { "NOT " } else { "" };
+                let (expr, low, high) = (SafeSql(&**expr), SafeSql(&**low), SafeSql(&**high));
+                write!(f, "{expr} {not}BETWEEN {low} AND {high}")
+            }
+            Expression::IsNull { expr, negated } => {
+                let not = if *negated { "NOT " } else { "" };
+                write!(f, "{} IS {not}NULL", SafeSql(expr.as_ref()))
+            }
+            Expression::Aggregate {
+                func,
+                arg,
+                distinct,
+            } => {
+                let distinct = if *distinct { "DISTINCT " } else { "" };
+                write!(f, "{func}({distinct}{})", SafeSql(arg.as_ref()))
+            }
+            Expression::Function { func, args } => write!(f, "{func}({})", join_safe(args, ", ")),
+            Expression::Case {
+                branches,
+                else_branch,
+            } => {
+                f.write_str("CASE")?;
+                for (condition, result) in branches {
+                    let (condition, result) = (SafeSql(condition), SafeSql(result));
+                    write!(f, " WHEN {condition} THEN {result}")?;
+                }
+                if let Some(else_branch) = else_branch {
+                    write!(f, " ELSE {}", SafeSql(else_branch.as_ref()))?;
+                }
+                f.write_str(" END")
+            }
+        }
+    }
+}
+
+impl Display for SafeSql<'_, Statement> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Statement::Create(create) => match create {
+                Create::Table {
+                    name,
+                    columns,
+                    if_not_exists,
+                } => {
+                    let if_not_exists = if *if_not_exists { "IF NOT EXISTS " } else { "" };
+                    let name = SafeSql(name);
+                    write!(
+                        f,
+                        "CREATE TABLE {if_not_exists}{name} ({})",
+                        join_safe(columns, ", ")
+                    )?;
+                }
+
+                Create::Database(name) => {
+                    write!(f, "CREATE DATABASE {}", SafeSql(name))?;
+                }
+
+                Create::Index {
+                    name,
+                    table,
+                    columns,
+                    unique,
+                } => {
+                    let unique = if *unique { " UNIQUE " } else { " " };
+                    let (name, table) = (SafeSql(name), SafeSql(table));
+                    let columns = join_safe(columns, ", ");
+                    write!(f, "CREATE{unique}INDEX {name} ON {table}({columns})")?;
+                }
+            },
+
+            Statement::Select {
+                distinct,
+                columns,
+                from,
+                joins,
+                r#where,
+                group_by,
+                having,
+                order_by,
+                limit,
+                offset,
+            } => {
+                let distinct = if *distinct { "DISTINCT " } else { "" };
+                write!(f, "SELECT {distinct}{}", join_safe(columns, ", "))?;
+                if let Some(from) = from {
+                    write!(f, " FROM {}", SafeSql(from))?;
+                }
+                for join in joins {
+                    write!(f, " {}", SafeSql(join))?;
+                }
+                if let Some(expr) = r#where {
+                    write!(f, " WHERE {}", SafeSql(expr))?;
+                }
+                if !group_by.is_empty() {
+                    write!(f, " GROUP BY {}", join_safe(group_by, ", "))?;
+                }
+                if let Some(expr) = having {
+                    write!(f, " HAVING {}", SafeSql(expr))?;
+                }
+                if !order_by.is_empty() {
+                    write!(f, " ORDER BY {}", join_safe(order_by, ", "))?;
+                }
+                if let Some(limit) = limit {
+                    write!(f, " LIMIT {limit}")?;
+                }
+                if let Some(offset) = offset {
+                    write!(f, " OFFSET {offset}")?;
+                }
+            }
+
+            Statement::Delete { from, r#where } => {
+                write!(f, "DELETE FROM {}", SafeSql(from))?;
+                if let Some(expr) = r#where {
+                    write!(f, " WHERE {}", SafeSql(expr))?;
+                }
+            }
+
+            Statement::Update {
+                table,
+                columns,
+                r#where,
+            } => {
+                let table = SafeSql(table);
+                write!(f, "UPDATE {table} SET {}", join_safe(columns, ", "))?;
+                if let Some(expr) = r#where {
+                    write!(f, " WHERE {}", SafeSql(expr))?;
+                }
+            }
+
+            Statement::Insert {
+                into,
+                columns,
+                values,
+            } => {
+                let columns = if columns.is_empty() {
+                    String::from(" ")
+                } else {
+                    format!(" ({}) ", join_safe(columns, ", "))
+                };
+
+                match values {
+                    InsertSource::Values(values) => {
+                        let rows = Vec::from_iter(
+                            values
+                                .iter()
+                                .map(|row| format!("({})", join_safe(row, ", "))),
+                        );
+
+                        write!(
+                            f,
+                            "INSERT INTO {}{columns}VALUES {}",
+                            SafeSql(into),
+                            rows.join(", ")
+                        )?;
+                    }
+
+                    // Nested statement already writes the semicolon.
+                    InsertSource::Select(select) => {
+                        return write!(
+                            f,
+                            "INSERT INTO {}{columns}{}",
+                            SafeSql(into),
+                            SafeSql(select.as_ref())
+                        );
+                    }
+                }
+            }
+
+            Statement::Drop(drop) => {
+                match drop {
+                    Drop::Table { name, if_exists } => {
+                        let if_exists = if *if_exists { "IF EXISTS " } else { "" };
+                        write!(f, "DROP TABLE {if_exists}{}", SafeSql(name))?;
+                    }
+                    Drop::Database(name) => write!(f, "DROP DATABASE {}", SafeSql(name))?,
+                    Drop::Index {
+                        name,
+                        table,
+                        if_exists,
+                    } => {
+                        let if_exists = if *if_exists { "IF EXISTS " } else { "" };
+                        write!(f, "DROP INDEX {if_exists}{}", SafeSql(name))?;
+                        if let Some(table) = table {
+                            write!(f, " ON {}", SafeSql(table))?;
+                        }
+                    }
+                };
+            }
+
+            Statement::Alter(alter) => match alter {
+                Alter::DropColumn { table, column } => {
+                    let (table, column) = (SafeSql(table), SafeSql(column));
+                    write!(f, "ALTER TABLE {table} DROP COLUMN {column}")?;
+                }
+
+                Alter::RenameColumn {
+                    table,
+                    column,
+                    new_name,
+                } => {
+                    let (table, column, new_name) =
+                        (SafeSql(table), SafeSql(column), SafeSql(new_name));
+                    write!(
+                        f,
+                        "ALTER TABLE {table} RENAME COLUMN {column} TO {new_name}"
+                    )?;
+                }
+
+                Alter::RenameTable { table, new_name } => {
+                    let (table, new_name) = (SafeSql(table), SafeSql(new_name));
+                    write!(f, "ALTER TABLE {table} RENAME TO {new_name}")?;
+                }
+            },
+
+            Statement::Explain { statement, analyze } => {
+                f.write_str("EXPLAIN ")?;
+                if *analyze {
+                    f.write_str("ANALYZE ")?;
+                }
+                // Nested statement already writes the semicolon.
+                return write!(f, "{}", SafeSql(statement.as_ref()));
+            }
+
+            Statement::Analyze(table) => write!(f, "ANALYZE {}", SafeSql(table))?,
+
+            // No identifiers or values here.
+            other => return write!(f, "{other}"),
+        };
+
+        f.write_char(';')
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DataType;
+    use crate::sql::parser::Parser;
+
+    #[test]
+    fn display_width() {
+        assert_eq!(DataType::Int.display_width(), 11);
+        assert_eq!(DataType::UnsignedInt.display_width(), 10);
+        assert_eq!(DataType::BigInt.display_width(), 20);
+        assert_eq!(DataType::UnsignedBigInt.display_width(), 20);
+        assert_eq!(DataType::Bool.display_width(), 5);
+        assert_eq!(DataType::Varchar(255).display_width(), 255);
+    }
+
+    #[test]
+    fn to_sql_safe_quotes_identifiers_and_strings() {
+        let statement = Parser::new("SELECT `order`, name FROM `select` WHERE name = 'it''s';")
+            .parse_statement()
+            .unwrap();
+
+        assert_eq!(
+            statement.to_sql_safe(),
+            "SELECT `order`, `name` FROM `select` WHERE `name` = 'it''s';"
+        );
+    }
+
+    #[test]
+    fn display_qualified_identifiers() {
+        let sql =
+            "SELECT users.id, `order`.total FROM users JOIN `order` ON users.id = `order`.user_id;";
+        let statement = Parser::new(sql).parse_statement().unwrap();
+
+        assert_eq!(
+            statement.to_string(),
+            "SELECT users.id, order.total FROM users JOIN order ON users.id = order.user_id;"
+        );
+        assert_eq!(
+            statement.to_sql_safe(),
+            "SELECT `users`.`id`, `order`.`total` FROM `users` JOIN `order` ON `users`.`id` = `order`.`user_id`;"
+        );
+
+        let expr = Parser::new("users.id").parse_expression().unwrap();
+        assert_eq!(Parser::new(&expr.to_string()).parse_expression(), Ok(expr));
+    }
+
+    #[test]
+    fn to_sql_safe_round_trip() {
+        let statements = [
+            "CREATE DATABASE `database`;",
+            "CREATE TABLE `select` (`order` INT PRIMARY KEY, name VARCHAR(255) UNIQUE, `we``ird` DOUBLE);",
+            "CREATE UNIQUE INDEX `index` ON `select`(`order`);",
+            "CREATE UNIQUE INDEX `index` ON `select`(`order`, `group`, id);",
+            "CREATE UNIQUE INDEX `index` ON `select`(`order` DESC, id);",
+            "SELECT COUNT(*), MAX(`order`) + 1, `count` FROM `select`;",
+            "SELECT `group`, SUM(x) FROM `select` WHERE x > 0 GROUP BY `group` ORDER BY `group` DESC;",
+            "SELECT `group` FROM `select` GROUP BY `group` HAVING COUNT(*) > 1;",
+            "SELECT DISTINCT `group`, x FROM `select` ORDER BY x;",
+            "SELECT id AS `key`, age + 1 AS next_age, * FROM users ORDER BY next_age;",
+            "SELECT `users`.id, orders.total FROM users INNER JOIN orders ON users.id = orders.user_id JOIN `join` ON `join`.`id` = 1;",
+            r#"SELECT `from`, * FROM `select` WHERE name = 'it''s' OR name = "say ""hi""" AND (`order` + 1) * 2 >= 10 ORDER BY `order` DESC, name LIMIT 10 OFFSET 2;"#,
+            "INSERT INTO `select` (`order`, name) VALUES (-1, 'semi; -- colon /* not a comment */ `tick` \\ back\nline ünïcode');",
+            "INSERT INTO users VALUES (1, '', 2.5, TRUE);",
+            "INSERT INTO users (id, name) VALUES (1, 'a'), (2, 'b');",
+            "INSERT INTO `archive` (id, `order`) SELECT id, `order` FROM orders WHERE done = TRUE;",
+            "CREATE TABLE t (id INT PRIMARY KEY, active BOOL NOT NULL DEFAULT TRUE, n INT DEFAULT (1 + 2) * 3);",
+            "UPDATE `update` SET `set` = `set` + 1, name = 'x''y' WHERE `where` != 1.5;",
+            "DELETE FROM `table` WHERE id = 1 AND name = '\"';",
+            "DROP TABLE `drop`;",
+            "DROP DATABASE `drop`;",
+            "ALTER TABLE `alter` DROP COLUMN `column`;",
+            "ALTER TABLE `alter` RENAME COLUMN `column` TO `to`;",
+            "ALTER TABLE `alter` RENAME TO `rename`;",
+            "EXPLAIN SELECT * FROM `limit` WHERE `key` < 'x''';",
+            "START TRANSACTION;",
+            "COMMIT;",
+            "ROLLBACK;",
+        ];
+
+        for sql in statements {
+            let statement = Parser::new(sql).parse_statement().unwrap();
+            let safe_sql = statement.to_sql_safe();
+
+            assert_eq!(
+                Parser::new(&safe_sql).parse_statement(),
+                Ok(statement),
+                "original: {sql} safe: {safe_sql}"
+            );
+        }
+    }
+}
@@ -20,9 +20,11 @@ pub(crate) enum Token {
     Div,
     Plus,
     Minus,
+    Concat,
     LeftParen,
     RightParen,
     Comma,
+    Dot,
     SemiColon,
     /// Not a real token, used to mark the end of a token stream.
     Eof,
@@ -53,6 +55,9 @@ pub(crate) enum Keyword {
     BigInt,
     Unsigned,
     Varchar,
+    Char,
+    Double,
+    Float,
     Bool,
     True,
     False,
@@ -64,7 +69,43 @@ pub(crate) enum Keyword {
     Transaction,
     Rollback,
     Commit,
+    Savepoint,
     Explain,
+    Analyze,
+    Vacuum,
+    Limit,
+    Offset,
+    Asc,
+    Desc,
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Group,
+    Having,
+    Join,
+    Inner,
+    As,
+    Like,
+    In,
+    Not,
+    Between,
+    Is,
+    Null,
+    Default,
+    Distinct,
+    Alter,
+    Column,
+    Rename,
+    To,
+    Case,
+    When,
+    Then,
+    Else,
+    End,
+    If,
+    Exists,
     /// Not a keyword, used for convenience. See [`super::tokenizer::Tokenizer`].
     None,
 }
@@ -75,6 +116,9 @@ pub(crate) enum Whitespace {
     Space,
     Tab,
     Newline,
+    /// Comments separate tokens just like whitespace does, so we don't need
+    /// a dedicated [`Token`] variant for them.
+    Comment,
 }
 
 impl Keyword {
@@ -88,6 +132,47 @@ impl Keyword {
             keyword => Some(*keyword),
         }
     }
+
+    /// Returns `true` if this keyword can never be used as an identifier.
+    ///
+    /// Non-reserved (or "soft") keywords like `ORDER` or `COMMIT` only have a
+    /// special meaning in certain positions, so the parser accepts them as
+    /// table or column names when it expects an identifier. Keywords that start
+    /// statements or clauses like `SELECT` or `FROM` are always reserved.
+    pub fn is_reserved(&self) -> bool {
+        !matches!(
+            self,
+            Self::Key
+                | Self::Database
+                | Self::Int
+                | Self::BigInt
+                | Self::Unsigned
+                | Self::Varchar
+                | Self::Char
+                | Self::Double
+                | Self::Float
+                | Self::Bool
+                | Self::Order
+                | Self::Group
+                | Self::Index
+                | Self::Start
+                | Self::Transaction
+                | Self::Rollback
+                | Self::Commit
+                | Self::Savepoint
+                | Self::Vacuum
+                | Self::Count
+                | Self::Sum
+                | Self::Avg
+                | Self::Min
+                | Self::Max
+                | Self::Column
+                | Self::Rename
+                | Self::To
+                | Self::If
+                | Self::Exists
+        )
+    }
 }
 
 impl Token {
@@ -123,9 +208,11 @@ impl Display for Token {
             Self::Div => f.write_str("/"),
             Self::Plus => f.write_str("+"),
             Self::Minus => f.write_str("-"),
+            Self::Concat => f.write_str("||"),
             Self::LeftParen => f.write_str("("),
             Self::RightParen => f.write_str(")"),
             Self::Comma => f.write_str(","),
+            Self::Dot => f.write_str("."),
             Self::SemiColon => f.write_str(";"),
         }
     }
@@ -156,6 +243,9 @@ impl Display for Keyword {
             Self::BigInt => "BIGINT",
             Self::Unsigned => "UNSIGNED",
             Self::Varchar => "VARCHAR",
+            Self::Char => "CHAR",
+            Self::Double => "DOUBLE",
+            Self::Float => "FLOAT",
             Self::Bool => "BOOL",
             Self::True => "TRUE",
             Self::False => "FALSE",
@@ -167,7 +257,43 @@ impl Display for Keyword {
             Self::Transaction => "TRANSACTION",
             Self::Rollback => "ROLLBACK",
             Self::Commit => "COMMIT",
+            Self::Savepoint => "SAVEPOINT",
             Self::Explain => "EXPLAIN",
+            Self::Analyze => "ANALYZE",
+            Self::Vacuum => "VACUUM",
+            Self::Limit => "LIMIT",
+            Self::Offset => "OFFSET",
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+            Self::Count => "COUNT",
+            Self::Sum => "SUM",
+            Self::Avg => "AVG",
+            Self::Min => "MIN",
+            Self::Max => "MAX",
+            Self::Group => "GROUP",
+            Self::Having => "HAVING",
+            Self::Join => "JOIN",
+            Self::Inner => "INNER",
+            Self::As => "AS",
+            Self::Like => "LIKE",
+            Self::In => "IN",
+            Self::Not => "NOT",
+            Self::Between => "BETWEEN",
+            Self::Is => "IS",
+            Self::Null => "NULL",
+            Self::Default => "DEFAULT",
+            Self::Distinct => "DISTINCT",
+            Self::Alter => "ALTER",
+            Self::Column => "COLUMN",
+            Self::Rename => "RENAME",
+            Self::To => "TO",
+            Self::Case => "CASE",
+            Self::When => "WHEN",
+            Self::Then => "THEN",
+            Self::Else => "ELSE",
+            Self::End => "END",
+            Self::If => "IF",
+            Self::Exists => "EXISTS",
             Self::None => "_",
         })
     }
@@ -179,6 +305,7 @@ impl Display for Whitespace {
             Self::Space => ' ',
             Self::Tab => '\t',
             Self::Newline => '\n',
+            Self::Comment => ' ',
         })
     }
 }
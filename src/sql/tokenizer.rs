@@ -1,6 +1,6 @@
 //! SQL tokenizer that produces [`Token`] instances.
 
-use std::{fmt::Display, iter::Peekable, str::Chars};
+use std::{collections::HashMap, fmt::Display, iter::Peekable, str::Chars};
 
 use super::token::{Keyword, Token, Whitespace};
 
@@ -11,19 +11,32 @@ pub(crate) struct Location {
     pub line: usize,
     /// Column number starting at 1.
     pub col: usize,
+    /// Offset in bytes from the beginning of the input string.
+    ///
+    /// Columns count characters, so this one is not simply `col - 1` once
+    /// there are multi-byte UTF-8 characters or multiple lines.
+    pub byte_offset: usize,
 }
 
 impl Default for Location {
     fn default() -> Self {
-        Self { line: 1, col: 1 }
+        Self {
+            line: 1,
+            col: 1,
+            byte_offset: 0,
+        }
     }
 }
 
-/// Stores both the [`Token`] and its starting location in the input string.
+/// Stores both the [`Token`] and its span in the input string.
 #[derive(Debug, PartialEq)]
 pub(super) struct TokenWithLocation {
     pub variant: Token,
+    /// Location of the first character of the token.
     pub location: Location,
+    /// Location right after the last character of the token, so the token
+    /// covers `input[location.byte_offset..end.byte_offset]`.
+    pub end: Location,
 }
 
 impl TokenWithLocation {
@@ -57,7 +70,7 @@ impl<'i> Stream<'i> {
     fn new(input: &'i str) -> Self {
         Self {
             input,
-            location: Location { line: 1, col: 1 },
+            location: Location::default(),
             chars: input.chars().peekable(),
         }
     }
@@ -65,6 +78,8 @@ impl<'i> Stream<'i> {
     /// Consumes the next value updating [`Self::location`] in the process.
     fn next(&mut self) -> Option<char> {
         self.chars.next().inspect(|chr| {
+            self.location.byte_offset += chr.len_utf8();
+
             if *chr == '\n' {
                 self.location.line += 1;
                 self.location.col = 1;
@@ -132,6 +147,12 @@ pub(crate) enum ErrorKind {
 
     StringNotClosed,
 
+    IdentifierNotClosed,
+
+    CommentNotClosed,
+
+    InputTooLarge,
+
     Other(String),
 }
 
@@ -152,6 +173,12 @@ impl Display for ErrorKind {
 
             ErrorKind::StringNotClosed => f.write_str("string not closed"),
 
+            ErrorKind::IdentifierNotClosed => f.write_str("quoted identifier not closed"),
+
+            ErrorKind::CommentNotClosed => f.write_str("comment not closed"),
+
+            ErrorKind::InputTooLarge => f.write_str("input exceeds maximum length or token count"),
+
             ErrorKind::OperatorNotClosed(operator) => write!(f, "'{operator}' operator not closed"),
 
             ErrorKind::Other(message) => f.write_str(message),
@@ -166,15 +193,104 @@ impl Display for ErrorKind {
 pub(super) struct TokenizerError {
     pub kind: ErrorKind,
     pub location: Location,
+    /// Original input, used to print the snippet where the error is. Empty
+    /// for [`ErrorKind::InputTooLarge`], which is not worth copying.
     pub input: String,
 }
 
+impl Display for TokenizerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Tokenizer Error at line {} column {}: {}",
+            self.location.line, self.location.col, self.kind,
+        )?;
+
+        write_snippet(f, &self.input, self.location)
+    }
+}
+
+/// Writes a new line and then the line of `input` that contains `location`
+/// followed by a `^` character below [`Location::col`]:
+///
+/// ```text
+/// SELECT * FROM users WHERE name = 'John
+///                                  ^
+/// ```
+///
+/// If `location` is past the end of the input (unexpected EOF) the caret
+/// points right after the last character. Tabs are preserved in the padding so
+/// that the caret stays aligned when the line is indented with tabs. Nothing
+/// is written if `input` is empty.
+pub(super) fn write_snippet(
+    f: &mut std::fmt::Formatter,
+    input: &str,
+    location: Location,
+) -> std::fmt::Result {
+    if input.is_empty() {
+        return Ok(());
+    }
+
+    let (line, col) = match input.lines().nth(location.line - 1) {
+        Some(line) => (line, location.col),
+        None => {
+            let line = input.lines().last().unwrap_or_default();
+            (line, line.chars().count() + 1)
+        }
+    };
+
+    let padding: String = line
+        .chars()
+        .chain(std::iter::repeat(' '))
+        .take(col - 1)
+        .map(|chr| if chr == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    write!(f, "\n{line}\n{padding}^")
+}
+
+/// Default value of [`Limits::max_input_len`]. 16 MiB.
+pub(super) const DEFAULT_MAX_INPUT_LEN: usize = 16 << 20;
+
+/// Default value of [`Limits::max_tokens`].
+pub(super) const DEFAULT_MAX_TOKENS: usize = 1 << 20;
+
+/// Guards against pathological inputs.
+///
+/// If any of these is exceeded the tokenizer returns
+/// [`ErrorKind::InputTooLarge`] instead of processing the rest of the input.
+/// [`None`] means unlimited.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// Maximum length of the input string in bytes.
+    pub max_input_len: Option<usize>,
+    /// Maximum number of tokens, including whitespace and comments but not
+    /// [`Token::Eof`].
+    pub max_tokens: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_input_len: Some(DEFAULT_MAX_INPUT_LEN),
+            max_tokens: Some(DEFAULT_MAX_TOKENS),
+        }
+    }
+}
+
 /// Main parsing structure. See [`Tokenizer::next_token`].
 pub(super) struct Tokenizer<'i> {
     /// Character stream.
     stream: Stream<'i>,
     /// True once we've returned [`Token::Eof`].
     reached_eof: bool,
+    /// Input guards.
+    limits: Limits,
+    /// Number of tokens returned so far.
+    tokens: usize,
+    /// Keywords that take precedence over the built-in set. Keys are
+    /// uppercase. See [`Tokenizer::with_keywords`].
+    keywords: HashMap<String, Keyword>,
 }
 
 type TokenResult = Result<Token, TokenizerError>;
@@ -186,9 +302,35 @@ impl<'i> Tokenizer<'i> {
     /// called through helper functions or iterators. See [`Tokenizer::iter`]
     /// and [`Tokenizer::tokenize`].
     pub fn new(input: &'i str) -> Self {
+        Self::with_limits(input, Limits::default())
+    }
+
+    /// Same as [`Tokenizer::new`] but with custom [`Limits`] instead of the
+    /// default ones.
+    pub fn with_limits(input: &'i str, limits: Limits) -> Self {
         Self {
             stream: Stream::new(input),
             reached_eof: false,
+            limits,
+            tokens: 0,
+            keywords: HashMap::new(),
+        }
+    }
+
+    /// Same as [`Tokenizer::new`] but `keywords` augments or overrides the
+    /// built-in keyword set.
+    ///
+    /// Words are matched case insensitively like the built-in ones. Mapping a
+    /// word to an existing [`Keyword`] adds a new spelling for it while mapping
+    /// a built-in keyword to [`Keyword::None`] turns it into a regular
+    /// identifier. Useful for experimenting with other SQL dialects.
+    pub fn with_keywords(input: &'i str, keywords: HashMap<String, Keyword>) -> Self {
+        Self {
+            keywords: keywords
+                .into_iter()
+                .map(|(word, keyword)| (word.to_uppercase(), keyword))
+                .collect(),
+            ..Self::new(input)
         }
     }
 
@@ -224,17 +366,51 @@ impl<'i> Tokenizer<'i> {
         }
     }
 
-    /// Same as [`Self::next_token`] but returns the starting location
-    /// of the token as well.
+    /// Same as [`Self::next_token`] but returns the starting and ending
+    /// locations of the token as well.
     fn next_token_with_location(&mut self) -> Result<TokenWithLocation, TokenizerError> {
         let location = self.stream.location();
 
-        self.next_token().map(|token| TokenWithLocation {
-            variant: token,
-            location,
+        self.check_limits()?;
+
+        self.next_token().map(|token| {
+            self.tokens += 1;
+            TokenWithLocation {
+                variant: token,
+                location,
+                end: self.stream.location(),
+            }
         })
     }
 
+    /// Makes sure we're not going past the configured [`Limits`].
+    ///
+    /// The input length is only checked before the first token so that we
+    /// don't even start processing large inputs. Counting tokens is cheap
+    /// enough to do it every time.
+    fn check_limits(&mut self) -> Result<(), TokenizerError> {
+        let Limits {
+            max_input_len,
+            max_tokens,
+        } = self.limits;
+
+        let input_too_large =
+            self.tokens == 0 && max_input_len.is_some_and(|max| self.stream.input.len() > max);
+
+        let too_many_tokens =
+            max_tokens.is_some_and(|max| self.tokens >= max) && self.stream.peek().is_some();
+
+        if input_too_large || too_many_tokens {
+            return Err(TokenizerError {
+                kind: ErrorKind::InputTooLarge,
+                location: self.stream.location(),
+                input: String::new(),
+            });
+        }
+
+        Ok(())
+    }
+
     /// Consumes and returns the next [`Token`] variant in [`Self::stream`].
     fn next_token(&mut self) -> TokenResult {
         // Done, no more chars.
@@ -244,19 +420,11 @@ impl<'i> Tokenizer<'i> {
         };
 
         match chr {
-            ' ' => self.consume(Token::Whitespace(Whitespace::Space)),
-
-            '\t' => self.consume(Token::Whitespace(Whitespace::Tab)),
-
-            '\n' => self.consume(Token::Whitespace(Whitespace::Newline)),
-
-            '\r' => match self.stream.peek_next() {
-                Some('\n') => self.consume(Token::Whitespace(Whitespace::Newline)),
-                _ => Ok(Token::Whitespace(Whitespace::Newline)),
-            },
+            ' ' | '\t' | '\n' | '\r' => self.tokenize_whitespace(),
 
             '<' => match self.stream.peek_next() {
                 Some('=') => self.consume(Token::LtEq),
+                Some('>') => self.consume(Token::Neq),
                 _ => Ok(Token::Lt),
             },
 
@@ -267,11 +435,20 @@ impl<'i> Tokenizer<'i> {
 
             '*' => self.consume(Token::Mul),
 
-            '/' => self.consume(Token::Div),
+            '/' => {
+                let start = self.stream.location();
+                match self.stream.peek_next() {
+                    Some('*') => self.tokenize_block_comment(start),
+                    _ => Ok(Token::Div),
+                }
+            }
 
             '+' => self.consume(Token::Plus),
 
-            '-' => self.consume(Token::Minus),
+            '-' => match self.stream.peek_next() {
+                Some('-') => self.tokenize_line_comment(),
+                _ => Ok(Token::Minus),
+            },
 
             '=' => self.consume(Token::Eq),
 
@@ -289,16 +466,34 @@ impl<'i> Tokenizer<'i> {
                 None => self.error(ErrorKind::OperatorNotClosed(Token::Neq)),
             },
 
+            '|' => match self.stream.peek_next() {
+                Some('|') => self.consume(Token::Concat),
+
+                Some(unexpected) => {
+                    let error_kind = ErrorKind::UnexpectedWhileParsingOperator {
+                        unexpected: *unexpected,
+                        operator: Token::Concat,
+                    };
+                    self.error(error_kind)
+                }
+
+                None => self.error(ErrorKind::OperatorNotClosed(Token::Concat)),
+            },
+
             '(' => self.consume(Token::LeftParen),
 
             ')' => self.consume(Token::RightParen),
 
             ',' => self.consume(Token::Comma),
 
+            '.' => self.consume(Token::Dot),
+
             ';' => self.consume(Token::SemiColon),
 
             '"' | '\'' => self.tokenize_string(),
 
+            '`' => self.tokenize_quoted_identifier(),
+
             '0'..='9' => self.tokenize_number(),
 
             _ if Token::is_part_of_ident_or_keyword(chr) => self.tokenize_keyword_or_identifier(),
@@ -327,25 +522,140 @@ impl<'i> Tokenizer<'i> {
         })
     }
 
+    /// Coalesces a run of whitespace characters into a single
+    /// [`Token::Whitespace`].
+    ///
+    /// The variant is determined by the first character of the run, so
+    /// `"  \n\t"` becomes [`Whitespace::Space`]. The stream keeps track of
+    /// lines and columns while skipping, so locations of the following tokens
+    /// are not affected.
+    fn tokenize_whitespace(&mut self) -> TokenResult {
+        let whitespace = match self.stream.next().unwrap() {
+            ' ' => Whitespace::Space,
+            '\t' => Whitespace::Tab,
+            _ => Whitespace::Newline,
+        };
+
+        while self
+            .stream
+            .peek()
+            .is_some_and(|chr| matches!(chr, ' ' | '\t' | '\n' | '\r'))
+        {
+            self.stream.next();
+        }
+
+        Ok(Token::Whitespace(whitespace))
+    }
+
+    /// Skips a `-- comment` until the end of the line or the end of the input.
+    ///
+    /// The first `-` must already be consumed. The newline character is not
+    /// part of the comment, so it will be returned as the next token. This
+    /// also means that `5--2` is the number `5` followed by a comment, just
+    /// like in other SQL databases.
+    fn tokenize_line_comment(&mut self) -> TokenResult {
+        while self.stream.peek().is_some_and(|chr| *chr != '\n') {
+            self.stream.next();
+        }
+
+        Ok(Token::Whitespace(Whitespace::Comment))
+    }
+
+    /// Consumes a `/* block comment */` that can span multiple lines.
+    ///
+    /// The stream must be positioned at the `*` that follows the initial `/`,
+    /// and `start` is the location of that `/`, which is where the error points
+    /// to if the comment is never closed.
+    ///
+    /// Nested comments are not supported, the comment ends at the first `*/`
+    /// found. So `/* a /* b */ c */` leaves `c */` in the stream, which is what
+    /// MySQL does as well (PostgreSQL allows nesting).
+    fn tokenize_block_comment(&mut self, start: Location) -> TokenResult {
+        self.stream.next();
+
+        loop {
+            match self.stream.next() {
+                Some('*') if self.stream.peek().is_some_and(|chr| *chr == '/') => {
+                    self.stream.next();
+                    return Ok(Token::Whitespace(Whitespace::Comment));
+                }
+
+                Some(_) => continue,
+
+                None => {
+                    return Err(TokenizerError {
+                        kind: ErrorKind::CommentNotClosed,
+                        location: start,
+                        input: self.stream.input.to_owned(),
+                    });
+                }
+            }
+        }
+    }
+
     /// Parses a single quoted or double quoted string like `"this one"` into
     /// [`Token::String`].
+    ///
+    /// The quote character can be escaped by writing it twice, as in
+    /// `'it''s'`.
     fn tokenize_string(&mut self) -> TokenResult {
+        match self.take_quoted() {
+            Some(string) => Ok(Token::String(string)),
+            None => self.error(ErrorKind::StringNotClosed),
+        }
+    }
+
+    /// Parses a backtick quoted identifier like `` `order` `` into
+    /// [`Token::Identifier`].
+    ///
+    /// Quoted identifiers are never keywords, so they can be used to name
+    /// tables or columns after reserved words. Backticks are escaped by
+    /// doubling them, same as quotes in [`Self::tokenize_string`].
+    fn tokenize_quoted_identifier(&mut self) -> TokenResult {
+        match self.take_quoted() {
+            Some(identifier) => Ok(Token::Identifier(identifier)),
+            None => self.error(ErrorKind::IdentifierNotClosed),
+        }
+    }
+
+    /// Consumes the opening quote character and everything up to the matching
+    /// closing quote. Doubled quotes are unescaped into a single one.
+    ///
+    /// Returns [`None`] if the input ends before the closing quote.
+    fn take_quoted(&mut self) -> Option<String> {
         let quote = self.stream.next().unwrap();
+        let mut string = String::new();
 
-        let string = self.stream.take_while(|chr| *chr != quote).collect();
+        loop {
+            string.extend(self.stream.take_while(|chr| *chr != quote));
 
-        if self.stream.next().is_some_and(|chr| chr == quote) {
-            Ok(Token::String(string))
-        } else {
-            self.error(ErrorKind::StringNotClosed)
+            if self.stream.next() != Some(quote) {
+                return None;
+            }
+
+            if self.stream.peek() != Some(&quote) {
+                return Some(string);
+            }
+
+            string.push(self.stream.next().unwrap());
         }
     }
 
-    /// Tokenizes numbers like `1234`. Floats are not supported.
+    /// Tokenizes numbers like `1234` or `3.14`.
+    ///
+    /// Floats are stored in the same [`Token::Number`] variant, the parser
+    /// decides what to do with them by looking for the decimal point. Numbers
+    /// must start with a digit (`.5` is not valid) and there's no support for
+    /// exponents like `1e10`.
     fn tokenize_number(&mut self) -> TokenResult {
-        Ok(Token::Number(
-            self.stream.take_while(char::is_ascii_digit).collect(),
-        ))
+        let mut number: String = self.stream.take_while(char::is_ascii_digit).collect();
+
+        if self.stream.peek().is_some_and(|chr| *chr == '.') {
+            number.push(self.stream.next().unwrap());
+            number.extend(self.stream.take_while(char::is_ascii_digit));
+        }
+
+        Ok(Token::Number(number))
     }
 
     /// Attempts to parse an instance of [`Token::Keyword`] or
@@ -356,51 +666,135 @@ impl<'i> Tokenizer<'i> {
             .take_while(Token::is_part_of_ident_or_keyword)
             .collect();
 
-        // TODO: Use [phf](https://docs.rs/phf/) or something similar if this
-        // keeps growing.
-        let keyword = match value.to_uppercase().as_str() {
-            "SELECT" => Keyword::Select,
-            "CREATE" => Keyword::Create,
-            "UPDATE" => Keyword::Update,
-            "DELETE" => Keyword::Delete,
-            "INSERT" => Keyword::Insert,
-            "VALUES" => Keyword::Values,
-            "INTO" => Keyword::Into,
-            "SET" => Keyword::Set,
-            "DROP" => Keyword::Drop,
-            "FROM" => Keyword::From,
-            "WHERE" => Keyword::Where,
-            "AND" => Keyword::And,
-            "OR" => Keyword::Or,
-            "PRIMARY" => Keyword::Primary,
-            "KEY" => Keyword::Key,
-            "UNIQUE" => Keyword::Unique,
-            "TABLE" => Keyword::Table,
-            "DATABASE" => Keyword::Database,
-            "INT" => Keyword::Int,
-            "BIGINT" => Keyword::BigInt,
-            "UNSIGNED" => Keyword::Unsigned,
-            "VARCHAR" => Keyword::Varchar,
-            "BOOL" => Keyword::Bool,
-            "TRUE" => Keyword::True,
-            "FALSE" => Keyword::False,
-            "ORDER" => Keyword::Order,
-            "BY" => Keyword::By,
-            "INDEX" => Keyword::Index,
-            "ON" => Keyword::On,
-            "START" => Keyword::Start,
-            "TRANSACTION" => Keyword::Transaction,
-            "ROLLBACK" => Keyword::Rollback,
-            "COMMIT" => Keyword::Commit,
-            "EXPLAIN" => Keyword::Explain,
-            _ => Keyword::None,
-        };
-
-        Ok(match keyword {
+        Ok(match self.keyword(&value) {
             Keyword::None => Token::Identifier(value),
-            _ => Token::Keyword(keyword),
+            keyword => Token::Keyword(keyword),
         })
     }
+
+    /// Finds the [`Keyword`] that matches `word` case insensitively.
+    ///
+    /// This runs for every single identifier in the input, so instead of
+    /// allocating an uppercase copy of `word` we write it to a small buffer on
+    /// the stack. Words only contain ASCII characters (see
+    /// [`Token::is_part_of_ident_or_keyword`]) and built-in keywords are all
+    /// short, so longer words can only be custom keywords (see
+    /// [`Tokenizer::with_keywords`]).
+    fn keyword(&self, word: &str) -> Keyword {
+        let mut buf = [0; MAX_KEYWORD_LEN];
+
+        if word.len() > buf.len() {
+            return match self.keywords.is_empty() {
+                true => Keyword::None,
+                false => self
+                    .keywords
+                    .get(&word.to_ascii_uppercase())
+                    .copied()
+                    .unwrap_or(Keyword::None),
+            };
+        }
+
+        let upper = &mut buf[..word.len()];
+        upper.copy_from_slice(word.as_bytes());
+        upper.make_ascii_uppercase();
+
+        // ASCII bytes are always valid UTF-8.
+        let upper = std::str::from_utf8(upper).unwrap();
+
+        match self.keywords.get(upper) {
+            Some(keyword) => *keyword,
+            None => builtin_keyword(upper),
+        }
+    }
+}
+
+/// Maximum length of the words that [`Tokenizer::keyword`] uppercases on the
+/// stack. Must be at least as long as the longest built-in keyword.
+const MAX_KEYWORD_LEN: usize = 16;
+
+/// Maps an uppercase `word` to its [`Keyword`] or [`Keyword::None`] if it's
+/// not a keyword.
+///
+/// The compiler turns this into comparisons on the length first and then the
+/// bytes, so it's cheap enough that we don't need a perfect hash table (and
+/// the project doesn't use dependencies anyway).
+fn builtin_keyword(word: &str) -> Keyword {
+    match word {
+        "SELECT" => Keyword::Select,
+        "CREATE" => Keyword::Create,
+        "UPDATE" => Keyword::Update,
+        "DELETE" => Keyword::Delete,
+        "INSERT" => Keyword::Insert,
+        "VALUES" => Keyword::Values,
+        "INTO" => Keyword::Into,
+        "SET" => Keyword::Set,
+        "DROP" => Keyword::Drop,
+        "FROM" => Keyword::From,
+        "WHERE" => Keyword::Where,
+        "AND" => Keyword::And,
+        "OR" => Keyword::Or,
+        "PRIMARY" => Keyword::Primary,
+        "KEY" => Keyword::Key,
+        "UNIQUE" => Keyword::Unique,
+        "TABLE" => Keyword::Table,
+        "DATABASE" => Keyword::Database,
+        "INT" => Keyword::Int,
+        "BIGINT" => Keyword::BigInt,
+        "UNSIGNED" => Keyword::Unsigned,
+        "VARCHAR" => Keyword::Varchar,
+        "CHAR" => Keyword::Char,
+        "DOUBLE" => Keyword::Double,
+        "FLOAT" => Keyword::Float,
+        "BOOL" => Keyword::Bool,
+        "TRUE" => Keyword::True,
+        "FALSE" => Keyword::False,
+        "ORDER" => Keyword::Order,
+        "BY" => Keyword::By,
+        "INDEX" => Keyword::Index,
+        "ON" => Keyword::On,
+        "START" => Keyword::Start,
+        "TRANSACTION" => Keyword::Transaction,
+        "ROLLBACK" => Keyword::Rollback,
+        "COMMIT" => Keyword::Commit,
+        "SAVEPOINT" => Keyword::Savepoint,
+        "EXPLAIN" => Keyword::Explain,
+        "ANALYZE" => Keyword::Analyze,
+        "VACUUM" => Keyword::Vacuum,
+        "LIMIT" => Keyword::Limit,
+        "OFFSET" => Keyword::Offset,
+        "ASC" => Keyword::Asc,
+        "DESC" => Keyword::Desc,
+        "COUNT" => Keyword::Count,
+        "SUM" => Keyword::Sum,
+        "AVG" => Keyword::Avg,
+        "MIN" => Keyword::Min,
+        "MAX" => Keyword::Max,
+        "GROUP" => Keyword::Group,
+        "HAVING" => Keyword::Having,
+        "JOIN" => Keyword::Join,
+        "INNER" => Keyword::Inner,
+        "AS" => Keyword::As,
+        "LIKE" => Keyword::Like,
+        "IN" => Keyword::In,
+        "NOT" => Keyword::Not,
+        "BETWEEN" => Keyword::Between,
+        "IS" => Keyword::Is,
+        "NULL" => Keyword::Null,
+        "DEFAULT" => Keyword::Default,
+        "DISTINCT" => Keyword::Distinct,
+        "ALTER" => Keyword::Alter,
+        "COLUMN" => Keyword::Column,
+        "RENAME" => Keyword::Rename,
+        "TO" => Keyword::To,
+        "CASE" => Keyword::Case,
+        "WHEN" => Keyword::When,
+        "THEN" => Keyword::Then,
+        "ELSE" => Keyword::Else,
+        "END" => Keyword::End,
+        "IF" => Keyword::If,
+        "EXISTS" => Keyword::Exists,
+        _ => Keyword::None,
+    }
 }
 
 /// Struct returned by [`Tokenizer::iter`].
@@ -449,8 +843,10 @@ impl<'i> IntoIterator for Tokenizer<'i> {
 
 #[cfg(test)]
 mod tests {
-    use super::{ErrorKind, Keyword, Token, Tokenizer, Whitespace};
-    use crate::sql::tokenizer::{Location, TokenizerError};
+    use std::collections::HashMap;
+
+    use super::{ErrorKind, Keyword, Limits, Token, Tokenizer, Whitespace};
+    use crate::sql::tokenizer::{Location, TokenWithLocation, TokenizerError};
 
     #[test]
     fn tokenize_simple_select() {
@@ -711,6 +1107,720 @@ mod tests {
         );
     }
 
+    #[test]
+    fn tokenize_coalesced_whitespace() {
+        let sql = "SELECT  \n\t  id\r\n\n FROM users;";
+
+        assert_eq!(
+            Tokenizer::new(sql).iter().collect::<Result<Vec<_>, _>>(),
+            Ok(vec![
+                TokenWithLocation {
+                    variant: Token::Keyword(Keyword::Select),
+                    location: Location {
+                        line: 1,
+                        col: 1,
+                        byte_offset: 0
+                    },
+                    end: Location {
+                        line: 1,
+                        col: 7,
+                        byte_offset: 6
+                    },
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Space),
+                    location: Location {
+                        line: 1,
+                        col: 7,
+                        byte_offset: 6
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 4,
+                        byte_offset: 12
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Identifier("id".into()),
+                    location: Location {
+                        line: 2,
+                        col: 4,
+                        byte_offset: 12
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 6,
+                        byte_offset: 14
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Newline),
+                    location: Location {
+                        line: 2,
+                        col: 6,
+                        byte_offset: 14
+                    },
+                    end: Location {
+                        line: 4,
+                        col: 2,
+                        byte_offset: 18
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Keyword(Keyword::From),
+                    location: Location {
+                        line: 4,
+                        col: 2,
+                        byte_offset: 18
+                    },
+                    end: Location {
+                        line: 4,
+                        col: 6,
+                        byte_offset: 22
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Space),
+                    location: Location {
+                        line: 4,
+                        col: 6,
+                        byte_offset: 22
+                    },
+                    end: Location {
+                        line: 4,
+                        col: 7,
+                        byte_offset: 23
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Identifier("users".into()),
+                    location: Location {
+                        line: 4,
+                        col: 7,
+                        byte_offset: 23
+                    },
+                    end: Location {
+                        line: 4,
+                        col: 12,
+                        byte_offset: 28
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::SemiColon,
+                    location: Location {
+                        line: 4,
+                        col: 12,
+                        byte_offset: 28
+                    },
+                    end: Location {
+                        line: 4,
+                        col: 13,
+                        byte_offset: 29
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Eof,
+                    location: Location {
+                        line: 4,
+                        col: 13,
+                        byte_offset: 29
+                    },
+                    end: Location {
+                        line: 4,
+                        col: 13,
+                        byte_offset: 29
+                    }
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn tokenize_line_comments() {
+        let sql = "SELECT id -- primary key\nFROM users; --";
+
+        assert_eq!(
+            Tokenizer::new(sql).iter().collect::<Result<Vec<_>, _>>(),
+            Ok(vec![
+                TokenWithLocation {
+                    variant: Token::Keyword(Keyword::Select),
+                    location: Location {
+                        line: 1,
+                        col: 1,
+                        byte_offset: 0
+                    },
+                    end: Location {
+                        line: 1,
+                        col: 7,
+                        byte_offset: 6
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Space),
+                    location: Location {
+                        line: 1,
+                        col: 7,
+                        byte_offset: 6
+                    },
+                    end: Location {
+                        line: 1,
+                        col: 8,
+                        byte_offset: 7
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Identifier("id".into()),
+                    location: Location {
+                        line: 1,
+                        col: 8,
+                        byte_offset: 7
+                    },
+                    end: Location {
+                        line: 1,
+                        col: 10,
+                        byte_offset: 9
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Space),
+                    location: Location {
+                        line: 1,
+                        col: 10,
+                        byte_offset: 9
+                    },
+                    end: Location {
+                        line: 1,
+                        col: 11,
+                        byte_offset: 10
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Comment),
+                    location: Location {
+                        line: 1,
+                        col: 11,
+                        byte_offset: 10
+                    },
+                    end: Location {
+                        line: 1,
+                        col: 25,
+                        byte_offset: 24
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Newline),
+                    location: Location {
+                        line: 1,
+                        col: 25,
+                        byte_offset: 24
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 1,
+                        byte_offset: 25
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Keyword(Keyword::From),
+                    location: Location {
+                        line: 2,
+                        col: 1,
+                        byte_offset: 25
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 5,
+                        byte_offset: 29
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Space),
+                    location: Location {
+                        line: 2,
+                        col: 5,
+                        byte_offset: 29
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 6,
+                        byte_offset: 30
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Identifier("users".into()),
+                    location: Location {
+                        line: 2,
+                        col: 6,
+                        byte_offset: 30
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 11,
+                        byte_offset: 35
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::SemiColon,
+                    location: Location {
+                        line: 2,
+                        col: 11,
+                        byte_offset: 35
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 12,
+                        byte_offset: 36
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Space),
+                    location: Location {
+                        line: 2,
+                        col: 12,
+                        byte_offset: 36
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 13,
+                        byte_offset: 37
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Comment),
+                    location: Location {
+                        line: 2,
+                        col: 13,
+                        byte_offset: 37
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 15,
+                        byte_offset: 39
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Eof,
+                    location: Location {
+                        line: 2,
+                        col: 15,
+                        byte_offset: 39
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 15,
+                        byte_offset: 39
+                    }
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn tokenize_minus_followed_by_comment() {
+        assert_eq!(
+            Tokenizer::new("5--2\n- 2").tokenize(),
+            Ok(vec![
+                Token::Number("5".into()),
+                Token::Whitespace(Whitespace::Comment),
+                Token::Whitespace(Whitespace::Newline),
+                Token::Minus,
+                Token::Whitespace(Whitespace::Space),
+                Token::Number("2".into()),
+                Token::Eof,
+            ])
+        );
+    }
+
+    #[test]
+    fn tokenize_block_comments() {
+        let sql = "SELECT /* all\n columns */ * FROM users; /**/";
+
+        assert_eq!(
+            Tokenizer::new(sql).iter().collect::<Result<Vec<_>, _>>(),
+            Ok(vec![
+                TokenWithLocation {
+                    variant: Token::Keyword(Keyword::Select),
+                    location: Location {
+                        line: 1,
+                        col: 1,
+                        byte_offset: 0
+                    },
+                    end: Location {
+                        line: 1,
+                        col: 7,
+                        byte_offset: 6
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Space),
+                    location: Location {
+                        line: 1,
+                        col: 7,
+                        byte_offset: 6
+                    },
+                    end: Location {
+                        line: 1,
+                        col: 8,
+                        byte_offset: 7
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Comment),
+                    location: Location {
+                        line: 1,
+                        col: 8,
+                        byte_offset: 7
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 12,
+                        byte_offset: 25
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Space),
+                    location: Location {
+                        line: 2,
+                        col: 12,
+                        byte_offset: 25
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 13,
+                        byte_offset: 26
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Mul,
+                    location: Location {
+                        line: 2,
+                        col: 13,
+                        byte_offset: 26
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 14,
+                        byte_offset: 27
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Space),
+                    location: Location {
+                        line: 2,
+                        col: 14,
+                        byte_offset: 27
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 15,
+                        byte_offset: 28
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Keyword(Keyword::From),
+                    location: Location {
+                        line: 2,
+                        col: 15,
+                        byte_offset: 28
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 19,
+                        byte_offset: 32
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Space),
+                    location: Location {
+                        line: 2,
+                        col: 19,
+                        byte_offset: 32
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 20,
+                        byte_offset: 33
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Identifier("users".into()),
+                    location: Location {
+                        line: 2,
+                        col: 20,
+                        byte_offset: 33
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 25,
+                        byte_offset: 38
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::SemiColon,
+                    location: Location {
+                        line: 2,
+                        col: 25,
+                        byte_offset: 38
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 26,
+                        byte_offset: 39
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Space),
+                    location: Location {
+                        line: 2,
+                        col: 26,
+                        byte_offset: 39
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 27,
+                        byte_offset: 40
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Whitespace(Whitespace::Comment),
+                    location: Location {
+                        line: 2,
+                        col: 27,
+                        byte_offset: 40
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 31,
+                        byte_offset: 44
+                    }
+                },
+                TokenWithLocation {
+                    variant: Token::Eof,
+                    location: Location {
+                        line: 2,
+                        col: 31,
+                        byte_offset: 44
+                    },
+                    end: Location {
+                        line: 2,
+                        col: 31,
+                        byte_offset: 44
+                    }
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn tokenize_div_is_not_comment() {
+        assert_eq!(
+            Tokenizer::new("4/2").tokenize(),
+            Ok(vec![
+                Token::Number("4".into()),
+                Token::Div,
+                Token::Number("2".into()),
+                Token::Eof,
+            ])
+        );
+    }
+
+    #[test]
+    fn tokenize_concat_operator() {
+        assert_eq!(
+            Tokenizer::new("name||'!'").tokenize(),
+            Ok(vec![
+                Token::Identifier("name".into()),
+                Token::Concat,
+                Token::String("!".into()),
+                Token::Eof,
+            ])
+        );
+    }
+
+    #[test]
+    fn tokenize_angle_brackets_neq_operator() {
+        assert_eq!(
+            Tokenizer::new("a <> b").tokenize(),
+            Ok(vec![
+                Token::Identifier("a".into()),
+                Token::Whitespace(Whitespace::Space),
+                Token::Neq,
+                Token::Whitespace(Whitespace::Space),
+                Token::Identifier("b".into()),
+                Token::Eof,
+            ])
+        );
+
+        assert_eq!(
+            Tokenizer::new("a<b<=c").tokenize(),
+            Ok(vec![
+                Token::Identifier("a".into()),
+                Token::Lt,
+                Token::Identifier("b".into()),
+                Token::LtEq,
+                Token::Identifier("c".into()),
+                Token::Eof,
+            ])
+        );
+    }
+
+    #[test]
+    fn tokenize_floats() {
+        assert_eq!(
+            Tokenizer::new("3.14 * 2.").tokenize(),
+            Ok(vec![
+                Token::Number("3.14".into()),
+                Token::Whitespace(Whitespace::Space),
+                Token::Mul,
+                Token::Whitespace(Whitespace::Space),
+                Token::Number("2.".into()),
+                Token::Eof,
+            ])
+        );
+    }
+
+    #[test]
+    fn tokenize_qualified_identifiers() {
+        assert_eq!(
+            Tokenizer::new("users.id = 1.5").tokenize(),
+            Ok(vec![
+                Token::Identifier("users".into()),
+                Token::Dot,
+                Token::Identifier("id".into()),
+                Token::Whitespace(Whitespace::Space),
+                Token::Eq,
+                Token::Whitespace(Whitespace::Space),
+                Token::Number("1.5".into()),
+                Token::Eof,
+            ])
+        );
+    }
+
+    #[test]
+    fn token_spans_cover_all_consumed_chars() {
+        let sql = "name VARCHAR(255) >= 'añejo'";
+
+        let spans = Tokenizer::new(sql)
+            .iter()
+            .map(|token| token.map(|token| &sql[token.location.byte_offset..token.end.byte_offset]))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(spans, vec![
+            "name", " ", "VARCHAR", "(", "255", ")", " ", ">=", " ", "'añejo'", ""
+        ]);
+
+        let tokens = Tokenizer::new(sql)
+            .iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!((tokens[4].location.col, tokens[4].end.col), (14, 17));
+        assert_eq!((tokens[9].location.col, tokens[9].end.col), (22, 29));
+    }
+
+    #[test]
+    fn track_byte_offset_of_multi_byte_chars() {
+        let sql = "SELECT 'añejo', 'ünïcödé'\n, id;";
+
+        let locations = Tokenizer::new(sql)
+            .iter()
+            .map(|token| token.map(|token| token.location))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(locations[5], Location {
+            line: 1,
+            col: 17,
+            byte_offset: 17
+        });
+        assert_eq!(locations[7], Location {
+            line: 2,
+            col: 1,
+            byte_offset: 31
+        });
+        assert_eq!(&sql[locations[9].byte_offset..], "id;");
+    }
+
+    #[test]
+    fn tokenize_mixed_case_keywords() {
+        assert_eq!(
+            Tokenizer::new("SeLeCt Transaction FROM\tselected").tokenize(),
+            Ok(vec![
+                Token::Keyword(Keyword::Select),
+                Token::Whitespace(Whitespace::Space),
+                Token::Keyword(Keyword::Transaction),
+                Token::Whitespace(Whitespace::Space),
+                Token::Keyword(Keyword::From),
+                Token::Whitespace(Whitespace::Tab),
+                Token::Identifier("selected".into()),
+                Token::Eof,
+            ])
+        );
+
+        assert_eq!(
+            Tokenizer::new("a_very_long_identifier_name").tokenize(),
+            Ok(vec![
+                Token::Identifier("a_very_long_identifier_name".into()),
+                Token::Eof,
+            ])
+        );
+    }
+
+    #[test]
+    fn tokenize_custom_keywords() {
+        let sql = "SELECT key FETCH 10";
+        let keywords = HashMap::from([
+            (String::from("fetch"), Keyword::Limit),
+            (String::from("KEY"), Keyword::None),
+        ]);
+
+        assert_eq!(
+            Tokenizer::with_keywords(sql, keywords).tokenize(),
+            Ok(vec![
+                Token::Keyword(Keyword::Select),
+                Token::Whitespace(Whitespace::Space),
+                Token::Identifier("key".into()),
+                Token::Whitespace(Whitespace::Space),
+                Token::Keyword(Keyword::Limit),
+                Token::Whitespace(Whitespace::Space),
+                Token::Number("10".into()),
+                Token::Eof,
+            ])
+        );
+
+        assert_eq!(
+            Tokenizer::new(sql).tokenize(),
+            Ok(vec![
+                Token::Keyword(Keyword::Select),
+                Token::Whitespace(Whitespace::Space),
+                Token::Keyword(Keyword::Key),
+                Token::Whitespace(Whitespace::Space),
+                Token::Identifier("FETCH".into()),
+                Token::Whitespace(Whitespace::Space),
+                Token::Number("10".into()),
+                Token::Eof,
+            ])
+        );
+    }
+
+    #[test]
+    fn tokenize_block_comment_not_closed() {
+        let sql = "SELECT 1;\n  /* not closed *";
+        assert_eq!(
+            Tokenizer::new(sql).tokenize(),
+            Err(TokenizerError {
+                kind: ErrorKind::CommentNotClosed,
+                location: Location {
+                    line: 2,
+                    col: 3,
+                    byte_offset: 12
+                },
+                input: sql.to_owned(),
+            })
+        );
+    }
+
     #[test]
     fn tokenize_single_quoted_string() {
         let string = "single quoted \"string\"";
@@ -730,7 +1840,11 @@ mod tests {
                     unexpected: ' ',
                     operator: Token::Neq
                 },
-                location: Location { line: 1, col: 35 },
+                location: Location {
+                    line: 1,
+                    col: 35,
+                    byte_offset: 34
+                },
                 input: sql.to_owned(),
             })
         );
@@ -743,7 +1857,31 @@ mod tests {
             Tokenizer::new(sql).tokenize(),
             Err(TokenizerError {
                 kind: ErrorKind::OperatorNotClosed(Token::Neq),
-                location: Location { line: 1, col: 35 },
+                location: Location {
+                    line: 1,
+                    col: 35,
+                    byte_offset: 34
+                },
+                input: sql.to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn tokenize_incorrect_concat_operator() {
+        let sql = "SELECT a | b FROM table";
+        assert_eq!(
+            Tokenizer::new(sql).tokenize(),
+            Err(TokenizerError {
+                kind: ErrorKind::UnexpectedWhileParsingOperator {
+                    unexpected: ' ',
+                    operator: Token::Concat
+                },
+                location: Location {
+                    line: 1,
+                    col: 11,
+                    byte_offset: 10
+                },
                 input: sql.to_owned(),
             })
         );
@@ -756,7 +1894,11 @@ mod tests {
             Tokenizer::new(sql).tokenize(),
             Err(TokenizerError {
                 kind: ErrorKind::StringNotClosed,
-                location: Location { line: 1, col: 47 },
+                location: Location {
+                    line: 1,
+                    col: 47,
+                    byte_offset: 46
+                },
                 input: sql.to_owned(),
             })
         );
@@ -769,12 +1911,188 @@ mod tests {
             Tokenizer::new(sql).tokenize(),
             Err(TokenizerError {
                 kind: ErrorKind::StringNotClosed,
-                location: Location { line: 1, col: 47 },
+                location: Location {
+                    line: 1,
+                    col: 47,
+                    byte_offset: 46
+                },
+                input: sql.to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn display_error_with_caret() {
+        let sql = "SELECT *\nFROM users\nWHERE name = 'John;";
+        let error = Tokenizer::new(sql).tokenize().unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            [
+                "Tokenizer Error at line 3 column 20: string not closed",
+                "WHERE name = 'John;",
+                "                   ^",
+            ]
+            .join("\n")
+        );
+
+        let error = Tokenizer::new("SELECT\t!x").tokenize().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            [
+                "Tokenizer Error at line 1 column 9: unexpected token 'x' while parsing '!=' operator",
+                "SELECT\t!x",
+                "      \t ^",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn tokenize_escaped_quotes_in_strings() {
+        let sql = r#"'it''s' "say ""hi""" ''''"#;
+        assert_eq!(
+            Tokenizer::new(sql).tokenize(),
+            Ok(vec![
+                Token::String("it's".into()),
+                Token::Whitespace(Whitespace::Space),
+                Token::String("say \"hi\"".into()),
+                Token::Whitespace(Whitespace::Space),
+                Token::String("'".into()),
+                Token::Eof,
+            ])
+        );
+    }
+
+    #[test]
+    fn tokenize_quoted_identifiers() {
+        let sql = "SELECT `order`, `we``ird` FROM `select`;";
+        assert_eq!(
+            Tokenizer::new(sql).tokenize(),
+            Ok(vec![
+                Token::Keyword(Keyword::Select),
+                Token::Whitespace(Whitespace::Space),
+                Token::Identifier("order".into()),
+                Token::Comma,
+                Token::Whitespace(Whitespace::Space),
+                Token::Identifier("we`ird".into()),
+                Token::Whitespace(Whitespace::Space),
+                Token::Keyword(Keyword::From),
+                Token::Whitespace(Whitespace::Space),
+                Token::Identifier("select".into()),
+                Token::SemiColon,
+                Token::Eof,
+            ])
+        );
+    }
+
+    #[test]
+    fn tokenize_quoted_identifier_not_closed() {
+        let sql = "SELECT `not closed";
+        assert_eq!(
+            Tokenizer::new(sql).tokenize(),
+            Err(TokenizerError {
+                kind: ErrorKind::IdentifierNotClosed,
+                location: Location {
+                    line: 1,
+                    col: 19,
+                    byte_offset: 18
+                },
                 input: sql.to_owned(),
             })
         );
     }
 
+    #[test]
+    fn tokenize_input_too_large() {
+        let sql = "SELECT * FROM users;";
+        let limits = Limits {
+            max_input_len: Some(sql.len() - 1),
+            max_tokens: None,
+        };
+
+        assert_eq!(
+            Tokenizer::with_limits(sql, limits).tokenize(),
+            Err(TokenizerError {
+                kind: ErrorKind::InputTooLarge,
+                location: Location {
+                    line: 1,
+                    col: 1,
+                    byte_offset: 0
+                },
+                input: String::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn tokenize_too_many_tokens() {
+        let sql = "SELECT * FROM users;";
+        let limits = Limits {
+            max_input_len: None,
+            max_tokens: Some(4),
+        };
+
+        assert_eq!(
+            Tokenizer::with_limits(sql, limits).tokenize(),
+            Err(TokenizerError {
+                kind: ErrorKind::InputTooLarge,
+                location: Location {
+                    line: 1,
+                    col: 10,
+                    byte_offset: 9
+                },
+                input: String::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn input_too_large_error_has_no_snippet() {
+        let sql = "SELECT * FROM users;";
+        let limits = Limits {
+            max_input_len: Some(1),
+            max_tokens: None,
+        };
+
+        let error = Tokenizer::with_limits(sql, limits).tokenize().unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Tokenizer Error at line 1 column 1: input exceeds maximum length or token count"
+        );
+    }
+
+    #[test]
+    fn tokenize_within_limits() {
+        let sql = "SELECT * FROM users;";
+        let expected = Ok(vec![
+            Token::Keyword(Keyword::Select),
+            Token::Whitespace(Whitespace::Space),
+            Token::Mul,
+            Token::Whitespace(Whitespace::Space),
+            Token::Keyword(Keyword::From),
+            Token::Whitespace(Whitespace::Space),
+            Token::Identifier("users".into()),
+            Token::SemiColon,
+            Token::Eof,
+        ]);
+
+        let exact = Limits {
+            max_input_len: Some(sql.len()),
+            max_tokens: Some(8),
+        };
+
+        let unlimited = Limits {
+            max_input_len: None,
+            max_tokens: None,
+        };
+
+        assert_eq!(Tokenizer::new(sql).tokenize(), expected);
+        assert_eq!(Tokenizer::with_limits(sql, exact).tokenize(), expected);
+        assert_eq!(Tokenizer::with_limits(sql, unlimited).tokenize(), expected);
+    }
+
     #[test]
     fn tokenize_unsupported_token() {
         let sql = "SELECT * FROM ^ WHERE unsupported = 1;";
@@ -782,7 +2100,11 @@ mod tests {
             Tokenizer::new(sql).tokenize(),
             Err(TokenizerError {
                 kind: ErrorKind::UnexpectedOrUnsupportedToken('^'),
-                location: Location { line: 1, col: 15 },
+                location: Location {
+                    line: 1,
+                    col: 15,
+                    byte_offset: 14
+                },
                 input: sql.to_owned(),
             })
         );
@@ -7,18 +7,21 @@ use std::{
     collections::{BinaryHeap, HashSet, VecDeque},
     io::{self, Read, Seek, Write},
     mem,
+    rc::Rc,
 };
 
 use super::{
     page::{Cell, OverflowPage, Page, SlotId},
-    tuple::{byte_length_of_integer_type, utf8_length_prefix_bytes},
+    tuple::{
+        byte_length_of_integer_type, read_length_prefix, utf8_length_prefix_bytes, FormatVersion,
+    },
 };
 use crate::{
     paging::{
         io::FileOps,
         pager::{PageNumber, Pager},
     },
-    sql::statement::DataType,
+    sql::statement::{DataType, Direction},
 };
 
 /// [`BTree`] key comparator. Entries are stored in binary, so we need a way to
@@ -87,12 +90,32 @@ impl TryFrom<&DataType> for FixedSizeMemCmp {
 
     fn try_from(data_type: &DataType) -> Result<Self, Self::Error> {
         match data_type {
-            DataType::Varchar(_) | DataType::Bool => Err(()),
+            DataType::Varchar(_) | DataType::Char(_) | DataType::Bool | DataType::Double => Err(()),
             fixed => Ok(Self(byte_length_of_integer_type(fixed))),
         }
     }
 }
 
+/// Compares 8 byte big endian IEEE-754 floats.
+///
+/// The sign bit doesn't allow us to compare the bytes with memcmp(), so the
+/// floats are decoded first. [`f64::total_cmp`] makes sure that `NaN` values
+/// can't break the BTree order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FloatCmp;
+
+impl BytesCmp for FloatCmp {
+    fn bytes_cmp(&self, a: &[u8], b: &[u8]) -> Ordering {
+        let a = f64::from_be_bytes(a[..mem::size_of::<f64>()].try_into().unwrap());
+        let b = f64::from_be_bytes(b[..mem::size_of::<f64>()].try_into().unwrap());
+
+        // total_cmp() orders -0.0 before 0.0 but SQL considers them equal.
+        let normalize = |float: f64| if float == 0.0 { 0.0 } else { float };
+
+        normalize(a).total_cmp(&normalize(b))
+    }
+}
+
 /// Compares UTF-8 strings.
 ///
 /// Assumes that the buffers have this format:
@@ -110,28 +133,29 @@ impl TryFrom<&DataType> for FixedSizeMemCmp {
 /// ```
 ///
 /// Then computes the total length of the string in bytes by reading the first
-/// `self.0` bytes as a little endian integer and once the total length is known
-/// [`str`] instances can be created.
+/// [`Self::prefix_bytes`] bytes as an integer whose byte order depends on the
+/// [`FormatVersion`] and once the total length is known [`str`] instances can
+/// be created.
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub(crate) struct StringCmp(pub usize);
+pub(crate) struct StringCmp {
+    /// Number of bytes used to store the length of the string.
+    pub prefix_bytes: usize,
+    /// Serialization format of the strings.
+    pub version: FormatVersion,
+}
 
 impl BytesCmp for StringCmp {
     fn bytes_cmp(&self, a: &[u8], b: &[u8]) -> Ordering {
         debug_assert!(
-            self.0 <= 4,
+            self.prefix_bytes <= 4,
             "strings longer than {} bytes are not supported",
             u32::MAX
         );
 
-        let mut buf = [0; std::mem::size_of::<usize>()];
+        let prefix = self.prefix_bytes;
 
-        buf[..self.0].copy_from_slice(&a[..self.0]);
-        let len_a = usize::from_le_bytes(buf);
-
-        buf.fill(0);
-
-        buf[..self.0].copy_from_slice(&b[..self.0]);
-        let len_b = usize::from_le_bytes(buf);
+        let len_a = read_length_prefix(self.version, &a[..prefix]);
+        let len_b = read_length_prefix(self.version, &b[..prefix]);
 
         // TODO: Not 100% sure if unwrap() can actually panic here. When we
         // insert data we already have a valid [`String`] instance which is
@@ -139,23 +163,9 @@ impl BytesCmp for StringCmp {
         // serialize it into binary. If unwrap() can't panic then we should
         // use the unchecked version of from_utf8() that doesn't loop through
         // the entire string to check that all bytes are valid UTF-8.
-        std::str::from_utf8(&a[self.0..self.0 + len_a])
+        std::str::from_utf8(&a[prefix..prefix + len_a])
             .unwrap()
-            .cmp(std::str::from_utf8(&b[self.0..self.0 + len_b]).unwrap())
-    }
-}
-
-impl From<&DataType> for Box<dyn BytesCmp> {
-    /// Easy way to obtain a [`BytesCmp`] impl at runtime based on SQL data
-    /// types.
-    fn from(data_type: &DataType) -> Self {
-        match data_type {
-            DataType::Varchar(max_characters) => {
-                Box::new(StringCmp(utf8_length_prefix_bytes(*max_characters)))
-            }
-
-            fixed => Box::new(FixedSizeMemCmp(byte_length_of_integer_type(fixed))),
-        }
+            .cmp(std::str::from_utf8(&b[prefix..prefix + len_b]).unwrap())
     }
 }
 
@@ -173,24 +183,86 @@ impl BytesCmp for Box<dyn BytesCmp> {
 
 /// [`Box<dyn BytesCmp>`] kinda sucks.
 ///
-/// Jump table based dynamic dispatch for the win. No allocations, easy
-/// [`Debug`] impl, [`Copy`], etc.
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Jump table based dynamic dispatch for the win. No allocations (except for
+/// multi-column keys), easy [`Debug`] impl, cheap [`Clone`], etc.
+#[derive(Debug, Clone, PartialEq)]
 pub(crate) enum BTreeKeyComparator {
     MemCmp(FixedSizeMemCmp),
     StrCmp(StringCmp),
+    Float(FloatCmp),
+    /// Keys made of multiple columns serialized one after the other. They are
+    /// compared column by column and the first column that is not equal
+    /// decides the order.
+    Composite(Rc<[BTreeKeyComparator]>),
+    /// Reverses the order of another comparator. Used by `DESC` indexes.
+    Desc(Rc<BTreeKeyComparator>),
 }
 
-impl From<&DataType> for BTreeKeyComparator {
-    fn from(data_type: &DataType) -> Self {
+impl BTreeKeyComparator {
+    /// Returns the comparator for BTree keys of the given type serialized with
+    /// the given format version.
+    pub fn new(data_type: &DataType, version: FormatVersion) -> Self {
         match data_type {
-            DataType::Varchar(max_characters) => {
-                Self::StrCmp(StringCmp(utf8_length_prefix_bytes(*max_characters)))
-            }
+            DataType::Varchar(max_characters) => Self::StrCmp(StringCmp {
+                prefix_bytes: utf8_length_prefix_bytes(*max_characters),
+                version,
+            }),
+
+            DataType::Char(length) => Self::MemCmp(FixedSizeMemCmp(*length)),
+
+            DataType::Double => Self::Float(FloatCmp),
 
             fixed => Self::MemCmp(FixedSizeMemCmp(byte_length_of_integer_type(fixed))),
         }
     }
+
+    /// Same as [`Self::new`] but keys are sorted in the given `direction`.
+    pub fn with_direction(
+        data_type: &DataType,
+        direction: Direction,
+        version: FormatVersion,
+    ) -> Self {
+        match direction {
+            Direction::Asc => Self::new(data_type, version),
+            Direction::Desc => Self::Desc(Rc::new(Self::new(data_type, version))),
+        }
+    }
+
+    /// Returns the comparator for keys composed of columns of the given types
+    /// and sort directions.
+    ///
+    /// Keys with only one column don't need [`Self::Composite`].
+    pub fn composite<'d>(
+        columns: impl IntoIterator<Item = (&'d DataType, Direction)>,
+        version: FormatVersion,
+    ) -> Self {
+        let mut comparators: Vec<_> = columns
+            .into_iter()
+            .map(|(data_type, direction)| Self::with_direction(data_type, direction, version))
+            .collect();
+
+        if comparators.len() == 1 {
+            comparators.remove(0)
+        } else {
+            Self::Composite(comparators.into())
+        }
+    }
+
+    /// Number of bytes taken by the key at the beginning of `buf`.
+    fn key_len(&self, buf: &[u8]) -> usize {
+        match self {
+            Self::MemCmp(FixedSizeMemCmp(size)) => *size,
+            Self::Float(_) => mem::size_of::<f64>(),
+            Self::StrCmp(StringCmp {
+                prefix_bytes,
+                version,
+            }) => prefix_bytes + read_length_prefix(*version, &buf[..*prefix_bytes]),
+            Self::Composite(comparators) => comparators
+                .iter()
+                .fold(0, |len, comparator| len + comparator.key_len(&buf[len..])),
+            Self::Desc(comparator) => comparator.key_len(buf),
+        }
+    }
 }
 
 impl BytesCmp for BTreeKeyComparator {
@@ -198,6 +270,23 @@ impl BytesCmp for BTreeKeyComparator {
         match self {
             Self::MemCmp(mem_cmp) => mem_cmp.bytes_cmp(a, b),
             Self::StrCmp(str_cmp) => str_cmp.bytes_cmp(a, b),
+            Self::Float(float_cmp) => float_cmp.bytes_cmp(a, b),
+            Self::Composite(comparators) => {
+                let (mut a, mut b) = (a, b);
+
+                for comparator in comparators.iter() {
+                    let ordering = comparator.bytes_cmp(a, b);
+                    if ordering != Ordering::Equal {
+                        return ordering;
+                    }
+
+                    a = &a[comparator.key_len(a)..];
+                    b = &b[comparator.key_len(b)..];
+                }
+
+                Ordering::Equal
+            }
+            Self::Desc(comparator) => comparator.bytes_cmp(a, b).reverse(),
         }
     }
 }
@@ -682,6 +771,42 @@ impl<'p, F: Seek + Read + Write + FileOps, C: BytesCmp> BTree<'p, F, C> {
         self.balance(search.page, &mut parents)
     }
 
+    /// Overwrites the content of an existing entry without allocating a new
+    /// cell.
+    ///
+    /// This only works if the key already exists and the new entry needs
+    /// exactly the same amount of space as the previous one, which is usually
+    /// the case when updating fixed size columns. Since the cell doesn't move
+    /// there's no need to call [`Self::balance`]. Returns `false` if the
+    /// entry could not be written in place, in which case the caller should
+    /// fall back to [`Self::insert`].
+    pub fn update_in_place(&mut self, entry: &[u8]) -> io::Result<bool> {
+        let max_payload_size =
+            Page::ideal_max_payload_size(self.pager.page_size, self.minimum_keys) as usize;
+
+        if entry.len() > max_payload_size {
+            return Ok(false);
+        }
+
+        let search = self.search(self.root, entry, &mut Vec::new())?;
+
+        let Ok(index) = search.index else {
+            return Ok(false);
+        };
+
+        let cell = self.pager.get(search.page)?.cell(index);
+
+        if cell.header.is_overflow || cell.content.len() != Cell::aligned_size_of(entry) as usize {
+            return Ok(false);
+        }
+
+        let cell = self.pager.get_mut(search.page)?.cell_mut(index);
+        cell.content[..entry.len()].copy_from_slice(entry);
+        cell.content[entry.len()..].fill(0);
+
+        Ok(true)
+    }
+
     /// Same as [`Self::insert`] but doesn't update the key if it already
     /// exists.
     ///
@@ -2078,6 +2203,8 @@ pub(crate) struct Cursor {
     init: bool,
     /// `true` if there are no more elements to return.
     done: bool,
+    /// Return entries in descending order. See [`Self::try_prev`].
+    reverse: bool,
 }
 
 impl Cursor {
@@ -2091,6 +2218,7 @@ impl Cursor {
             descent: vec![],
             init: false,
             done: false,
+            reverse: false,
         }
     }
 
@@ -2105,6 +2233,7 @@ impl Cursor {
             descent,
             init: true,
             done: false,
+            reverse: false,
         }
     }
 
@@ -2116,9 +2245,23 @@ impl Cursor {
             descent: vec![],
             init: true,
             done: true,
+            reverse: false,
         }
     }
 
+    /// Makes the cursor move backwards, from the greatest key to the smallest.
+    ///
+    /// Uninitialized cursors start at the rightmost key of the subtree.
+    pub fn reverse(mut self) -> Self {
+        self.reverse = true;
+        self
+    }
+
+    /// `true` if the cursor moves backwards. See [`Self::reverse`].
+    pub fn is_reverse(&self) -> bool {
+        self.reverse
+    }
+
     /// Moves the cursor to the leftmost node in the current subtree.
     ///
     /// ```text
@@ -2159,6 +2302,27 @@ impl Cursor {
         Ok(())
     }
 
+    /// Moves the cursor to the rightmost key in the current subtree.
+    ///
+    /// Same as [`Self::move_to_leftmost`] but following the last child of
+    /// each node instead of the first one.
+    fn move_to_rightmost<F: Seek + Read + Write + FileOps>(
+        &mut self,
+        pager: &mut Pager<F>,
+    ) -> io::Result<()> {
+        let mut node = pager.get(self.page)?;
+
+        while !node.is_leaf() {
+            self.descent.push(self.page);
+            self.page = node.child(node.len());
+            node = pager.get(self.page)?;
+        }
+
+        self.slot = node.len().saturating_sub(1);
+
+        Ok(())
+    }
+
     /// Disk BTree traversal algorithm.
     ///
     /// Traversing a disk BTree with an iterator that maintains its state (which
@@ -2355,6 +2519,10 @@ impl Cursor {
             return Ok(None);
         }
 
+        if self.reverse {
+            return self.try_prev(pager);
+        }
+
         // This only runs on the first call to `try_next`. Lazy initialization
         // for the iterator.
         if !self.init {
@@ -2419,6 +2587,74 @@ impl Cursor {
         position
     }
 
+    /// Reverse in-order traversal used by [`Self::reverse`] cursors.
+    ///
+    /// Mirror image of [`Self::try_next`]: start at the rightmost key, after
+    /// returning a key from an internal node move to the bottom right of the
+    /// subtree at its left and when a subtree is exhausted go back upwards
+    /// until we find a parent with keys on the left.
+    ///
+    /// ```text
+    /// Key Order:     [16, 15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1]
+    /// Visited Pages: [1, 8, 6, 8, 5, 8, 1, 7, 4, 7, 3, 7, 2, 7, 1]
+    /// ```
+    fn try_prev<F: Seek + Read + Write + FileOps>(
+        &mut self,
+        pager: &mut Pager<F>,
+    ) -> io::Result<Option<(PageNumber, SlotId)>> {
+        if !self.init {
+            self.move_to_rightmost(pager)?;
+            self.init = true;
+        }
+
+        let node = pager.get(self.page)?;
+
+        if node.is_empty() && node.is_leaf() {
+            self.done = true;
+            return Ok(None);
+        }
+
+        let position = Ok(Some((self.page, self.slot)));
+
+        // Still have keys on the left in this leaf.
+        if node.is_leaf() && self.slot > 0 {
+            self.slot -= 1;
+            return position;
+        }
+
+        // Internal node key, the previous one is the greatest key of the
+        // subtree at its left.
+        if !node.is_leaf() {
+            self.descent.push(self.page);
+            self.page = node.child(self.slot);
+            self.move_to_rightmost(pager)?;
+
+            return position;
+        }
+
+        // Done with the subtree, go back upwards until we find a parent that
+        // has a key at the left of the branch we came from.
+        let mut found_branch = false;
+
+        while !self.descent.is_empty() && !found_branch {
+            let parent_page = self.descent.pop().unwrap();
+            let parent = pager.get(parent_page)?;
+            let index = parent.iter_children().position(|c| c == self.page).unwrap() as u16;
+            self.page = parent_page;
+
+            if index > 0 {
+                self.slot = index - 1;
+                found_branch = true;
+            }
+        }
+
+        if self.descent.is_empty() && !found_branch {
+            self.done = true;
+        }
+
+        position
+    }
+
     /// Returns the next position in the BTree.
     ///
     /// See [`Self::try_next`] for the actual code. This one just flips
@@ -2501,7 +2737,7 @@ mod tests {
     }
 
     fn init_pager(builder: pager::Builder) -> io::Result<Pager<MemBuf>> {
-        let mut pager = builder.wrap(io::Cursor::new(Vec::new()));
+        let mut pager = builder.wrap(MemBuf::default());
         pager.init()?;
 
         Ok(pager)
@@ -3906,6 +4142,30 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn update_in_place() -> io::Result<()> {
+        let pager = &mut pager_with_page_size(optimal_page_size_for_max_payload(16, 3))?;
+        let mut btree = BTree::test().on(pager)?;
+
+        for key in 1..=9 {
+            btree.insert(serialize_key_of_size(key, 16))?;
+        }
+
+        let structure = btree.into_test_nodes(btree.root)?;
+
+        let mut entry = serialize_key_of_size(5, 16);
+        entry[8..].fill(1);
+
+        assert!(btree.update_in_place(&entry)?);
+        assert_eq!(btree.get(&entry)?, Some(Payload::PageRef(&entry)));
+        assert_eq!(btree.into_test_nodes(btree.root)?, structure);
+
+        assert!(!btree.update_in_place(&serialize_key_of_size(5, 24))?);
+        assert!(!btree.update_in_place(&serialize_key_of_size(10, 16))?);
+
+        Ok(())
+    }
+
     /// Some obscure edge case when deleting. See the source of
     /// [`BTree::remove`].
     ///
@@ -4148,8 +4408,21 @@ mod tests {
         btree: &mut BTree<'_, MemBuf, FixedSizeMemCmp>,
         keys: impl Iterator<Item = Key>,
     ) -> io::Result<()> {
-        let mut cursor = Cursor::new(btree.root, 0);
+        assert_traversal_matches(btree, Cursor::new(btree.root, 0), keys)
+    }
+
+    fn assert_reverse_cursor_traversal_matches(
+        btree: &mut BTree<'_, MemBuf, FixedSizeMemCmp>,
+        keys: impl DoubleEndedIterator<Item = Key>,
+    ) -> io::Result<()> {
+        assert_traversal_matches(btree, Cursor::new(btree.root, 0).reverse(), keys.rev())
+    }
 
+    fn assert_traversal_matches(
+        btree: &mut BTree<'_, MemBuf, FixedSizeMemCmp>,
+        mut cursor: Cursor,
+        keys: impl Iterator<Item = Key>,
+    ) -> io::Result<()> {
         for expected_key in keys {
             let (page, slot) = cursor.next(btree.pager).unwrap_or_else(|| {
                 panic!("cursor should return the position of key {expected_key} but returns None")
@@ -4197,6 +4470,28 @@ mod tests {
         assert_cursor_traversal_matches(&mut btree, keys)
     }
 
+    /// Same tree as [`basic_cursor`] but traversed backwards.
+    #[test]
+    fn reverse_cursor() -> io::Result<()> {
+        let pager = &mut pager_for_order(3)?;
+
+        let keys = 1..=30;
+        let mut btree = BTree::test().keys(keys.clone()).on(pager)?;
+
+        assert_reverse_cursor_traversal_matches(&mut btree, keys)
+    }
+
+    #[cfg(not(miri))]
+    #[test]
+    fn reverse_cursor_with_more_depth_and_keys() -> io::Result<()> {
+        let pager = &mut pager_for_order(6)?;
+
+        let keys = 1..=400;
+        let mut btree = BTree::test().keys(keys.clone()).on(pager)?;
+
+        assert_reverse_cursor_traversal_matches(&mut btree, keys)
+    }
+
     /// Not gonna draw this one but you get the idea.
     #[cfg(not(miri))]
     #[test]
@@ -4235,7 +4530,8 @@ mod tests {
             .keys(keys.clone())
             .on(pager)?;
 
-        assert_cursor_traversal_matches(&mut btree, keys)
+        assert_cursor_traversal_matches(&mut btree, keys.clone())?;
+        assert_reverse_cursor_traversal_matches(&mut btree, keys)
     }
 
     #[test]
@@ -4247,6 +4543,9 @@ mod tests {
 
         assert!(cursor.next(btree.pager).is_none());
 
+        let mut cursor = Cursor::new(btree.root, 0).reverse();
+        assert!(cursor.next(btree.pager).is_none());
+
         Ok(())
     }
 
@@ -125,14 +125,20 @@ use std::{
     ptr::{self, NonNull},
 };
 
-use crate::paging::pager::PageNumber;
+use crate::{paging::pager::PageNumber, storage::tuple::FormatVersion};
 
 /// Magic number at the beginning of the database file.
 ///
 /// `0xB74EE` is supposed to stand for "BTree" and also serves as endianess
 /// check, since the big endian and little endian representations are different.
+/// The magic number only takes the 3 least significant bytes of
+/// [`DbHeader::magic`], see [`MAGIC_MASK`].
 pub(crate) const MAGIC: u32 = 0xB74EE;
 
+/// Bits of [`DbHeader::magic`] that store [`MAGIC`]. The most significant byte
+/// stores the [`FormatVersion`] of the file.
+pub(crate) const MAGIC_MASK: u32 = 0x00FF_FFFF;
+
 /// Maximum page size is 64 KiB.
 pub(crate) const MAX_PAGE_SIZE: usize = 64 << 10;
 
@@ -1888,7 +1894,8 @@ impl OverflowPage {
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(C, align(8))]
 pub(crate) struct DbHeader {
-    /// Magic number at the beginning of the file.
+    /// Magic number at the beginning of the file followed by the format
+    /// version byte. See [`DbHeader::format_version`].
     pub magic: u32,
     /// Page size used for this DB file.
     pub page_size: u32,
@@ -1900,6 +1907,54 @@ pub(crate) struct DbHeader {
     pub first_free_page: PageNumber,
     /// Last free page in the freelist.
     pub last_free_page: PageNumber,
+    /// Checksum of all the fields above. See [`DbHeader::compute_checksum`].
+    pub checksum: u32,
+}
+
+impl DbHeader {
+    /// Returns the raw [`FormatVersion`] byte stored in the most significant
+    /// byte of [`Self::magic`].
+    ///
+    /// Files created before the version was stored here have a 0 instead.
+    pub fn format_version(&self) -> u8 {
+        (self.magic >> 24) as u8
+    }
+
+    /// Computes the checksum of the header fields, excluding
+    /// [`Self::checksum`] itself.
+    ///
+    /// This is a simple FNV-1a hash, it's only meant to detect corrupted or
+    /// tampered headers when opening the file, not to provide any kind of
+    /// security.
+    pub fn compute_checksum(&self) -> u32 {
+        const FNV_OFFSET_BASIS: u32 = 0x811C_9DC5;
+        const FNV_PRIME: u32 = 0x0100_0193;
+
+        [
+            self.magic,
+            self.page_size,
+            self.total_pages,
+            self.free_pages,
+            self.first_free_page,
+            self.last_free_page,
+        ]
+        .iter()
+        .flat_map(|field| field.to_ne_bytes())
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ byte as u32).wrapping_mul(FNV_PRIME)
+        })
+    }
+
+    /// Recomputes and stores the checksum. Must be called every time the
+    /// header is modified.
+    pub fn update_checksum(&mut self) {
+        self.checksum = self.compute_checksum();
+    }
+
+    /// Returns `true` if the stored checksum matches the header content.
+    pub fn has_valid_checksum(&self) -> bool {
+        self.checksum == self.compute_checksum()
+    }
 }
 
 /// The first page of the DB file (offset 0) is a special case since it contains
@@ -1924,13 +1979,15 @@ impl<H> From<BufferWithHeader<H>> for PageZero {
         let mut buffer = ManuallyDrop::new(buffer.cast());
 
         *buffer.header_mut() = DbHeader {
-            magic: MAGIC,
+            magic: MAGIC | (FormatVersion::CURRENT as u32) << 24,
             page_size: buffer.size as u32,
             total_pages: 1,
             free_pages: 0,
             first_free_page: 0,
             last_free_page: 0,
+            checksum: 0,
         };
+        buffer.header_mut().update_checksum();
 
         // SAFETY: `BufferWithHeader::from_non_null` requires 2 main guarantees
         // for wrapped buffers which we meet as follows:
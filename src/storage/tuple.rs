@@ -6,6 +6,8 @@
 //! them into [`Value`] structures. Serializing and deserializing made it easy
 //! to develop in the beginning because it doesn't require any unsafe code, but
 //! it's probably the biggest performance hit not counting unoptimized IO.
+//! In the meantime, code that only needs a few columns can decode them with
+//! [`read_column`] instead of deserializing the entire tuple.
 //!
 //! # Serialization Format
 //!
@@ -16,12 +18,14 @@
 //! big endian. This avoids the case "if number is PK then big endian else
 //! little endian". But that's what we *should* do (laziness wins again).
 //!
-//! Strings on the other hand are UTF-8 encoded with a 1, 2 or 4 byte little
+//! Strings on the other hand are UTF-8 encoded with a 1, 2 or 4 byte big
 //! endian prefix where we store the byte length of the string (number of bytes,
 //! not number of characters). The amount of bytes required to store the length
 //! depends on the maximum character limit defined by `VARCHAR` types. See
-//! [`utf8_length_prefix_bytes`] for details. So, putting it all together, a
-//! tuple like this one:
+//! [`utf8_length_prefix_bytes`] for details. `CHAR(n)` strings don't need a
+//! length prefix because they always take exactly `n` bytes, shorter strings
+//! are padded with spaces. `DOUBLE` values are stored as 8 byte big endian
+//! IEEE-754 floats. So, putting it all together, a tuple like this one:
 //!
 //! ```ignore
 //! [
@@ -40,31 +44,211 @@
 //! would serialize into the following bytes (not bits, bytes):
 //!
 //! ```text
-//! +-----------------+-----+---------------------+---------+
-//! | 0 0 0 0 0 0 0 1 | 5 0 | 'h' 'e' 'l' 'l' 'o' | 0 0 0 2 |
-//! +-----------------+-----+---------------------+---------+
-//!      8 byte        2 byte    String bytes       4 byte
-//!    big endian      little                     big endian
-//!      BigInt        endian                         Int
-//!                    String
-//!                    length
+//! +-------------------+-----+---------------------+-----------+
+//! | 128 0 0 0 0 0 0 1 | 0 5 | 'h' 'e' 'l' 'l' 'o' | 128 0 0 2 |
+//! +-------------------+-----+---------------------+-----------+
+//!        8 byte        2 byte    String bytes        4 byte
+//!      big endian       big                        big endian
+//!        BigInt        endian                          Int
+//!                      String
+//!                      length
 //! ```
 //!
+//! Signed integers (`INT` and `BIGINT`) are stored with their sign bit flipped.
+//! Two's complement places negative numbers after positive ones when compared
+//! byte by byte, flipping the sign bit moves them before zero so that memcmp()
+//! still matches the numeric order. That's why the numbers above start with
+//! 128. Unsigned integers are stored as they are.
+//!
+//! # Versions
+//!
+//! The format is versioned with [`FormatVersion`] and the version of each
+//! database file is stored in its header. The first version used little endian
+//! string length prefixes and the second one didn't flip the sign bit of
+//! signed integers, so negative keys were sorted after positive ones in files
+//! created with those versions. The fourth version didn't change tuples at all,
+//! it only adds a checksum to the database file header. Old files can still be
+//! read and written, the pager tells everyone which version to use. Tuples
+//! that never reach the database file (temporary files, network packets, etc)
+//! always use [`FormatVersion::CURRENT`].
+//!
+//! # Integer Encoding
+//!
+//! Integers can also be encoded as variable length integers (LEB128, plus
+//! zigzag encoding for signed types) with [`IntEncoding::Varint`], where small
+//! numbers like most [`RowId`] values take 1 or 2 bytes instead of 8. That's
+//! only an option for tuples that are never compared as raw bytes, see
+//! [`IntEncoding`] for details. Everything else uses the fixed width big
+//! endian format described above.
+//!
+//! # Aligned Layout
+//!
 //! The only thing we're missing here is alignment. The page module already
 //! supports 64 bit alignment, so if we align columns and write some unsafe
 //! code to obtain references to values from a binary buffer we would get rid
 //! of serialization / deserialization. It would require some changes throughout
 //! the codebase, but definitely doable.
+//!
+//! There's a first step in that direction: [`serialize_aligned`] stores the
+//! columns in [`physical_order`] instead of the order of the schema. Fixed
+//! width numbers go first sorted by decreasing width, so every number lands at
+//! an offset that is a multiple of its own size without any padding, and
+//! variable length strings go last. Numbers are still big endian with the sign
+//! bit flipped, only their position changes. For the tuple above:
+//!
+//! ```text
+//! +-------------------+-----------+-----+---------------------+
+//! | 128 0 0 0 0 0 0 1 | 128 0 0 2 | 0 5 | 'h' 'e' 'l' 'l' 'o' |
+//! +-------------------+-----------+-----+---------------------+
+//! offset 0            offset 8    offset 12
+//! ```
+//!
+//! [`deserialize_aligned`] puts the values back in logical order, so the
+//! physical layout is invisible to everyone else. The database file can't use
+//! this layout because BTrees expect the key in the first column, which is
+//! why it's only used for temporary files. See [`crate::vm::plan::TupleBuffer`].
 use std::{
     io::{self, Read},
     mem,
 };
 
 use crate::{
-    db::{RowId, Schema},
-    sql::statement::{DataType, Value},
+    db::{RowId, Schema, SqlError},
+    sql::{
+        analyzer::AnalyzerError,
+        statement::{DataType, Value},
+    },
 };
 
+/// Version of the serialization format.
+///
+/// See the module level documentation for details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub(crate) enum FormatVersion {
+    /// `VARCHAR` length prefixes are little endian, everything else is big
+    /// endian.
+    V1 = 1,
+    /// Everything is big endian.
+    V2 = 2,
+    /// Same as [`FormatVersion::V2`] but signed integers have their sign bit
+    /// flipped.
+    V3 = 3,
+    /// Same tuples as [`FormatVersion::V3`], the database file header has a
+    /// checksum.
+    V4 = 4,
+}
+
+impl FormatVersion {
+    /// Version used to create new database files.
+    pub const CURRENT: Self = Self::V4;
+
+    /// Whether fixed size integers of `data_type` are stored with their sign
+    /// bit flipped. See the module level documentation.
+    fn flips_sign_bit(self, data_type: &DataType) -> bool {
+        matches!(self, Self::V3 | Self::V4) && matches!(data_type, DataType::Int | DataType::BigInt)
+    }
+
+    /// Whether the database file header stores a checksum that can be
+    /// verified. See [`crate::storage::page::DbHeader::compute_checksum`].
+    pub fn has_header_checksum(self) -> bool {
+        self == Self::V4
+    }
+}
+
+impl TryFrom<u8> for FormatVersion {
+    type Error = u8;
+
+    /// Files created before the version was stored in the header have a 0 in
+    /// there, which is also [`FormatVersion::V1`].
+    fn try_from(version: u8) -> Result<Self, Self::Error> {
+        match version {
+            0 | 1 => Ok(Self::V1),
+            2 => Ok(Self::V2),
+            3 => Ok(Self::V3),
+            4 => Ok(Self::V4),
+            unknown => Err(unknown),
+        }
+    }
+}
+
+/// How integers are encoded in serialized tuples.
+///
+/// [`IntEncoding::Fixed`] is what goes into the database file. BTree keys are
+/// compared with [`crate::storage::FixedSizeMemCmp`] and similar comparators
+/// that memcmp() the raw bytes, which only works because every number takes
+/// exactly the same amount of bytes and is stored in big endian. Varints are
+/// neither fixed size nor ordered byte by byte (zigzag encoding places -1
+/// right after 0), so tuples encoded with [`IntEncoding::Varint`] can't be
+/// used as BTree keys unless the comparator decodes them first. Use varints
+/// only for tuples that are written and read back as a whole, like the
+/// temporary files of [`crate::vm::plan::TupleBuffer`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum IntEncoding {
+    /// Big endian integers of [`byte_length_of_integer_type`] bytes.
+    #[default]
+    Fixed,
+    /// LEB128 variable length integers. Signed types are zigzag encoded.
+    Varint,
+}
+
+/// Maximum number of bytes of a varint. The largest integers that we store
+/// are 64 bits wide and LEB128 stores 7 bits per byte.
+const MAX_VARINT_BYTES: usize = 10;
+
+/// Maps signed integers to unsigned integers so that numbers with a small
+/// absolute value have a short varint: 0, -1, 1, -2, 2... become 0, 1, 2, 3,
+/// 4... Unsigned types are not modified.
+fn zigzag_encode(num: i128, integer_type: &DataType) -> u128 {
+    match integer_type {
+        DataType::Int | DataType::BigInt => ((num << 1) ^ (num >> 127)) as u128,
+        _ => num as u128,
+    }
+}
+
+/// Inverse of [`zigzag_encode`].
+fn zigzag_decode(num: u128, integer_type: &DataType) -> i128 {
+    match integer_type {
+        DataType::Int | DataType::BigInt => (num >> 1) as i128 ^ -((num & 1) as i128),
+        _ => num as i128,
+    }
+}
+
+/// Number of bytes that [`write_varint`] needs to encode `num`.
+fn varint_length(num: u128) -> usize {
+    (128 - num.leading_zeros() as usize).div_ceil(7).max(1)
+}
+
+/// Appends the LEB128 encoding of `num` to `buf`.
+fn write_varint(buf: &mut Vec<u8>, mut num: u128) {
+    while num >= 0x80 {
+        buf.push(num as u8 | 0x80);
+        num >>= 7;
+    }
+
+    buf.push(num as u8);
+}
+
+/// Reads a LEB128 integer written by [`write_varint`].
+fn read_varint(reader: &mut impl Read) -> io::Result<u128> {
+    let mut num = 0;
+
+    for i in 0..MAX_VARINT_BYTES {
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        num |= ((byte[0] & 0x7F) as u128) << (7 * i);
+
+        if byte[0] & 0x80 == 0 {
+            return Ok(num);
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("corrupted varint: longer than {MAX_VARINT_BYTES} bytes"),
+    ))
+}
+
 /// Almost all tuples (except BTree index tuples) have a [`RowId`] as the first
 /// element.
 ///
@@ -87,6 +271,13 @@ pub(crate) fn byte_length_of_integer_type(data_type: &DataType) -> usize {
     }
 }
 
+/// Maximum number of characters that a `VARCHAR` type can store.
+///
+/// Length prefixes take 4 bytes at most, so in the worst case where every
+/// single character takes 4 bytes the string can't have more than
+/// `u32::MAX / 4` characters. See [`utf8_length_prefix_bytes`].
+pub(crate) const MAX_VARCHAR_LENGTH: usize = u32::MAX as usize / 4;
+
 /// Returns the number of bytes we need to store the length of a `VARCHAR` type.
 ///
 /// UTF-8 encodes each character using anywhere from 1 to 4 bytes. So
@@ -117,6 +308,11 @@ pub(crate) fn integer_is_within_range(integer: &i128, integer_type: &DataType) -
 
 /// Calculates the size that the given tuple would take on disk once serialized.
 pub(crate) fn size_of(tuple: &[Value], schema: &Schema) -> usize {
+    size_of_encoded(IntEncoding::Fixed, tuple, schema)
+}
+
+/// Same as [`size_of`] but with a specific [`IntEncoding`].
+pub(crate) fn size_of_encoded(encoding: IntEncoding, tuple: &[Value], schema: &Schema) -> usize {
     schema
         .columns
         .iter()
@@ -136,7 +332,16 @@ pub(crate) fn size_of(tuple: &[Value], schema: &Schema) -> usize {
                 utf8_length_prefix_bytes(max_characters) + string.as_bytes().len()
             }
 
-            integer_type => byte_length_of_integer_type(&integer_type),
+            DataType::Char(length) => length,
+
+            DataType::Double => mem::size_of::<f64>(),
+
+            integer_type => match (encoding, &tuple[i]) {
+                (IntEncoding::Varint, Value::Number(num)) => {
+                    varint_length(zigzag_encode(*num, &integer_type))
+                }
+                _ => byte_length_of_integer_type(&integer_type),
+            },
         })
         .sum()
 }
@@ -146,19 +351,57 @@ pub(crate) fn size_of(tuple: &[Value], schema: &Schema) -> usize {
 /// It's called serialize key because otherwise we just use [`serialize`].
 /// This is only used to serialize the first part of a tuple in order to search
 /// BTrees.
-pub(crate) fn serialize_key(data_type: &DataType, value: &Value) -> Vec<u8> {
+pub(crate) fn serialize_key(data_type: &DataType, value: &Value) -> Result<Vec<u8>, SqlError> {
+    serialize_key_with(FormatVersion::CURRENT, data_type, value)
+}
+
+/// Same as [`serialize_key`] but with a specific format version.
+pub(crate) fn serialize_key_with(
+    version: FormatVersion,
+    data_type: &DataType,
+    value: &Value,
+) -> Result<Vec<u8>, SqlError> {
     let mut buf = Vec::new();
-    serialize_value_into(&mut buf, data_type, value);
-    buf
+    serialize_value_into(&mut buf, version, IntEncoding::Fixed, data_type, value)?;
+    Ok(buf)
 }
 
 /// Serialize a complete tuple.
 ///
-/// See the module level documentation for the serialization format.
+/// See the module level documentation for the serialization format. Fails if
+/// some number doesn't fit in the integer type of its column.
 pub(crate) fn serialize<'v>(
     schema: &Schema,
-    values: (impl IntoIterator<Item = &'v Value> + Copy),
-) -> Vec<u8> {
+    values: impl IntoIterator<Item = &'v Value> + Copy,
+) -> Result<Vec<u8>, SqlError> {
+    serialize_with(FormatVersion::CURRENT, schema, values)
+}
+
+/// Same as [`serialize`] but with a specific format version.
+pub(crate) fn serialize_with<'v>(
+    version: FormatVersion,
+    schema: &Schema,
+    values: impl IntoIterator<Item = &'v Value> + Copy,
+) -> Result<Vec<u8>, SqlError> {
+    serialize_tuple(version, IntEncoding::Fixed, schema, values)
+}
+
+/// Same as [`serialize`] but with a specific [`IntEncoding`].
+pub(crate) fn serialize_encoded<'v>(
+    encoding: IntEncoding,
+    schema: &Schema,
+    values: impl IntoIterator<Item = &'v Value> + Copy,
+) -> Result<Vec<u8>, SqlError> {
+    serialize_tuple(FormatVersion::CURRENT, encoding, schema, values)
+}
+
+/// Serializes a complete tuple with all the knobs.
+fn serialize_tuple<'v>(
+    version: FormatVersion,
+    encoding: IntEncoding,
+    schema: &Schema,
+    values: impl IntoIterator<Item = &'v Value> + Copy,
+) -> Result<Vec<u8>, SqlError> {
     let mut buf = Vec::new();
 
     debug_assert_eq!(
@@ -168,10 +411,46 @@ pub(crate) fn serialize<'v>(
     );
 
     for (col, val) in schema.columns.iter().zip(values.into_iter()) {
-        serialize_value_into(&mut buf, &col.data_type, val);
+        serialize_value_into(&mut buf, version, encoding, &col.data_type, val)?;
+    }
+
+    Ok(buf)
+}
+
+/// Reads the byte length of a `VARCHAR` string from its prefix.
+///
+/// `prefix` must be exactly [`utf8_length_prefix_bytes`] long.
+pub(crate) fn read_length_prefix(version: FormatVersion, prefix: &[u8]) -> usize {
+    let mut buf = [0; mem::size_of::<usize>()];
+
+    match version {
+        FormatVersion::V1 => {
+            buf[..prefix.len()].copy_from_slice(prefix);
+            usize::from_le_bytes(buf)
+        }
+
+        FormatVersion::V2 | FormatVersion::V3 | FormatVersion::V4 => {
+            let start = buf.len() - prefix.len();
+            buf[start..].copy_from_slice(prefix);
+            usize::from_be_bytes(buf)
+        }
     }
+}
 
-    buf
+/// Writes the `VARCHAR` length prefix of a string that takes `length` bytes.
+fn write_length_prefix(
+    buf: &mut Vec<u8>,
+    version: FormatVersion,
+    length: usize,
+    prefix_bytes: usize,
+) {
+    match version {
+        FormatVersion::V1 => buf.extend_from_slice(&length.to_le_bytes()[..prefix_bytes]),
+        FormatVersion::V2 | FormatVersion::V3 | FormatVersion::V4 => {
+            let big_endian_bytes = length.to_be_bytes();
+            buf.extend_from_slice(&big_endian_bytes[big_endian_bytes.len() - prefix_bytes..]);
+        }
+    }
 }
 
 /// Low level serialization.
@@ -180,90 +459,699 @@ pub(crate) fn serialize<'v>(
 /// that would be expensive for serializing complete tuples as we'd have to
 /// allocate multiple vectors and join them together.
 ///
-/// TODO: Alignment.
-fn serialize_value_into(buf: &mut Vec<u8>, data_type: &DataType, value: &Value) {
+/// Numbers computed at runtime (`UPDATE` expressions, `INSERT INTO ...
+/// SELECT`, etc) are not checked by the analyzer, so integers that don't fit
+/// in `data_type` return [`AnalyzerError::IntegerOutOfRange`].
+fn serialize_value_into(
+    buf: &mut Vec<u8>,
+    version: FormatVersion,
+    encoding: IntEncoding,
+    data_type: &DataType,
+    value: &Value,
+) -> Result<(), SqlError> {
     match (data_type, value) {
         (DataType::Varchar(max_characters), Value::String(string)) => {
-            if string.as_bytes().len() > u32::MAX as usize {
-                todo!("strings longer than {} bytes are not handled", u32::MAX);
-            }
-
-            let byte_length = string.as_bytes().len().to_le_bytes();
             let length_prefix_bytes = utf8_length_prefix_bytes(*max_characters);
 
-            buf.extend_from_slice(&byte_length[..length_prefix_bytes]);
+            // The analyzer doesn't allow VARCHAR types that can't store their
+            // length in the prefix and both the analyzer and the VM reject
+            // strings that don't fit. See [`MAX_VARCHAR_LENGTH`].
+            debug_assert!(
+                (string.len() as u64) < 1 << (8 * length_prefix_bytes),
+                "string of {} bytes doesn't fit in {}",
+                string.len(),
+                data_type
+            );
+
+            write_length_prefix(buf, version, string.len(), length_prefix_bytes);
             buf.extend_from_slice(string.as_bytes());
         }
 
+        (DataType::Char(length), Value::String(string)) => {
+            // The analyzer doesn't allow longer strings but in case we get one
+            // anyway truncate it without splitting UTF-8 characters.
+            let end = (0..=string.len().min(*length))
+                .rev()
+                .find(|index| string.is_char_boundary(*index))
+                .unwrap();
+
+            buf.extend_from_slice(&string.as_bytes()[..end]);
+            buf.resize(buf.len() + length - end, b' ');
+        }
+
         (DataType::Bool, Value::Bool(bool)) => buf.push(u8::from(*bool)),
 
+        (DataType::Double, Value::Float(float)) => buf.extend_from_slice(&float.to_be_bytes()),
+
+        // Integer literals can be assigned to DOUBLE columns.
+        (DataType::Double, Value::Number(num)) => {
+            buf.extend_from_slice(&(*num as f64).to_be_bytes());
+        }
+
         (integer_type, Value::Number(num)) => {
-            assert!(
-                integer_is_within_range(num, integer_type),
-                "integer overflow while serializing number {num} into data type {integer_type:?}"
-            );
+            if !integer_is_within_range(num, integer_type) {
+                return Err(AnalyzerError::IntegerOutOfRange(*num, *integer_type).into());
+            }
+
+            match encoding {
+                IntEncoding::Fixed => {
+                    let byte_length = byte_length_of_integer_type(integer_type);
+                    let big_endian_bytes = num.to_be_bytes();
+                    let start = buf.len();
+                    buf.extend_from_slice(
+                        &big_endian_bytes[big_endian_bytes.len() - byte_length..],
+                    );
 
-            let byte_length = byte_length_of_integer_type(integer_type);
-            let big_endian_bytes = num.to_be_bytes();
-            buf.extend_from_slice(&big_endian_bytes[big_endian_bytes.len() - byte_length..]);
+                    if version.flips_sign_bit(integer_type) {
+                        buf[start] ^= 0x80;
+                    }
+                }
+
+                IntEncoding::Varint => write_varint(buf, zigzag_encode(*num, integer_type)),
+            }
         }
 
         _ => unreachable!("attempt to serialize {value} into {data_type}"),
     }
+
+    Ok(())
 }
 
 /// See the module level documentation for the serialization format.
-pub fn deserialize(buf: &[u8], schema: &Schema) -> Vec<Value> {
-    read_from(&mut io::Cursor::new(buf), schema).unwrap()
+///
+/// Fails with [`io::ErrorKind::InvalidData`] if the buffer contains strings
+/// that are not valid UTF-8. That can only happen if the file is corrupted,
+/// every string that we serialize comes from a Rust [`String`].
+pub fn deserialize(buf: &[u8], schema: &Schema) -> io::Result<Vec<Value>> {
+    deserialize_with(FormatVersion::CURRENT, buf, schema)
+}
+
+/// Same as [`deserialize`] but with a specific format version.
+pub(crate) fn deserialize_with(
+    version: FormatVersion,
+    buf: &[u8],
+    schema: &Schema,
+) -> io::Result<Vec<Value>> {
+    read_from_with(version, &mut io::Cursor::new(buf), schema)
 }
 
 /// Reads one single tuple from the given reader.
 ///
 /// This will call [`Read::read_exact`] many times so make sure the reader is
 /// buffered or is an in-memory array such as [`io::Cursor<Vec<u8>>`].
+pub(crate) fn read_from_with(
+    version: FormatVersion,
+    reader: &mut impl Read,
+    schema: &Schema,
+) -> io::Result<Vec<Value>> {
+    read_tuple(version, IntEncoding::Fixed, reader, schema)
+}
+
+/// Same as [`read_from_with`] but with a specific [`IntEncoding`] instead of a
+/// format version. Tuples that use varints never reach the database file, so
+/// they're always [`FormatVersion::CURRENT`].
+pub(crate) fn read_from_encoded(
+    encoding: IntEncoding,
+    reader: &mut impl Read,
+    schema: &Schema,
+) -> io::Result<Vec<Value>> {
+    read_tuple(FormatVersion::CURRENT, encoding, reader, schema)
+}
+
+/// Reads a complete tuple with all the knobs.
+fn read_tuple(
+    version: FormatVersion,
+    encoding: IntEncoding,
+    reader: &mut impl Read,
+    schema: &Schema,
+) -> io::Result<Vec<Value>> {
+    schema
+        .columns
+        .iter()
+        .map(|column| read_value(version, encoding, reader, &column.data_type))
+        .collect()
+}
+
+/// Computes the byte offset where the column located at `index` starts in the
+/// serialized tuple `buf`.
 ///
-/// TODO: Alignment.
-pub fn read_from(reader: &mut impl Read, schema: &Schema) -> io::Result<Vec<Value>> {
-    let values = schema.columns.iter().map(|column| {
-        Ok(match column.data_type {
+/// Columns that only have fixed size columns before them don't need `buf` at
+/// all, the offset is computed from the schema. Every `VARCHAR` column that
+/// we cross requires reading its length prefix from `buf` though.
+///
+/// Only works with [`IntEncoding::Fixed`] tuples, which is what the database
+/// file stores.
+pub(crate) fn column_offset(
+    version: FormatVersion,
+    buf: &[u8],
+    schema: &Schema,
+    index: usize,
+) -> usize {
+    schema.columns[..index]
+        .iter()
+        .fold(0, |offset, column| match column.data_type {
             DataType::Varchar(max_characters) => {
-                let mut length_buffer = [0; mem::size_of::<usize>()];
-                let length_prefix_bytes = utf8_length_prefix_bytes(max_characters);
+                let prefix_bytes = utf8_length_prefix_bytes(max_characters);
+                let length = read_length_prefix(version, &buf[offset..offset + prefix_bytes]);
+                offset + prefix_bytes + length
+            }
+
+            DataType::Char(length) => offset + length,
+
+            DataType::Bool => offset + 1,
+
+            DataType::Double => offset + mem::size_of::<f64>(),
+
+            integer_type => offset + byte_length_of_integer_type(&integer_type),
+        })
+}
+
+/// Deserializes only the column located at `index` in the serialized tuple
+/// `buf`.
+///
+/// Use this instead of [`deserialize_with`] when only some columns of the
+/// tuple are needed, there's no point in decoding strings that we're going to
+/// throw away. See [`column_offset`].
+pub(crate) fn read_column(
+    version: FormatVersion,
+    buf: &[u8],
+    schema: &Schema,
+    index: usize,
+) -> io::Result<Value> {
+    let offset = column_offset(version, buf, schema, index);
+    read_value(
+        version,
+        IntEncoding::Fixed,
+        &mut &buf[offset..],
+        &schema.columns[index].data_type,
+    )
+}
+
+/// Order in which [`serialize_aligned`] stores the columns of `schema`.
+///
+/// Returns the logical indexes of the columns sorted by physical position.
+/// 8 byte numbers come first, then 4 byte numbers, then `BOOL` and `CHAR(n)`
+/// columns and finally `VARCHAR` columns. Columns of the same group keep their
+/// logical order.
+pub(crate) fn physical_order(schema: &Schema) -> Vec<usize> {
+    let mut order = Vec::from_iter(0..schema.len());
 
-                reader.read_exact(&mut length_buffer[..length_prefix_bytes])?;
-                let length = usize::from_le_bytes(length_buffer);
+    order.sort_by_key(|index| match schema.columns[*index].data_type {
+        DataType::Double | DataType::BigInt | DataType::UnsignedBigInt => 0,
+        DataType::Int | DataType::UnsignedInt => 1,
+        DataType::Bool | DataType::Char(_) => 2,
+        DataType::Varchar(_) => 3,
+    });
+
+    order
+}
+
+/// Serializes a complete tuple using the aligned layout.
+///
+/// The size of the tuple is the same as [`serialize`], see [`size_of`]. Only
+/// the position of the columns changes. See [`physical_order`].
+pub(crate) fn serialize_aligned(schema: &Schema, values: &[Value]) -> Result<Vec<u8>, SqlError> {
+    let mut buf = Vec::new();
+
+    for index in physical_order(schema) {
+        serialize_value_into(
+            &mut buf,
+            FormatVersion::CURRENT,
+            IntEncoding::Fixed,
+            &schema.columns[index].data_type,
+            &values[index],
+        )?;
+    }
+
+    Ok(buf)
+}
+
+/// Deserializes a tuple written by [`serialize_aligned`].
+///
+/// The values are returned in logical order, just like [`deserialize`].
+/// Numbers are decoded straight from `buf`, see [`read_aligned_number`].
+pub(crate) fn deserialize_aligned(buf: &[u8], schema: &Schema) -> io::Result<Vec<Value>> {
+    let mut values = vec![Value::Bool(false); schema.len()];
+    let mut reader = buf;
+
+    for index in physical_order(schema) {
+        values[index] = match schema.columns[index].data_type {
+            DataType::Bool | DataType::Char(_) | DataType::Varchar(_) => read_value(
+                FormatVersion::CURRENT,
+                IntEncoding::Fixed,
+                &mut reader,
+                &schema.columns[index].data_type,
+            )?,
+
+            number_type => read_aligned_number(&mut reader, &number_type)?,
+        };
+    }
+
+    Ok(values)
+}
 
-                let mut string = vec![0; length];
-                reader.read_exact(&mut string)?;
+/// Reads a number stored by [`serialize_aligned`] without copying its bytes
+/// into a temporary buffer.
+///
+/// The aligned layout always uses [`FormatVersion::CURRENT`], so XOR with the
+/// minimum value flips the sign bit of signed integers back.
+fn read_aligned_number(reader: &mut &[u8], data_type: &DataType) -> io::Result<Value> {
+    let byte_length = match data_type {
+        DataType::Double => mem::size_of::<f64>(),
+        integer_type => byte_length_of_integer_type(integer_type),
+    };
+
+    let (bytes, rest) = reader
+        .split_at_checked(byte_length)
+        .ok_or(io::ErrorKind::UnexpectedEof)?;
+    *reader = rest;
+
+    Ok(match data_type {
+        DataType::Int => {
+            Value::Number((i32::from_be_bytes(bytes.try_into().unwrap()) ^ i32::MIN).into())
+        }
+        DataType::UnsignedInt => {
+            Value::Number(u32::from_be_bytes(bytes.try_into().unwrap()).into())
+        }
+        DataType::BigInt => {
+            Value::Number((i64::from_be_bytes(bytes.try_into().unwrap()) ^ i64::MIN).into())
+        }
+        DataType::UnsignedBigInt => {
+            Value::Number(u64::from_be_bytes(bytes.try_into().unwrap()).into())
+        }
+        DataType::Double => Value::Float(f64::from_be_bytes(bytes.try_into().unwrap())),
+        _ => unreachable!("read_aligned_number() called with {data_type}"),
+    })
+}
+
+/// Converts bytes read from disk into a [`String`].
+fn read_string(bytes: Vec<u8>) -> io::Result<String> {
+    String::from_utf8(bytes).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("corrupted string: {}", e.utf8_error()),
+        )
+    })
+}
+
+/// Reads a single value of the given type.
+fn read_value(
+    version: FormatVersion,
+    encoding: IntEncoding,
+    reader: &mut impl Read,
+    data_type: &DataType,
+) -> io::Result<Value> {
+    Ok(match *data_type {
+        DataType::Varchar(max_characters) => {
+            let mut length_buffer = [0; mem::size_of::<u32>()];
+            let length_prefix = &mut length_buffer[..utf8_length_prefix_bytes(max_characters)];
+
+            reader.read_exact(length_prefix)?;
+            let length = read_length_prefix(version, length_prefix);
+
+            let mut string = vec![0; length];
+            reader.read_exact(&mut string)?;
+
+            Value::String(read_string(string)?)
+        }
 
-                // TODO: We can probably call from_utf8_unchecked() here.
-                Value::String(String::from_utf8(string).unwrap())
+        DataType::Char(length) => {
+            let mut string = vec![0; length];
+            reader.read_exact(&mut string)?;
+
+            let padding = string
+                .iter()
+                .rev()
+                .take_while(|byte| **byte == b' ')
+                .count();
+            string.truncate(length - padding);
+
+            Value::String(read_string(string)?)
+        }
+
+        DataType::Bool => {
+            let mut byte = [0];
+            reader.read_exact(&mut byte)?;
+            Value::Bool(byte[0] != 0)
+        }
+
+        DataType::Double => {
+            let mut big_endian_buf = [0; mem::size_of::<f64>()];
+            reader.read_exact(&mut big_endian_buf)?;
+            Value::Float(f64::from_be_bytes(big_endian_buf))
+        }
+
+        integer_type if encoding == IntEncoding::Varint => {
+            let num = zigzag_decode(read_varint(reader)?, &integer_type);
+
+            if !integer_is_within_range(&num, &integer_type) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("corrupted varint: {num} out of range for {integer_type}"),
+                ));
+            }
+
+            Value::Number(num)
+        }
+
+        integer_type => {
+            let byte_length = byte_length_of_integer_type(&integer_type);
+            let mut big_endian_buf = [0; mem::size_of::<i128>()];
+
+            let start_index = mem::size_of::<i128>() - byte_length;
+            reader.read_exact(&mut big_endian_buf[start_index..])?;
+
+            if version.flips_sign_bit(&integer_type) {
+                big_endian_buf[start_index] ^= 0x80;
             }
 
-            DataType::Bool => {
-                let mut byte = [0];
-                reader.read_exact(&mut byte)?;
-                Value::Bool(byte[0] != 0)
+            // Adjustment for negative numbers. Gotta love two's complement.
+            if big_endian_buf[start_index] & 0x80 != 0
+                && matches!(integer_type, DataType::BigInt | DataType::Int)
+            {
+                big_endian_buf[..start_index].fill(u8::MAX);
             }
 
-            integer_type => {
-                let byte_length = byte_length_of_integer_type(&integer_type);
-                let mut big_endian_buf = [0; mem::size_of::<i128>()];
+            Value::Number(i128::from_be_bytes(big_endian_buf))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        column_offset, deserialize_aligned, deserialize_with, integer_is_within_range,
+        physical_order, read_column, read_from_encoded, read_from_with, serialize_aligned,
+        serialize_encoded, serialize_key, serialize_key_with, serialize_with, size_of,
+        size_of_encoded, FormatVersion, IntEncoding,
+    };
+    use crate::{
+        db::{DbError, Schema, SqlError},
+        sql::{
+            analyzer::AnalyzerError,
+            statement::{Column, DataType, Value},
+        },
+    };
+
+    fn schema() -> Schema {
+        Schema::new(vec![
+            Column::new("id", DataType::BigInt),
+            Column::new("name", DataType::Varchar(255)),
+            Column::new("age", DataType::Int),
+        ])
+    }
 
-                let start_index = mem::size_of::<i128>() - byte_length;
-                reader.read_exact(&mut big_endian_buf[start_index..])?;
+    fn values() -> Vec<Value> {
+        vec![
+            Value::Number(1),
+            Value::String("hello".into()),
+            Value::Number(2),
+        ]
+    }
 
-                // Adjustment for negative numbers. Gotta love two's complement.
-                if big_endian_buf[start_index] & 0x80 != 0
-                    && matches!(integer_type, DataType::BigInt | DataType::Int)
-                {
-                    big_endian_buf[..start_index].fill(u8::MAX);
-                }
+    #[test]
+    fn round_trip() -> Result<(), DbError> {
+        for version in [
+            FormatVersion::V1,
+            FormatVersion::V2,
+            FormatVersion::V3,
+            FormatVersion::V4,
+        ] {
+            let buf = serialize_with(version, &schema(), &values())?;
+
+            assert_eq!(deserialize_with(version, &buf, &schema())?, values());
+            assert_eq!(
+                read_from_with(version, &mut buf.as_slice(), &schema())?,
+                values()
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn current_format_uses_big_endian_length_prefixes() -> Result<(), DbError> {
+        assert_eq!(
+            serialize_with(FormatVersion::CURRENT, &schema(), &values())?,
+            [128, 0, 0, 0, 0, 0, 0, 1, 0, 5, b'h', b'e', b'l', b'l', b'o', 128, 0, 0, 2]
+        );
+
+        Ok(())
+    }
 
-                Value::Number(i128::from_be_bytes(big_endian_buf))
+    #[test]
+    fn read_v1_fixture() -> Result<(), DbError> {
+        // Written by the first version, length prefix is little endian.
+        let fixture = [
+            0, 0, 0, 0, 0, 0, 0, 1, 5, 0, b'h', b'e', b'l', b'l', b'o', 0, 0, 0, 2,
+        ];
+
+        assert_eq!(
+            deserialize_with(FormatVersion::V1, &fixture, &schema())?,
+            values()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn signed_keys_preserve_numeric_order() -> Result<(), DbError> {
+        let numbers = [i64::MIN as i128, -300, -2, -1, 0, 1, 250, i64::MAX as i128];
+
+        for data_type in [DataType::Int, DataType::BigInt] {
+            let keys = numbers
+                .iter()
+                .filter(|num| integer_is_within_range(num, &data_type))
+                .map(|num| serialize_key(&data_type, &Value::Number(*num)))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            assert!(keys.is_sorted(), "{data_type} keys are not sorted");
+        }
+
+        // Older versions store signed integers as they are.
+        assert_eq!(
+            serialize_key_with(FormatVersion::V2, &DataType::Int, &Value::Number(-1))?,
+            [0xFF; 4]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn column_offsets() -> Result<(), DbError> {
+        let buf = serialize_with(FormatVersion::CURRENT, &schema(), &values())?;
+        let offsets = (0..3)
+            .map(|i| column_offset(FormatVersion::CURRENT, &buf, &schema(), i))
+            .collect::<Vec<_>>();
+
+        assert_eq!(offsets, [0, 8, 8 + 2 + 5]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_single_columns() -> Result<(), DbError> {
+        let schema = Schema::new(vec![
+            Column::new("id", DataType::UnsignedBigInt),
+            Column::new("code", DataType::Char(4)),
+            Column::new("name", DataType::Varchar(255)),
+            Column::new("active", DataType::Bool),
+            Column::new("bio", DataType::Varchar(20000)),
+            Column::new("score", DataType::Double),
+            Column::new("age", DataType::Int),
+        ]);
+
+        let values = vec![
+            Value::Number(7),
+            Value::String("ab".into()),
+            Value::String("hello".into()),
+            Value::Bool(true),
+            Value::String("x".repeat(300)),
+            Value::Float(1.5),
+            Value::Number(-3),
+        ];
+
+        for version in [
+            FormatVersion::V1,
+            FormatVersion::V2,
+            FormatVersion::V3,
+            FormatVersion::V4,
+        ] {
+            let buf = serialize_with(version, &schema, &values)?;
+            let deserialized = deserialize_with(version, &buf, &schema)?;
+
+            for (i, value) in deserialized.into_iter().enumerate() {
+                assert_eq!(read_column(version, &buf, &schema, i)?, value);
             }
-        })
-    });
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_invalid_utf8() -> Result<(), DbError> {
+        let mut buf = serialize_with(FormatVersion::CURRENT, &schema(), &values())?;
+        // First byte of "hello".
+        buf[10] = 0xFF;
+
+        assert_eq!(
+            deserialize_with(FormatVersion::CURRENT, &buf, &schema()).map_err(|e| e.kind()),
+            Err(std::io::ErrorKind::InvalidData)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_integer_overflow() {
+        let schema = Schema::new(vec![
+            Column::new("id", DataType::Int),
+            Column::new("count", DataType::UnsignedBigInt),
+        ]);
+
+        for (values, (num, data_type)) in [
+            (
+                [i32::MAX as i128 + 1, 0],
+                (i32::MAX as i128 + 1, DataType::Int),
+            ),
+            ([1, -1], (-1, DataType::UnsignedBigInt)),
+        ] {
+            let expected = Err(SqlError::from(AnalyzerError::IntegerOutOfRange(
+                num, data_type,
+            )));
+            let tuple = values.map(Value::Number);
+
+            assert_eq!(
+                serialize_with(FormatVersion::CURRENT, &schema, &tuple),
+                expected
+            );
+            assert_eq!(
+                serialize_encoded(IntEncoding::Varint, &schema, &tuple),
+                expected
+            );
+            assert_eq!(serialize_aligned(&schema, &tuple), expected);
+        }
+    }
+
+    #[test]
+    fn varint_round_trip() -> Result<(), DbError> {
+        let schema = Schema::new(vec![
+            Column::new("a", DataType::Int),
+            Column::new("b", DataType::UnsignedInt),
+            Column::new("c", DataType::BigInt),
+            Column::new("d", DataType::UnsignedBigInt),
+            Column::new("e", DataType::Varchar(10)),
+        ]);
+
+        let tuples = [
+            [0, 0, 0, 0],
+            [-1, 1, -64, 127],
+            [
+                i32::MIN as i128,
+                u32::MAX as i128,
+                i64::MIN as i128,
+                u64::MAX as i128,
+            ],
+            [i32::MAX as i128, 128, i64::MAX as i128, 300],
+        ];
+
+        for numbers in tuples {
+            let mut tuple = numbers.map(Value::Number).to_vec();
+            tuple.push(Value::String("varint".into()));
+
+            let buf = serialize_encoded(IntEncoding::Varint, &schema, &tuple)?;
+            assert_eq!(
+                buf.len(),
+                size_of_encoded(IntEncoding::Varint, &tuple, &schema)
+            );
+            assert_eq!(
+                read_from_encoded(IntEncoding::Varint, &mut buf.as_slice(), &schema)?,
+                tuple
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn varints_are_smaller_for_small_numbers() -> Result<(), DbError> {
+        let buf = serialize_encoded(IntEncoding::Varint, &schema(), &values())?;
+
+        // 1 byte per integer instead of 8 and 4.
+        assert_eq!(buf.len(), 1 + 2 + "hello".len() + 1);
+        assert_eq!(size_of(&values(), &schema()), 8 + 2 + "hello".len() + 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_overlong_varint() {
+        let schema = Schema::new(vec![Column::new("id", DataType::UnsignedBigInt)]);
 
-    values.collect()
+        for buf in [vec![0xFF; 11], [vec![0xFF; 9], vec![0x7F]].concat()] {
+            assert_eq!(
+                read_from_encoded(IntEncoding::Varint, &mut buf.as_slice(), &schema)
+                    .map_err(|e| e.kind()),
+                Err(std::io::ErrorKind::InvalidData)
+            );
+        }
+    }
+
+    fn mixed_schema() -> Schema {
+        Schema::new(vec![
+            Column::new("name", DataType::Varchar(255)),
+            Column::new("active", DataType::Bool),
+            Column::new("age", DataType::Int),
+            Column::new("code", DataType::Char(3)),
+            Column::new("id", DataType::UnsignedBigInt),
+            Column::new("score", DataType::Double),
+            Column::new("bio", DataType::Varchar(1000)),
+            Column::new("count", DataType::UnsignedInt),
+        ])
+    }
+
+    #[test]
+    fn physical_order_places_wide_numbers_first() {
+        assert_eq!(physical_order(&mixed_schema()), vec![
+            4, 5, 2, 7, 1, 3, 0, 6
+        ]);
+        assert_eq!(physical_order(&schema()), vec![0, 2, 1]);
+    }
+
+    #[test]
+    fn aligned_layout_preserves_logical_order() -> Result<(), DbError> {
+        let tuples = [
+            (schema(), values()),
+            (mixed_schema(), vec![
+                Value::String("John".into()),
+                Value::Bool(true),
+                Value::Number(-30),
+                Value::String("abc".into()),
+                Value::Number(u64::MAX as i128),
+                Value::Float(1.5),
+                Value::String("".into()),
+                Value::Number(7),
+            ]),
+        ];
+
+        for (schema, tuple) in tuples {
+            let buf = serialize_aligned(&schema, &tuple)?;
+            assert_eq!(buf.len(), size_of(&tuple, &schema));
+            assert_eq!(deserialize_aligned(&buf, &schema)?, tuple);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn aligned_layout_puts_numbers_at_aligned_offsets() -> Result<(), DbError> {
+        let buf = serialize_aligned(&schema(), &values())?;
+
+        assert_eq!(buf[..8], (1u64 | 1 << 63).to_be_bytes());
+        assert_eq!(buf[8..12], (2u32 | 1 << 31).to_be_bytes());
+        assert_eq!(buf[12..14], [0, 5]);
+        assert_eq!(&buf[14..], b"hello");
+
+        Ok(())
+    }
 }
@@ -109,11 +109,14 @@
 //!     DataType::BigInt => 3,
 //!     DataType::UnsignedBigInt => 4,
 //!     DataType::Varchar(_) => 5,
+//!     DataType::Char(_) => 6,
+//!     DataType::Double => 7,
 //! }
 //! ```
 //!
 //! If the data type is `VARCHAR` then the character limit is encoded as 4 byte
-//! little endian integer right after the data type byte:
+//! little endian integer right after the data type byte (`CHAR` does the same
+//! with its length):
 //!
 //! ```text
 //!    Name      Column     Data    Varchar
@@ -193,6 +196,7 @@ pub enum EncodingError {
     UtfDecode(FromUtf8Error),
     InvalidPrefix(u8),
     InvalidDataType(u8),
+    InvalidTuple(String),
 }
 
 impl From<TryFromIntError> for EncodingError {
@@ -221,6 +225,7 @@ impl fmt::Display for EncodingError {
             Self::UtfDecode(e) => write!(f, "{e}"),
             Self::InvalidPrefix(prefix) => write!(f, "invalid ASCII prefix: {prefix}"),
             Self::InvalidDataType(byte) => write!(f, "invalid data type: {byte}"),
+            Self::InvalidTuple(message) => write!(f, "invalid tuple: {message}"),
         }
     }
 }
@@ -283,14 +288,20 @@ pub fn serialize(payload: &Response) -> Result<Vec<u8>, EncodingError> {
                     DataType::BigInt => 3,
                     DataType::UnsignedBigInt => 4,
                     DataType::Varchar(_) => 5,
+                    DataType::Char(_) => 6,
+                    DataType::Double => 7,
                 });
-                if let DataType::Varchar(max_characters) = col.data_type {
+                if let DataType::Varchar(max_characters) | DataType::Char(max_characters) =
+                    col.data_type
+                {
                     packet.extend_from_slice(&(max_characters as u32).to_le_bytes());
                 }
             }
             packet.extend_from_slice(&(u32::try_from(query_set.tuples.len())?).to_le_bytes());
             for tuple in &query_set.tuples {
-                packet.extend_from_slice(&tuple::serialize(&query_set.schema, tuple));
+                let tuple = tuple::serialize(&query_set.schema, tuple)
+                    .map_err(|e| EncodingError::InvalidTuple(e.to_string()))?;
+                packet.extend_from_slice(&tuple);
             }
         }
     }
@@ -332,14 +343,19 @@ pub fn deserialize(payload: &[u8]) -> Result<Response, EncodingError> {
                     2 => DataType::UnsignedInt,
                     3 => DataType::BigInt,
                     4 => DataType::UnsignedBigInt,
-                    5 => {
+                    7 => DataType::Double,
+                    string @ (5 | 6) => {
                         let mut max_chars_buf = [0; 4];
                         max_chars_buf.copy_from_slice(&payload[cursor + 1..cursor + 5]);
 
                         let max_chars = u32::from_le_bytes(max_chars_buf) as usize;
                         cursor += 4;
 
-                        DataType::Varchar(max_chars)
+                        if string == 5 {
+                            DataType::Varchar(max_chars)
+                        } else {
+                            DataType::Char(max_chars)
+                        }
                     }
                     invalid => Err(EncodingError::InvalidDataType(invalid))?,
                 };
@@ -352,7 +368,8 @@ pub fn deserialize(payload: &[u8]) -> Result<Response, EncodingError> {
             cursor += 4;
 
             for _ in 0..num_tuples {
-                let tuple = tuple::deserialize(&payload[cursor..], &query_set.schema);
+                let tuple = tuple::deserialize(&payload[cursor..], &query_set.schema)
+                    .map_err(|e| EncodingError::InvalidTuple(e.to_string()))?;
                 cursor += tuple::size_of(&tuple, &query_set.schema);
                 query_set.tuples.push(tuple);
             }
@@ -4,7 +4,10 @@ use std::{fmt::Display, mem};
 
 use crate::{
     db::{Schema, SqlError},
-    sql::statement::{BinaryOperator, DataType, Expression, UnaryOperator, Value},
+    sql::statement::{
+        join, AggregateFunction, BinaryOperator, DataType, Expression, ScalarFunction,
+        UnaryOperator, Value,
+    },
 };
 
 /// Generic data types used at runtime by [`crate::vm`] without SQL details
@@ -16,6 +19,7 @@ pub(crate) enum VmDataType {
     Bool,
     String,
     Number,
+    Float,
 }
 
 impl Display for VmDataType {
@@ -24,6 +28,7 @@ impl Display for VmDataType {
             Self::Bool => "boolean",
             Self::Number => "number",
             Self::String => "string",
+            Self::Float => "float",
         })
     }
 }
@@ -31,8 +36,9 @@ impl Display for VmDataType {
 impl From<DataType> for VmDataType {
     fn from(data_type: DataType) -> Self {
         match data_type {
-            DataType::Varchar(_) => VmDataType::String,
+            DataType::Varchar(_) | DataType::Char(_) => VmDataType::String,
             DataType::Bool => VmDataType::Bool,
+            DataType::Double => VmDataType::Float,
             _ => VmDataType::Number,
         }
     }
@@ -41,13 +47,29 @@ impl From<DataType> for VmDataType {
 /// Errors that can only be thrown by the VM itself.
 #[derive(Debug, PartialEq)]
 pub(crate) enum VmError {
-    DivisionByZero(i128, i128),
+    DivisionByZero(Value, Value),
+    /// Function call whose result doesn't fit in an integer.
+    IntegerOverflow(Expression),
+    /// `POWER()` with integer arguments and a negative exponent.
+    NegativeExponent(i128),
+    /// `SUM()`, `AVG()`, `MIN()` or `MAX()` of an empty input, which would be
+    /// `NULL`.
+    EmptyAggregate(AggregateFunction),
 }
 
 impl Display for VmError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Self::DivisionByZero(left, right) => write!(f, "division by zero: {left} / {right}"),
+            Self::IntegerOverflow(expr) => write!(f, "integer overflow: {expr}"),
+            Self::NegativeExponent(exponent) => write!(
+                f,
+                "negative exponent {exponent} requires a float base like POWER(2.0, {exponent})"
+            ),
+            Self::EmptyAggregate(func) => write!(
+                f,
+                "{func}() of an empty input is NULL, NULL values are not supported"
+            ),
         }
     }
 }
@@ -119,7 +141,9 @@ pub(crate) fn resolve_expression(
 
         Expression::UnaryOperation { operator, expr } => {
             match resolve_expression(tuple, schema, expr)? {
-                Value::Number(mut num) => {
+                Value::Bool(bool) if *operator == UnaryOperator::Not => Ok(Value::Bool(!bool)),
+
+                Value::Number(mut num) if *operator != UnaryOperator::Not => {
                     if let UnaryOperator::Minus = operator {
                         num = -num;
                     }
@@ -127,6 +151,14 @@ pub(crate) fn resolve_expression(
                     Ok(Value::Number(num))
                 }
 
+                Value::Float(mut float) if *operator != UnaryOperator::Not => {
+                    if let UnaryOperator::Minus = operator {
+                        float = -float;
+                    }
+
+                    Ok(Value::Float(float))
+                }
+
                 value => Err(SqlError::TypeError(TypeError::CannotApplyUnary {
                     operator: *operator,
                     value,
@@ -134,6 +166,20 @@ pub(crate) fn resolve_expression(
             }
         }
 
+        Expression::BinaryOperation {
+            left,
+            operator,
+            right,
+        } if matches!(**left, Expression::Tuple(_)) => {
+            let (Expression::Tuple(left), Expression::Tuple(right)) = (&**left, &**right) else {
+                return Err(SqlError::Other(format!(
+                    "cannot compare {left} with {right}"
+                )));
+            };
+
+            compare_tuples(tuple, schema, left, *operator, right)
+        }
+
         Expression::BinaryOperation {
             left,
             operator,
@@ -142,6 +188,8 @@ pub(crate) fn resolve_expression(
             let left = resolve_expression(tuple, schema, left)?;
             let right = resolve_expression(tuple, schema, right)?;
 
+            let (left, right) = promote_numbers(left, right);
+
             let mismatched_types = || {
                 SqlError::TypeError(TypeError::CannotApplyBinary {
                     left: Expression::Value(left.clone()),
@@ -162,6 +210,22 @@ pub(crate) fn resolve_expression(
                 BinaryOperator::Gt => Value::Bool(left > right),
                 BinaryOperator::GtEq => Value::Bool(left >= right),
 
+                BinaryOperator::Like => {
+                    let (Value::String(string), Value::String(pattern)) = (&left, &right) else {
+                        return Err(mismatched_types());
+                    };
+
+                    Value::Bool(like(string, pattern))
+                }
+
+                BinaryOperator::Concat => {
+                    let (Value::String(left), Value::String(right)) = (&left, &right) else {
+                        return Err(mismatched_types());
+                    };
+
+                    Value::String(format!("{left}{right}"))
+                }
+
                 logical @ (BinaryOperator::And | BinaryOperator::Or) => {
                     let (Value::Bool(left), Value::Bool(right)) = (&left, &right) else {
                         return Err(mismatched_types());
@@ -175,33 +239,363 @@ pub(crate) fn resolve_expression(
                 }
 
                 arithmetic => {
-                    let (Value::Number(left), Value::Number(right)) = (&left, &right) else {
-                        return Err(mismatched_types());
+                    let division_by_zero = match (&left, &right) {
+                        (Value::Number(_), Value::Number(right)) => *right == 0,
+                        (Value::Float(_), Value::Float(right)) => *right == 0.0,
+                        _ => return Err(mismatched_types()),
                     };
 
-                    if arithmetic == &BinaryOperator::Div && *right == 0 {
-                        return Err(VmError::DivisionByZero(*left, *right).into());
+                    if arithmetic == &BinaryOperator::Div && division_by_zero {
+                        return Err(VmError::DivisionByZero(left, right).into());
                     }
 
-                    Value::Number(match arithmetic {
-                        BinaryOperator::Plus => left + right,
-                        BinaryOperator::Minus => left - right,
-                        BinaryOperator::Mul => left * right,
-                        BinaryOperator::Div => left / right,
-                        _ => unreachable!("unhandled arithmetic operator: {arithmetic}"),
-                    })
+                    match (left, right) {
+                        (Value::Number(left), Value::Number(right)) => {
+                            Value::Number(match arithmetic {
+                                BinaryOperator::Plus => left + right,
+                                BinaryOperator::Minus => left - right,
+                                BinaryOperator::Mul => left * right,
+                                BinaryOperator::Div => left / right,
+                                _ => unreachable!("unhandled arithmetic operator: {arithmetic}"),
+                            })
+                        }
+
+                        (Value::Float(left), Value::Float(right)) => {
+                            Value::Float(match arithmetic {
+                                BinaryOperator::Plus => left + right,
+                                BinaryOperator::Minus => left - right,
+                                BinaryOperator::Mul => left * right,
+                                BinaryOperator::Div => left / right,
+                                _ => unreachable!("unhandled arithmetic operator: {arithmetic}"),
+                            })
+                        }
+
+                        _ => unreachable!(),
+                    }
                 }
             })
         }
 
         Expression::Nested(expr) => resolve_expression(tuple, schema, expr),
 
+        Expression::InList {
+            expr,
+            list,
+            negated,
+        } => {
+            let value = resolve_expression(tuple, schema, expr)?;
+            let mut found = false;
+
+            for item in list {
+                let item = resolve_expression(tuple, schema, item)?;
+
+                let equal = match (&value, &item) {
+                    (Value::Number(left), Value::Float(right)) => *left as f64 == *right,
+                    (Value::Float(left), Value::Number(right)) => *left == *right as f64,
+                    (left, right) if mem::discriminant(left) == mem::discriminant(right) => {
+                        left == right
+                    }
+                    _ => {
+                        return Err(SqlError::TypeError(TypeError::CannotApplyBinary {
+                            left: Expression::Value(value),
+                            operator: BinaryOperator::Eq,
+                            right: Expression::Value(item),
+                        }))
+                    }
+                };
+
+                if equal {
+                    found = true;
+                    break;
+                }
+            }
+
+            Ok(Value::Bool(found != *negated))
+        }
+
+        Expression::Between {
+            expr,
+            low,
+            high,
+            negated,
+        } => {
+            let value = resolve_expression(tuple, schema, expr)?;
+            let mut between = true;
+
+            for (bound, operator) in [(low, BinaryOperator::GtEq), (high, BinaryOperator::LtEq)] {
+                let bound = resolve_expression(tuple, schema, bound)?;
+                let (value, bound) = promote_numbers(value.clone(), bound);
+
+                if mem::discriminant(&value) != mem::discriminant(&bound) {
+                    return Err(SqlError::TypeError(TypeError::CannotApplyBinary {
+                        left: Expression::Value(value),
+                        operator,
+                        right: Expression::Value(bound),
+                    }));
+                }
+
+                between &= match operator {
+                    BinaryOperator::GtEq => value >= bound,
+                    _ => value <= bound,
+                };
+            }
+
+            Ok(Value::Bool(between != *negated))
+        }
+
+        Expression::IsNull { .. } => {
+            unreachable!("IS NULL should be rejected by the analyzer")
+        }
+
+        Expression::Function { func, args } => {
+            let args = args
+                .iter()
+                .map(|arg| resolve_expression(tuple, schema, arg))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            call_function(*func, args)
+        }
+
+        Expression::Case {
+            branches,
+            else_branch,
+        } => {
+            for (condition, result) in branches {
+                match resolve_expression(tuple, schema, condition)? {
+                    Value::Bool(true) => return resolve_expression(tuple, schema, result),
+                    Value::Bool(false) => {}
+                    other => Err(TypeError::ExpectedType {
+                        expected: VmDataType::Bool,
+                        found: Expression::Value(other),
+                    })?,
+                }
+            }
+
+            let Some(else_branch) = else_branch else {
+                unreachable!("CASE without ELSE should be rejected by the analyzer")
+            };
+
+            resolve_expression(tuple, schema, else_branch)
+        }
+
+        Expression::Tuple(_) => Err(SqlError::Other(format!(
+            "tuples can only be compared with other tuples: {expr}"
+        ))),
+
         Expression::Wildcard => {
             unreachable!("wildcards should be resolved into identifiers at this point")
         }
+
+        Expression::QualifiedIdentifier { .. } => {
+            unreachable!("qualified identifiers should be resolved at this point")
+        }
+
+        Expression::Aggregate { .. } => {
+            unreachable!("aggregate functions should be computed by the aggregate plan")
+        }
+    }
+}
+
+/// Compares two tuples element by element.
+///
+/// Only `=` and `!=` are supported. Elements are resolved lazily, so `=`
+/// stops at the first pair of elements that are not equal.
+fn compare_tuples(
+    tuple: &Vec<Value>,
+    schema: &Schema,
+    left: &[Expression],
+    operator: BinaryOperator,
+    right: &[Expression],
+) -> Result<Value, SqlError> {
+    if left.len() != right.len() || !matches!(operator, BinaryOperator::Eq | BinaryOperator::Neq) {
+        return Err(SqlError::Other(format!(
+            "cannot apply {operator} to tuples of {} and {} elements",
+            left.len(),
+            right.len()
+        )));
+    }
+
+    let mut equal = true;
+
+    for (left, right) in left.iter().zip(right) {
+        let left = resolve_expression(tuple, schema, left)?;
+        let right = resolve_expression(tuple, schema, right)?;
+        let (left, right) = promote_numbers(left, right);
+
+        if mem::discriminant(&left) != mem::discriminant(&right) {
+            return Err(SqlError::TypeError(TypeError::CannotApplyBinary {
+                left: Expression::Value(left),
+                operator,
+                right: Expression::Value(right),
+            }));
+        }
+
+        if left != right {
+            equal = false;
+            break;
+        }
+    }
+
+    Ok(Value::Bool(equal == (operator == BinaryOperator::Eq)))
+}
+
+/// Computes the result of a scalar function given its already resolved
+/// arguments.
+///
+/// Strings are processed character by character, not byte by byte. `SUBSTR`
+/// positions start at 1 like in every other SQL database. Numeric functions
+/// work on integers unless they get floats, in which case all the arguments
+/// are promoted to floats. Integer results that don't fit in [`i128`] are
+/// reported as errors, so are integer exponents that would produce fractions.
+fn call_function(func: ScalarFunction, args: Vec<Value>) -> Result<Value, SqlError> {
+    let overflow = || {
+        VmError::IntegerOverflow(Expression::Function {
+            func,
+            args: args.iter().cloned().map(Expression::Value).collect(),
+        })
+    };
+
+    let invalid_arguments = || {
+        SqlError::Other(format!(
+            "invalid arguments for function {func}(): {}",
+            join(&args, ", ")
+        ))
+    };
+
+    Ok(match (func, &args[..]) {
+        (ScalarFunction::Upper, [Value::String(string)]) => Value::String(string.to_uppercase()),
+        (ScalarFunction::Lower, [Value::String(string)]) => Value::String(string.to_lowercase()),
+        (ScalarFunction::Trim, [Value::String(string)]) => {
+            Value::String(string.trim_matches(' ').into())
+        }
+        (ScalarFunction::Length, [Value::String(string)]) => {
+            Value::Number(string.chars().count() as i128)
+        }
+
+        (
+            ScalarFunction::Substr,
+            [Value::String(string), Value::Number(start), Value::Number(length)],
+        ) => {
+            // Positions before the first character still count towards the
+            // length, so SUBSTR('abc', 0, 2) is 'a'.
+            let skip = start.saturating_sub(1).max(0);
+            let take = start.saturating_sub(1).saturating_add(*length).max(skip) - skip;
+
+            Value::String(
+                string
+                    .chars()
+                    .skip(skip.try_into().unwrap_or(usize::MAX))
+                    .take(take.try_into().unwrap_or(usize::MAX))
+                    .collect(),
+            )
+        }
+
+        (ScalarFunction::Abs, [Value::Number(num)]) => {
+            Value::Number(num.checked_abs().ok_or_else(overflow)?)
+        }
+        (ScalarFunction::Abs, [Value::Float(float)]) => Value::Float(float.abs()),
+
+        (ScalarFunction::Mod | ScalarFunction::Power, [left, right]) => {
+            match promote_numbers(left.clone(), right.clone()) {
+                (Value::Number(left), Value::Number(right)) if func == ScalarFunction::Mod => {
+                    if right == 0 {
+                        return Err(VmError::DivisionByZero(
+                            Value::Number(left),
+                            Value::Number(right),
+                        )
+                        .into());
+                    }
+
+                    Value::Number(left.checked_rem(right).ok_or_else(overflow)?)
+                }
+
+                (Value::Number(base), Value::Number(exponent)) => {
+                    if exponent < 0 {
+                        return Err(VmError::NegativeExponent(exponent).into());
+                    }
+
+                    let result = u32::try_from(exponent)
+                        .ok()
+                        .and_then(|exponent| base.checked_pow(exponent));
+
+                    Value::Number(result.ok_or_else(overflow)?)
+                }
+
+                (Value::Float(left), Value::Float(right)) if func == ScalarFunction::Mod => {
+                    if right == 0.0 {
+                        return Err(VmError::DivisionByZero(
+                            Value::Float(left),
+                            Value::Float(right),
+                        )
+                        .into());
+                    }
+
+                    Value::Float(left % right)
+                }
+
+                (Value::Float(base), Value::Float(exponent)) => Value::Float(base.powf(exponent)),
+
+                _ => return Err(invalid_arguments()),
+            }
+        }
+
+        _ => return Err(invalid_arguments()),
+    })
+}
+
+/// Integers are promoted to floats when mixed with floats.
+fn promote_numbers(left: Value, right: Value) -> (Value, Value) {
+    match (left, right) {
+        (Value::Number(left), right @ Value::Float(_)) => (Value::Float(left as f64), right),
+        (left @ Value::Float(_), Value::Number(right)) => (left, Value::Float(right as f64)),
+        operands => operands,
     }
 }
 
+/// Returns `true` if `string` matches the `LIKE` `pattern`.
+///
+/// `%` matches any sequence of characters (including none) and `_` matches
+/// exactly one character. Everything else must match exactly, there's no
+/// escape character. When a character doesn't match we go back to the last
+/// `%` and let it consume one more character, so there's no need to compile
+/// the pattern into a regex or anything like that.
+pub(crate) fn like(string: &str, pattern: &str) -> bool {
+    let string: Vec<char> = string.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+
+    let (mut s, mut p) = (0, 0);
+
+    // Position of the last % in the pattern and the string position where it
+    // stopped consuming characters.
+    let mut backtrack = None;
+
+    while s < string.len() {
+        match pattern.get(p) {
+            Some('%') => {
+                backtrack = Some((p, s));
+                p += 1;
+            }
+
+            Some(chr) if *chr == '_' || *chr == string[s] => {
+                s += 1;
+                p += 1;
+            }
+
+            _ => {
+                let Some((percent, consumed)) = backtrack else {
+                    return false;
+                };
+
+                backtrack = Some((percent, consumed + 1));
+                p = percent + 1;
+                s = consumed + 1;
+            }
+        }
+    }
+
+    pattern[p..].iter().all(|chr| *chr == '%')
+}
+
 /// Same as [`resolve_expression`] but without variables.
 ///
 /// If the given expression actually contains variables
@@ -229,12 +623,14 @@ pub(crate) fn eval_where(
 
 #[cfg(test)]
 mod tests {
-    use super::VmError;
+    use super::{like, TypeError, VmError};
     use crate::{
         db::{DbError, Schema, SqlError},
         sql::{
             parser::Parser,
-            statement::{Column, DataType, Value},
+            statement::{
+                BinaryOperator, Column, DataType, Expression, ScalarFunction, UnaryOperator, Value,
+            },
         },
         vm::resolve_expression,
     };
@@ -341,6 +737,268 @@ mod tests {
         })
     }
 
+    #[test]
+    fn like_patterns() {
+        for (string, pattern, expected) in [
+            ("John", "John", true),
+            ("John", "john", false),
+            ("John", "Jo%", true),
+            ("John", "%hn", true),
+            ("John", "%o%", true),
+            ("John", "J__n", true),
+            ("John", "J_n", false),
+            ("John", "%", true),
+            ("", "%", true),
+            ("", "_", false),
+            ("Johnny", "J%n%y", true),
+            ("Johnny", "J%n%x", false),
+            ("aaab", "%a%ab", true),
+            ("ñandú", "_and_", true),
+        ] {
+            assert_eq!(
+                like(string, pattern),
+                expected,
+                "'{string}' LIKE '{pattern}'"
+            );
+        }
+    }
+
+    #[test]
+    fn resolve_like() -> Result<(), DbError> {
+        assert_resolve(Resolve {
+            expression: "name LIKE 'Jo%' AND name LIKE '%e'",
+            vm_context: VmCtx {
+                schema: Schema::new(vec![Column::new("name", DataType::Varchar(255))]),
+                tuple: vec![Value::String("Joe".into())],
+            },
+            expected: Ok(Value::Bool(true)),
+        })
+    }
+
+    #[test]
+    fn resolve_in_list() -> Result<(), DbError> {
+        let vm_context = || VmCtx {
+            schema: Schema::new(vec![
+                Column::new("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+            ]),
+            tuple: vec![Value::Number(2), Value::String("John".into())],
+        };
+
+        for (expression, expected) in [
+            ("id IN (1, 2, 3)", true),
+            ("id IN (1, 3)", false),
+            ("id IN (2.0)", true),
+            ("id NOT IN (1, 3)", true),
+            ("id IN ()", false),
+            ("id NOT IN ()", true),
+            ("name IN ('Mary', 'John')", true),
+        ] {
+            assert_resolve(Resolve {
+                expression,
+                vm_context: vm_context(),
+                expected: Ok(Value::Bool(expected)),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_between() -> Result<(), DbError> {
+        let vm_context = || VmCtx {
+            schema: Schema::new(vec![
+                Column::new("age", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+            ]),
+            tuple: vec![Value::Number(18), Value::String("John".into())],
+        };
+
+        for (expression, expected) in [
+            ("age BETWEEN 18 AND 65", true),
+            ("age BETWEEN 10 AND 18", true),
+            ("age BETWEEN 19 AND 65", false),
+            ("age BETWEEN 17.5 AND 18.5", true),
+            ("age NOT BETWEEN 19 AND 65", true),
+            ("name BETWEEN 'A' AND 'K'", true),
+        ] {
+            assert_resolve(Resolve {
+                expression,
+                vm_context: vm_context(),
+                expected: Ok(Value::Bool(expected)),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_functions() -> Result<(), DbError> {
+        let vm_context = || VmCtx {
+            schema: Schema::new(vec![Column::new("name", DataType::Varchar(255))]),
+            tuple: vec![Value::String("  Straße  ".into())],
+        };
+
+        for (expression, expected) in [
+            ("UPPER(name)", Value::String("  STRASSE  ".into())),
+            ("LOWER('MkDb')", Value::String("mkdb".into())),
+            ("TRIM(name)", Value::String("Straße".into())),
+            ("LENGTH(name)", Value::Number(10)),
+            ("LENGTH(TRIM(name))", Value::Number(6)),
+            ("SUBSTR(name, 3, 4)", Value::String("Stra".into())),
+            ("SUBSTR(name, 7, 100)", Value::String("ße  ".into())),
+            ("SUBSTR(name, 0, 3)", Value::String("  ".into())),
+            ("SUBSTR(name, 20, 3)", Value::String("".into())),
+            ("SUBSTR(name, 5, -1)", Value::String("".into())),
+        ] {
+            assert_resolve(Resolve {
+                expression,
+                vm_context: vm_context(),
+                expected: Ok(expected),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_numeric_functions() -> Result<(), DbError> {
+        let vm_context = || VmCtx {
+            schema: Schema::new(vec![
+                Column::new("x", DataType::BigInt),
+                Column::new("y", DataType::Double),
+            ]),
+            tuple: vec![Value::Number(-7), Value::Float(-2.5)],
+        };
+
+        for (expression, expected) in [
+            ("ABS(x)", Ok(Value::Number(7))),
+            ("ABS(y)", Ok(Value::Float(2.5))),
+            ("MOD(x, 3)", Ok(Value::Number(-1))),
+            ("MOD(y, 2)", Ok(Value::Float(-0.5))),
+            ("POWER(x, 2)", Ok(Value::Number(49))),
+            ("POWER(x, 0)", Ok(Value::Number(1))),
+            ("POWER(2, y)", Ok(Value::Float(2f64.powf(-2.5)))),
+            (
+                "MOD(x, 0)",
+                Err(VmError::DivisionByZero(Value::Number(-7), Value::Number(0)).into()),
+            ),
+            ("POWER(x, -1)", Err(VmError::NegativeExponent(-1).into())),
+            (
+                "POWER(x, 100)",
+                Err(VmError::IntegerOverflow(Expression::Function {
+                    func: ScalarFunction::Power,
+                    args: vec![
+                        Expression::Value(Value::Number(-7)),
+                        Expression::Value(Value::Number(100)),
+                    ],
+                })
+                .into()),
+            ),
+        ] {
+            assert_resolve(Resolve {
+                expression,
+                vm_context: vm_context(),
+                expected,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_case() -> Result<(), DbError> {
+        let vm_context = || VmCtx {
+            schema: Schema::new(vec![Column::new("age", DataType::Int)]),
+            tuple: vec![Value::Number(15)],
+        };
+
+        for (expression, expected) in [
+            (
+                "CASE WHEN age >= 18 THEN 'adult' ELSE 'minor' END",
+                Value::String("minor".into()),
+            ),
+            (
+                "CASE WHEN age > 12 THEN 'teen' WHEN age > 5 THEN 'child' ELSE 'baby' END",
+                Value::String("teen".into()),
+            ),
+            (
+                "CASE WHEN age < 5 THEN 0 WHEN age < 18 THEN age * 2 ELSE age END",
+                Value::Number(30),
+            ),
+        ] {
+            assert_resolve(Resolve {
+                expression,
+                vm_context: vm_context(),
+                expected: Ok(expected),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_tuple_comparisons() -> Result<(), DbError> {
+        let vm_context = || VmCtx {
+            schema: Schema::new(vec![
+                Column::new("id", DataType::Int),
+                Column::new("name", DataType::Varchar(255)),
+            ]),
+            tuple: vec![Value::Number(1), Value::String("John".into())],
+        };
+
+        for (expression, expected) in [
+            ("(id, name) = (1, 'John')", true),
+            ("(id, name) = (1.0, 'John')", true),
+            ("(id, name) = (1, 'Mary')", false),
+            ("(id, name) != (1, 'John')", false),
+            ("(id, name) != (2, 'John')", true),
+        ] {
+            assert_resolve(Resolve {
+                expression,
+                vm_context: vm_context(),
+                expected: Ok(Value::Bool(expected)),
+            })?;
+        }
+
+        assert_resolve(Resolve {
+            expression: "(id, name) = ('John', 1)",
+            vm_context: vm_context(),
+            expected: Err(SqlError::TypeError(TypeError::CannotApplyBinary {
+                left: Expression::Value(Value::Number(1)),
+                operator: BinaryOperator::Eq,
+                right: Expression::Value(Value::String("John".into())),
+            })),
+        })
+    }
+
+    #[test]
+    fn resolve_not() -> Result<(), DbError> {
+        let vm_context = || VmCtx {
+            schema: Schema::new(vec![
+                Column::new("id", DataType::Int),
+                Column::new("is_admin", DataType::Bool),
+            ]),
+            tuple: vec![Value::Number(5), Value::Bool(false)],
+        };
+
+        assert_resolve(Resolve {
+            expression: "NOT is_admin AND NOT id > 10",
+            vm_context: vm_context(),
+            expected: Ok(Value::Bool(true)),
+        })?;
+
+        assert_resolve(Resolve {
+            expression: "NOT id",
+            vm_context: vm_context(),
+            expected: Err(TypeError::CannotApplyUnary {
+                operator: UnaryOperator::Not,
+                value: Value::Number(5),
+            }
+            .into()),
+        })
+    }
+
     #[test]
     fn division_by_zero() -> Result<(), DbError> {
         assert_resolve(Resolve {
@@ -352,7 +1010,37 @@ mod tests {
                 ]),
                 tuple: vec![Value::Number(15), Value::Number(5)],
             },
-            expected: Err(VmError::DivisionByZero(10, 0).into()),
+            expected: Err(VmError::DivisionByZero(Value::Number(10), Value::Number(0)).into()),
+        })
+    }
+
+    #[test]
+    fn float_arithmetic_promotes_integers() -> Result<(), DbError> {
+        assert_resolve(Resolve {
+            expression: "x * 2 + 0.5",
+            vm_context: VmCtx {
+                schema: Schema::new(vec![Column::new("x", DataType::Double)]),
+                tuple: vec![Value::Float(1.25)],
+            },
+            expected: Ok(Value::Float(3.0)),
+        })?;
+
+        assert_resolve(Resolve {
+            expression: "x > 1",
+            vm_context: VmCtx {
+                schema: Schema::new(vec![Column::new("x", DataType::Double)]),
+                tuple: vec![Value::Float(1.25)],
+            },
+            expected: Ok(Value::Bool(true)),
+        })
+    }
+
+    #[test]
+    fn float_division_by_zero() -> Result<(), DbError> {
+        assert_resolve(Resolve {
+            expression: "1.5 / 0.0",
+            vm_context: VmCtx::none(),
+            expected: Err(VmError::DivisionByZero(Value::Float(1.5), Value::Float(0.0)).into()),
         })
     }
 
@@ -46,7 +46,7 @@
 use std::{
     cell::RefCell,
     cmp::{self, Ordering},
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     fmt::{self, Debug, Display},
     io::{self, BufRead, BufReader, Read, Seek, Write},
     iter, mem,
@@ -55,6 +55,7 @@ use std::{
     ptr,
     rc::Rc,
     slice,
+    time::{Duration, Instant},
 };
 
 use crate::{
@@ -63,11 +64,16 @@ use crate::{
         io::FileOps,
         pager::{PageNumber, Pager},
     },
-    sql::statement::{join, Assignment, Expression, Value},
+    sql::{
+        analyzer,
+        statement::{join, AggregateFunction, Assignment, Direction, Expression, Value},
+    },
     storage::{
-        reassemble_payload, tuple, BTree, BTreeKeyComparator, BytesCmp, Cursor, FixedSizeMemCmp,
+        free_cell, reassemble_payload,
+        tuple::{self, FormatVersion, IntEncoding},
+        BTree, BTreeKeyComparator, BytesCmp, Cursor, FixedSizeMemCmp,
     },
-    vm,
+    vm::{self, VmDataType, VmError},
 };
 
 pub(crate) type Tuple = Vec<Value>;
@@ -94,12 +100,24 @@ pub(crate) enum Plan<F> {
     KeyScan(KeyScan<F>),
     /// Multi-index or multi-range scan.
     LogicalOrScan(LogicalOrScan<F>),
+    /// Intersection of the keys returned by two index scans.
+    LogicalAndScan(LogicalAndScan<F>),
+    /// Executes `JOIN` clauses.
+    NestedLoopJoin(NestedLoopJoin<F>),
     /// Returns raw values from `INSERT INTO` statements.
     Values(Values),
+    /// Returns nothing. Used when the query can't possibly match any tuple.
+    Empty(Empty),
     /// Executes `WHERE` clauses and filters rows.
     Filter(Filter<F>),
     /// Final projection of a plan. Usually the columns of `SELECT` statements.
     Project(Project<F>),
+    /// Executes `GROUP BY` clauses and aggregate functions like `COUNT(*)`.
+    GroupBy(GroupBy<F>),
+    /// Executes `LIMIT` and `OFFSET` clauses.
+    Limit(Limit<F>),
+    /// Removes duplicated tuples for `SELECT DISTINCT`.
+    Distinct(Distinct<F>),
     /// Inserts data into tables.
     Insert(Insert<F>),
     /// Executes assignment expressions from `UPDATE` statements.
@@ -108,10 +126,14 @@ pub(crate) enum Plan<F> {
     Delete(Delete<F>),
     /// Executes `ORDER BY` clauses or any other internal sorting.
     Sort(Sort<F>),
+    /// Sorts and keeps only the first N tuples. See [`TopN`].
+    TopN(TopN<F>),
     /// Helper for the main [`Plan::Sort`] plan.
     SortKeysGen(SortKeysGen<F>),
     /// Helper for various plans.
     Collect(Collect<F>),
+    /// Collects runtime stats of another node for `EXPLAIN ANALYZE`.
+    Instrumented(Instrumented<F>),
 }
 
 // TODO: As mentioned at [`crate::paging::pager::get_as`], we could also use
@@ -128,15 +150,23 @@ impl<F: Seek + Read + Write + FileOps> Plan<F> {
             Self::RangeScan(range_scan) => range_scan.try_next(),
             Self::KeyScan(index_scan) => index_scan.try_next(),
             Self::LogicalOrScan(or_scan) => or_scan.try_next(),
+            Self::LogicalAndScan(and_scan) => and_scan.try_next(),
+            Self::NestedLoopJoin(join) => join.try_next(),
             Self::Values(values) => values.try_next(),
+            Self::Empty(_) => Ok(None),
             Self::Filter(filter) => filter.try_next(),
             Self::Project(project) => project.try_next(),
+            Self::GroupBy(group_by) => group_by.try_next(),
+            Self::Limit(limit) => limit.try_next(),
+            Self::Distinct(distinct) => distinct.try_next(),
             Self::Insert(insert) => insert.try_next(),
             Self::Update(update) => update.try_next(),
             Self::Delete(delete) => delete.try_next(),
             Self::Sort(sort) => sort.try_next(),
+            Self::TopN(top_n) => top_n.try_next(),
             Self::SortKeysGen(sort_keys_gen) => sort_keys_gen.try_next(),
             Self::Collect(collect) => collect.try_next(),
+            Self::Instrumented(instrumented) => instrumented.try_next(),
         }
     }
 }
@@ -156,15 +186,23 @@ impl<F> Plan<F> {
     pub fn schema(&self) -> Option<Schema> {
         let schema = match self {
             Self::Project(project) => &project.output_schema,
+            Self::GroupBy(group_by) => &group_by.output_schema,
+            Self::NestedLoopJoin(join) => &join.schema,
             Self::KeyScan(index_scan) => &index_scan.table.schema,
             Self::SeqScan(seq_scan) => &seq_scan.table.schema,
             Self::RangeScan(range_scan) => &range_scan.schema,
             Self::ExactMatch(exact_match) => exact_match.relation.schema(),
             Self::Sort(sort) => &sort.collection.schema,
+            Self::TopN(top_n) => &top_n.comparator.schema,
             Self::Collect(collect) => &collect.schema,
+            Self::Empty(empty) => &empty.schema,
             Self::Filter(filter) => return filter.source.schema(),
+            Self::Limit(limit) => return limit.source.schema(),
+            Self::Distinct(distinct) => return distinct.source.schema(),
 
             Self::LogicalOrScan(or_scan) => return or_scan.scans[0].schema().to_owned(),
+            Self::LogicalAndScan(and_scan) => return and_scan.left.schema(),
+            Self::Instrumented(instrumented) => return instrumented.source.schema(),
             _ => return None,
         };
 
@@ -175,61 +213,250 @@ impl<F> Plan<F> {
     pub fn child(&self) -> Option<&Self> {
         Some(match self {
             Self::KeyScan(index_scan) => &index_scan.source,
+            Self::NestedLoopJoin(join) => &join.outer,
             Self::Filter(filter) => &filter.source,
             Self::Project(project) => &project.source,
+            Self::GroupBy(group_by) => &group_by.source,
+            Self::Limit(limit) => &limit.source,
+            Self::Distinct(distinct) => &distinct.source,
             Self::Insert(insert) => &insert.source,
             Self::Update(update) => &update.source,
             Self::Delete(delete) => &delete.source,
             Self::Sort(sort) => &sort.collection.source,
+            Self::TopN(top_n) => &top_n.source,
             Self::SortKeysGen(sort_keys_gen) => &sort_keys_gen.source,
             Self::Collect(collect) => &collect.source,
+            Self::Instrumented(instrumented) => &instrumented.source,
             _ => return None,
         })
     }
 
-    /// String representation of a plan.
-    pub fn display(&self) -> String {
-        let prefix = "-> ";
+    /// Mutable version of [`Self::child`].
+    fn child_mut(&mut self) -> Option<&mut Self> {
+        Some(match self {
+            Self::KeyScan(index_scan) => &mut index_scan.source,
+            Self::NestedLoopJoin(join) => &mut join.outer,
+            Self::Filter(filter) => &mut filter.source,
+            Self::Project(project) => &mut project.source,
+            Self::GroupBy(group_by) => &mut group_by.source,
+            Self::Limit(limit) => &mut limit.source,
+            Self::Distinct(distinct) => &mut distinct.source,
+            Self::Insert(insert) => &mut insert.source,
+            Self::Update(update) => &mut update.source,
+            Self::Delete(delete) => &mut delete.source,
+            Self::Sort(sort) => &mut sort.collection.source,
+            Self::TopN(top_n) => &mut top_n.source,
+            Self::SortKeysGen(sort_keys_gen) => &mut sort_keys_gen.source,
+            Self::Collect(collect) => &mut collect.source,
+            Self::Instrumented(instrumented) => &mut instrumented.source,
+            _ => return None,
+        })
+    }
+
+    /// Returns all the child nodes of this plan.
+    ///
+    /// Same as [`Self::child`] but also works with plans that have multiple
+    /// sources.
+    pub fn children(&self) -> Vec<&Self> {
+        match self {
+            Self::LogicalOrScan(or_scan) => or_scan.scans.iter().collect(),
+            Self::LogicalAndScan(and_scan) => vec![&and_scan.left, &and_scan.right],
+            _ => self.child().into_iter().collect(),
+        }
+    }
 
+    /// Mutable version of [`Self::children`].
+    fn children_mut(&mut self) -> Vec<&mut Self> {
+        match self {
+            Self::LogicalOrScan(or_scan) => or_scan.scans.iter_mut().collect(),
+            Self::LogicalAndScan(and_scan) => vec![&mut and_scan.left, &mut and_scan.right],
+            _ => self.child_mut().into_iter().collect(),
+        }
+    }
+
+    /// Wraps every node of this plan in [`Plan::Instrumented`] so that the
+    /// number of rows and the time spent on each node can be displayed after
+    /// the plan is executed. Used for `EXPLAIN ANALYZE`.
+    pub fn instrument(mut self) -> Self {
+        for child in self.children_mut() {
+            let placeholder = Self::Values(Values {
+                values: VecDeque::new(),
+            });
+
+            *child = mem::replace(child, placeholder).instrument();
+        }
+
+        Self::Instrumented(Instrumented {
+            source: Box::new(self),
+            rows: 0,
+            time: Duration::ZERO,
+        })
+    }
+
+    /// Writes this node and all its children indented by `depth` levels. See
+    /// the [`Display`] impl.
+    fn fmt_tree(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        write_tree_line(f, depth, &self.display())?;
+
+        // Instrumented nodes are displayed on the same line as the node they
+        // wrap.
+        let node = match self {
+            Self::Instrumented(instrumented) => &instrumented.source,
+            _ => self,
+        };
+
+        for child in node.children() {
+            child.fmt_tree(f, depth + 1)?;
+        }
+
+        // The inner table of joins is not a [`Plan`], it's always a SeqScan.
+        if let Self::NestedLoopJoin(join) = node {
+            write_tree_line(f, depth + 1, &join.inner)?;
+        }
+
+        Ok(())
+    }
+
+    /// Number of times that the [`Collect`] nodes of this plan had to write
+    /// their in-memory buffer to a file because it ran out of space.
+    pub fn spills(&self) -> usize {
+        let spills = match self {
+            Self::Collect(collect) => collect.spills,
+            Self::Sort(sort) => sort.collection.spills,
+            _ => 0,
+        };
+
+        spills + self.child().map_or(0, Self::spills)
+    }
+
+    /// Number of tuples read by each scan node of this plan so far, starting
+    /// from the top.
+    ///
+    /// [`KeyScan`] nodes count the table tuples they read and the index scans
+    /// below them count the index entries, so they're listed separately.
+    pub fn scan_stats(&self) -> Vec<ScanStats> {
+        let mut stats = Vec::new();
+        self.collect_scan_stats(&mut stats);
+        stats
+    }
+
+    /// Recursive helper for [`Self::scan_stats`].
+    fn collect_scan_stats(&self, stats: &mut Vec<ScanStats>) {
+        let rows = match self {
+            Self::SeqScan(seq_scan) => Some(seq_scan.rows_scanned),
+            Self::ExactMatch(exact_match) => Some(exact_match.rows_scanned),
+            Self::RangeScan(range_scan) => Some(range_scan.rows_scanned),
+            Self::KeyScan(index_scan) => Some(index_scan.rows_scanned),
+            _ => None,
+        };
+
+        if let Some(rows) = rows {
+            stats.push(ScanStats {
+                node: self.display(),
+                rows,
+            });
+        }
+
+        for child in self.children() {
+            child.collect_scan_stats(stats);
+        }
+
+        // Not a child plan, see [`Self::fmt_tree`].
+        if let Self::NestedLoopJoin(join) = self {
+            stats.push(ScanStats {
+                node: join.inner.to_string(),
+                rows: join.inner.rows_scanned,
+            });
+        }
+    }
+
+    /// String representation of this node only, without its children.
+    pub fn display(&self) -> String {
         // TODO: Can be optimized with write! macro and fmt::Write. Too lazy to
         // change it, doesn't matter for now.
-        let display = match self {
+        match self {
             Self::SeqScan(seq_scan) => format!("{seq_scan}"),
             Self::ExactMatch(exact_match) => format!("{exact_match}"),
             Self::RangeScan(range_scan) => format!("{range_scan}"),
             Self::KeyScan(index_scan) => format!("{index_scan}"),
             Self::LogicalOrScan(or_scan) => format!("{or_scan}"),
+            Self::LogicalAndScan(and_scan) => format!("{and_scan}"),
+            Self::NestedLoopJoin(join) => format!("{join}"),
             Self::Values(values) => format!("{values}"),
+            Self::Empty(empty) => format!("{empty}"),
             Self::Filter(filter) => format!("{filter}"),
             Self::Project(project) => format!("{project}"),
+            Self::GroupBy(group_by) => format!("{group_by}"),
+            Self::Limit(limit) => format!("{limit}"),
+            Self::Distinct(distinct) => format!("{distinct}"),
             Self::Insert(insert) => format!("{insert}"),
             Self::Update(update) => format!("{update}"),
             Self::Delete(delete) => format!("{delete}"),
             Self::Sort(sort) => format!("{sort}"),
+            Self::TopN(top_n) => format!("{top_n}"),
             Self::SortKeysGen(sort_keys_gen) => format!("{sort_keys_gen}"),
             Self::Collect(collect) => format!("{collect}"),
-        };
-
-        format!("{prefix}{display}")
+            Self::Instrumented(instrumented) => {
+                format!("{} {instrumented}", instrumented.source.display())
+            }
+        }
     }
 }
 
-impl<F> Display for Plan<F> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut plans = vec![self.display()];
+/// Tuples read by one of the scan nodes of a plan. See [`Plan::scan_stats`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ScanStats {
+    /// The scan node as displayed by `EXPLAIN`.
+    pub node: String,
+    /// Tuples read from the BTree of the node.
+    pub rows: usize,
+}
 
-        let mut node = self;
-        while let Some(child) = node.child() {
-            plans.push(child.display());
-            node = child;
-        }
+/// Writes one line of the plan tree. See [`Plan::fmt_tree`].
+fn write_tree_line(f: &mut fmt::Formatter, depth: usize, node: &impl Display) -> fmt::Result {
+    if depth > 0 {
+        write!(f, "{:indent$}-> ", "", indent = depth * 4)?;
+    }
+
+    writeln!(f, "{node}")
+}
 
-        writeln!(f, "{}", plans.pop().unwrap())?;
-        while let Some(plan) = plans.pop() {
-            writeln!(f, "{plan}")?;
+/// Relation name and type for [`Display`] impls, like `table 'users'`.
+///
+/// Indexes also include their key columns so that it's easy to tell which
+/// index is used, like `index 'name_idx' (last_name, first_name DESC)`.
+fn display_relation(relation: &Relation) -> String {
+    let name = format!("{} '{}'", relation.kind(), relation.name());
+
+    match relation {
+        Relation::Table(_) => name,
+        Relation::Index(index) => {
+            let columns = index
+                .columns
+                .iter()
+                .zip(&index.directions)
+                .map(|(column, direction)| match direction {
+                    Direction::Asc => column.name.clone(),
+                    Direction::Desc => format!("{} {direction}", column.name),
+                })
+                .collect::<Vec<_>>();
+
+            format!("{name} ({})", join(&columns, ", "))
         }
+    }
+}
 
-        Ok(())
+/// Renders the plan tree starting at the root, one node per line. Children
+/// are indented below their parent:
+///
+/// ```text
+/// Project (name, email)
+///     -> Filter (age > 20)
+///         -> SeqScan on table 'users'
+/// ```
+impl<F> Display for Plan<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_tree(f, 0)
     }
 }
 
@@ -244,6 +471,8 @@ pub(crate) struct SeqScan<F> {
     pub table: TableMetadata,
     pub pager: Rc<RefCell<Pager<F>>>,
     pub cursor: Cursor,
+    /// Tuples read so far. See [`Plan::scan_stats`].
+    pub rows_scanned: usize,
 }
 
 impl<F: Seek + Read + Write + FileOps> SeqScan<F> {
@@ -254,15 +483,24 @@ impl<F: Seek + Read + Write + FileOps> SeqScan<F> {
             return Ok(None);
         };
 
-        Ok(Some(tuple::deserialize(
+        let tuple = tuple::deserialize_with(
+            pager.format_version,
             reassemble_payload(&mut pager, page, slot)?.as_ref(),
             &self.table.schema,
-        )))
+        )?;
+
+        self.rows_scanned += 1;
+
+        Ok(Some(tuple))
     }
 }
 
 impl<F> Display for SeqScan<F> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.cursor.is_reverse() {
+            f.write_str("Reverse ")?;
+        }
+
         write!(f, "SeqScan on table '{}'", self.table.name)
     }
 }
@@ -276,6 +514,8 @@ pub(crate) struct ExactMatch<F> {
     pub pager: Rc<RefCell<Pager<F>>>,
     pub done: bool,
     pub emit_table_key_only: bool,
+    /// Tuples read so far. See [`Plan::scan_stats`].
+    pub rows_scanned: usize,
 }
 
 impl<F: Seek + Read + Write + FileOps> ExactMatch<F> {
@@ -288,19 +528,29 @@ impl<F: Seek + Read + Write + FileOps> ExactMatch<F> {
         self.done = true;
 
         let mut pager = self.pager.borrow_mut();
-        let mut btree = BTree::new(&mut pager, self.relation.root(), self.relation.comparator());
+        let version = pager.format_version;
+        let comparator = self.relation.comparator(version);
+        let mut btree = BTree::new(&mut pager, self.relation.root(), comparator);
 
         let Some(entry) = btree.get(&self.key)? else {
             return Ok(None);
         };
 
-        let mut tuple = tuple::deserialize(entry.as_ref(), self.relation.schema());
+        let schema = self.relation.schema();
 
-        if self.emit_table_key_only {
+        let tuple = if self.emit_table_key_only {
             let table_key_index = self.relation.index_of_table_key();
-            tuple.drain(table_key_index + 1..);
-            tuple.drain(..table_key_index);
-        }
+            vec![tuple::read_column(
+                version,
+                entry.as_ref(),
+                schema,
+                table_key_index,
+            )?]
+        } else {
+            tuple::deserialize_with(version, entry.as_ref(), schema)?
+        };
+
+        self.rows_scanned += 1;
 
         Ok(Some(tuple))
     }
@@ -310,10 +560,9 @@ impl<F> Display for ExactMatch<F> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "ExactMatch ({}) on {} '{}'",
+            "ExactMatch ({}) on {}",
             self.expr,
-            self.relation.kind(),
-            self.relation.name()
+            display_relation(&self.relation)
         )
     }
 }
@@ -350,20 +599,47 @@ pub(crate) struct RangeScanConfig<F> {
 /// Each [`RangeScan`] object will receive an implementation of [`RangeBounds`]
 /// and will use [`RangeBounds::start_bound`] to position the cursor initially
 /// then it will use [`RangeBounds::end_bound`] to know when to stop.
+///
+/// # Page Skipping
+///
+/// There's no need to maintain separate min/max metadata per page ("zone
+/// maps") in order to skip pages that can't contain keys within the range.
+/// Every key stored in an internal BTree node is already an exact upper bound
+/// for the subtree at its left and a lower bound for the subtree at its right,
+/// so the initial [`BTree::search`] only reads the pages in one root-to-leaf
+/// path and the end bound stops the cursor before it visits any subtree that
+/// starts after the range. In other words, the BTree is its own zone map and
+/// it's always up to date.
+///
+/// # Multi-Column Indexes
+///
+/// The bounds only contain the first column of the BTree key, so when
+/// scanning multi-column indexes many entries can compare equal to a bound.
+/// The cursor is positioned before the first one of them (or after the last
+/// one if the bound is excluded) and [`Bound::Included`] end bounds only stop
+/// the scan once an entry is greater than the bound.
 #[derive(Debug, PartialEq)]
 pub(crate) struct RangeScan<F> {
     pub emit_table_key_only: bool,
+    /// Scan the range backwards, from the end bound to the start bound. Must
+    /// be set before the first call to [`Self::try_next`].
+    pub reverse: bool,
     key_index: usize,
-    relation: Relation,
+    pub relation: Relation,
     root: PageNumber,
     schema: Schema,
     pager: Rc<RefCell<Pager<F>>>,
-    range: (Bound<Vec<u8>>, Bound<Vec<u8>>),
+    pub range: (Bound<Vec<u8>>, Bound<Vec<u8>>),
     comparator: BTreeKeyComparator,
+    /// See [`Relation::has_composite_key`].
+    composite_key: bool,
+    version: FormatVersion,
     expr: Expression,
     cursor: Cursor,
     init: bool,
     done: bool,
+    /// Tuples read so far. See [`Plan::scan_stats`].
+    rows_scanned: usize,
 }
 
 impl<F> From<RangeScanConfig<F>> for RangeScan<F> {
@@ -376,19 +652,25 @@ impl<F> From<RangeScanConfig<F>> for RangeScan<F> {
             expr,
         }: RangeScanConfig<F>,
     ) -> Self {
+        let version = pager.borrow().format_version;
+
         Self {
             schema: relation.schema().clone(),
-            comparator: relation.comparator(),
+            comparator: relation.leading_key_comparator(version),
+            composite_key: relation.has_composite_key(),
+            version,
             root: relation.root(),
             cursor: Cursor::new(relation.root(), 0),
             key_index: relation.index_of_table_key(),
             emit_table_key_only,
+            reverse: false,
             expr,
             pager,
             range,
             relation,
             done: false,
             init: false,
+            rows_scanned: 0,
         }
     }
 }
@@ -398,51 +680,77 @@ impl<F: Seek + Read + Write + FileOps> RangeScan<F> {
     fn init(&mut self) -> io::Result<()> {
         let mut pager = self.pager.borrow_mut();
 
-        let key = match self.range.start_bound() {
-            Bound::Unbounded => return Ok(()),
+        // Reverse scans start at the end bound.
+        let bound = match self.reverse {
+            false => self.range.start_bound(),
+            true => self.range.end_bound(),
+        };
+
+        let key = match bound {
+            Bound::Unbounded => {
+                if self.reverse {
+                    self.cursor = Cursor::new(self.root, 0).reverse();
+                }
+                return Ok(());
+            }
             Bound::Excluded(key) => key,
             Bound::Included(key) => key,
         };
 
+        // Forward scans land before the entries that are equal to an included
+        // bound and reverse scans land after them, so that they are returned.
+        // Excluded bounds do the opposite to skip them.
+        let excluded = matches!(bound, Bound::Excluded(_));
+
+        let comparator = BoundCmp {
+            comparator: self.comparator.clone(),
+            equal: if excluded != self.reverse {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            },
+        };
+
         let mut descent = Vec::new();
-        let mut btree = BTree::new(&mut pager, self.root, self.comparator);
+        let mut btree = BTree::new(&mut pager, self.root, comparator);
         let search = btree.search(self.root, key, &mut descent)?;
 
-        match search.index {
-            // Found exact match. Easy case.
-            Ok(slot) => {
-                self.cursor = Cursor::initialized(search.page, slot, descent);
-
-                // Skip it.
-                if let Bound::Excluded(_) = self.range.start_bound() {
-                    self.cursor.try_next(&mut pager)?;
-                }
+        // [`BoundCmp`] never finds an exact match, so the search always ends
+        // in a leaf and this index is the index where the key "should" be
+        // located. If we were looking for key 2 in this array:
+        //
+        // [1, 3, 5, 7]
+        //
+        // "slot" would be 1. Index 1 points to 3 in the array, which means
+        // we are already located at a key that is >= 1.
+        //
+        // On the other hand, if we were looking for key 8, "slot" would be
+        // 4 which is out of bounds. That means we have to move to the next
+        // page in order to find the first key >= 8. Since that's not easy
+        // at all we'll position the cursor at the last key in the page and
+        // consume that key, allowing the cursor to compute where the next
+        // one is.
+        let (Ok(slot) | Err(slot)) = search.index;
+
+        // Reverse scans start at the key that precedes the slot instead. If
+        // the slot is the first one in the page we let the cursor compute
+        // where the previous key is, just like forward scans do.
+        if self.reverse {
+            self.cursor =
+                Cursor::initialized(search.page, slot.saturating_sub(1), descent).reverse();
+            if slot == 0 {
+                self.cursor.try_next(&mut pager)?;
             }
 
-            // We didn't find the exact key we were looking for. This index is
-            // the index where the key "should" be located. If we were looking
-            // for key 2 in this array:
-            //
-            // [1, 3, 5, 7]
-            //
-            // "slot" would be 1. Index 1 points to 3 in the array, which means
-            // we are already located at a key that is >= 1.
-            //
-            // On the other hand, if we were looking for key 8, "slot" would be
-            // 4 which is out of bounds. That means we have to move to the next
-            // page in order to find the first key >= 8. Since that's not easy
-            // at all we'll position the cursor at the last key in the page and
-            // consume that key, allowing the cursor to compute where the next
-            // one is.
-            Err(slot) => {
-                if slot >= pager.get(search.page)?.len() {
-                    self.cursor = Cursor::initialized(search.page, slot.saturating_sub(1), descent);
-                    self.cursor.try_next(&mut pager)?;
-                } else {
-                    self.cursor = Cursor::initialized(search.page, slot, descent);
-                }
-            }
-        };
+            return Ok(());
+        }
+
+        if slot >= pager.get(search.page)?.len() {
+            self.cursor = Cursor::initialized(search.page, slot.saturating_sub(1), descent);
+            self.cursor.try_next(&mut pager)?;
+        } else {
+            self.cursor = Cursor::initialized(search.page, slot, descent);
+        }
 
         Ok(())
     }
@@ -464,41 +772,80 @@ impl<F: Seek + Read + Write + FileOps> RangeScan<F> {
             return Ok(None);
         };
 
+        self.rows_scanned += 1;
         let entry = reassemble_payload(&mut pager, page, slot)?;
 
-        let bound = self.range.end_bound();
+        // Reverse scans stop at the start bound.
+        let (bound, past_bound) = match self.reverse {
+            false => (self.range.end_bound(), Ordering::Greater),
+            true => (self.range.start_bound(), Ordering::Less),
+        };
+
         if let Bound::Excluded(key) | Bound::Included(key) = bound {
             let ordering = self.comparator.bytes_cmp(entry.as_ref(), key);
-            if let Ordering::Equal | Ordering::Greater = ordering {
+            if ordering == past_bound
+                || ordering == Ordering::Equal && matches!(bound, Bound::Excluded(_))
+            {
                 self.done = true;
-                if matches!(bound, Bound::Excluded(_))
-                    || matches!(bound, Bound::Included(_)) && ordering == Ordering::Greater
-                {
-                    return Ok(None);
-                }
+                return Ok(None);
             }
-        }
 
-        let mut tuple = tuple::deserialize(entry.as_ref(), &self.schema);
+            // Keys are unique unless we're only looking at a prefix of them,
+            // so the next one can't be equal.
+            if ordering == Ordering::Equal && !self.composite_key {
+                self.done = true;
+            }
+        }
 
         if self.emit_table_key_only {
-            tuple.drain(self.key_index + 1..);
-            tuple.drain(..self.key_index);
+            let key =
+                tuple::read_column(self.version, entry.as_ref(), &self.schema, self.key_index)?;
+            return Ok(Some(vec![key]));
         }
 
-        Ok(Some(tuple))
+        Ok(Some(tuple::deserialize_with(
+            self.version,
+            entry.as_ref(),
+            &self.schema,
+        )?))
+    }
+}
+
+/// Comparator used to position the [`RangeScan`] cursor at its first bound.
+///
+/// Entries that are equal to the bound are reported as [`Self::equal`]. That
+/// way the BTree search never stops at an arbitrary entry within a group of
+/// equal keys and ends up right before or right after all of them.
+struct BoundCmp {
+    comparator: BTreeKeyComparator,
+    /// [`Ordering::Greater`] to land before the equal entries or
+    /// [`Ordering::Less`] to land after them.
+    equal: Ordering,
+}
+
+impl BytesCmp for BoundCmp {
+    fn bytes_cmp(&self, a: &[u8], b: &[u8]) -> Ordering {
+        match self.comparator.bytes_cmp(a, b) {
+            Ordering::Equal => self.equal,
+            ordering => ordering,
+        }
     }
 }
 
 impl<F> Display for RangeScan<F> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "RangeScan ({}) on {} '{}'",
-            self.expr,
-            self.relation.kind(),
-            self.relation.name()
-        )
+        if self.reverse {
+            f.write_str("Reverse ")?;
+        }
+
+        f.write_str("RangeScan ")?;
+
+        // Full scans don't have any range to show.
+        if self.expr != Expression::Wildcard {
+            write!(f, "({}) ", self.expr)?;
+        }
+
+        write!(f, "on {}", display_relation(&self.relation))
     }
 }
 
@@ -578,6 +925,8 @@ pub(crate) struct KeyScan<F> {
     pub table: TableMetadata,
     pub pager: Rc<RefCell<Pager<F>>>,
     pub source: Box<Plan<F>>,
+    /// Table tuples read so far. See [`Plan::scan_stats`].
+    pub rows_scanned: usize,
 }
 
 impl<F: Seek + Read + Write + FileOps> KeyScan<F> {
@@ -592,6 +941,7 @@ impl<F: Seek + Read + Write + FileOps> KeyScan<F> {
         );
 
         let mut pager = self.pager.borrow_mut();
+        let version = pager.format_version;
 
         let mut btree = BTree::new(&mut pager, self.table.root, self.comparator);
 
@@ -599,7 +949,7 @@ impl<F: Seek + Read + Write + FileOps> KeyScan<F> {
             .get(&tuple::serialize_key(
                 &self.table.schema.columns[0].data_type,
                 &key_only_tuple[0],
-            ))?
+            )?)?
             .ok_or_else(|| {
                 DbError::Corrupted(format!(
                     "KeyScan received key {key_only_tuple:?} that doesn't exist on table {} at root {}",
@@ -607,10 +957,10 @@ impl<F: Seek + Read + Write + FileOps> KeyScan<F> {
                 ))
             })?;
 
-        Ok(Some(tuple::deserialize(
-            table_entry.as_ref(),
-            &self.table.schema,
-        )))
+        let tuple = tuple::deserialize_with(version, table_entry.as_ref(), &self.table.schema)?;
+        self.rows_scanned += 1;
+
+        Ok(Some(tuple))
     }
 }
 
@@ -693,20 +1043,184 @@ impl<F: Seek + Read + Write + FileOps> LogicalOrScan<F> {
 
 impl<F> Display for LogicalOrScan<F> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "LogicalOrScan")?;
+        f.write_str("LogicalOrScan")
+    }
+}
+
+/// Returns the keys that appear in both of its sources.
+///
+/// Used for AND expressions where each branch can be computed with a different
+/// index:
+///
+/// ```sql
+/// CREATE TABLE users (id INT PRIMARY KEY, email VARCHAR(255) UNIQUE);
+///
+/// SELECT * FROM users WHERE id > 5 AND email < 'm';
+/// ```
+///
+/// Both sources must return unique table keys sorted in ascending order, so
+/// the intersection is computed by merging the two streams without buffering
+/// anything:
+///
+/// ```text
+/// LogicalAndScan
+///     -> RangeScan (id > 5) on table 'users'
+///     -> Sort (id)
+/// ```
+///
+/// Where the [`Sort`] plan sorts the keys returned by a [`RangeScan`] on the
+/// email index.
+///
+/// The keys are then passed to a [`KeyScan`] that fetches the tuples.
+#[derive(Debug, PartialEq)]
+pub(crate) struct LogicalAndScan<F> {
+    pub left: Box<Plan<F>>,
+    pub right: Box<Plan<F>>,
+}
+
+impl<F: Seek + Read + Write + FileOps> LogicalAndScan<F> {
+    fn try_next(&mut self) -> Result<Option<Tuple>, DbError> {
+        let Some(mut left) = self.left.try_next()? else {
+            return Ok(None);
+        };
+
+        let Some(mut right) = self.right.try_next()? else {
+            return Ok(None);
+        };
+
+        // Advance whichever side is behind until both keys are equal.
+        loop {
+            match left[0].partial_cmp(&right[0]) {
+                Some(Ordering::Less) => match self.left.try_next()? {
+                    Some(next) => left = next,
+                    None => return Ok(None),
+                },
+
+                Some(Ordering::Greater) => match self.right.try_next()? {
+                    Some(next) => right = next,
+                    None => return Ok(None),
+                },
+
+                _ => return Ok(Some(left)),
+            }
+        }
+    }
+}
+
+impl<F> Display for LogicalAndScan<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("LogicalAndScan")
+    }
+}
+
+/// Used to build [`NestedLoopJoin`] objects.
+pub(crate) struct NestedLoopJoinConfig<F> {
+    pub outer: Box<Plan<F>>,
+    pub inner: SeqScan<F>,
+    pub schema: Schema,
+    pub on: Expression,
+}
+
+/// Inner join of two relations.
+///
+/// This is the simplest join algorithm there is. For every tuple returned by
+/// the outer source we scan the entire inner table from the beginning and
+/// return the concatenation of both tuples whenever the `ON` expression
+/// evaluates to true:
+///
+/// ```sql
+/// SELECT * FROM users JOIN orders ON users.id = orders.user_id;
+/// ```
+///
+/// The tuples returned by this plan contain all the columns of the outer
+/// source followed by all the columns of the inner table, see
+/// [`Schema::qualified`]. Queries that join more than two tables build a
+/// left-deep tree where the outer source is another [`NestedLoopJoin`].
+///
+/// Only inner joins are supported. The algorithm is O(n * m) and doesn't use
+/// indexes at all, but it preserves the order of the outer source and doesn't
+/// need any memory or temporary files.
+#[derive(Debug, PartialEq)]
+pub(crate) struct NestedLoopJoin<F> {
+    outer: Box<Plan<F>>,
+    inner: SeqScan<F>,
+    schema: Schema,
+    on: Expression,
+    outer_tuple: Option<Tuple>,
+}
 
-        for scan in &self.scans {
-            write!(f, "\n    {}", scan.display())?;
+impl<F> From<NestedLoopJoinConfig<F>> for NestedLoopJoin<F> {
+    fn from(
+        NestedLoopJoinConfig {
+            outer,
+            inner,
+            schema,
+            on,
+        }: NestedLoopJoinConfig<F>,
+    ) -> Self {
+        Self {
+            outer,
+            inner,
+            schema,
+            on,
+            outer_tuple: None,
         }
+    }
+}
 
-        Ok(())
+impl<F: Seek + Read + Write + FileOps> NestedLoopJoin<F> {
+    fn try_next(&mut self) -> Result<Option<Tuple>, DbError> {
+        loop {
+            if self.outer_tuple.is_none() {
+                let Some(tuple) = self.outer.try_next()? else {
+                    return Ok(None);
+                };
+
+                self.outer_tuple = Some(tuple);
+                self.inner.cursor = Cursor::new(self.inner.table.root, 0);
+            }
+
+            let outer_tuple = self.outer_tuple.as_ref().unwrap();
+
+            while let Some(inner_tuple) = self.inner.try_next()? {
+                let tuple = [outer_tuple.as_slice(), &inner_tuple].concat();
+
+                if vm::eval_where(&self.schema, &tuple, &self.on)? {
+                    return Ok(Some(tuple));
+                }
+            }
+
+            // Inner table exhausted, move on to the next outer tuple.
+            self.outer_tuple = None;
+        }
+    }
+}
+
+impl<F> Display for NestedLoopJoin<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "NestedLoopJoin ({})", self.on)
+    }
+}
+
+/// Plan that doesn't return any tuples.
+///
+/// The optimizer uses this when it can determine at planning time that the
+/// query won't match anything, like `SELECT * FROM t WHERE 1 = 2`. No cursors
+/// are opened and no pages are read.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Empty {
+    pub schema: Schema,
+}
+
+impl Display for Empty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Empty")
     }
 }
 
 /// Raw values from `INSERT INTO table (c1, c2) VALUES (v1, v2)`.
 ///
-/// This supports multiple values but the parser does not currently parse
-/// `INSERT` statements with multiple values.
+/// Each element of [`Self::values`] is one row.
 #[derive(Debug, PartialEq)]
 pub(crate) struct Values {
     pub values: VecDeque<Vec<Expression>>,
@@ -746,61 +1260,534 @@ pub(crate) struct Filter<F> {
 
 impl<F: Seek + Read + Write + FileOps> Filter<F> {
     fn try_next(&mut self) -> Result<Option<Tuple>, DbError> {
-        while let Some(tuple) = self.source.try_next()? {
-            if vm::eval_where(&self.schema, &tuple, &self.filter)? {
-                return Ok(Some(tuple));
+        while let Some(tuple) = self.source.try_next()? {
+            if vm::eval_where(&self.schema, &tuple, &self.filter)? {
+                return Ok(Some(tuple));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl<F> Display for Filter<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Filter ({})", self.filter)
+    }
+}
+
+/// Applies a projection to a tuple.
+///
+/// A "projection" is a relation algebra unary operation which, in simple words,
+/// "selects" columns from a row. For example:
+///
+/// ```sql
+/// CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);
+///
+/// SELECT id, age FROM users;
+/// ```
+///
+/// The projection in this case would be the "id" and "age columns". The name
+/// is discarded.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Project<F> {
+    pub source: Box<Plan<F>>,
+    pub input_schema: Schema,
+    pub output_schema: Schema,
+    pub projection: Vec<Expression>,
+}
+
+impl<F: Seek + Read + Write + FileOps> Project<F> {
+    fn try_next(&mut self) -> Result<Option<Tuple>, DbError> {
+        let Some(tuple) = self.source.try_next()? else {
+            return Ok(None);
+        };
+
+        Ok(Some(
+            self.projection
+                .iter()
+                .map(|expr| vm::resolve_expression(&tuple, &self.input_schema, expr))
+                .collect::<Result<Tuple, _>>()?,
+        ))
+    }
+}
+
+impl<F> Display for Project<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Project ({})", join(&self.projection, ", "))
+    }
+}
+
+/// Used to build [`GroupBy`] objects.
+pub(crate) struct GroupByConfig<F> {
+    pub source: Box<Plan<F>>,
+    pub input_schema: Schema,
+    /// Grouping keys followed by one column for each aggregate function.
+    pub output_schema: Schema,
+    /// Expressions that make up the grouping keys.
+    pub group_by: Vec<Expression>,
+    /// [`Expression::Aggregate`] nodes computed for each group.
+    pub aggregates: Vec<Expression>,
+    /// Tuples with equal grouping keys are next to each other in the source.
+    /// See [`GroupBy`].
+    pub sorted: bool,
+}
+
+/// Implements `GROUP BY` clauses and aggregate functions like `COUNT(*)`.
+///
+/// The source is consumed entirely and each tuple is placed in a bucket
+/// depending on the values of [`Self::group_by`]. Buckets are stored in a
+/// [`HashMap`] keyed by the serialized grouping values and each bucket has one
+/// [`Accumulator`] per aggregate function. Once the source is exhausted this
+/// plan returns one tuple per group with the grouping values followed by the
+/// results of the aggregate functions, which is what [`Self::output_schema`]
+/// describes. The final `SELECT` expressions are computed by a [`Project`]
+/// plan on top of this one.
+///
+/// Groups are returned in the order in which they were first seen, so if the
+/// source is sorted by the grouping keys the groups are sorted as well. If
+/// there are no grouping keys at all (`SELECT COUNT(*) FROM table`) then
+/// there's exactly one group, even when the source doesn't return any tuples.
+///
+/// All the groups are held in memory. That's fine for low cardinality keys
+/// like `GROUP BY dept`, but grouping by something close to unique will use
+/// memory proportional to the table size. When the source is already sorted
+/// by the grouping keys (see [`Self::sorted`]) each group ends as soon as the
+/// keys change, so this plan streams the groups instead, keeping only the
+/// current one in memory.
+///
+/// TODO: Unsorted large inputs should spill to disk like [`Collect`] does.
+#[derive(Debug, PartialEq)]
+pub(crate) struct GroupBy<F> {
+    /// Tuple source.
+    source: Box<Plan<F>>,
+    /// Schema of the source tuples.
+    input_schema: Schema,
+    /// Schema of the tuples returned by this plan.
+    output_schema: Schema,
+    /// Grouping keys.
+    group_by: Vec<Expression>,
+    /// Aggregate functions computed for each group.
+    aggregates: Vec<Expression>,
+    /// Result type of each function in [`Self::aggregates`].
+    aggregate_types: Vec<VmDataType>,
+    /// Equal grouping keys are next to each other in the source, so groups
+    /// can be returned one by one.
+    sorted: bool,
+    /// Group being computed when [`Self::sorted`] is `true`.
+    current: Option<(Tuple, Vec<Accumulator>)>,
+    /// Groups ready to be returned.
+    output: VecDeque<Tuple>,
+    /// `true` once the source has been consumed.
+    done: bool,
+}
+
+impl<F> From<GroupByConfig<F>> for GroupBy<F> {
+    fn from(
+        GroupByConfig {
+            source,
+            input_schema,
+            output_schema,
+            group_by,
+            aggregates,
+            sorted,
+        }: GroupByConfig<F>,
+    ) -> Self {
+        let aggregate_types = output_schema.columns[group_by.len()..]
+            .iter()
+            .map(|col| VmDataType::from(col.data_type))
+            .collect();
+
+        Self {
+            source,
+            input_schema,
+            output_schema,
+            group_by,
+            aggregates,
+            aggregate_types,
+            sorted,
+            current: None,
+            output: VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+impl<F> GroupBy<F> {
+    /// Creates the accumulators of a new group.
+    fn accumulators(&self) -> Vec<Accumulator> {
+        self.aggregates
+            .iter()
+            .zip(&self.aggregate_types)
+            .map(|(aggregate, data_type)| match aggregate {
+                Expression::Aggregate {
+                    func,
+                    distinct: false,
+                    ..
+                } => Accumulator::new(*func, *data_type),
+                Expression::Aggregate {
+                    func,
+                    distinct: true,
+                    ..
+                } => Accumulator::Distinct {
+                    seen: HashSet::new(),
+                    inner: Box::new(Accumulator::new(*func, *data_type)),
+                },
+                _ => unreachable!("GroupBy aggregate is not an aggregate function: {aggregate}"),
+            })
+            .collect()
+    }
+
+    /// Computes the grouping keys of the given source tuple.
+    fn keys(&self, tuple: &Tuple) -> Result<Tuple, SqlError> {
+        self.group_by
+            .iter()
+            .map(|expr| vm::resolve_expression(tuple, &self.input_schema, expr))
+            .collect()
+    }
+
+    /// Feeds the source tuple to the accumulators of its group.
+    fn accumulate(&self, tuple: &Tuple, accumulators: &mut [Accumulator]) -> Result<(), SqlError> {
+        for (aggregate, accumulator) in self.aggregates.iter().zip(accumulators) {
+            let Expression::Aggregate { arg, .. } = aggregate else {
+                unreachable!("GroupBy aggregate is not an aggregate function: {aggregate}");
+            };
+
+            accumulator.update(|| vm::resolve_expression(tuple, &self.input_schema, arg))?;
+        }
+
+        Ok(())
+    }
+
+    /// Builds the output tuple of a group.
+    fn finish((mut tuple, accumulators): (Tuple, Vec<Accumulator>)) -> Result<Tuple, SqlError> {
+        for accumulator in accumulators {
+            tuple.push(accumulator.finish()?);
+        }
+
+        Ok(tuple)
+    }
+}
+
+impl<F: Seek + Read + Write + FileOps> GroupBy<F> {
+    /// Consumes the source and computes all the groups.
+    fn group(&mut self) -> Result<(), DbError> {
+        let keys_schema = Schema::new(self.output_schema.columns[..self.group_by.len()].to_vec());
+
+        let mut buckets = HashMap::new();
+        let mut groups: Vec<(Tuple, Vec<Accumulator>)> = Vec::new();
+
+        while let Some(tuple) = self.source.try_next()? {
+            let keys = self.keys(&tuple)?;
+
+            let index = *buckets
+                .entry(tuple::serialize(&keys_schema, &keys)?)
+                .or_insert_with(|| {
+                    groups.push((keys, self.accumulators()));
+                    groups.len() - 1
+                });
+
+            self.accumulate(&tuple, &mut groups[index].1)?;
+        }
+
+        if groups.is_empty() && self.group_by.is_empty() {
+            groups.push((vec![], self.accumulators()));
+        }
+
+        self.output = groups
+            .into_iter()
+            .map(Self::finish)
+            .collect::<Result<_, _>>()?;
+
+        Ok(())
+    }
+
+    /// Returns the next group of a sorted source, which is complete once a
+    /// tuple with different keys shows up.
+    fn next_sorted_group(&mut self) -> Result<Option<Tuple>, DbError> {
+        if self.done {
+            return Ok(None);
+        }
+
+        while let Some(tuple) = self.source.try_next()? {
+            let keys = self.keys(&tuple)?;
+
+            let (finished, (keys, mut accumulators)) = match self.current.take() {
+                Some(current) if current.0 == keys => (None, current),
+                previous => (previous, (keys, self.accumulators())),
+            };
+
+            self.accumulate(&tuple, &mut accumulators)?;
+            self.current = Some((keys, accumulators));
+
+            if let Some(group) = finished {
+                return Ok(Some(Self::finish(group)?));
+            }
+        }
+
+        self.done = true;
+
+        if self.current.is_none() && self.group_by.is_empty() {
+            self.current = Some((vec![], self.accumulators()));
+        }
+
+        Ok(self.current.take().map(Self::finish).transpose()?)
+    }
+
+    fn try_next(&mut self) -> Result<Option<Tuple>, DbError> {
+        if self.sorted {
+            return self.next_sorted_group();
+        }
+
+        if !self.done {
+            self.group()?;
+            self.done = true;
+        }
+
+        Ok(self.output.pop_front())
+    }
+}
+
+impl<F> Display for GroupBy<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.group_by.is_empty() {
+            return write!(f, "Aggregate ({})", join(&self.aggregates, ", "));
+        }
+
+        if self.sorted {
+            write!(f, "Streaming")?;
+        }
+
+        write!(f, "GroupBy ({})", join(&self.group_by, ", "))?;
+
+        if !self.aggregates.is_empty() {
+            write!(f, " Aggregate ({})", join(&self.aggregates, ", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Running state of one aggregate function.
+///
+/// `COUNT` of an empty input is 0 but `SUM`, `AVG`, `MIN` and `MAX` should
+/// return `NULL`, which doesn't exist yet, so they fail with
+/// [`VmError::EmptyAggregate`] instead. Only queries without grouping keys can
+/// have empty groups.
+#[derive(Debug, PartialEq)]
+enum Accumulator {
+    Count(i128),
+    Sum(Value, bool),
+    Avg {
+        sum: f64,
+        count: usize,
+    },
+    Min(Value, bool),
+    Max(Value, bool),
+    /// Wraps another accumulator and only feeds it values that haven't been
+    /// seen before in the group. See [`distinct_key`].
+    Distinct {
+        seen: HashSet<Vec<u8>>,
+        inner: Box<Accumulator>,
+    },
+}
+
+impl Accumulator {
+    /// Creates the accumulator of `func`, which evaluates to `data_type`.
+    fn new(func: AggregateFunction, data_type: VmDataType) -> Self {
+        let zero = match data_type {
+            VmDataType::Number => Value::Number(0),
+            VmDataType::Float => Value::Float(0.0),
+            VmDataType::String => Value::String(String::new()),
+            VmDataType::Bool => Value::Bool(false),
+        };
+
+        match func {
+            AggregateFunction::Count => Self::Count(0),
+            AggregateFunction::Sum => Self::Sum(zero, false),
+            AggregateFunction::Avg => Self::Avg { sum: 0.0, count: 0 },
+            AggregateFunction::Min => Self::Min(zero, false),
+            AggregateFunction::Max => Self::Max(zero, false),
+        }
+    }
+
+    /// Feeds the next input value into the accumulator.
+    ///
+    /// The value is computed lazily because `COUNT` doesn't need it and its
+    /// argument could be a wildcard.
+    fn update(&mut self, arg: impl FnOnce() -> Result<Value, SqlError>) -> Result<(), SqlError> {
+        match self {
+            Self::Count(count) => *count += 1,
+
+            Self::Sum(sum, found) => {
+                *sum = match (&*sum, arg()?) {
+                    (Value::Number(a), Value::Number(b)) => Value::Number(a + b),
+                    (Value::Float(a), Value::Float(b)) => Value::Float(a + b),
+                    (Value::Float(a), Value::Number(b)) => Value::Float(a + b as f64),
+                    (_, value) => unreachable!("SUM() of non numeric value {value}"),
+                };
+                *found = true;
+            }
+
+            Self::Avg { sum, count } => {
+                *sum += match arg()? {
+                    Value::Number(num) => num as f64,
+                    Value::Float(float) => float,
+                    value => unreachable!("AVG() of non numeric value {value}"),
+                };
+                *count += 1;
+            }
+
+            Self::Min(min, found) => {
+                let value = arg()?;
+                if !*found || value < *min {
+                    *min = value;
+                    *found = true;
+                }
+            }
+
+            Self::Max(max, found) => {
+                let value = arg()?;
+                if !*found || value > *max {
+                    *max = value;
+                    *found = true;
+                }
+            }
+
+            Self::Distinct { seen, inner } => {
+                let value = arg()?;
+                if seen.insert(distinct_key(&value)) {
+                    // Boxed so that the recursive call doesn't instantiate a
+                    // new closure type every time.
+                    let value: Box<dyn FnOnce() -> Result<Value, SqlError>> =
+                        Box::new(|| Ok(value));
+                    inner.update(value)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Consumes the accumulator and returns the final result.
+    fn finish(self) -> Result<Value, VmError> {
+        Ok(match self {
+            Self::Count(count) => Value::Number(count),
+            Self::Sum(value, true) | Self::Min(value, true) | Self::Max(value, true) => value,
+            Self::Avg { sum, count } if count > 0 => Value::Float(sum / count as f64),
+            Self::Distinct { inner, .. } => inner.finish()?,
+            Self::Sum(..) => return Err(VmError::EmptyAggregate(AggregateFunction::Sum)),
+            Self::Avg { .. } => return Err(VmError::EmptyAggregate(AggregateFunction::Avg)),
+            Self::Min(..) => return Err(VmError::EmptyAggregate(AggregateFunction::Min)),
+            Self::Max(..) => return Err(VmError::EmptyAggregate(AggregateFunction::Max)),
+        })
+    }
+}
+
+/// Serializes `value` for [`Accumulator::Distinct`].
+///
+/// All the values fed to the same accumulator have the same type, so there's
+/// no need to encode the type or use the [`tuple`] format, which would need
+/// the exact column type that we don't have here.
+pub(crate) fn distinct_key(value: &Value) -> Vec<u8> {
+    match value {
+        Value::Number(num) => num.to_le_bytes().to_vec(),
+        // 0.0 and -0.0 are equal but their bits are not.
+        Value::Float(float) if *float == 0.0 => 0f64.to_bits().to_le_bytes().to_vec(),
+        Value::Float(float) => float.to_bits().to_le_bytes().to_vec(),
+        Value::String(string) => string.as_bytes().to_vec(),
+        Value::Bool(bool) => vec![*bool as u8],
+    }
+}
+
+/// Implements `LIMIT` and `OFFSET` clauses.
+///
+/// Skips the first [`Self::offset`] tuples of its source and then returns at
+/// most [`Self::limit`] tuples. Once the limit is reached the source is not
+/// polled anymore, so scans stop reading from disk.
+///
+/// Both counters are decremented as tuples go through this plan, there's no
+/// need to keep track of the original values.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Limit<F> {
+    pub source: Box<Plan<F>>,
+    /// Tuples left to return. [`None`] if there's only an `OFFSET` clause.
+    pub limit: Option<u64>,
+    /// Tuples left to skip.
+    pub offset: u64,
+}
+
+impl<F: Seek + Read + Write + FileOps> Limit<F> {
+    fn try_next(&mut self) -> Result<Option<Tuple>, DbError> {
+        if self.limit == Some(0) {
+            return Ok(None);
+        }
+
+        while self.offset > 0 {
+            if self.source.try_next()?.is_none() {
+                self.offset = 0;
+                return Ok(None);
             }
+
+            self.offset -= 1;
         }
 
-        Ok(None)
+        let tuple = self.source.try_next()?;
+
+        if let (Some(limit), Some(_)) = (self.limit.as_mut(), &tuple) {
+            *limit -= 1;
+        }
+
+        Ok(tuple)
     }
 }
 
-impl<F> Display for Filter<F> {
+impl<F> Display for Limit<F> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Filter ({})", self.filter)
+        f.write_str("Limit")?;
+
+        if let Some(limit) = self.limit {
+            write!(f, " {limit}")?;
+        }
+
+        if self.offset > 0 {
+            write!(f, " Offset {}", self.offset)?;
+        }
+
+        Ok(())
     }
 }
 
-/// Applies a projection to a tuple.
-///
-/// A "projection" is a relation algebra unary operation which, in simple words,
-/// "selects" columns from a row. For example:
+/// Removes duplicated tuples from a sorted source.
 ///
-/// ```sql
-/// CREATE TABLE users (id INT PRIMARY KEY, name VARCHAR(255), age INT);
-///
-/// SELECT id, age FROM users;
-/// ```
-///
-/// The projection in this case would be the "id" and "age columns". The name
-/// is discarded.
+/// The planner places a [`Sort`] that uses all the columns of the tuple as
+/// sort keys below this node, so equal tuples always come one after the other
+/// and we only need to remember the last tuple we returned. That way the
+/// memory usage doesn't depend on the number of tuples and large inputs are
+/// handled by the external sort algorithm.
 #[derive(Debug, PartialEq)]
-pub(crate) struct Project<F> {
+pub(crate) struct Distinct<F> {
     pub source: Box<Plan<F>>,
-    pub input_schema: Schema,
-    pub output_schema: Schema,
-    pub projection: Vec<Expression>,
+    /// Last tuple returned by this plan.
+    pub previous: Option<Tuple>,
 }
 
-impl<F: Seek + Read + Write + FileOps> Project<F> {
+impl<F: Seek + Read + Write + FileOps> Distinct<F> {
     fn try_next(&mut self) -> Result<Option<Tuple>, DbError> {
-        let Some(tuple) = self.source.try_next()? else {
-            return Ok(None);
-        };
+        while let Some(tuple) = self.source.try_next()? {
+            if self.previous.as_ref() != Some(&tuple) {
+                self.previous = Some(tuple.clone());
+                return Ok(Some(tuple));
+            }
+        }
 
-        Ok(Some(
-            self.projection
-                .iter()
-                .map(|expr| vm::resolve_expression(&tuple, &self.input_schema, expr))
-                .collect::<Result<Tuple, _>>()?,
-        ))
+        Ok(None)
     }
 }
 
-impl<F> Display for Project<F> {
+impl<F> Display for Distinct<F> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Project ({})", join(&self.projection, ", "))
+        f.write_str("Distinct")
     }
 }
 
@@ -811,6 +1798,12 @@ pub(crate) struct Insert<F> {
     pub source: Box<Plan<F>>,
     pub table: TableMetadata,
     pub comparator: FixedSizeMemCmp,
+    /// Prepend a new row ID to every tuple returned by the source.
+    ///
+    /// [`Plan::Values`] sources already contain the row IDs, see
+    /// [`crate::sql::prepare`]. Other sources like `SELECT` statements can't
+    /// know them in advance.
+    pub generate_row_ids: bool,
 }
 
 impl<F: Seek + Read + Write + FileOps> Insert<F> {
@@ -819,35 +1812,41 @@ impl<F: Seek + Read + Write + FileOps> Insert<F> {
             return Ok(None);
         };
 
+        if self.generate_row_ids {
+            tuple.insert(0, Value::Number(self.table.next_row_id().into()));
+        }
+
+        for (column, value) in self.table.schema.columns.iter().zip(&tuple) {
+            analyzer::check_length(value, &column.data_type)?;
+        }
+
         let mut pager = self.pager.borrow_mut();
+        let version = pager.format_version;
 
         // TODO: We know that all tables use integers as BTree keys whereas
         // indexes can use either strings or integers. Having two types of
         // BTrees introduces code bloat but at the same time using dynamic
         // dispatch for a type that we alrady know doesn't make sense.
         BTree::new(&mut pager, self.table.root, self.comparator)
-            .try_insert(tuple::serialize(&self.table.schema, &tuple))?
-            .map_err(|_| SqlError::DuplicatedKey(tuple.swap_remove(0)))?;
+            .try_insert(tuple::serialize_with(version, &self.table.schema, &tuple)?)?
+            .map_err(|_| SqlError::DuplicatedKey {
+                table: self.table.name.clone(),
+                column: self.table.schema.columns[0].name.clone(),
+                value: tuple.swap_remove(0),
+            })?;
 
         for index in &self.table.indexes {
-            let col = self
-                .table
-                .schema
-                .index_of(&index.column.name)
-                .ok_or(DbError::Corrupted(format!(
-                    "index column '{}' not found on table {} schema: {:?}",
-                    index.column.name, self.table.name, self.table.schema,
-                )))?;
+            let key = index.key_values(&self.table, &tuple)?;
 
             // This one's dynamic, we can either use Box<dyn BytesCmp> or the
             // BTreeKeyComparator enum which dispatches using jump tables
             // instead of VTables. The enum also doesn't need an additional Box
             // allocation.
-            let comparator = BTreeKeyComparator::from(&index.column.data_type);
+            let comparator = index.comparator(version);
 
             BTree::new(&mut pager, index.root, comparator)
-                .try_insert(tuple::serialize(&index.schema, [&tuple[col], &tuple[0]]))?
-                .map_err(|_| SqlError::DuplicatedKey(tuple.swap_remove(col)))?;
+                .try_insert(index.serialize_entry(version, &key, &tuple[0])?)?
+                .map_err(|_| index.duplicated_key(&self.table.name, key))?;
         }
 
         Ok(Some(vec![]))
@@ -879,7 +1878,16 @@ impl<F: Seek + Read + Write + FileOps> Update<F> {
         // Col Name -> (old value, new value index)
         let mut updated_cols = HashMap::new();
 
-        for assignment in &self.assignments {
+        // Compute all the updated column values before modifying the tuple.
+        // Every assignment sees the row as it was before the update, so
+        // `SET a = b, b = a` swaps the values instead of copying b into a.
+        let new_values = self
+            .assignments
+            .iter()
+            .map(|assignment| vm::resolve_expression(&tuple, &self.table.schema, &assignment.value))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (assignment, new_value) in self.assignments.iter().zip(new_values) {
             let col =
                 self.table
                     .schema
@@ -889,8 +1897,7 @@ impl<F: Seek + Read + Write + FileOps> Update<F> {
                         assignment.identifier, self.table
                     )))?;
 
-            // Compute updated column value.
-            let new_value = vm::resolve_expression(&tuple, &self.table.schema, &assignment.value)?;
+            analyzer::check_length(&new_value, &self.table.schema.columns[col].data_type)?;
 
             // If the value did not change we'll skip this column.
             if new_value != tuple[col] {
@@ -900,59 +1907,80 @@ impl<F: Seek + Read + Write + FileOps> Update<F> {
         }
 
         let mut pager = self.pager.borrow_mut();
+        let version = pager.format_version;
         let mut btree = BTree::new(&mut pager, self.table.root, self.comparator);
 
         // Updated tuple.
-        let updated_entry = tuple::serialize(&self.table.schema, &tuple);
+        let updated_entry = tuple::serialize_with(version, &self.table.schema, &tuple)?;
 
         // If the primary key changes we have to remove the old entry from the
-        // BTree. Otherwise we do a normal update where we override the existing
-        // entry.
+        // BTree. Otherwise we override the existing entry, which can be done
+        // in place if the size of the tuple didn't change.
         if let Some((old_pk, new_pk)) = updated_cols.get(&self.table.schema.columns[0].name) {
             btree
                 .try_insert(updated_entry)?
-                .map_err(|_| SqlError::DuplicatedKey(tuple.swap_remove(0)))?;
-            btree.remove(&tuple::serialize_key(
+                .map_err(|_| SqlError::DuplicatedKey {
+                    table: self.table.name.clone(),
+                    column: self.table.schema.columns[0].name.clone(),
+                    value: tuple.swap_remove(0),
+                })?;
+            let old_entry = btree.remove(&tuple::serialize_key(
                 &self.table.schema.columns[0].data_type,
                 old_pk,
-            ))?;
-        } else {
+            )?)?;
+
+            if let Some(cell) = old_entry {
+                free_cell(&mut pager, cell)?;
+            }
+        } else if !btree.update_in_place(&updated_entry)? {
+            // The tuple size changed (VARCHAR columns), so we can't overwrite
+            // the entry in place and the BTree might need rebalancing.
             btree.insert(updated_entry)?;
         }
 
         for index in &self.table.indexes {
-            let mut btree = BTree::new(
-                &mut pager,
-                index.root,
-                BTreeKeyComparator::from(&index.column.data_type),
-            );
+            let mut btree = BTree::new(&mut pager, index.root, index.comparator(version));
+
+            let key = index.key_values(&self.table, &tuple)?;
 
             // Three cases to consider:
             //
-            // 1. The value of the indexed column has changed. Remove the
-            // previous one and insert the new one. If the primary key we're
+            // 1. The value of any indexed column has changed. Remove the
+            // previous key and insert the new one. If the primary key we're
             // pointing to has changed then this case covers that as well.
             //
-            // 2. Only the primary key has changed while the indexed column
-            // value remains the same. In that case do a normal update
-            // overriding the previous index entry.
+            // 2. Only the primary key has changed while the indexed columns
+            // remain the same. In that case do a normal update overriding the
+            // previous index entry.
             //
             // 3. Nothing has change, move to the next iteration.
-            if let Some((old_key, new_key)) = updated_cols.get(&index.column.name) {
+            if index
+                .columns
+                .iter()
+                .any(|column| updated_cols.contains_key(&column.name))
+            {
+                let old_key: Vec<Value> = index
+                    .columns
+                    .iter()
+                    .zip(&key)
+                    .map(|(column, new_value)| match updated_cols.get(&column.name) {
+                        Some((old_value, _)) => old_value.clone(),
+                        None => new_value.clone(),
+                    })
+                    .collect();
+
+                let entry = index.serialize_entry(version, &key, &tuple[0])?;
                 btree
-                    .try_insert(tuple::serialize(&index.schema, [
-                        &tuple[*new_key],
-                        &tuple[0],
-                    ]))?
-                    .map_err(|_| SqlError::DuplicatedKey(tuple.swap_remove(*new_key)))?;
+                    .try_insert(entry)?
+                    .map_err(|_| index.duplicated_key(&self.table.name, key))?;
+
+                let old_entry = btree.remove(&index.serialize_key(version, &old_key)?)?;
 
-                btree.remove(&tuple::serialize_key(&index.column.data_type, old_key))?;
+                if let Some(cell) = old_entry {
+                    free_cell(&mut pager, cell)?;
+                }
             } else if updated_cols.contains_key(&self.table.schema.columns[0].name) {
-                let index_col = self.table.schema.index_of(&index.column.name).unwrap();
-                btree.insert(tuple::serialize(&index.schema, [
-                    &tuple[index_col],
-                    &tuple[0],
-                ]))?;
+                btree.insert(index.serialize_entry(version, &key, &tuple[0])?)?;
             }
         }
 
@@ -987,24 +2015,30 @@ impl<F: Seek + Read + Write + FileOps> Delete<F> {
         };
 
         let mut pager = self.pager.borrow_mut();
+        let version = pager.format_version;
         let mut btree = BTree::new(&mut pager, self.table.root, self.comparator);
 
-        btree.remove(&tuple::serialize_key(
+        let entry = btree.remove(&tuple::serialize_key(
             &self.table.schema.columns[0].data_type,
             &tuple[0],
-        ))?;
+        )?)?;
+
+        // The BTree takes care of freeing pages that become empty while
+        // rebalancing, but the overflow pages of the removed cells are our
+        // responsibility. Freeing pages doesn't corrupt the source scan
+        // because the planner makes sure its tuples are buffered first.
+        if let Some(cell) = entry {
+            free_cell(&mut pager, cell)?;
+        }
 
         for index in &self.table.indexes {
-            let col = self.table.schema.index_of(&index.column.name).unwrap();
-            let key = tuple::serialize_key(&index.column.data_type, &tuple[col]);
+            let key = index.serialize_key(version, &index.key_values(&self.table, &tuple)?)?;
 
-            let mut btree = BTree::new(
-                &mut pager,
-                index.root,
-                BTreeKeyComparator::from(&index.column.data_type),
-            );
+            let mut btree = BTree::new(&mut pager, index.root, index.comparator(version));
 
-            btree.remove(&key)?;
+            if let Some(cell) = btree.remove(&key)? {
+                free_cell(&mut pager, cell)?;
+            }
         }
 
         Ok(Some(vec![]))
@@ -1059,7 +2093,8 @@ const TUPLE_PAGE_HEADER_SIZE: usize = mem::size_of::<u32>();
 ///
 /// Each page contains a header that encodes the number of tuples in the page
 /// using a 32 bit little endian integer. The header is followed by N tuples
-/// stored in the same format that the database uses, see [`tuple`] for details.
+/// stored in the aligned layout ([`tuple::serialize_aligned`]), which places
+/// numbers before strings so that they can be decoded straight from the page.
 /// Storing the size of each individual tuple is not necessary because we
 /// already have the schema of the table in memory which can be used to parse
 /// variable size tuples. Finally, the page contains padding if necessary to
@@ -1095,7 +2130,9 @@ const TUPLE_PAGE_HEADER_SIZE: usize = mem::size_of::<u32>();
 ///
 /// This format is used by the [`Collect`] plan to simply store the
 /// results of a large query like `SELECT * FROM table` in a file and then
-/// "stream" the rows one by one. Again, we don't need any information about the
+/// "stream" the rows one by one. Integers are encoded as varints
+/// ([`IntEncoding::Varint`]), the file is never searched or compared byte by
+/// byte so we might as well save some space. Again, we don't need any information about the
 /// size of anything because the table [`Schema`] already tells us exactly how
 /// to parse tuples back into [`Tuple`] structures.
 ///
@@ -1117,7 +2154,9 @@ pub(crate) struct TupleBuffer {
     /// buffer.
     ///
     /// This number is not updated if tuples are removed from the buffer, it
-    /// simply stores the maximum size that has been recorded.
+    /// simply stores the maximum size that has been recorded. It's always
+    /// measured in the fixed size format, even if the buffer is packed,
+    /// because it's used to compute the page size of [`Sort`] files.
     largest_tuple_size: usize,
 
     /// Packed or fixed size mode.
@@ -1169,7 +2208,7 @@ impl TupleBuffer {
     /// Returns `true` if the given `tuple` can be appended to this buffer
     /// without incrementing its size past [`Self::page_size`].
     pub fn can_fit(&self, tuple: &Tuple) -> bool {
-        self.current_size + tuple::size_of(tuple, &self.schema) <= self.page_size
+        self.current_size + self.size_of(tuple) <= self.page_size
     }
 
     /// Appends the given `tuple` to the buffer.
@@ -1181,23 +2220,33 @@ impl TupleBuffer {
     /// its own tricks to avoid working with tuples that wouldn't fit in the
     /// buffer.
     pub fn push(&mut self, tuple: Tuple) {
-        let tuple_size = tuple::size_of(&tuple, &self.schema);
-
-        if tuple_size > self.largest_tuple_size {
-            self.largest_tuple_size = tuple_size;
-        }
+        self.largest_tuple_size = self
+            .largest_tuple_size
+            .max(tuple::size_of(&tuple, &self.schema));
 
-        self.current_size += tuple_size;
+        self.current_size += self.size_of(&tuple);
         self.tuples.push_back(tuple);
     }
 
     /// Removes the first tuple in this buffer and returns it.
     pub fn pop_front(&mut self) -> Option<Tuple> {
         self.tuples.pop_front().inspect(|tuple| {
-            self.current_size -= tuple::size_of(tuple, &self.schema);
+            self.current_size -= self.size_of(tuple);
         })
     }
 
+    /// Size of the given `tuple` once written to the file.
+    ///
+    /// Packed buffers encode integers as varints, fixed size buffers use the
+    /// aligned layout which takes as much space as the database format.
+    fn size_of(&self, tuple: &Tuple) -> usize {
+        if self.packed {
+            tuple::size_of_encoded(IntEncoding::Varint, tuple, &self.schema)
+        } else {
+            tuple::size_of(tuple, &self.schema)
+        }
+    }
+
     /// `true` if there are no tuples stored in this buffer.
     pub fn is_empty(&self) -> bool {
         self.tuples.is_empty()
@@ -1214,7 +2263,7 @@ impl TupleBuffer {
     }
 
     /// Serializes this buffer into a byte array that can be written to a file.
-    pub fn serialize(&self) -> Vec<u8> {
+    pub fn serialize(&self) -> Result<Vec<u8>, SqlError> {
         let mut buf = Vec::with_capacity(self.page_size);
 
         // Page header.
@@ -1224,7 +2273,11 @@ impl TupleBuffer {
 
         // Tuples.
         for tuple in &self.tuples {
-            buf.extend_from_slice(&tuple::serialize(&self.schema, tuple));
+            buf.extend_from_slice(&if self.packed {
+                tuple::serialize_encoded(IntEncoding::Varint, &self.schema, tuple)?
+            } else {
+                tuple::serialize_aligned(&self.schema, tuple)?
+            });
         }
 
         // Padding.
@@ -1232,15 +2285,17 @@ impl TupleBuffer {
             buf.resize(self.page_size, 0);
         }
 
-        buf
+        Ok(buf)
     }
 
     /// Writes the contents of this buffer to the given `file`.
     ///
     /// The buffer is not modified in any way, call [`Self::clear`] to delete
     /// the tuples from it.
-    pub fn write_to(&self, file: &mut impl Write) -> io::Result<()> {
-        file.write_all(&self.serialize())
+    pub fn write_to(&self, file: &mut impl Write) -> Result<(), DbError> {
+        file.write_all(&self.serialize()?)?;
+
+        Ok(())
     }
 
     /// Reads one page from the given file into memory.
@@ -1268,8 +2323,8 @@ impl TupleBuffer {
         let mut cursor = TUPLE_PAGE_HEADER_SIZE;
 
         for _ in 0..number_of_tuples {
-            let tuple = tuple::deserialize(&buf[cursor..], &self.schema);
-            cursor += tuple::size_of(&tuple, &self.schema);
+            let tuple = tuple::deserialize_aligned(&buf[cursor..], &self.schema)?;
+            cursor += self.size_of(&tuple);
             self.push(tuple);
         }
 
@@ -1370,6 +2425,8 @@ pub(crate) struct Collect<F> {
     file_path: PathBuf,
     /// Working directory.
     work_dir: PathBuf,
+    /// Number of times that [`Self::mem_buf`] had to be written to the file.
+    spills: usize,
 }
 
 impl<F> Display for Collect<F> {
@@ -1433,6 +2490,7 @@ impl<F> From<CollectConfig<F>> for Collect<F> {
             work_dir,
             file: None,
             reader: None,
+            spills: 0,
         }
     }
 }
@@ -1452,6 +2510,7 @@ impl<F: Seek + Read + Write + FileOps> Collect<F> {
                 }
                 self.mem_buf.write_to(self.file.as_mut().unwrap())?;
                 self.mem_buf.clear();
+                self.spills += 1;
             }
 
             self.mem_buf.push(tuple);
@@ -1477,7 +2536,11 @@ impl<F: Seek + Read + Write + FileOps> Collect<F> {
         // While there's stuff written to the file return from there.
         if let Some(reader) = self.reader.as_mut() {
             if reader.has_data_left()? {
-                return Ok(Some(tuple::read_from(reader, &self.schema)?));
+                return Ok(Some(tuple::read_from_encoded(
+                    IntEncoding::Varint,
+                    reader,
+                    &self.schema,
+                )?));
             }
 
             // Reader is done, drop the file.
@@ -2016,6 +3079,8 @@ pub(crate) struct TuplesComparator {
     pub sort_schema: Schema,
     /// Index of each sort key in [`Self::sort_schema`].
     pub sort_keys_indexes: Vec<usize>,
+    /// Direction of each sort key, same length as [`Self::sort_keys_indexes`].
+    pub directions: Vec<Direction>,
 }
 
 impl TuplesComparator {
@@ -2027,11 +3092,21 @@ impl TuplesComparator {
             "tuple length doesn't match sort schema length"
         );
 
-        for index in self.sort_keys_indexes.iter().copied() {
+        debug_assert!(
+            self.sort_keys_indexes.len() == self.directions.len(),
+            "every sort key must have a direction"
+        );
+
+        let sort_keys = self.sort_keys_indexes.iter().copied();
+
+        for (index, direction) in sort_keys.zip(self.directions.iter()) {
             match t1[index].partial_cmp(&t2[index]) {
                 Some(ordering) => {
                     if ordering != Ordering::Equal {
-                        return ordering;
+                        return match direction {
+                            Direction::Asc => ordering,
+                            Direction::Desc => ordering.reverse(),
+                        };
                     }
                 }
                 None => {
@@ -2048,9 +3123,35 @@ impl TuplesComparator {
 
         Ordering::Equal
     }
+
+    /// Sort key column names for [`Display`] impls, like `age DESC, name`.
+    fn display_sort_keys(&self) -> String {
+        let sort_keys = self
+            .sort_keys_indexes
+            .iter()
+            .zip(&self.directions)
+            .map(|(index, direction)| {
+                let name = &self.sort_schema.columns[*index].name;
+                match direction {
+                    Direction::Asc => name.clone(),
+                    Direction::Desc => format!("{name} {direction}"),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        join(&sort_keys, ", ")
+    }
 }
 
 impl<F> Sort<F> {
+    /// Consumes the sorter and returns its input plan.
+    ///
+    /// Nothing has been sorted yet at plan generation time, so there are no
+    /// files to remove.
+    pub fn into_source(self) -> Plan<F> {
+        *self.collection.source
+    }
+
     /// Returns the index of the buffer that contains the minimum tuple.
     fn find_min_tuple_index(&self, input_buffers: &[TupleBuffer]) -> usize {
         let mut min = input_buffers
@@ -2102,7 +3203,7 @@ impl<F: FileOps> Sort<F> {
 
 impl<F: Seek + Read + Write + FileOps> Sort<F> {
     /// Writes the output buffer to the output file.
-    fn write_output_buffer(&mut self) -> io::Result<()> {
+    fn write_output_buffer(&mut self) -> Result<(), DbError> {
         self.output_buffer
             .write_to(self.output_file.as_mut().unwrap())?;
         self.output_buffer.clear();
@@ -2128,7 +3229,10 @@ impl<F: Seek + Read + Write + FileOps> Sort<F> {
     ///
     /// This is necessary for the first pass of the algorithm. Returns the
     /// numbers of pages produces in this "run".
-    fn precompute_sorted_run(&mut self, input_buffers: &mut [TupleBuffer]) -> io::Result<usize> {
+    fn precompute_sorted_run(
+        &mut self,
+        input_buffers: &mut [TupleBuffer],
+    ) -> Result<usize, DbError> {
         let mut run = 0;
 
         // Sort all buffers individually.
@@ -2349,13 +3453,170 @@ impl<F: Seek + Read + Write + FileOps> Sort<F> {
 
 impl<F> Display for Sort<F> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let sort_col_names = self
-            .comparator
-            .sort_keys_indexes
-            .iter()
-            .map(|i| &self.comparator.sort_schema.columns[*i].name);
+        write!(f, "Sort ({})", self.comparator.display_sort_keys())
+    }
+}
+
+/// Largest `LIMIT` (plus `OFFSET`) for which the planner uses [`TopN`] instead
+/// of [`Sort`]. [`TopN`] keeps all its tuples in memory, so this is what bounds
+/// its memory usage.
+pub const TOP_N_MAX_LIMIT: u64 = 1000;
+
+/// Used to build [`TopN`] objects.
+pub(crate) struct TopNConfig<F> {
+    pub source: Box<Plan<F>>,
+    pub comparator: TuplesComparator,
+    pub limit: usize,
+}
+
+/// Returns the first [`TopN::limit`] tuples of its source in sorted order
+/// without sorting the entire source.
+///
+/// This is what `ORDER BY` combined with a small `LIMIT` needs. Instead of
+/// collecting everything and running the external merge sort of [`Sort`], we
+/// keep a max-heap of at most `limit` tuples. Each new tuple is compared
+/// against the root, which is the "worst" tuple we have so far, and replaces
+/// it only if it's smaller. Memory is bounded to `limit` tuples and we never
+/// touch the disk, so this must only be used when `limit` is small.
+///
+/// Just like [`Sort`], the source must be a [`SortKeysGen`] plan (or anything
+/// that returns tuples with sort keys appended at the end) and the keys are
+/// removed before returning the tuples.
+///
+/// Tuples that compare equal are returned in the order they were received,
+/// same as the stable sort used by [`Sort`] does, so switching from one plan
+/// to the other doesn't change the results.
+#[derive(Debug, PartialEq)]
+pub(crate) struct TopN<F> {
+    /// Tuple source.
+    source: Box<Plan<F>>,
+    /// Tuples comparator used to obtain [`Ordering`] instances.
+    comparator: TuplesComparator,
+    /// Max number of tuples returned.
+    limit: usize,
+    /// Binary max-heap. Each tuple is stored along with the position in which
+    /// it was received to break ties.
+    heap: Vec<(usize, Tuple)>,
+    /// Sorted tuples ready to be returned.
+    output: VecDeque<Tuple>,
+    /// `true` once the source has been consumed.
+    done: bool,
+}
+
+impl<F> From<TopNConfig<F>> for TopN<F> {
+    fn from(
+        TopNConfig {
+            source,
+            comparator,
+            limit,
+        }: TopNConfig<F>,
+    ) -> Self {
+        Self {
+            source,
+            comparator,
+            limit,
+            heap: Vec::with_capacity(limit),
+            output: VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+impl<F> TopN<F> {
+    /// Compares two heap entries using the sort keys first and then the
+    /// position as a tie breaker.
+    fn cmp(&self, (pos1, t1): &(usize, Tuple), (pos2, t2): &(usize, Tuple)) -> Ordering {
+        self.comparator.cmp(t1, t2).then(pos1.cmp(pos2))
+    }
+
+    /// Moves the entry at `index` up until its parent is greater.
+    fn sift_up(&mut self, mut index: usize) {
+        while index > 0 {
+            let parent = (index - 1) / 2;
+            if self.cmp(&self.heap[index], &self.heap[parent]) != Ordering::Greater {
+                break;
+            }
+            self.heap.swap(index, parent);
+            index = parent;
+        }
+    }
+
+    /// Moves the root down until both children are smaller.
+    fn sift_down(&mut self) {
+        let mut index = 0;
+
+        loop {
+            let mut max = index;
+
+            for child in [2 * index + 1, 2 * index + 2] {
+                if child < self.heap.len()
+                    && self.cmp(&self.heap[child], &self.heap[max]) == Ordering::Greater
+                {
+                    max = child;
+                }
+            }
+
+            if max == index {
+                break;
+            }
+
+            self.heap.swap(index, max);
+            index = max;
+        }
+    }
+}
+
+impl<F: Seek + Read + Write + FileOps> TopN<F> {
+    /// Consumes the source keeping only the smallest tuples.
+    fn select(&mut self) -> Result<(), DbError> {
+        let mut position = 0;
+
+        while let Some(tuple) = self.source.try_next()? {
+            let entry = (position, tuple);
+            position += 1;
+
+            if self.heap.len() < self.limit {
+                self.heap.push(entry);
+                self.sift_up(self.heap.len() - 1);
+            } else if self.cmp(&entry, &self.heap[0]) == Ordering::Less {
+                self.heap[0] = entry;
+                self.sift_down();
+            }
+        }
+
+        let mut heap = mem::take(&mut self.heap);
+        heap.sort_by(|e1, e2| self.cmp(e1, e2));
+        self.output = heap.into_iter().map(|(_, tuple)| tuple).collect();
+
+        Ok(())
+    }
+
+    fn try_next(&mut self) -> Result<Option<Tuple>, DbError> {
+        if self.limit == 0 {
+            return Ok(None);
+        }
+
+        if !self.done {
+            self.select()?;
+            self.done = true;
+        }
+
+        // Remove sort keys when returning to the next plan node.
+        Ok(self.output.pop_front().map(|mut tuple| {
+            tuple.drain(self.comparator.schema.len()..);
+            tuple
+        }))
+    }
+}
 
-        write!(f, "Sort ({})", join(sort_col_names, ", "))
+impl<F> Display for TopN<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TopN {} ({})",
+            self.limit,
+            self.comparator.display_sort_keys()
+        )
     }
 }
 
@@ -2573,6 +3834,328 @@ fn tmp_file<F: FileOps>(work_dir: &Path, extension: &str) -> io::Result<(PathBuf
 }
 
 // TODO: All the code in this module is indirectly tested by
-// [`crate::db::tests`] but some specific tests would be nice here. We can use
-// the [`Values`] plan as a base for mocks that return any tuples we want and
-// build a little testing framework with that.
+// [`crate::db::tests`] but more specific tests would be nice here. The tests
+// below use the [`Values`] plan as a mock that returns any tuples we want, we
+// can build a little testing framework on top of that.
+
+/// Counts the tuples returned by the [`Self::source`] plan and the time it
+/// takes to produce them.
+///
+/// The time includes the time spent on all the children of the source plan,
+/// so the root node of the tree has the total execution time. See
+/// [`Plan::instrument`].
+#[derive(Debug, PartialEq)]
+pub(crate) struct Instrumented<F> {
+    pub source: Box<Plan<F>>,
+    pub rows: usize,
+    pub time: Duration,
+}
+
+impl<F: Seek + Read + Write + FileOps> Instrumented<F> {
+    fn try_next(&mut self) -> Result<Option<Tuple>, DbError> {
+        let start = Instant::now();
+        let tuple = self.source.try_next();
+        self.time += start.elapsed();
+
+        if let Ok(Some(_)) = tuple {
+            self.rows += 1;
+        }
+
+        tuple
+    }
+}
+
+impl<F> Display for Instrumented<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "(actual rows={} time={:.3}ms)",
+            self.rows,
+            self.time.as_secs_f64() * 1000.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::{GroupBy, GroupByConfig, Plan, TopN, TopNConfig, Tuple, TuplesComparator, Values};
+    use crate::{
+        db::{DbError, Schema, SqlError},
+        paging::io::MemBuf,
+        sql::{
+            analyzer,
+            parser::Parser,
+            statement::{AggregateFunction, Column, DataType, Direction, Expression, Value},
+        },
+        vm::{VmDataType, VmError},
+    };
+
+    /// Builds a [`Values`] plan that returns the given tuples.
+    fn values(tuples: &[Tuple]) -> Box<Plan<MemBuf>> {
+        Box::new(Plan::Values(Values {
+            values: VecDeque::from_iter(
+                tuples
+                    .iter()
+                    .map(|tuple| tuple.iter().cloned().map(Expression::Value).collect()),
+            ),
+        }))
+    }
+
+    fn top_n(tuples: &[Tuple], limit: usize) -> Result<Vec<Tuple>, DbError> {
+        let schema = Schema::new(vec![
+            Column::new("name", DataType::Varchar(255)),
+            Column::new("score", DataType::Int),
+        ]);
+
+        let plan = Plan::TopN(TopN::from(TopNConfig {
+            source: values(tuples),
+            comparator: TuplesComparator {
+                sort_schema: schema.clone(),
+                schema,
+                sort_keys_indexes: vec![1],
+                directions: vec![Direction::Asc],
+            },
+            limit,
+        }));
+
+        plan.collect()
+    }
+
+    fn tuple(name: &str, score: i128) -> Tuple {
+        vec![Value::String(name.into()), Value::Number(score)]
+    }
+
+    #[test]
+    fn top_n_returns_smallest_tuples_sorted() -> Result<(), DbError> {
+        let tuples = Vec::from_iter([7, 3, 9, 1, 8, 2, 6].map(|i| tuple(&format!("u{i}"), i)));
+
+        assert_eq!(top_n(&tuples, 3)?, vec![
+            tuple("u1", 1),
+            tuple("u2", 2),
+            tuple("u3", 3)
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn top_n_keeps_arrival_order_of_ties() -> Result<(), DbError> {
+        let tuples = vec![
+            tuple("a", 2),
+            tuple("b", 1),
+            tuple("c", 2),
+            tuple("d", 1),
+            tuple("e", 2),
+        ];
+
+        assert_eq!(top_n(&tuples, 3)?, vec![
+            tuple("b", 1),
+            tuple("d", 1),
+            tuple("a", 2)
+        ]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn top_n_with_limit_larger_than_source() -> Result<(), DbError> {
+        let tuples = vec![tuple("a", 3), tuple("b", 1), tuple("c", 2)];
+
+        assert_eq!(top_n(&tuples, 10)?, vec![
+            tuple("b", 1),
+            tuple("c", 2),
+            tuple("a", 3)
+        ]);
+        assert!(top_n(&tuples, 0)?.is_empty());
+
+        Ok(())
+    }
+
+    fn group_by(
+        tuples: &[Tuple],
+        group_by: &[&str],
+        aggregates: &[&str],
+    ) -> Result<Vec<Tuple>, DbError> {
+        group_by_plan(tuples, group_by, aggregates, false)?.collect()
+    }
+
+    fn group_by_plan(
+        tuples: &[Tuple],
+        group_by: &[&str],
+        aggregates: &[&str],
+        sorted: bool,
+    ) -> Result<Plan<MemBuf>, DbError> {
+        let schema = Schema::new(vec![
+            Column::new("name", DataType::Varchar(255)),
+            Column::new("score", DataType::Int),
+        ]);
+
+        let parse = |exprs: &[&str]| {
+            exprs
+                .iter()
+                .map(|expr| Parser::new(expr).parse_expression())
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let group_by = parse(group_by)?;
+        let aggregates = parse(aggregates)?;
+
+        let mut output_schema = Schema::empty();
+        for expr in group_by.iter().chain(&aggregates) {
+            let data_type = match analyzer::analyze_expression(&schema, None, expr)? {
+                VmDataType::String => DataType::Varchar(255),
+                VmDataType::Float => DataType::Double,
+                _ => DataType::BigInt,
+            };
+            output_schema.push(Column::new(&expr.to_string(), data_type));
+        }
+
+        Ok(Plan::GroupBy(GroupBy::from(GroupByConfig {
+            source: values(tuples),
+            input_schema: schema,
+            output_schema,
+            group_by,
+            aggregates,
+            sorted,
+        })))
+    }
+
+    #[test]
+    fn aggregate_functions() -> Result<(), DbError> {
+        let tuples = vec![tuple("b", 4), tuple("a", 1), tuple("c", 7)];
+
+        assert_eq!(
+            group_by(&tuples, &[], &[
+                "COUNT(*)",
+                "SUM(score)",
+                "AVG(score)",
+                "MIN(name)",
+                "MAX(score)"
+            ])?,
+            vec![vec![
+                Value::Number(3),
+                Value::Number(12),
+                Value::Float(4.0),
+                Value::String("a".into()),
+                Value::Number(7),
+            ]]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn aggregate_empty_source() -> Result<(), DbError> {
+        assert_eq!(
+            group_by(&[], &[], &["COUNT(*)", "COUNT(DISTINCT score)"])?,
+            vec![vec![Value::Number(0), Value::Number(0)]]
+        );
+
+        for (aggregate, func) in [
+            ("SUM(score)", AggregateFunction::Sum),
+            ("AVG(score)", AggregateFunction::Avg),
+            ("MIN(score)", AggregateFunction::Min),
+            ("MAX(DISTINCT score)", AggregateFunction::Max),
+        ] {
+            assert_eq!(
+                group_by(&[], &[], &["COUNT(*)", aggregate]),
+                Err(DbError::Sql(SqlError::VmError(VmError::EmptyAggregate(
+                    func
+                )))),
+                "{aggregate}"
+            );
+        }
+
+        // Empty groups only exist without grouping keys.
+        assert_eq!(group_by(&[], &["name"], &["SUM(score)"])?, vec![]
+            as Vec<Tuple>);
+
+        Ok(())
+    }
+
+    #[test]
+    fn group_by_returns_groups_in_arrival_order() -> Result<(), DbError> {
+        let tuples = vec![
+            tuple("b", 4),
+            tuple("a", 1),
+            tuple("b", 2),
+            tuple("c", 7),
+            tuple("a", 5),
+        ];
+
+        assert_eq!(
+            group_by(&tuples, &["name"], &["COUNT(*)", "SUM(score)"])?,
+            vec![
+                vec![
+                    Value::String("b".into()),
+                    Value::Number(2),
+                    Value::Number(6)
+                ],
+                vec![
+                    Value::String("a".into()),
+                    Value::Number(2),
+                    Value::Number(6)
+                ],
+                vec![
+                    Value::String("c".into()),
+                    Value::Number(1),
+                    Value::Number(7)
+                ],
+            ]
+        );
+
+        assert!(group_by(&[], &["name"], &["COUNT(*)"])?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn sorted_group_by_streams_groups() -> Result<(), DbError> {
+        let tuples = vec![
+            tuple("a", 1),
+            tuple("a", 5),
+            tuple("b", 4),
+            tuple("c", 7),
+            tuple("c", 2),
+            tuple("c", 3),
+        ];
+
+        let mut plan = group_by_plan(&tuples, &["name"], &["COUNT(*)", "SUM(score)"], true)?;
+
+        let group = |name: &str, count, sum| {
+            vec![
+                Value::String(name.into()),
+                Value::Number(count),
+                Value::Number(sum),
+            ]
+        };
+
+        // Each group is returned as soon as the first tuple of the next one
+        // is read, the rest of the source is still there.
+        assert_eq!(plan.try_next()?, Some(group("a", 2, 6)));
+        let Plan::GroupBy(GroupBy { source, .. }) = &plan else {
+            unreachable!();
+        };
+        let Plan::Values(Values { values }) = &**source else {
+            unreachable!();
+        };
+        assert_eq!(values.len(), 3);
+
+        assert_eq!(plan.try_next()?, Some(group("b", 1, 4)));
+        assert_eq!(plan.try_next()?, Some(group("c", 3, 12)));
+        assert_eq!(plan.try_next()?, None);
+
+        assert!(group_by_plan(&[], &["name"], &["COUNT(*)"], true)?
+            .collect::<Result<Vec<_>, _>>()?
+            .is_empty());
+
+        assert_eq!(
+            group_by_plan(&[], &[], &["COUNT(*)"], true)?.collect::<Result<Vec<_>, _>>()?,
+            vec![vec![Value::Number(0)]]
+        );
+
+        Ok(())
+    }
+}
@@ -1,26 +1,33 @@
 //! Code that deals with simple SQL statements that don't require [`Plan`]
 //! trees.
 //!
-//! This boils down to `CREATE` statements, which don't need plans because they
-//! don't work with "tuples".
+//! This boils down to `CREATE`, `DROP`, `ALTER`, `ANALYZE` and `VACUUM`
+//! statements, which don't need plans because they don't return "tuples".
 
 use std::{
+    collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hasher},
     io::{self, Read, Seek, Write},
+    mem,
     rc::Rc,
 };
 
-use super::plan::{Collect, CollectConfig, Filter, Plan, SeqScan};
+use super::plan::{distinct_key, Collect, CollectConfig, Filter, Plan, SeqScan};
 use crate::{
     db::{
         has_btree_key, mkdb_meta_schema, Database, DatabaseContext, DbError, IndexMetadata, RowId,
-        Schema, SqlError, MKDB_META, MKDB_META_ROOT,
+        Schema, SqlError, TableMetadata, TableStats, MKDB_META, MKDB_META_ROOT,
     },
     paging::{io::FileOps, pager::PageNumber},
     sql::{
         parser::Parser,
-        statement::{Constraint, Create, Drop, Statement, Value},
+        statement::{Alter, Constraint, Create, Drop, Statement, Value},
+    },
+    storage::{
+        free_cell,
+        page::{OverflowPage, Page},
+        tuple, BTree, Cursor, FixedSizeMemCmp,
     },
-    storage::{free_cell, page::Page, tuple, BTree, BytesCmp, Cursor, FixedSizeMemCmp},
 };
 
 /// Executes a SQL statement that doesn't require a query plan.
@@ -30,17 +37,29 @@ use crate::{
 /// into a BTree and we're not returning anything. We can do that with only
 /// the information provided by the [`Statement`] itself.
 pub(crate) fn exec<F: Seek + Read + Write + FileOps>(
-    statement: Statement,
+    mut statement: Statement,
     db: &mut Database<F>,
 ) -> Result<usize, DbError> {
-    let sql = statement.to_string();
+    // The analyzer lets these through without errors when there's nothing to
+    // do, see [`skip_if_exists`].
+    if skip_if_exists(&statement, db)? {
+        return Ok(0);
+    }
+
+    // The modifier is meaningless once the table exists, no need to store it
+    // in the metadata table.
+    if let Statement::Create(Create::Table { if_not_exists, .. }) = &mut statement {
+        *if_not_exists = false;
+    }
+
+    let sql = statement.to_sql_safe();
 
     // Most statements don't "affect" any user rows, only internal rows. The
     // drop table statement does affect user rows.
     let mut affected_rows = 0;
 
     match statement {
-        Statement::Create(Create::Table { name, columns }) => {
+        Statement::Create(Create::Table { name, columns, .. }) => {
             let root = alloc_root_page(db)?;
 
             insert_into_mkdb_meta(db, vec![
@@ -59,18 +78,20 @@ pub(crate) fn exec<F: Seek + Read + Write + FileOps>(
                 .filter(|col| !col.constraints.is_empty())
                 .flat_map(|col| {
                     let table_name = name.clone();
-                    col.constraints.into_iter().map(move |constraint| {
+                    col.constraints.into_iter().filter_map(move |constraint| {
                         let index_name = match constraint {
                             Constraint::PrimaryKey => format!("{table_name}_pk_index"),
                             Constraint::Unique => format!("{table_name}_{}_uq_index", &col.name),
+                            // Doesn't need an index, checked by the analyzer.
+                            Constraint::NotNull => return None,
                         };
 
-                        Create::Index {
+                        Some(Create::Index {
                             name: index_name,
                             table: table_name.clone(),
-                            column: col.name.clone(),
+                            columns: vec![col.name.as_str().into()],
                             unique: true,
-                        }
+                        })
                     })
                 });
 
@@ -82,7 +103,7 @@ pub(crate) fn exec<F: Seek + Read + Write + FileOps>(
         Statement::Create(Create::Index {
             name,
             table,
-            column,
+            columns,
             unique,
         }) => {
             if !unique {
@@ -103,48 +124,48 @@ pub(crate) fn exec<F: Seek + Read + Write + FileOps>(
             ])?;
 
             // Now build up the index.
-            let metadata = db.table_metadata(&table)?;
+            let metadata = db.table_metadata(&table)?.clone();
 
-            let col = metadata
-                .schema
-                .index_of(&column)
-                .ok_or(SqlError::InvalidColumn(column))?;
-
-            let index = IndexMetadata {
-                column: metadata.schema.columns[col].clone(),
-                schema: Schema::new(vec![
-                    metadata.schema.columns[col].clone(),
-                    metadata.schema.columns[0].clone(),
-                ]),
-                name: name.clone(),
+            let index_columns = columns
+                .into_iter()
+                .map(|column| match metadata.schema.index_of(&column.name) {
+                    Some(col) => Ok((metadata.schema.columns[col].clone(), column.direction)),
+                    None => Err(SqlError::InvalidColumn(column.name)),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let index = IndexMetadata::new(
                 root,
+                name.clone(),
+                index_columns,
+                metadata.schema.columns[0].clone(),
                 unique,
-            };
+            );
 
             let mut scan = Plan::SeqScan(SeqScan {
                 cursor: Cursor::new(metadata.root, 0),
                 table: metadata.clone(),
                 pager: Rc::clone(&db.pager),
+                rows_scanned: 0,
             });
 
-            let comparator = Box::<dyn BytesCmp>::from(&index.column.data_type);
+            let version = db.pager.borrow().format_version;
+            let comparator = index.comparator(version);
 
-            while let Some(mut tuple) = scan.try_next()? {
+            while let Some(tuple) = scan.try_next()? {
                 // TODO: We have to borrow the pager and recreate the BTree on
                 // every iteration because the scan plan above already borrows
                 // the pager when we call .try_next(), so we can't create the
                 // BTree before starting the loop.
                 let mut pager = db.pager.borrow_mut();
-                let mut btree = BTree::new(&mut pager, index.root, &comparator);
-
-                let index_key = tuple.swap_remove(col);
-                let primary_key = tuple.swap_remove(0);
+                let mut btree = BTree::new(&mut pager, index.root, comparator.clone());
 
-                let entry = tuple::serialize(&index.schema.clone(), [&index_key, &primary_key]);
+                let index_key = index.key_values(&metadata, &tuple)?;
+                let entry = index.serialize_entry(version, &index_key, &tuple[0])?;
 
                 btree
                     .try_insert(entry)?
-                    .map_err(|_| SqlError::DuplicatedKey(index_key))?;
+                    .map_err(|_| index.duplicated_key(&table, index_key))?;
             }
 
             // Invalidate the table so that the next time it is loaded it
@@ -153,8 +174,8 @@ pub(crate) fn exec<F: Seek + Read + Write + FileOps>(
             db.context.invalidate(&table);
         }
 
-        Statement::Drop(Drop::Table(name)) => {
-            let comparator = db.table_metadata(MKDB_META)?.comparator()?;
+        Statement::Drop(Drop::Table { name, .. }) => {
+            let comparator = db.table_metadata(MKDB_META)?.comparator();
 
             let mut plan = collect_from_mkdb_meta_where(db, &format!("table_name = '{name}'"))?;
 
@@ -171,7 +192,11 @@ pub(crate) fn exec<F: Seek + Read + Write + FileOps>(
                     )));
                 };
 
-                let removed_cells = free_btree(db, *root as PageNumber)?;
+                // Statistics don't have a BTree.
+                let removed_cells = match is_stats_row(&schema, &tuple) {
+                    true => 0,
+                    false => free_btree(db, *root as PageNumber)?,
+                };
 
                 // Only rows removed from tables count. Index data doesn't
                 // count.
@@ -188,13 +213,154 @@ pub(crate) fn exec<F: Seek + Read + Write + FileOps>(
                 }
 
                 BTree::new(&mut db.pager.borrow_mut(), MKDB_META_ROOT, comparator).remove(
-                    &tuple::serialize_key(&schema.columns[0].data_type, &tuple[0]),
+                    &tuple::serialize_key(&schema.columns[0].data_type, &tuple[0])?,
                 )?;
             }
 
             db.context.invalidate(&name);
         }
 
+        Statement::Drop(Drop::Index { name, .. }) => {
+            let comparator = db.table_metadata(MKDB_META)?.comparator();
+
+            let mut plan =
+                collect_from_mkdb_meta_where(db, &format!("type = 'index' AND name = '{name}'"))?;
+
+            let schema = plan.schema().ok_or(DbError::Corrupted(format!(
+                "could not obtain schema of {MKDB_META} table"
+            )))?;
+
+            let Some(tuple) = plan.try_next()? else {
+                return Err(DbError::Sql(SqlError::Other(format!(
+                    "index {name} does not exist"
+                ))));
+            };
+
+            let (Some(Value::Number(root)), Some(Value::String(table))) = (
+                schema.index_of("root").and_then(|index| tuple.get(index)),
+                schema
+                    .index_of("table_name")
+                    .and_then(|index| tuple.get(index)),
+            ) else {
+                return Err(DbError::Corrupted(format!(
+                    "could not read root of index {name}"
+                )));
+            };
+
+            free_btree(db, *root as PageNumber)?;
+
+            BTree::new(&mut db.pager.borrow_mut(), MKDB_META_ROOT, comparator).remove(
+                &tuple::serialize_key(&schema.columns[0].data_type, &tuple[0])?,
+            )?;
+
+            db.context.invalidate(table);
+        }
+
+        Statement::Alter(Alter::DropColumn { table, column }) => {
+            let metadata = db.table_metadata(&table)?.clone();
+
+            let col = metadata
+                .schema
+                .index_of(&column)
+                .ok_or(SqlError::InvalidColumn(column.clone()))?;
+
+            let mut columns = metadata.schema.columns.clone();
+            columns.remove(col);
+            let schema = Schema::new(columns);
+
+            // Tuples are serialized positionally, so every single row has to
+            // be rewritten without the bytes of the dropped column. We collect
+            // the rows first because the scan can't run while the BTree is
+            // being modified.
+            let mut rows = Plan::Collect(Collect::from(CollectConfig {
+                work_dir: db.work_dir.clone(),
+                mem_buf_size: db.pager.borrow().page_size,
+                schema: metadata.schema.clone(),
+                source: Box::new(Plan::SeqScan(SeqScan {
+                    cursor: Cursor::new(metadata.root, 0),
+                    table: metadata.clone(),
+                    pager: Rc::clone(&db.pager),
+                    rows_scanned: 0,
+                })),
+            }));
+
+            while let Some(mut tuple) = rows.try_next()? {
+                tuple.remove(col);
+
+                let mut pager = db.pager.borrow_mut();
+                let entry = tuple::serialize_with(pager.format_version, &schema, &tuple)?;
+                BTree::new(&mut pager, metadata.root, metadata.comparator()).insert(entry)?;
+
+                affected_rows += 1;
+            }
+
+            // Now update the definition of the table stored in the meta table.
+            update_mkdb_meta_definitions(db, &table, |definition| {
+                if let Create::Table { columns, .. } = definition {
+                    columns.retain(|col| col.name != column);
+                }
+            })?;
+
+            db.context.invalidate(&table);
+        }
+
+        // Renames don't touch the rows, only the definitions of the table and
+        // its indexes.
+        Statement::Alter(Alter::RenameColumn {
+            table,
+            column,
+            new_name,
+        }) => {
+            update_mkdb_meta_definitions(db, &table, |definition| match definition {
+                Create::Table { columns, .. } => columns
+                    .iter_mut()
+                    .filter(|col| col.name == column)
+                    .for_each(|col| col.name = new_name.clone()),
+
+                Create::Index { columns, .. } => columns
+                    .iter_mut()
+                    .filter(|index_column| index_column.name == column)
+                    .for_each(|index_column| index_column.name = new_name.clone()),
+
+                _ => {}
+            })?;
+
+            db.context.invalidate(&table);
+        }
+
+        Statement::Alter(Alter::RenameTable { table, new_name }) => {
+            update_mkdb_meta_definitions(db, &table, |definition| match definition {
+                Create::Table { name, .. } => *name = new_name.clone(),
+                Create::Index { table, .. } => *table = new_name.clone(),
+                _ => {}
+            })?;
+
+            db.context.invalidate(&table);
+            db.context.invalidate(&new_name);
+        }
+
+        Statement::Analyze(table) => {
+            let metadata = db.table_metadata(&table)?.clone();
+            let stats = collect_table_stats(db, &metadata)?;
+
+            remove_from_mkdb_meta_where(db, &format!("type = 'stats' AND table_name = '{table}'"))?;
+
+            insert_into_mkdb_meta(db, vec![
+                Value::String(String::from("stats")),
+                Value::String(table.clone()),
+                Value::Number(0),
+                Value::String(table.clone()),
+                Value::String(stats.serialize(&metadata.schema)),
+            ])?;
+
+            db.context.invalidate(&table);
+        }
+
+        Statement::Vacuum => {
+            vacuum(db)?;
+            db.context.clear();
+        }
+
         other => {
             return Err(DbError::Other(format!(
                 "statement is not yet implemented or supported: {other}"
@@ -205,6 +371,43 @@ pub(crate) fn exec<F: Seek + Read + Write + FileOps>(
     Ok(affected_rows)
 }
 
+/// Returns `true` if the statement has an `IF [NOT] EXISTS` modifier and the
+/// condition doesn't hold, in which case the statement does nothing.
+fn skip_if_exists<F: Seek + Read + Write + FileOps>(
+    statement: &Statement,
+    db: &mut Database<F>,
+) -> Result<bool, DbError> {
+    let table_exists = |db: &mut Database<F>, table: &str| match db.table_metadata(table) {
+        Ok(_) => Ok(true),
+        Err(DbError::Sql(SqlError::InvalidTable(_))) => Ok(false),
+        Err(e) => Err(e),
+    };
+
+    Ok(match statement {
+        Statement::Create(Create::Table {
+            name,
+            if_not_exists: true,
+            ..
+        }) => table_exists(db, name)?,
+
+        Statement::Drop(Drop::Table {
+            name,
+            if_exists: true,
+        }) => !table_exists(db, name)?,
+
+        Statement::Drop(Drop::Index {
+            name,
+            table,
+            if_exists: true,
+        }) => {
+            let owner = db.index_table(name)?;
+            owner.is_none() || table.is_some() && owner != *table
+        }
+
+        _ => false,
+    })
+}
+
 /// Allocates a page on disk that can be used as a table root.
 fn alloc_root_page<F: Seek + Read + Write + FileOps>(
     db: &mut Database<F>,
@@ -256,6 +459,159 @@ fn free_btree<F: Seek + Read + Write + FileOps>(
     Ok(removed_cells)
 }
 
+/// Moves the pages in use to the beginning of the file and drops the rest.
+///
+/// See [`move_pages_to_front`] for details.
+fn vacuum<F: Seek + Read + Write + FileOps>(db: &mut Database<F>) -> Result<(), DbError> {
+    // Updating the roots in the MKDB_META table can free and allocate pages
+    // again when the rows don't fit in one page, so we need another pass. The
+    // second one never moves roots because they're all at the front already.
+    while move_pages_to_front(db)? {}
+
+    Ok(())
+}
+
+/// Every page in use is reachable from [`MKDB_META_ROOT`] or one of the roots
+/// stored in [`MKDB_META`], so we walk all the BTrees first and count them.
+/// If there are `n` pages in use, then every one of them located at `n` or
+/// after is copied into a page before `n` that's not in use (a free page or
+/// one that leaked somehow), the pointers to the copied pages are updated and
+/// the file is truncated to `n` pages.
+///
+/// Returns `true` if the roots stored in [`MKDB_META`] had to be updated.
+fn move_pages_to_front<F: Seek + Read + Write + FileOps>(
+    db: &mut Database<F>,
+) -> Result<bool, DbError> {
+    let comparator = db.table_metadata(MKDB_META)?.comparator();
+
+    let mut plan = collect_from_mkdb_meta_where(db, "type = 'table' OR type = 'index'")?;
+
+    let schema = plan.schema().ok_or(DbError::Corrupted(format!(
+        "could not obtain schema of {MKDB_META} table"
+    )))?;
+
+    let root = schema.index_of("root").ok_or(DbError::Corrupted(format!(
+        "{MKDB_META} table has no root column"
+    )))?;
+
+    let mut btrees = Vec::new();
+
+    while let Some(tuple) = plan.try_next()? {
+        let Value::Number(page_number) = tuple[root] else {
+            return Err(DbError::Corrupted(format!(
+                "{MKDB_META} table contains unexpected data: {tuple:?}"
+            )));
+        };
+
+        btrees.push((page_number as PageNumber, tuple));
+    }
+
+    let mut pager = db.pager.borrow_mut();
+
+    let mut btree_pages = Vec::new();
+    let mut overflow_pages = Vec::new();
+    let mut stack = Vec::from_iter(btrees.iter().map(|(root, _)| *root));
+    stack.push(MKDB_META_ROOT);
+
+    while let Some(page_number) = stack.pop() {
+        let page = pager.get(page_number)?;
+        stack.extend(page.iter_children());
+        btree_pages.push(page_number);
+
+        let overflow = (0..page.len())
+            .map(|slot| page.cell(slot).overflow_page())
+            .filter(|overflow_page| *overflow_page != 0)
+            .collect::<Vec<_>>();
+
+        for mut overflow_page in overflow {
+            while overflow_page != 0 {
+                overflow_pages.push(overflow_page);
+                overflow_page = pager.get_as::<OverflowPage>(overflow_page)?.header().next;
+            }
+        }
+    }
+
+    let total_pages = (btree_pages.len() + overflow_pages.len()) as PageNumber;
+    let in_use =
+        HashSet::<PageNumber>::from_iter(btree_pages.iter().chain(&overflow_pages).copied());
+
+    // Both lists are sorted and have the same length because there are as
+    // many pages in use at or after `total_pages` as holes before it.
+    let holes = (0..total_pages).filter(|page_number| !in_use.contains(page_number));
+    let mut tail = Vec::from_iter(in_use.iter().copied().filter(|page| *page >= total_pages));
+    tail.sort();
+
+    let moved = HashMap::<PageNumber, PageNumber>::from_iter(tail.into_iter().zip(holes));
+
+    let overflow = HashSet::<PageNumber>::from_iter(overflow_pages.iter().copied());
+
+    for (page_number, destination) in &moved {
+        match overflow.contains(page_number) {
+            true => pager.copy_page::<OverflowPage>(*page_number, *destination)?,
+            false => pager.copy_page::<Page>(*page_number, *destination)?,
+        }
+    }
+
+    let location = |page_number: &PageNumber| *moved.get(page_number).unwrap_or(page_number);
+
+    for page_number in &btree_pages {
+        let page = pager.get(location(page_number))?;
+
+        let points_to_moved_page = page.iter_children().any(|child| moved.contains_key(&child))
+            || (0..page.len()).any(|slot| moved.contains_key(&page.cell(slot).overflow_page()));
+
+        if !points_to_moved_page {
+            continue;
+        }
+
+        let page = pager.get_mut(location(page_number))?;
+
+        for slot in 0..page.len() {
+            let overflow_page = location(&page.cell(slot).overflow_page());
+            let cell = page.cell_mut(slot);
+            cell.header.left_child = location(&cell.header.left_child);
+
+            if cell.header.is_overflow {
+                let offset = cell.content.len() - mem::size_of::<PageNumber>();
+                cell.content[offset..].copy_from_slice(&overflow_page.to_le_bytes());
+            }
+        }
+
+        page.header_mut().right_child = location(&page.header().right_child);
+    }
+
+    for page_number in &overflow_pages {
+        let next = pager
+            .get_as::<OverflowPage>(location(page_number))?
+            .header()
+            .next;
+
+        if moved.contains_key(&next) {
+            pager
+                .get_mut_as::<OverflowPage>(location(page_number))?
+                .header_mut()
+                .next = location(&next);
+        }
+    }
+
+    pager.truncate(total_pages)?;
+
+    // The free list is gone by now, so if updating the roots requires new
+    // pages they'll be appended at the end of the file.
+    let mut updated_roots = false;
+
+    for (root_page, mut tuple) in btrees {
+        if let Some(destination) = moved.get(&root_page) {
+            tuple[root] = Value::Number(*destination as i128);
+            let entry = tuple::serialize_with(pager.format_version, &schema, &tuple)?;
+            BTree::new(&mut pager, MKDB_META_ROOT, comparator).insert(entry)?;
+            updated_roots = true;
+        }
+    }
+
+    Ok(updated_roots)
+}
+
 /// Inserts data into the [`MKDB_META`] table.
 ///
 /// This is the same as running:
@@ -297,17 +653,237 @@ fn insert_into_mkdb_meta<F: Seek + Read + Write + FileOps>(
     );
 
     let mut pager = db.pager.borrow_mut();
+    let tuple = tuple::serialize_with(pager.format_version, &schema, &values)?;
+
     let mut btree = BTree::new(
         &mut pager,
         MKDB_META_ROOT,
         FixedSizeMemCmp::for_type::<RowId>(),
     );
 
-    btree.insert(tuple::serialize(&schema, &values))?;
+    btree.insert(tuple)?;
+
+    Ok(())
+}
+
+/// Rewrites the `CREATE` statements of `table` and its indexes stored in the
+/// [`MKDB_META`] table.
+///
+/// The given function receives each definition and can modify it however it
+/// wants. The `name` and `table_name` columns are updated to match the new
+/// definition, so it can also rename tables and indexes.
+///
+/// The statistics of the table are discarded because they might not match the
+/// new definition anymore. `ANALYZE` has to run again to collect them.
+fn update_mkdb_meta_definitions<F: Seek + Read + Write + FileOps>(
+    db: &mut Database<F>,
+    table: &str,
+    mut update: impl FnMut(&mut Create),
+) -> Result<(), DbError> {
+    let comparator = db.table_metadata(MKDB_META)?.comparator();
+
+    let mut plan = collect_from_mkdb_meta_where(db, &format!("table_name = '{table}'"))?;
+
+    let schema = plan.schema().ok_or(DbError::Corrupted(format!(
+        "could not obtain schema of {MKDB_META} table"
+    )))?;
+
+    let corrupted_error = || {
+        DbError::Corrupted(format!(
+            "{MKDB_META} table contains a wrong definition for table {table}"
+        ))
+    };
+
+    let [name, table_name, sql] =
+        ["name", "table_name", "sql"].map(|col| schema.index_of(col).ok_or_else(corrupted_error));
+    let (name, table_name, sql) = (name?, table_name?, sql?);
+
+    while let Some(mut tuple) = plan.try_next()? {
+        if is_stats_row(&schema, &tuple) {
+            BTree::new(&mut db.pager.borrow_mut(), MKDB_META_ROOT, comparator).remove(
+                &tuple::serialize_key(&schema.columns[0].data_type, &tuple[0])?,
+            )?;
+            continue;
+        }
+
+        let Value::String(definition) = &tuple[sql] else {
+            return Err(corrupted_error());
+        };
+
+        let Statement::Create(mut definition) = Parser::new(definition).parse_statement()? else {
+            return Err(corrupted_error());
+        };
+
+        update(&mut definition);
+
+        let (new_name, new_table_name) = match &definition {
+            Create::Table { name, .. } => (name.clone(), name.clone()),
+            Create::Index { name, table, .. } => (name.clone(), table.clone()),
+            Create::Database(_) => return Err(corrupted_error()),
+        };
+
+        tuple[name] = Value::String(new_name);
+        tuple[table_name] = Value::String(new_table_name);
+        tuple[sql] = Value::String(Statement::Create(definition).to_sql_safe());
+
+        let mut pager = db.pager.borrow_mut();
+        let entry = tuple::serialize_with(pager.format_version, &schema, &tuple)?;
+        BTree::new(&mut pager, MKDB_META_ROOT, comparator).insert(entry)?;
+    }
 
     Ok(())
 }
 
+/// Removes all the rows of the [`MKDB_META`] table that match `filter`.
+fn remove_from_mkdb_meta_where<F: Seek + Read + Write + FileOps>(
+    db: &mut Database<F>,
+    filter: &str,
+) -> Result<(), DbError> {
+    let comparator = db.table_metadata(MKDB_META)?.comparator();
+
+    let mut plan = collect_from_mkdb_meta_where(db, filter)?;
+
+    let schema = plan.schema().ok_or(DbError::Corrupted(format!(
+        "could not obtain schema of {MKDB_META} table"
+    )))?;
+
+    while let Some(tuple) = plan.try_next()? {
+        BTree::new(&mut db.pager.borrow_mut(), MKDB_META_ROOT, comparator).remove(
+            &tuple::serialize_key(&schema.columns[0].data_type, &tuple[0])?,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// `true` if the given [`MKDB_META`] row stores [`TableStats`] instead of the
+/// definition of a table or index.
+fn is_stats_row(schema: &Schema, tuple: &[Value]) -> bool {
+    schema
+        .index_of("type")
+        .is_some_and(|index| tuple[index] == Value::String(String::from("stats")))
+}
+
+/// Scans `table` once to build its [`TableStats`].
+///
+/// Distinct values are counted for every column that belongs to an index,
+/// using a [`DistinctCounter`] for each one of them.
+fn collect_table_stats<F: Seek + Read + Write + FileOps>(
+    db: &mut Database<F>,
+    table: &TableMetadata,
+) -> Result<TableStats, DbError> {
+    let mut columns = Vec::new();
+
+    for column in table.indexes.iter().flat_map(|index| &index.columns) {
+        let position = table
+            .schema
+            .index_of(&column.name)
+            .ok_or_else(|| SqlError::InvalidColumn(column.name.clone()))?;
+
+        if !columns.contains(&position) {
+            columns.push(position);
+        }
+    }
+
+    let mut counters = vec![DistinctCounter::new(); columns.len()];
+    let mut rows = 0;
+
+    let mut scan = Plan::SeqScan(SeqScan {
+        table: table.clone(),
+        pager: Rc::clone(&db.pager),
+        cursor: Cursor::new(table.root, 0),
+        rows_scanned: 0,
+    });
+
+    while let Some(tuple) = scan.try_next()? {
+        rows += 1;
+
+        for (counter, position) in counters.iter_mut().zip(&columns) {
+            counter.insert(&tuple[*position]);
+        }
+    }
+
+    let distinct = columns
+        .into_iter()
+        .zip(counters)
+        .map(|(position, counter)| {
+            let name = table.schema.columns[position].name.clone();
+            (name, counter.estimate().min(rows))
+        })
+        .collect();
+
+    Ok(TableStats { rows, distinct })
+}
+
+/// Number of bits of the hash that select a [`DistinctCounter`] register.
+const DISTINCT_COUNTER_BITS: u32 = 10;
+
+/// HyperLogLog approximate distinct counter.
+///
+/// Every value is hashed and the first [`DISTINCT_COUNTER_BITS`] bits of the
+/// hash select a register, which remembers the longest run of leading zeros
+/// seen in the rest of the hashes that went through it. Long runs of zeros are
+/// unlikely, so they say how many different hashes the register has seen. The
+/// estimation that combines all the registers has a standard error of about
+/// 3% and only needs 1 KiB of memory no matter how big the table is.
+///
+/// See the original paper for the details:
+///
+/// <https://algo.inria.fr/flajolet/Publications/FlFuGaMe07.pdf>
+#[derive(Clone)]
+struct DistinctCounter {
+    registers: Vec<u8>,
+}
+
+impl DistinctCounter {
+    /// Creates a counter that hasn't seen any value.
+    fn new() -> Self {
+        Self {
+            registers: vec![0; 1 << DISTINCT_COUNTER_BITS],
+        }
+    }
+
+    /// Counts `value`. Duplicates don't change the estimation.
+    fn insert(&mut self, value: &Value) {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(&distinct_key(value));
+        let hash = hasher.finish();
+
+        let register = (hash >> (u64::BITS - DISTINCT_COUNTER_BITS)) as usize;
+
+        // The extra bit stops the count if the rest of the hash is all zeros.
+        let rank = ((hash << DISTINCT_COUNTER_BITS) | 1 << (DISTINCT_COUNTER_BITS - 1))
+            .leading_zeros()
+            + 1;
+
+        self.registers[register] = self.registers[register].max(rank as u8);
+    }
+
+    /// Approximate number of distinct values counted so far.
+    fn estimate(&self) -> usize {
+        let registers = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / registers);
+
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|rank| 2f64.powi(-(*rank as i32)))
+            .sum();
+
+        let estimate = alpha * registers * registers / sum;
+
+        // Small cardinalities leave registers empty, in which case counting
+        // them ("linear counting") is way more accurate.
+        let empty = self.registers.iter().filter(|rank| **rank == 0).count();
+
+        if estimate <= 2.5 * registers && empty > 0 {
+            return (registers * (registers / empty as f64).ln()).round() as usize;
+        }
+
+        estimate.round() as usize
+    }
+}
+
 /// Manual selection from meta table without parsing overhead and mutual
 /// recursion.
 ///
@@ -332,6 +908,7 @@ fn collect_from_mkdb_meta_where<F: Seek + Read + Write + FileOps>(
                 table: table.to_owned(),
                 pager: Rc::clone(&db.pager),
                 cursor: Cursor::new(MKDB_META_ROOT, 0),
+                rows_scanned: 0,
             })),
         })),
     })))